@@ -0,0 +1,99 @@
+//! Accessibility - the spatial browser without the motion, and easier to read
+//!
+//! `accessible` under `[render]` (or `:set accessible`) is for motion
+//! sensitivity and low vision: the camera jumps to where it's going instead
+//! of easing there, the selection stops pulsing, hover glow and directory
+//! changes happen at once, the CRT overlay and the error glitch are off (the
+//! failed entry still turns red), and bloom is left out.
+//! Dim text is drawn brighter and the names over the entries bigger.
+//!
+//! ```toml
+//! [render]
+//! accessible = true
+//! ```
+
+use bevy::prelude::*;
+
+use crate::config::Config;
+use crate::{FileLabel, FELIPE_ORANGE_DIM};
+
+/// What dim text is drawn in instead, closer to the full orange
+pub const CONTRAST_DIM: Color = Color::srgb(0.95, 0.5, 0.15);
+
+/// Names over the entries grow by this much
+const LABEL_ENLARGE: f32 = 1.5;
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (raise_text_contrast, enlarge_labels));
+    }
+}
+
+/// Color for text that is usually dim
+pub fn dim_text(config: &Config) -> Color {
+    if config.render.accessible {
+        CONTRAST_DIM
+    } else {
+        FELIPE_ORANGE_DIM
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Swap dim text for the brighter color in newly written text, or everywhere
+/// when the setting changes (labels pick theirs in `update_file_labels`)
+fn raise_text_contrast(config: Res<Config>, mut text_query: Query<&mut Text, Without<FileLabel>>) {
+    let (from, to) = if config.render.accessible {
+        (FELIPE_ORANGE_DIM, CONTRAST_DIM)
+    } else if config.is_changed() {
+        (CONTRAST_DIM, FELIPE_ORANGE_DIM)
+    } else {
+        return;
+    };
+    let from = from.to_srgba();
+    for mut text in text_query.iter_mut() {
+        if !config.is_changed() && !text.is_changed() {
+            continue;
+        }
+        // Look first: writing through `text` would lay it out again
+        let dim = |section: &TextSection| {
+            let color = section.style.color.to_srgba();
+            color.with_alpha(1.0) == from.with_alpha(1.0)
+        };
+        if !text.sections.iter().any(dim) {
+            continue;
+        }
+        for section in text.sections.iter_mut() {
+            if dim(section) {
+                let alpha = section.style.color.alpha();
+                section.style.color = to.with_alpha(alpha);
+            }
+        }
+    }
+}
+
+/// Scale new labels up, and all of them when the setting changes
+fn enlarge_labels(
+    config: Res<Config>,
+    mut enlarged: Local<bool>,
+    mut label_query: Query<(Ref<FileLabel>, &mut Transform)>,
+) {
+    let accessible = config.render.accessible;
+    let toggled = accessible != *enlarged;
+    *enlarged = accessible;
+    for (label, mut transform) in label_query.iter_mut() {
+        if toggled && !label.is_added() {
+            transform.scale *= if accessible {
+                LABEL_ENLARGE
+            } else {
+                1.0 / LABEL_ENLARGE
+            };
+        } else if accessible && label.is_added() {
+            transform.scale *= LABEL_ENLARGE;
+        }
+    }
+}
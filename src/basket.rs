@@ -0,0 +1,357 @@
+//! Marks and the basket - a selection of scattered entries, across directories
+//!
+//! Space marks the selected entry, or unmarks it (Shift-Space plays a sound
+//! file, see player.rs); a marked entry wears a ring around its base. While
+//! entries of the current directory are marked, `y`, `m`, `d` (trash.rs) and
+//! `t` and the `:` commands that act on the selection (`:chmod`, `:tag`,
+//! `:hash`, `:!`...) take them instead of the entry under the cursor; a visual
+//! range still wins.
+//!
+//! Marks stay when you leave the directory, so everything marked anywhere
+//! makes up the basket, counted in the status line. In the directory it all
+//! should end up in:
+//!
+//! ```text
+//! :paste-basket     copy everything marked here
+//! :paste-basket!    move it here
+//! :basket           what's marked
+//! :clearsel         unmark everything (also :basket!)
+//! ```
+//!
+//! A whole class of entries is marked in one go, here:
+//!
+//! ```text
+//! :select all       every entry
+//! :select invert    the unmarked ones instead of the marked ones
+//! :select *.log     the matching ones too (/regex/ and tag:color as well)
+//! ```
+//!
+//! The paste runs through the job queue like `p` and clears the marks.
+//!
+//! Enter with files marked here opens them all, each in its default
+//! application, or hands them to one command at once when the config has one:
+//!
+//! ```toml
+//! [operations]
+//! open_marked = "code %*"    # run like `:!`, %* the marked files
+//! ```
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::command::{Command, CommandLine, RunCommand};
+use crate::config::Config;
+use crate::filter::NamePattern;
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::jobs::JobQueue;
+use crate::ops::{self, TransferKind};
+use crate::picker::Picker;
+use crate::{entry_height, remote, EntryOpened, EntryPalette, FileEntity};
+use crate::{CurrentDirectory, Prompt, StatusMessage, VimMode};
+
+/// Color of the ring around a marked entry
+const MARK_COLOR: Color = Color::srgb(0.2, 0.85, 1.0);
+
+/// Entries marked so far, in the order they were marked
+#[derive(Resource, Default)]
+pub struct Basket {
+    pub paths: Vec<PathBuf>,
+}
+
+impl Basket {
+    /// Marked entries of the current directory
+    pub fn marked_here(&self, current_dir: &CurrentDirectory) -> Vec<PathBuf> {
+        current_dir
+            .entries
+            .iter()
+            .filter(|entry| self.paths.contains(&entry.path))
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+
+    /// What `y` and `m` take in NORMAL mode: the entries marked here, else
+    /// the one under the cursor. VISUAL mode's range is `handle_keyboard`'s
+    pub fn yank_targets(&self, current_dir: &CurrentDirectory) -> Vec<PathBuf> {
+        let marked = self.marked_here(current_dir);
+        if !marked.is_empty() {
+            return marked;
+        }
+        current_dir
+            .entries
+            .get(current_dir.selected_index)
+            .filter(|entry| entry.name != "..")
+            .map(|entry| vec![entry.path.clone()])
+            .unwrap_or_default()
+    }
+}
+
+/// Marker for the ring around a marked entry
+#[derive(Component)]
+struct MarkRing;
+
+pub struct BasketPlugin;
+
+impl Plugin for BasketPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Basket>().add_systems(
+            Update,
+            (
+                handle_basket_key,
+                // Takes the Enter before it opens the cursor's entry
+                open_marked.before(crate::handle_keyboard),
+                handle_basket_commands,
+                update_mark_rings,
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Space - mark the selected entry, or unmark it
+fn handle_basket_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    current_dir: Res<CurrentDirectory>,
+    mut basket: ResMut<Basket>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !keyboard.just_pressed(KeyCode::Space)
+        || keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || focus.any_open()
+        || fly.enabled
+    {
+        return;
+    }
+    let Some(entry) = current_dir
+        .entries
+        .get(current_dir.selected_index)
+        .filter(|entry| entry.name != "..")
+    else {
+        return;
+    };
+    if let Some(at) = basket.paths.iter().position(|path| *path == entry.path) {
+        basket.paths.remove(at);
+        status.0 = format!("Unmarked {} ({} marked)", entry.name, basket.paths.len());
+    } else {
+        basket.paths.push(entry.path.clone());
+        status.0 = format!("Marked {} ({} marked)", entry.name, basket.paths.len());
+    }
+}
+
+/// Enter with files marked here - open those, not the cursor's entry
+fn open_marked(
+    mut keyboard: ResMut<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    picker: Res<Picker>,
+    config: Res<Config>,
+    current_dir: Res<CurrentDirectory>,
+    basket: Res<Basket>,
+    mut command_line: ResMut<CommandLine>,
+    mut run_commands: EventWriter<RunCommand>,
+    mut opened: EventWriter<EntryOpened>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !keyboard.just_pressed(KeyCode::Enter)
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || focus.any_open()
+        || fly.enabled
+        || picker.mode.is_some()
+        || basket.paths.is_empty()
+    {
+        return;
+    }
+    // Directories can't be opened together; l still enters one
+    let files: Vec<PathBuf> = current_dir
+        .entries
+        .iter()
+        .filter(|entry| !entry.is_dir && basket.paths.contains(&entry.path))
+        .map(|entry| entry.path.clone())
+        .collect();
+    if files.is_empty() {
+        return;
+    }
+    keyboard.clear_just_pressed(KeyCode::Enter);
+    if let Some(line) = &config.operations.open_marked {
+        // `%*` of the command is what's marked
+        command_line.range = None;
+        command_line.marked = files;
+        run_commands.send(RunCommand(Command::Shell(line.clone())));
+        return;
+    }
+    for path in &files {
+        if remote::location(path).is_some() {
+            remote::open(path);
+        } else {
+            crate::open_with_default_app(path);
+        }
+        opened.send(EntryOpened { path: path.clone() });
+    }
+    status.0 = format!("Opened {} files", files.len());
+}
+
+fn handle_basket_commands(
+    mut run_commands: EventReader<RunCommand>,
+    config: Res<Config>,
+    current_dir: Res<CurrentDirectory>,
+    mut basket: ResMut<Basket>,
+    mut prompt: ResMut<Prompt>,
+    mut jobs: ResMut<JobQueue>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        match command {
+            Command::PasteBasket { moving } => {
+                // Deleted or moved away since they went in
+                basket.paths.retain(|path| path.exists());
+                if basket.paths.is_empty() {
+                    status.0 = "Nothing marked - Space marks the selected entry".to_string();
+                    continue;
+                }
+                let kind = if *moving {
+                    TransferKind::Move
+                } else {
+                    TransferKind::Copy
+                };
+                // What's already here stays where it is
+                let paths: Vec<PathBuf> = std::mem::take(&mut basket.paths)
+                    .into_iter()
+                    .filter(|path| path.parent() != Some(current_dir.path()))
+                    .collect();
+                if paths.is_empty() {
+                    status.0 = "Everything marked is already here".to_string();
+                    continue;
+                }
+                let plan = ops::plan_transfer(kind, &paths, current_dir.path());
+                crate::offer_transfer(plan, &config, &mut prompt, &mut jobs);
+            }
+            Command::Select(what) => {
+                let entries = current_dir
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.name != "..");
+                match what.as_str() {
+                    "all" => {
+                        for entry in entries {
+                            if !basket.paths.contains(&entry.path) {
+                                basket.paths.push(entry.path.clone());
+                            }
+                        }
+                    }
+                    "invert" => {
+                        for entry in entries {
+                            match basket.paths.iter().position(|path| *path == entry.path) {
+                                Some(at) => {
+                                    basket.paths.remove(at);
+                                }
+                                None => basket.paths.push(entry.path.clone()),
+                            }
+                        }
+                    }
+                    pattern => {
+                        let pattern = match NamePattern::parse(pattern) {
+                            Ok(pattern) => pattern,
+                            Err(err) => {
+                                status.0 = format!("Bad pattern {}: {}", pattern, err);
+                                continue;
+                            }
+                        };
+                        for entry in entries {
+                            if pattern.matches(&entry.name, &entry.path)
+                                && !basket.paths.contains(&entry.path)
+                            {
+                                basket.paths.push(entry.path.clone());
+                            }
+                        }
+                    }
+                }
+                let here = basket.marked_here(&current_dir).len();
+                status.0 = format!("{} marked here ({} in all)", here, basket.paths.len());
+            }
+            Command::Basket { clear: true } => {
+                let count = basket.paths.len();
+                basket.paths.clear();
+                status.0 = format!("Unmarked {} entries", count);
+            }
+            Command::Basket { clear: false } => {
+                status.0 = if basket.paths.is_empty() {
+                    "Nothing marked - Space marks the selected entry".to_string()
+                } else {
+                    let names: Vec<String> = basket
+                        .paths
+                        .iter()
+                        .map(|path| {
+                            path.file_name().map_or_else(
+                                || path.display().to_string(),
+                                |name| name.to_string_lossy().to_string(),
+                            )
+                        })
+                        .collect();
+                    format!("Marked ({}): {}", names.len(), names.join(", "))
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rebuild rings when marks change or the entries were respawned
+fn update_mark_rings(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    basket: Res<Basket>,
+    current_dir: Res<CurrentDirectory>,
+    entity_query: Query<(Entity, &FileEntity)>,
+    new_entities: Query<(), Added<FileEntity>>,
+    ring_query: Query<Entity, With<MarkRing>>,
+) {
+    if !basket.is_changed() && new_entities.is_empty() {
+        return;
+    }
+    for ring in ring_query.iter() {
+        commands.entity(ring).despawn();
+    }
+    if basket.paths.is_empty() {
+        return;
+    }
+    let mesh = palette.mesh(&mut meshes, "mark ring", || Torus::new(0.55, 0.62).into());
+    let material = palette.material(&mut materials, MARK_COLOR.to_linear());
+    for (entity, file_entity) in entity_query.iter() {
+        let Some(entry) = current_dir.entries.get(file_entity.index) else {
+            continue;
+        };
+        if !basket.paths.contains(&entry.path) {
+            continue;
+        }
+        // Flat on the floor around the entry, whose unit cuboid is stretched
+        // to its height
+        let height = entry_height(entry);
+        let ring = commands
+            .spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(0.0, -0.5 + 0.05 / height, 0.0)
+                        .with_scale(Vec3::new(1.0, 1.0 / height, 1.0)),
+                    ..default()
+                },
+                MarkRing,
+            ))
+            .id();
+        commands.entity(entity).add_child(ring);
+    }
+}
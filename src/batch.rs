@@ -0,0 +1,549 @@
+//! `felipe --batch <script.toml>` - run file operations without a window
+//!
+//! A script is a declarative list of copy/move/delete/sync steps executed by
+//! the same engine as the scene: every step is planned (dry-run) before it
+//! touches the disk, existing targets follow a conflict policy, and every
+//! change is appended to a journal. Meant for cron jobs.
+//!
+//! ```toml
+//! on_conflict = "rename"      # abort (default) | skip | rename | overwrite
+//!
+//! [[step]]
+//! action = "copy"             # copy | move | delete | sync
+//! from = ["report.pdf", "assets"]
+//! to = "/backup/today"
+//! ```
+
+use serde::Deserialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::ops::{self, RenameStrategy, TransferKind, TransferReport, UndoStep};
+
+// =============================================================================
+// Script
+// =============================================================================
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Script {
+    /// Only print what would happen
+    #[serde(default)]
+    dry_run: bool,
+    /// Default policy for copy/move targets that already exist
+    #[serde(default)]
+    on_conflict: ConflictPolicy,
+    /// Where executed changes are appended (default: Felipe's data directory)
+    journal: Option<PathBuf>,
+    #[serde(default, rename = "step")]
+    steps: Vec<Step>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum ConflictPolicy {
+    /// Stop the script before the step runs
+    #[default]
+    Abort,
+    Skip,
+    /// Give the incoming entry a free name like `foo (1).txt`
+    Rename,
+    /// Move the existing entry to the trash first
+    Overwrite,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase", deny_unknown_fields)]
+enum Step {
+    Copy {
+        from: Sources,
+        to: PathBuf,
+        on_conflict: Option<ConflictPolicy>,
+    },
+    Move {
+        from: Sources,
+        to: PathBuf,
+        on_conflict: Option<ConflictPolicy>,
+    },
+    Delete {
+        paths: Sources,
+        /// Skip the trash; cannot be undone
+        #[serde(default)]
+        permanent: bool,
+    },
+    /// Mirror `from` into `to`: copy new and changed entries
+    Sync {
+        from: PathBuf,
+        to: PathBuf,
+        /// Also remove entries of `to` that `from` doesn't have
+        #[serde(default)]
+        delete: bool,
+    },
+}
+
+/// `from = "a"` or `from = ["a", "b"]`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Sources {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+impl Sources {
+    fn resolve(&self, base: &Path) -> Vec<PathBuf> {
+        match self {
+            Sources::One(path) => vec![base.join(path)],
+            Sources::Many(paths) => paths.iter().map(|path| base.join(path)).collect(),
+        }
+    }
+}
+
+// =============================================================================
+// Entry Point
+// =============================================================================
+
+/// Run `felipe --batch <script.toml> [--dry-run]`; returns the process exit code
+pub fn run_cli(script_path: &Path, force_dry_run: bool) -> i32 {
+    let script: Script = match std::fs::read_to_string(script_path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| toml::from_str(&text).map_err(|err| err.to_string()))
+    {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("felipe --batch: {}: {}", script_path.display(), err);
+            return 2;
+        }
+    };
+    // Relative paths in a script are relative to the script, not to cron's cwd
+    let base = script_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let dry_run = force_dry_run || script.dry_run;
+
+    let journal_path = script
+        .journal
+        .as_ref()
+        .map(|path| base.join(path))
+        .or_else(|| crate::data_dir().map(|dir| dir.join("journal.log")));
+    let mut journal = Journal {
+        path: if dry_run { None } else { journal_path },
+    };
+
+    let total = script.steps.len();
+    let mut failed = false;
+    for (i, step) in script.steps.iter().enumerate() {
+        let prefix = format!("[{}/{}]", i + 1, total);
+        match run_step(step, &base, script.on_conflict, dry_run, &mut journal) {
+            Ok(summary) => println!("{} {}", prefix, summary),
+            Err(StepError::Aborted(reason)) => {
+                eprintln!("{} aborted: {}", prefix, reason);
+                return 1;
+            }
+            Err(StepError::Failed(summary)) => {
+                eprintln!("{} {}", prefix, summary);
+                failed = true;
+            }
+        }
+    }
+    if dry_run {
+        println!("dry run - nothing was changed");
+    }
+    i32::from(failed)
+}
+
+enum StepError {
+    /// The step refused to run; later steps would build on a wrong state
+    Aborted(String),
+    /// The step ran but some entries failed
+    Failed(String),
+}
+
+// =============================================================================
+// Steps
+// =============================================================================
+
+fn run_step(
+    step: &Step,
+    base: &Path,
+    default_policy: ConflictPolicy,
+    dry_run: bool,
+    journal: &mut Journal,
+) -> Result<String, StepError> {
+    match step {
+        Step::Copy {
+            from,
+            to,
+            on_conflict,
+        } => run_transfer(
+            TransferKind::Copy,
+            &from.resolve(base),
+            &base.join(to),
+            on_conflict.unwrap_or(default_policy),
+            dry_run,
+            journal,
+        ),
+        Step::Move {
+            from,
+            to,
+            on_conflict,
+        } => run_transfer(
+            TransferKind::Move,
+            &from.resolve(base),
+            &base.join(to),
+            on_conflict.unwrap_or(default_policy),
+            dry_run,
+            journal,
+        ),
+        Step::Delete { paths, permanent } => {
+            let paths = paths.resolve(base);
+            if dry_run {
+                for path in &paths {
+                    println!("  would delete {}", path.display());
+                }
+                return Ok(format!("{} entries to delete", paths.len()));
+            }
+            let mut report = TransferReport::default();
+            for path in paths {
+                let result = if *permanent {
+                    ops::remove_path(&path).map(|()| None)
+                } else {
+                    ops::trash_entry(&path).map(Some)
+                };
+                match result {
+                    Ok(Some(step)) => {
+                        report.done += 1;
+                        report.steps.push(step);
+                    }
+                    Ok(None) => {
+                        report.done += 1;
+                        journal.record_text(&format!("delete {} permanently", path.display()));
+                    }
+                    Err(err) => report.failed.push((path, err)),
+                }
+            }
+            journal.record(&report.steps);
+            summarize("deleted", report)
+        }
+        Step::Sync { from, to, delete } => {
+            let (from, to) = (base.join(from), base.join(to));
+            let plan = ops::plan_sync(&from, &to, *delete)
+                .map_err(|err| StepError::Aborted(format!("sync {}: {}", from.display(), err)))?;
+            if dry_run {
+                for (source, target) in &plan.copies {
+                    println!("  would copy {} -> {}", source.display(), target.display());
+                }
+                for path in &plan.deletions {
+                    println!("  would delete {}", path.display());
+                }
+                return Ok(format!(
+                    "sync {} -> {}: {} to copy, {} to delete",
+                    from.display(),
+                    to.display(),
+                    plan.copies.len(),
+                    plan.deletions.len()
+                ));
+            }
+            let report = ops::execute_sync(&plan);
+            journal.record(&report.steps);
+            summarize("synced", report)
+        }
+    }
+}
+
+fn run_transfer(
+    kind: TransferKind,
+    sources: &[PathBuf],
+    dest: &Path,
+    policy: ConflictPolicy,
+    dry_run: bool,
+    journal: &mut Journal,
+) -> Result<String, StepError> {
+    if !dest.is_dir() {
+        return Err(StepError::Aborted(format!(
+            "{} is not a directory",
+            dest.display()
+        )));
+    }
+    let mut plan = ops::plan_transfer(kind, sources, dest);
+    // Case collisions get the same treatment as plain name clashes
+    if plan.conflicts().next().is_some() {
+        match policy {
+            ConflictPolicy::Abort => {
+                return Err(StepError::Aborted(conflict_list(plan.conflicts())));
+            }
+            ConflictPolicy::Skip => plan.resolve_case_collisions(RenameStrategy::Skip),
+            ConflictPolicy::Rename | ConflictPolicy::Overwrite => {
+                plan.resolve_case_collisions(RenameStrategy::Suffix)
+            }
+        }
+    }
+    let existing: Vec<PathBuf> = plan
+        .existing_targets()
+        .map(|step| step.target.clone())
+        .collect();
+    match policy {
+        ConflictPolicy::Abort if !existing.is_empty() => {
+            return Err(StepError::Aborted(conflict_list(plan.existing_targets())));
+        }
+        ConflictPolicy::Skip => plan.resolve_existing(RenameStrategy::Skip),
+        ConflictPolicy::Rename => plan.resolve_existing(RenameStrategy::Suffix),
+        _ => {}
+    }
+
+    let action = if kind == TransferKind::Copy {
+        "copy"
+    } else {
+        "move"
+    };
+    if dry_run {
+        for step in &plan.steps {
+            let replacing = if existing.contains(&step.target) {
+                " (replacing)"
+            } else {
+                ""
+            };
+            println!(
+                "  would {} {} -> {}{}",
+                action,
+                step.source.display(),
+                step.target.display(),
+                replacing
+            );
+        }
+        for (path, reason) in &plan.skipped {
+            println!("  would skip {} ({})", path.display(), reason);
+        }
+        return Ok(format!("{} entries to {}", plan.steps.len(), action));
+    }
+
+    let mut trashed = Vec::new();
+    if policy == ConflictPolicy::Overwrite {
+        for target in &existing {
+            match ops::trash_entry(target) {
+                Ok(step) => trashed.push(step),
+                Err(err) => {
+                    return Err(StepError::Aborted(format!(
+                        "could not move {} out of the way: {}",
+                        target.display(),
+                        err
+                    )))
+                }
+            }
+        }
+    }
+    journal.record(&trashed);
+    let report = ops::execute_plan(&plan);
+    journal.record(&report.steps);
+    for (path, reason) in &plan.skipped {
+        println!("  skipped {} ({})", path.display(), reason);
+    }
+    summarize(kind.verb(), report)
+}
+
+fn conflict_list<'a>(steps: impl Iterator<Item = &'a ops::PlannedStep>) -> String {
+    let targets: Vec<String> = steps
+        .map(|step| step.target.display().to_string())
+        .collect();
+    format!(
+        "target exists: {} (set on_conflict to skip, rename or overwrite)",
+        targets.join(", ")
+    )
+}
+
+fn summarize(verb: &str, report: TransferReport) -> Result<String, StepError> {
+    let summary = format!("{} {}, {} failed", report.done, verb, report.failed.len());
+    if report.failed.is_empty() {
+        return Ok(summary);
+    }
+    let details: Vec<String> = report
+        .failed
+        .iter()
+        .map(|(path, err)| format!("  {}: {}", path.display(), err))
+        .collect();
+    Err(StepError::Failed(format!(
+        "{}\n{}",
+        summary,
+        details.join("\n")
+    )))
+}
+
+// =============================================================================
+// Journal
+// =============================================================================
+
+/// Append-only log of executed changes: `<unix time>\t<change>`
+struct Journal {
+    path: Option<PathBuf>,
+}
+
+impl Journal {
+    fn record(&mut self, steps: &[UndoStep]) {
+        for step in steps {
+            self.record_text(&step.describe());
+        }
+    }
+
+    fn record_text(&mut self, text: &str) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+            })
+            .and_then(|mut file| writeln!(file, "{}\t{}", now, text));
+        if let Err(err) = result {
+            eprintln!("felipe --batch: journal {}: {}", path.display(), err);
+            // Don't repeat the same complaint for every step
+            self.path = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Script, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    #[test]
+    fn an_empty_script_has_the_defaults() {
+        let script = parse("").unwrap();
+        assert!(!script.dry_run);
+        assert_eq!(script.on_conflict, ConflictPolicy::Abort);
+        assert!(script.journal.is_none());
+        assert!(script.steps.is_empty());
+    }
+
+    #[test]
+    fn every_action_parses() {
+        let script = parse(
+            r#"
+            dry_run = true
+            on_conflict = "rename"
+            journal = "ops.log"
+
+            [[step]]
+            action = "copy"
+            from = ["a.txt", "b"]
+            to = "/backup"
+
+            [[step]]
+            action = "move"
+            from = "c.txt"
+            to = "done"
+            on_conflict = "overwrite"
+
+            [[step]]
+            action = "delete"
+            paths = "tmp"
+            permanent = true
+
+            [[step]]
+            action = "sync"
+            from = "photos"
+            to = "/mnt/photos"
+            delete = true
+            "#,
+        )
+        .unwrap();
+        assert!(script.dry_run);
+        assert_eq!(script.on_conflict, ConflictPolicy::Rename);
+        assert_eq!(script.journal, Some(PathBuf::from("ops.log")));
+        assert_eq!(script.steps.len(), 4);
+        assert!(matches!(
+            &script.steps[0],
+            Step::Copy { from: Sources::Many(paths), on_conflict: None, .. } if paths.len() == 2
+        ));
+        assert!(matches!(
+            &script.steps[1],
+            Step::Move {
+                from: Sources::One(_),
+                on_conflict: Some(ConflictPolicy::Overwrite),
+                ..
+            }
+        ));
+        assert!(matches!(
+            &script.steps[2],
+            Step::Delete {
+                permanent: true,
+                ..
+            }
+        ));
+        assert!(matches!(&script.steps[3], Step::Sync { delete: true, .. }));
+    }
+
+    #[test]
+    fn mistakes_are_refused_rather_than_ignored() {
+        let bad = [
+            // Unknown action
+            "[[step]]\naction = \"trash\"\npaths = \"a\"",
+            // A field of another action
+            "[[step]]\naction = \"delete\"\nfrom = \"a\"",
+            // A misspelled field
+            "[[step]]\naction = \"copy\"\nfrom = \"a\"\nto = \"b\"\non_conflit = \"skip\"",
+            // Missing target
+            "[[step]]\naction = \"copy\"\nfrom = \"a\"",
+            // Unknown policy
+            "on_conflict = \"ask\"",
+            // Unknown top-level key
+            "dryrun = true",
+        ];
+        for text in bad {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn sources_are_relative_to_the_script() {
+        let base = Path::new("/scripts");
+        assert_eq!(
+            Sources::One(PathBuf::from("a.txt")).resolve(base),
+            [PathBuf::from("/scripts/a.txt")]
+        );
+        assert_eq!(
+            Sources::Many(vec![PathBuf::from("b"), PathBuf::from("/abs/c")]).resolve(base),
+            [PathBuf::from("/scripts/b"), PathBuf::from("/abs/c")]
+        );
+    }
+
+    #[test]
+    fn a_dry_run_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::create_dir(dir.path().join("out")).unwrap();
+        let script = dir.path().join("script.toml");
+        std::fs::write(
+            &script,
+            "journal = \"journal.log\"\n\
+             [[step]]\naction = \"move\"\nfrom = \"a.txt\"\nto = \"out\"\n\
+             [[step]]\naction = \"delete\"\npaths = \"a.txt\"\n",
+        )
+        .unwrap();
+        assert_eq!(run_cli(&script, true), 0);
+        assert!(dir.path().join("a.txt").exists());
+        assert!(!dir.path().join("out/a.txt").exists());
+        assert!(!dir.path().join("journal.log").exists());
+    }
+
+    #[test]
+    fn an_unreadable_script_exits_with_two() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(run_cli(&dir.path().join("missing.toml"), false), 2);
+        let script = dir.path().join("bad.toml");
+        std::fs::write(&script, "[[step]]\naction = \"fly\"").unwrap();
+        assert_eq!(run_cli(&script, false), 2);
+    }
+}
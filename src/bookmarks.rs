@@ -0,0 +1,485 @@
+//! Pinned locations - a row of portals at the edge of the grid
+//!
+//! Home, Downloads and mounted drives, plus the pins from the config and the
+//! ones made with `:pin`, stand as rings in front of the first row. Click one,
+//! or type `'` and its number, to go there. The ring of the directory you're
+//! in is lit. Pins made at runtime are kept in `pins.tsv` in the data
+//! directory; the ones from the config stay until the config changes.
+//!
+//! ```toml
+//! [bookmarks]
+//! defaults = true           # home, downloads and mounted drives
+//! pins = [{ name = "projects", path = "~/src" }]
+//! ```
+//!
+//! ```text
+//! :pin [name]    pin the current directory (named after it by default)
+//! :unpin name    remove a pin made with :pin
+//! '3             go to the third portal
+//! ```
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::math::bounding::{Aabb3d, RayCast3d};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::whichkey::PendingKey;
+use crate::{
+    cursor_ray, data_dir, CurrentDirectory, EntryPalette, MainCamera, Prompt, StatusMessage,
+    VimMode, FELIPE_GRID, FELIPE_ORANGE, FELIPE_ORANGE_DIM, ITEM_SPACING,
+};
+
+const FILE_HEADER: &str = "felipe-pins-1";
+/// Rings stand this far in front of the first row
+const PORTAL_ROW_Z: f32 = -2.5 * ITEM_SPACING;
+const PORTAL_RADIUS: f32 = 0.7;
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BookmarksConfig {
+    /// Pin home, downloads and mounted drives
+    pub defaults: bool,
+    pub pins: Vec<PinConfig>,
+}
+
+impl Default for BookmarksConfig {
+    fn default() -> Self {
+        Self {
+            defaults: true,
+            pins: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinConfig {
+    pub name: String,
+    /// `~/x` is home-relative
+    pub path: PathBuf,
+}
+
+// =============================================================================
+// Pins
+// =============================================================================
+
+struct Pin {
+    name: String,
+    path: PathBuf,
+}
+
+#[derive(Resource, Default)]
+pub struct Bookmarks {
+    /// In portal order: defaults, then the config's, then `:pin`'s
+    pins: Vec<Pin>,
+    /// Pins made with `:pin`, as saved in `pins.tsv`
+    runtime: Vec<(String, PathBuf)>,
+}
+
+impl Bookmarks {
+    /// Names of the portals, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.pins.iter().map(|pin| pin.name.as_str())
+    }
+
+    fn rebuild(&mut self, config: &BookmarksConfig) {
+        let mut pins = Vec::new();
+        if config.defaults {
+            pins.extend(dirs::home_dir().map(|path| ("home".to_string(), path)));
+            pins.extend(dirs::download_dir().map(|path| ("downloads".to_string(), path)));
+            pins.extend(mounted_drives());
+        }
+        pins.extend(
+            config
+                .pins
+                .iter()
+                .map(|pin| (pin.name.clone(), expand_home(&pin.path))),
+        );
+        pins.extend(self.runtime.iter().cloned());
+        self.pins = pins
+            .into_iter()
+            .map(|(name, path)| Pin { name, path })
+            .collect();
+    }
+}
+
+/// `~/x` is home-relative
+pub fn expand_home(path: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Removable and network drives the system mounted, named after their mount point
+fn mounted_drives() -> Vec<(String, PathBuf)> {
+    let mut roots = Vec::new();
+    if cfg!(target_os = "macos") {
+        roots.push(PathBuf::from("/Volumes"));
+    } else if cfg!(unix) {
+        let user = std::env::var("USER").unwrap_or_default();
+        roots.push(Path::new("/media").join(&user));
+        roots.push(Path::new("/run/media").join(&user));
+        roots.push(PathBuf::from("/mnt"));
+    } else if cfg!(windows) {
+        return ('A'..='Z')
+            .map(|letter| {
+                (
+                    format!("{}:", letter),
+                    PathBuf::from(format!("{}:\\", letter)),
+                )
+            })
+            .filter(|(_, path)| path.is_dir())
+            .collect();
+    }
+
+    let mut drives = Vec::new();
+    for root in roots {
+        let Ok(read_dir) = std::fs::read_dir(&root) else {
+            continue;
+        };
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            // macOS lists the boot volume as a link to /
+            if !path.is_dir() || path.canonicalize().is_ok_and(|real| real == Path::new("/")) {
+                continue;
+            }
+            drives.push((entry.file_name().to_string_lossy().to_string(), path));
+        }
+    }
+    drives.sort();
+    drives
+}
+
+/// A portal of the pin at `index`
+#[derive(Component)]
+struct PinPortal {
+    index: usize,
+}
+
+pub struct BookmarksPlugin;
+
+impl Plugin for BookmarksPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bookmarks::default())
+            .add_systems(Startup, load_pins)
+            .add_systems(
+                Update,
+                (
+                    rebuild_on_config_change,
+                    handle_pin_commands,
+                    // Sees a sequence's second key after counts and before `z`
+                    handle_pin_keys
+                        .after(crate::handle_count_keys)
+                        .before(crate::handle_keyboard),
+                    handle_portal_click,
+                    spawn_portals,
+                ),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn load_pins(mut bookmarks: ResMut<Bookmarks>) {
+    match load_runtime_pins() {
+        Ok(runtime) => bookmarks.runtime = runtime,
+        Err(err) => warn!("Could not read pins: {}", err),
+    }
+}
+
+fn rebuild_on_config_change(config: Res<Config>, mut bookmarks: ResMut<Bookmarks>) {
+    if config.is_changed() {
+        bookmarks.rebuild(&config.bookmarks);
+    }
+}
+
+/// `:pin [name]` and `:unpin name`
+fn handle_pin_commands(
+    mut run_commands: EventReader<RunCommand>,
+    mut bookmarks: ResMut<Bookmarks>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        match command {
+            Command::Pin(name) => {
+                let name = name.clone().unwrap_or_else(|| {
+                    current_dir
+                        .path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_else(|| current_dir.path.to_string_lossy().to_string())
+                });
+                if bookmarks.pins.iter().any(|pin| pin.name == name) {
+                    status.0 = format!("A pin named {} exists already", name);
+                    continue;
+                }
+                bookmarks
+                    .runtime
+                    .push((name.clone(), current_dir.path.clone()));
+                status.0 = format!("Pinned {} as {}", current_dir.path.display(), name);
+            }
+            Command::Unpin(name) => {
+                let Some(position) = bookmarks
+                    .runtime
+                    .iter()
+                    .position(|(pinned, _)| pinned == name)
+                else {
+                    status.0 = if bookmarks.pins.iter().any(|pin| pin.name == *name) {
+                        format!(
+                            "{} is pinned in the config - :config edit to remove it",
+                            name
+                        )
+                    } else {
+                        format!("No pin named {}", name)
+                    };
+                    continue;
+                };
+                bookmarks.runtime.remove(position);
+                status.0 = format!("Unpinned {}", name);
+            }
+            _ => continue,
+        }
+        bookmarks.rebuild(&config.bookmarks);
+        if let Err(err) = save_runtime_pins(&bookmarks.runtime) {
+            status.0 = format!("Could not save pins: {}", err);
+        }
+    }
+}
+
+/// `'` then a digit - go to that portal
+fn handle_pin_keys(
+    mut key_events: EventReader<KeyboardInput>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    bookmarks: Res<Bookmarks>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut pending_key: ResMut<PendingKey>,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if *vim_mode != VimMode::Normal
+            || prompt.pending.is_some()
+            || focus.any_open()
+            || fly.enabled
+        {
+            if pending_key.key == Some('\'') {
+                pending_key.key = None;
+            }
+            continue;
+        }
+        let Key::Character(c) = &event.logical_key else {
+            continue;
+        };
+        match pending_key.key {
+            None => {
+                if c == "'" {
+                    pending_key.key = Some('\'');
+                }
+                continue;
+            }
+            Some('\'') => pending_key.key = None,
+            // The second key of another sequence
+            Some(_) => continue,
+        }
+        let Some(number) = c.parse::<usize>().ok().filter(|n| *n > 0) else {
+            continue;
+        };
+        match bookmarks.pins.get(number - 1) {
+            Some(pin) => go_to_pin(pin, &mut current_dir, &mut status),
+            None => status.0 = format!("No portal {}", number),
+        }
+    }
+}
+
+/// A click on a portal goes straight there
+fn handle_portal_click(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    portal_query: Query<(&PinPortal, &GlobalTransform)>,
+    prompt: Res<Prompt>,
+    bookmarks: Res<Bookmarks>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) || prompt.pending.is_some() {
+        return;
+    }
+    let Some((_, ray)) = cursor_ray(&window_query, &camera_query) else {
+        return;
+    };
+    let ray_cast = RayCast3d::from_ray(ray, f32::MAX);
+    let hit = portal_query
+        .iter()
+        .filter_map(|(portal, transform)| {
+            // The ring's own bounds lie flat before it's stood up, so use a box
+            let bounds = Aabb3d::new(
+                transform.translation(),
+                Vec3::new(PORTAL_RADIUS, PORTAL_RADIUS, 0.2),
+            );
+            ray_cast
+                .aabb_intersection_at(&bounds)
+                .map(|distance| (portal.index, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some(pin) = hit.and_then(|(index, _)| bookmarks.pins.get(index)) {
+        go_to_pin(pin, &mut current_dir, &mut status);
+    }
+}
+
+/// Rebuild the row when the pins change, lighting the one we're in
+fn spawn_portals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    bookmarks: Res<Bookmarks>,
+    current_dir: Res<CurrentDirectory>,
+    portal_query: Query<Entity, With<PinPortal>>,
+    mut shown_path: Local<PathBuf>,
+) {
+    if !bookmarks.is_changed() && *shown_path == current_dir.path {
+        return;
+    }
+    shown_path.clone_from(&current_dir.path);
+    for entity in portal_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let ring = palette.mesh(&mut meshes, "portal", || {
+        Torus {
+            minor_radius: 0.08,
+            major_radius: PORTAL_RADIUS,
+        }
+        .into()
+    });
+    for (i, pin) in bookmarks.pins.iter().enumerate() {
+        let here = pin.path == current_dir.path;
+        let color = if here { FELIPE_ORANGE } else { FELIPE_GRID };
+        let material = palette.material(&mut materials, color.to_linear());
+        let x = i as f32 * ITEM_SPACING * 1.5 - 9.0;
+        commands
+            .spawn((
+                PinPortal { index: i },
+                SpatialBundle::from_transform(Transform::from_xyz(
+                    x,
+                    PORTAL_RADIUS + 0.2,
+                    PORTAL_ROW_Z,
+                )),
+            ))
+            .with_children(|parent| {
+                // Stood upright, facing the grid
+                parent.spawn(PbrBundle {
+                    mesh: ring.clone(),
+                    material,
+                    transform: Transform::from_rotation(Quat::from_rotation_x(
+                        std::f32::consts::FRAC_PI_2,
+                    )),
+                    ..default()
+                });
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        format!("{} {}", i + 1, pin.name),
+                        TextStyle {
+                            font_size: 30.0,
+                            color: if here {
+                                FELIPE_ORANGE
+                            } else {
+                                FELIPE_ORANGE_DIM
+                            },
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_xyz(0.0, PORTAL_RADIUS + 0.8, 0.0)
+                        .with_scale(Vec3::splat(0.03)),
+                    ..default()
+                });
+            });
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn go_to_pin(pin: &Pin, current_dir: &mut CurrentDirectory, status: &mut StatusMessage) {
+    if !pin.path.is_dir() {
+        status.0 = format!("{} is gone: {}", pin.name, pin.path.display());
+        return;
+    }
+    if pin.path != current_dir.path {
+        current_dir.set_path(&pin.path);
+    }
+}
+
+fn pins_file() -> std::io::Result<PathBuf> {
+    Ok(data_dir()
+        .ok_or_else(|| std::io::Error::other("no data directory on this system"))?
+        .join("pins.tsv"))
+}
+
+/// Tab-separated, path last: `name path`
+fn save_runtime_pins(pins: &[(String, PathBuf)]) -> std::io::Result<()> {
+    let path = pins_file()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = BufWriter::new(std::fs::File::create(&path)?);
+    writeln!(out, "{}", FILE_HEADER)?;
+    for (name, dir) in pins {
+        let Some(dir) = dir.to_str() else {
+            continue;
+        };
+        // Tabs and newlines would break the line format
+        if name.contains(['\t', '\n']) || dir.contains('\n') {
+            continue;
+        }
+        writeln!(out, "{}\t{}", name, dir)?;
+    }
+    out.flush()
+}
+
+fn load_runtime_pins() -> std::io::Result<Vec<(String, PathBuf)>> {
+    let path = pins_file()?;
+    let file = match std::fs::File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut lines = BufReader::new(file).lines();
+    if lines.next().transpose()?.as_deref() != Some(FILE_HEADER) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "unrecognized format",
+        ));
+    }
+
+    let mut pins = Vec::new();
+    for line in lines {
+        let line = line?;
+        if let Some((name, dir)) = line.split_once('\t') {
+            pins.push((name.to_string(), PathBuf::from(dir)));
+        }
+    }
+    Ok(pins)
+}
@@ -0,0 +1,265 @@
+//! Breadcrumbs - the path in the top bar, one directory at a time
+//!
+//! Each directory from the root down to the current one is a segment of its
+//! own. Click one, or type `b` and its number (the root is 1; the numbers
+//! show while `b` waits for one), to jump straight there with the cursor on
+//! the directory you came through. Remote locations and views break up the
+//! same way `h` climbs them.
+//!
+//! ```text
+//! 📂 / home / me / src / felipe
+//! b2             go to /home
+//! ```
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::whichkey::PendingKey;
+use crate::{remote, CurrentDirectory, Prompt, StatusMessage, VimMode};
+use crate::{FELIPE_ORANGE, FELIPE_ORANGE_DIM};
+
+/// No path runs deeper than this; a parent that never ends stops here
+const MAX_CRUMBS: usize = 256;
+
+/// The directories of the path, the root first
+#[derive(Resource, Default)]
+struct Breadcrumbs {
+    crumbs: Vec<PathBuf>,
+    unreadable: bool,
+}
+
+/// Node the segments are spawned into (see `setup_ui`)
+#[derive(Component)]
+pub struct CrumbRow;
+
+/// A clickable segment, by its place in `Breadcrumbs::crumbs`
+#[derive(Component)]
+struct Crumb(usize);
+
+pub struct BreadcrumbsPlugin;
+
+impl Plugin for BreadcrumbsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Breadcrumbs>().add_systems(
+            Update,
+            (
+                follow_path,
+                // Sees a sequence's second key after counts and before `z`
+                handle_crumb_keys
+                    .after(crate::handle_count_keys)
+                    .before(crate::handle_keyboard),
+                handle_crumb_clicks,
+                spawn_crumbs,
+            )
+                .chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn follow_path(current_dir: Res<CurrentDirectory>, mut breadcrumbs: ResMut<Breadcrumbs>) {
+    let unreadable = current_dir.unreadable.is_some();
+    if breadcrumbs.crumbs.last().map(PathBuf::as_path) == Some(current_dir.path())
+        && breadcrumbs.unreadable == unreadable
+    {
+        return;
+    }
+    breadcrumbs.crumbs = ancestors(current_dir.path());
+    breadcrumbs.unreadable = unreadable;
+}
+
+/// `b` and a number - go to that segment
+fn handle_crumb_keys(
+    mut key_events: EventReader<KeyboardInput>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    breadcrumbs: Res<Breadcrumbs>,
+    mut pending_key: ResMut<PendingKey>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if *vim_mode != VimMode::Normal
+            || prompt.pending.is_some()
+            || focus.any_open()
+            || fly.enabled
+        {
+            if pending_key.key == Some('b') {
+                pending_key.key = None;
+            }
+            continue;
+        }
+        let Key::Character(c) = &event.logical_key else {
+            continue;
+        };
+        match pending_key.key {
+            None => {
+                if c == "b" {
+                    pending_key.key = Some('b');
+                }
+                continue;
+            }
+            Some('b') => pending_key.key = None,
+            // The second key of another sequence, like `zb`
+            Some(_) => continue,
+        }
+        let Some(number) = c.parse::<usize>().ok().filter(|n| *n > 0) else {
+            continue;
+        };
+        if number > breadcrumbs.crumbs.len() {
+            status.0 = format!("No segment {}", number);
+            continue;
+        }
+        go_to_crumb(&breadcrumbs, number - 1, &mut current_dir);
+    }
+}
+
+/// Click a segment to go there; the one under the cursor lights up
+fn handle_crumb_clicks(
+    breadcrumbs: Res<Breadcrumbs>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut crumb_query: Query<(&Interaction, &Crumb, &mut Text), Changed<Interaction>>,
+) {
+    for (interaction, crumb, mut text) in crumb_query.iter_mut() {
+        let last = crumb.0 + 1 == breadcrumbs.crumbs.len();
+        let color = match interaction {
+            Interaction::Pressed => {
+                go_to_crumb(&breadcrumbs, crumb.0, &mut current_dir);
+                FELIPE_ORANGE
+            }
+            Interaction::Hovered => FELIPE_ORANGE,
+            Interaction::None if last => FELIPE_ORANGE,
+            Interaction::None => FELIPE_ORANGE_DIM,
+        };
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
+}
+
+fn spawn_crumbs(
+    mut commands: Commands,
+    breadcrumbs: Res<Breadcrumbs>,
+    pending_key: Res<PendingKey>,
+    row_query: Query<Entity, With<CrumbRow>>,
+) {
+    if !breadcrumbs.is_changed() && !pending_key.is_changed() {
+        return;
+    }
+    let numbered = pending_key.key == Some('b');
+    let Ok(row) = row_query.get_single() else {
+        return;
+    };
+    let icon = if breadcrumbs.unreadable {
+        "🔒"
+    } else {
+        "📂"
+    };
+    commands
+        .entity(row)
+        .despawn_descendants()
+        .with_children(|row| {
+            row.spawn(TextBundle::from_section(
+                format!("{} ", icon),
+                crumb_style(FELIPE_ORANGE),
+            ));
+            let count = breadcrumbs.crumbs.len();
+            for (i, path) in breadcrumbs.crumbs.iter().enumerate() {
+                let label = crumb_label(path, i.checked_sub(1).map(|p| &*breadcrumbs.crumbs[p]));
+                let last = i + 1 == count;
+                let mut sections = Vec::new();
+                if numbered {
+                    sections.push(TextSection::new(
+                        format!("{}:", i + 1),
+                        crumb_style(FELIPE_ORANGE),
+                    ));
+                }
+                sections.push(TextSection::new(
+                    label.clone(),
+                    crumb_style(if last {
+                        FELIPE_ORANGE
+                    } else {
+                        FELIPE_ORANGE_DIM
+                    }),
+                ));
+                row.spawn((
+                    TextBundle::from_sections(sections),
+                    Interaction::default(),
+                    Crumb(i),
+                ));
+                // Roots end in a separator of their own
+                if !last && !label.ends_with(['/', '\\']) {
+                    row.spawn(TextBundle::from_section(
+                        " / ",
+                        crumb_style(FELIPE_ORANGE_DIM),
+                    ));
+                }
+            }
+        });
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// `path` and the directories above it as `h` climbs them, the root first
+pub fn ancestors(path: &Path) -> Vec<PathBuf> {
+    let mut crumbs = vec![path.to_path_buf()];
+    while let Some(parent) = remote::parent(&crumbs[crumbs.len() - 1]) {
+        if crumbs.contains(&parent) || crumbs.len() >= MAX_CRUMBS {
+            break;
+        }
+        crumbs.push(parent);
+    }
+    crumbs.reverse();
+    crumbs
+}
+
+/// Go to segment `index`, with the cursor on the directory below it
+fn go_to_crumb(breadcrumbs: &Breadcrumbs, index: usize, current_dir: &mut CurrentDirectory) {
+    let Some(path) = breadcrumbs.crumbs.get(index) else {
+        return;
+    };
+    if path == current_dir.path() {
+        return;
+    }
+    current_dir.pending_select = breadcrumbs.crumbs.get(index + 1).cloned();
+    current_dir.set_path(path);
+}
+
+/// What `path` adds to `parent`; the whole of it for a root
+pub fn crumb_label(path: &Path, parent: Option<&Path>) -> String {
+    let text = path.to_string_lossy();
+    let Some(parent) = parent else {
+        return text.to_string();
+    };
+    let label = text
+        .strip_prefix(&*parent.to_string_lossy())
+        .unwrap_or(&text)
+        .trim_matches(['/', '\\']);
+    if label.is_empty() {
+        text.to_string()
+    } else {
+        label.to_string()
+    }
+}
+
+fn crumb_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 24.0,
+        color,
+        ..default()
+    }
+}
@@ -0,0 +1,402 @@
+//! Checksums - `:hash sha256` computes them, `:verify` checks them
+//!
+//! Digests are computed on a worker thread, since a disk image takes a while
+//! to read. `:hash` lists them in the properties panel (`i`) and copies them
+//! to the clipboard one `digest  name` line per file, the way `sha256sum`
+//! prints them, ready to paste next to a download link. Like `:chmod` it acts
+//! on a visual range when typed over one; directories are skipped.
+//!
+//! `:verify` reads the checksum files of the current directory - `SHA256SUMS`,
+//! `SHA512SUMS`, `MD5SUMS` and sidecars like `image.iso.sha256` - and checks
+//! every file they list. Entries that match turn green, those that don't (or
+//! can't be read) red; files listed but missing are counted in the status.
+//!
+//! ```text
+//! :hash            sha256 of the selection
+//! :hash md5        md5 (also sha512)
+//! :verify          check against SHA256SUMS, *.sha256, *.md5 ...
+//! ```
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use md5::Md5;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::clipboard::HeldClipboard;
+use crate::command::{Command, CommandLine, RunCommand};
+use crate::focus::Focus;
+use crate::properties::{self, PropertiesView};
+use crate::{CurrentDirectory, EntryTints, StatusMessage, DIFF_ADDED, DIFF_REMOVED};
+
+/// Bytes read from a file at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Hex digits in a digest
+    fn hex_len(self) -> usize {
+        match self {
+            HashAlgorithm::Md5 => 32,
+            HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha512 => 128,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Lower-case hex digest of everything in `path`
+    pub fn digest_file(self, path: &Path) -> io::Result<String> {
+        let file = File::open(path)?;
+        match self {
+            HashAlgorithm::Md5 => digest_reader::<Md5>(file),
+            HashAlgorithm::Sha256 => digest_reader::<Sha256>(file),
+            HashAlgorithm::Sha512 => digest_reader::<Sha512>(file),
+        }
+    }
+}
+
+impl std::str::FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "md5" => Ok(HashAlgorithm::Md5),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            _ => Err(format!("Unknown hash: {} (sha256, sha512, md5)", s)),
+        }
+    }
+}
+
+fn digest_reader<D: Digest>(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = D::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// One file's digest, or why it couldn't be read
+pub struct FileDigest {
+    pub name: String,
+    pub digest: Result<String, String>,
+}
+
+/// What a listed file turned out to be
+enum Verdict {
+    Matches,
+    Differs,
+    Unreadable,
+    Missing,
+}
+
+enum TaskResult {
+    Hashed {
+        algorithm: HashAlgorithm,
+        digests: Vec<FileDigest>,
+    },
+    Verified {
+        /// Checksum files read
+        sources: Vec<String>,
+        verdicts: Vec<(PathBuf, Verdict)>,
+    },
+}
+
+/// Hashing runs on a worker thread; big files take a while
+#[derive(Resource, Default)]
+struct HashTask {
+    worker: Option<JoinHandle<TaskResult>>,
+}
+
+pub struct ChecksumPlugin;
+
+impl Plugin for ChecksumPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HashTask::default()).add_systems(
+            Update,
+            (handle_hash_command, handle_verify_command, finish_hash_task),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_hash_command(
+    mut run_commands: EventReader<RunCommand>,
+    command_line: Res<CommandLine>,
+    current_dir: Res<CurrentDirectory>,
+    mut task: ResMut<HashTask>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Hash(algorithm) = command else {
+            continue;
+        };
+        if task.worker.is_some() {
+            status.0 = "Still hashing the last selection".to_string();
+            continue;
+        }
+        let files: Vec<(String, PathBuf)> = command_line
+            .targets(&current_dir)
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| (entry.name.clone(), entry.path.clone()))
+            .collect();
+        if files.is_empty() {
+            status.0 = "No files selected to hash".to_string();
+            continue;
+        }
+        status.0 = format!(
+            "Computing {} of {} file(s)...",
+            algorithm.name(),
+            files.len()
+        );
+        let algorithm = *algorithm;
+        task.worker = Some(std::thread::spawn(move || TaskResult::Hashed {
+            algorithm,
+            digests: files
+                .into_iter()
+                .map(|(name, path)| FileDigest {
+                    name,
+                    digest: algorithm.digest_file(&path).map_err(|err| err.to_string()),
+                })
+                .collect(),
+        }));
+    }
+}
+
+fn handle_verify_command(
+    mut run_commands: EventReader<RunCommand>,
+    current_dir: Res<CurrentDirectory>,
+    mut task: ResMut<HashTask>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Verify = command else {
+            continue;
+        };
+        if task.worker.is_some() {
+            status.0 = "Still hashing the last selection".to_string();
+            continue;
+        }
+        let sums = checksum_files(&current_dir.path);
+        if sums.is_empty() {
+            status.0 = "No checksum files here (SHA256SUMS, *.sha256, *.md5...)".to_string();
+            continue;
+        }
+        status.0 = format!("Verifying against {} checksum file(s)...", sums.len());
+        let dir = current_dir.path.clone();
+        task.worker = Some(std::thread::spawn(move || verify(&dir, sums)));
+    }
+}
+
+fn finish_hash_task(
+    mut commands: Commands,
+    mut task: ResMut<HashTask>,
+    mut clipboard: NonSendMut<HeldClipboard>,
+    mut view: ResMut<PropertiesView>,
+    mut focus: ResMut<Focus>,
+    mut tints: ResMut<EntryTints>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !task
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        return;
+    }
+    let worker = task.worker.take().expect("checked above");
+    let Ok(result) = worker.join() else {
+        status.0 = "Hash worker crashed".to_string();
+        return;
+    };
+
+    let (algorithm, digests) = match result {
+        TaskResult::Hashed { algorithm, digests } => (algorithm, digests),
+        TaskResult::Verified { sources, verdicts } => {
+            status.0 = verify_summary(&sources, &verdicts);
+            tints.colors = verdicts
+                .into_iter()
+                .filter_map(|(path, verdict)| match verdict {
+                    Verdict::Matches => Some((path, DIFF_ADDED)),
+                    Verdict::Differs | Verdict::Unreadable => Some((path, DIFF_REMOVED)),
+                    Verdict::Missing => None,
+                })
+                .collect();
+            return;
+        }
+    };
+
+    // Same layout as sha256sum, so `sha256sum -c` reads it back
+    let lines: Vec<String> = digests
+        .iter()
+        .filter_map(|file| {
+            let digest = file.digest.as_ref().ok()?;
+            Some(format!("{}  {}", digest, file.name))
+        })
+        .collect();
+    let failed = digests.len() - lines.len();
+    let copied = if lines.is_empty() {
+        Err("nothing to copy".to_string())
+    } else {
+        clipboard.copy(lines.join("\n"))
+    };
+    status.0 = match (&copied, failed) {
+        (Ok(()), 0) => format!("{} of {} file(s) copied", algorithm.name(), lines.len()),
+        (Ok(()), _) => format!(
+            "{} of {} file(s) copied, {} unreadable",
+            algorithm.name(),
+            lines.len(),
+            failed
+        ),
+        (Err(reason), _) => format!("{} computed, not copied: {}", algorithm.name(), reason),
+    };
+
+    view.show_digests(algorithm.name(), digests);
+    properties::open_panel(&mut commands, &mut focus);
+}
+
+// =============================================================================
+// Worker Tasks
+// =============================================================================
+
+/// Check every file listed in `sums` (checksum file and its algorithm)
+fn verify(dir: &Path, sums: Vec<(PathBuf, HashAlgorithm)>) -> TaskResult {
+    let mut sources = Vec::new();
+    let mut expected: HashMap<PathBuf, (HashAlgorithm, String)> = HashMap::new();
+    for (sum_path, algorithm) in sums {
+        let Ok(text) = fs::read_to_string(&sum_path) else {
+            continue;
+        };
+        let sum_name = sum_path.file_name().unwrap_or_default().to_string_lossy();
+        // A sidecar holding a bare digest is about the file it's named after
+        let sidecar_of = sum_name
+            .rsplit_once('.')
+            .map(|(stem, _)| stem.to_string())
+            .filter(|_| !sum_name.to_ascii_uppercase().ends_with("SUMS"));
+        for line in text.lines() {
+            let Some((digest, name)) = parse_sum_line(line, algorithm, sidecar_of.as_deref())
+            else {
+                continue;
+            };
+            expected.insert(dir.join(name), (algorithm, digest));
+        }
+        sources.push(sum_name.into_owned());
+    }
+
+    let verdicts = expected
+        .into_iter()
+        .map(|(path, (algorithm, digest))| {
+            let verdict = match algorithm.digest_file(&path) {
+                Ok(actual) if actual == digest => Verdict::Matches,
+                Ok(_) => Verdict::Differs,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Verdict::Missing,
+                Err(_) => Verdict::Unreadable,
+            };
+            (path, verdict)
+        })
+        .collect();
+    TaskResult::Verified { sources, verdicts }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Checksum files in `dir` and the algorithm each one uses
+fn checksum_files(dir: &Path) -> Vec<(PathBuf, HashAlgorithm)> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut sums: Vec<(PathBuf, HashAlgorithm)> = read_dir
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_ascii_lowercase();
+            let algorithm = match name.as_str() {
+                "sha256sums" | "sha256sums.txt" => HashAlgorithm::Sha256,
+                "sha512sums" | "sha512sums.txt" => HashAlgorithm::Sha512,
+                "md5sums" | "md5sums.txt" => HashAlgorithm::Md5,
+                _ => name.rsplit_once('.')?.1.parse().ok()?,
+            };
+            Some((entry.path(), algorithm))
+        })
+        .collect();
+    sums.sort_by(|a, b| a.0.cmp(&b.0));
+    sums
+}
+
+/// Digest and file name of one line of a checksum file: `digest  name` or
+/// `digest *name` (GNU), `SHA256 (name) = digest` (BSD), or a bare digest
+/// in the sidecar of `sidecar_of`
+fn parse_sum_line(
+    line: &str,
+    algorithm: HashAlgorithm,
+    sidecar_of: Option<&str>,
+) -> Option<(String, String)> {
+    let line = line.trim_end();
+    let is_digest = |text: &str| {
+        text.len() == algorithm.hex_len() && text.chars().all(|c| c.is_ascii_hexdigit())
+    };
+    if let Some((name, digest)) = line
+        .split_once(" (")
+        .and_then(|(_, rest)| rest.rsplit_once(") = "))
+    {
+        return is_digest(digest).then(|| (digest.to_ascii_lowercase(), name.to_string()));
+    }
+    let (digest, name) = match line.split_once(' ') {
+        Some((digest, name)) => (digest, name.trim_start_matches([' ', '*'])),
+        None => (line, sidecar_of?),
+    };
+    // Names are relative to the checksum file; anything else isn't ours to check
+    if !is_digest(digest) || name.is_empty() || name.contains(['/', '\\']) {
+        return None;
+    }
+    Some((digest.to_ascii_lowercase(), name.to_string()))
+}
+
+fn verify_summary(sources: &[String], verdicts: &[(PathBuf, Verdict)]) -> String {
+    let count = |wanted: fn(&Verdict) -> bool| verdicts.iter().filter(|(_, v)| wanted(v)).count();
+    let matching = count(|v| matches!(v, Verdict::Matches));
+    let differing = count(|v| matches!(v, Verdict::Differs | Verdict::Unreadable));
+    let missing = count(|v| matches!(v, Verdict::Missing));
+    let mut summary = format!("{}: {} OK", sources.join(", "), matching);
+    if differing > 0 {
+        summary.push_str(&format!(", {} FAILED", differing));
+    }
+    if missing > 0 {
+        summary.push_str(&format!(", {} missing", missing));
+    }
+    summary
+}
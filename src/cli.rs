@@ -0,0 +1,139 @@
+//! Command line - `felipe [options] [path]`
+//!
+//! The path is the directory to start in (the working directory when left
+//! out), a remote location like `sftp://user@host/path`,
+//! `s3://bucket/prefix` or `ftp://host/path`, or a `.workspace` file to open
+//! as tabs. Options override the config for this run only:
+//!
+//! ```text
+//! felipe ~/photos --show-hidden
+//! felipe --theme retro --layout flat src/
+//! felipe --read-only /mnt/backup
+//! felipe --gpu-low              # no bloom, CRT or far grid lines
+//! felipe --demo                 # a sample tree in memory (see demofs.rs)
+//! felipe --config ./felipe.toml
+//! file=$(felipe --choose-file ~/Downloads)
+//! felipe --cwd-file /tmp/felipe-cwd
+//! felipe --verbose              # debug lines in the log file (see logging.rs)
+//! felipe --tui                  # over SSH, with --features tui
+//! ```
+//!
+//! `felipe --batch script.toml` runs a batch script instead (see batch.rs).
+
+use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
+
+use crate::config::RenderConfig;
+use crate::picker::PickMode;
+
+#[derive(Parser)]
+#[command(
+    name = "felipe",
+    version,
+    about = "Cyberpunk file manager in 3D, with vim keys"
+)]
+pub struct Args {
+    /// Directory to start in (sftp://, s3://, ftp://, dav:// too), or a .workspace file to open as tabs
+    pub path: Option<PathBuf>,
+    /// Look to start with
+    #[arg(long, value_enum)]
+    pub theme: Option<Theme>,
+    /// How the entries are arranged
+    #[arg(long, value_enum)]
+    pub layout: Option<Layout>,
+    /// Refuse every operation that would change files
+    #[arg(long)]
+    pub read_only: bool,
+    /// List dotfiles
+    #[arg(long)]
+    pub show_hidden: bool,
+    /// Render without bloom, CRT or far grid lines, for weak GPUs
+    #[arg(long)]
+    pub gpu_low: bool,
+    /// Start in a made-up tree kept in memory instead of the disk
+    #[arg(long, conflicts_with = "path")]
+    pub demo: bool,
+    /// Config file to use instead of the one in the config directory
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Print file events as JSON lines on stdout (see events.rs)
+    #[arg(long)]
+    pub events_json: bool,
+    /// Pick files and print their paths on stdout (see picker.rs)
+    #[arg(long, conflicts_with = "choose_dir")]
+    pub choose_file: bool,
+    /// Pick a directory and print its path on stdout
+    #[arg(long)]
+    pub choose_dir: bool,
+    /// Log debug lines too, to stderr and the log file in the data directory
+    #[arg(short, long)]
+    pub verbose: bool,
+    /// Write the last directory to FILE on exit, for cd on quit (see cwdfile.rs)
+    #[arg(long, value_name = "FILE")]
+    pub cwd_file: Option<PathBuf>,
+    /// Listen for JSON commands on this socket (see ipc.rs)
+    #[cfg(all(unix, feature = "ipc"))]
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+    /// Serve the desktop's file chooser portal (see portal.rs)
+    #[cfg(all(target_os = "linux", feature = "portal"))]
+    #[arg(long)]
+    pub portal: bool,
+    /// Run a script of file operations instead of opening a window (see batch.rs)
+    #[arg(long, value_name = "SCRIPT", conflicts_with_all = ["path", "demo"])]
+    pub batch: Option<PathBuf>,
+    /// With --batch, only print what the script would do
+    #[arg(short = 'n', long, requires = "batch")]
+    pub dry_run: bool,
+    /// Show the listing in the terminal instead of a window (see tui.rs)
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    pub tui: bool,
+}
+
+impl Args {
+    pub fn pick_mode(&self) -> Option<PickMode> {
+        if self.choose_file {
+            Some(PickMode::File)
+        } else if self.choose_dir {
+            Some(PickMode::Dir)
+        } else {
+            None
+        }
+    }
+}
+
+/// Presets of the render settings
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Theme {
+    /// Glowing solid books (the defaults)
+    Neon,
+    /// Neon through a CRT: scanlines and color fringes
+    Retro,
+    /// Edge outlines without glow
+    Blueprint,
+    /// Solid books without glow or effects
+    Plain,
+}
+
+impl Theme {
+    pub fn apply(self, render: &mut RenderConfig) {
+        let (bloom, crt, wireframe) = match self {
+            Theme::Neon => (true, false, false),
+            Theme::Retro => (true, true, false),
+            Theme::Blueprint => (false, false, true),
+            Theme::Plain => (false, false, false),
+        };
+        render.bloom = bloom;
+        render.crt = crt;
+        render.wireframe = wireframe;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Layout {
+    /// The directory's entries on a grid
+    Grid,
+    /// Every file below the start directory on one grid, like `:flatten`
+    Flat,
+}
@@ -0,0 +1,324 @@
+//! Clipboard - paths of the selection out, files from other applications in
+//!
+//! `y` waits for the key after it. `p`, `n` or `d` copy where the selection
+//! is to the system clipboard as text, and leave the register alone:
+//!
+//! ```text
+//! yp   full path        /home/me/src/felipe/Cargo.toml
+//! yn   name             Cargo.toml
+//! yd   its directory    /home/me/src/felipe
+//! ```
+//!
+//! Any other key, or none for a moment, and the `y` yanks into the register
+//! as usual - the marked entries, else the one under the cursor (see
+//! basket.rs); `yy` yanks straight away.
+//!
+//! The other way, Ctrl-v copies the files another file manager put on the
+//! clipboard (Explorer, Finder, Nautilus and the like) into the current
+//! directory, through the job queue like a paste of the register.
+//!
+//! Dragging an entry to the edge of the window puts it on the clipboard as a
+//! file, ready to paste into a browser, chat or editor. winit can't start a
+//! native drag out of the window, so a paste there stands in for the drop.
+//!
+//! The clipboard is opened once and held while Felipe runs (`:hash` copies
+//! through it too). The browser build has none of its own to reach.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use std::path::PathBuf;
+
+use crate::basket::Basket;
+use crate::config::Config;
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::jobs::JobQueue;
+use crate::ops::{self, TransferKind};
+use crate::whichkey::PendingKey;
+use crate::{CurrentDirectory, DragState, Prompt, Register, StatusMessage, VimMode};
+
+/// Seconds a `y` waits for a `p`, `n` or `d` before it yanks; the hints
+/// (see whichkey.rs) show for most of it
+const YANK_WAIT: f32 = 1.5;
+
+/// Pixels from the window's border where a drag counts as leaving it
+const DRAG_OUT_MARGIN: f32 = 12.0;
+
+/// The clipboard stays open while felipe runs: on X11 the copied text is only
+/// served as long as its owner lives
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+pub struct HeldClipboard(Option<arboard::Clipboard>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HeldClipboard {
+    fn open(&mut self) -> Result<&mut arboard::Clipboard, String> {
+        if self.0.is_none() {
+            self.0 = Some(arboard::Clipboard::new().map_err(|err| err.to_string())?);
+        }
+        Ok(self.0.as_mut().expect("opened above"))
+    }
+
+    pub fn copy(&mut self, text: String) -> Result<(), String> {
+        self.open()?.set_text(text).map_err(|err| err.to_string())
+    }
+
+    /// Files for another application to paste
+    fn offer_files(&mut self, files: &[PathBuf]) -> Result<(), String> {
+        self.open()?
+            .set()
+            .file_list(files)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Files copied in another application (`text/uri-list`, `CF_HDROP`)
+    fn files(&mut self) -> Result<Vec<PathBuf>, String> {
+        match self.open()?.get().file_list() {
+            Ok(files) => Ok(files),
+            Err(arboard::Error::ContentNotAvailable) => Ok(Vec::new()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub struct HeldClipboard;
+
+#[cfg(target_arch = "wasm32")]
+impl HeldClipboard {
+    const MISSING: &'static str = "no system clipboard in the browser";
+
+    pub fn copy(&mut self, _text: String) -> Result<(), String> {
+        Err(Self::MISSING.to_string())
+    }
+
+    fn offer_files(&mut self, _files: &[PathBuf]) -> Result<(), String> {
+        Err(Self::MISSING.to_string())
+    }
+
+    fn files(&mut self) -> Result<Vec<PathBuf>, String> {
+        Err(Self::MISSING.to_string())
+    }
+}
+
+pub struct ClipboardPlugin;
+
+impl Plugin for ClipboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_non_send_resource(HeldClipboard::default())
+            // `handle_keyboard` leaves NORMAL mode's `y` and `m` here, and the
+            // `p` of `yp` alone
+            .add_systems(
+                Update,
+                (
+                    handle_yank_keys.after(crate::handle_keyboard),
+                    handle_paste_files_key,
+                    offer_drag_out.after(crate::handle_mouse_drag),
+                ),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// `y` waiting for the key after it: when it was pressed, the entry under
+/// the cursor for `yp`, `yn` or `yd`, and what it yanks if it's neither
+#[derive(Default)]
+struct HeldYank {
+    since: f32,
+    path: Option<PathBuf>,
+    targets: Vec<PathBuf>,
+}
+
+/// `y` then `p`, `n` or `d` - copy the selection's path, name or directory;
+/// `y` then anything else, or nothing for a moment, yanks. `m` cuts at once,
+/// after a `y` it ends. Both take what `Basket::yank_targets` says
+fn handle_yank_keys(
+    mut key_events: EventReader<KeyboardInput>,
+    time: Res<Time>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    current_dir: Res<CurrentDirectory>,
+    basket: Res<Basket>,
+    mut held: Local<HeldYank>,
+    mut clipboard: NonSendMut<HeldClipboard>,
+    mut register: ResMut<Register>,
+    mut pending_key: ResMut<PendingKey>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if *vim_mode != VimMode::Normal
+            || prompt.pending.is_some()
+            || focus.any_open()
+            || fly.enabled
+        {
+            // The key opened a prompt, a panel or another mode
+            if pending_key.key == Some('y') {
+                pending_key.key = None;
+                yank_held(&mut held, &mut register, &current_dir, &mut status);
+            }
+            continue;
+        }
+        let c = match &event.logical_key {
+            Key::Character(c) => c.as_str(),
+            // Shift on its way to a capital
+            Key::Shift | Key::Control | Key::Alt | Key::Super => continue,
+            _ => "",
+        };
+        // A key that just left VISUAL mode yanked or cut the range already
+        let fresh = !vim_mode.is_changed();
+        let what = match (pending_key.key, c) {
+            (None, "y") if fresh => {
+                pending_key.key = Some('y');
+                held.since = time.elapsed_seconds();
+                held.path = current_dir.selected_path().map(PathBuf::from);
+                held.targets = basket.yank_targets(&current_dir);
+                continue;
+            }
+            (None, "m") if fresh => {
+                cut(&basket, &mut register, &current_dir, &mut status);
+                continue;
+            }
+            (Some('y'), "p") => "path",
+            (Some('y'), "n") => "name",
+            (Some('y'), "d") => "directory",
+            // Not a chord after all, `yy` included
+            (Some('y'), _) => {
+                pending_key.key = None;
+                yank_held(&mut held, &mut register, &current_dir, &mut status);
+                if c == "m" {
+                    cut(&basket, &mut register, &current_dir, &mut status);
+                }
+                continue;
+            }
+            // Another key, or the second key of another sequence
+            _ => continue,
+        };
+        pending_key.key = None;
+        held.targets.clear();
+        let Some(path) = held.path.take() else {
+            continue;
+        };
+        let text = match c {
+            "p" => path,
+            "n" => path.file_name().map_or(path.clone(), PathBuf::from),
+            _ => path.parent().unwrap_or(current_dir.path()).to_path_buf(),
+        };
+        let text = text.to_string_lossy().to_string();
+        status.0 = match clipboard.copy(text.clone()) {
+            Ok(()) => format!("Copied {}: {}", what, text),
+            Err(reason) => format!("Cannot copy the {}: {}", what, reason),
+        };
+    }
+
+    if pending_key.key == Some('y') && time.elapsed_seconds() - held.since >= YANK_WAIT {
+        pending_key.key = None;
+        yank_held(&mut held, &mut register, &current_dir, &mut status);
+    }
+}
+
+fn cut(
+    basket: &Basket,
+    register: &mut Register,
+    current_dir: &CurrentDirectory,
+    status: &mut StatusMessage,
+) {
+    let targets = basket.yank_targets(current_dir);
+    crate::yank_paths(register, current_dir, &targets, TransferKind::Move, status);
+}
+
+/// Yank what a held `y` was pressed on, as far as it's still listed
+fn yank_held(
+    held: &mut HeldYank,
+    register: &mut Register,
+    current_dir: &CurrentDirectory,
+    status: &mut StatusMessage,
+) {
+    let targets = std::mem::take(&mut held.targets);
+    crate::yank_paths(register, current_dir, &targets, TransferKind::Copy, status);
+}
+
+/// Ctrl-v - copy the files on the clipboard here
+fn handle_paste_files_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    current_dir: Res<CurrentDirectory>,
+    mut clipboard: NonSendMut<HeldClipboard>,
+    config: Res<Config>,
+    mut prompt: ResMut<Prompt>,
+    mut jobs: ResMut<JobQueue>,
+    mut status: ResMut<StatusMessage>,
+) {
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !ctrl
+        || !keyboard.just_pressed(KeyCode::KeyV)
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || focus.any_open()
+        || fly.enabled
+    {
+        return;
+    }
+    let files = match clipboard.files() {
+        Ok(files) if files.is_empty() => {
+            status.0 = "No files on the clipboard".to_string();
+            return;
+        }
+        Ok(files) => files,
+        Err(reason) => {
+            status.0 = format!("Cannot read the clipboard: {}", reason);
+            return;
+        }
+    };
+    let plan = ops::plan_transfer(TransferKind::Copy, &files, current_dir.path());
+    crate::offer_transfer(plan, &config, &mut prompt, &mut jobs);
+}
+
+/// An entry dragged to the window's edge goes on the clipboard, once a drag
+fn offer_drag_out(
+    drag_state: Res<DragState>,
+    current_dir: Res<CurrentDirectory>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut clipboard: NonSendMut<HeldClipboard>,
+    mut status: ResMut<StatusMessage>,
+    mut offered: Local<bool>,
+) {
+    let Some((index, _)) = drag_state.pressed.filter(|_| drag_state.active) else {
+        *offered = false;
+        return;
+    };
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    // Off the window there's no position at all
+    let at_edge = window.cursor_position().is_none_or(|cursor| {
+        let size = Vec2::new(window.width(), window.height());
+        cursor.min_element() < DRAG_OUT_MARGIN || (size - cursor).min_element() < DRAG_OUT_MARGIN
+    });
+    if !at_edge || *offered {
+        return;
+    }
+    let Some(entry) = current_dir.entries.get(index) else {
+        return;
+    };
+    *offered = true;
+    status.0 = match clipboard.offer_files(std::slice::from_ref(&entry.path)) {
+        Ok(()) => format!(
+            "{} is on the clipboard, paste it in the other application",
+            entry.name
+        ),
+        Err(reason) => format!("Cannot put {} on the clipboard: {}", entry.name, reason),
+    };
+}
@@ -0,0 +1,385 @@
+//! Cloud-sync badges - sync state of entries inside Dropbox, Nextcloud and OneDrive folders
+//!
+//! Each client is asked the way it allows: the `dropbox filestatus` CLI, the
+//! Nextcloud desktop client's socket, and file attributes for OneDrive on
+//! Windows. Conflicted copies are recognized by name everywhere (see `conflicts.rs`). Queries run
+//! on a worker thread and are repeated while the directory is on screen.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use crate::conflicts::conflict_original;
+use crate::{entry_height, CurrentDirectory, EntryPalette, FileEntity, FileEntry, DIFF_REMOVED};
+
+/// Seconds between status refreshes, so "syncing" turns into "synced" on its own
+const POLL_SECONDS: f32 = 3.0;
+/// Edge length of the badge cube on top of an entry
+const BADGE_SIZE: f32 = 0.22;
+
+/// Per-entry sync state reported by a client
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SyncStatus {
+    Synced,
+    Syncing,
+    /// Present only in the cloud (OneDrive Files On-Demand)
+    #[cfg_attr(not(windows), allow(dead_code))]
+    OnlineOnly,
+    Conflict,
+    Error,
+}
+
+impl SyncStatus {
+    fn color(self) -> Color {
+        match self {
+            SyncStatus::Synced => Color::srgb(0.2, 0.8, 1.0),
+            SyncStatus::Syncing => Color::srgb(1.0, 1.0, 1.0),
+            SyncStatus::OnlineOnly => Color::srgb(0.4, 0.4, 0.45),
+            SyncStatus::Conflict => Color::srgb(1.0, 0.2, 0.8),
+            SyncStatus::Error => DIFF_REMOVED,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Provider {
+    Dropbox,
+    Nextcloud,
+    OneDrive,
+}
+
+// =============================================================================
+// Detection
+// =============================================================================
+
+/// The sync client owning `dir`, if any
+fn detect_provider(dir: &Path) -> Option<Provider> {
+    if dropbox_roots().iter().any(|root| dir.starts_with(root)) {
+        return Some(Provider::Dropbox);
+    }
+    if onedrive_roots().iter().any(|root| dir.starts_with(root)) {
+        return Some(Provider::OneDrive);
+    }
+    for ancestor in dir.ancestors() {
+        if ancestor.join(".dropbox.cache").is_dir() {
+            return Some(Provider::Dropbox);
+        }
+        // The Nextcloud/ownCloud client keeps its journal in the sync root
+        let has_journal = std::fs::read_dir(ancestor).is_ok_and(|mut entries| {
+            entries.any(|entry| {
+                entry.is_ok_and(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    (name.starts_with(".sync_") || name.starts_with("._sync_"))
+                        && name.ends_with(".db")
+                })
+            })
+        });
+        if has_journal {
+            return Some(Provider::Nextcloud);
+        }
+    }
+    None
+}
+
+/// Folders listed in `~/.dropbox/info.json` (personal and business accounts)
+fn dropbox_roots() -> Vec<PathBuf> {
+    let Some(info) = dirs::home_dir().map(|home| home.join(".dropbox/info.json")) else {
+        return Vec::new();
+    };
+    let Ok(text) = std::fs::read_to_string(info) else {
+        return Vec::new();
+    };
+    let Ok(serde_json::Value::Object(accounts)) = serde_json::from_str(&text) else {
+        return Vec::new();
+    };
+    accounts
+        .values()
+        .filter_map(|account| account.get("path")?.as_str().map(PathBuf::from))
+        .collect()
+}
+
+/// `OneDrive*` environment variables on Windows, `~/OneDrive` elsewhere
+fn onedrive_roots() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"]
+        .iter()
+        .filter_map(|var| std::env::var_os(var).map(PathBuf::from))
+        .collect();
+    if let Some(home) = dirs::home_dir() {
+        roots.push(home.join("OneDrive"));
+    }
+    roots
+}
+
+// =============================================================================
+// Status Queries
+// =============================================================================
+
+fn query_statuses(dir: &Path, entries: &[FileEntry]) -> HashMap<PathBuf, SyncStatus> {
+    let Some(provider) = detect_provider(dir) else {
+        return HashMap::new();
+    };
+    let paths: Vec<&Path> = entries
+        .iter()
+        .filter(|entry| entry.name != "..")
+        .map(|entry| entry.path.as_path())
+        .collect();
+
+    let mut statuses = match provider {
+        Provider::Dropbox => dropbox_statuses(&paths),
+        Provider::Nextcloud => nextcloud_statuses(&paths),
+        Provider::OneDrive => onedrive_statuses(&paths),
+    };
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if conflict_original(&name).is_some() {
+            statuses.insert(path.to_path_buf(), SyncStatus::Conflict);
+        }
+    }
+    statuses
+}
+
+/// `dropbox filestatus` prints `<path>: up to date|syncing|unsyncable|...`
+fn dropbox_statuses(paths: &[&Path]) -> HashMap<PathBuf, SyncStatus> {
+    let Ok(output) = std::process::Command::new("dropbox")
+        .arg("filestatus")
+        .args(paths)
+        .output()
+    else {
+        return HashMap::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (path, state) = line.rsplit_once(": ")?;
+            let status = match state.trim() {
+                "up to date" => SyncStatus::Synced,
+                "syncing" => SyncStatus::Syncing,
+                "unsyncable" => SyncStatus::Error,
+                _ => return None,
+            };
+            Some((PathBuf::from(path), status))
+        })
+        .collect()
+}
+
+/// Ask the desktop client over its socket: `RETRIEVE_FILE_STATUS:<path>`
+#[cfg(unix)]
+fn nextcloud_statuses(paths: &[&Path]) -> HashMap<PathBuf, SyncStatus> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut statuses = HashMap::new();
+    let Some(runtime) = dirs::runtime_dir() else {
+        return statuses;
+    };
+    let Some(mut stream) = ["Nextcloud", "ownCloud"]
+        .iter()
+        .find_map(|client| UnixStream::connect(runtime.join(client).join("socket")).ok())
+    else {
+        return statuses;
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(500)));
+
+    for path in paths {
+        if writeln!(stream, "RETRIEVE_FILE_STATUS:{}", path.display()).is_err() {
+            return statuses;
+        }
+    }
+    // Replies are `STATUS:<state>:<path>`, mixed with unrelated broadcasts
+    let reader = BufReader::new(stream);
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Some(rest) = line.strip_prefix("STATUS:") else {
+            continue;
+        };
+        let Some((state, path)) = rest.split_once(':') else {
+            continue;
+        };
+        let status = match state.split('+').next().unwrap_or(state) {
+            "OK" => SyncStatus::Synced,
+            "SYNC" | "NEW" => SyncStatus::Syncing,
+            "ERROR" => SyncStatus::Error,
+            _ => continue,
+        };
+        statuses.insert(PathBuf::from(path), status);
+        if statuses.len() == paths.len() {
+            break;
+        }
+    }
+    statuses
+}
+
+#[cfg(not(unix))]
+fn nextcloud_statuses(_paths: &[&Path]) -> HashMap<PathBuf, SyncStatus> {
+    HashMap::new()
+}
+
+/// Files On-Demand state lives in the Windows file attributes
+#[cfg(windows)]
+fn onedrive_statuses(paths: &[&Path]) -> HashMap<PathBuf, SyncStatus> {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_PINNED: u32 = 0x0008_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x0000_1000;
+
+    paths
+        .iter()
+        .filter_map(|path| {
+            let attributes = std::fs::symlink_metadata(path).ok()?.file_attributes();
+            let status = if attributes
+                & (FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_OFFLINE)
+                != 0
+            {
+                SyncStatus::OnlineOnly
+            } else if attributes & FILE_ATTRIBUTE_PINNED != 0 {
+                SyncStatus::Synced
+            } else {
+                return None;
+            };
+            Some((path.to_path_buf(), status))
+        })
+        .collect()
+}
+
+/// The Linux and macOS clients expose no per-file state; only conflicts show
+#[cfg(not(windows))]
+fn onedrive_statuses(_paths: &[&Path]) -> HashMap<PathBuf, SyncStatus> {
+    HashMap::new()
+}
+
+// =============================================================================
+// State
+// =============================================================================
+
+/// Latest statuses for the current directory
+#[derive(Resource, Default)]
+pub struct SyncStatuses {
+    pub statuses: HashMap<PathBuf, SyncStatus>,
+}
+
+/// Background query bookkeeping, kept apart so polling doesn't look like a change
+#[derive(Resource, Default)]
+struct SyncPoller {
+    dir: PathBuf,
+    worker: Option<JoinHandle<(PathBuf, HashMap<PathBuf, SyncStatus>)>>,
+    since_poll: f32,
+}
+
+/// Small cube on top of an entry, colored by its sync status
+#[derive(Component)]
+struct SyncBadge;
+
+pub struct CloudSyncPlugin;
+
+impl Plugin for CloudSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SyncStatuses::default())
+            .insert_resource(SyncPoller::default())
+            .add_systems(Update, (poll_sync_statuses, update_sync_badges));
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn poll_sync_statuses(
+    time: Res<Time>,
+    current_dir: Res<CurrentDirectory>,
+    mut poller: ResMut<SyncPoller>,
+    mut sync: ResMut<SyncStatuses>,
+) {
+    if poller
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        let worker = poller.worker.take().expect("checked above");
+        if let Ok((dir, statuses)) = worker.join() {
+            // Results for a directory we already left are stale
+            if dir == current_dir.path && statuses != sync.statuses {
+                sync.statuses = statuses;
+            }
+        }
+    }
+    if poller.worker.is_some() || current_dir.needs_reload {
+        return;
+    }
+
+    let moved = poller.dir != current_dir.path;
+    if moved {
+        poller.dir = current_dir.path.clone();
+        if !sync.statuses.is_empty() {
+            sync.statuses.clear();
+        }
+    }
+    poller.since_poll += time.delta_seconds();
+    if !moved && poller.since_poll < POLL_SECONDS {
+        return;
+    }
+    poller.since_poll = 0.0;
+
+    let dir = current_dir.path.clone();
+    let entries = current_dir.entries.clone();
+    poller.worker = Some(std::thread::spawn(move || {
+        let statuses = query_statuses(&dir, &entries);
+        (dir, statuses)
+    }));
+}
+
+/// Rebuild badges when statuses change or the entries were respawned
+fn update_sync_badges(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    sync: Res<SyncStatuses>,
+    current_dir: Res<CurrentDirectory>,
+    entity_query: Query<(Entity, &FileEntity)>,
+    new_entities: Query<(), Added<FileEntity>>,
+    badge_query: Query<Entity, With<SyncBadge>>,
+) {
+    if !sync.is_changed() && new_entities.is_empty() {
+        return;
+    }
+    for badge in badge_query.iter() {
+        commands.entity(badge).despawn();
+    }
+    if sync.statuses.is_empty() {
+        return;
+    }
+
+    let mesh = palette.mesh(&mut meshes, "sync badge", || {
+        Cuboid::from_size(Vec3::splat(BADGE_SIZE)).into()
+    });
+    for (entity, file_entity) in entity_query.iter() {
+        let Some(entry) = current_dir.entries.get(file_entity.index) else {
+            continue;
+        };
+        let Some(status) = sync.statuses.get(&entry.path) else {
+            continue;
+        };
+        let material = palette.material(&mut materials, status.color().to_linear());
+        // Child of the entry, so it rises and sinks along with it; the entry's
+        // unit cuboid is stretched to its height, which the badge undoes
+        let height = entry_height(entry);
+        let badge = commands
+            .spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material,
+                    transform: Transform::from_xyz(0.3, 0.5 + BADGE_SIZE / height, 0.0)
+                        .with_scale(Vec3::new(1.0, 1.0 / height, 1.0)),
+                    ..default()
+                },
+                SyncBadge,
+            ))
+            .id();
+        commands.entity(entity).add_child(badge);
+    }
+}
@@ -0,0 +1,129 @@
+//! Color modes - `:colorby mtime` turns the scene into a recency map
+//!
+//! Normally books are colored by what they are and by their git state.
+//! `:colorby mtime` colors them by when they last changed instead: the newest
+//! entries of the directory glow bright orange and the oldest fade towards the
+//! grid. Ages are spread on a log scale between the newest and the oldest, so
+//! one ancient file doesn't wash out the difference between today and last
+//! week. `:colorby none` goes back, `:colorby` alone shows the mode.
+
+use bevy::prelude::*;
+use std::time::SystemTime;
+
+use crate::command::{Command, RunCommand};
+use crate::{CurrentDirectory, FileEntry, StatusMessage, FELIPE_GRID, FELIPE_ORANGE};
+
+/// What decides the color of a book
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Files, directories and git state
+    #[default]
+    Kind,
+    /// Time since the last modification
+    Mtime,
+}
+
+impl ColorMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Kind => "none",
+            ColorMode::Mtime => "mtime",
+        }
+    }
+}
+
+impl std::str::FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "none" | "kind" => Ok(ColorMode::Kind),
+            "mtime" => Ok(ColorMode::Mtime),
+            _ => Err(format!("Unknown color mode: {} (none, mtime)", s)),
+        }
+    }
+}
+
+/// Active color mode and what it needs to know about the listing
+#[derive(Resource, Default)]
+pub struct ColorBy {
+    pub mode: ColorMode,
+    /// Newest and oldest modification time among the entries
+    mtimes: Option<(SystemTime, SystemTime)>,
+}
+
+impl ColorBy {
+    /// Color of `entry` in the active mode, None to color it as usual
+    pub fn color(&self, entry: &FileEntry) -> Option<Color> {
+        match self.mode {
+            ColorMode::Kind => None,
+            ColorMode::Mtime => {
+                let (newest, oldest) = self.mtimes?;
+                let modified = entry.modified.filter(|_| entry.name != "..")?;
+                let span = newest.duration_since(oldest).ok()?.as_secs_f32();
+                let age = newest
+                    .duration_since(modified)
+                    .map(|age| age.as_secs_f32())
+                    .unwrap_or(0.0);
+                let staleness = if span > 0.0 {
+                    (1.0 + age).ln() / (1.0 + span).ln()
+                } else {
+                    0.0
+                };
+                Some(FELIPE_ORANGE.mix(&FELIPE_GRID, staleness.clamp(0.0, 1.0)))
+            }
+        }
+    }
+}
+
+pub struct ColorByPlugin;
+
+impl Plugin for ColorByPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColorBy>().add_systems(
+            Update,
+            (
+                handle_colorby_command,
+                update_mtime_range.after(crate::load_directory),
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_colorby_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut color_by: ResMut<ColorBy>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::ColorBy(mode) = command else {
+            continue;
+        };
+        if let Some(mode) = mode {
+            color_by.mode = *mode;
+        }
+        status.0 = format!("Color by: {}", color_by.mode.name());
+    }
+}
+
+/// Newest and oldest entry of the listing, which the heat scale runs between
+fn update_mtime_range(current_dir: Res<CurrentDirectory>, mut color_by: ResMut<ColorBy>) {
+    if !current_dir.is_changed() {
+        return;
+    }
+    let mut mtimes = current_dir
+        .entries
+        .iter()
+        .filter(|entry| entry.name != "..")
+        .filter_map(|entry| entry.modified);
+    let range = mtimes.next().map(|first| {
+        mtimes.fold((first, first), |(newest, oldest), time| {
+            (newest.max(time), oldest.min(time))
+        })
+    });
+    color_by.mtimes = range;
+}
@@ -0,0 +1,654 @@
+//! Command mode - `:` opens a vim-style command line
+//!
+//! The input system only parses; each subsystem reads `RunCommand` events
+//! and handles the commands it owns.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use crate::basket::Basket;
+use crate::checksum::HashAlgorithm;
+use crate::colorby::ColorMode;
+use crate::config::Config;
+use crate::heights::HeightScale;
+use crate::ops;
+use crate::properties::ModeChange;
+use crate::quality::Quality;
+use crate::sort::SortKey;
+use crate::tags::TagColor;
+use crate::{CurrentDirectory, FileEntry, Prompt, StatusMessage, VimMode};
+
+// =============================================================================
+// Commands
+// =============================================================================
+
+/// A parsed `:` command
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// `:q`, `:quit`
+    Quit,
+    /// `:tutor` - start (or leave) the interactive tutorial
+    Tutor,
+    /// `:oplog` - toggle the operation history panel
+    Oplog,
+    /// `:fly` - toggle first-person fly-through
+    Fly,
+    /// `:snapshot [hash]` - record the tree under the current directory
+    Snapshot { hash: bool },
+    /// `:changes` - toggle the review of changes since the snapshot
+    Changes,
+    /// `:history` - list filesystem snapshots of the current directory
+    History,
+    /// `:conflicts` - list sync-conflict copies under the current directory
+    Conflicts,
+    /// `:gitlog` - commits that changed the selected file
+    GitLog,
+    /// `:blame` - lines of the selected file with the commit behind each
+    Blame,
+    /// `:filter *.rs`, `:filter /regex/` - only list matching files until
+    /// `:filter!`; None clears it
+    Filter(Option<String>),
+    /// `:flatten` - toggle showing every file below the current directory
+    Flatten,
+    /// `:chmod 755`, `:chmod u+x,go-w` - change the mode of the selected entries
+    Chmod(ModeChange),
+    /// `:chown user:group`, `:chown user`, `:chown :group` - change the owner
+    /// of the selected entries
+    Chown {
+        user: Option<String>,
+        group: Option<String>,
+    },
+    /// `:xattr set user.tag value` - set an extended attribute on the selected
+    /// entries; `value` None for `:xattr rm user.tag`
+    Xattr { name: String, value: Option<String> },
+    /// `:ln target [name]` - hard link in the current directory; `:ln -s`
+    /// makes a symlink whose content is `target` as typed
+    Link {
+        target: PathBuf,
+        name: Option<String>,
+        symbolic: bool,
+    },
+    /// `:set name`, `:set noname`, `:set name!` - change a boolean option;
+    /// `value` is None for a toggle
+    Set { option: String, value: Option<bool> },
+    /// `:set quality low|medium|high` - rendering preset (see quality.rs);
+    /// `:set quality` alone shows the one in use
+    Quality(Option<Quality>),
+    /// `:set heightscale linear|log|sqrt|rank` - how sizes become heights
+    /// (see heights.rs); `:set heightscale` alone shows the one in use
+    HeightScale(Option<HeightScale>),
+    /// `:sort key [asc|desc]` - sort by one key, directories first;
+    /// `:sort` alone shows the active order
+    Sort(Option<SortKey>),
+    /// `:colorby mtime`, `:colorby none` - what colors the books; `:colorby`
+    /// alone shows the active mode
+    ColorBy(Option<ColorMode>),
+    /// `:whatsnew` - toggle the list of new commands and keys
+    WhatsNew,
+    /// `:config edit` - open the config file in the default editor
+    ConfigEdit,
+    /// `:config reload` - read the config file again
+    ConfigReload,
+    /// `:choose` - in picker mode, choose the selected file or the current
+    /// directory
+    Choose,
+    /// `:terminal` - open a terminal in the current directory
+    Terminal,
+    /// `:!cmd` - run a shell command; `%` and `%*` are expanded in shell.rs
+    Shell(String),
+    /// `:name` - a command from the config's `[[commands]]` (see custom.rs)
+    User(String),
+    /// `:script name` - run a Rhai script (see script.rs); `:script` alone
+    /// lists them
+    Script(Option<String>),
+    /// `:script!` - stop the script running
+    ScriptStop,
+    /// `:layout name` - arrange the entries another way (see layout.rs);
+    /// `:layout` alone lists the layouts
+    Layout(Option<String>),
+    /// `:z fragment...` - jump to the most used directory matching (see
+    /// frecency.rs)
+    Jump(Vec<String>),
+    /// `:find [text]` - fuzzy-find a file below the current directory (see
+    /// deepjump.rs)
+    Find(String),
+    /// `:pin [name]` - pin the current directory as a portal (see
+    /// bookmarks.rs)
+    Pin(Option<String>),
+    /// `:unpin name` - remove a pin made with `:pin`
+    Unpin(String),
+    /// `:vsplit [path]` - show another directory mirrored beside this one
+    /// (see split.rs)
+    VSplit(Option<PathBuf>),
+    /// `:only` - close the other side of `:vsplit`
+    Only,
+    /// `:diff other-dir` - color the differences with another tree (see
+    /// diff.rs); `:diff` alone closes the comparison
+    Diff(Option<PathBuf>),
+    /// `:diff >` / `:diff <` - copy what differs over there / over here
+    DiffSync { to_other: bool },
+    /// `:record [file.mp4|dir]` - fly around the directory and save it as a
+    /// video or PNG frames (see record.rs); `:record` again stops
+    Record(Option<PathBuf>),
+    /// `:paste-basket` - copy everything marked with Space here,
+    /// `:paste-basket!` moves it (see basket.rs)
+    PasteBasket { moving: bool },
+    /// `:basket` - what's marked, `:clearsel` / `:basket!` unmarks everything
+    Basket { clear: bool },
+    /// `:select all|invert|pattern` - mark entries of the current directory
+    /// (see basket.rs); the pattern is written like `:filter`'s
+    Select(String),
+    /// `:hash [sha256|sha512|md5]` - digests of the selected files, copied to
+    /// the clipboard (see checksum.rs)
+    Hash(HashAlgorithm),
+    /// `:verify` - check files against the checksum files of the directory
+    Verify,
+    /// `:tail` - follow the selected file in the preview pane as it grows, or
+    /// stop (see preview.rs); `:tail!` pauses and resumes
+    Tail { pause: bool },
+    /// `:sync dest` - mirror the current directory to `dest` after a preview
+    /// (see dirsync.rs); `:sync!` also deletes what only `dest` has
+    Sync { dest: PathBuf, delete: bool },
+    /// `:cd path` - go to a directory, local or remote like
+    /// `sftp://user@host/path` (see remote.rs)
+    Cd(PathBuf),
+    /// `:mtp [name]` - go to a connected phone or camera (see mtp.rs)
+    Mtp(Option<String>),
+    /// `:volumes` - toggle the list of mounted volumes (see volumes.rs)
+    Volumes,
+    /// `:starred` - every starred entry in one view (see stars.rs)
+    Starred,
+    /// `:note text` - pin a note to the selected entry, `:note!` takes it off
+    /// (see notes.rs)
+    Note(Option<String>),
+    /// `:tag color` - tag the selection, `:tag! color` takes the tag off and
+    /// `:tag!` all tags (see tags.rs)
+    Tag { color: Option<TagColor>, on: bool },
+    /// `:savesearch name query...` - keep a query over the current directory
+    /// as a directory of its own (see searches.rs); `query` is None for
+    /// `:savesearch! name`, which forgets it
+    SaveSearch { name: String, query: Option<String> },
+    /// `:searches` - the saved searches, as directories
+    Searches,
+    /// `:stats` - toggle counts and sizes for the current directory (see
+    /// stats.rs)
+    Stats,
+    /// `:dashboard` - toggle 3D charts of the tree in place of the grid (see
+    /// dashboard.rs)
+    Dashboard,
+    /// `:tree` - show or hide the directory tree on the left (see tree.rs)
+    Tree,
+    /// `:debug` - toggle frame rate, entity and asset counts, timings and the
+    /// job queue in a corner (see debug.rs)
+    Debug,
+}
+
+/// Fired when the user submits a valid command line
+#[derive(Event, Clone, Debug)]
+pub struct RunCommand(pub Command);
+
+/// Parse a command line (without the leading `:`)
+pub fn parse_command(input: &str) -> Result<Command, String> {
+    // `:!cmd` needs no space after the `!`, and keeps the rest as typed
+    if let Some(line) = input.trim_start().strip_prefix('!') {
+        let line = line.trim();
+        if line.is_empty() {
+            return Err("Usage: :!command (% is the selection, %* the visual range)".to_string());
+        }
+        return Ok(Command::Shell(line.to_string()));
+    }
+    let mut words = input.split_whitespace();
+    let Some(name) = words.next() else {
+        return Err(String::new());
+    };
+
+    match name {
+        "q" | "quit" => Ok(Command::Quit),
+        "tutor" => Ok(Command::Tutor),
+        "oplog" => Ok(Command::Oplog),
+        "fly" => Ok(Command::Fly),
+        "snapshot" => match words.next() {
+            None => Ok(Command::Snapshot { hash: false }),
+            Some("hash") => Ok(Command::Snapshot { hash: true }),
+            Some(arg) => Err(format!("Usage: :snapshot [hash] (got {})", arg)),
+        },
+        "changes" => Ok(Command::Changes),
+        "history" => Ok(Command::History),
+        "conflicts" => Ok(Command::Conflicts),
+        "volumes" => Ok(Command::Volumes),
+        "starred" => Ok(Command::Starred),
+        "searches" => Ok(Command::Searches),
+        "stats" => Ok(Command::Stats),
+        "dashboard" => Ok(Command::Dashboard),
+        "tree" => Ok(Command::Tree),
+        "debug" => Ok(Command::Debug),
+        "gitlog" => Ok(Command::GitLog),
+        "blame" => Ok(Command::Blame),
+        "flatten" => Ok(Command::Flatten),
+        "chmod" => match (words.next(), words.next()) {
+            (Some(mode), None) => mode.parse().map(Command::Chmod),
+            _ => Err("Usage: :chmod 755|u+x,go-w".to_string()),
+        },
+        "chown" => {
+            let usage = || "Usage: :chown user[:group] or :chown :group".to_string();
+            let (Some(spec), None) = (words.next(), words.next()) else {
+                return Err(usage());
+            };
+            let (user, group) = spec.split_once(':').unwrap_or((spec, ""));
+            let name = |text: &str| (!text.is_empty()).then(|| text.to_string());
+            if user.is_empty() && group.is_empty() {
+                return Err(usage());
+            }
+            Ok(Command::Chown {
+                user: name(user),
+                group: name(group),
+            })
+        }
+        "xattr" => {
+            let usage = || "Usage: :xattr set name value or :xattr rm name".to_string();
+            let (Some(action), Some(name)) = (words.next(), words.next()) else {
+                return Err(usage());
+            };
+            let rest: Vec<&str> = words.collect();
+            let value = match (action, rest.is_empty()) {
+                ("set", false) => Some(rest.join(" ")),
+                ("rm", true) => None,
+                _ => return Err(usage()),
+            };
+            Ok(Command::Xattr {
+                name: name.to_string(),
+                value,
+            })
+        }
+        "ln" => {
+            let mut args: Vec<&str> = words.collect();
+            let symbolic = args.first() == Some(&"-s");
+            if symbolic {
+                args.remove(0);
+            }
+            match args.as_slice() {
+                [target] => Ok(Command::Link {
+                    target: PathBuf::from(target),
+                    name: None,
+                    symbolic,
+                }),
+                [target, name] => Ok(Command::Link {
+                    target: PathBuf::from(target),
+                    name: Some(name.to_string()),
+                    symbolic,
+                }),
+                _ => Err("Usage: :ln [-s] target [name]".to_string()),
+            }
+        }
+        "filter!" => Ok(Command::Filter(None)),
+        "filter" => {
+            // The pattern is the rest of the line, spaces included
+            let pattern = input.trim_start()[name.len()..].trim();
+            if pattern.is_empty() {
+                return Err("Usage: :filter glob|/regex/|tag:color (:filter! clears)".to_string());
+            }
+            Ok(Command::Filter(Some(pattern.to_string())))
+        }
+        "note!" => Ok(Command::Note(None)),
+        "note" => {
+            // The note is the rest of the line, spaces included
+            let text = input.trim_start()[name.len()..].trim();
+            if text.is_empty() {
+                return Err("Usage: :note text (:note! removes it)".to_string());
+            }
+            Ok(Command::Note(Some(text.to_string())))
+        }
+        "tag" | "tag!" => match (words.next(), words.next()) {
+            (Some(color), None) => color.parse().map(|color| Command::Tag {
+                color: Some(color),
+                on: name == "tag",
+            }),
+            (None, None) if name == "tag!" => Ok(Command::Tag {
+                color: None,
+                on: false,
+            }),
+            _ => Err("Usage: :tag color (:tag! color untags, :tag! clears)".to_string()),
+        },
+        "savesearch!" => match (words.next(), words.next()) {
+            (Some(name), None) => Ok(Command::SaveSearch {
+                name: name.to_string(),
+                query: None,
+            }),
+            _ => Err("Usage: :savesearch! name".to_string()),
+        },
+        "savesearch" => {
+            let usage = || "Usage: :savesearch name query (:savesearch! name forgets it)";
+            let name = words.next().ok_or_else(usage)?;
+            let query = words.collect::<Vec<_>>().join(" ");
+            if query.is_empty() || name.contains(['/', '\\']) {
+                return Err(usage().to_string());
+            }
+            Ok(Command::SaveSearch {
+                name: name.to_string(),
+                query: Some(query),
+            })
+        }
+        "whatsnew" => Ok(Command::WhatsNew),
+        "choose" => Ok(Command::Choose),
+        "terminal" | "term" => Ok(Command::Terminal),
+        "script!" => Ok(Command::ScriptStop),
+        "script" => match (words.next(), words.next()) {
+            (name, None) => Ok(Command::Script(name.map(str::to_string))),
+            _ => Err("Usage: :script [name]".to_string()),
+        },
+        "layout" => match (words.next(), words.next()) {
+            (name, None) => Ok(Command::Layout(name.map(str::to_string))),
+            _ => Err("Usage: :layout [name]".to_string()),
+        },
+        "z" => {
+            let fragments: Vec<String> = words.map(str::to_string).collect();
+            if fragments.is_empty() {
+                return Err("Usage: :z fragment...".to_string());
+            }
+            Ok(Command::Jump(fragments))
+        }
+        "pin" | "unpin" => {
+            // Names may have spaces
+            let pin = input.trim_start()[name.len()..].trim();
+            match (name, pin.is_empty()) {
+                ("pin", true) => Ok(Command::Pin(None)),
+                ("pin", false) => Ok(Command::Pin(Some(pin.to_string()))),
+                (_, true) => Err("Usage: :unpin name".to_string()),
+                (_, false) => Ok(Command::Unpin(pin.to_string())),
+            }
+        }
+        "vsplit" | "vs" => {
+            // The path is the rest of the line, spaces included
+            let path = input.trim_start()[name.len()..].trim();
+            Ok(Command::VSplit(
+                (!path.is_empty()).then(|| PathBuf::from(path)),
+            ))
+        }
+        "only" | "on" => Ok(Command::Only),
+        "diff" => match input.trim_start()[name.len()..].trim() {
+            "" => Ok(Command::Diff(None)),
+            ">" => Ok(Command::DiffSync { to_other: true }),
+            "<" => Ok(Command::DiffSync { to_other: false }),
+            path => Ok(Command::Diff(Some(PathBuf::from(path)))),
+        },
+        "record" | "rec" => {
+            let path = input.trim_start()[name.len()..].trim();
+            Ok(Command::Record(
+                (!path.is_empty()).then(|| PathBuf::from(path)),
+            ))
+        }
+        "paste-basket" | "paste-basket!" => Ok(Command::PasteBasket {
+            moving: name == "paste-basket!",
+        }),
+        "basket" => Ok(Command::Basket { clear: false }),
+        "select" | "sel" => {
+            // A pattern may have spaces
+            let what = input.trim_start()[name.len()..].trim();
+            if what.is_empty() {
+                return Err("Usage: :select all|invert|glob|/regex/|tag:color".to_string());
+            }
+            Ok(Command::Select(what.to_string()))
+        }
+        "basket!" | "clearsel" => Ok(Command::Basket { clear: true }),
+        "hash" => match (words.next(), words.next()) {
+            (None, _) => Ok(Command::Hash(HashAlgorithm::default())),
+            (Some(algorithm), None) => algorithm.parse().map(Command::Hash),
+            _ => Err("Usage: :hash [sha256|sha512|md5]".to_string()),
+        },
+        "verify" => Ok(Command::Verify),
+        "tail" => Ok(Command::Tail { pause: false }),
+        "tail!" => Ok(Command::Tail { pause: true }),
+        "sync" | "sync!" => match input.trim_start()[name.len()..].trim() {
+            "" => Err("Usage: :sync dest (:sync! also deletes extraneous entries)".to_string()),
+            dest => Ok(Command::Sync {
+                dest: PathBuf::from(dest),
+                delete: name == "sync!",
+            }),
+        },
+        "cd" => match input.trim_start()[name.len()..].trim() {
+            "" => Ok(Command::Cd(PathBuf::from("~"))),
+            path => Ok(Command::Cd(PathBuf::from(path))),
+        },
+        "mtp" => match input.trim_start()[name.len()..].trim() {
+            "" => Ok(Command::Mtp(None)),
+            device => Ok(Command::Mtp(Some(device.to_string()))),
+        },
+        "find" => Ok(Command::Find(words.collect::<Vec<_>>().join(" "))),
+        "colorby" => match (words.next(), words.next()) {
+            (None, _) => Ok(Command::ColorBy(None)),
+            (Some(mode), None) => mode.parse().map(|mode| Command::ColorBy(Some(mode))),
+            _ => Err("Usage: :colorby mtime|none".to_string()),
+        },
+        "sort" => {
+            let Some(key) = words.next() else {
+                return Ok(Command::Sort(None));
+            };
+            let key = match words.next() {
+                None | Some("asc") => key.to_string(),
+                Some("desc") => format!("-{}", key),
+                Some(arg) => return Err(format!("Usage: :sort key [asc|desc] (got {})", arg)),
+            };
+            key.parse()
+                .map(|key| Command::Sort(Some(key)))
+                .map_err(|_| format!("Unknown sort key: {}", key.trim_start_matches('-')))
+        }
+        "config" => match words.next() {
+            Some("edit") => Ok(Command::ConfigEdit),
+            Some("reload") => Ok(Command::ConfigReload),
+            _ => Err("Usage: :config edit|reload".to_string()),
+        },
+        "set" | "se" => {
+            let Some(arg) = words.next() else {
+                return Err("Usage: :set [no]option[!]".to_string());
+            };
+            // The options with a value, `:set quality low` or `quality=low`
+            if arg == "quality" || arg.starts_with("quality=") {
+                let value = arg.strip_prefix("quality=").or_else(|| words.next());
+                return match value {
+                    Some(value) => value.parse().map(|quality| Command::Quality(Some(quality))),
+                    None => Ok(Command::Quality(None)),
+                };
+            }
+            if arg == "heightscale" || arg.starts_with("heightscale=") {
+                let value = arg.strip_prefix("heightscale=").or_else(|| words.next());
+                return match value {
+                    Some(value) => value.parse().map(|scale| Command::HeightScale(Some(scale))),
+                    None => Ok(Command::HeightScale(None)),
+                };
+            }
+            let (option, value) = if let Some(option) = arg.strip_suffix('!') {
+                (option, None)
+            } else if let Some(option) = arg.strip_prefix("inv") {
+                (option, None)
+            } else if let Some(option) = arg.strip_prefix("no") {
+                (option, Some(false))
+            } else {
+                (arg, Some(true))
+            };
+            Ok(Command::Set {
+                option: option.to_string(),
+                value,
+            })
+        }
+        // Config commands are looked up when run, as the config can change
+        _ if words.next().is_none() => Ok(Command::User(name.to_string())),
+        _ => Err(format!("Not an editor command: {}", name)),
+    }
+}
+
+// =============================================================================
+// Command Line State
+// =============================================================================
+
+/// Text typed after `:`
+#[derive(Resource, Default)]
+pub struct CommandLine {
+    pub input: String,
+    /// Visual range the command line was opened from (`:'<,'>` in vim)
+    pub range: Option<RangeInclusive<usize>>,
+    /// Entries marked with Space when it was opened (see basket.rs)
+    pub marked: Vec<PathBuf>,
+}
+
+impl CommandLine {
+    /// Entries a command acts on: the visual range it was typed over, else the
+    /// marked entries, else the selected entry; `..` is never a target
+    pub fn targets<'a>(&self, current_dir: &'a CurrentDirectory) -> Vec<&'a FileEntry> {
+        if self.range.is_none() && !self.marked.is_empty() {
+            return current_dir
+                .entries
+                .iter()
+                .filter(|entry| self.marked.contains(&entry.path))
+                .collect();
+        }
+        let range = self
+            .range
+            .clone()
+            .unwrap_or(current_dir.selected_index..=current_dir.selected_index);
+        // A range that runs past the end of the listing picks nothing
+        current_dir
+            .entries
+            .get(range)
+            .unwrap_or_default()
+            .iter()
+            .filter(|entry| entry.name != "..")
+            .collect()
+    }
+}
+
+pub struct CommandPlugin;
+
+impl Plugin for CommandPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CommandLine::default())
+            .add_event::<RunCommand>()
+            .add_systems(
+                Update,
+                (
+                    handle_command_line.after(crate::handle_keyboard),
+                    run_builtin_commands,
+                ),
+            )
+            .add_systems(Last, forget_command_targets);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_command_line(
+    mut key_events: EventReader<KeyboardInput>,
+    mut vim_mode: ResMut<VimMode>,
+    mut command_line: ResMut<CommandLine>,
+    mut run_command: EventWriter<RunCommand>,
+    mut status: ResMut<StatusMessage>,
+    prompt: Res<Prompt>,
+    current_dir: Res<CurrentDirectory>,
+    basket: Res<Basket>,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed || prompt.pending.is_some() {
+            continue;
+        }
+
+        if matches!(*vim_mode, VimMode::Normal | VimMode::Visual) {
+            if matches!(&event.logical_key, Key::Character(c) if c == ":") {
+                command_line.range =
+                    (*vim_mode == VimMode::Visual).then(|| current_dir.visual_range());
+                command_line.marked = basket.marked_here(&current_dir);
+                *vim_mode = VimMode::Command;
+                command_line.input.clear();
+            }
+            continue;
+        }
+        if *vim_mode != VimMode::Command {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(c) => command_line.input.push_str(c),
+            Key::Space => command_line.input.push(' '),
+            Key::Backspace => {
+                // Backspace on an empty line leaves command mode, like vim
+                let was_empty = command_line.input.pop().is_none();
+                if was_empty {
+                    *vim_mode = VimMode::Normal;
+                }
+            }
+            Key::Escape => {
+                command_line.input.clear();
+                *vim_mode = VimMode::Normal;
+            }
+            Key::Enter => {
+                let input = std::mem::take(&mut command_line.input);
+                *vim_mode = VimMode::Normal;
+                match parse_command(&input) {
+                    Ok(command) => {
+                        run_command.send(RunCommand(command));
+                    }
+                    Err(message) => status.0 = message,
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Drop the range and marks a command was typed over once it has run, so a
+/// command sent later by a hook, a prompt or an embedding app acts on the
+/// selected entry rather than on a stale selection. Readers that run before
+/// the sender see the event a frame late, hence the one-frame wait.
+fn forget_command_targets(
+    mut run_commands: EventReader<RunCommand>,
+    mut dispatched: Local<bool>,
+    vim_mode: Res<VimMode>,
+    mut command_line: ResMut<CommandLine>,
+) {
+    // A command line opened since has taken fresh targets of its own
+    if *dispatched && *vim_mode != VimMode::Command {
+        command_line.range = None;
+        command_line.marked.clear();
+    }
+    *dispatched = run_commands.read().count() > 0;
+}
+
+fn run_builtin_commands(
+    mut commands: EventReader<RunCommand>,
+    mut exit: EventWriter<AppExit>,
+    mut config: ResMut<Config>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in commands.read() {
+        match command {
+            Command::Quit => {
+                exit.send(AppExit::Success);
+            }
+            Command::Set { option, value } if option == "readonly" => {
+                let read_only = value.unwrap_or(!ops::is_read_only());
+                status.0 = match ops::set_read_only(read_only) {
+                    Ok(()) if read_only => "readonly".to_string(),
+                    Ok(()) => "noreadonly".to_string(),
+                    Err(message) => message,
+                };
+            }
+            Command::Set { option, value } => {
+                let Some(current) = config.option_mut(option) else {
+                    status.0 = format!("Unknown option: {}", option);
+                    continue;
+                };
+                *current = value.unwrap_or(!*current);
+                // Echo the new state like vim does
+                status.0 = if *current {
+                    option.clone()
+                } else {
+                    format!("no{}", option)
+                };
+            }
+            _ => {}
+        }
+    }
+}
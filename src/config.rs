@@ -0,0 +1,425 @@
+//! User settings from `config.toml` in Felipe's config directory
+//!
+//! ```toml
+//! [render]
+//! bloom = true
+//! bloom_intensity = 0.3
+//! wireframe = false         # also `:set wireframe` at runtime
+//! crt = false               # scanline overlay, also `:set crt`
+//! number = false            # entry numbers, also `:set number` / `relativenumber`
+//! ui_scale = 1.5            # text and panels, from the screen if unset; Ctrl-= / Ctrl--
+//! accessible = false        # no motion, more contrast, also `:set accessible`
+//! idle_fps = 10             # frame rate once still, 0 for full rate (see power.rs)
+//! max_fps = 0               # cap, 0 for the display's rate
+//! quality = "high"          # low / medium for weak GPUs, also `:set quality`, `--gpu-low`
+//!
+//! [window]                  # see windowstate.rs
+//! fullscreen = false        # also F11 / `:set fullscreen`
+//! remember = true           # reopen with the last session's size and place
+//! transparent = false       # the desktop through the background, at `opacity`
+//! ontop = false             # above other windows, also `:set ontop`
+//!
+//! [operations]
+//! dry_run = false           # review pastes and renames first, also `:set dryrun`
+//! open_marked = "code %*"   # Enter on marked files runs this once (see basket.rs)
+//! autorename = false        # number clashing names instead of asking, also `:set autorename`
+//! rename_pattern = "{name}_{n}{ext}"   # how they're numbered, `{name} ({n}){ext}` by default
+//!
+//! [listing]
+//! hidden = false            # dotfiles, also `.` / `zh` / `:set hidden`
+//! gitignore = false         # hide what git ignores, also `zi` / `:set gitignore`
+//!
+//! [format]                  # see format.rs
+//! size_units = "si"
+//!
+//! [font]                    # see font.rs
+//! paths = ["~/fonts/PlemolJP-Regular.ttf"]
+//!
+//! [locale]                  # see locale.rs
+//! language = "ja"
+//!
+//! [sort]                    # see sort.rs
+//! default = "name"
+//!
+//! [heights]                 # see heights.rs
+//! scale = "sqrt"            # linear / log / sqrt / rank, also `:set heightscale`
+//!
+//! [shapes]                  # see shapes.rs
+//! py = "pyramid"
+//!
+//! [sound]                   # see sound.rs
+//! volume = 0.3
+//!
+//! [preview]                 # see preview.rs
+//! enabled = false
+//!
+//! [terminal]                # see terminal.rs
+//! command = "kitty"
+//!
+//! [index]                   # see index.rs
+//! roots = ["~"]
+//!
+//! [statusline]              # see statusline.rs
+//! segments = ["mode", "position", "sort", "free"]
+//!
+//! [bookmarks]               # see bookmarks.rs
+//! pins = [{ name = "projects", path = "~/src" }]
+//!
+//! [[commands]]              # see custom.rs
+//! name = "optimize-png"
+//! run = "oxipng %*"
+//!
+//! [[hooks]]                 # see hooks.rs
+//! on = "open"
+//! run = "echo % >> ~/.felipe-opened"
+//! ```
+//!
+//! Every key is optional; missing ones keep their defaults. `felipe --config
+//! file` reads another file instead (see cli.rs).
+//!
+//! A config that fails to parse doesn't stop Felipe: it starts in safe mode
+//! with the last config that did parse (or the defaults), and a banner shows
+//! the error until `:config edit` and `:config reload` fix it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Deserializer};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::bookmarks::BookmarksConfig;
+use crate::command::{Command, RunCommand};
+use crate::custom::CustomCommand;
+use crate::font::FontConfig;
+use crate::format::FormatConfig;
+use crate::heights::HeightsConfig;
+use crate::hooks::Hook;
+use crate::locale::{tr, trf, LocaleConfig};
+use crate::ops;
+use crate::preview::PreviewConfig;
+use crate::quality::Quality;
+use crate::shapes::ShapesConfig;
+use crate::sort::SortConfig;
+use crate::sound::SoundConfig;
+use crate::statusline::StatusLineConfig;
+use crate::terminal::TerminalConfig;
+use crate::windowstate::WindowConfig;
+use crate::{data_dir, StatusMessage, UiElement, DIFF_REMOVED};
+
+#[derive(Resource, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub render: RenderConfig,
+    pub window: WindowConfig,
+    pub listing: ListingConfig,
+    pub operations: OperationsConfig,
+    pub format: FormatConfig,
+    pub font: FontConfig,
+    pub locale: LocaleConfig,
+    pub sort: SortConfig,
+    pub heights: HeightsConfig,
+    pub shapes: ShapesConfig,
+    pub sound: SoundConfig,
+    pub preview: PreviewConfig,
+    pub terminal: TerminalConfig,
+    pub statusline: StatusLineConfig,
+    pub bookmarks: BookmarksConfig,
+    pub index: IndexConfig,
+    pub commands: Vec<CustomCommand>,
+    pub hooks: Vec<Hook>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    /// Let emissive entries glow (HDR + bloom)
+    pub bloom: bool,
+    /// Strength of the glow, 0.0-1.0
+    pub bloom_intensity: f32,
+    /// Draw entries as edge outlines instead of solid books
+    pub wireframe: bool,
+    /// Scanlines, color fringes and a vignette over the view
+    pub crt: bool,
+    /// The directory from above in a corner (see minimap.rs)
+    pub minimap: bool,
+    /// Entry numbers under the names (see numbers.rs)
+    pub number: bool,
+    /// ...counted from the selection
+    pub relativenumber: bool,
+    /// Size of text and panels; from the screen when unset (see uiscale.rs)
+    pub ui_scale: Option<f32>,
+    /// No camera easing, pulsing or screen effects; brighter, bigger text
+    /// (see accessibility.rs)
+    pub accessible: bool,
+    /// Frames a second while nothing moves, 0 for full rate (see power.rs)
+    pub idle_fps: u32,
+    /// Cap on the full rate, 0 for none
+    pub max_fps: u32,
+    /// Lighter rendering for weak GPUs (see quality.rs)
+    pub quality: Quality,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            bloom: true,
+            bloom_intensity: 0.3,
+            wireframe: false,
+            crt: false,
+            minimap: true,
+            number: false,
+            relativenumber: false,
+            ui_scale: None,
+            accessible: false,
+            idle_fps: 10,
+            max_fps: 0,
+            quality: Quality::High,
+        }
+    }
+}
+
+/// Which entries `load_directory` lists
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListingConfig {
+    /// Show dotfiles
+    pub hidden: bool,
+    /// Hide entries git ignores (see gitignore.rs)
+    pub gitignore: bool,
+}
+
+/// How file operations run
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct OperationsConfig {
+    /// Plan operations for review instead of running them (see dryrun.rs)
+    pub dry_run: bool,
+    /// Command Enter runs with the marked files as `%*`, instead of opening
+    /// each in its default application
+    pub open_marked: Option<String>,
+    /// Pastes give clashing entries a free name instead of asking (see
+    /// overwrite.rs)
+    pub autorename: bool,
+    /// How a free name is made, `{name}`, `{n}` and `{ext}` filled in; empty
+    /// for `ops::DEFAULT_RENAME_PATTERN`
+    #[serde(deserialize_with = "rename_pattern")]
+    pub rename_pattern: String,
+}
+
+/// A rename pattern that makes names, with a number in them
+fn rename_pattern<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    ops::check_rename_pattern(&pattern).map_err(serde::de::Error::custom)?;
+    Ok(pattern)
+}
+
+/// What the search index covers (see index.rs, feature `index`)
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexConfig {
+    /// Directories indexed with everything below them; none turns it off
+    #[cfg_attr(not(feature = "index"), allow(dead_code))]
+    pub roots: Vec<PathBuf>,
+}
+
+impl Config {
+    /// Boolean option by its `:set` name
+    pub fn option_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "wireframe" => Some(&mut self.render.wireframe),
+            "crt" => Some(&mut self.render.crt),
+            "minimap" => Some(&mut self.render.minimap),
+            "number" => Some(&mut self.render.number),
+            "relativenumber" => Some(&mut self.render.relativenumber),
+            "accessible" => Some(&mut self.render.accessible),
+            "fullscreen" => Some(&mut self.window.fullscreen),
+            "ontop" => Some(&mut self.window.ontop),
+            "sound" => Some(&mut self.sound.enabled),
+            "preview" => Some(&mut self.preview.enabled),
+            "hidden" => Some(&mut self.listing.hidden),
+            "gitignore" => Some(&mut self.listing.gitignore),
+            "dryrun" => Some(&mut self.operations.dry_run),
+            "autorename" => Some(&mut self.operations.autorename),
+            _ => None,
+        }
+    }
+}
+
+/// File given with `--config`, used instead of the usual one
+static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Read the config from `path` from now on, `:config edit` and `reload` too
+pub fn use_config_file(path: PathBuf) {
+    let _ = CONFIG_FILE.set(path);
+}
+
+/// `~/.config/felipe/config.toml` (or the platform's equivalent)
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = CONFIG_FILE.get() {
+        return Some(path.clone());
+    }
+    Some(dirs::config_dir()?.join("felipe").join("config.toml"))
+}
+
+/// Copy of the last config that parsed, kept for safe mode
+fn last_good_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("config.last-good.toml"))
+}
+
+/// Read the config file; a missing file is not an error unless it was named
+/// with `--config`
+pub fn load() -> Result<Config, String> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && CONFIG_FILE.get().is_none() => {
+            return Ok(Config::default())
+        }
+        Err(err) => return Err(format!("{}: {}", path.display(), err)),
+    };
+    let config = toml::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+    if let Some(last_good) = last_good_path() {
+        let saved = last_good
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&last_good, &text));
+        if let Err(err) = saved {
+            warn!("Could not save {}: {}", last_good.display(), err);
+        }
+    }
+    Ok(config)
+}
+
+/// The config to run with, falling back to the last one that worked
+pub fn load_or_safe_mode() -> (Config, SafeMode) {
+    let error = match load() {
+        Ok(config) => return (config, SafeMode::default()),
+        Err(error) => error,
+    };
+    let last_good = last_good_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| toml::from_str(&text).ok());
+    let using_last_good = last_good.is_some();
+    (
+        last_good.unwrap_or_default(),
+        SafeMode {
+            error: Some(error),
+            using_last_good,
+        },
+    )
+}
+
+// =============================================================================
+// Safe Mode
+// =============================================================================
+
+/// Why the config file isn't in use, if it isn't
+#[derive(Resource, Default)]
+pub struct SafeMode {
+    pub error: Option<String>,
+    using_last_good: bool,
+}
+
+/// Marker for the safe-mode banner
+#[derive(Component)]
+struct SafeModeBanner;
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SafeMode>()
+            .add_systems(Update, (handle_config_command, update_safe_mode_banner));
+    }
+}
+
+fn handle_config_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut config: ResMut<Config>,
+    mut safe_mode: ResMut<SafeMode>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        match command {
+            Command::ConfigEdit => {
+                let Some(path) = config_path() else {
+                    status.0 = tr("No config directory on this system").to_string();
+                    continue;
+                };
+                if !path.exists() {
+                    let created = path
+                        .parent()
+                        .map_or(Ok(()), std::fs::create_dir_all)
+                        .and_then(|_| std::fs::write(&path, "# Felipe config\n"));
+                    if let Err(err) = created {
+                        status.0 = trf("Could not create {}: {}", &[&path.display(), &err]);
+                        continue;
+                    }
+                }
+                crate::open_with_default_app(&path);
+                status.0 = trf("Editing {} - :config reload when done", &[&path.display()]);
+            }
+            Command::ConfigReload => match load() {
+                Ok(loaded) => {
+                    *config = loaded;
+                    *safe_mode = SafeMode::default();
+                    status.0 = tr("Config reloaded (render settings apply on restart)").to_string();
+                }
+                Err(error) => {
+                    status.0 = tr("Config still has errors").to_string();
+                    safe_mode.error = Some(error);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+fn update_safe_mode_banner(
+    mut commands: Commands,
+    safe_mode: Res<SafeMode>,
+    banner_query: Query<Entity, With<SafeModeBanner>>,
+) {
+    if !safe_mode.is_changed() {
+        return;
+    }
+    for entity in banner_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(error) = &safe_mode.error else {
+        return;
+    };
+    let fallback = if safe_mode.using_last_good {
+        tr("the last working config")
+    } else {
+        tr("default settings")
+    };
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                trf(
+                    "SAFE MODE - config not loaded, using {}\n{}\n:config edit to fix it, :config reload to retry",
+                    &[&fallback, error],
+                ),
+                TextStyle {
+                    font_size: 16.0,
+                    color: DIFF_REMOVED,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(50.0),
+                left: Val::Px(10.0),
+                max_width: Val::Px(700.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+            ..default()
+        },
+        SafeModeBanner,
+        UiElement,
+    ));
+}
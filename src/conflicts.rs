@@ -0,0 +1,623 @@
+//! `:conflicts` - find sync-conflict copies in the current tree and resolve them
+//!
+//! Dropbox/Nextcloud (`name (host's conflicted copy 2024-01-02).ext`) and
+//! Syncthing (`name.sync-conflict-20240102-150405-ABCDEFG.ext`) copies are
+//! paired with their originals.
+//! Enter compares a pair - sizes, age and a line diff for text - and
+//! `o`/`c` keep one side; the loser goes to the trash, so `u` brings it back.
+
+use bevy::prelude::*;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::dryrun::DryRunReview;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::oplog::OperationLog;
+use crate::ops::{self, UndoStep};
+use crate::{
+    CurrentDirectory, StatusMessage, UiElement, VimMode, DIFF_ADDED, DIFF_REMOVED, FELIPE_ORANGE,
+    FELIPE_ORANGE_DIM,
+};
+
+/// Walks stop here so `:conflicts` in `/` can't run forever
+const MAX_SCAN_ENTRIES: usize = 100_000;
+/// Text files larger than this aren't diffed
+const MAX_DIFF_BYTES: u64 = 512 * 1024;
+/// Line diffs give up beyond this many lines per side
+const MAX_DIFF_LINES: usize = 2_000;
+/// Changed lines shown in the compare view
+const DIFF_ROWS: usize = 24;
+
+// =============================================================================
+// Detection
+// =============================================================================
+
+/// Syncthing: `name.sync-conflict-<yyyymmdd>-<hhmmss>-<device>.ext`
+static SYNCTHING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+)\.sync-conflict-\d{8}-\d{6}-[0-9A-Z]{7}(\.[^.]+)?$").unwrap()
+});
+/// Dropbox: `name (<host>'s conflicted copy <yyyy-mm-dd>).ext`, numbered
+/// `(1)` when there are more; Nextcloud and ownCloud add the time
+static CONFLICTED_COPY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(.+) \((?:[^()]+'s )?conflicted copy \d{4}-\d{2}-\d{2}(?: \d{6})?(?: \(\d+\))?\)(.*)$",
+    )
+    .unwrap()
+});
+
+/// The name a conflicted copy stands in for, e.g.
+/// `report (conflicted copy 2024-01-02).txt` -> `report.txt`; only the
+/// patterns the sync tools write count, not any name mentioning a conflict
+pub fn conflict_original(name: &str) -> Option<String> {
+    let captures = SYNCTHING
+        .captures(name)
+        .or_else(|| CONFLICTED_COPY.captures(name))?;
+    let part = |i| captures.get(i).map_or("", |part| part.as_str());
+    Some(format!("{}{}", part(1), part(2)))
+}
+
+struct ConflictPair {
+    copy: PathBuf,
+    /// May be missing if the original was deleted or renamed since
+    original: PathBuf,
+}
+
+fn scan_conflicts(root: &Path) -> Vec<ConflictPair> {
+    let mut pairs = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    let mut seen = 0;
+
+    while let Some(dir) = stack.pop() {
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            seen += 1;
+            if seen > MAX_SCAN_ENTRIES {
+                return pairs;
+            }
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                stack.push(path.clone());
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(original) = conflict_original(&name) {
+                pairs.push(ConflictPair {
+                    original: dir.join(original),
+                    copy: path,
+                });
+            }
+        }
+    }
+    pairs.sort_by(|a, b| a.copy.cmp(&b.copy));
+    pairs
+}
+
+// =============================================================================
+// Compare
+// =============================================================================
+
+struct Side {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Side {
+    fn of(path: &Path) -> Option<Side> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Side {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+struct Comparison {
+    original: Option<Side>,
+    copy: Option<Side>,
+    /// Changed lines (`-` only in the original, `+` only in the copy), or why there are none
+    diff: Result<Vec<(char, String)>, String>,
+}
+
+fn compare(pair: &ConflictPair) -> Comparison {
+    let original = Side::of(&pair.original);
+    let copy = Side::of(&pair.copy);
+    let diff = match (&original, &copy) {
+        (Some(a), Some(b)) if a.size > MAX_DIFF_BYTES || b.size > MAX_DIFF_BYTES => {
+            Err("too large to diff".to_string())
+        }
+        (Some(_), Some(_)) => match (read_text(&pair.original), read_text(&pair.copy)) {
+            (Some(old), Some(new)) => diff_lines(&old, &new),
+            _ => Err("binary or unreadable".to_string()),
+        },
+        _ => Err("nothing to compare against".to_string()),
+    };
+    Comparison {
+        original,
+        copy,
+        diff,
+    }
+}
+
+fn read_text(path: &Path) -> Option<String> {
+    String::from_utf8(std::fs::read(path).ok()?).ok()
+}
+
+/// Lines removed from `old` and added in `new`, via a longest-common-subsequence table
+fn diff_lines(old: &str, new: &str) -> Result<Vec<(char, String)>, String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    if old.len() > MAX_DIFF_LINES || new.len() > MAX_DIFF_LINES {
+        return Err("too many lines to diff".to_string());
+    }
+
+    let width = new.len() + 1;
+    let mut lcs = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len()
+            && (i == old.len() || lcs[i * width + j + 1] >= lcs[(i + 1) * width + j])
+        {
+            changes.push(('+', new[j].to_string()));
+            j += 1;
+        } else {
+            changes.push(('-', old[i].to_string()));
+            i += 1;
+        }
+    }
+    Ok(changes)
+}
+
+// =============================================================================
+// View
+// =============================================================================
+
+/// `:conflicts` panel state
+#[derive(Resource, Default)]
+pub struct ConflictsView {
+    pairs: Vec<ConflictPair>,
+    cursor: usize,
+    /// Compare view for the pair under the cursor
+    comparison: Option<Comparison>,
+    scan: Option<JoinHandle<Vec<ConflictPair>>>,
+}
+
+/// Marker for the conflicts panel
+#[derive(Component)]
+struct ConflictsPanel;
+
+/// Marker for the conflicts panel text
+#[derive(Component)]
+struct ConflictsText;
+
+pub struct ConflictsPlugin;
+
+impl Plugin for ConflictsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ConflictsView::default()).add_systems(
+            Update,
+            (
+                handle_conflicts_command,
+                finish_conflict_scan,
+                handle_conflicts_keys,
+                update_conflicts_panel,
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_conflicts_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut view: ResMut<ConflictsView>,
+    current_dir: Res<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<ConflictsPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Conflicts {
+            continue;
+        }
+        if focus.is_open(Panel::Conflicts) {
+            close_panel(&mut commands, &mut view, &mut focus, &panel_query);
+            continue;
+        }
+        if view.scan.is_some() {
+            continue;
+        }
+        let root = current_dir.path.clone();
+        status.0 = format!("Looking for sync conflicts under {}...", root.display());
+        view.scan = Some(std::thread::spawn(move || scan_conflicts(&root)));
+    }
+}
+
+fn finish_conflict_scan(
+    mut commands: Commands,
+    mut view: ResMut<ConflictsView>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+) {
+    if !view.scan.as_ref().is_some_and(|scan| scan.is_finished()) {
+        return;
+    }
+    let scan = view.scan.take().expect("checked above");
+    let pairs = scan.join().unwrap_or_default();
+    if pairs.is_empty() {
+        status.0 = "No sync conflicts found".to_string();
+        return;
+    }
+    status.0 = format!("{} conflicted copies", pairs.len());
+    view.pairs = pairs;
+    view.cursor = 0;
+    view.comparison = None;
+    focus.open(Panel::Conflicts);
+    spawn_panel(&mut commands);
+}
+
+fn handle_conflicts_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut view: ResMut<ConflictsView>,
+    mut oplog: ResMut<OperationLog>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    mut review: ResMut<DryRunReview>,
+    config: Res<Config>,
+    panel_query: Query<Entity, With<ConflictsPanel>>,
+) {
+    // Esc backs out of a comparison before it closes the panel
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::Conflicts)
+    {
+        if view.comparison.take().is_none() {
+            close_panel(&mut commands, &mut view, &mut focus, &panel_query);
+        }
+        return;
+    }
+    if !focus.has_focus(Panel::Conflicts) || *vim_mode != VimMode::Normal {
+        return;
+    }
+
+    if view.comparison.is_some() {
+        let keep_original = keyboard.just_pressed(KeyCode::KeyO);
+        let keep_copy = keyboard.just_pressed(KeyCode::KeyC);
+        if keep_original || keep_copy {
+            let cursor = view.cursor;
+            let pair = view.pairs.remove(cursor);
+            if config.operations.dry_run {
+                plan_resolve(pair, keep_copy, &mut review);
+            } else {
+                status.0 = resolve(&pair, keep_copy, &mut oplog);
+                current_dir.needs_reload = true;
+            }
+            view.comparison = None;
+            view.cursor = view.cursor.min(view.pairs.len().saturating_sub(1));
+            if view.pairs.is_empty() {
+                close_panel(&mut commands, &mut view, &mut focus, &panel_query);
+            }
+        } else if keyboard.just_pressed(KeyCode::KeyB) {
+            view.comparison = None;
+        }
+        return;
+    }
+
+    let count = view.pairs.len();
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        view.cursor = (view.cursor + 1).min(count.saturating_sub(1));
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        view.cursor = view.cursor.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::Enter) {
+        if let Some(pair) = view.pairs.get(view.cursor) {
+            // Bring the copy into view alongside the comparison
+            if let Some(parent) = pair.copy.parent() {
+                current_dir.path = parent.to_path_buf();
+                current_dir.pending_select = Some(pair.copy.clone());
+                current_dir.needs_reload = true;
+            }
+            view.comparison = Some(compare(pair));
+        }
+    }
+}
+
+fn update_conflicts_panel(
+    view: Res<ConflictsView>,
+    focus: Res<Focus>,
+    config: Res<Config>,
+    mut text_query: Query<&mut Text, With<ConflictsText>>,
+) {
+    if !focus.is_open(Panel::Conflicts) {
+        return;
+    }
+    let mut sections = Vec::new();
+
+    match (&view.comparison, view.pairs.get(view.cursor)) {
+        (Some(comparison), Some(pair)) => {
+            sections.push(TextSection::new(
+                "COMPARE  o:keep original  c:keep copy  b/Esc:back\n",
+                panel_style(FELIPE_ORANGE),
+            ));
+            let newer_copy = match (&comparison.original, &comparison.copy) {
+                (Some(a), Some(b)) => b.modified > a.modified,
+                _ => false,
+            };
+            for (label, path, side, newer) in [
+                (
+                    "original",
+                    &pair.original,
+                    &comparison.original,
+                    !newer_copy,
+                ),
+                ("copy", &pair.copy, &comparison.copy, newer_copy),
+            ] {
+                let detail = match side {
+                    Some(side) => {
+                        format!(
+                            "{}{}{}",
+                            config.format.size(side.size),
+                            side.modified
+                                .map(|modified| format!(", {}", config.format.date(modified)))
+                                .unwrap_or_default(),
+                            if newer { ", newer" } else { "" }
+                        )
+                    }
+                    None => "missing".to_string(),
+                };
+                sections.push(TextSection::new(
+                    format!(
+                        "{:>8}: {} ({})\n",
+                        label,
+                        path.file_name().unwrap_or_default().to_string_lossy(),
+                        detail
+                    ),
+                    panel_style(FELIPE_ORANGE_DIM),
+                ));
+            }
+            match &comparison.diff {
+                Ok(changes) if changes.is_empty() => sections.push(TextSection::new(
+                    "contents are identical\n",
+                    panel_style(FELIPE_ORANGE_DIM),
+                )),
+                Ok(changes) => {
+                    for (marker, line) in changes.iter().take(DIFF_ROWS) {
+                        let color = if *marker == '+' {
+                            DIFF_ADDED
+                        } else {
+                            DIFF_REMOVED
+                        };
+                        sections.push(TextSection::new(
+                            format!("{} {}\n", marker, line),
+                            panel_style(color),
+                        ));
+                    }
+                    if changes.len() > DIFF_ROWS {
+                        sections.push(TextSection::new(
+                            format!("  ... {} more changed lines\n", changes.len() - DIFF_ROWS),
+                            panel_style(FELIPE_ORANGE_DIM),
+                        ));
+                    }
+                }
+                Err(reason) => sections.push(TextSection::new(
+                    format!("no line diff: {}\n", reason),
+                    panel_style(FELIPE_ORANGE_DIM),
+                )),
+            }
+        }
+        _ => {
+            sections.push(TextSection::new(
+                "CONFLICTS  j/k:select  Enter:compare  Esc:close\n",
+                panel_style(FELIPE_ORANGE),
+            ));
+            for (i, pair) in view.pairs.iter().enumerate() {
+                let selected = i == view.cursor;
+                sections.push(TextSection::new(
+                    format!(
+                        "{} {}{}\n",
+                        if selected { ">" } else { " " },
+                        pair.copy.display(),
+                        if pair.original.exists() {
+                            ""
+                        } else {
+                            "  (original missing)"
+                        }
+                    ),
+                    panel_style(if selected {
+                        FELIPE_ORANGE
+                    } else {
+                        FELIPE_ORANGE_DIM
+                    }),
+                ));
+            }
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Trash the losing side; a kept copy takes over the original's name
+/// Hold the choice back for the dry-run review
+fn plan_resolve(pair: ConflictPair, keep_copy: bool, review: &mut DryRunReview) {
+    let mut lines = Vec::new();
+    let label = if keep_copy {
+        if pair.original.exists() {
+            lines.push(format!("trash {}", pair.original.display()));
+        }
+        lines.push(format!(
+            "move {} -> {}",
+            pair.copy.display(),
+            pair.original.display()
+        ));
+        format!("keep copy of {}", pair.original.display())
+    } else {
+        lines.push(format!("trash {}", pair.copy.display()));
+        format!("keep original of {}", pair.original.display())
+    };
+    review.plan(label, lines, move |oplog, current_dir| {
+        current_dir.needs_reload = true;
+        resolve(&pair, keep_copy, oplog)
+    });
+}
+
+/// Keep one side of `pair`; what to tell the user
+fn resolve(pair: &ConflictPair, keep_copy: bool, oplog: &mut OperationLog) -> String {
+    let name = pair
+        .original
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let mut steps: Vec<UndoStep> = Vec::new();
+    let result = if keep_copy {
+        let trashed = if pair.original.exists() {
+            ops::trash_entry(&pair.original).map(|step| steps.push(step))
+        } else {
+            Ok(())
+        };
+        trashed
+            .and_then(|()| ops::move_path(&pair.copy, &pair.original))
+            .map(|step| steps.push(step))
+    } else {
+        ops::trash_entry(&pair.copy).map(|step| steps.push(step))
+    };
+
+    let kept = if keep_copy { "copy" } else { "original" };
+    oplog.record(format!("keep {} of {}", kept, name), steps);
+    match result {
+        Ok(()) => format!("Kept the {} of {}", kept, name),
+        Err(err) => format!("Could not keep the {} of {}: {}", kept, name, err),
+    }
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Percent(20.0),
+                    max_width: Val::Percent(60.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.95)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            ConflictsPanel,
+            Focusable(Panel::Conflicts),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), ConflictsText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    view: &mut ConflictsView,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<ConflictsPanel>>,
+) {
+    focus.close(Panel::Conflicts);
+    view.comparison = None;
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflict_original_knows_the_sync_tools_names() {
+        let cases = [
+            ("report (conflicted copy 2024-01-02).txt", "report.txt"),
+            (
+                "report (Ann's conflicted copy 2024-01-02).txt",
+                "report.txt",
+            ),
+            (
+                "report (Ann's conflicted copy 2024-01-02 (1)).txt",
+                "report.txt",
+            ),
+            (
+                "report (conflicted copy 2024-01-02 150405).txt",
+                "report.txt",
+            ),
+            ("Makefile (conflicted copy 2024-01-02)", "Makefile"),
+            ("notes.sync-conflict-20240102-150405-ABCDEFG.md", "notes.md"),
+            (
+                "archive.tar.sync-conflict-20240102-150405-ABCDEFG.gz",
+                "archive.tar.gz",
+            ),
+            ("Makefile.sync-conflict-20240102-150405-ABCDEFG", "Makefile"),
+        ];
+        for (copy, original) in cases {
+            assert_eq!(
+                conflict_original(copy).as_deref(),
+                Some(original),
+                "{}",
+                copy
+            );
+        }
+    }
+
+    #[test]
+    fn conflict_original_leaves_other_names_alone() {
+        for name in [
+            "Notes (conflict resolution).md",
+            "report (conflicted copy).txt",
+            "conflicts.rs",
+            "a.sync-conflict-notes.txt",
+            "report.txt",
+        ] {
+            assert_eq!(conflict_original(name), None, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,463 @@
+//! Copy engine - copies run on several cores and count what they've done
+//!
+//! A copy first walks its sources: directories are created up front and every
+//! file becomes a piece of work, or several for files over `LARGE_FILE`, which
+//! are split into `CHUNK_SIZE` ranges written at their own offsets. Worker
+//! threads take pieces off a shared list, so a tree of small files copies many
+//! at a time and a single disk image still keeps every worker busy. Bytes
+//! copied go to a `CopyProgress`, which the job panel turns into a rate and an
+//! ETA.
+//!
+//! Copies to or from a remote location (remote.rs) go through its backend;
+//! walking the sources, whole files and new directories go through the
+//! `VfsBackend` (vfs.rs). Large files are only split into chunks, and
+//! symlinks only made, on the local disk.
+//!
+//! Files keep their permission bits and access and modification times, and
+//! directories theirs once everything inside is written. Where the platform,
+//! filesystem or backend won't have it, the copy goes on without. Symlinks are made again
+//! as symlinks with the same content, like `cp -R` does, so a link to a
+//! directory neither fails as a file nor loops.
+//!
+//! Named pipes, sockets and devices aren't copied: reading one would wait or
+//! never end, so the entry holding one fails instead.
+//!
+//! A file that fails is deleted rather than left truncated. Directories already
+//! made stay, and so do the files copied into them: the caller keeps what a
+//! failed copy left at a free target in its undo (see ops.rs).
+
+use std::fs::{self, File, FileTimes, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::remote;
+use crate::vfs;
+
+/// Files larger than this are copied in chunks by several workers at once
+const LARGE_FILE: u64 = 64 * 1024 * 1024;
+const CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+/// Read buffer of a worker copying a chunk
+const BUFFER_SIZE: usize = 1024 * 1024;
+/// More threads than this only make the disk seek
+const MAX_WORKERS: usize = 8;
+
+/// Bytes a copy has to write and has written so far, shared with the UI
+#[derive(Debug, Default)]
+pub struct CopyProgress {
+    total: AtomicU64,
+    copied: AtomicU64,
+}
+
+impl CopyProgress {
+    /// (copied, total) in bytes; the total grows as sources are walked
+    pub fn bytes(&self) -> (u64, u64) {
+        (
+            self.copied.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Count bytes a copy has found it has to write
+    pub fn add_total(&self, bytes: u64) {
+        self.total.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_copied(&self, bytes: u64) {
+        self.copied.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+/// A file to copy and which of the copied entries it belongs to
+struct FileCopy {
+    source: PathBuf,
+    target: PathBuf,
+    size: u64,
+    /// What the copy keeps of the source; None off the local disk
+    metadata: Option<Metadata>,
+    entry: usize,
+}
+
+/// What a worker picks up: a whole file or a range of a large one
+enum Piece {
+    Whole { file: usize },
+    Range { file: usize, offset: u64, len: u64 },
+}
+
+/// Copy one file or directory tree
+pub fn copy_entry(source: &Path, target: &Path, progress: &CopyProgress) -> io::Result<()> {
+    copy_entries(&[(source.to_path_buf(), target.to_path_buf())], progress)
+        .pop()
+        .unwrap_or(Ok(()))
+}
+
+/// Copy each (source, target) pair, all at once; one result per pair
+pub fn copy_entries(pairs: &[(PathBuf, PathBuf)], progress: &CopyProgress) -> Vec<io::Result<()>> {
+    let mut results: Vec<io::Result<()>> = pairs.iter().map(|_| Ok(())).collect();
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for (entry, (source, target)) in pairs.iter().enumerate() {
+        // Transfers to or from a server go through its backend, one by one
+        if remote::location(source).is_some() || remote::location(target).is_some() {
+            results[entry] = remote::copy(source, target, progress);
+            continue;
+        }
+        let (files_before, dirs_before) = (files.len(), dirs.len());
+        let walked = check_target(source, target)
+            .and_then(|()| walk(source, target, entry, &mut files, &mut dirs));
+        if let Err(err) = walked {
+            // Directories already made stay, for the caller's undo
+            files.truncate(files_before);
+            dirs.truncate(dirs_before);
+            results[entry] = Err(err);
+        }
+    }
+    progress
+        .total
+        .fetch_add(files.iter().map(|file| file.size).sum(), Ordering::Relaxed);
+
+    let mut failures: Vec<Option<io::Error>> = files.iter().map(|_| None).collect();
+    let mut pieces = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        let len = file.size;
+        let local = vfs::is_local(&file.source) && vfs::is_local(&file.target);
+        if len <= LARGE_FILE || !local || !cfg!(any(unix, windows)) {
+            pieces.push(Piece::Whole { file: i });
+            continue;
+        }
+        // Full size up front, so chunks can land in any order
+        match File::create(&file.target).and_then(|target| target.set_len(len)) {
+            Ok(()) => {
+                pieces.extend(
+                    (0..len)
+                        .step_by(CHUNK_SIZE as usize)
+                        .map(|offset| Piece::Range {
+                            file: i,
+                            offset,
+                            len: CHUNK_SIZE.min(len - offset),
+                        }),
+                )
+            }
+            Err(err) => failures[i] = Some(err),
+        }
+    }
+
+    let failures = Mutex::new(failures);
+    let next = AtomicUsize::new(0);
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .clamp(1, MAX_WORKERS)
+        .min(pieces.len());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                let mut buffer = vec![0; BUFFER_SIZE];
+                while let Some(piece) = pieces.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let (file, result) = match *piece {
+                        Piece::Whole { file } => (file, copy_whole(&files[file], progress)),
+                        Piece::Range { file, offset, len } => (
+                            file,
+                            copy_range(&files[file], offset, len, &mut buffer, progress),
+                        ),
+                    };
+                    if let Err(err) = result {
+                        let mut failures = failures.lock().unwrap_or_else(|e| e.into_inner());
+                        failures[file].get_or_insert(err);
+                    }
+                }
+            });
+        }
+    });
+
+    let failures = failures.into_inner().unwrap_or_else(|e| e.into_inner());
+    for (file, failure) in files.iter().zip(failures) {
+        match failure {
+            Some(err) => {
+                // Not left half written
+                if let Ok(fs) = vfs::backend(&file.target) {
+                    let _ = fs.delete(&file.target);
+                }
+                if results[file.entry].is_ok() {
+                    results[file.entry] = Err(err);
+                }
+            }
+            None => {
+                if let Some(metadata) = &file.metadata {
+                    preserve(&file.target, metadata);
+                }
+            }
+        }
+    }
+    // Innermost first; setting a directory's times doesn't touch its parent
+    for (dir, metadata) in dirs.iter().rev() {
+        if let Some(metadata) = metadata {
+            preserve(dir, metadata);
+        }
+    }
+    results
+}
+
+fn check_target(source: &Path, target: &Path) -> io::Result<()> {
+    if source == target {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "source and target are the same",
+        ));
+    }
+    if target.starts_with(source) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot copy a directory into itself",
+        ));
+    }
+    Ok(())
+}
+
+/// Create the directories below `source` at `target` and list its files
+fn walk(
+    source: &Path,
+    target: &Path,
+    entry: usize,
+    files: &mut Vec<FileCopy>,
+    dirs: &mut Vec<(PathBuf, Option<Metadata>)>,
+) -> io::Result<()> {
+    let fs = vfs::backend(source)?;
+    let found = fs.stat(source)?;
+    if found.link.is_some() {
+        vfs::require_local(source)?;
+        vfs::require_local(target)?;
+        return copy_link(source, target);
+    }
+    let metadata = vfs::is_local(source)
+        .then(|| fs::symlink_metadata(source).ok())
+        .flatten();
+    // Reading a pipe or a device would wait or never end
+    if let Some(kind) = metadata.as_ref().and_then(special_kind) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is {}, not copied", source.display(), kind),
+        ));
+    }
+    if !found.is_dir {
+        files.push(FileCopy {
+            source: source.to_path_buf(),
+            target: target.to_path_buf(),
+            size: found.size,
+            metadata,
+            entry,
+        });
+        return Ok(());
+    }
+    vfs::backend(target)?.make_dir(target)?;
+    dirs.push((target.to_path_buf(), metadata));
+    for child in fs.list(source)? {
+        // The name as it is on disk, even where it isn't valid UTF-8
+        let name = child.path.file_name().unwrap_or(child.name.as_ref());
+        walk(&child.path, &target.join(name), entry, files, dirs)?;
+    }
+    Ok(())
+}
+
+/// What a file that's neither a regular file, a directory nor a symlink is
+#[cfg(unix)]
+fn special_kind(metadata: &Metadata) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let kind = metadata.file_type();
+    if kind.is_fifo() {
+        Some("a named pipe")
+    } else if kind.is_socket() {
+        Some("a socket")
+    } else if kind.is_block_device() || kind.is_char_device() {
+        Some("a device")
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(metadata: &Metadata) -> Option<&'static str> {
+    let kind = metadata.file_type();
+    (!kind.is_file() && !kind.is_dir() && !kind.is_symlink()).then_some("a special file")
+}
+
+/// A symlink at `target` with the content of the one at `source`
+#[cfg(unix)]
+fn copy_link(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)
+}
+
+/// Windows has separate file and directory symlinks; the copy is the kind the
+/// source is
+#[cfg(windows)]
+fn copy_link(source: &Path, target: &Path) -> io::Result<()> {
+    let content = fs::read_link(source)?;
+    if fs::metadata(source).is_ok_and(|m| m.is_dir()) {
+        std::os::windows::fs::symlink_dir(content, target)
+    } else {
+        std::os::windows::fs::symlink_file(content, target)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn copy_link(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+fn copy_whole(file: &FileCopy, progress: &CopyProgress) -> io::Result<()> {
+    let mut reader = vfs::backend(&file.source)?.read(&file.source)?;
+    let mut writer = vfs::backend(&file.target)?.write(&file.target)?;
+    let copied = io::copy(&mut reader, &mut writer)?;
+    progress.add_copied(copied);
+    Ok(())
+}
+
+fn copy_range(
+    file: &FileCopy,
+    offset: u64,
+    len: u64,
+    buffer: &mut [u8],
+    progress: &CopyProgress,
+) -> io::Result<()> {
+    let reader = File::open(&file.source)?;
+    let writer = File::options().write(true).open(&file.target)?;
+    let mut done = 0;
+    while done < len {
+        let want = buffer.len().min((len - done) as usize);
+        let read = match read_at(&reader, &mut buffer[..want], offset + done) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "file shrank while copying",
+                ))
+            }
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        write_all_at(&writer, &buffer[..read], offset + done)?;
+        done += read as u64;
+        progress.add_copied(read as u64);
+    }
+    Ok(())
+}
+
+/// Give `target` the permission bits and times of its source, if it can
+fn preserve(target: &Path, metadata: &Metadata) {
+    let mut times = FileTimes::new();
+    if let Ok(modified) = metadata.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    // Directories can't be opened for writing; on Unix reading is enough
+    let handle = if metadata.is_dir() {
+        File::open(target)
+    } else {
+        File::options().write(true).open(target)
+    };
+    if let Ok(handle) = handle {
+        let _ = handle.set_times(times);
+    }
+    // Last, as it may take away the write permission used above
+    let _ = fs::set_permissions(target, metadata.permissions());
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buffer, offset)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &File, buffer: &[u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buffer, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buffer, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &File, mut buffer: &[u8], mut offset: u64) -> io::Result<()> {
+    while !buffer.is_empty() {
+        let written = std::os::windows::fs::FileExt::seek_write(file, buffer, offset)?;
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        buffer = &buffer[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
+
+/// Large files are copied whole where there's no positional I/O
+#[cfg(not(any(unix, windows)))]
+fn read_at(_file: &File, _buffer: &mut [u8], _offset: u64) -> io::Result<usize> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn write_all_at(_file: &File, _buffer: &[u8], _offset: u64) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_a_tree_with_its_links_as_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::write(source.join("sub/a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink("sub", source.join("link")).unwrap();
+
+        let target = dir.path().join("copy");
+        copy_entry(&source, &target, &CopyProgress::default()).unwrap();
+        assert_eq!(fs::read_to_string(target.join("sub/a.txt")).unwrap(), "a");
+        assert_eq!(
+            fs::read_link(target.join("link")).unwrap(),
+            Path::new("sub")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_a_named_pipe_instead_of_reading_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let pipe = dir.path().join("pipe");
+        let made = std::process::Command::new("mkfifo").arg(&pipe).status();
+        if !made.is_ok_and(|status| status.success()) {
+            return;
+        }
+        let err =
+            copy_entry(&pipe, &dir.path().join("copy"), &CopyProgress::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!dir.path().join("copy").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn a_file_that_fails_is_deleted_and_the_others_are_copied() {
+        // Reading this process's memory from offset 0 fails
+        let broken = PathBuf::from("/proc/self/mem");
+        let dir = tempfile::tempdir().unwrap();
+        let fine = dir.path().join("fine.txt");
+        fs::write(&fine, "fine").unwrap();
+
+        let pairs = [
+            (broken, dir.path().join("broken copy")),
+            (fine, dir.path().join("fine copy.txt")),
+        ];
+        let results = copy_entries(&pairs, &CopyProgress::default());
+        assert!(results[0].is_err());
+        assert!(!pairs[0].1.exists());
+        assert!(results[1].is_ok());
+        assert_eq!(fs::read_to_string(&pairs[1].1).unwrap(), "fine");
+    }
+}
@@ -0,0 +1,275 @@
+//! CRT overlay - scanlines, chromatic aberration and a vignette over the 3D view
+//!
+//! A post-processing pass after tonemapping, enabled with `crt = true` under
+//! `[render]` or `:set crt` at runtime. Off by default.
+//!
+//! The same pass plays the error glitch (see glitch.rs), so the pulse shows
+//! whether or not the CRT look is on.
+
+use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::extract_component::{
+    ComponentUniforms, DynamicUniformIndex, ExtractComponentPlugin, UniformComponentPlugin,
+};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::texture::BevyDefault;
+use bevy::render::view::ViewTarget;
+use bevy::render::RenderApp;
+
+use crate::config::Config;
+use crate::glitch::Glitch;
+use crate::MainCamera;
+
+const CRT_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x6f1c_83a2_5d4e_4b07_9a61_c2f0_e8d3_1b59);
+
+use settings::CrtSettings;
+
+mod settings {
+    // ShaderType's derive emits per-field layout checks that nothing calls
+    #![allow(dead_code)]
+
+    use bevy::prelude::*;
+    use bevy::render::extract_component::ExtractComponent;
+    use bevy::render::render_resource::ShaderType;
+
+    /// Per-camera strength of each effect; the camera has this while CRT is on
+    /// or a glitch is playing
+    #[derive(Component, Clone, Copy, ExtractComponent, ShaderType)]
+    pub struct CrtSettings {
+        /// How much darker the dark scanlines are, 0.0-1.0
+        pub scanlines: f32,
+        /// Color fringe at the screen border, in pixels
+        pub aberration: f32,
+        /// How much the corners darken, 0.0-1.0
+        pub vignette: f32,
+        /// Seconds, for the scanline crawl
+        pub time: f32,
+        /// Strength of the error glitch, 0.0-1.0
+        pub glitch: f32,
+    }
+
+    impl CrtSettings {
+        /// The CRT look, or a clean picture that only carries the glitch
+        pub fn new(crt: bool) -> Self {
+            let strength = if crt { 1.0 } else { 0.0 };
+            Self {
+                scanlines: 0.25 * strength,
+                aberration: 1.5 * strength,
+                vignette: 0.45 * strength,
+                time: 0.0,
+                glitch: 0.0,
+            }
+        }
+    }
+}
+
+pub struct CrtPlugin;
+
+impl Plugin for CrtPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, CRT_SHADER, "crt.wgsl", Shader::from_wgsl);
+        app.add_plugins((
+            ExtractComponentPlugin::<CrtSettings>::default(),
+            UniformComponentPlugin::<CrtSettings>::default(),
+        ))
+        .add_systems(Update, update_screen_effects);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<CrtNode>>(Core3d, CrtLabel)
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::Tonemapping,
+                    CrtLabel,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<CrtPipeline>();
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Keep the pass on the camera while CRT is on or a glitch is playing
+fn update_screen_effects(
+    mut commands: Commands,
+    config: Res<Config>,
+    glitch: Res<Glitch>,
+    time: Res<Time>,
+    mut camera_query: Query<(Entity, Option<&mut CrtSettings>), With<MainCamera>>,
+) {
+    // Accessible mode keeps the screen still
+    let accessible = config.render.accessible;
+    let crt = config.render.crt && !accessible && config.render.quality.effects();
+    let pulse = if accessible { 0.0 } else { glitch.pulse() };
+    let wanted = crt || pulse > 0.0;
+    for (camera, settings) in camera_query.iter_mut() {
+        match settings {
+            Some(mut settings) if wanted => {
+                if config.is_changed() {
+                    *settings = CrtSettings::new(crt);
+                }
+                settings.time = time.elapsed_seconds_wrapped();
+                settings.glitch = pulse;
+            }
+            Some(_) => {
+                commands.entity(camera).remove::<CrtSettings>();
+            }
+            None if wanted => {
+                commands.entity(camera).insert(CrtSettings::new(crt));
+            }
+            None => {}
+        }
+    }
+}
+
+// =============================================================================
+// Render Graph
+// =============================================================================
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct CrtLabel;
+
+#[derive(Default)]
+struct CrtNode;
+
+impl ViewNode for CrtNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static DynamicUniformIndex<CrtSettings>,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, settings_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let crt_pipeline = world.resource::<CrtPipeline>();
+        let pipeline_id = if view_target.is_hdr() {
+            crt_pipeline.hdr_pipeline
+        } else {
+            crt_pipeline.sdr_pipeline
+        };
+        // Shaders compile in the background; skip frames until they're ready
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline_id)
+        else {
+            return Ok(());
+        };
+        let Some(settings) = world
+            .resource::<ComponentUniforms<CrtSettings>>()
+            .uniforms()
+            .binding()
+        else {
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "crt_bind_group",
+            &crt_pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &crt_pipeline.sampler,
+                settings.clone(),
+            )),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("crt_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[settings_index.index()]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct CrtPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    /// The view texture is HDR when bloom is on
+    hdr_pipeline: CachedRenderPipelineId,
+    sdr_pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for CrtPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "crt_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<CrtSettings>(true),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let queue = |format: TextureFormat| {
+            pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
+                label: Some("crt_pipeline".into()),
+                layout: vec![layout.clone()],
+                vertex: fullscreen_shader_vertex_state(),
+                fragment: Some(FragmentState {
+                    shader: CRT_SHADER,
+                    shader_defs: vec![],
+                    entry_point: "fragment".into(),
+                    targets: vec![Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                push_constant_ranges: vec![],
+            })
+        };
+        let hdr_pipeline = queue(ViewTarget::TEXTURE_FORMAT_HDR);
+        let sdr_pipeline = queue(TextureFormat::bevy_default());
+
+        Self {
+            layout,
+            sampler,
+            hdr_pipeline,
+            sdr_pipeline,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// CRT overlay: chromatic aberration, rolling scanlines and a vignette,
+// plus the torn-signal glitch played when an operation fails
+
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct CrtSettings {
+    scanlines: f32,
+    aberration: f32,
+    vignette: f32,
+    time: f32,
+    glitch: f32,
+}
+
+fn hash(n: f32) -> f32 {
+    return fract(sin(n * 12.9898) * 43758.5453);
+}
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var screen_sampler: sampler;
+@group(0) @binding(2) var<uniform> settings: CrtSettings;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(screen_texture));
+    var uv = in.uv;
+
+    // Glitch: some horizontal bands jump sideways, re-rolled 30 times a second
+    let frame = floor(settings.time * 30.0);
+    let band = floor(uv.y * 24.0);
+    let tear = hash(band + frame * 7.0);
+    if tear > 0.6 {
+        uv.x = fract(uv.x + (hash(band * 3.0 + frame) - 0.5) * 0.1 * settings.glitch);
+    }
+
+    // Red and blue drift apart towards the edges, `aberration` pixels at the border
+    let fringe = settings.aberration + settings.glitch * 12.0;
+    let offset = (uv - 0.5) * 2.0 * fringe / size;
+    let center = textureSample(screen_texture, screen_sampler, uv);
+    let red = textureSample(screen_texture, screen_sampler, uv + offset).r;
+    let blue = textureSample(screen_texture, screen_sampler, uv - offset).b;
+    var color = vec3<f32>(red, center.g, blue);
+
+    // Every other pixel row darker, crawling slowly down the screen
+    let line = 0.5 + 0.5 * sin((uv.y * size.y - settings.time * 8.0) * 3.14159265);
+    color *= 1.0 - settings.scanlines * line;
+
+    let edge = distance(uv, vec2<f32>(0.5, 0.5));
+    color *= 1.0 - settings.vignette * smoothstep(0.35, 0.85, edge);
+
+    return vec4<f32>(color, center.a);
+}
@@ -0,0 +1,277 @@
+//! User commands - shell commands from the config, run by name or key
+//!
+//! ```toml
+//! [[commands]]
+//! name = "optimize-png"
+//! run = "oxipng %*"
+//! key = "ctrl-o"        # optional
+//! confirm = true        # ask first (default false)
+//! ```
+//!
+//! `:optimize-png` or Ctrl-o then runs the command like `:!` does (see
+//! shell.rs): `%` is the selected path, `%*` the visual range, output goes to
+//! the shell panel and the listing is reloaded when it ends. Keys are a
+//! letter, digit or F1-F12, after any of `ctrl-`, `alt-` and `shift-`. They
+//! work in normal and visual mode on top of the built-in keys, so a plain
+//! letter would do both its own job and the command: bind with a modifier.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::basket::Basket;
+use crate::command::{Command, CommandLine, RunCommand};
+use crate::config::Config;
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::locale::trf;
+use crate::{CurrentDirectory, PendingPrompt, Prompt, StatusMessage, VimMode};
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CustomCommand {
+    pub name: String,
+    /// Shell command line, with `%` and `%*` placeholders
+    pub run: String,
+    pub key: Option<KeyBinding>,
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// A key with the modifiers that must be held, e.g. `ctrl-o`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    key: KeyCode,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+}
+
+impl KeyBinding {
+    fn just_pressed(&self, keyboard: &ButtonInput<KeyCode>) -> bool {
+        let held = |keys: [KeyCode; 2]| keyboard.any_pressed(keys);
+        keyboard.just_pressed(self.key)
+            && held([KeyCode::ControlLeft, KeyCode::ControlRight]) == self.ctrl
+            && held([KeyCode::AltLeft, KeyCode::AltRight]) == self.alt
+            && held([KeyCode::ShiftLeft, KeyCode::ShiftRight]) == self.shift
+    }
+}
+
+/// Written back the way the config spells it, e.g. `ctrl-o`
+impl std::fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "ctrl-"),
+            (self.alt, "alt-"),
+            (self.shift, "shift-"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        let key = format!("{:?}", self.key);
+        let key = key
+            .strip_prefix("Key")
+            .or_else(|| key.strip_prefix("Digit"))
+            .unwrap_or(&key);
+        f.write_str(&key.to_lowercase())
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("unknown key {:?}", value)))
+    }
+}
+
+impl std::str::FromStr for KeyBinding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let lower = s.to_lowercase();
+        let mut parts: Vec<&str> = lower.split(['-', '+']).collect();
+        let key = key_code(parts.pop().ok_or(())?).ok_or(())?;
+        let mut binding = KeyBinding {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        for modifier in parts {
+            match modifier {
+                "ctrl" => binding.ctrl = true,
+                "alt" => binding.alt = true,
+                "shift" => binding.shift = true,
+                _ => return Err(()),
+            }
+        }
+        Ok(binding)
+    }
+}
+
+fn key_code(name: &str) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::KeyA,
+        KeyCode::KeyB,
+        KeyCode::KeyC,
+        KeyCode::KeyD,
+        KeyCode::KeyE,
+        KeyCode::KeyF,
+        KeyCode::KeyG,
+        KeyCode::KeyH,
+        KeyCode::KeyI,
+        KeyCode::KeyJ,
+        KeyCode::KeyK,
+        KeyCode::KeyL,
+        KeyCode::KeyM,
+        KeyCode::KeyN,
+        KeyCode::KeyO,
+        KeyCode::KeyP,
+        KeyCode::KeyQ,
+        KeyCode::KeyR,
+        KeyCode::KeyS,
+        KeyCode::KeyT,
+        KeyCode::KeyU,
+        KeyCode::KeyV,
+        KeyCode::KeyW,
+        KeyCode::KeyX,
+        KeyCode::KeyY,
+        KeyCode::KeyZ,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Digit0,
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+    const FUNCTION_KEYS: [KeyCode; 12] = [
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+        KeyCode::F12,
+    ];
+    let mut chars = name.chars();
+    match (chars.next()?, chars.as_str()) {
+        (c @ 'a'..='z', "") => Some(LETTERS[c as usize - 'a' as usize]),
+        (c @ '0'..='9', "") => Some(DIGITS[c as usize - '0' as usize]),
+        ('f', number) => {
+            let number: usize = number.parse().ok()?;
+            FUNCTION_KEYS.get(number.checked_sub(1)?).copied()
+        }
+        _ => None,
+    }
+}
+
+/// A user command waiting for y/n
+pub struct CommandRequest {
+    name: String,
+    line: String,
+    targets: usize,
+}
+
+impl CommandRequest {
+    pub fn question(&self) -> String {
+        match self.targets {
+            0 | 1 => trf("Run {}? y:yes  n:no", &[&self.name]),
+            count => trf("Run {} on {} entries? y:yes  n:no", &[&self.name, &count]),
+        }
+    }
+
+    /// What runs it once confirmed
+    pub fn command(self) -> Command {
+        Command::Shell(self.line)
+    }
+}
+
+pub struct CustomPlugin;
+
+impl Plugin for CustomPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_custom_keys, handle_custom_commands).chain());
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_custom_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    current_dir: Res<CurrentDirectory>,
+    basket: Res<Basket>,
+    mut command_line: ResMut<CommandLine>,
+    mut run_commands: EventWriter<RunCommand>,
+) {
+    let visual = match *vim_mode {
+        VimMode::Normal => false,
+        VimMode::Visual => true,
+        _ => return,
+    };
+    if prompt.pending.is_some() || focus.any_open() || fly.enabled {
+        return;
+    }
+    for custom in &config.commands {
+        if custom.key.is_some_and(|key| key.just_pressed(&keyboard)) {
+            // Same targets as typing `:name` here would have
+            command_line.range = visual.then(|| current_dir.visual_range());
+            command_line.marked = basket.marked_here(&current_dir);
+            run_commands.send(RunCommand(Command::User(custom.name.clone())));
+        }
+    }
+}
+
+/// `:name` - run the user command, or ask first; it's handed on as `:!`
+fn handle_custom_commands(
+    mut events: ParamSet<(EventReader<RunCommand>, EventWriter<RunCommand>)>,
+    config: Res<Config>,
+    command_line: Res<CommandLine>,
+    current_dir: Res<CurrentDirectory>,
+    mut vim_mode: ResMut<VimMode>,
+    mut prompt: ResMut<Prompt>,
+    mut status: ResMut<StatusMessage>,
+) {
+    let mut runs = Vec::new();
+    for RunCommand(command) in events.p0().read() {
+        let Command::User(name) = command else {
+            continue;
+        };
+        let Some(custom) = config.commands.iter().find(|custom| custom.name == *name) else {
+            status.0 = format!("Not an editor command: {}", name);
+            continue;
+        };
+        if *vim_mode == VimMode::Visual {
+            *vim_mode = VimMode::Normal;
+        }
+        if custom.confirm {
+            prompt.pending = Some(PendingPrompt::ConfirmCommand(CommandRequest {
+                name: custom.name.clone(),
+                line: custom.run.clone(),
+                targets: command_line.targets(&current_dir).len(),
+            }));
+        } else {
+            runs.push(RunCommand(Command::Shell(custom.run.clone())));
+        }
+    }
+    events.p1().send_batch(runs);
+}
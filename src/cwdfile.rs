@@ -0,0 +1,69 @@
+//! cd on quit - `felipe --cwd-file <file>`
+//!
+//! A program can't change its shell's directory, so on exit Felipe writes
+//! the directory it was showing to the file given with `--cwd-file` (no
+//! trailing newline), and a shell function does the `cd`. For bash and zsh:
+//!
+//! ```sh
+//! fe() {
+//!     tmp="$(mktemp -t felipe-cwd.XXXXXX)"
+//!     felipe --cwd-file "$tmp" "$@"
+//!     cwd="$(cat -- "$tmp")"
+//!     [ -n "$cwd" ] && [ "$cwd" != "$PWD" ] && cd -- "$cwd"
+//!     rm -f -- "$tmp"
+//! }
+//! ```
+//!
+//! and for fish:
+//!
+//! ```fish
+//! function fe
+//!     set tmp (mktemp -t felipe-cwd.XXXXXX)
+//!     felipe --cwd-file $tmp $argv
+//!     set cwd (cat -- $tmp)
+//!     if test -n "$cwd" -a "$cwd" != "$PWD"
+//!         cd -- $cwd
+//!     end
+//!     rm -f -- $tmp
+//! end
+//! ```
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::CurrentDirectory;
+
+/// Where to write the last directory on exit, if anywhere
+#[derive(Resource, Default)]
+pub struct CwdFile(pub Option<PathBuf>);
+
+pub struct CwdFilePlugin;
+
+impl Plugin for CwdFilePlugin {
+    fn build(&self, app: &mut App) {
+        // Last, so exits sent anywhere in the frame (closing the window too)
+        // are seen before the app stops
+        app.init_resource::<CwdFile>()
+            .add_systems(Last, write_cwd_on_exit);
+    }
+}
+
+fn write_cwd_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    cwd_file: Res<CwdFile>,
+    current_dir: Res<CurrentDirectory>,
+) {
+    if exit_events.read().next().is_none() {
+        return;
+    }
+    if let Some(file) = &cwd_file.0 {
+        write(file, &current_dir.path);
+    }
+}
+
+/// Write `dir` to the cwd file, reporting on stderr if that fails
+pub fn write(file: &Path, dir: &Path) {
+    if let Err(err) = std::fs::write(file, dir.as_os_str().as_encoded_bytes()) {
+        eprintln!("felipe: cannot write {}: {}", file.display(), err);
+    }
+}
@@ -0,0 +1,526 @@
+//! `:dashboard` - the directory tree as 3D charts instead of the grid
+//!
+//! The grid steps aside and three bar charts rise from the floor: size by
+//! subdirectory, how old the files are, and the size each file type takes.
+//! They come from a walk of the whole tree below the current directory in
+//! the background (the same as `:stats`, see stats.rs), so a big disk shows
+//! where its space went at a glance. Drag and scroll move around the charts
+//! as they do around the grid; `:dashboard` again or Esc brings the grid back.
+
+use bevy::color::Mix;
+use bevy::prelude::*;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::debug;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::format::FormatConfig;
+use crate::layout::Layouts;
+use crate::stats::{self, DirStats, AGE_BUCKETS};
+use crate::{
+    frame_selection, update_file_labels, CameraState, CurrentDirectory, EntryPalette, FarChunk,
+    FileEntity, FileLabel, StatusMessage, UiElement, FELIPE_GRID, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+    ITEM_SPACING,
+};
+
+/// Bars per chart; the rest are summed up in one more bar
+const BARS_SHOWN: usize = 10;
+
+/// Height of the tallest bar of a chart
+const MAX_BAR_HEIGHT: f32 = 12.0;
+
+/// Width and depth of a bar
+const BAR_SIZE: f32 = 1.6;
+
+/// Distance between the middles of two charts
+const CHART_GAP: f32 = 28.0;
+
+/// One bar of a chart
+struct Bar {
+    label: String,
+    /// Size against the largest bar of its chart, 0 to 1
+    value: f32,
+    /// What the bar stands for, under its label
+    note: String,
+    color: Color,
+}
+
+/// A titled row of bars
+struct Chart {
+    title: &'static str,
+    bars: Vec<Bar>,
+}
+
+/// The tree being charted, and whether the charts replace the grid
+#[derive(Resource, Default)]
+struct Dashboard {
+    enabled: bool,
+    root: PathBuf,
+    worker: Option<JoinHandle<DirStats>>,
+    stats: Option<DirStats>,
+}
+
+impl Dashboard {
+    /// Start walking `root` over again
+    fn scan(&mut self, root: PathBuf, config: &Config) {
+        let listing = config.listing.clone();
+        self.root = root.clone();
+        self.stats = None;
+        self.worker = Some(std::thread::spawn(move || {
+            debug::timed("dashboard scan", || stats::count(&root, &listing))
+        }));
+    }
+}
+
+/// A bar, label or title of the charts
+#[derive(Component)]
+struct ChartEntity;
+
+/// Marker for the dashboard panel
+#[derive(Component)]
+struct DashboardPanel;
+
+/// Marker for the dashboard panel text
+#[derive(Component)]
+struct DashboardText;
+
+pub struct DashboardPlugin;
+
+impl Plugin for DashboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Dashboard>().add_systems(
+            Update,
+            (
+                handle_dashboard_command,
+                close_on_dismiss,
+                follow_directory,
+                finish_scan,
+                spawn_charts,
+                update_dashboard_panel,
+                hide_grid.after(update_file_labels),
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_dashboard_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut dashboard: ResMut<Dashboard>,
+    current_dir: Res<CurrentDirectory>,
+    layouts: Res<Layouts>,
+    config: Res<Config>,
+    mut camera_state: ResMut<CameraState>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<DashboardPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Dashboard {
+            continue;
+        }
+        if dashboard.enabled {
+            close_dashboard(
+                &mut commands,
+                &mut dashboard,
+                &mut focus,
+                &panel_query,
+                &current_dir,
+                &layouts,
+                &mut camera_state,
+            );
+            continue;
+        }
+        // Views and remote locations have no tree on this disk to walk
+        if !current_dir.path().is_dir() {
+            status.0 = "The dashboard works on local directories only".to_string();
+            continue;
+        }
+        dashboard.enabled = true;
+        dashboard.scan(current_dir.path().to_path_buf(), &config);
+        focus.open(Panel::Dashboard);
+        spawn_panel(&mut commands);
+        frame_charts(&mut camera_state);
+    }
+}
+
+fn close_on_dismiss(
+    mut commands: Commands,
+    mut dismissed: EventReader<Dismiss>,
+    mut dashboard: ResMut<Dashboard>,
+    current_dir: Res<CurrentDirectory>,
+    layouts: Res<Layouts>,
+    mut camera_state: ResMut<CameraState>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<DashboardPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::Dashboard)
+    {
+        close_dashboard(
+            &mut commands,
+            &mut dashboard,
+            &mut focus,
+            &panel_query,
+            &current_dir,
+            &layouts,
+            &mut camera_state,
+        );
+    }
+}
+
+/// Chart the new directory when `:cd` or a bookmark moves away
+fn follow_directory(
+    mut dashboard: ResMut<Dashboard>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mut camera_state: ResMut<CameraState>,
+) {
+    if !dashboard.enabled || dashboard.root == current_dir.path() {
+        return;
+    }
+    if current_dir.path().is_dir() {
+        dashboard.scan(current_dir.path().to_path_buf(), &config);
+    } else {
+        dashboard.root = current_dir.path().to_path_buf();
+        dashboard.worker = None;
+        dashboard.stats = None;
+    }
+    frame_charts(&mut camera_state);
+}
+
+fn finish_scan(mut dashboard: ResMut<Dashboard>, mut status: ResMut<StatusMessage>) {
+    if !dashboard
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        return;
+    }
+    let worker = dashboard.worker.take().expect("checked above");
+    match worker.join() {
+        Ok(stats) => dashboard.stats = Some(stats),
+        Err(_) => status.0 = "Counting crashed".to_string(),
+    }
+}
+
+fn spawn_charts(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    dashboard: Res<Dashboard>,
+    config: Res<Config>,
+    chart_query: Query<Entity, With<ChartEntity>>,
+) {
+    if !dashboard.is_changed() {
+        return;
+    }
+    for entity in chart_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let (true, Some(stats)) = (dashboard.enabled, &dashboard.stats) else {
+        return;
+    };
+
+    let charts = charts(stats, &config.format);
+    let first_x = -CHART_GAP * (charts.len() - 1) as f32 / 2.0;
+    for (c, chart) in charts.iter().enumerate() {
+        let chart_x = first_x + c as f32 * CHART_GAP;
+        let first_bar = -ITEM_SPACING * chart.bars.len().saturating_sub(1) as f32 / 2.0;
+        for (i, bar) in chart.bars.iter().enumerate() {
+            let x = chart_x + first_bar + i as f32 * ITEM_SPACING;
+            // A sliver stays for bars too small to see, so none goes missing
+            let height = (bar.value * MAX_BAR_HEIGHT).max(0.05);
+            commands.spawn((
+                PbrBundle {
+                    mesh: palette.cuboid(&mut meshes),
+                    material: palette.material(&mut materials, bar.color.to_linear()),
+                    // The entry cuboid is 0.8 x 1 x 0.3
+                    transform: Transform::from_xyz(x, height / 2.0, 0.0).with_scale(Vec3::new(
+                        BAR_SIZE / 0.8,
+                        height,
+                        BAR_SIZE / 0.3,
+                    )),
+                    ..default()
+                },
+                ChartEntity,
+            ));
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_sections([
+                        TextSection::new(format!("{}\n", bar.label), chart_style(FELIPE_ORANGE)),
+                        TextSection::new(bar.note.clone(), chart_style(FELIPE_ORANGE_DIM)),
+                    ]),
+                    transform: Transform::from_xyz(x, height + 1.5, 0.0)
+                        .with_scale(Vec3::splat(0.02)),
+                    ..default()
+                },
+                ChartEntity,
+            ));
+        }
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    chart.title,
+                    TextStyle {
+                        font_size: 40.0,
+                        color: FELIPE_ORANGE,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_xyz(chart_x, MAX_BAR_HEIGHT + 5.0, 0.0)
+                    .with_scale(Vec3::splat(0.03)),
+                ..default()
+            },
+            ChartEntity,
+        ));
+    }
+}
+
+fn update_dashboard_panel(
+    dashboard: Res<Dashboard>,
+    config: Res<Config>,
+    mut text_query: Query<&mut Text, With<DashboardText>>,
+    spawned: Query<(), Added<DashboardText>>,
+) {
+    // The panel is spawned a frame after `:dashboard` changes the dashboard
+    if !dashboard.is_changed() && spawned.is_empty() {
+        return;
+    }
+
+    let summary = match &dashboard.stats {
+        Some(stats) => format!(
+            "{} files in {} directories, {}",
+            stats.files,
+            stats.dirs,
+            config.format.size(stats.bytes)
+        ),
+        None if dashboard.worker.is_some() => "counting...".to_string(),
+        None => "nothing to chart here".to_string(),
+    };
+    let sections = vec![
+        TextSection::new(
+            format!("DASHBOARD  {}\n", dashboard.root.display()),
+            panel_style(FELIPE_ORANGE),
+        ),
+        TextSection::new(
+            format!("{}\ndrag/scroll:look around  Esc:grid\n", summary),
+            panel_style(FELIPE_ORANGE_DIM),
+        ),
+    ];
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+/// Keep the grid's books and labels out of sight of the charts; the labels
+/// show or hide themselves every frame, so this runs after them
+fn hide_grid(
+    dashboard: Res<Dashboard>,
+    mut grid_query: Query<&mut Visibility, Or<(With<FileEntity>, With<FileLabel>, With<FarChunk>)>>,
+) {
+    if !dashboard.enabled && !dashboard.is_changed() {
+        return;
+    }
+    let wanted = if dashboard.enabled {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    for mut visibility in grid_query.iter_mut() {
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Size by subdirectory, file age and size by type
+fn charts(stats: &DirStats, format: &FormatConfig) -> [Chart; 3] {
+    let subdirs = top_bars(
+        stats
+            .subdirs
+            .iter()
+            .map(|(name, bytes)| {
+                let label = if name.is_empty() {
+                    "(here)".to_string()
+                } else {
+                    name.clone()
+                };
+                (label, *bytes)
+            })
+            .collect(),
+        |bytes| format.size(bytes),
+        |_| FELIPE_ORANGE,
+    );
+
+    let oldest = stats.ages.len().saturating_sub(1).max(1);
+    let most = stats.ages.iter().copied().max().unwrap_or(0).max(1);
+    let ages = stats
+        .ages
+        .iter()
+        .enumerate()
+        .map(|(i, &files)| Bar {
+            label: match AGE_BUCKETS.get(i) {
+                Some((within, _)) => format!("< {}", within),
+                None => "older".to_string(),
+            },
+            value: files as f32 / most as f32,
+            note: format!("{} files", files),
+            // Newest brightest, fading to the floor's color
+            color: FELIPE_ORANGE.mix(&FELIPE_GRID, i as f32 / oldest as f32),
+        })
+        .collect();
+
+    let types = top_bars(
+        stats
+            .extensions
+            .iter()
+            .map(|(extension, _, bytes)| {
+                let label = if extension.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    format!(".{}", extension)
+                };
+                (label, *bytes)
+            })
+            .collect(),
+        |bytes| format.size(bytes),
+        |i| {
+            if i % 2 == 0 {
+                FELIPE_ORANGE
+            } else {
+                FELIPE_ORANGE_DIM
+            }
+        },
+    );
+
+    [
+        Chart {
+            title: "SIZE BY SUBDIRECTORY",
+            bars: subdirs,
+        },
+        Chart {
+            title: "FILE AGE",
+            bars: ages,
+        },
+        Chart {
+            title: "SIZE BY TYPE",
+            bars: types,
+        },
+    ]
+}
+
+/// Bars for the largest `BARS_SHOWN` of `values` (sorted largest first) and
+/// one for the rest
+fn top_bars(
+    mut values: Vec<(String, u64)>,
+    note: impl Fn(u64) -> String,
+    color: impl Fn(usize) -> Color,
+) -> Vec<Bar> {
+    if values.len() > BARS_SHOWN {
+        let rest = values.split_off(BARS_SHOWN);
+        let bytes = rest.iter().map(|(_, bytes)| bytes).sum();
+        values.push((format!("{} more", rest.len()), bytes));
+    }
+    let largest = values
+        .iter()
+        .map(|(_, bytes)| *bytes)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let shown = values.len().min(BARS_SHOWN);
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(i, (label, bytes))| Bar {
+            label,
+            value: bytes as f32 / largest as f32,
+            note: note(bytes),
+            color: if i < shown { color(i) } else { FELIPE_GRID },
+        })
+        .collect()
+}
+
+/// Pull the camera back to take in all three charts
+fn frame_charts(camera_state: &mut CameraState) {
+    *camera_state = CameraState {
+        target: Vec3::new(0.0, MAX_BAR_HEIGHT / 3.0, 0.0),
+        distance: CHART_GAP * 2.2,
+        angle: 0.45,
+        ..default()
+    };
+}
+
+fn close_dashboard(
+    commands: &mut Commands,
+    dashboard: &mut Dashboard,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<DashboardPanel>>,
+    current_dir: &CurrentDirectory,
+    layouts: &Layouts,
+    camera_state: &mut CameraState,
+) {
+    // A walk still running finishes on its own and is dropped
+    dashboard.enabled = false;
+    dashboard.worker = None;
+    dashboard.stats = None;
+    focus.close(Panel::Dashboard);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    frame_selection(current_dir, layouts, camera_state);
+}
+
+fn chart_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 30.0,
+        color,
+        ..default()
+    }
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(620.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            DashboardPanel,
+            Focusable(Panel::Dashboard),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), DashboardText));
+        });
+}
@@ -0,0 +1,255 @@
+//! Debug - where the time goes, for when a big directory gets slow
+//!
+//! `:debug` shows a panel in the top right corner, until it's typed again:
+//!
+//! ```text
+//! 60 fps  16.7 ms             frames drawn, smoothed
+//! 4211 entities               everything in the world, UI included
+//! 1830 meshes  912 materials  assets alive
+//! listing       38.2 ms   2s  the last of each timed piece of work,
+//! books        121.0 ms   2s  and how long ago it finished
+//! jobs  1 running, 2 queued   the job queue, with the running job's label
+//! ```
+//!
+//! When nothing moves, Felipe only draws `idle_fps` times a second (see
+//! power.rs), and so does the panel; touch a key for the full rate.
+//!
+//! The timed pieces are the directory listing, spawning its books, measuring
+//! directory sizes, peeking into the selected directory and the `:stats` and
+//! `:dashboard` scans. Each also runs in a `tracing` span; built with
+//! `--features trace`, the log gets a line as each span closes, with how long
+//! it was busy, so a slow run can be read afterwards (see logging.rs).
+
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::command::{Command, RunCommand};
+use crate::jobs::JobQueue;
+use crate::{UiElement, FELIPE_ORANGE, FELIPE_ORANGE_DIM};
+
+/// The panel is rewritten this often, slow enough to read
+const REFRESH_EVERY: Duration = Duration::from_millis(250);
+
+/// Queued jobs listed by label; the rest are counted
+const LISTED_JOBS: usize = 3;
+
+/// Last run of each timed piece of work, in the order they first ran
+static TIMINGS: Mutex<Vec<Timing>> = Mutex::new(Vec::new());
+
+struct Timing {
+    name: &'static str,
+    took: Duration,
+    at: Instant,
+}
+
+/// Run `work` in a span called `name`, keeping how long it took for the
+/// panel; works from any thread
+pub fn timed<T>(name: &'static str, work: impl FnOnce() -> T) -> T {
+    let _span = info_span!("timed", work = name).entered();
+    let started = Instant::now();
+    let result = work();
+    record(name, started.elapsed());
+    result
+}
+
+fn record(name: &'static str, took: Duration) {
+    let mut timings = TIMINGS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let timing = Timing {
+        name,
+        took,
+        at: Instant::now(),
+    };
+    match timings.iter_mut().find(|timing| timing.name == name) {
+        Some(last) => *last = timing,
+        None => timings.push(timing),
+    }
+}
+
+/// Whether the panel is shown, and when it was last rewritten
+#[derive(Resource, Default)]
+struct DebugPanel {
+    shown: bool,
+    refreshed: Option<Instant>,
+}
+
+/// Marker for the panel's UI node
+#[derive(Component)]
+struct DebugNode;
+
+/// Marker for the panel's text
+#[derive(Component)]
+struct DebugText;
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.init_resource::<DebugPanel>()
+            .add_systems(Update, (handle_debug_command, update_debug_panel).chain());
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// `:debug` shows the panel or takes it away
+fn handle_debug_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut panel: ResMut<DebugPanel>,
+    node_query: Query<Entity, With<DebugNode>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Debug {
+            continue;
+        }
+        panel.shown = !panel.shown;
+        panel.refreshed = None;
+        if panel.shown {
+            spawn_panel(&mut commands);
+        } else {
+            for entity in node_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn update_debug_panel(
+    mut panel: ResMut<DebugPanel>,
+    diagnostics: Res<DiagnosticsStore>,
+    entities: Query<Entity>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+    images: Res<Assets<Image>>,
+    queue: Res<JobQueue>,
+    mut text_query: Query<&mut Text, With<DebugText>>,
+) {
+    if !panel.shown
+        || panel
+            .refreshed
+            .is_some_and(|refreshed| refreshed.elapsed() < REFRESH_EVERY)
+    {
+        return;
+    }
+    panel.refreshed = Some(Instant::now());
+
+    let smoothed = |path| {
+        diagnostics
+            .get(path)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or(0.0)
+    };
+    let mut sections = vec![
+        TextSection::new(
+            format!(
+                "{:.0} fps  {:.1} ms\n",
+                smoothed(&FrameTimeDiagnosticsPlugin::FPS),
+                smoothed(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+            ),
+            panel_style(FELIPE_ORANGE),
+        ),
+        TextSection::new(
+            format!(
+                "{} entities\n{} meshes  {} materials  {} images\n",
+                entities.iter().count(),
+                meshes.len(),
+                materials.len(),
+                images.len()
+            ),
+            panel_style(FELIPE_ORANGE_DIM),
+        ),
+    ];
+
+    {
+        let timings = TIMINGS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if timings.is_empty() {
+            sections.push(TextSection::new(
+                "nothing timed yet\n",
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+        }
+        for timing in timings.iter() {
+            sections.push(TextSection::new(
+                format!(
+                    "{:<12} {:>8.1} ms  {}s ago\n",
+                    timing.name,
+                    timing.took.as_secs_f64() * 1000.0,
+                    timing.at.elapsed().as_secs()
+                ),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+        }
+    }
+
+    let queued: Vec<&str> = queue.queued().map(|(label, _)| label).collect();
+    let jobs = match queue.running() {
+        Some((label, elapsed)) => format!(
+            "jobs  {} running {}s, {} queued",
+            label,
+            elapsed.as_secs(),
+            queued.len()
+        ),
+        None if queued.is_empty() => "jobs  none".to_string(),
+        None => format!("jobs  {} queued", queued.len()),
+    };
+    sections.push(TextSection::new(jobs, panel_style(FELIPE_ORANGE)));
+    for label in queued.iter().take(LISTED_JOBS) {
+        sections.push(TextSection::new(
+            format!("\n  next {}", label),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    if queued.len() > LISTED_JOBS {
+        sections.push(TextSection::new(
+            format!("\n  {} more", queued.len() - LISTED_JOBS),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections.clone_from(&sections);
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    right: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.85)),
+                border_color: BorderColor(FELIPE_ORANGE_DIM),
+                ..default()
+            },
+            DebugNode,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), DebugText));
+        });
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 14.0,
+        color,
+        ..default()
+    }
+}
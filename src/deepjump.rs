@@ -0,0 +1,432 @@
+//! Deep jump - fuzzy-find a file anywhere below the current directory
+//!
+//! `F` (or `:find`) walks the whole subtree on all cores in the background,
+//! the same walk `:flatten` uses, and narrows it as you type. The letters only
+//! have to appear in order, so a few initials are enough; starts of names and
+//! words, runs of adjacent letters and hits in the file name rank higher.
+//! Enter opens the directory of the best match with the file selected.
+//!
+//! With a search index (index.rs), `:find` looks through the index instead,
+//! so it finds files under every indexed root, not just here.
+//!
+//! ```text
+//! F sfbb Enter     -> src/foo/bar/ with baz.rs selected
+//! Up/Down, Tab     -> move through the matches
+//! :find main       -> same, starting with "main" typed
+//! ```
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::flatten::{self, MAX_FLAT_ENTRIES};
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::{
+    CurrentDirectory, Prompt, StatusMessage, UiElement, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+/// Matches listed in the panel
+const SHOWN_MATCHES: usize = 12;
+
+/// State of the `F` prompt
+#[derive(Resource, Default)]
+pub struct DeepJump {
+    query: String,
+    /// Directory the walk started from
+    root: PathBuf,
+    /// Every file below `root`, relative to it, once the walk is done
+    files: Vec<String>,
+    truncated: bool,
+    walker: Option<JoinHandle<(Vec<String>, bool)>>,
+    /// `files` come from the search index, fetched again as the query changes
+    indexed: bool,
+    /// Indices into `files`, best first
+    matches: Vec<usize>,
+    cursor: usize,
+}
+
+impl DeepJump {
+    /// Forget the last walk and index the subtree under `root` again
+    fn start(&mut self, root: PathBuf, config: &Config, query: String) {
+        let listing = config.listing.clone();
+        let walk_root = root.clone();
+        self.walker = Some(std::thread::spawn(move || {
+            let (files, truncated) = flatten::walk_files(&walk_root, &listing);
+            (files.into_iter().map(|file| file.name).collect(), truncated)
+        }));
+        self.root = root;
+        self.files.clear();
+        self.truncated = false;
+        self.indexed = false;
+        self.query = query;
+        self.rank();
+    }
+
+    /// Search the index instead of walking; false when there's no index
+    fn start_indexed(&mut self, query: String) -> bool {
+        if indexed_files(&query).is_none() {
+            return false;
+        }
+        self.walker = None;
+        // Indexed paths are absolute, so they join onto nothing
+        self.root = PathBuf::new();
+        self.truncated = false;
+        self.indexed = true;
+        self.query = query;
+        self.rank();
+        true
+    }
+
+    /// Re-rank `files` against the query, best first
+    fn rank(&mut self) {
+        if self.indexed {
+            self.files = indexed_files(&self.query).unwrap_or_default();
+        }
+        let query: Vec<char> = self.query.chars().filter(|c| *c != ' ').collect();
+        // Smart case: an upper-case letter makes the whole query exact
+        let exact_case = query.iter().any(|c| c.is_uppercase());
+        let mut scored: Vec<(i64, usize)> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, file)| fuzzy_score(&query, file, exact_case).map(|score| (score, i)))
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| self.files[a.1].len().cmp(&self.files[b.1].len()))
+                .then_with(|| self.files[a.1].cmp(&self.files[b.1]))
+        });
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.cursor = 0;
+    }
+
+    /// Absolute path of the match under the cursor
+    fn chosen(&self) -> Option<PathBuf> {
+        let index = *self.matches.get(self.cursor)?;
+        Some(self.root.join(&self.files[index]))
+    }
+}
+
+/// Score of `query` as a subsequence of `path`, higher is better; `None` when
+/// some letter is missing. Letters are taken as early as possible, which is
+/// what people type when they spell out a path's initials.
+fn fuzzy_score(query: &[char], path: &str, exact_case: bool) -> Option<i64> {
+    let same = |a: char, b: char| {
+        if exact_case {
+            a == b
+        } else {
+            a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+    let name_start = path.rfind(['/', '\\']).map_or(0, |i| i + 1);
+
+    let mut wanted = query.iter().peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    let mut last_match = 0;
+    for (i, c) in path.char_indices() {
+        let Some(&&letter) = wanted.peek() else {
+            break;
+        };
+        let matched = same(c, letter);
+        if matched {
+            let word_start = previous.is_none_or(|p| {
+                matches!(p, '/' | '\\' | '_' | '-' | '.' | ' ')
+                    || (p.is_lowercase() && c.is_uppercase())
+            });
+            score += 1;
+            if word_start {
+                score += 8;
+            }
+            if previous_matched {
+                score += 5;
+            }
+            last_match = i;
+            wanted.next();
+        }
+        previous_matched = matched;
+        previous = Some(c);
+    }
+    if wanted.peek().is_some() {
+        return None;
+    }
+    // Hits reaching into the file name beat ones spent on directories
+    if !query.is_empty() && last_match >= name_start {
+        score += 10;
+    }
+    Some(score)
+}
+
+/// Paths from the search index for `query`; None without an index
+#[cfg(feature = "index")]
+fn indexed_files(query: &str) -> Option<Vec<String>> {
+    crate::index::search(query)
+}
+
+#[cfg(not(feature = "index"))]
+fn indexed_files(_query: &str) -> Option<Vec<String>> {
+    None
+}
+
+/// Marker for the deep jump panel
+#[derive(Component)]
+struct DeepJumpPanel;
+
+/// Marker for the deep jump panel text
+#[derive(Component)]
+struct DeepJumpText;
+
+pub struct DeepJumpPlugin;
+
+impl Plugin for DeepJumpPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DeepJump::default()).add_systems(
+            Update,
+            (
+                handle_jump_input,
+                handle_find_command,
+                collect_walk,
+                update_jump_panel,
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_jump_input(
+    mut commands: Commands,
+    mut key_events: EventReader<KeyboardInput>,
+    mut vim_mode: ResMut<VimMode>,
+    mut jump: ResMut<DeepJump>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    config: Res<Config>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    panel_query: Query<Entity, With<DeepJumpPanel>>,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed || prompt.pending.is_some() {
+            continue;
+        }
+
+        if *vim_mode == VimMode::Normal {
+            if focus.any_open() || fly.enabled {
+                continue;
+            }
+            if matches!(&event.logical_key, Key::Character(c) if c == "F") {
+                jump.start(current_dir.path.clone(), &config, String::new());
+                *vim_mode = VimMode::Jump;
+                spawn_panel(&mut commands);
+            }
+            continue;
+        }
+        if *vim_mode != VimMode::Jump {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(c) => jump.query.push_str(c),
+            Key::Space => jump.query.push(' '),
+            Key::Backspace => {
+                // Backspace on empty text gives up, like the command line
+                if jump.query.pop().is_none() {
+                    close_panel(&mut commands, &mut vim_mode, &panel_query);
+                    continue;
+                }
+            }
+            Key::ArrowDown | Key::Tab => {
+                jump.cursor = (jump.cursor + 1).min(jump.matches.len().saturating_sub(1));
+                continue;
+            }
+            Key::ArrowUp => {
+                jump.cursor = jump.cursor.saturating_sub(1);
+                continue;
+            }
+            Key::Escape => {
+                close_panel(&mut commands, &mut vim_mode, &panel_query);
+                continue;
+            }
+            Key::Enter => {
+                match jump.chosen() {
+                    Some(file) => {
+                        if let Some(dir) = file.parent() {
+                            current_dir.set_path(dir);
+                        }
+                        current_dir.pending_select = Some(file);
+                        status.0.clear();
+                    }
+                    None if jump.walker.is_some() => continue,
+                    None => status.0 = format!("No file matches {}", jump.query),
+                }
+                close_panel(&mut commands, &mut vim_mode, &panel_query);
+                continue;
+            }
+            _ => continue,
+        }
+        jump.rank();
+    }
+}
+
+fn handle_find_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut vim_mode: ResMut<VimMode>,
+    mut jump: ResMut<DeepJump>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Find(query) = command else {
+            continue;
+        };
+        if !jump.start_indexed(query.clone()) {
+            jump.start(current_dir.path.clone(), &config, query.clone());
+        }
+        if *vim_mode != VimMode::Jump {
+            *vim_mode = VimMode::Jump;
+            spawn_panel(&mut commands);
+        }
+    }
+}
+
+/// Pick up the file list once the background walk is done
+fn collect_walk(mut jump: ResMut<DeepJump>, mut status: ResMut<StatusMessage>) {
+    if !jump
+        .walker
+        .as_ref()
+        .is_some_and(|walker| walker.is_finished())
+    {
+        return;
+    }
+    let Some(walker) = jump.walker.take() else {
+        return;
+    };
+    let Ok((files, truncated)) = walker.join() else {
+        status.0 = "Cannot list the files below this directory".to_string();
+        return;
+    };
+    jump.files = files;
+    jump.truncated = truncated;
+    if truncated {
+        status.0 = format!("Only the first {} files are searched", MAX_FLAT_ENTRIES);
+    }
+    jump.rank();
+}
+
+fn update_jump_panel(
+    jump: Res<DeepJump>,
+    vim_mode: Res<VimMode>,
+    mut text_query: Query<&mut Text, With<DeepJumpText>>,
+) {
+    if *vim_mode != VimMode::Jump {
+        return;
+    }
+    let count = if jump.walker.is_some() {
+        "indexing...".to_string()
+    } else if jump.indexed {
+        format!("{} in the index", jump.matches.len())
+    } else {
+        format!(
+            "{}/{}{}",
+            jump.matches.len(),
+            jump.files.len(),
+            if jump.truncated { "+" } else { "" }
+        )
+    };
+    let mut sections = vec![
+        TextSection::new(
+            "FIND  type to narrow  Up/Down:select  Enter:jump  Esc:cancel\n",
+            panel_style(FELIPE_ORANGE_DIM),
+        ),
+        TextSection::new(
+            format!("> {}_  {}\n", jump.query, count),
+            panel_style(FELIPE_ORANGE),
+        ),
+    ];
+    // Keep the cursor in view when it goes past the first page
+    let skip = jump.cursor.saturating_sub(SHOWN_MATCHES - 1);
+    for (i, &index) in jump
+        .matches
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .take(SHOWN_MATCHES)
+    {
+        let selected = i == jump.cursor;
+        sections.push(TextSection::new(
+            format!(
+                "{} {}\n",
+                if selected { ">" } else { " " },
+                jump.files[index]
+            ),
+            panel_style(if selected {
+                FELIPE_ORANGE
+            } else {
+                FELIPE_ORANGE_DIM
+            }),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(640.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            DeepJumpPanel,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), DeepJumpText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    vim_mode: &mut VimMode,
+    panel_query: &Query<Entity, With<DeepJumpPanel>>,
+) {
+    *vim_mode = VimMode::Normal;
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
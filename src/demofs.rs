@@ -0,0 +1,299 @@
+//! Demo filesystem - a made-up tree kept in memory
+//!
+//! `felipe --demo` starts in `Demo`, a small project, photo and music tree
+//! that exists only while Felipe runs: it can be browsed, and files made,
+//! renamed and deleted in it, without touching the disk. It's the start of a
+//! browser build, which has no disk to show; that also needs every `std::fs`
+//! call outside vfs.rs to go through a `VfsBackend` first, so for now only
+//! listings and the file operations see it (previews and the like read
+//! nothing there).
+//!
+//! `cargo check --target wasm32-unknown-unknown` builds: git, the metadata
+//! store, the system clipboard and log files are left out there (see
+//! metadata_web.rs). Running it takes more, as the workers listing, measuring
+//! and previewing are threads, which the browser doesn't give.
+
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::vfs::VfsBackend;
+use crate::FileEntry;
+
+/// Top of the demo tree, shown like the `Starred` view
+const DEMO_ROOT: &str = "Demo";
+
+/// What the tree starts with; directories are made for every parent
+const SAMPLE: &[(&str, &str)] = &[
+    (
+        "README.md",
+        "# Demo\n\nA tree that lives in memory. Nothing here is on disk.\n",
+    ),
+    (
+        "notes.txt",
+        "hjkl to move, l to enter, h to leave, : for commands\n",
+    ),
+    (
+        "project/Cargo.toml",
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n",
+    ),
+    (
+        "project/src/main.rs",
+        "fn main() {\n    println!(\"Hello from the demo\");\n}\n",
+    ),
+    (
+        "project/src/lib.rs",
+        "pub fn answer() -> u32 {\n    42\n}\n",
+    ),
+    ("project/.gitignore", "/target\n"),
+    ("photos/2024/beach.jpg", ""),
+    ("photos/2024/mountain.jpg", ""),
+    ("photos/2025/city.png", ""),
+    ("music/album/01-intro.flac", ""),
+    ("music/album/02-theme.flac", ""),
+    ("documents/report.pdf", ""),
+    ("documents/budget.csv", "month,amount\njan,120\nfeb,95\n"),
+];
+
+/// An entry of the tree; `data` is None for a directory
+struct Node {
+    data: Option<Vec<u8>>,
+    modified: SystemTime,
+}
+
+/// Entries of the tree by full path
+pub struct MemoryFs {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+}
+
+static DEMO: LazyLock<MemoryFs> = LazyLock::new(MemoryFs::sample);
+
+pub fn demo_root() -> PathBuf {
+    PathBuf::from(DEMO_ROOT)
+}
+
+pub fn is_demo_root(path: &Path) -> bool {
+    path == Path::new(DEMO_ROOT)
+}
+
+/// Whether `path` is in the demo tree
+pub fn is_demo(path: &Path) -> bool {
+    path.starts_with(DEMO_ROOT)
+}
+
+/// The demo tree itself
+pub fn demo() -> &'static MemoryFs {
+    &DEMO
+}
+
+impl MemoryFs {
+    fn sample() -> Self {
+        // Fixed times, a clock isn't a given everywhere this should run
+        let day = Duration::from_secs(24 * 60 * 60);
+        let start = SystemTime::UNIX_EPOCH + day * 20_000;
+        let mut nodes = BTreeMap::new();
+        nodes.insert(
+            demo_root(),
+            Node {
+                data: None,
+                modified: start,
+            },
+        );
+        for (i, (path, text)) in SAMPLE.iter().enumerate() {
+            let path = demo_root().join(path);
+            let modified = start + day * i as u32;
+            for parent in path.ancestors().skip(1) {
+                if parent.as_os_str().is_empty() {
+                    break;
+                }
+                nodes.entry(parent.to_path_buf()).or_insert(Node {
+                    data: None,
+                    modified,
+                });
+            }
+            // Pictures and songs get a believable size without the bytes
+            let data = if text.is_empty() {
+                vec![0; 200_000 + i * 150_000]
+            } else {
+                text.as_bytes().to_vec()
+            };
+            nodes.insert(
+                path,
+                Node {
+                    data: Some(data),
+                    modified,
+                },
+            );
+        }
+        Self {
+            nodes: Mutex::new(nodes),
+        }
+    }
+
+    fn nodes(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Node>> {
+        self.nodes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{}: not in the demo", path.display()),
+    )
+}
+
+fn entry(path: &Path, node: &Node) -> FileEntry {
+    FileEntry {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string()),
+        path: path.to_path_buf(),
+        is_dir: node.data.is_none(),
+        size: node.data.as_ref().map_or(0, |data| data.len() as u64),
+        modified: Some(node.modified),
+        locked: false,
+        link: None,
+        inode: None,
+        executable: false,
+    }
+}
+
+/// A file being written, put in the tree when it's dropped
+struct MemoryFile {
+    path: PathBuf,
+    data: Vec<u8>,
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for MemoryFile {
+    fn drop(&mut self) {
+        demo().nodes().insert(
+            std::mem::take(&mut self.path),
+            Node {
+                data: Some(std::mem::take(&mut self.data)),
+                modified: SystemTime::now(),
+            },
+        );
+    }
+}
+
+impl VfsBackend for MemoryFs {
+    fn list(&self, path: &Path) -> io::Result<Vec<FileEntry>> {
+        let nodes = self.nodes();
+        match nodes.get(path) {
+            Some(node) if node.data.is_none() => {}
+            Some(_) => return Err(io::Error::other("not a directory")),
+            None => return Err(not_found(path)),
+        }
+        Ok(nodes
+            .iter()
+            .filter(|(child, _)| child.parent() == Some(path))
+            .map(|(child, node)| entry(child, node))
+            .collect())
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileEntry> {
+        let nodes = self.nodes();
+        let node = nodes.get(path).ok_or_else(|| not_found(path))?;
+        Ok(entry(path, node))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let nodes = self.nodes();
+        match nodes.get(path).map(|node| &node.data) {
+            Some(Some(data)) => Ok(Box::new(Cursor::new(data.clone()))),
+            Some(None) => Err(io::Error::other("is a directory")),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        let nodes = self.nodes();
+        let parent = path.parent().ok_or_else(|| not_found(path))?;
+        if nodes.get(parent).is_none_or(|node| node.data.is_some()) {
+            return Err(not_found(parent));
+        }
+        Ok(Box::new(MemoryFile {
+            path: path.to_path_buf(),
+            data: Vec::new(),
+        }))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        if !is_demo(to) || is_demo_root(from) {
+            return Err(io::Error::other("cannot move out of the demo"));
+        }
+        let mut nodes = self.nodes();
+        if !nodes.contains_key(from) {
+            return Err(not_found(from));
+        }
+        // The entry and everything under it
+        let moved: Vec<PathBuf> = nodes
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for path in moved {
+            if let Some(node) = nodes.remove(&path) {
+                let rest = path.strip_prefix(from).unwrap_or(Path::new(""));
+                nodes.insert(to.join(rest), node);
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        if is_demo_root(path) {
+            return Err(io::Error::other("cannot delete the demo"));
+        }
+        let mut nodes = self.nodes();
+        if !nodes.contains_key(path) {
+            return Err(not_found(path));
+        }
+        nodes.retain(|entry, _| !entry.starts_with(path));
+        Ok(())
+    }
+
+    fn make_dir(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        for dir in path.ancestors() {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            match nodes.get(dir) {
+                Some(node) if node.data.is_some() => {
+                    return Err(io::Error::other(format!(
+                        "{}: not a directory",
+                        dir.display()
+                    )))
+                }
+                Some(_) => {}
+                None => {
+                    nodes.insert(
+                        dir.to_path_buf(),
+                        Node {
+                            data: None,
+                            modified: SystemTime::now(),
+                        },
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
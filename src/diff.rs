@@ -0,0 +1,347 @@
+//! `:diff other-dir` - compare the tree under the current directory with another
+//!
+//! Both trees are scanned on a worker thread (sizes and mtimes, as
+//! `:snapshot` records them) and the scene is colored like a diff: green for
+//! entries only here, yellow for files whose size or mtime differ and for
+//! directories holding a difference. Entries only over there have nothing to
+//! draw, so the panel lists them in red.
+//!
+//! The panel offers the sync actions: `:diff >` copies what's new or changed
+//! here over there, `:diff <` the other way round. Neither deletes anything;
+//! replaced files go to the trash and `u` undoes the whole sync.
+//!
+//! ```text
+//! :diff ../backup    compare with the backup
+//! :diff >            bring the backup up to date
+//! :diff              close
+//! ```
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+use crate::bookmarks::expand_home;
+use crate::command::{Command, RunCommand};
+use crate::jobs::JobSummary;
+use crate::oplog::OperationLog;
+use crate::ops::{self, TransferReport};
+use crate::snapshot::{self, ChangeSet};
+use crate::{
+    CurrentDirectory, EntryTints, StatusMessage, UiElement, DIFF_ADDED, DIFF_MODIFIED,
+    DIFF_REMOVED, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+/// Differing paths listed in the panel per category
+const PANEL_ROWS: usize = 12;
+
+enum TaskResult {
+    Compared(ChangeSet),
+    Synced {
+        label: String,
+        report: TransferReport,
+    },
+    Failed(String),
+}
+
+/// Scans and syncs run on a worker thread; large trees take a while
+#[derive(Resource, Default)]
+struct DiffTask {
+    worker: Option<JoinHandle<TaskResult>>,
+}
+
+/// The comparison shown in the scene
+#[derive(Resource, Default)]
+struct DiffView {
+    /// The directory compared and the one it's compared against
+    pair: Option<(PathBuf, PathBuf)>,
+    changes: Option<ChangeSet>,
+}
+
+/// Marker for the diff panel
+#[derive(Component)]
+struct DiffPanel;
+
+/// Marker for the diff panel text
+#[derive(Component)]
+struct DiffText;
+
+pub struct DiffPlugin;
+
+impl Plugin for DiffPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DiffTask::default())
+            .insert_resource(DiffView::default())
+            .add_systems(
+                Update,
+                (handle_diff_commands, finish_diff_task, update_diff_panel),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_diff_commands(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut task: ResMut<DiffTask>,
+    mut view: ResMut<DiffView>,
+    mut tints: ResMut<EntryTints>,
+    current_dir: Res<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    panel_query: Query<Entity, With<DiffPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let here = current_dir.path.clone();
+        let job: Box<dyn FnOnce() -> TaskResult + Send> = match command {
+            Command::Diff(None) => {
+                if view.pair.take().is_none() {
+                    status.0 = "Usage: :diff other-dir (:diff again closes)".to_string();
+                    continue;
+                }
+                view.changes = None;
+                tints.colors.clear();
+                for entity in panel_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                continue;
+            }
+            Command::Diff(Some(path)) => {
+                let Ok(other) = here.join(expand_home(path)).canonicalize() else {
+                    status.0 = format!("No such directory: {}", path.display());
+                    continue;
+                };
+                if !other.is_dir() || other == here {
+                    status.0 = format!("Cannot compare with {}", other.display());
+                    continue;
+                }
+                if task.worker.is_some() {
+                    status.0 = "A diff task is already running".to_string();
+                    continue;
+                }
+                status.0 = format!("Comparing with {}...", other.display());
+                view.pair = Some((here.clone(), other.clone()));
+                Box::new(move || compare(here, other))
+            }
+            Command::DiffSync { to_other } => {
+                // Sync the trees that were compared, wherever the user is now
+                let Some((here, other)) = view.pair.clone() else {
+                    status.0 = "Nothing to sync - :diff other-dir first".to_string();
+                    continue;
+                };
+                let (source, dest) = if *to_other {
+                    (here, other)
+                } else {
+                    (other, here)
+                };
+                status.0 = format!("Syncing {} into {}...", source.display(), dest.display());
+                Box::new(move || sync(source, dest))
+            }
+            _ => continue,
+        };
+
+        if task.worker.is_some() {
+            status.0 = "A diff task is already running".to_string();
+            continue;
+        }
+        task.worker = Some(std::thread::spawn(job));
+    }
+}
+
+fn finish_diff_task(
+    mut commands: Commands,
+    mut task: ResMut<DiffTask>,
+    mut view: ResMut<DiffView>,
+    mut tints: ResMut<EntryTints>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut oplog: ResMut<OperationLog>,
+    mut summary: ResMut<JobSummary>,
+    mut status: ResMut<StatusMessage>,
+    panel_query: Query<Entity, With<DiffPanel>>,
+) {
+    if !task
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        return;
+    }
+    let worker = task.worker.take().expect("checked above");
+    let result = worker
+        .join()
+        .unwrap_or_else(|_| TaskResult::Failed("Diff worker crashed".to_string()));
+
+    match result {
+        TaskResult::Compared(changes) => {
+            // Closed or moved on while the worker ran
+            if view.pair.is_none() || changes.root != current_dir.path {
+                return;
+            }
+            status.0 = if changes.is_empty() {
+                "No differences".to_string()
+            } else {
+                format!(
+                    "{} only here, {} only there, {} different",
+                    changes.added.len(),
+                    changes.removed.len(),
+                    changes.modified.len()
+                )
+            };
+            tints.colors = changes.tints();
+            view.changes = Some(changes);
+            if panel_query.is_empty() {
+                spawn_panel(&mut commands);
+            }
+        }
+        TaskResult::Synced { label, report } => {
+            let failed: Vec<(PathBuf, String)> = report
+                .failed
+                .into_iter()
+                .map(|(path, err)| (path, err.to_string()))
+                .collect();
+            status.0 = match failed.first() {
+                None => format!("{}: {} copied", label, report.done),
+                Some((path, reason)) => format!(
+                    "{}: {} copied, {} failed ({}: {})",
+                    label,
+                    report.done,
+                    failed.len(),
+                    path.display(),
+                    reason
+                ),
+            };
+            let failures = failed
+                .iter()
+                .map(|(path, reason)| format!("{}: {}", path.display(), reason))
+                .collect();
+            oplog.record_outcome(label, report.steps, failures);
+            summary.add("synced", report.done, failed);
+            current_dir.keep_selection();
+            // Compare again so the colors show what's left
+            if let Some((here, other)) = view.pair.clone() {
+                task.worker = Some(std::thread::spawn(move || compare(here, other)));
+            }
+        }
+        TaskResult::Failed(message) => status.0 = message,
+    }
+}
+
+fn update_diff_panel(view: Res<DiffView>, mut text_query: Query<&mut Text, With<DiffText>>) {
+    let (Some((_, other)), Some(changes)) = (&view.pair, &view.changes) else {
+        return;
+    };
+
+    let mut sections = vec![
+        TextSection::new(
+            "DIFF  :diff > push there  :diff < pull here  :diff to close\n",
+            panel_style(FELIPE_ORANGE),
+        ),
+        TextSection::new(
+            format!(
+                "{}\nvs {}{}\n",
+                changes.root.display(),
+                other.display(),
+                if changes.truncated {
+                    " (trees truncated)"
+                } else {
+                    ""
+                }
+            ),
+            panel_style(FELIPE_ORANGE_DIM),
+        ),
+    ];
+    if changes.is_empty() {
+        sections.push(TextSection::new(
+            "(no differences)",
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    for (marker, paths, color) in [
+        ("+", &changes.added, DIFF_ADDED),
+        ("-", &changes.removed, DIFF_REMOVED),
+        ("~", &changes.modified, DIFF_MODIFIED),
+    ] {
+        for path in paths.iter().take(PANEL_ROWS) {
+            sections.push(TextSection::new(
+                format!("{} {}\n", marker, path.display()),
+                panel_style(color),
+            ));
+        }
+        if paths.len() > PANEL_ROWS {
+            sections.push(TextSection::new(
+                format!("  ... {} more\n", paths.len() - PANEL_ROWS),
+                panel_style(color),
+            ));
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Worker Tasks
+// =============================================================================
+
+/// Differences of `here` against `other`: added is only here, removed only there
+fn compare(here: PathBuf, other: PathBuf) -> TaskResult {
+    let there = snapshot::scan_tree(&other, false);
+    let here = snapshot::scan_tree(&here, false);
+    TaskResult::Compared(snapshot::compare_trees(&there, &here))
+}
+
+/// Copy what's new or changed in `source` into `dest`
+fn sync(source: PathBuf, dest: PathBuf) -> TaskResult {
+    let plan = match ops::plan_sync(&source, &dest, false) {
+        Ok(plan) => plan,
+        Err(err) => return TaskResult::Failed(format!("Cannot sync: {}", err)),
+    };
+    let label = format!(
+        "sync {} into {}",
+        source.file_name().unwrap_or_default().to_string_lossy(),
+        dest.display()
+    );
+    TaskResult::Synced {
+        label,
+        report: ops::execute_sync(&plan),
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(520.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            DiffPanel,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), DiffText));
+        });
+}
@@ -0,0 +1,339 @@
+//! `:sync dest` - mirror the current directory to another one
+//!
+//! The sync is planned first, without touching anything: files missing at the
+//! destination or changed here (another size, a newer mtime) are to be copied,
+//! and with `:sync!` entries only the destination has are to be deleted. The
+//! panel lists the plan; y or Enter runs it, Esc or q drops it. Replaced and
+//! deleted entries go to the trash and `u` undoes the whole sync.
+//!
+//! ```text
+//! :sync /media/usb/photos     copy what's new or changed here
+//! :sync! ~/backup/project     ...and delete what's gone here
+//! ```
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use crate::bookmarks::expand_home;
+use crate::command::{Command, RunCommand};
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::jobs::JobSummary;
+use crate::oplog::OperationLog;
+use crate::ops::{self, SyncPlan, TransferReport};
+use crate::{
+    CurrentDirectory, StatusMessage, UiElement, VimMode, DIFF_ADDED, DIFF_REMOVED, FELIPE_ORANGE,
+    FELIPE_ORANGE_DIM,
+};
+
+/// Planned actions listed in the panel per kind
+const PANEL_ROWS: usize = 15;
+
+enum TaskResult {
+    Planned(Result<SyncPlan, String>),
+    Synced {
+        label: String,
+        report: TransferReport,
+    },
+}
+
+/// Planning walks both trees and the sync copies them; both run on a worker
+#[derive(Resource, Default)]
+struct SyncTask {
+    worker: Option<JoinHandle<TaskResult>>,
+}
+
+/// The sync asked for and, once planned, what it would do
+#[derive(Resource, Default)]
+struct SyncPreview {
+    source: PathBuf,
+    dest: PathBuf,
+    delete: bool,
+    plan: Option<SyncPlan>,
+}
+
+/// Marker for the sync preview panel
+#[derive(Component)]
+struct SyncPanel;
+
+/// Marker for the sync preview panel text
+#[derive(Component)]
+struct SyncText;
+
+pub struct DirSyncPlugin;
+
+impl Plugin for DirSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SyncTask::default())
+            .insert_resource(SyncPreview::default())
+            .add_systems(
+                Update,
+                (
+                    handle_sync_command,
+                    handle_preview_keys,
+                    finish_sync_task,
+                    update_sync_panel,
+                ),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_sync_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut task: ResMut<SyncTask>,
+    mut preview: ResMut<SyncPreview>,
+    current_dir: Res<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Sync { dest, delete } = command else {
+            continue;
+        };
+        if task.worker.is_some() {
+            status.0 = "A sync is already running".to_string();
+            continue;
+        }
+        let source = current_dir.path.clone();
+        let dest = source.join(expand_home(dest));
+        status.0 = format!("Planning sync to {}...", dest.display());
+        *preview = SyncPreview {
+            source: source.clone(),
+            dest: dest.clone(),
+            delete: *delete,
+            plan: None,
+        };
+        let delete = *delete;
+        task.worker = Some(std::thread::spawn(move || {
+            TaskResult::Planned(
+                ops::plan_sync(&source, &dest, delete).map_err(|err| err.to_string()),
+            )
+        }));
+    }
+}
+
+/// y / Enter runs the previewed sync; Esc or q drops it
+fn handle_preview_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut task: ResMut<SyncTask>,
+    mut preview: ResMut<SyncPreview>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<SyncPanel>>,
+) {
+    if dismissed.read().any(|Dismiss(panel)| *panel == Panel::Sync) {
+        preview.plan = None;
+        status.0 = "Sync cancelled".to_string();
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    if !focus.has_focus(Panel::Sync) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    if !keyboard.just_pressed(KeyCode::KeyY) && !keyboard.just_pressed(KeyCode::Enter) {
+        return;
+    }
+    close_panel(&mut commands, &mut focus, &panel_query);
+    let Some(plan) = preview.plan.take() else {
+        return;
+    };
+    if task.worker.is_some() {
+        status.0 = "A sync is already running".to_string();
+        return;
+    }
+    let label = format!(
+        "sync {} to {}",
+        file_name(&preview.source),
+        preview.dest.display()
+    );
+    status.0 = format!("Running: {}", label);
+    task.worker = Some(std::thread::spawn(move || TaskResult::Synced {
+        label,
+        report: ops::execute_sync(&plan),
+    }));
+}
+
+fn finish_sync_task(
+    mut commands: Commands,
+    mut task: ResMut<SyncTask>,
+    mut preview: ResMut<SyncPreview>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut oplog: ResMut<OperationLog>,
+    mut summary: ResMut<JobSummary>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+) {
+    if !task
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        return;
+    }
+    let worker = task.worker.take().expect("checked above");
+    let Ok(result) = worker.join() else {
+        status.0 = "Sync worker crashed".to_string();
+        return;
+    };
+
+    match result {
+        TaskResult::Planned(Err(err)) => status.0 = format!("Cannot sync: {}", err),
+        TaskResult::Planned(Ok(plan)) => {
+            if plan.copies.is_empty() && plan.deletions.is_empty() {
+                status.0 = format!("{} is already in sync", preview.dest.display());
+                return;
+            }
+            status.0 = format!(
+                "Sync plan: {} to copy, {} to delete - y:run  Esc:cancel",
+                plan.copies.len(),
+                plan.deletions.len()
+            );
+            preview.plan = Some(plan);
+            if !focus.is_open(Panel::Sync) {
+                spawn_panel(&mut commands);
+            }
+            focus.open(Panel::Sync);
+        }
+        TaskResult::Synced { label, report } => {
+            let failed: Vec<(PathBuf, String)> = report
+                .failed
+                .into_iter()
+                .map(|(path, err)| (path, err.to_string()))
+                .collect();
+            status.0 = match failed.first() {
+                None => format!("{}: {} done", label, report.done),
+                Some((path, reason)) => format!(
+                    "{}: {} done, {} failed ({}: {})",
+                    label,
+                    report.done,
+                    failed.len(),
+                    path.display(),
+                    reason
+                ),
+            };
+            let failures = failed
+                .iter()
+                .map(|(path, reason)| format!("{}: {}", path.display(), reason))
+                .collect();
+            oplog.record_outcome(label, report.steps, failures);
+            summary.add("synced", report.done, failed);
+            current_dir.keep_selection();
+        }
+    }
+}
+
+fn update_sync_panel(preview: Res<SyncPreview>, mut text_query: Query<&mut Text, With<SyncText>>) {
+    let Some(plan) = &preview.plan else {
+        return;
+    };
+
+    let mut sections = vec![
+        TextSection::new(
+            "SYNC PREVIEW  y/Enter:run  Esc:cancel\n",
+            panel_style(FELIPE_ORANGE),
+        ),
+        TextSection::new(
+            format!(
+                "{}\n-> {}{}\n",
+                preview.source.display(),
+                preview.dest.display(),
+                if preview.delete {
+                    " (deleting extraneous)"
+                } else {
+                    ""
+                }
+            ),
+            panel_style(FELIPE_ORANGE_DIM),
+        ),
+    ];
+    let copies: Vec<&Path> = plan
+        .copies
+        .iter()
+        .map(|(_, target)| target.as_path())
+        .collect();
+    let deletions: Vec<&Path> = plan.deletions.iter().map(PathBuf::as_path).collect();
+    for (verb, paths, color) in [
+        ("copy", copies, DIFF_ADDED),
+        ("delete", deletions, DIFF_REMOVED),
+    ] {
+        for path in paths.iter().take(PANEL_ROWS) {
+            let shown = path.strip_prefix(&preview.dest).unwrap_or(path);
+            sections.push(TextSection::new(
+                format!("{:>6} {}\n", verb, shown.display()),
+                panel_style(color),
+            ));
+        }
+        if paths.len() > PANEL_ROWS {
+            sections.push(TextSection::new(
+                format!("{:>6} ... {} more\n", "", paths.len() - PANEL_ROWS),
+                panel_style(color),
+            ));
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(620.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            SyncPanel,
+            Focusable(Panel::Sync),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), SyncText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<SyncPanel>>,
+) {
+    focus.close(Panel::Sync);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,281 @@
+//! Directory weight - heavy folders look heavy before you go in
+//!
+//! A directory's book gets thicker with the size of everything below it,
+//! `DEPTH_PER_GB` per gigabyte up to nearly the gap to the next row, and a
+//! faint grid on its top gets finer the more entries it holds directly.
+//!
+//! A worker counts the entries of each subdirectory of the listing, then
+//! walks the ones whose size it doesn't know yet, one at a time, and the books
+//! thicken as the sizes come in. Sizes are kept in the metadata store
+//! (metadata.rs), so a directory seen before shows its weight at once; a size
+//! is measured again once the directory changed since, or after a day. Only
+//! local directories are weighed.
+
+use bevy::prelude::*;
+use ignore::WalkBuilder;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{
+    debug, metadata, CurrentDirectory, FileEntity, DEPTH_PER_GB, FELIPE_ORANGE, ITEM_SPACING,
+};
+
+/// Depth of the entry cuboid (see `EntryPalette::cuboid`)
+const BOOK_DEPTH: f32 = 0.3;
+/// Thickest a directory gets, leaving a gap to the next row
+const MAX_DEPTH: f32 = ITEM_SPACING - 0.4;
+/// Age after which a stored size is measured again
+const REMEASURE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+/// Most lines each way of the grid on top
+const MAX_GRID_LINES: u32 = 8;
+
+/// What's known of one directory so far
+#[derive(Clone, Copy, Default)]
+struct Weight {
+    /// Size of everything below it
+    bytes: Option<u64>,
+    /// Entries right in it
+    children: Option<usize>,
+}
+
+/// A measurement the worker made
+enum Measured {
+    Children(PathBuf, usize),
+    Bytes(PathBuf, u64),
+}
+
+/// The worker weighing the subdirectories of one listing
+struct Worker {
+    cancel: Arc<AtomicBool>,
+    results: Arc<Mutex<Vec<Measured>>>,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Resource, Default)]
+struct DirWeights {
+    /// Directory the weights are of
+    dir: PathBuf,
+    weights: HashMap<PathBuf, Weight>,
+    worker: Option<Worker>,
+}
+
+impl DirWeights {
+    /// Start over for the subdirectories `dirs` of `dir`
+    fn weigh(&mut self, dir: PathBuf, dirs: Vec<PathBuf>) {
+        if let Some(worker) = self.worker.take() {
+            worker.cancel.store(true, Ordering::Relaxed);
+        }
+        self.dir = dir;
+        self.weights.clear();
+        let mut stale = Vec::new();
+        for path in &dirs {
+            let modified = std::fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok();
+            let stored = metadata::dir_size(path);
+            if let Some((bytes, _)) = stored {
+                self.weights.entry(path.clone()).or_default().bytes = Some(bytes);
+            }
+            let fresh = stored.is_some_and(|(_, measured)| {
+                modified.is_none_or(|modified| modified <= measured)
+                    && measured.elapsed().is_ok_and(|age| age < REMEASURE_AFTER)
+            });
+            if !fresh {
+                stale.push(path.clone());
+            }
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let handle = {
+            let cancel = cancel.clone();
+            let results = results.clone();
+            std::thread::spawn(move || {
+                debug::timed("dir sizes", || measure(dirs, stale, &cancel, &results))
+            })
+        };
+        self.worker = Some(Worker {
+            cancel,
+            results,
+            handle,
+        });
+    }
+}
+
+/// Count the entries of every directory of `dirs`, then the bytes below the
+/// `stale` ones, storing those
+fn measure(
+    dirs: Vec<PathBuf>,
+    stale: Vec<PathBuf>,
+    cancel: &AtomicBool,
+    results: &Mutex<Vec<Measured>>,
+) {
+    let send = |measured| {
+        results
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(measured)
+    };
+    for dir in dirs {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            send(Measured::Children(dir, entries.count()));
+        }
+    }
+    for dir in stale {
+        let mut bytes = 0;
+        // Everything counts, hidden and ignored files too; links aren't followed
+        for entry in WalkBuilder::new(&dir).standard_filters(false).build() {
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            let Ok(entry) = entry else {
+                continue;
+            };
+            if entry.file_type().is_some_and(|kind| kind.is_file()) {
+                bytes += entry.metadata().map_or(0, |meta| meta.len());
+            }
+        }
+        if let Err(err) = metadata::cache_dir_size(&dir, bytes) {
+            warn!("Cannot store the size of {}: {}", dir.display(), err);
+        }
+        send(Measured::Bytes(dir, bytes));
+    }
+}
+
+pub struct DirWeightPlugin;
+
+impl Plugin for DirWeightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DirWeights>().add_systems(
+            Update,
+            (
+                follow_directory.after(crate::load_directory),
+                collect_weights,
+                apply_depth,
+                draw_child_grids,
+            )
+                .chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Weigh the subdirectories of each new listing
+fn follow_directory(current_dir: Res<CurrentDirectory>, mut weights: ResMut<DirWeights>) {
+    if !current_dir.is_changed() || current_dir.path == weights.dir {
+        return;
+    }
+    // Views and remote locations have nothing on this disk to walk
+    let dirs = if current_dir.path().is_dir() {
+        current_dir
+            .entries
+            .iter()
+            .filter(|entry| entry.is_dir && entry.name != "..")
+            .map(|entry| entry.path.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    weights.weigh(current_dir.path.clone(), dirs);
+}
+
+fn collect_weights(mut weights: ResMut<DirWeights>) {
+    let Some(worker) = &weights.worker else {
+        return;
+    };
+    let finished = worker.handle.is_finished();
+    let measured = std::mem::take(
+        &mut *worker
+            .results
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    // Only what comes in changes the weights, so books aren't rescaled each frame
+    for measured in measured {
+        match measured {
+            Measured::Children(path, children) => {
+                weights.weights.entry(path).or_default().children = Some(children);
+            }
+            Measured::Bytes(path, bytes) => {
+                weights.weights.entry(path).or_default().bytes = Some(bytes);
+            }
+        }
+    }
+    if finished {
+        weights.worker = None;
+    }
+}
+
+/// Thicken the books of weighed directories, new ones as they're spawned
+fn apply_depth(
+    weights: Res<DirWeights>,
+    current_dir: Res<CurrentDirectory>,
+    mut entity_query: Query<(&FileEntity, &mut Transform)>,
+    new_entities: Query<(), Added<FileEntity>>,
+) {
+    if !weights.is_changed() && new_entities.is_empty() {
+        return;
+    }
+    for (file_entity, mut transform) in entity_query.iter_mut() {
+        let Some(bytes) = current_dir
+            .entries
+            .get(file_entity.index)
+            .and_then(|entry| weights.weights.get(&entry.path))
+            .and_then(|weight| weight.bytes)
+        else {
+            continue;
+        };
+        let gigabytes = bytes as f32 / (1024.0 * 1024.0 * 1024.0);
+        let depth = (BOOK_DEPTH + gigabytes * DEPTH_PER_GB).min(MAX_DEPTH);
+        let scale = depth / BOOK_DEPTH;
+        if transform.scale.z != scale {
+            transform.scale.z = scale;
+        }
+    }
+}
+
+/// A grid on top of each directory, a line more for every doubling of its
+/// entries
+fn draw_child_grids(
+    mut gizmos: Gizmos,
+    weights: Res<DirWeights>,
+    current_dir: Res<CurrentDirectory>,
+    entity_query: Query<(&FileEntity, &GlobalTransform)>,
+) {
+    if weights.weights.is_empty() {
+        return;
+    }
+    let color = FELIPE_ORANGE.with_alpha(0.35);
+    for (file_entity, global) in entity_query.iter() {
+        let Some(children) = current_dir
+            .entries
+            .get(file_entity.index)
+            .and_then(|entry| weights.weights.get(&entry.path))
+            .and_then(|weight| weight.children)
+            .filter(|children| *children > 0)
+        else {
+            continue;
+        };
+        let lines = (usize::BITS - children.leading_zeros()).min(MAX_GRID_LINES);
+        // The top face of the unit cuboid, just above it
+        let (x, z) = (0.4, BOOK_DEPTH / 2.0);
+        let corner = |u: f32, v: f32| {
+            global.transform_point(Vec3::new(x * (2.0 * u - 1.0), 0.5, z * (2.0 * v - 1.0)))
+                + Vec3::Y * 0.01
+        };
+        for i in 1..=lines {
+            let t = i as f32 / (lines + 1) as f32;
+            gizmos.line(corner(t, 0.0), corner(t, 1.0), color);
+            gizmos.line(corner(0.0, t), corner(1.0, t), color);
+        }
+    }
+}
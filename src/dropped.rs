@@ -0,0 +1,71 @@
+//! Dropped files - files dragged in from Explorer, Finder and other apps
+//!
+//! Files dropped on the window are copied into the current directory, or
+//! moved with Shift held, through the job queue like a paste (`p`). While
+//! they hover over the window the status line says where they'll go.
+
+use bevy::prelude::*;
+use bevy::window::FileDragAndDrop;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::jobs::JobQueue;
+use crate::ops::{self, TransferKind};
+use crate::{CurrentDirectory, Prompt, StatusMessage};
+
+pub struct DroppedPlugin;
+
+impl Plugin for DroppedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, handle_dropped_files);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_dropped_files(
+    mut drops: EventReader<FileDragAndDrop>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mut prompt: ResMut<Prompt>,
+    mut jobs: ResMut<JobQueue>,
+    mut status: ResMut<StatusMessage>,
+) {
+    // Every file of a drop comes as an event of its own
+    let mut dropped: Vec<PathBuf> = Vec::new();
+    for event in drops.read() {
+        match event {
+            FileDragAndDrop::DroppedFile { path_buf, .. } => dropped.push(path_buf.clone()),
+            FileDragAndDrop::HoveredFile { .. } => {
+                status.0 = format!(
+                    "Drop to copy into {} (Shift moves)",
+                    current_dir.path().display()
+                );
+            }
+            FileDragAndDrop::HoveredFileCanceled { .. } => status.0.clear(),
+        }
+    }
+    if dropped.is_empty() {
+        return;
+    }
+    if prompt.pending.is_some() {
+        status.0 = "Answer the question first, then drop again".to_string();
+        return;
+    }
+    let kind = if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        TransferKind::Move
+    } else {
+        TransferKind::Copy
+    };
+    // Dropped back where they are, there's nothing to do
+    dropped.retain(|path| path.parent() != Some(current_dir.path()));
+    if dropped.is_empty() {
+        status.0 = "Already here".to_string();
+        return;
+    }
+    let plan = ops::plan_transfer(kind, &dropped, current_dir.path());
+    crate::offer_transfer(plan, &config, &mut prompt, &mut jobs);
+}
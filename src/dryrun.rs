@@ -0,0 +1,307 @@
+//! Dry-run mode - `:set dryrun` reviews operations before they touch the disk
+//!
+//! While it's on, pastes (copies, moves and links), renames and the choices
+//! made in `:conflicts` are planned instead of run. The review panel lists
+//! every step each of them would take - what goes where, what it replaces,
+//! what it leaves out and why. y or Enter runs them all for real: renames and
+//! trashing right away, transfers on the job queue in the order they were
+//! asked for. Esc drops them. `:sync` shows its plan first anyway, and scripts
+//! don't start in dry-run mode since they change files as they go.
+//!
+//! `[operations] dry_run = true` in the config starts in dry-run mode.
+
+use bevy::prelude::*;
+
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::jobs::JobQueue;
+use crate::oplog::OperationLog;
+use crate::ops::{Conflict, TransferKind, TransferPlan};
+use crate::{
+    CurrentDirectory, StatusMessage, UiElement, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+/// Step lines listed in the panel before the rest are summed up
+const PANEL_ROWS: usize = 20;
+
+/// Runs a confirmed operation on the main thread; returns what to tell the user
+type Deferred = Box<dyn FnOnce(&mut OperationLog, &mut CurrentDirectory) -> String + Send + Sync>;
+
+enum Run {
+    /// Goes on the job queue, which then runs it without asking again
+    Transfer(TransferPlan),
+    Now(Deferred),
+}
+
+/// An operation held back for review
+struct Planned {
+    label: String,
+    /// What it would do, one line per step
+    lines: Vec<String>,
+    run: Run,
+}
+
+/// Operations planned under `:set dryrun`, in the order they were asked for
+#[derive(Resource, Default)]
+pub struct DryRunReview {
+    planned: Vec<Planned>,
+}
+
+impl DryRunReview {
+    /// Hold back a transfer the job queue was about to start
+    pub fn plan_transfer(&mut self, label: String, plan: TransferPlan) {
+        self.planned.push(Planned {
+            label,
+            lines: describe_transfer(&plan),
+            run: Run::Transfer(plan),
+        });
+    }
+
+    /// Hold back an operation described by `lines`; `run` does it once confirmed
+    pub fn plan(
+        &mut self,
+        label: String,
+        lines: Vec<String>,
+        run: impl FnOnce(&mut OperationLog, &mut CurrentDirectory) -> String + Send + Sync + 'static,
+    ) {
+        self.planned.push(Planned {
+            label,
+            lines,
+            run: Run::Now(Box::new(run)),
+        });
+    }
+}
+
+/// Marker for the review panel
+#[derive(Component)]
+struct ReviewPanel;
+
+/// Marker for the review panel text
+#[derive(Component)]
+struct ReviewText;
+
+pub struct DryRunPlugin;
+
+impl Plugin for DryRunPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DryRunReview::default()).add_systems(
+            Update,
+            (open_review, handle_review_keys, update_review_panel).chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Bring the review up whenever something new is planned
+fn open_review(
+    mut commands: Commands,
+    review: Res<DryRunReview>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+) {
+    if !review.is_changed() || review.planned.is_empty() {
+        return;
+    }
+    let steps: usize = review
+        .planned
+        .iter()
+        .map(|planned| planned.lines.len())
+        .sum();
+    status.0 = format!(
+        "Dry run: {} operations, {} steps planned - y:run  Esc:drop",
+        review.planned.len(),
+        steps
+    );
+    if !focus.is_open(Panel::DryRun) {
+        spawn_panel(&mut commands);
+    }
+    focus.open(Panel::DryRun);
+}
+
+/// y / Enter runs everything planned; Esc drops it
+fn handle_review_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut review: ResMut<DryRunReview>,
+    mut jobs: ResMut<JobQueue>,
+    mut oplog: ResMut<OperationLog>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<ReviewPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::DryRun)
+    {
+        let dropped = std::mem::take(&mut review.planned).len();
+        status.0 = format!("Dry run: dropped {} operations", dropped);
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    // Enter ending a rename plans it; the same press mustn't run it too
+    if review.is_changed() || !focus.has_focus(Panel::DryRun) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    if !keyboard.just_pressed(KeyCode::KeyY) && !keyboard.just_pressed(KeyCode::Enter) {
+        return;
+    }
+    close_panel(&mut commands, &mut focus, &panel_query);
+
+    let mut messages = Vec::new();
+    let mut queued = 0;
+    for planned in std::mem::take(&mut review.planned) {
+        match planned.run {
+            Run::Transfer(plan) => {
+                jobs.push_reviewed(planned.label, plan);
+                queued += 1;
+            }
+            Run::Now(run) => messages.push(run(&mut oplog, &mut current_dir)),
+        }
+    }
+    if queued > 0 {
+        messages.push(format!("{} transfers queued", queued));
+    }
+    status.0 = messages.join("; ");
+}
+
+fn update_review_panel(
+    review: Res<DryRunReview>,
+    mut text_query: Query<&mut Text, With<ReviewText>>,
+) {
+    if review.planned.is_empty() {
+        return;
+    }
+
+    let mut sections = vec![TextSection::new(
+        "DRY RUN  y/Enter:run  Esc:drop\n",
+        panel_style(FELIPE_ORANGE),
+    )];
+    let mut rows = 0;
+    let mut hidden = 0;
+    for planned in &review.planned {
+        if rows >= PANEL_ROWS {
+            hidden += planned.lines.len();
+            continue;
+        }
+        sections.push(TextSection::new(
+            format!("{}\n", planned.label),
+            panel_style(FELIPE_ORANGE),
+        ));
+        for line in &planned.lines {
+            if rows >= PANEL_ROWS {
+                hidden += 1;
+                continue;
+            }
+            rows += 1;
+            sections.push(TextSection::new(
+                format!("  {}\n", line),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+        }
+    }
+    if hidden > 0 {
+        sections.push(TextSection::new(
+            format!("  ... {} more steps\n", hidden),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// One line per step of `plan`, then the sources it leaves out
+fn describe_transfer(plan: &TransferPlan) -> Vec<String> {
+    let verb = match plan.kind {
+        TransferKind::Copy => "copy",
+        TransferKind::Move => "move",
+        TransferKind::Symlink => "link",
+        TransferKind::Hardlink => "hard-link",
+        TransferKind::Trash => "trash",
+    };
+    let mut lines: Vec<String> = plan
+        .steps
+        .iter()
+        .map(|step| {
+            let note = match &step.conflict {
+                Some(Conflict::CaseCollision { existing }) => {
+                    format!(" (clashes with {} in case only)", existing)
+                }
+                None if step.source != step.target
+                    && std::fs::symlink_metadata(&step.target).is_ok() =>
+                {
+                    " (replacing it)".to_string()
+                }
+                None => String::new(),
+            };
+            format!(
+                "{} {} -> {}{}",
+                verb,
+                step.source.display(),
+                step.target.display(),
+                note
+            )
+        })
+        .collect();
+    lines.extend(
+        plan.skipped
+            .iter()
+            .map(|(path, reason)| format!("skip {} ({})", path.display(), reason)),
+    );
+    lines
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(620.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            ReviewPanel,
+            Focusable(Panel::DryRun),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), ReviewText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<ReviewPanel>>,
+) {
+    focus.close(Panel::DryRun);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
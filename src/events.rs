@@ -0,0 +1,133 @@
+//! `--events-json` - a machine-readable stream of what Felipe is doing
+//!
+//! Every event is one JSON object per line on stdout (Bevy logs to stderr), e.g.
+//! `{"event":"navigate","path":"/home/me","entries":12,"time_ms":...}`, so status
+//! bars, loggers and test harnesses can follow a running instance.
+
+use bevy::prelude::*;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::hooks::{DirectoryLoaded, EntrySelected};
+use crate::VimMode;
+
+/// Whether events are written; set from the command line
+#[derive(Resource, Default)]
+pub struct EventStream {
+    pub enabled: bool,
+}
+
+/// An event for the stream: `{"event": name, ...fields}`
+#[derive(Event)]
+pub struct StreamEvent(pub Value);
+
+impl StreamEvent {
+    /// `fields` must be a JSON object; `event` and `time_ms` are added to it
+    pub fn new(name: &str, fields: Value) -> Self {
+        let mut object = match fields {
+            Value::Object(object) => object,
+            _ => serde_json::Map::new(),
+        };
+        object.insert("event".to_string(), json!(name));
+        Self(Value::Object(object))
+    }
+}
+
+pub struct EventsPlugin;
+
+impl Plugin for EventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventStream>()
+            .add_event::<StreamEvent>()
+            .add_systems(
+                Update,
+                (watch_navigation, watch_mode, write_events)
+                    .chain()
+                    .after(crate::hooks::watch_selection),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Directory changes and selection moves
+fn watch_navigation(
+    stream: Res<EventStream>,
+    mut loaded: EventReader<DirectoryLoaded>,
+    mut selected: EventReader<EntrySelected>,
+    mut shown_dir: Local<Option<PathBuf>>,
+    mut events: EventWriter<StreamEvent>,
+) {
+    if !stream.enabled {
+        loaded.clear();
+        selected.clear();
+        return;
+    }
+    // Reloads of the same directory aren't navigation
+    for loaded in loaded.read() {
+        if shown_dir.as_ref() != Some(&loaded.path) {
+            *shown_dir = Some(loaded.path.clone());
+            events.send(StreamEvent::new(
+                "navigate",
+                json!({
+                    "path": loaded.path,
+                    "entries": loaded.entries,
+                }),
+            ));
+        }
+    }
+    for selected in selected.read() {
+        events.send(StreamEvent::new(
+            "select",
+            json!({
+                "path": selected.path,
+                "index": selected.index,
+                "is_dir": selected.is_dir,
+                "size": selected.size,
+            }),
+        ));
+    }
+}
+
+fn watch_mode(
+    stream: Res<EventStream>,
+    vim_mode: Res<VimMode>,
+    mut events: EventWriter<StreamEvent>,
+) {
+    if !stream.enabled || !vim_mode.is_changed() {
+        return;
+    }
+    let mode = match *vim_mode {
+        VimMode::Normal => "normal",
+        VimMode::Visual => "visual",
+        VimMode::Command => "command",
+        VimMode::Rename => "rename",
+        VimMode::Filter => "filter",
+        VimMode::Jump => "find",
+    };
+    events.send(StreamEvent::new("mode", json!({ "mode": mode })));
+}
+
+fn write_events(stream: Res<EventStream>, mut events: EventReader<StreamEvent>) {
+    if !stream.enabled {
+        events.clear();
+        return;
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let mut stdout = std::io::stdout().lock();
+    for StreamEvent(event) in events.read() {
+        let mut event = event.clone();
+        if let Value::Object(object) = &mut event {
+            object.insert("time_ms".to_string(), json!(now));
+        }
+        // A closed pipe just means nobody is listening any more
+        let _ = writeln!(stdout, "{}", event);
+    }
+    let _ = stdout.flush();
+}
@@ -0,0 +1,251 @@
+//! Filtering - `f` narrows the listing to names containing the typed text
+//!
+//! The scene is rebuilt on every keystroke. Enter keeps the narrowed listing
+//! for browsing, Esc (while typing, or later in NORMAL mode) brings back the
+//! full one. Leaving the directory drops the filter.
+//!
+//! `:filter *.rs` (a glob) or `:filter /te?st/` (a regex) instead stays on in
+//! every directory until `:filter!`. It applies to files only, so directories
+//! remain to move around in. `:filter tag:red` keeps the files tagged red (see
+//! tags.rs).
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::command::{Command, RunCommand};
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::tags::{self, TagColor};
+use crate::{CurrentDirectory, FileEntry, Prompt, StatusMessage, VimMode};
+
+// =============================================================================
+// Patterns
+// =============================================================================
+
+/// A `:filter` pattern, kept with the text it was written as
+pub struct NamePattern {
+    pub text: String,
+    matcher: Matcher,
+}
+
+enum Matcher {
+    Glob(globset::GlobMatcher),
+    Regex(regex::Regex),
+    Tag(TagColor),
+}
+
+impl NamePattern {
+    /// `/.../` is a regex (found anywhere in the name), `tag:color` a color
+    /// tag, anything else a glob (matching the whole name)
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if let Some(color) = text.strip_prefix("tag:") {
+            return Ok(Self {
+                text: text.to_string(),
+                matcher: Matcher::Tag(color.parse()?),
+            });
+        }
+        let regex = text
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+            .filter(|inner| !inner.is_empty());
+        let matcher = match regex {
+            Some(inner) => Matcher::Regex(regex::Regex::new(inner).map_err(|e| e.to_string())?),
+            None => Matcher::Glob(
+                globset::Glob::new(text)
+                    .map_err(|e| e.to_string())?
+                    .compile_matcher(),
+            ),
+        };
+        Ok(Self {
+            text: text.to_string(),
+            matcher,
+        })
+    }
+
+    /// Whether the entry called `name` at `path` passes
+    pub fn matches(&self, name: &str, path: &Path) -> bool {
+        match &self.matcher {
+            Matcher::Glob(glob) => glob.is_match(name),
+            Matcher::Regex(regex) => regex.is_match(name),
+            Matcher::Tag(color) => tags::has_tag(path, *color),
+        }
+    }
+
+    /// Whether what passes depends on tags rather than names
+    pub fn is_tag(&self) -> bool {
+        matches!(self.matcher, Matcher::Tag(_))
+    }
+}
+
+/// Active filters; entries must pass both
+#[derive(Resource, Default)]
+pub struct Filter {
+    /// Text typed after `f`, matched anywhere in entry names
+    pub narrow: String,
+    /// `:filter` pattern for file names
+    pub pattern: Option<NamePattern>,
+}
+
+impl Filter {
+    /// `..` always stays so there's a way out
+    pub fn matches(&self, entry: &FileEntry) -> bool {
+        if entry.name == ".." {
+            return true;
+        }
+        let pattern_ok = entry.is_dir
+            || self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches(&entry.name, &entry.path));
+        pattern_ok && self.narrow_matches(&entry.name)
+    }
+
+    /// Case-insensitive unless the text has capitals, like vim's smartcase
+    fn narrow_matches(&self, name: &str) -> bool {
+        if self.narrow.chars().any(char::is_uppercase) {
+            name.contains(&self.narrow)
+        } else {
+            name.to_lowercase().contains(&self.narrow)
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.narrow.is_empty()
+    }
+
+    /// `:filter text`, or `:filter!` for None; a bad pattern keeps the old one
+    pub fn set_pattern(&mut self, text: Option<&str>) -> Result<(), String> {
+        self.pattern = match text {
+            None => None,
+            Some(text) => Some(
+                NamePattern::parse(text).map_err(|err| format!("Bad filter {}: {}", text, err))?,
+            ),
+        };
+        Ok(())
+    }
+}
+
+pub struct FilterPlugin;
+
+impl Plugin for FilterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Filter>().add_systems(
+            Update,
+            (
+                handle_filter_input.after(crate::handle_keyboard),
+                handle_filter_command,
+                clear_filter_on_leave.before(crate::load_directory),
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_filter_input(
+    mut key_events: EventReader<KeyboardInput>,
+    mut vim_mode: ResMut<VimMode>,
+    mut filter: ResMut<Filter>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed || prompt.pending.is_some() {
+            continue;
+        }
+
+        if *vim_mode == VimMode::Normal {
+            if focus.any_open() || fly.enabled {
+                continue;
+            }
+            match &event.logical_key {
+                Key::Character(c) if c == "f" => {
+                    *vim_mode = VimMode::Filter;
+                    if filter.is_active() {
+                        filter.narrow.clear();
+                        refilter(&mut current_dir);
+                    }
+                }
+                Key::Escape if filter.is_active() => {
+                    filter.narrow.clear();
+                    refilter(&mut current_dir);
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if *vim_mode != VimMode::Filter {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(c) => filter.narrow.push_str(c),
+            Key::Space => filter.narrow.push(' '),
+            Key::Backspace => {
+                // Backspace on empty text leaves filter mode, like the command line
+                if filter.narrow.pop().is_none() {
+                    *vim_mode = VimMode::Normal;
+                    continue;
+                }
+            }
+            Key::Escape => {
+                filter.narrow.clear();
+                *vim_mode = VimMode::Normal;
+            }
+            Key::Enter => {
+                *vim_mode = VimMode::Normal;
+                continue;
+            }
+            _ => continue,
+        }
+        refilter(&mut current_dir);
+    }
+}
+
+fn handle_filter_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut filter: ResMut<Filter>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Filter(text) = command else {
+            continue;
+        };
+        match filter.set_pattern(text.as_deref()) {
+            Ok(()) if text.is_none() => status.0 = "Filter cleared".to_string(),
+            Ok(()) => status.0.clear(),
+            Err(message) => {
+                status.0 = message;
+                continue;
+            }
+        }
+        refilter(&mut current_dir);
+    }
+}
+
+/// Narrowing belongs to the directory it was typed in
+fn clear_filter_on_leave(
+    current_dir: Res<CurrentDirectory>,
+    mut filter: ResMut<Filter>,
+    mut shown: Local<Option<PathBuf>>,
+) {
+    if shown.as_ref() == Some(&current_dir.path) {
+        return;
+    }
+    *shown = Some(current_dir.path.clone());
+    if filter.is_active() {
+        filter.narrow.clear();
+    }
+}
+
+/// Reload with the new filter, staying on the selected entry if it still matches
+fn refilter(current_dir: &mut CurrentDirectory) {
+    current_dir.keep_selection();
+}
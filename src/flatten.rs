@@ -0,0 +1,117 @@
+//! `:flatten` - every file below the current directory in one scene
+//!
+//! Labels show paths relative to the flattened directory, and the usual sort
+//! orders, visual selection and operations work across the whole subtree.
+//! `:flatten` again, or leaving the directory, goes back to the normal view.
+
+use bevy::prelude::*;
+use ignore::WalkState;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::command::{Command, RunCommand};
+use crate::config::ListingConfig;
+use crate::{gitignore, links, shapes};
+use crate::{CurrentDirectory, FileEntry, StatusMessage};
+
+/// Walks stop here so flattening `/` can't run forever
+pub const MAX_FLAT_ENTRIES: usize = 100_000;
+
+pub struct FlattenPlugin;
+
+impl Plugin for FlattenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, handle_flatten_command);
+    }
+}
+
+fn handle_flatten_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Flatten {
+            continue;
+        }
+        if !toggle_flat_view(&mut current_dir) {
+            status.0 = "Flat view off".to_string();
+        }
+    }
+}
+
+/// Flatten the current directory, or show it normally again; whether it's
+/// flat now
+pub fn toggle_flat_view(current_dir: &mut CurrentDirectory) -> bool {
+    let flat = current_dir.flat_root.as_ref() == Some(&current_dir.path);
+    current_dir.flat_root = if flat {
+        None
+    } else {
+        Some(current_dir.path.clone())
+    };
+    // Stay on the selected entry if the other view has it too
+    current_dir.keep_selection();
+    !flat
+}
+
+// =============================================================================
+// Walker
+// =============================================================================
+
+/// Every file below `root` that `listing` shows, named by its path relative to
+/// `root`; the flag is set when the walk stopped at `MAX_FLAT_ENTRIES`.
+/// Directories are read on all cores, and symlinks to directories aren't
+/// followed.
+pub fn walk_files(root: &Path, listing: &ListingConfig) -> (Vec<FileEntry>, bool) {
+    let found = Mutex::new(Vec::new());
+    let truncated = AtomicBool::new(false);
+
+    gitignore::walker(root, listing).build_parallel().run(|| {
+        Box::new(|result| {
+            let Ok(entry) = result else {
+                return WalkState::Continue;
+            };
+            if entry.file_type().is_none_or(|t| t.is_dir()) {
+                return WalkState::Continue;
+            }
+            // Symlinks count as files when they point at one
+            let path = entry.path();
+            let Ok(metadata) = std::fs::metadata(path) else {
+                return WalkState::Continue;
+            };
+            if metadata.is_dir() {
+                return WalkState::Continue;
+            }
+            let mut found = found.lock().unwrap_or_else(|e| e.into_inner());
+            if found.len() >= MAX_FLAT_ENTRIES {
+                truncated.store(true, Ordering::Relaxed);
+                return WalkState::Quit;
+            }
+            found.push(FileEntry {
+                name: path
+                    .strip_prefix(root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .to_string(),
+                path: path.to_path_buf(),
+                is_dir: false,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+                locked: false,
+                link: entry
+                    .path_is_symlink()
+                    .then(|| links::read_link(path))
+                    .flatten(),
+                inode: (!entry.path_is_symlink())
+                    .then(|| links::hardlink_inode(&metadata))
+                    .flatten(),
+                executable: shapes::is_executable(&metadata),
+            });
+            WalkState::Continue
+        })
+    });
+
+    let files = found.into_inner().unwrap_or_else(|e| e.into_inner());
+    (files, truncated.load(Ordering::Relaxed))
+}
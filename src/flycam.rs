@@ -0,0 +1,182 @@
+//! First-person fly-through - walk between the file towers, TRON-style
+//!
+//! `:fly` toggles it. WASD moves, the mouse looks around, and whichever entry
+//! is closest in front of you becomes the selection.
+
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+
+use crate::command::{Command, RunCommand};
+use crate::hooks::EntryOpened;
+use crate::{
+    open_selected, update_camera_target, CameraState, CurrentDirectory, Layouts, MainCamera,
+    StatusMessage,
+};
+
+/// Walking speed in world units per second (doubled while Shift is held)
+const FLY_SPEED: f32 = 8.0;
+/// Radians of look per pixel of mouse motion
+const LOOK_SPEED: f32 = 0.003;
+/// Camera height above the grid
+const EYE_HEIGHT: f32 = 1.6;
+/// Entries closer than this (on the ground plane) get auto-selected
+const SELECT_RADIUS: f32 = 3.0;
+
+/// Free-fly camera state; while enabled it owns the camera and the keyboard
+#[derive(Resource, Default)]
+pub struct FlyCamera {
+    pub enabled: bool,
+    position: Vec3,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl FlyCamera {
+    fn rotation(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0)
+    }
+}
+
+pub struct FlyCameraPlugin;
+
+impl Plugin for FlyCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FlyCamera::default()).add_systems(
+            Update,
+            (
+                handle_fly_command,
+                fly_camera.after(handle_fly_command),
+                select_nearest_entry.after(fly_camera),
+            ),
+        );
+    }
+}
+
+fn handle_fly_command(
+    mut run_commands: EventReader<RunCommand>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut fly: ResMut<FlyCamera>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    current_dir: Res<CurrentDirectory>,
+    layouts: Res<Layouts>,
+    mut camera_state: ResMut<CameraState>,
+    mut status: ResMut<StatusMessage>,
+) {
+    let toggled = run_commands
+        .read()
+        .filter(|RunCommand(command)| *command == Command::Fly)
+        .count()
+        % 2
+        == 1;
+    let escaped = fly.enabled && keyboard.just_pressed(KeyCode::Escape);
+    if !toggled && !escaped {
+        return;
+    }
+
+    fly.enabled = !fly.enabled;
+    if fly.enabled {
+        // Start on the ground just behind where the overview camera was looking
+        let start = camera_query
+            .get_single()
+            .map(|t| t.translation)
+            .unwrap_or(Vec3::ZERO);
+        fly.position = Vec3::new(start.x, EYE_HEIGHT, start.z.max(-10.0));
+        fly.yaw = std::f32::consts::PI;
+        fly.pitch = 0.0;
+        status.0 = "FLY  WASD:move  mouse:look  Shift:run  Enter:open  Esc:exit".to_string();
+    } else {
+        update_camera_target(&current_dir, &layouts, &mut camera_state);
+        status.0.clear();
+    }
+
+    if let Ok(mut window) = window_query.get_single_mut() {
+        window.cursor.grab_mode = if fly.enabled {
+            CursorGrabMode::Locked
+        } else {
+            CursorGrabMode::None
+        };
+        window.cursor.visible = !fly.enabled;
+    }
+}
+
+fn fly_camera(
+    time: Res<Time>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut motion_events: EventReader<MouseMotion>,
+    mut fly: ResMut<FlyCamera>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    mut opened: EventWriter<EntryOpened>,
+) {
+    if !fly.enabled {
+        motion_events.clear();
+        return;
+    }
+
+    let delta: Vec2 = motion_events.read().map(|event| event.delta).sum();
+    fly.yaw -= delta.x * LOOK_SPEED;
+    fly.pitch = (fly.pitch - delta.y * LOOK_SPEED).clamp(-1.4, 1.4);
+
+    // Movement stays on the ground plane regardless of pitch
+    let forward = Quat::from_rotation_y(fly.yaw) * Vec3::NEG_Z;
+    let right = Quat::from_rotation_y(fly.yaw) * Vec3::X;
+    let mut direction = Vec3::ZERO;
+    if keyboard.pressed(KeyCode::KeyW) {
+        direction += forward;
+    }
+    if keyboard.pressed(KeyCode::KeyS) {
+        direction -= forward;
+    }
+    if keyboard.pressed(KeyCode::KeyD) {
+        direction += right;
+    }
+    if keyboard.pressed(KeyCode::KeyA) {
+        direction -= right;
+    }
+    let speed = if keyboard.pressed(KeyCode::ShiftLeft) {
+        FLY_SPEED * 2.0
+    } else {
+        FLY_SPEED
+    };
+    fly.position += direction.normalize_or_zero() * speed * time.delta_seconds();
+
+    if keyboard.just_pressed(KeyCode::Enter) {
+        if let Err(message) = open_selected(&mut current_dir, &mut opened) {
+            status.0 = message;
+        }
+    }
+    // Entering a directory drops us at the start of its first row
+    if current_dir.needs_reload {
+        fly.position = Vec3::new(0.0, EYE_HEIGHT, -6.0);
+        fly.yaw = std::f32::consts::PI;
+    }
+
+    for mut transform in camera_query.iter_mut() {
+        transform.translation = fly.position;
+        transform.rotation = fly.rotation();
+    }
+}
+
+fn select_nearest_entry(
+    fly: Res<FlyCamera>,
+    layouts: Res<Layouts>,
+    mut current_dir: ResMut<CurrentDirectory>,
+) {
+    if !fly.enabled || current_dir.needs_reload {
+        return;
+    }
+    let here = fly.position.xz();
+    let count = current_dir.entries.len();
+    let nearest = (0..count)
+        .map(|i| (i, layouts.position(i, count).xz().distance(here)))
+        .filter(|(_, distance)| *distance < SELECT_RADIUS)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((index, _)) = nearest {
+        if current_dir.selected_index != index {
+            current_dir.selected_index = index;
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Keyboard focus for overlay panels
+//!
+//! Every panel that takes keys (oplog, job summary, history, conflicts, git
+//! log, properties, what's new, shell output, sync preview, volumes) opens
+//! and closes through `Focus`. While any is open the scene ignores
+//! the keyboard; keys go to the focused panel only. The same keys work on all
+//! of them: Tab / Shift-Tab move focus between open panels, Esc or q closes the
+//! focused one. The focused panel is drawn on top with a bright border.
+
+use bevy::prelude::*;
+
+use crate::{Prompt, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Panel {
+    Oplog,
+    Jobs,
+    History,
+    Conflicts,
+    GitLog,
+    Properties,
+    WhatsNew,
+    Shell,
+    Sync,
+    Volumes,
+    DryRun,
+    Stats,
+    Dashboard,
+    Tree,
+    Help,
+}
+
+/// Open panels in the order they opened, and which one has the keyboard
+#[derive(Resource, Default)]
+pub struct Focus {
+    open: Vec<Panel>,
+    focused: usize,
+}
+
+impl Focus {
+    /// Open `panel` (or bring it forward) and give it the keyboard
+    pub fn open(&mut self, panel: Panel) {
+        self.open.retain(|&p| p != panel);
+        self.open.push(panel);
+        self.focused = self.open.len() - 1;
+    }
+
+    /// Close `panel`; the most recently opened of the rest gets the keyboard
+    pub fn close(&mut self, panel: Panel) {
+        let before = self.open.len();
+        self.open.retain(|&p| p != panel);
+        if self.open.len() != before {
+            self.focused = self.open.len().saturating_sub(1);
+        }
+    }
+
+    pub fn is_open(&self, panel: Panel) -> bool {
+        self.open.contains(&panel)
+    }
+
+    /// `panel` should handle keys this frame
+    pub fn has_focus(&self, panel: Panel) -> bool {
+        self.open.get(self.focused) == Some(&panel)
+    }
+
+    /// Some panel captures the keyboard, so the scene shouldn't react to it
+    pub fn any_open(&self) -> bool {
+        !self.open.is_empty()
+    }
+
+    fn cycle(&mut self, forward: bool) {
+        let count = self.open.len();
+        if count > 0 {
+            self.focused = if forward {
+                (self.focused + 1) % count
+            } else {
+                (self.focused + count - 1) % count
+            };
+        }
+    }
+}
+
+/// Sent when Esc or q asks the focused panel to close; its module closes it
+#[derive(Event)]
+pub struct Dismiss(pub Panel);
+
+/// Root node of a panel's UI, styled by focus
+#[derive(Component)]
+pub struct Focusable(pub Panel);
+
+pub struct FocusPlugin;
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Focus>()
+            .add_event::<Dismiss>()
+            .add_systems(Update, (handle_focus_keys, style_focused_panel));
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_focus_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    mut focus: ResMut<Focus>,
+    mut dismiss: EventWriter<Dismiss>,
+) {
+    if !focus.any_open() || *vim_mode != VimMode::Normal || prompt.pending.is_some() {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Tab) {
+        let backward = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        focus.cycle(!backward);
+    }
+    if keyboard.just_pressed(KeyCode::Escape) || keyboard.just_pressed(KeyCode::KeyQ) {
+        if let Some(&panel) = focus.open.get(focus.focused) {
+            dismiss.send(Dismiss(panel));
+        }
+    }
+}
+
+fn style_focused_panel(
+    focus: Res<Focus>,
+    mut panels: Query<(Ref<Focusable>, &mut BorderColor, &mut ZIndex)>,
+) {
+    for (focusable, mut border, mut z_index) in panels.iter_mut() {
+        if !focus.is_changed() && !focusable.is_added() {
+            continue;
+        }
+        let focused = focus.has_focus(focusable.0);
+        border.0 = if focused {
+            FELIPE_ORANGE
+        } else {
+            FELIPE_ORANGE_DIM
+        };
+        // Panels share a corner of the screen; the focused one goes on top
+        *z_index = ZIndex::Global(if focused { 2 } else { 1 });
+    }
+}
@@ -0,0 +1,120 @@
+//! Font - one that can show the file names of every language
+//!
+//! Bevy's built-in font has Latin letters only, so Japanese, Chinese and
+//! Korean names come out as boxes. At startup the first font of this chain
+//! that loads replaces it everywhere, labels and panels alike:
+//!
+//! 1. the files under `[font]`, in order
+//! 2. a CJK font of the system (Noto Sans CJK, Droid Sans Fallback,
+//!    Hiragino, Apple SD Gothic, MS Gothic, Yu Gothic, Microsoft YaHei...),
+//!    unless `system = false`
+//! 3. the built-in font
+//!
+//! Text is drawn with a single font, so the chain picks one rather than
+//! filling in missing glyphs from the next; color emoji don't draw either
+//! way. Changes take effect on the next start.
+//!
+//! ```toml
+//! [font]
+//! paths = ["~/.local/share/fonts/PlemolJP-Regular.ttf"]
+//! system = true
+//! ```
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::bookmarks::expand_home;
+use crate::config::Config;
+
+/// Fonts with CJK glyphs where the systems install them, likeliest first
+#[cfg(target_os = "linux")]
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-sans-cjk-fonts/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/google-droid-sans-fonts/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+];
+#[cfg(target_os = "macos")]
+const SYSTEM_FONTS: &[&str] = &[
+    "/System/Library/Fonts/ヒラギノ角ゴシック W3.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/System/Library/Fonts/AppleSDGothicNeo.ttc",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+];
+#[cfg(target_os = "windows")]
+const SYSTEM_FONTS: &[&str] = &[
+    "C:\\Windows\\Fonts\\YuGothR.ttc",
+    "C:\\Windows\\Fonts\\meiryo.ttc",
+    "C:\\Windows\\Fonts\\msgothic.ttc",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\malgun.ttf",
+];
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+const SYSTEM_FONTS: &[&str] = &[];
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    /// Font files to try first; `~/x` is home-relative
+    pub paths: Vec<PathBuf>,
+    /// Then look for a CJK font of the system
+    pub system: bool,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            system: true,
+        }
+    }
+}
+
+pub struct FontPlugin;
+
+impl Plugin for FontPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, replace_default_font);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Put the first font of the chain under the default handle, before any text
+/// is laid out with the built-in one
+fn replace_default_font(config: Res<Config>, mut fonts: ResMut<Assets<Font>>) {
+    let configured = config
+        .font
+        .paths
+        .iter()
+        .map(|path| (expand_home(path), true));
+    let system = SYSTEM_FONTS
+        .iter()
+        .filter(|_| config.font.system)
+        .map(|path| (PathBuf::from(path), false));
+    for (path, configured) in configured.chain(system) {
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            // Most of the system list is missing on any one machine
+            Err(_) if !configured => continue,
+            Err(err) => {
+                warn!("Font {}: {}", path.display(), err);
+                continue;
+            }
+        };
+        match Font::try_from_bytes(bytes) {
+            Ok(font) => {
+                info!("Font: {}", path.display());
+                fonts.insert(&Handle::<Font>::default(), font);
+                return;
+            }
+            Err(err) => warn!("Font {} doesn't load: {}", path.display(), err),
+        }
+    }
+}
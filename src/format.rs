@@ -0,0 +1,146 @@
+//! How sizes and dates are written everywhere in the UI (`[format]` in the config)
+//!
+//! ```toml
+//! [format]
+//! size_units = "binary"        # binary (KiB, MiB) | si (kB, MB) | a fixed unit like "MB" or "B"
+//! size_decimals = 1
+//! thousands_separator = ","    # "" for none
+//! date_format = "%Y-%m-%d %H:%M"
+//! ```
+
+use serde::Deserialize;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Fixed units accepted by `size_units`, with their size in bytes
+const FIXED_UNITS: [(&str, f64); 11] = [
+    ("B", 1.0),
+    ("kB", 1e3),
+    ("KB", 1e3),
+    ("MB", 1e6),
+    ("GB", 1e9),
+    ("TB", 1e12),
+    ("KiB", 1024.0),
+    ("MiB", 1048576.0),
+    ("GiB", 1073741824.0),
+    ("TiB", 1099511627776.0),
+    ("bytes", 1.0),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeUnits {
+    /// Powers of 1024: KiB, MiB, ...
+    Binary,
+    /// Powers of 1000: kB, MB, ...
+    Si,
+    /// Always this unit, e.g. everything in MB
+    Fixed(&'static str, f64),
+}
+
+impl<'de> Deserialize<'de> for SizeUnits {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        match value.as_str() {
+            "binary" => Ok(SizeUnits::Binary),
+            "si" => Ok(SizeUnits::Si),
+            unit => FIXED_UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|(name, bytes)| SizeUnits::Fixed(name, *bytes))
+                .ok_or_else(|| serde::de::Error::custom(format!("unknown size unit {:?}", unit))),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatConfig {
+    pub size_units: SizeUnits,
+    pub size_decimals: usize,
+    pub thousands_separator: String,
+    /// strftime-style, in local time
+    pub date_format: String,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            size_units: SizeUnits::Binary,
+            size_decimals: 1,
+            thousands_separator: ",".to_string(),
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+        }
+    }
+}
+
+impl FormatConfig {
+    /// A byte count in the configured unit, e.g. `1.5 MiB` or `1,234 B`
+    pub fn size(&self, bytes: u64) -> String {
+        let (value, unit) = match self.size_units {
+            SizeUnits::Binary => scale(bytes, 1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB"]),
+            SizeUnits::Si => scale(bytes, 1000.0, ["B", "kB", "MB", "GB", "TB", "PB"]),
+            SizeUnits::Fixed(unit, unit_bytes) => (bytes as f64 / unit_bytes, unit),
+        };
+        if unit == "B" || unit == "bytes" {
+            return format!("{} {}", self.number(bytes), unit);
+        }
+        let text = format!("{:.*}", self.size_decimals, value);
+        // Group the integer part only
+        let (integer, fraction) = text.split_at(text.find('.').unwrap_or(text.len()));
+        let integer = self.number(integer.parse().unwrap_or(0));
+        format!("{}{} {}", integer, fraction, unit)
+    }
+
+    /// An integer with thousands separators
+    pub fn number(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut grouped = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped.push_str(&self.thousands_separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    /// A point in time in local time; a broken `date_format` falls back to the default
+    pub fn date(&self, time: SystemTime) -> String {
+        let local = chrono::DateTime::<chrono::Local>::from(time);
+        let mut text = String::new();
+        if write!(text, "{}", local.format(&self.date_format)).is_err() {
+            text.clear();
+            let _ = write!(text, "{}", local.format(DEFAULT_DATE_FORMAT));
+        }
+        text
+    }
+}
+
+/// Largest unit in which `bytes` is at least 1
+fn scale(bytes: u64, base: f64, units: [&'static str; 6]) -> (f64, &'static str) {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    (value, units[unit])
+}
+
+/// A size as written in a query, like `1GB`, `1.5 GiB` or `4096`: a number
+/// and one of the fixed units, bytes without one
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let unit_bytes = match unit.trim() {
+        "" => 1.0,
+        unit => FIXED_UNITS.iter().find(|(name, _)| *name == unit)?.1,
+    };
+    Some((number * unit_bytes) as u64)
+}
@@ -0,0 +1,232 @@
+//! Directory visit database - how often and how recently each directory was opened
+//!
+//! Every directory change is recorded in the metadata store (metadata.rs).
+//! The renderer reads it back as "shelf wear": directories you live in glow a
+//! little brighter than the ones you never open.
+//!
+//! `:z fragment...` jumps there like zoxide: to the best-scoring directory
+//! whose path has the fragments in order (ignoring case), the last one in its
+//! own name. `:z dow` goes to ~/Downloads, `:z felipe src` to the Felipe
+//! checkout's src/ rather than another project's.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::command::{Command, RunCommand};
+use crate::metadata;
+use crate::{CurrentDirectory, StatusMessage};
+
+/// Once the visits add up to this, every count is halved so old habits fade
+const MAX_TOTAL_VISITS: u64 = 5000;
+/// Wear is quantized to this many steps so worn shelves share materials
+const WEAR_LEVELS: f32 = 6.0;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+// =============================================================================
+// Database
+// =============================================================================
+
+#[derive(Clone, Copy)]
+struct Visits {
+    count: u64,
+    /// Seconds since the epoch
+    last: u64,
+}
+
+impl Visits {
+    /// Visit count weighted by how recent the last visit was
+    fn score(&self, now: u64) -> f32 {
+        let age = now.saturating_sub(self.last);
+        let recency = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        self.count as f32 * recency
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Frecency {
+    visits: HashMap<PathBuf, Visits>,
+    /// Highest score in the database, what full wear is measured against
+    top_score: f32,
+    /// When scores were last taken; wear doesn't need to be more current than that
+    scored_at: u64,
+}
+
+impl Frecency {
+    fn record(&mut self, path: &Path, now: u64) {
+        let visits = self.visits.entry(path.to_path_buf()).or_insert(Visits {
+            count: 0,
+            last: now,
+        });
+        visits.count += 1;
+        visits.last = now;
+
+        if self.visits.values().map(|v| v.count).sum::<u64>() > MAX_TOTAL_VISITS {
+            for visits in self.visits.values_mut() {
+                visits.count /= 2;
+            }
+            self.visits.retain(|_, visits| visits.count > 0);
+        }
+        self.rescore(now);
+    }
+
+    fn rescore(&mut self, now: u64) {
+        self.scored_at = now;
+        self.top_score = self
+            .visits
+            .values()
+            .map(|v| v.score(now))
+            .fold(0.0, f32::max);
+    }
+
+    /// Highest-scoring directory that still exists and matches every
+    /// fragment; `current` is left out so `:z` always goes somewhere
+    pub fn best_match(&self, fragments: &[String], current: &Path) -> Option<&Path> {
+        let now = now_secs();
+        let mut candidates: Vec<(&PathBuf, f32)> = self
+            .visits
+            .iter()
+            .filter(|(dir, _)| dir.as_path() != current && matches(dir, fragments))
+            .map(|(dir, visits)| (dir, visits.score(now)))
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        candidates
+            .into_iter()
+            .map(|(dir, _)| dir.as_path())
+            .find(|dir| dir.is_dir())
+    }
+
+    /// 0.0 for unvisited directories up to 1.0 for the most used one
+    pub fn wear(&self, path: &Path) -> f32 {
+        let Some(visits) = self.visits.get(path) else {
+            return 0.0;
+        };
+        if self.top_score <= 0.0 {
+            return 0.0;
+        }
+        // Logarithmic, so a handful of visits already shows
+        let wear = (1.0 + visits.score(self.scored_at)).ln() / (1.0 + self.top_score).ln();
+        (wear.clamp(0.0, 1.0) * WEAR_LEVELS).round() / WEAR_LEVELS
+    }
+}
+
+/// Fragments in order anywhere in the path, the last in the final component
+fn matches(dir: &Path, fragments: &[String]) -> bool {
+    let path = dir.to_string_lossy().to_lowercase();
+    let Some((last, others)) = fragments.split_last() else {
+        return false;
+    };
+    let mut rest = path.as_str();
+    for fragment in others {
+        let fragment = fragment.to_lowercase();
+        let Some(at) = rest.find(&fragment) else {
+            return false;
+        };
+        rest = &rest[at + fragment.len()..];
+    }
+    // The last match is the one most likely to be in the final component
+    let Some(at) = rest.rfind(&last.to_lowercase()) else {
+        return false;
+    };
+    let name_start = path
+        .rfind(std::path::MAIN_SEPARATOR)
+        .map_or(0, |sep| sep + 1);
+    path.len() - rest.len() + at >= name_start
+}
+
+pub struct FrecencyPlugin;
+
+impl Plugin for FrecencyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Frecency>()
+            .add_systems(Startup, load_visits)
+            .add_systems(
+                Update,
+                (
+                    record_visits.after(crate::load_directory),
+                    handle_jump_command,
+                ),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn load_visits(mut frecency: ResMut<Frecency>) {
+    match metadata::visits() {
+        Ok(visits) => {
+            frecency.visits = visits
+                .into_iter()
+                .map(|(path, count, last)| (path, Visits { count, last }))
+                .collect();
+            frecency.rescore(now_secs());
+        }
+        Err(err) => warn!("Could not read visit database: {}", err),
+    }
+}
+
+/// Count a visit whenever the current directory changes
+fn record_visits(
+    current_dir: Res<CurrentDirectory>,
+    mut frecency: ResMut<Frecency>,
+    mut last_path: Local<Option<PathBuf>>,
+) {
+    if !current_dir.is_changed()
+        || current_dir.needs_reload
+        || last_path.as_ref() == Some(&current_dir.path)
+    {
+        return;
+    }
+    *last_path = Some(current_dir.path.clone());
+    frecency.record(&current_dir.path, now_secs());
+    let visits = frecency
+        .visits
+        .iter()
+        .map(|(dir, visits)| (dir.as_path(), visits.count, visits.last));
+    if let Err(err) = metadata::save_visits(visits) {
+        warn!("Could not save visit database: {}", err);
+    }
+}
+
+/// `:z fragment...`
+fn handle_jump_command(
+    mut run_commands: EventReader<RunCommand>,
+    frecency: Res<Frecency>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Jump(fragments) = command else {
+            continue;
+        };
+        match frecency.best_match(fragments, &current_dir.path) {
+            Some(dir) => current_dir.set_path(dir),
+            None => status.0 = format!("No visited directory matches {}", fragments.join(" ")),
+        }
+    }
+}
+
+// =============================================================================
+// Storage
+// =============================================================================
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,123 @@
+//! FTP backend of `ftp://user@host:port/path` and `ftps://` locations
+//! (feature `ftp`)
+//!
+//! `ftps://` is explicit FTPS: the session starts plain on port 21 and
+//! switches to TLS before logging in, as NAS boxes usually offer it. Without
+//! a user the login is anonymous; a password comes from `~/.netrc` (see
+//! remote.rs). One control connection serves listings and transfers in turn.
+
+use std::fs::{File, FileTimes};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use suppaftp::list::File as ListedFile;
+use suppaftp::native_tls::TlsConnector;
+use suppaftp::types::FileType;
+use suppaftp::{FtpError, NativeTlsConnector, NativeTlsFtpStream};
+
+use crate::copier::CopyProgress;
+use crate::remote::{self, Backend, RemoteEntry};
+
+const DEFAULT_PORT: u16 = 21;
+const ANONYMOUS: &str = "anonymous";
+
+pub struct FtpBackend {
+    stream: Mutex<NativeTlsFtpStream>,
+}
+
+impl FtpBackend {
+    /// Connect and log in to `[user@]host[:port]`, over TLS if `secure`
+    pub fn connect(authority: &str, secure: bool) -> io::Result<Self> {
+        let (user, address) = match authority.split_once('@') {
+            Some((user, address)) => (user, address),
+            None => (ANONYMOUS, authority),
+        };
+        let (host, port) = remote::host_port(address, DEFAULT_PORT)?;
+
+        let mut stream = NativeTlsFtpStream::connect((host, port)).map_err(ftp_error)?;
+        if secure {
+            let tls = TlsConnector::new().map_err(io::Error::other)?;
+            stream = stream
+                .into_secure(NativeTlsConnector::from(tls), host)
+                .map_err(ftp_error)?;
+        }
+        let password = remote::netrc_password(host, user).unwrap_or_default();
+        stream.login(user, password.as_str()).map_err(ftp_error)?;
+        stream.transfer_type(FileType::Binary).map_err(ftp_error)?;
+        Ok(Self {
+            stream: Mutex::new(stream),
+        })
+    }
+
+    fn stream(&self) -> std::sync::MutexGuard<'_, NativeTlsFtpStream> {
+        self.stream.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend for FtpBackend {
+    fn list(&self, path: &str) -> io::Result<Vec<RemoteEntry>> {
+        let lines = self.stream().list(Some(path)).map_err(ftp_error)?;
+        // Lines that aren't entries (`total 12`) don't parse
+        Ok(lines
+            .iter()
+            .filter_map(|line| ListedFile::try_from(line.as_str()).ok())
+            .filter(|file| !matches!(file.name(), "." | ".."))
+            .map(|file| RemoteEntry {
+                name: file.name().to_string(),
+                is_dir: file.is_directory(),
+                size: file.size() as u64,
+                modified: Some(file.modified()),
+            })
+            .collect())
+    }
+
+    /// FTP has no stat; the entry is looked up in its parent's listing
+    fn stat(&self, path: &str) -> io::Result<RemoteEntry> {
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        if name.is_empty() {
+            return Ok(RemoteEntry {
+                name: String::new(),
+                is_dir: true,
+                size: 0,
+                modified: None,
+            });
+        }
+        let parent = if parent.is_empty() { "/" } else { parent };
+        self.list(parent)?
+            .into_iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no {}", path)))
+    }
+
+    fn download(&self, path: &str, local: &Path, progress: &CopyProgress) -> io::Result<()> {
+        let modified = self.stat(path).ok().and_then(|entry| entry.modified);
+        let mut stream = self.stream();
+        let mut data = stream.retr_as_stream(path).map_err(ftp_error)?;
+        let mut file = File::create(local)?;
+        remote::transfer(&mut data, &mut file, progress)?;
+        stream.finalize_retr_stream(data).map_err(ftp_error)?;
+        if let Some(modified) = modified {
+            let _ = file.set_times(FileTimes::new().set_modified(modified));
+        }
+        Ok(())
+    }
+
+    fn upload(&self, local: &Path, path: &str, progress: &CopyProgress) -> io::Result<()> {
+        let mut file = File::open(local)?;
+        let mut stream = self.stream();
+        let mut data = stream.put_with_stream(path).map_err(ftp_error)?;
+        remote::transfer(&mut file, &mut data, progress)?;
+        stream.finalize_put_stream(data).map_err(ftp_error)
+    }
+
+    fn make_dir(&self, path: &str) -> io::Result<()> {
+        self.stream().mkdir(path).map_err(ftp_error)
+    }
+}
+
+fn ftp_error(err: FtpError) -> io::Error {
+    match err {
+        FtpError::ConnectionError(err) => err,
+        err => io::Error::other(err.to_string()),
+    }
+}
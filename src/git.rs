@@ -0,0 +1,241 @@
+//! Git status - entries colored by their state in the repository
+//!
+//! Inside a git work tree, modified entries turn yellow, staged ones cyan,
+//! untracked ones green and ignored ones grey. A directory
+//! takes the most pressing state of anything inside it, so changes can be
+//! followed down the tree. The top bar shows the branch, with `*` while the
+//! work tree is dirty.
+//!
+//! Status is read on a worker thread whenever the listing reloads, so large
+//! repositories don't stall the scene. The browser build has no libgit2 and no
+//! repositories to read, so nothing is colored there.
+
+use bevy::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use git2::{Repository, Status, StatusOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use crate::{CurrentDirectory, DIFF_ADDED, DIFF_MODIFIED};
+
+const GIT_STAGED: Color = Color::srgb(0.2, 0.8, 1.0);
+const GIT_IGNORED: Color = Color::srgb(0.15, 0.15, 0.15);
+
+/// State of an entry, least pressing first so directories can take the max
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryStatus {
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+}
+
+impl EntryStatus {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_git(status: Status) -> Option<Self> {
+        if status.is_ignored() {
+            Some(Self::Ignored)
+        } else if status.is_conflicted()
+            || status.intersects(
+                Status::WT_MODIFIED
+                    | Status::WT_DELETED
+                    | Status::WT_RENAMED
+                    | Status::WT_TYPECHANGE,
+            )
+        {
+            Some(Self::Modified)
+        } else if status.intersects(
+            Status::INDEX_NEW
+                | Status::INDEX_MODIFIED
+                | Status::INDEX_DELETED
+                | Status::INDEX_RENAMED
+                | Status::INDEX_TYPECHANGE,
+        ) {
+            Some(Self::Staged)
+        } else if status.is_wt_new() {
+            Some(Self::Untracked)
+        } else {
+            None
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Self::Ignored => GIT_IGNORED,
+            Self::Untracked => DIFF_ADDED,
+            Self::Staged => GIT_STAGED,
+            Self::Modified => DIFF_MODIFIED,
+        }
+    }
+}
+
+/// What the worker found out about the repository around a directory
+#[derive(Default)]
+pub struct RepoStatus {
+    /// Current branch, or the short commit id when HEAD is detached
+    pub branch: String,
+    /// Anything modified, staged or untracked
+    pub dirty: bool,
+    /// By absolute path; directories hold the max of what's below them
+    entries: HashMap<PathBuf, EntryStatus>,
+}
+
+/// Status of the repository the current directory is in, if any
+#[derive(Resource, Default)]
+pub struct GitStatus {
+    pub repo: Option<RepoStatus>,
+    /// Directory the worker was last started for
+    dir: Option<PathBuf>,
+    worker: Option<JoinHandle<Option<RepoStatus>>>,
+    /// A reload happened while the worker was busy
+    stale: bool,
+}
+
+impl GitStatus {
+    pub fn entry(&self, path: &Path) -> Option<EntryStatus> {
+        self.repo.as_ref()?.entries.get(path).copied()
+    }
+}
+
+pub struct GitPlugin;
+
+impl Plugin for GitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GitStatus>().add_systems(
+            Update,
+            (
+                mark_git_status_stale.before(crate::load_directory),
+                (start_git_status, finish_git_status).after(crate::load_directory),
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Every reload may follow a change on disk, so status is read again
+fn mark_git_status_stale(current_dir: Res<CurrentDirectory>, mut git: ResMut<GitStatus>) {
+    if current_dir.needs_reload {
+        git.stale = true;
+    }
+}
+
+fn start_git_status(current_dir: Res<CurrentDirectory>, mut git: ResMut<GitStatus>) {
+    if !git.stale || git.worker.is_some() {
+        return;
+    }
+    git.stale = false;
+    // A different directory may be outside the repository; don't keep its colors meanwhile
+    if git.dir.as_ref() != Some(&current_dir.path) {
+        git.repo = None;
+        git.dir = Some(current_dir.path.clone());
+    }
+    let dir = current_dir.path.clone();
+    git.worker = Some(std::thread::spawn(move || read_status(&dir)));
+}
+
+fn finish_git_status(mut git: ResMut<GitStatus>) {
+    if !git
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        return;
+    }
+    let worker = git.worker.take().expect("checked above");
+    git.repo = worker.join().ok().flatten();
+}
+
+// =============================================================================
+// Reading
+// =============================================================================
+
+/// The repository holding `path`, and `path` relative to its work tree;
+/// `None` outside a repository (or in a bare one)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_repo(path: &Path) -> Option<(Repository, PathBuf)> {
+    // libgit2 resolves symlinks in the work tree path, so compare real paths.
+    // A symlinked file is tracked as the link, so only its directory is resolved.
+    let (dir, real) = match (path.is_dir(), path.parent(), path.file_name()) {
+        (false, Some(parent), Some(name)) => (parent, parent.canonicalize().ok()?.join(name)),
+        _ => (path, path.canonicalize().ok()?),
+    };
+    let repo = Repository::discover(dir).ok()?;
+    let relative = real
+        .strip_prefix(repo.workdir()?.canonicalize().ok()?)
+        .ok()?
+        .to_path_buf();
+    Some((repo, relative))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_status(_dir: &Path) -> Option<RepoStatus> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_status(dir: &Path) -> Option<RepoStatus> {
+    let (repo, relative) = open_repo(dir)?;
+    // Entries are keyed by paths as Felipe lists them, which may go through
+    // symlinks libgit2 resolved; rebuild the root from `dir`
+    let workdir = dir
+        .ancestors()
+        .nth(relative.components().count())?
+        .to_path_buf();
+
+    let head = repo.head().ok();
+    let branch = match &head {
+        Some(head) if head.is_branch() => head.shorthand().unwrap_or("HEAD").to_string(),
+        Some(head) => head
+            .target()
+            .map(|oid| oid.to_string()[..7].to_string())
+            .unwrap_or_else(|| "HEAD".to_string()),
+        // No commits yet; HEAD names a branch that doesn't exist
+        None => repo
+            .find_reference("HEAD")
+            .ok()
+            .and_then(|head| head.symbolic_target().map(str::to_string))
+            .map(|target| target.trim_start_matches("refs/heads/").to_string())
+            .unwrap_or_else(|| "HEAD".to_string()),
+    };
+
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .include_ignored(true)
+        .recurse_ignored_dirs(false);
+    let statuses = repo.statuses(Some(&mut options)).ok()?;
+
+    let mut status = RepoStatus {
+        branch,
+        ..default()
+    };
+    for item in statuses.iter() {
+        let (Some(relative), Some(state)) = (item.path(), EntryStatus::from_git(item.status()))
+        else {
+            continue;
+        };
+        let path = workdir.join(relative);
+        if state != EntryStatus::Ignored {
+            status.dirty = true;
+            // Directories show what's inside them; an ignored file doesn't make them ignored
+            for ancestor in path.ancestors().skip(1) {
+                if !ancestor.starts_with(&workdir) {
+                    break;
+                }
+                let current = status
+                    .entries
+                    .entry(ancestor.to_path_buf())
+                    .or_insert(state);
+                *current = (*current).max(state);
+            }
+        }
+        let current = status.entries.entry(path).or_insert(state);
+        *current = (*current).max(state);
+    }
+    Some(status)
+}
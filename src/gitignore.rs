@@ -0,0 +1,40 @@
+//! Gitignore-aware listing - `zi` / `:set gitignore` hides what git ignores
+//!
+//! Build output like `target/` or `node_modules/` can dwarf the source tree in
+//! the scene. The rules are git's own: `.gitignore` files from the repository
+//! root down, `.git/info/exclude` and the global excludes file. Outside a git
+//! repository nothing is hidden.
+
+use ignore::WalkBuilder;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::config::ListingConfig;
+
+/// Walker over `root` that skips what `listing` hides
+pub fn walker(root: &Path, listing: &ListingConfig) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .standard_filters(false)
+        .hidden(!listing.hidden)
+        .parents(listing.gitignore)
+        .git_ignore(listing.gitignore)
+        .git_exclude(listing.gitignore)
+        .git_global(listing.gitignore);
+    builder
+}
+
+/// Paths of the entries directly in `dir` that git doesn't ignore
+pub fn kept_entries(dir: &Path) -> HashSet<PathBuf> {
+    let listing = ListingConfig {
+        hidden: true,
+        gitignore: true,
+    };
+    walker(dir, &listing)
+        .max_depth(Some(1))
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| entry.into_path())
+        .collect()
+}
@@ -0,0 +1,354 @@
+//! `:gitlog` and `:blame` - read-only git history of the selected file
+//!
+//! `:gitlog` lists the commits that changed the file, newest first. `:blame`
+//! lists its lines, each with the commit that last touched it. Both share one
+//! side panel: j/k scroll, Ctrl-d/Ctrl-u scroll by half a page, Esc or q closes.
+
+use bevy::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use git2::{Oid, Repository, Sort};
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, SystemTime};
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::format::FormatConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::git::open_repo;
+use crate::{
+    CurrentDirectory, StatusMessage, UiElement, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+/// Commits looked at before `:gitlog` gives up on older history
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_LOG_WALK: usize = 20_000;
+/// Lines of the panel body
+const PANEL_ROWS: usize = 30;
+
+/// `:gitlog` / `:blame` panel state
+#[derive(Resource, Default)]
+pub struct GitLogView {
+    title: String,
+    lines: Vec<String>,
+    scroll: usize,
+}
+
+/// Marker for the git log panel
+#[derive(Component)]
+struct GitLogPanel;
+
+/// Marker for the git log panel text
+#[derive(Component)]
+struct GitLogText;
+
+pub struct GitLogPlugin;
+
+impl Plugin for GitLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GitLogView>().add_systems(
+            Update,
+            (
+                handle_gitlog_commands,
+                handle_gitlog_keys,
+                update_gitlog_panel,
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_gitlog_commands(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut view: ResMut<GitLogView>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<GitLogPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let blame = match command {
+            Command::GitLog => false,
+            Command::Blame => true,
+            _ => continue,
+        };
+        let Some(entry) = current_dir
+            .entries
+            .get(current_dir.selected_index)
+            .filter(|entry| !entry.is_dir)
+        else {
+            status.0 = "Select a file first".to_string();
+            continue;
+        };
+        let Some((relative, lines)) = history(&entry.path, blame, &config.format) else {
+            status.0 = format!("{} isn't in a git repository", entry.name);
+            continue;
+        };
+        match lines {
+            Ok(lines) if lines.is_empty() => {
+                status.0 = format!("{} has no history yet", entry.name);
+            }
+            Ok(lines) => {
+                view.title = format!(
+                    "{} {}",
+                    if blame { "BLAME" } else { "GIT LOG" },
+                    relative.display()
+                );
+                view.lines = lines;
+                view.scroll = 0;
+                if !focus.is_open(Panel::GitLog) {
+                    spawn_panel(&mut commands);
+                }
+                focus.open(Panel::GitLog);
+            }
+            Err(err) => {
+                status.0 = format!("git: {}", err);
+                close_panel(&mut commands, &mut focus, &panel_query);
+            }
+        }
+    }
+}
+
+fn handle_gitlog_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut view: ResMut<GitLogView>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<GitLogPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::GitLog)
+    {
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    if !focus.has_focus(Panel::GitLog) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    let last = view.lines.len().saturating_sub(PANEL_ROWS);
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        view.scroll = (view.scroll + 1).min(last);
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        view.scroll = view.scroll.saturating_sub(1);
+    }
+    if ctrl && keyboard.just_pressed(KeyCode::KeyD) {
+        view.scroll = (view.scroll + PANEL_ROWS / 2).min(last);
+    }
+    if ctrl && keyboard.just_pressed(KeyCode::KeyU) {
+        view.scroll = view.scroll.saturating_sub(PANEL_ROWS / 2);
+    }
+}
+
+fn update_gitlog_panel(
+    view: Res<GitLogView>,
+    focus: Res<Focus>,
+    mut text_query: Query<&mut Text, With<GitLogText>>,
+) {
+    if !focus.is_open(Panel::GitLog) {
+        return;
+    }
+    let end = (view.scroll + PANEL_ROWS).min(view.lines.len());
+    let mut sections = vec![TextSection::new(
+        format!(
+            "{}  {}-{}/{}  j/k:scroll  Esc:close\n",
+            view.title,
+            view.scroll + 1,
+            end,
+            view.lines.len()
+        ),
+        panel_style(FELIPE_ORANGE),
+    )];
+    for line in &view.lines[view.scroll..end] {
+        sections.push(TextSection::new(
+            format!("{}\n", line),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Reading
+// =============================================================================
+
+/// The file's path in its repository and its log or blame lines; None outside
+/// a repository
+#[cfg(not(target_arch = "wasm32"))]
+fn history(
+    path: &Path,
+    blame: bool,
+    format: &FormatConfig,
+) -> Option<(PathBuf, Result<Vec<String>, String>)> {
+    let (repo, relative) = open_repo(path)?;
+    let lines = if blame {
+        blame_lines(&repo, &relative, path, format)
+    } else {
+        log_lines(&repo, &relative, format)
+    };
+    Some((relative, lines.map_err(|err| err.message().to_string())))
+}
+
+/// The browser build reads no repositories
+#[cfg(target_arch = "wasm32")]
+fn history(
+    _path: &Path,
+    _blame: bool,
+    _format: &FormatConfig,
+) -> Option<(PathBuf, Result<Vec<String>, String>)> {
+    None
+}
+
+/// Commits from HEAD that changed `relative`, like `git log -- <file>`: a
+/// commit counts when the file differs from every parent, so merges that only
+/// brought one side's version along are skipped
+#[cfg(not(target_arch = "wasm32"))]
+fn log_lines(
+    repo: &Repository,
+    relative: &Path,
+    format: &FormatConfig,
+) -> Result<Vec<String>, git2::Error> {
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    walk.set_sorting(Sort::TIME)?;
+
+    let blob_at = |commit: &git2::Commit| -> Option<Oid> {
+        commit.tree().ok()?.get_path(relative).ok().map(|e| e.id())
+    };
+    let mut lines = Vec::new();
+    for oid in walk.take(MAX_LOG_WALK) {
+        let commit = repo.find_commit(oid?)?;
+        let Some(blob) = blob_at(&commit) else {
+            continue;
+        };
+        let changed = commit
+            .parents()
+            .all(|parent| blob_at(&parent) != Some(blob));
+        if !changed {
+            continue;
+        }
+        lines.push(format!(
+            "{} {} {:<16} {}",
+            short_id(commit.id()),
+            date(commit.time().seconds(), format),
+            truncate(commit.author().name().unwrap_or("?"), 16),
+            commit.summary().unwrap_or("")
+        ));
+    }
+    Ok(lines)
+}
+
+/// Lines of the file as it is on disk, each with the commit that last changed it
+#[cfg(not(target_arch = "wasm32"))]
+fn blame_lines(
+    repo: &Repository,
+    relative: &Path,
+    path: &Path,
+    format: &FormatConfig,
+) -> Result<Vec<String>, git2::Error> {
+    let blame = repo.blame_file(relative, None)?;
+    let text = std::fs::read(path).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    // Blame the working copy, so lines not committed yet show up as such
+    let blame = blame.blame_buffer(&text)?;
+
+    let text = String::from_utf8_lossy(&text);
+    let lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let origin = blame
+                .get_line(i + 1)
+                .filter(|hunk| !hunk.final_commit_id().is_zero());
+            let who = match origin {
+                Some(hunk) => format!(
+                    "{} {} {:<12}",
+                    short_id(hunk.final_commit_id()),
+                    date(hunk.final_signature().when().seconds(), format),
+                    truncate(hunk.final_signature().name().unwrap_or("?"), 12)
+                ),
+                None => format!("{:<7} {:<10} {:<12}", "-", "", "uncommitted"),
+            };
+            format!("{} {:>4} {}", who, i + 1, line)
+        })
+        .collect();
+    Ok(lines)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn short_id(oid: Oid) -> String {
+    oid.to_string()[..7].to_string()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn date(seconds: i64, format: &FormatConfig) -> String {
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64);
+    format.date(time)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 14.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    right: Val::Px(10.0),
+                    max_width: Val::Px(720.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            GitLogPanel,
+            Focusable(Panel::GitLog),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), GitLogText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<GitLogPanel>>,
+) {
+    focus.close(Panel::GitLog);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
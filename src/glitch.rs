@@ -0,0 +1,70 @@
+//! Error feedback in the scene - a failed operation glitches the screen and
+//! flashes the entry it failed on red, alongside the status line message
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How long the screen glitch lasts
+const GLITCH_SECONDS: f32 = 0.35;
+/// How long an entry stays red after failing
+const FLASH_SECONDS: f32 = 0.8;
+
+/// Sent when an operation on `path` (or on nothing in particular) fails
+#[derive(Event)]
+pub struct OperationFailed {
+    pub path: Option<PathBuf>,
+}
+
+/// Remaining strength of the glitch and of each entry's flash, 1.0 down to 0.0
+#[derive(Resource, Default)]
+pub struct Glitch {
+    pulse: f32,
+    flashes: HashMap<PathBuf, f32>,
+}
+
+impl Glitch {
+    pub fn pulse(&self) -> f32 {
+        self.pulse
+    }
+
+    pub fn flash(&self, path: &Path) -> f32 {
+        self.flashes.get(path).copied().unwrap_or(0.0)
+    }
+}
+
+pub struct GlitchPlugin;
+
+impl Plugin for GlitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Glitch>()
+            .add_event::<OperationFailed>()
+            .add_systems(Update, (start_glitch, fade_glitch).chain());
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn start_glitch(mut failures: EventReader<OperationFailed>, mut glitch: ResMut<Glitch>) {
+    for failure in failures.read() {
+        glitch.pulse = 1.0;
+        if let Some(path) = &failure.path {
+            glitch.flashes.insert(path.clone(), 1.0);
+        }
+    }
+}
+
+fn fade_glitch(time: Res<Time>, mut glitch: ResMut<Glitch>) {
+    // Don't touch the resource while idle, so readers can skip unchanged frames
+    if glitch.pulse <= 0.0 && glitch.flashes.is_empty() {
+        return;
+    }
+    let delta = time.delta_seconds();
+    glitch.pulse = (glitch.pulse - delta / GLITCH_SECONDS).max(0.0);
+    for flash in glitch.flashes.values_mut() {
+        *flash -= delta / FLASH_SECONDS;
+    }
+    glitch.flashes.retain(|_, flash| *flash > 0.0);
+}
@@ -0,0 +1,225 @@
+//! Growth - files being written, watched as they grow
+//!
+//! The selected file and the files marked here (see basket.rs) have their
+//! size checked twice a second. One that grows - a log being written, a
+//! download coming in - rises in place as it does, its rate floating over it
+//! (`+1.2 MiB/s`) until it has stood still for a few seconds; one that
+//! shrinks, like a rotated log, sinks back. The info bar follows the new
+//! size. Remote files aren't watched.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::basket::Basket;
+use crate::config::Config;
+use crate::transition::EntryTransition;
+use crate::{entry_height, vfs, CurrentDirectory, FileEntity, FileLabel, DIFF_ADDED};
+
+/// Seconds between two looks at the watched sizes
+const POLL_SECONDS: f32 = 0.5;
+/// Seconds without growth before the rate goes away
+const QUIET_SECONDS: f32 = 3.0;
+/// How fast a book catches up with its new height, per second
+const GROW_SPEED: f32 = 4.0;
+/// Weight of the newest look in the smoothed rate
+const RATE_SMOOTHING: f64 = 0.5;
+
+/// What's known of one watched file
+struct Watch {
+    /// Bytes per second, smoothed
+    rate: f64,
+    /// When its size was last looked at, and when it last grew
+    polled: f32,
+    grew: Option<f32>,
+}
+
+#[derive(Resource, Default)]
+struct Growth {
+    watched: HashMap<PathBuf, Watch>,
+    last_poll: f32,
+}
+
+/// Rate shown over a growing file
+#[derive(Component)]
+struct GrowthReadout {
+    path: PathBuf,
+}
+
+pub struct GrowthPlugin;
+
+impl Plugin for GrowthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Growth>()
+            .add_systems(Update, (poll_sizes, grow_books, update_readouts).chain());
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Look at the sizes of the selected and marked files, and take in changes
+fn poll_sizes(
+    time: Res<Time>,
+    basket: Res<Basket>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut growth: ResMut<Growth>,
+) {
+    let now = time.elapsed_seconds();
+    if now - growth.last_poll < POLL_SECONDS {
+        return;
+    }
+    growth.last_poll = now;
+    let marked = basket.marked_here(&current_dir);
+    let selected = current_dir.selected_index;
+    let watched: Vec<usize> = (0..current_dir.entries.len())
+        .filter(|&i| {
+            let entry = &current_dir.entries[i];
+            !entry.is_dir && (i == selected || marked.contains(&entry.path))
+        })
+        .collect();
+    growth.watched.retain(|path, _| {
+        watched
+            .iter()
+            .any(|&i| current_dir.entries[i].path == *path)
+    });
+    for i in watched {
+        let entry = &current_dir.entries[i];
+        let Ok(size) = vfs::backend(&entry.path)
+            .and_then(|fs| fs.stat(&entry.path))
+            .map(|stat| stat.size)
+        else {
+            continue;
+        };
+        let old = entry.size;
+        let path = entry.path.clone();
+        let Some(watch) = growth.watched.get_mut(&path) else {
+            // Whatever it did before it was watched has no rate to show
+            growth.watched.insert(
+                path,
+                Watch {
+                    rate: 0.0,
+                    polled: now,
+                    grew: None,
+                },
+            );
+            if size != old {
+                current_dir.entries[i].size = size;
+            }
+            continue;
+        };
+        let elapsed = (now - watch.polled).max(f32::EPSILON) as f64;
+        watch.polled = now;
+        if size > old {
+            let rate = (size - old) as f64 / elapsed;
+            watch.rate = if watch.grew.is_some() {
+                watch.rate + (rate - watch.rate) * RATE_SMOOTHING
+            } else {
+                rate
+            };
+            watch.grew = Some(now);
+        }
+        if size != old {
+            current_dir.entries[i].size = size;
+        }
+    }
+}
+
+/// Ease the books of watched files to the height of their size, labels along
+fn grow_books(
+    time: Res<Time>,
+    current_dir: Res<CurrentDirectory>,
+    growth: Res<Growth>,
+    mut entity_query: Query<
+        (&FileEntity, &mut Transform),
+        (Without<EntryTransition>, Without<FileLabel>),
+    >,
+    mut label_query: Query<(&FileLabel, &mut Transform), Without<EntryTransition>>,
+) {
+    if growth.watched.is_empty() {
+        return;
+    }
+    let step = 1.0 - (-GROW_SPEED * time.delta_seconds()).exp();
+    for (file_entity, mut transform) in entity_query.iter_mut() {
+        let Some(entry) = current_dir
+            .entries
+            .get(file_entity.index)
+            .filter(|entry| growth.watched.contains_key(&entry.path))
+        else {
+            continue;
+        };
+        let target = entry_height(entry);
+        if (transform.scale.y - target).abs() < 0.001 {
+            continue;
+        }
+        let height = transform.scale.y + (target - transform.scale.y) * step;
+        transform.scale.y = height;
+        transform.translation.y = height / 2.0;
+        if let Some((_, mut label)) = label_query
+            .iter_mut()
+            .find(|(label, _)| label.index == file_entity.index)
+        {
+            label.translation.y = height + 1.5;
+        }
+    }
+}
+
+/// The rate over each file that grew lately
+fn update_readouts(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<Config>,
+    current_dir: Res<CurrentDirectory>,
+    growth: Res<Growth>,
+    entity_query: Query<(&FileEntity, &Transform), Without<GrowthReadout>>,
+    mut readout_query: Query<(Entity, &GrowthReadout, &mut Text, &mut Transform)>,
+) {
+    let now = time.elapsed_seconds();
+    let mut shown: HashMap<&PathBuf, (Vec3, String)> = HashMap::new();
+    for (file_entity, transform) in entity_query.iter() {
+        let Some(entry) = current_dir.entries.get(file_entity.index) else {
+            continue;
+        };
+        let Some(watch) = growth.watched.get(&entry.path) else {
+            continue;
+        };
+        if !watch.grew.is_some_and(|grew| now - grew < QUIET_SECONDS) {
+            continue;
+        }
+        let position = Vec3::new(
+            transform.translation.x,
+            transform.scale.y + 1.0,
+            transform.translation.z,
+        );
+        let rate = format!("+{}/s", config.format.size(watch.rate as u64));
+        shown.insert(&entry.path, (position, rate));
+    }
+    for (entity, readout, mut text, mut transform) in readout_query.iter_mut() {
+        let Some((position, rate)) = shown.remove(&readout.path) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        transform.translation = position;
+        if text.sections[0].value != rate {
+            text.sections[0].value = rate;
+        }
+    }
+    for (path, (position, rate)) in shown {
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    rate,
+                    TextStyle {
+                        font_size: 30.0,
+                        color: DIFF_ADDED,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(position).with_scale(Vec3::splat(0.02)),
+                ..default()
+            },
+            GrowthReadout { path: path.clone() },
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+//! Heights - how a file's size becomes the height of its book
+//!
+//! Directories are all the same low block; a file rises with its size, on one
+//! of four scales (`scale` under `[heights]`, or `:set heightscale sqrt` until
+//! Felipe quits; `:set heightscale` alone shows the one in use):
+//!
+//! - `log`: every tenfold size adds the same height, from 1 MiB (and smaller,
+//!   flat) up to 64 GiB (the default). Good for mixed directories, but a
+//!   directory of small text files stays flat
+//! - `linear`: height in proportion to the size, up to the largest file here
+//! - `sqrt`: in between, small files stand out a little more than linear
+//! - `rank`: by place among the files here, the smallest flat and the largest
+//!   full height, however close their sizes are
+//!
+//! `min_size` and `max_size` clamp the scale: files up to the first are flat
+//! and files from the second on are full height. Without them `linear` and
+//! `sqrt` go from nothing to the largest file of the directory, so there's
+//! always something to compare; `rank` has no use for them.
+//!
+//! ```toml
+//! [heights]
+//! scale = "sqrt"
+//! min_size = "4KB"
+//! max_size = "100MB"
+//! ```
+
+use bevy::prelude::*;
+use serde::{Deserialize, Deserializer};
+use std::sync::RwLock;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::format::parse_size;
+use crate::{CurrentDirectory, StatusMessage};
+
+/// Where the `log` scale starts and ends without a range
+const LOG_MIN_SIZE: u64 = 1 << 20;
+const LOG_MAX_SIZE: u64 = 64 << 30;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HeightScale {
+    Linear,
+    #[default]
+    Log,
+    Sqrt,
+    Rank,
+}
+
+impl HeightScale {
+    pub fn name(self) -> &'static str {
+        match self {
+            HeightScale::Linear => "linear",
+            HeightScale::Log => "log",
+            HeightScale::Sqrt => "sqrt",
+            HeightScale::Rank => "rank",
+        }
+    }
+
+    /// `bytes` on the scale, growing with the size
+    fn curve(self, bytes: u64) -> f64 {
+        let bytes = bytes as f64;
+        match self {
+            HeightScale::Linear | HeightScale::Rank => bytes,
+            HeightScale::Log => bytes.ln_1p(),
+            HeightScale::Sqrt => bytes.sqrt(),
+        }
+    }
+}
+
+impl std::str::FromStr for HeightScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "linear" => Ok(HeightScale::Linear),
+            "log" => Ok(HeightScale::Log),
+            "sqrt" => Ok(HeightScale::Sqrt),
+            "rank" => Ok(HeightScale::Rank),
+            _ => Err(format!(
+                "Unknown height scale: {} (linear, log, sqrt, rank)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeightsConfig {
+    pub scale: HeightScale,
+    /// Files this size and smaller stay flat, e.g. `"4KB"`
+    #[serde(deserialize_with = "size")]
+    pub min_size: Option<u64>,
+    /// Files this size and larger are full height
+    #[serde(deserialize_with = "size")]
+    pub max_size: Option<u64>,
+}
+
+/// A size written like `1.5 GiB` or `4096`
+fn size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_size(&text)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("not a size: {:?}", text)))
+}
+
+/// The scale in use and the file sizes of the listing, sorted; read by
+/// `entry_height`, which everything placing things on top of a book calls
+struct Scale {
+    scale: HeightScale,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    sizes: Vec<u64>,
+}
+
+static SCALE: RwLock<Scale> = RwLock::new(Scale {
+    scale: HeightScale::Log,
+    min_size: None,
+    max_size: None,
+    sizes: Vec::new(),
+});
+
+/// How far up the scale a file of `size` bytes is, from 0 (flat) to 1 (full
+/// height)
+pub fn fraction(size: u64) -> f32 {
+    let scale = SCALE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if scale.scale == HeightScale::Rank {
+        // Files of the same size share a place
+        let places = scale.sizes.len().saturating_sub(1);
+        if places == 0 {
+            return 0.0;
+        }
+        let below = scale.sizes.partition_point(|&other| other < size);
+        return (below as f32 / places as f32).clamp(0.0, 1.0);
+    }
+    let largest = scale.sizes.last().copied().unwrap_or(0);
+    let (min, max) = match scale.scale {
+        HeightScale::Log => (
+            scale.min_size.unwrap_or(LOG_MIN_SIZE),
+            scale.max_size.unwrap_or(LOG_MAX_SIZE),
+        ),
+        _ => (
+            scale.min_size.unwrap_or(0),
+            scale.max_size.unwrap_or(largest),
+        ),
+    };
+    let (low, high) = (scale.scale.curve(min), scale.scale.curve(max));
+    if high <= low {
+        return if size > min { 1.0 } else { 0.0 };
+    }
+    let at = scale.scale.curve(size.clamp(min, max));
+    ((at - low) / (high - low)) as f32
+}
+
+pub struct HeightsPlugin;
+
+impl Plugin for HeightsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                handle_heightscale_command,
+                // Before the books of a new listing are made
+                update_scale
+                    .after(crate::load_directory)
+                    .before(crate::spawn_file_entities),
+            )
+                .chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_heightscale_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut config: ResMut<Config>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::HeightScale(scale) = command else {
+            continue;
+        };
+        if let Some(scale) = scale {
+            config.heights.scale = *scale;
+        }
+        status.0 = format!("heightscale={}", config.heights.scale.name());
+    }
+}
+
+/// Follow the config and the listing; books are made again when the scale
+/// changes
+fn update_scale(
+    config: Res<Config>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut shown: Local<Option<(HeightScale, Option<u64>, Option<u64>)>>,
+) {
+    let heights = &config.heights;
+    let wanted = (heights.scale, heights.min_size, heights.max_size);
+    let rescaled = *shown != Some(wanted);
+    if !rescaled && !current_dir.is_changed() {
+        return;
+    }
+    let mut sizes: Vec<u64> = current_dir
+        .entries
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| entry.size)
+        .collect();
+    sizes.sort_unstable();
+    {
+        let mut scale = SCALE
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        scale.scale = heights.scale;
+        scale.min_size = heights.min_size;
+        scale.max_size = heights.max_size;
+        scale.sizes = sizes;
+    }
+    let first_run = shown.is_none();
+    *shown = Some(wanted);
+    if rescaled && !first_run {
+        current_dir.needs_reload = true;
+    }
+}
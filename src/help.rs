@@ -0,0 +1,319 @@
+//! Help - `?` shows every key and command on one screen
+//!
+//! The built-in keys are listed in `KEY_MAP` below, next to each other by
+//! what they do; the keys bound to user commands (`[[commands]]`, see
+//! custom.rs) are read from the config as it is now, so a rebinding shows up
+//! the next time the overlay opens. `?` again, Esc or q closes it.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+
+use crate::config::Config;
+use crate::flycam::FlyCamera;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::locale::tr;
+use crate::{Prompt, UiElement, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM};
+
+// =============================================================================
+// Key map
+// =============================================================================
+
+struct Binding {
+    keys: &'static str,
+    what: &'static str,
+}
+
+struct Section {
+    title: &'static str,
+    bindings: &'static [Binding],
+}
+
+const fn bind(keys: &'static str, what: &'static str) -> Binding {
+    Binding { keys, what }
+}
+
+/// Built-in keys and commands; keep in step with the handlers, and the
+/// descriptions with the catalog in locale.rs
+const KEY_MAP: &[Section] = &[
+    Section {
+        title: "MOVE",
+        bindings: &[
+            bind("j / k", "next / previous entry"),
+            bind("g / G", "first / last entry"),
+            bind("7j / 15G", "7 down / to entry 15"),
+            bind("l / Enter", "open the directory or file"),
+            bind("h", "up to the parent"),
+            bind("b 1-9", "up the path to that segment"),
+            bind("' 1-9", "go to that portal"),
+            bind("F", "fuzzy-find below here"),
+            bind("Ctrl-e", "browse the :tree sidebar"),
+            bind("Tab", "next root of the workspace"),
+        ],
+    },
+    Section {
+        title: "VIEW",
+        bindings: &[
+            bind("zz", "frame the selection"),
+            bind("zt / zb", "the whole directory, from above / as is"),
+            bind(". / zh", "show or hide dotfiles"),
+            bind("zi", "show or hide ignored files"),
+            bind("s", "next sort order"),
+            bind("f", "filter by name, Esc clears it"),
+            bind("Ctrl-w", "swap the sides of :vsplit"),
+            bind("Ctrl-= / Ctrl--", "bigger / smaller text and panels, Ctrl-0 resets"),
+            bind("F11", "fullscreen"),
+        ],
+    },
+    Section {
+        title: "FILES",
+        bindings: &[
+            bind("v", "visual mode, a range of entries"),
+            bind("y / m", "yank / cut the selection"),
+            bind("d", "trash the selection, u brings it back"),
+            bind("yp / yn / yd", "copy the path / name / directory"),
+            bind("Ctrl-v", "paste files copied in another app"),
+            bind("p / P", "paste here / paste as links"),
+            bind("r", "rename in place"),
+            bind("i", "properties"),
+            bind("t + color", "tag with r o y g b p, x clears"),
+            bind("*", "star"),
+            bind("u", "undo the last operation"),
+            bind("Shift-Space", "play or pause a sound file"),
+            bind("Space", "mark or unmark, y / m / : act on the marks"),
+            bind("Enter", "open the marked files, together"),
+            bind("Ctrl-t", "terminal here"),
+        ],
+    },
+    Section {
+        title: "MOUSE",
+        bindings: &[
+            bind("click", "select"),
+            bind("double click", "open"),
+            bind("drag", "move the selection into a directory"),
+            bind("drag to the edge", "put it on the clipboard for another app"),
+            bind("drop files in", "copy them here, Shift moves"),
+            bind("middle drag", "orbit"),
+            bind("right drag", "pan"),
+            bind("wheel", "zoom"),
+        ],
+    },
+    Section {
+        title: "PANELS",
+        bindings: &[
+            bind("Tab / Shift-Tab", "next / previous open panel"),
+            bind("Esc / q", "close the focused panel"),
+            bind("?", "this help"),
+        ],
+    },
+    Section {
+        title: "COMMANDS",
+        bindings: &[
+            bind(":cd path", "go to a directory, local or remote"),
+            bind(":z words", "the most used directory matching"),
+            bind(":find text", "fuzzy-find below here"),
+            bind(":pin / :unpin", "portals to directories"),
+            bind(":filter glob|/re/", "files matching, :filter! clears"),
+            bind(":sort key [desc]", "sort order"),
+            bind(":colorby mtime|none", "what colors the books"),
+            bind(":layout name", "arrange the entries another way"),
+            bind(":flatten", "every file below here"),
+            bind(":record [file.mp4|dir]", "fly around and save a video"),
+            bind(":paste-basket[!]", "copy (move) the basket here"),
+            bind(":clearsel", "unmark everything"),
+            bind(":select all|invert|*.log", "mark entries here"),
+            bind(":vsplit / :only", "a second directory beside this one"),
+            bind(":diff dir", "differences with another tree"),
+            bind(":sync dest", "mirror here to dest"),
+            bind(":chmod / :chown", "permissions and owners"),
+            bind(":tag color", "tag the selection, :tag! color untags"),
+            bind(":xattr / :ln", "attributes and links"),
+            bind(":hash / :verify", "checksums"),
+            bind(":note text", "a note on the selection, :note! drops it"),
+            bind(":starred / :searches", "starred entries, saved searches"),
+            bind(":stats / :dashboard", "what the tree holds"),
+            bind(":tree", "directory tree sidebar"),
+            bind(":snapshot / :changes", "what changed since"),
+            bind(":history / :conflicts", "older versions, sync conflicts"),
+            bind(":gitlog / :blame", "history of the selected file"),
+            bind(":tail", "follow the selected file, :tail! pauses"),
+            bind(":oplog", "operations, undo and redo"),
+            bind(":volumes / :mtp", "drives and devices"),
+            bind(":!cmd %", "shell command on the selection"),
+            bind(":script name", "run a Rhai script"),
+            bind(":terminal", "terminal here"),
+            bind(":fly", "first-person fly-through"),
+            bind(":debug", "frame rate, counts and timings"),
+            bind(
+                ":set option",
+                "accessible autorename crt dryrun fullscreen gitignore hidden minimap number ontop preview readonly relativenumber sound wireframe",
+            ),
+            bind(":set quality low|medium|high", "lighter rendering for weak GPUs"),
+            bind(":set heightscale sqrt", "how sizes become heights"),
+            bind(":config edit|reload", "the config file"),
+            bind(":tutor / :whatsnew", "walkthrough, what's new"),
+            bind(":q", "quit"),
+        ],
+    },
+];
+
+/// Marker for the overlay
+#[derive(Component)]
+struct HelpPanel;
+
+pub struct HelpPlugin;
+
+impl Plugin for HelpPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_help_key, close_on_dismiss));
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// `?` - open or close the overlay
+fn handle_help_key(
+    mut commands: Commands,
+    mut key_events: EventReader<KeyboardInput>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    fly: Res<FlyCamera>,
+    config: Res<Config>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<HelpPanel>>,
+) {
+    let pressed = key_events.read().any(|event| {
+        event.state == ButtonState::Pressed
+            && matches!(&event.logical_key, Key::Character(c) if c == "?")
+    });
+    if !pressed || *vim_mode != VimMode::Normal || prompt.pending.is_some() || fly.enabled {
+        return;
+    }
+    if focus.is_open(Panel::Help) {
+        close_panel(&mut commands, &mut focus, &panel_query);
+    } else {
+        focus.open(Panel::Help);
+        spawn_panel(&mut commands, &config);
+    }
+}
+
+fn close_on_dismiss(
+    mut commands: Commands,
+    mut dismissed: EventReader<Dismiss>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<HelpPanel>>,
+) {
+    if dismissed.read().any(|Dismiss(panel)| *panel == Panel::Help) {
+        close_panel(&mut commands, &mut focus, &panel_query);
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+/// A section as text: its title, then a line per binding
+fn section_text(title: &str, bindings: &[(String, String)]) -> Text {
+    let mut sections = vec![TextSection::new(
+        format!("{}\n", title),
+        panel_style(FELIPE_ORANGE),
+    )];
+    for (keys, what) in bindings {
+        sections.push(TextSection::new(
+            format!("{:<16} ", keys),
+            panel_style(FELIPE_ORANGE),
+        ));
+        sections.push(TextSection::new(
+            format!("{}\n", what),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    Text::from_sections(sections)
+}
+
+fn spawn_panel(commands: &mut Commands, config: &Config) {
+    let mut texts: Vec<Text> = KEY_MAP
+        .iter()
+        .map(|section| {
+            let bindings: Vec<(String, String)> = section
+                .bindings
+                .iter()
+                .map(|binding| (binding.keys.to_string(), tr(binding.what).to_string()))
+                .collect();
+            section_text(tr(section.title), &bindings)
+        })
+        .collect();
+    let user: Vec<(String, String)> = config
+        .commands
+        .iter()
+        .map(|command| {
+            let keys = match &command.key {
+                Some(key) => format!("{}  :{}", key, command.name),
+                None => format!(":{}", command.name),
+            };
+            (keys, command.run.clone())
+        })
+        .collect();
+    if !user.is_empty() {
+        texts.push(section_text(tr("YOUR COMMANDS"), &user));
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    right: Val::Px(20.0),
+                    bottom: Val::Px(40.0),
+                    padding: UiRect::all(Val::Px(16.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    // Sections run down and wrap into columns
+                    flex_direction: FlexDirection::Column,
+                    flex_wrap: FlexWrap::Wrap,
+                    align_content: AlignContent::FlexStart,
+                    row_gap: Val::Px(12.0),
+                    column_gap: Val::Px(32.0),
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.95)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            HelpPanel,
+            Focusable(Panel::Help),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                tr("HELP  ?/Esc:close"),
+                panel_style(FELIPE_ORANGE),
+            ));
+            for text in texts {
+                parent.spawn(TextBundle { text, ..default() });
+            }
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<HelpPanel>>,
+) {
+    focus.close(Panel::Help);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,381 @@
+//! `:history` - browse older versions of the current directory
+//!
+//! Filesystem snapshots (btrfs/snapper `.snapshots`, ZFS `.zfs/snapshot`,
+//! Time Machine backups) are detected for the current directory and listed in
+//! a panel. Enter opens the directory as it was in that snapshot; restoring is
+//! just a yank there and a paste back home, so no special restore path exists.
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::{
+    CurrentDirectory, StatusMessage, UiElement, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+// =============================================================================
+// Providers
+// =============================================================================
+
+/// The current directory as it was in one filesystem snapshot
+struct FsSnapshot {
+    provider: &'static str,
+    name: String,
+    taken: Option<SystemTime>,
+    /// Same directory inside the snapshot
+    path: PathBuf,
+}
+
+/// Every snapshot that still holds `dir`, newest first
+fn find_snapshots(dir: &Path) -> Vec<FsSnapshot> {
+    let mut found = Vec::new();
+    for ancestor in dir.ancestors() {
+        let Ok(relative) = dir.strip_prefix(ancestor) else {
+            continue;
+        };
+        // ZFS: <dataset>/.zfs/snapshot/<name>/...
+        collect_snapshots(&mut found, "zfs", &ancestor.join(".zfs/snapshot"), |root| {
+            root.join(relative)
+        });
+        // snapper (btrfs): <subvolume>/.snapshots/<n>/snapshot/...
+        collect_snapshots(&mut found, "btrfs", &ancestor.join(".snapshots"), |root| {
+            let inner = root.join("snapshot");
+            if inner.is_dir() {
+                inner.join(relative)
+            } else {
+                root.join(relative)
+            }
+        });
+    }
+    found.extend(time_machine_snapshots(dir));
+
+    found.sort_by(|a, b| b.taken.cmp(&a.taken).then_with(|| b.name.cmp(&a.name)));
+    found
+}
+
+fn collect_snapshots(
+    found: &mut Vec<FsSnapshot>,
+    provider: &'static str,
+    snapshot_dir: &Path,
+    locate: impl Fn(&Path) -> PathBuf,
+) {
+    let Ok(read_dir) = std::fs::read_dir(snapshot_dir) else {
+        return;
+    };
+    for entry in read_dir.filter_map(|e| e.ok()) {
+        let root = entry.path();
+        let path = locate(&root);
+        if !path.is_dir() {
+            continue;
+        }
+        found.push(FsSnapshot {
+            provider,
+            name: entry.file_name().to_string_lossy().to_string(),
+            taken: entry.metadata().and_then(|m| m.modified()).ok(),
+            path,
+        });
+    }
+}
+
+/// Backups listed by `tmutil`, each holding volume folders that mirror `/`
+#[cfg(target_os = "macos")]
+fn time_machine_snapshots(dir: &Path) -> Vec<FsSnapshot> {
+    let Ok(output) = std::process::Command::new("tmutil")
+        .arg("listbackups")
+        .output()
+    else {
+        return Vec::new();
+    };
+    let Ok(relative) = dir.strip_prefix("/") else {
+        return Vec::new();
+    };
+
+    let mut found = Vec::new();
+    for backup in String::from_utf8_lossy(&output.stdout).lines() {
+        let backup = Path::new(backup.trim());
+        let Ok(volumes) = std::fs::read_dir(backup) else {
+            continue;
+        };
+        for volume in volumes.filter_map(|e| e.ok()) {
+            let path = volume.path().join(relative);
+            if path.is_dir() {
+                found.push(FsSnapshot {
+                    provider: "time machine",
+                    name: backup
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                    taken: volume.metadata().and_then(|m| m.modified()).ok(),
+                    path,
+                });
+                break;
+            }
+        }
+    }
+    found
+}
+
+#[cfg(not(target_os = "macos"))]
+fn time_machine_snapshots(_dir: &Path) -> Vec<FsSnapshot> {
+    Vec::new()
+}
+
+// =============================================================================
+// View
+// =============================================================================
+
+struct HistoryRow {
+    snapshot: FsSnapshot,
+    /// Size of the selected file in this snapshot, if it existed there
+    selected_size: Option<u64>,
+}
+
+/// `:history` panel state
+#[derive(Resource, Default)]
+pub struct HistoryView {
+    rows: Vec<HistoryRow>,
+    /// Name of the entry that was selected when the panel opened
+    selected_name: Option<String>,
+    cursor: usize,
+    /// Live directory to come back to while browsing a snapshot
+    return_path: Option<PathBuf>,
+}
+
+/// Marker for the history panel
+#[derive(Component)]
+struct HistoryPanel;
+
+/// Marker for the history panel text
+#[derive(Component)]
+struct HistoryText;
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HistoryView::default()).add_systems(
+            Update,
+            (
+                handle_history_command,
+                handle_history_keys,
+                update_history_panel,
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_history_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut view: ResMut<HistoryView>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<HistoryPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::History {
+            continue;
+        }
+        if focus.is_open(Panel::History) {
+            close_panel(&mut commands, &mut focus, &panel_query);
+            continue;
+        }
+        // While browsing a snapshot, :history goes back to the live directory
+        if let Some(return_path) = view.return_path.take() {
+            current_dir.pending_select = current_dir
+                .entries
+                .get(current_dir.selected_index)
+                .map(|entry| return_path.join(&entry.name));
+            current_dir.path = return_path;
+            current_dir.needs_reload = true;
+            status.0 = "Back to the live directory".to_string();
+            continue;
+        }
+
+        let snapshots = find_snapshots(&current_dir.path);
+        if snapshots.is_empty() {
+            status.0 = format!(
+                "No btrfs, ZFS or Time Machine snapshots hold {}",
+                current_dir.path.display()
+            );
+            continue;
+        }
+        let selected = current_dir
+            .entries
+            .get(current_dir.selected_index)
+            .filter(|entry| entry.name != ".." && !entry.is_dir);
+        view.selected_name = selected.map(|entry| entry.name.clone());
+        view.rows = snapshots
+            .into_iter()
+            .map(|snapshot| HistoryRow {
+                selected_size: view
+                    .selected_name
+                    .as_ref()
+                    .and_then(|name| std::fs::metadata(snapshot.path.join(name)).ok())
+                    .map(|metadata| metadata.len()),
+                snapshot,
+            })
+            .collect();
+        view.cursor = 0;
+        focus.open(Panel::History);
+        spawn_panel(&mut commands);
+    }
+}
+
+fn handle_history_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut view: ResMut<HistoryView>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<HistoryPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::History)
+    {
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    if !focus.has_focus(Panel::History) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    let count = view.rows.len();
+
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        view.cursor = (view.cursor + 1).min(count.saturating_sub(1));
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        view.cursor = view.cursor.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::Enter) || keyboard.just_pressed(KeyCode::KeyL) {
+        if let Some(row) = view.rows.get(view.cursor) {
+            let snapshot_path = row.snapshot.path.clone();
+            status.0 = format!(
+                "Browsing {} snapshot {} (read-only) - y to yank, :history to return, p to restore",
+                row.snapshot.provider, row.snapshot.name
+            );
+            current_dir.pending_select = view
+                .selected_name
+                .as_ref()
+                .map(|name| snapshot_path.join(name));
+            let live = std::mem::replace(&mut current_dir.path, snapshot_path);
+            current_dir.needs_reload = true;
+            view.return_path = Some(live);
+        }
+        close_panel(&mut commands, &mut focus, &panel_query);
+    }
+}
+
+fn update_history_panel(
+    view: Res<HistoryView>,
+    focus: Res<Focus>,
+    config: Res<Config>,
+    mut text_query: Query<&mut Text, With<HistoryText>>,
+) {
+    if !focus.is_open(Panel::History) {
+        return;
+    }
+    let mut sections = vec![TextSection::new(
+        "HISTORY  j/k:select  Enter:browse  Esc:close\n",
+        panel_style(FELIPE_ORANGE),
+    )];
+    if let Some(name) = &view.selected_name {
+        sections.push(TextSection::new(
+            format!("size of {} in each snapshot:\n", name),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+
+    for (i, row) in view.rows.iter().enumerate() {
+        let selected = i == view.cursor;
+        let size = match (&view.selected_name, row.selected_size) {
+            (None, _) => String::new(),
+            (Some(_), Some(size)) => format!("  {}", config.format.size(size)),
+            (Some(_), None) => "  (absent)".to_string(),
+        };
+        sections.push(TextSection::new(
+            format!(
+                "{} [{}] {}{}{}\n",
+                if selected { ">" } else { " " },
+                row.snapshot.provider,
+                row.snapshot.name,
+                row.snapshot
+                    .taken
+                    .map(|taken| format!("  {}", config.format.date(taken)))
+                    .unwrap_or_default(),
+                size
+            ),
+            panel_style(if selected {
+                FELIPE_ORANGE
+            } else {
+                FELIPE_ORANGE_DIM
+            }),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    right: Val::Px(10.0),
+                    max_width: Val::Px(520.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            HistoryPanel,
+            Focusable(Panel::History),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), HistoryText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<HistoryPanel>>,
+) {
+    focus.close(Panel::History);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
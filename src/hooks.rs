@@ -0,0 +1,250 @@
+//! Hooks - react to what Felipe does without forking it
+//!
+//! Apps embedding `FelipePlugin` read these like any Bevy event:
+//!
+//! ```text
+//! DirectoryLoaded    a listing was (re)loaded
+//! EntrySelected      the cursor moved to another entry
+//! EntryOpened        a file was handed to its default app
+//! OperationFinished  an undoable operation was recorded (see oplog.rs)
+//! OperationFailed    an operation failed (see glitch.rs)
+//! ```
+//!
+//! The config can run a shell command or a script on them:
+//!
+//! ```toml
+//! [[hooks]]
+//! on = "open"                            # load, select, open, operation or error
+//! run = "echo % >> ~/.felipe-opened"     # % is the path, like :! (see shell.rs)
+//!
+//! [[hooks]]
+//! on = "load"
+//! script = "summary"                     # scripts/summary.rhai, as :script runs it
+//! ```
+//!
+//! Commands run in the background in the directory being shown, with
+//! `FELIPE_HOOK` set to the event and `FELIPE_PATH` to its path (for
+//! operations `FELIPE_LABEL` says what was done); their output is dropped.
+//! They don't run in read-only mode. A script hook is skipped while another
+//! script is still running.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::glitch::OperationFailed;
+use crate::oplog::OperationLog;
+use crate::ops;
+use crate::script::Scripts;
+use crate::shell::{expand, shell_process, ChildEnv};
+use crate::{CurrentDirectory, StatusMessage};
+
+/// Sent each time `load_directory` lists a directory
+#[derive(Event, Clone, Debug)]
+pub struct DirectoryLoaded {
+    pub path: PathBuf,
+    /// Entries listed, `..` included
+    pub entries: usize,
+}
+
+/// Sent when the cursor lands on another entry
+#[derive(Event, Clone, Debug)]
+pub struct EntrySelected {
+    pub path: PathBuf,
+    pub index: usize,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Sent when a file is opened with its default app
+#[derive(Event, Clone, Debug)]
+pub struct EntryOpened {
+    pub path: PathBuf,
+}
+
+/// Sent when an operation lands in the operation log
+#[derive(Event, Clone, Debug)]
+pub struct OperationFinished {
+    /// Same label `:oplog` shows, e.g. "paste 3 entries"
+    pub label: String,
+    pub steps: usize,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookEvent {
+    Load,
+    Select,
+    Open,
+    Operation,
+    Error,
+}
+
+impl HookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            HookEvent::Load => "load",
+            HookEvent::Select => "select",
+            HookEvent::Open => "open",
+            HookEvent::Operation => "operation",
+            HookEvent::Error => "error",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    pub on: HookEvent,
+    /// Shell command line, `%` being the event's path
+    pub run: Option<String>,
+    /// Script name, as given to `:script`
+    pub script: Option<String>,
+}
+
+/// One event as the config hooks see it
+struct Fired {
+    event: HookEvent,
+    path: Option<PathBuf>,
+    label: Option<String>,
+}
+
+pub struct HooksPlugin;
+
+impl Plugin for HooksPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DirectoryLoaded>()
+            .add_event::<EntrySelected>()
+            .add_event::<EntryOpened>()
+            .add_event::<OperationFinished>()
+            .add_systems(
+                Update,
+                (watch_selection.after(crate::load_directory), watch_oplog),
+            )
+            .add_systems(PostUpdate, run_hooks);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+pub fn watch_selection(
+    current_dir: Res<CurrentDirectory>,
+    mut shown: Local<Option<PathBuf>>,
+    mut selected: EventWriter<EntrySelected>,
+) {
+    if !current_dir.is_changed() || current_dir.needs_reload {
+        return;
+    }
+    let entry = current_dir.entries.get(current_dir.selected_index);
+    if shown.as_ref() == entry.map(|entry| &entry.path) {
+        return;
+    }
+    *shown = entry.map(|entry| entry.path.clone());
+    if let Some(entry) = entry {
+        selected.send(EntrySelected {
+            path: entry.path.clone(),
+            index: current_dir.selected_index,
+            is_dir: entry.is_dir,
+            size: entry.size,
+        });
+    }
+}
+
+/// Groups appended to the operation log since the last frame
+fn watch_oplog(
+    oplog: Res<OperationLog>,
+    mut seen: Local<usize>,
+    mut finished: EventWriter<OperationFinished>,
+) {
+    for group in oplog.groups.iter().skip(*seen) {
+        finished.send(OperationFinished {
+            label: group.label.clone(),
+            steps: group.steps.len(),
+        });
+    }
+    *seen = oplog.groups.len();
+}
+
+fn run_hooks(
+    mut loaded: EventReader<DirectoryLoaded>,
+    mut selected: EventReader<EntrySelected>,
+    mut opened: EventReader<EntryOpened>,
+    mut finished: EventReader<OperationFinished>,
+    mut failed: EventReader<OperationFailed>,
+    config: Res<Config>,
+    scripts: Res<Scripts>,
+    current_dir: Res<CurrentDirectory>,
+    child_env: Res<ChildEnv>,
+    mut run_commands: EventWriter<RunCommand>,
+    mut status: ResMut<StatusMessage>,
+) {
+    let at = |event, path: &Path| Fired {
+        event,
+        path: Some(path.to_path_buf()),
+        label: None,
+    };
+    let fired: Vec<Fired> = loaded
+        .read()
+        .map(|e| at(HookEvent::Load, &e.path))
+        .chain(selected.read().map(|e| at(HookEvent::Select, &e.path)))
+        .chain(opened.read().map(|e| at(HookEvent::Open, &e.path)))
+        .chain(finished.read().map(|e| Fired {
+            event: HookEvent::Operation,
+            path: None,
+            label: Some(e.label.clone()),
+        }))
+        .chain(failed.read().map(|e| Fired {
+            event: HookEvent::Error,
+            path: e.path.clone(),
+            label: None,
+        }))
+        .collect();
+    if config.hooks.is_empty() {
+        return;
+    }
+    // A script hook starts once per frame at most: one script runs at a time
+    let mut script_started = scripts.is_running();
+    for fired in &fired {
+        for hook in config.hooks.iter().filter(|hook| hook.on == fired.event) {
+            if let Some(line) = hook.run.as_ref().filter(|_| !ops::is_read_only()) {
+                if let Err(err) = spawn(line, fired, &current_dir.path, &child_env) {
+                    status.0 = format!("Hook on {}: {}", fired.event.name(), err);
+                }
+            }
+            if let Some(name) = &hook.script {
+                if !script_started {
+                    script_started = true;
+                    run_commands.send(RunCommand(Command::Script(Some(name.clone()))));
+                }
+            }
+        }
+    }
+}
+
+/// Start `line` for `fired` and reap it in the background
+fn spawn(line: &str, fired: &Fired, dir: &Path, child_env: &ChildEnv) -> Result<(), String> {
+    let path = fired.path.as_deref();
+    let line = expand(line, path, &path.into_iter().collect::<Vec<_>>())?;
+    let mut process = shell_process(&line);
+    child_env
+        .apply(&mut process)
+        .current_dir(dir)
+        .env("FELIPE_HOOK", fired.event.name())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(path) = path {
+        process.env("FELIPE_PATH", path);
+    }
+    if let Some(label) = &fired.label {
+        process.env("FELIPE_LABEL", label);
+    }
+    let mut child = process.spawn().map_err(|err| err.to_string())?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
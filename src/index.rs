@@ -0,0 +1,263 @@
+//! Search index - `:find` across whole drives without walking them (feature
+//! `index`)
+//!
+//! With roots in the config, a background thread walks them into an SQLite
+//! database in Felipe's data directory, then keeps it up to date from the
+//! filesystem's change notifications. `:find` searches the index instead of
+//! the current directory, so matches from anywhere under the roots show up as
+//! you type; `F` still walks the subtree below the current directory. The
+//! index outlives the session: the next start searches it right away while
+//! the walk refreshes it.
+//!
+//! ```toml
+//! [index]
+//! roots = ["~", "/mnt/data"]
+//! ```
+//!
+//! The walk skips what the listing hides: dotfiles unless `hidden = true`, and
+//! what git ignores with `gitignore = true`. Changed roots take effect on the
+//! next start.
+
+use bevy::prelude::*;
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bookmarks::expand_home;
+use crate::config::{Config, ListingConfig};
+use crate::{data_dir, gitignore};
+
+/// Matches fetched per search, before they're ranked
+const SEARCH_LIMIT: i64 = 5_000;
+/// Paths written per transaction
+const BATCH_SIZE: usize = 10_000;
+
+/// There's an index to search, from this session or the last
+static READY: AtomicBool = AtomicBool::new(false);
+/// Connection searches go through; the indexer has its own
+static READER: Mutex<Option<Connection>> = Mutex::new(None);
+
+fn index_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("index.sqlite"))
+}
+
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+    // WAL lets searches read while the indexer writes
+    connection.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         CREATE TABLE IF NOT EXISTS files (
+             path TEXT PRIMARY KEY,
+             name TEXT NOT NULL,
+             seen INTEGER NOT NULL
+         );",
+    )?;
+    Ok(connection)
+}
+
+/// Indexed paths whose name has the letters of `query` in order (the whole
+/// path once the query has a separator); None while there's no index
+pub fn search(query: &str) -> Option<Vec<String>> {
+    if !READY.load(Ordering::Relaxed) {
+        return None;
+    }
+    let mut reader = READER.lock().unwrap_or_else(|e| e.into_inner());
+    if reader.is_none() {
+        *reader = open(&index_path()?).ok();
+    }
+    let connection = reader.as_ref()?;
+
+    let mut pattern = String::from("%");
+    for letter in query.chars().filter(|c| *c != ' ') {
+        pattern.push_str(&escape_like(&letter.to_string()));
+        pattern.push('%');
+    }
+    let column = if query.contains(['/', '\\']) {
+        "path"
+    } else {
+        "name"
+    };
+    let mut statement = connection
+        .prepare_cached(&format!(
+            "SELECT path FROM files WHERE {} LIKE ?1 ESCAPE '\\' LIMIT ?2",
+            column
+        ))
+        .ok()?;
+    let paths = statement
+        .query_map(params![pattern, SEARCH_LIMIT], |row| row.get(0))
+        .ok()?
+        .filter_map(Result::ok)
+        .collect();
+    Some(paths)
+}
+
+/// `text` matching only itself in a LIKE pattern
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+pub struct IndexPlugin;
+
+impl Plugin for IndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_indexer);
+    }
+}
+
+fn start_indexer(config: Res<Config>) {
+    let roots: Vec<PathBuf> = config
+        .index
+        .roots
+        .iter()
+        .map(|root| expand_home(root))
+        .collect();
+    if roots.is_empty() {
+        return;
+    }
+    let Some(path) = index_path() else {
+        return;
+    };
+    let listing = config.listing.clone();
+    std::thread::spawn(move || {
+        if let Err(err) = run_indexer(&path, &roots, &listing) {
+            warn!("Search index stopped: {}", err);
+        }
+    });
+}
+
+/// Walk `roots` into the index, then follow their changes for good
+fn run_indexer(
+    path: &Path,
+    roots: &[PathBuf],
+    listing: &ListingConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut connection = open(path)?;
+    let indexed: i64 = connection.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))?;
+    if indexed > 0 {
+        READY.store(true, Ordering::Relaxed);
+    }
+
+    // Watching starts before the walk so nothing changed meanwhile is missed
+    let (sender, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for root in roots {
+        if let Err(err) = watcher.watch(root, RecursiveMode::Recursive) {
+            warn!(
+                "Cannot follow changes under {} for the search index: {}",
+                root.display(),
+                err
+            );
+        }
+    }
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    for root in roots {
+        add_tree(&mut connection, root, listing, stamp)?;
+    }
+    // Whatever this walk didn't see is gone, or under a root no longer listed
+    connection.execute("DELETE FROM files WHERE seen < ?1", [stamp])?;
+    READY.store(true, Ordering::Relaxed);
+
+    for event in changes.into_iter().flatten() {
+        apply_change(&mut connection, &event, roots, listing, stamp)?;
+    }
+    Ok(())
+}
+
+/// Add `root` and everything the listing shows below it
+fn add_tree(
+    connection: &mut Connection,
+    root: &Path,
+    listing: &ListingConfig,
+    stamp: i64,
+) -> rusqlite::Result<()> {
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for entry in gitignore::walker(root, listing).build().flatten() {
+        batch.push(entry.into_path());
+        if batch.len() == BATCH_SIZE {
+            insert(connection, &batch, stamp)?;
+            batch.clear();
+        }
+    }
+    insert(connection, &batch, stamp)
+}
+
+fn insert(connection: &mut Connection, paths: &[PathBuf], stamp: i64) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    {
+        let mut statement = transaction.prepare_cached(
+            "INSERT OR REPLACE INTO files (path, name, seen) VALUES (?1, ?2, ?3)",
+        )?;
+        for path in paths {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            statement.execute(params![path.to_string_lossy(), name, stamp])?;
+        }
+    }
+    transaction.commit()
+}
+
+/// Bring the index in line with one change notification
+fn apply_change(
+    connection: &mut Connection,
+    event: &Event,
+    roots: &[PathBuf],
+    listing: &ListingConfig,
+    stamp: i64,
+) -> rusqlite::Result<()> {
+    if !matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+    ) {
+        return Ok(());
+    }
+    for path in &event.paths {
+        if !listing.hidden && is_hidden_below(path, roots) {
+            continue;
+        }
+        if std::fs::symlink_metadata(path).is_ok() {
+            // A directory moved in brings everything below it
+            add_tree(connection, path, listing, stamp)?;
+        } else {
+            let below = format!(
+                "{}%",
+                escape_like(&format!("{}{}", path.to_string_lossy(), MAIN_SEPARATOR))
+            );
+            connection.execute(
+                "DELETE FROM files WHERE path = ?1 OR path LIKE ?2 ESCAPE '\\'",
+                params![path.to_string_lossy(), below],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` is a dotfile or inside one, under the root it's in
+fn is_hidden_below(path: &Path, roots: &[PathBuf]) -> bool {
+    roots
+        .iter()
+        .filter_map(|root| path.strip_prefix(root).ok())
+        .any(|rest| {
+            rest.components()
+                .any(|part| part.as_os_str().to_string_lossy().starts_with('.'))
+        })
+}
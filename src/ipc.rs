@@ -0,0 +1,352 @@
+//! Control socket - remote-control a running Felipe (`--features ipc`, Unix)
+//!
+//! Felipe listens on `--socket PATH`, by default `felipe-<pid>.sock` in
+//! `$XDG_RUNTIME_DIR` (else the temp directory), and puts the path in
+//! `FELIPE_SOCKET` for the programs it starts (`ChildEnv` in shell.rs). Only
+//! the user running Felipe can connect, and a path that holds anything but a
+//! dead socket is refused. Clients send one JSON object per line and get one
+//! back, `{"ok":true}` or `{"ok":false,"error":"..."}`:
+//!
+//! ```text
+//! {"cmd":"cd","path":"/tmp"}              show a directory
+//! {"cmd":"select","name":"foo.rs"}        select an entry of the listing
+//! {"cmd":"select","path":"/tmp/foo.rs"}   show its directory and select it
+//! {"cmd":"command","line":"sort size"}    run a `:` command
+//! {"cmd":"get"}                           {"ok":true,"path":...,"selected":...}
+//! {"cmd":"subscribe"}                     then follow the events below
+//! ```
+//!
+//! Subscribed clients get `{"event":"navigate","path":...,"entries":12}` when
+//! another directory is shown and `{"event":"select","path":...,"index":3}`
+//! when the selection moves. For example, from a shell:
+//!
+//! ```sh
+//! echo '{"cmd":"cd","path":"/tmp"}' | socat - UNIX-CONNECT:"$FELIPE_SOCKET"
+//! ```
+
+use bevy::prelude::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::{DirBuilder, Permissions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::command::{parse_command, Command, CommandLine, RunCommand};
+use crate::hooks::{DirectoryLoaded, EntrySelected};
+use crate::shell::ChildEnv;
+use crate::{update_camera_target, CameraState, CurrentDirectory, Layouts};
+
+/// How long a write may wait on a client that doesn't read
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Where to listen; None for the default path
+#[derive(Resource, Default)]
+pub struct IpcSocket(pub Option<PathBuf>);
+
+/// What the connection threads hand to the app
+enum Message {
+    Connected(usize, UnixStream),
+    Request(usize, String),
+    Closed(usize),
+}
+
+struct Client {
+    stream: UnixStream,
+    subscribed: bool,
+}
+
+/// The socket being listened on and its clients
+#[derive(Resource, Default)]
+struct Ipc {
+    path: Option<PathBuf>,
+    messages: Option<Mutex<Receiver<Message>>>,
+    clients: HashMap<usize, Client>,
+}
+
+impl Ipc {
+    /// Write one JSON line to a client; one that can't take it is dropped
+    fn send(&mut self, id: usize, value: &Value) {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return;
+        };
+        if writeln!(client.stream, "{}", value).is_err() {
+            self.clients.remove(&id);
+        }
+    }
+
+    fn broadcast(&mut self, value: &Value) {
+        let subscribed: Vec<usize> = self
+            .clients
+            .iter()
+            .filter(|(_, client)| client.subscribed)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in subscribed {
+            self.send(id, value);
+        }
+    }
+}
+
+pub struct IpcPlugin;
+
+impl Plugin for IpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<IpcSocket>()
+            .init_resource::<Ipc>()
+            .add_systems(Startup, start_listening)
+            .add_systems(
+                Update,
+                (
+                    handle_requests,
+                    send_events.after(crate::hooks::watch_selection),
+                ),
+            )
+            .add_systems(Last, remove_socket_on_exit);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn start_listening(socket: Res<IpcSocket>, mut ipc: ResMut<Ipc>, mut child_env: ResMut<ChildEnv>) {
+    let path = socket.0.clone().unwrap_or_else(default_path);
+    // A socket left behind by a crashed Felipe would make bind fail; anything
+    // else at the path is left alone
+    let is_socket = std::fs::symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_socket());
+    if is_socket && UnixStream::connect(&path).is_err() {
+        let _ = std::fs::remove_file(&path);
+    }
+    let listener = match bind_private(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("felipe: cannot listen on {}: {}", path.display(), err);
+            return;
+        }
+    };
+    child_env
+        .0
+        .push(("FELIPE_SOCKET", path.clone().into_os_string()));
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || accept(listener, sender));
+    ipc.path = Some(path);
+    ipc.messages = Some(Mutex::new(receiver));
+}
+
+/// Listen on `path`, open to this user alone from the moment it exists: the
+/// socket is made in a directory only we can enter, narrowed to 0600 there and
+/// then linked into place, which fails rather than replace what's at `path`
+fn bind_private(path: &Path) -> io::Result<UnixListener> {
+    let dir = path.with_file_name(format!(".felipe-{}.bind", std::process::id()));
+    DirBuilder::new().mode(0o700).create(&dir)?;
+    let bound = (|| {
+        let inner = dir.join("socket");
+        let listener = UnixListener::bind(&inner)?;
+        std::fs::set_permissions(&inner, Permissions::from_mode(0o600))?;
+        std::fs::hard_link(&inner, path)?;
+        Ok(listener)
+    })();
+    let _ = std::fs::remove_dir_all(&dir);
+    bound
+}
+
+fn handle_requests(
+    mut ipc: ResMut<Ipc>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut camera_state: ResMut<CameraState>,
+    layouts: Res<Layouts>,
+    mut command_line: ResMut<CommandLine>,
+    mut run_commands: EventWriter<RunCommand>,
+) {
+    let messages: Vec<Message> = match &ipc.messages {
+        Some(messages) => match messages.lock() {
+            Ok(messages) => messages.try_iter().collect(),
+            Err(_) => return,
+        },
+        None => return,
+    };
+    for message in messages {
+        match message {
+            Message::Connected(id, stream) => {
+                let client = Client {
+                    stream,
+                    subscribed: false,
+                };
+                ipc.clients.insert(id, client);
+            }
+            Message::Closed(id) => {
+                ipc.clients.remove(&id);
+            }
+            Message::Request(id, line) => {
+                let request = serde_json::from_str::<Value>(&line)
+                    .map_err(|err| format!("bad JSON: {}", err))
+                    .and_then(|request| handle(&request, &mut current_dir));
+                let reply = match request {
+                    Ok(Reply::Done) => json!({ "ok": true }),
+                    Ok(Reply::Selected) => {
+                        update_camera_target(&current_dir, &layouts, &mut camera_state);
+                        json!({ "ok": true })
+                    }
+                    Ok(Reply::Run(command)) => {
+                        // Commands act on the selection, not a visual range left from before
+                        command_line.range = None;
+                        command_line.marked.clear();
+                        run_commands.send(RunCommand(command));
+                        json!({ "ok": true })
+                    }
+                    Ok(Reply::State) => state(&current_dir),
+                    Ok(Reply::Subscribed) => {
+                        if let Some(client) = ipc.clients.get_mut(&id) {
+                            client.subscribed = true;
+                        }
+                        json!({ "ok": true })
+                    }
+                    Err(err) => json!({ "ok": false, "error": err }),
+                };
+                ipc.send(id, &reply);
+            }
+        }
+    }
+}
+
+fn send_events(
+    mut ipc: ResMut<Ipc>,
+    mut loaded: EventReader<DirectoryLoaded>,
+    mut selected: EventReader<EntrySelected>,
+    mut shown_dir: Local<Option<PathBuf>>,
+) {
+    let events: Vec<Value> = loaded
+        .read()
+        .filter(|loaded| {
+            // Reloads of the same directory aren't navigation
+            let new = shown_dir.as_ref() != Some(&loaded.path);
+            *shown_dir = Some(loaded.path.clone());
+            new
+        })
+        .map(
+            |loaded| json!({ "event": "navigate", "path": loaded.path, "entries": loaded.entries }),
+        )
+        .chain(selected.read().map(
+            |selected| json!({ "event": "select", "path": selected.path, "index": selected.index }),
+        ))
+        .collect();
+    for event in &events {
+        ipc.broadcast(event);
+    }
+}
+
+fn remove_socket_on_exit(mut exit_events: EventReader<AppExit>, ipc: Res<Ipc>) {
+    if exit_events.read().next().is_none() {
+        return;
+    }
+    if let Some(path) = &ipc.path {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// =============================================================================
+// Requests
+// =============================================================================
+
+/// What's left to do for a request once it's understood
+enum Reply {
+    Done,
+    /// The selection moved within the listing
+    Selected,
+    Run(Command),
+    State,
+    Subscribed,
+}
+
+fn handle(request: &Value, current_dir: &mut CurrentDirectory) -> Result<Reply, String> {
+    let text = |key: &str| {
+        request[key]
+            .as_str()
+            .ok_or_else(|| format!("\"{}\" is missing", key))
+    };
+    match text("cmd")? {
+        "cd" => {
+            let path = PathBuf::from(text("path")?);
+            if !path.is_dir() {
+                return Err(format!("not a directory: {}", path.display()));
+            }
+            current_dir.set_path(path);
+        }
+        "select" => match (request["name"].as_str(), request["path"].as_str()) {
+            (Some(name), _) => {
+                let index = current_dir
+                    .entries
+                    .iter()
+                    .position(|entry| entry.name == name)
+                    .ok_or_else(|| format!("no entry {}", name))?;
+                current_dir.selected_index = index;
+                return Ok(Reply::Selected);
+            }
+            (None, Some(path)) => {
+                let path = Path::new(path);
+                let parent = path
+                    .parent()
+                    .filter(|_| path.exists())
+                    .ok_or_else(|| format!("no such file: {}", path.display()))?;
+                current_dir.pending_select = Some(path.to_path_buf());
+                current_dir.set_path(parent);
+            }
+            (None, None) => return Err("\"name\" or \"path\" is missing".to_string()),
+        },
+        "command" => return parse_command(text("line")?).map(Reply::Run),
+        "get" => return Ok(Reply::State),
+        "subscribe" => return Ok(Reply::Subscribed),
+        cmd => return Err(format!("unknown cmd {}", cmd)),
+    }
+    Ok(Reply::Done)
+}
+
+fn state(current_dir: &CurrentDirectory) -> Value {
+    json!({
+        "ok": true,
+        "path": current_dir.path(),
+        "selected": current_dir.selected_path(),
+        "entries": current_dir.entries.len(),
+    })
+}
+
+// =============================================================================
+// Connections (worker threads)
+// =============================================================================
+
+fn default_path() -> PathBuf {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    dir.join(format!("felipe-{}.sock", std::process::id()))
+}
+
+fn accept(listener: UnixListener, sender: Sender<Message>) {
+    for (id, stream) in listener.incoming().flatten().enumerate() {
+        let Ok(writer) = stream.try_clone() else {
+            continue;
+        };
+        let _ = writer.set_write_timeout(Some(WRITE_TIMEOUT));
+        if sender.send(Message::Connected(id, writer)).is_err() {
+            return;
+        }
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if !line.trim().is_empty() && sender.send(Message::Request(id, line)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Message::Closed(id));
+        });
+    }
+}
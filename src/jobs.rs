@@ -0,0 +1,630 @@
+//! Job queue - transfers run one at a time on a worker thread
+//!
+//! A job that takes a moment shows a progress panel: bytes copied of the
+//! total, the rate and the time left. When the queue drains, a summary of the
+//! whole batch is shown; failed items can be jumped to in the scene with Enter.
+
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::copier::CopyProgress;
+use crate::dryrun::DryRunReview;
+use crate::events::StreamEvent;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::glitch::OperationFailed;
+use crate::metadata;
+use crate::oplog::OperationLog;
+use crate::ops::{self, TransferKind, TransferPlan, TransferReport};
+use crate::overwrite::renamed_note;
+use crate::{
+    CurrentDirectory, MoveFailure, PendingPrompt, Prompt, Register, StatusMessage, UiElement,
+    VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+// =============================================================================
+// Queue
+// =============================================================================
+
+struct Job {
+    label: String,
+    plan: TransferPlan,
+    /// Confirmed in the dry-run review, so it runs even under `:set dryrun`
+    reviewed: bool,
+}
+
+struct RunningJob {
+    label: String,
+    plan: TransferPlan,
+    /// Its row in the journal, while it runs (see recovery.rs)
+    journal: Option<i64>,
+    worker: JoinHandle<TransferReport>,
+    progress: Arc<CopyProgress>,
+    started: Instant,
+}
+
+#[derive(Resource, Default)]
+pub struct JobQueue {
+    pending: VecDeque<Job>,
+    running: Option<RunningJob>,
+}
+
+impl JobQueue {
+    pub fn push(&mut self, label: String, plan: TransferPlan) {
+        self.pending.push_back(Job {
+            label,
+            plan,
+            reviewed: false,
+        });
+    }
+
+    /// Jobs running or waiting to start
+    pub fn count(&self) -> usize {
+        self.pending.len() + usize::from(self.running.is_some())
+    }
+
+    /// Label of the running job and how long it has run
+    pub fn running(&self) -> Option<(&str, Duration)> {
+        self.running
+            .as_ref()
+            .map(|running| (running.label.as_str(), running.started.elapsed()))
+    }
+
+    /// Jobs waiting to start, next first
+    pub fn queued(&self) -> impl Iterator<Item = (&str, &TransferPlan)> {
+        self.pending
+            .iter()
+            .map(|job| (job.label.as_str(), &job.plan))
+    }
+
+    /// Queue a job the dry-run review let through
+    pub fn push_reviewed(&mut self, label: String, plan: TransferPlan) {
+        self.pending.push_back(Job {
+            label,
+            plan,
+            reviewed: true,
+        });
+    }
+}
+
+// =============================================================================
+// Batch Summary
+// =============================================================================
+
+/// Totals for everything that ran since the queue was last idle
+#[derive(Default)]
+struct BatchTotals {
+    jobs: usize,
+    /// (verb, count), e.g. ("copied", 3)
+    done: Vec<(&'static str, usize)>,
+    skipped: Vec<(PathBuf, String)>,
+    failed: Vec<(PathBuf, String)>,
+    /// Sources that landed under another name, with that name
+    renamed: Vec<(PathBuf, String)>,
+}
+
+/// "Operations complete" overlay
+#[derive(Resource, Default)]
+pub struct JobSummary {
+    batch: BatchTotals,
+    shown: BatchTotals,
+    /// Index into `shown.failed`
+    cursor: usize,
+    /// Something was added to `batch` since the overlay was last considered
+    finished: bool,
+}
+
+impl JobSummary {
+    /// Count the outcome of one operation; the overlay shows once the queue is idle
+    pub fn add(&mut self, verb: &'static str, done: usize, failed: Vec<(PathBuf, String)>) {
+        let batch = &mut self.batch;
+        batch.jobs += 1;
+        match batch.done.iter_mut().find(|(v, _)| *v == verb) {
+            Some((_, n)) => *n += done,
+            None => batch.done.push((verb, done)),
+        }
+        batch.failed.extend(failed);
+        self.finished = true;
+    }
+}
+
+/// Jobs quicker than this finish without a progress panel flashing up
+const PROGRESS_DELAY: Duration = Duration::from_millis(500);
+
+/// Width of the progress bar in characters
+const BAR_WIDTH: usize = 30;
+
+/// Marker for the progress panel of the running job
+#[derive(Component)]
+struct ProgressPanel;
+
+/// Marker for the progress panel text
+#[derive(Component)]
+struct ProgressText;
+
+/// Marker for the summary panel
+#[derive(Component)]
+struct SummaryPanel;
+
+/// Marker for the summary panel text
+#[derive(Component)]
+struct SummaryText;
+
+pub struct JobsPlugin;
+
+impl Plugin for JobsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(JobQueue::default())
+            .insert_resource(JobSummary::default())
+            .add_systems(
+                Update,
+                (
+                    run_jobs,
+                    update_progress_panel,
+                    handle_summary_keys,
+                    update_summary_panel,
+                ),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn run_jobs(
+    mut commands: Commands,
+    mut queue: ResMut<JobQueue>,
+    mut summary: ResMut<JobSummary>,
+    mut oplog: ResMut<OperationLog>,
+    mut register: ResMut<Register>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut prompt: ResMut<Prompt>,
+    mut events: EventWriter<StreamEvent>,
+    mut failures: EventWriter<OperationFailed>,
+    mut focus: ResMut<Focus>,
+    mut review: ResMut<DryRunReview>,
+    config: Res<Config>,
+    panel_query: Query<Entity, With<SummaryPanel>>,
+) {
+    // Under :set dryrun new jobs wait in the review instead of starting
+    if config.operations.dry_run && queue.pending.iter().any(|job| !job.reviewed) {
+        let (held, reviewed): (VecDeque<Job>, _) = std::mem::take(&mut queue.pending)
+            .into_iter()
+            .partition(|job| !job.reviewed);
+        queue.pending = reviewed;
+        for job in held {
+            review.plan_transfer(job.label, job.plan);
+        }
+    }
+
+    if queue
+        .running
+        .as_ref()
+        .is_some_and(|running| running.worker.is_finished())
+    {
+        let RunningJob {
+            label,
+            plan,
+            journal,
+            worker,
+            ..
+        } = queue.running.take().expect("checked above");
+        let report = worker.join().unwrap_or_else(|_| TransferReport {
+            failed: plan
+                .steps
+                .iter()
+                .map(|step| (step.source.clone(), std::io::Error::other("worker crashed")))
+                .collect(),
+            ..default()
+        });
+        if let Some(id) = journal {
+            if let Err(err) = metadata::end_transfer(id) {
+                warn!("Cannot note {} as finished: {}", label, err);
+            }
+        }
+        events.send(StreamEvent::new(
+            "job_finished",
+            serde_json::json!({
+                "label": label,
+                "done": report.done,
+                "skipped": plan.skipped.len(),
+                "failed": report.failed.len(),
+                "queued": queue.pending.len(),
+            }),
+        ));
+        for (path, _) in &report.failed {
+            failures.send(OperationFailed {
+                path: Some(path.clone()),
+            });
+        }
+        // A multi-entry move stopped at its first failure: ask before going on
+        if is_atomic(&plan) && report.failed.len() == 1 {
+            let mut rest = plan.clone();
+            rest.steps.drain(..=report.done);
+            rest.skipped.clear();
+            let (path, err) = report.failed.into_iter().next().expect("checked above");
+            prompt.pending = Some(PendingPrompt::MoveFailed(MoveFailure {
+                label,
+                moved: report.steps,
+                failed: (path, err.to_string()),
+                rest,
+            }));
+            current_dir.needs_reload = true;
+            return;
+        }
+        finish_job(
+            label,
+            &plan,
+            report,
+            &mut summary,
+            &mut oplog,
+            &mut register,
+            &mut current_dir,
+            &mut status,
+        );
+    }
+
+    if summary.finished && queue.running.is_none() && queue.pending.is_empty() {
+        summary.finished = false;
+        let batch = std::mem::take(&mut summary.batch);
+        // A clean single job is fully described by the status line
+        if batch.jobs > 1 || !batch.failed.is_empty() || !batch.skipped.is_empty() {
+            for entity in panel_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            summary.shown = batch;
+            summary.cursor = 0;
+            focus.open(Panel::Jobs);
+            spawn_panel(&mut commands);
+        }
+    }
+
+    // Nothing new starts while the user is being asked something
+    if queue.running.is_none() && prompt.pending.is_none() {
+        if let Some(job) = queue.pending.pop_front() {
+            let journal = metadata::begin_transfer(&job.label, &job.plan)
+                .map_err(|err| warn!("Cannot journal {}: {}", job.label, err))
+                .ok();
+            let plan = job.plan.clone();
+            let progress = Arc::new(CopyProgress::default());
+            let worker_progress = Arc::clone(&progress);
+            let worker = std::thread::spawn(move || {
+                let whole = |step| {
+                    if let Some(id) = journal {
+                        if let Err(err) = metadata::note_whole_step(id, step) {
+                            warn!("Cannot journal a finished step: {}", err);
+                        }
+                    }
+                };
+                ops::execute_plan_tracked(&plan, is_atomic(&plan), &worker_progress, &whole)
+            });
+            status.0 = format!("Running: {}", job.label);
+            events.send(StreamEvent::new(
+                "job_started",
+                serde_json::json!({
+                    "label": job.label,
+                    "entries": job.plan.steps.len(),
+                    "queued": queue.pending.len(),
+                }),
+            ));
+            queue.running = Some(RunningJob {
+                label: job.label,
+                plan: job.plan,
+                journal,
+                worker,
+                progress,
+                started: Instant::now(),
+            });
+        }
+    }
+}
+
+/// Bytes, rate and time left of the running job, once it has run a moment
+fn update_progress_panel(
+    mut commands: Commands,
+    queue: Res<JobQueue>,
+    config: Res<Config>,
+    panel_query: Query<Entity, With<ProgressPanel>>,
+    mut text_query: Query<&mut Text, With<ProgressText>>,
+) {
+    let Some(running) = queue
+        .running
+        .as_ref()
+        .filter(|running| running.started.elapsed() >= PROGRESS_DELAY)
+    else {
+        for entity in panel_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    if panel_query.is_empty() {
+        spawn_progress_panel(&mut commands);
+    }
+
+    let (copied, total) = running.progress.bytes();
+    let elapsed = running.started.elapsed().as_secs_f64();
+    let rate = copied as f64 / elapsed.max(0.001);
+    let fraction = if total > 0 {
+        (copied as f64 / total as f64).min(1.0)
+    } else {
+        0.0
+    };
+    let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+    let eta = if copied > 0 && total > copied {
+        let seconds = ((total - copied) as f64 / rate).ceil() as u64;
+        format!("  ETA {}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        String::new()
+    };
+
+    let mut sections = vec![TextSection::new(
+        format!("RUNNING  {}\n", running.label),
+        panel_style(FELIPE_ORANGE),
+    )];
+    if !queue.pending.is_empty() {
+        sections.push(TextSection::new(
+            format!("{} more queued\n", queue.pending.len()),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    // Moves within a filesystem and links copy nothing; only copies have bytes
+    if total > 0 {
+        sections.push(TextSection::new(
+            format!(
+                "[{}{}] {:>3.0}%\n",
+                "#".repeat(filled),
+                ".".repeat(BAR_WIDTH - filled),
+                fraction * 100.0
+            ),
+            panel_style(FELIPE_ORANGE),
+        ));
+        sections.push(TextSection::new(
+            format!(
+                "{} of {}  {}/s{}",
+                config.format.size(copied),
+                config.format.size(total),
+                config.format.size(rate as u64),
+                eta
+            ),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+fn handle_summary_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut summary: ResMut<JobSummary>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<SummaryPanel>>,
+) {
+    if dismissed.read().any(|Dismiss(panel)| *panel == Panel::Jobs) {
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    if !focus.has_focus(Panel::Jobs) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    let failed = summary.shown.failed.len();
+
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        summary.cursor = (summary.cursor + 1).min(failed.saturating_sub(1));
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        summary.cursor = summary.cursor.saturating_sub(1);
+    }
+
+    if keyboard.just_pressed(KeyCode::Enter) {
+        if let Some((path, _)) = summary.shown.failed.get(summary.cursor) {
+            if let Some(parent) = path.parent() {
+                current_dir.path = parent.to_path_buf();
+                current_dir.pending_select = Some(path.clone());
+                current_dir.needs_reload = true;
+            }
+        }
+        close_panel(&mut commands, &mut focus, &panel_query);
+    }
+}
+
+fn update_summary_panel(
+    summary: Res<JobSummary>,
+    focus: Res<Focus>,
+    mut text_query: Query<&mut Text, With<SummaryText>>,
+) {
+    if !focus.is_open(Panel::Jobs) {
+        return;
+    }
+    let batch = &summary.shown;
+    let mut counts: Vec<String> = batch
+        .done
+        .iter()
+        .map(|(verb, n)| format!("{} {}", n, verb))
+        .collect();
+    counts.push(format!("{} skipped", batch.skipped.len()));
+    counts.push(format!("{} failed", batch.failed.len()));
+
+    let mut sections = vec![
+        TextSection::new("OPERATIONS COMPLETE\n", panel_style(FELIPE_ORANGE)),
+        TextSection::new(
+            format!("{}\n", counts.join(", ")),
+            panel_style(FELIPE_ORANGE),
+        ),
+    ];
+    for (i, (path, reason)) in batch.failed.iter().enumerate() {
+        let selected = i == summary.cursor;
+        sections.push(TextSection::new(
+            format!(
+                "{} FAILED {} - {}\n",
+                if selected { ">" } else { " " },
+                path.display(),
+                reason
+            ),
+            panel_style(if selected {
+                FELIPE_ORANGE
+            } else {
+                FELIPE_ORANGE_DIM
+            }),
+        ));
+    }
+    for (path, reason) in &batch.skipped {
+        sections.push(TextSection::new(
+            format!("  skipped {} - {}\n", path.display(), reason),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    for (path, name) in &batch.renamed {
+        sections.push(TextSection::new(
+            format!("  renamed {} - as {}\n", path.display(), name),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    let hint = if batch.failed.is_empty() {
+        "Esc:close"
+    } else {
+        "j/k:select  Enter:jump to failed item  Esc:close"
+    };
+    sections.push(TextSection::new(hint, panel_style(FELIPE_ORANGE_DIM)));
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Moves of several entries stop at the first failure, so they can be rolled back
+fn is_atomic(plan: &TransferPlan) -> bool {
+    plan.kind == TransferKind::Move && plan.steps.len() > 1
+}
+
+fn finish_job(
+    label: String,
+    plan: &TransferPlan,
+    report: TransferReport,
+    summary: &mut JobSummary,
+    oplog: &mut OperationLog,
+    register: &mut Register,
+    current_dir: &mut CurrentDirectory,
+    status: &mut StatusMessage,
+) {
+    let verb = plan.kind.verb();
+    let renamed = renamed_note(plan, &report);
+    status.0 = match report.failed.first() {
+        None => format!("{} {}{}", report.done, verb, renamed),
+        Some((path, err)) => format!(
+            "{} {}{}, {} failed ({}: {})",
+            report.done,
+            verb,
+            renamed,
+            report.failed.len(),
+            path.display(),
+            err
+        ),
+    };
+
+    summary.batch.skipped.extend(plan.skipped.iter().cloned());
+    summary.batch.renamed.extend(plan.renamed(&report));
+    summary.add(
+        verb,
+        report.done,
+        report
+            .failed
+            .iter()
+            .map(|(path, err)| (path.clone(), err.to_string()))
+            .collect(),
+    );
+
+    let failures = report
+        .failed
+        .iter()
+        .map(|(path, err)| format!("{}: {}", path.display(), err))
+        .collect();
+    oplog.record_outcome(label, report.steps, failures);
+    register.forget_missing();
+    current_dir.needs_reload = true;
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Percent(25.0),
+                    max_width: Val::Percent(50.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.95)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            SummaryPanel,
+            Focusable(Panel::Jobs),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), SummaryText));
+        });
+}
+
+fn spawn_progress_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Percent(25.0),
+                    max_width: Val::Percent(50.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE_DIM),
+                ..default()
+            },
+            ProgressPanel,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), ProgressText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<SummaryPanel>>,
+) {
+    focus.close(Panel::Jobs);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
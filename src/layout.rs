@@ -0,0 +1,172 @@
+//! Layouts - where the entries stand
+//!
+//! Entries are laid out by the active [`LayoutProvider`] in the [`Layouts`]
+//! resource: the built-in grid (ten entries a row) unless a plugin registers
+//! its own and `:layout name` switches to it. `:layout` alone lists them.
+//! Selection, the camera, labels, link arrows, the trail and fly-through
+//! all follow the active layout.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use felipe::{LayoutProvider, Layouts};
+//!
+//! /// Entries on a spiral, the first in the middle
+//! struct GalaxyLayout;
+//!
+//! impl LayoutProvider for GalaxyLayout {
+//!     fn name(&self) -> &str {
+//!         "galaxy"
+//!     }
+//!
+//!     fn transform(&self, index: usize, _count: usize) -> Transform {
+//!         let angle = index as f32 * 0.5;
+//!         let radius = 2.0 + index as f32 * 0.3;
+//!         Transform::from_xyz(radius * angle.cos(), 0.0, radius * angle.sin())
+//!             .with_rotation(Quat::from_rotation_y(-angle))
+//!     }
+//! }
+//!
+//! fn register(mut layouts: ResMut<Layouts>) {
+//!     layouts.register(GalaxyLayout);
+//! }
+//! ```
+//!
+//! Large directories only get books for the entries around the selection;
+//! the grid shows the rest as low slabs, other layouts leave them out.
+
+use bevy::prelude::*;
+use std::sync::Arc;
+
+use crate::command::{Command, RunCommand};
+use crate::{CurrentDirectory, StatusMessage, ITEM_SPACING};
+
+/// Entries per row of the grid
+pub const GRID_COLUMNS: usize = 10;
+
+/// Places entries in the scene
+pub trait LayoutProvider: Send + Sync + 'static {
+    /// What `:layout` calls it
+    fn name(&self) -> &str;
+
+    /// Where entry `index` of `count` stands: its ground point (the book grows
+    /// up from there) and which way it faces
+    fn transform(&self, index: usize, count: usize) -> Transform;
+
+    /// Point the camera looks at while entry `index` is selected
+    fn camera_target(&self, index: usize, count: usize) -> Vec3 {
+        self.transform(index, count).translation
+    }
+}
+
+/// The built-in layout: rows of `GRID_COLUMNS` entries
+pub struct GridLayout;
+
+impl LayoutProvider for GridLayout {
+    fn name(&self) -> &str {
+        "grid"
+    }
+
+    fn transform(&self, index: usize, _count: usize) -> Transform {
+        let x = (index % GRID_COLUMNS) as f32 * ITEM_SPACING - 9.0;
+        let z = (index / GRID_COLUMNS) as f32 * ITEM_SPACING;
+        Transform::from_xyz(x, 0.0, z)
+    }
+}
+
+/// The layouts to choose from, the grid first, and the one in use
+#[derive(Resource)]
+pub struct Layouts {
+    providers: Vec<Arc<dyn LayoutProvider>>,
+    active: usize,
+}
+
+impl Default for Layouts {
+    fn default() -> Self {
+        Self {
+            providers: vec![Arc::new(GridLayout)],
+            active: 0,
+        }
+    }
+}
+
+impl Layouts {
+    /// Add a layout for `:layout` to switch to
+    pub fn register(&mut self, provider: impl LayoutProvider) {
+        self.providers.push(Arc::new(provider));
+    }
+
+    /// Use the layout called `name`; false if there is none
+    pub fn activate(&mut self, name: &str) -> bool {
+        match self.providers.iter().position(|p| p.name() == name) {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn active(&self) -> &dyn LayoutProvider {
+        self.providers[self.active].as_ref()
+    }
+
+    /// Whether the built-in grid is in use
+    pub fn is_grid(&self) -> bool {
+        self.active == 0
+    }
+
+    /// Ground point of entry `index` of `count`
+    pub fn position(&self, index: usize, count: usize) -> Vec3 {
+        self.active().transform(index, count).translation
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.providers.iter().map(|p| p.name()).collect()
+    }
+}
+
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Layouts>()
+            .add_systems(Update, handle_layout_command);
+    }
+}
+
+/// `:layout name` - switch layouts; `:layout` lists them
+fn handle_layout_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut layouts: ResMut<Layouts>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Layout(name) = command else {
+            continue;
+        };
+        let Some(name) = name else {
+            let active = layouts.active().name().to_string();
+            let names: Vec<String> = layouts
+                .names()
+                .into_iter()
+                .map(|name| {
+                    if name == active {
+                        format!("[{}]", name)
+                    } else {
+                        name.to_string()
+                    }
+                })
+                .collect();
+            status.0 = format!("Layouts: {}", names.join("  "));
+            continue;
+        };
+        if !layouts.activate(name) {
+            status.0 = format!("No layout {} (:layout lists them)", name);
+            continue;
+        }
+        // Rebuild the scene with the entries in their new places
+        current_dir.keep_selection();
+        status.0 = format!("Layout: {}", name);
+    }
+}
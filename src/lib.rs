@@ -0,0 +1,3136 @@
+//! Felipe - Cyberpunk File Manager
+//!
+//! Felipe = anagram of Filepe (FILE + Parallel Experience)
+//! Inspired by TRON and Philip's Bookshelf.
+//! Orange wireframe aesthetics, vim keybindings, 3D navigation.
+//!
+//! The `felipe` binary is [`run`]. Other Bevy apps can embed the browser by
+//! adding [`FelipePlugin`] next to `DefaultPlugins`:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use felipe::{CurrentDirectory, FelipePlugin, Theme};
+//!
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugins(FelipePlugin {
+//!         theme: Some(Theme::Blueprint),
+//!     })
+//!     .insert_resource(CurrentDirectory::new("/home"))
+//!     .run();
+//! ```
+//!
+//! The config is loaded the same way the binary loads it, unless the app
+//! inserts its own [`Config`]. [`CurrentDirectory`] and [`CameraState`] can be
+//! read and changed from the app's systems, and [`RunCommand`] runs any `:`
+//! command, e.g. `RunCommand(Command::Terminal)`.
+
+// Bevy systems routinely take many parameters and nested query filters
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+mod accessibility;
+mod basket;
+mod batch;
+mod bookmarks;
+mod breadcrumbs;
+mod checksum;
+mod cli;
+mod clipboard;
+mod cloudsync;
+mod colorby;
+mod command;
+mod config;
+mod conflicts;
+mod copier;
+mod crt;
+mod custom;
+mod cwdfile;
+mod dashboard;
+mod debug;
+mod deepjump;
+mod demofs;
+mod diff;
+mod dirsync;
+mod dirweight;
+mod dropped;
+mod dryrun;
+mod events;
+mod filter;
+mod flatten;
+mod flycam;
+mod focus;
+mod font;
+mod format;
+mod frecency;
+#[cfg(feature = "ftp")]
+mod ftp;
+mod git;
+mod gitignore;
+mod gitlog;
+mod glitch;
+mod growth;
+mod heights;
+mod help;
+mod history;
+mod hooks;
+#[cfg(feature = "index")]
+mod index;
+#[cfg(all(unix, feature = "ipc"))]
+mod ipc;
+mod jobs;
+mod layout;
+mod links;
+mod locale;
+mod logging;
+mod markdown;
+#[cfg_attr(target_arch = "wasm32", path = "metadata_web.rs")]
+mod metadata;
+mod mime;
+mod minimap;
+#[cfg(all(target_os = "linux", feature = "mtp"))]
+mod mtp;
+mod notes;
+mod numbers;
+mod oplog;
+mod ops;
+mod overwrite;
+mod peek;
+mod photo;
+mod picker;
+mod player;
+#[cfg(all(target_os = "linux", feature = "portal"))]
+mod portal;
+mod power;
+mod preview;
+mod properties;
+mod quality;
+mod record;
+mod recovery;
+mod remote;
+mod rename;
+#[cfg(feature = "s3")]
+mod s3;
+mod script;
+mod searches;
+#[cfg(feature = "sftp")]
+mod sftp;
+mod shapes;
+mod shell;
+mod snapshot;
+mod sort;
+mod sound;
+mod split;
+mod stars;
+mod stats;
+mod statusline;
+mod tags;
+mod terminal;
+mod trail;
+mod transition;
+mod trash;
+mod tree;
+#[cfg(feature = "tui")]
+mod tui;
+mod tutorial;
+mod uiscale;
+mod vfs;
+mod volumes;
+#[cfg(feature = "webdav")]
+mod webdav;
+mod whatsnew;
+mod whichkey;
+mod windowstate;
+mod workspace;
+#[cfg(feature = "xr")]
+mod xr;
+
+use accessibility::AccessibilityPlugin;
+use basket::BasketPlugin;
+use bevy::core_pipeline::bloom::BloomSettings;
+use bevy::ecs::system::EntityCommands;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::mouse::MouseMotion;
+use bevy::input::ButtonState;
+use bevy::math::bounding::{Aabb3d, RayCast3d};
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::window::PrimaryWindow;
+use bookmarks::BookmarksPlugin;
+use breadcrumbs::{BreadcrumbsPlugin, CrumbRow};
+use checksum::ChecksumPlugin;
+use clipboard::ClipboardPlugin;
+use cloudsync::CloudSyncPlugin;
+use colorby::{ColorBy, ColorByPlugin};
+use command::CommandPlugin;
+use config::ConfigPlugin;
+use conflicts::ConflictsPlugin;
+use crt::CrtPlugin;
+use custom::{CommandRequest, CustomPlugin};
+use cwdfile::{CwdFile, CwdFilePlugin};
+use dashboard::DashboardPlugin;
+use debug::DebugPlugin;
+use deepjump::DeepJumpPlugin;
+use diff::DiffPlugin;
+use dirsync::DirSyncPlugin;
+use dirweight::DirWeightPlugin;
+use dropped::DroppedPlugin;
+use dryrun::DryRunPlugin;
+use events::{EventStream, EventsPlugin};
+use filter::{Filter, FilterPlugin};
+use flatten::FlattenPlugin;
+use flycam::{FlyCamera, FlyCameraPlugin};
+use focus::{Focus, FocusPlugin};
+use font::FontPlugin;
+use frecency::{Frecency, FrecencyPlugin};
+use git::{GitPlugin, GitStatus};
+use gitlog::GitLogPlugin;
+use glitch::{Glitch, GlitchPlugin};
+use growth::GrowthPlugin;
+use heights::HeightsPlugin;
+use help::HelpPlugin;
+use history::HistoryPlugin;
+use hooks::HooksPlugin;
+use jobs::{JobQueue, JobSummary, JobsPlugin};
+use layout::{LayoutPlugin, GRID_COLUMNS};
+use links::{LinkTarget, LinksPlugin};
+use locale::{tr, trf, LocalePlugin};
+use mime::{MimePlugin, MimeTypes};
+use minimap::MinimapPlugin;
+use notes::NotesPlugin;
+use numbers::NumbersPlugin;
+use oplog::{OperationLog, OplogPlugin};
+use ops::{Overwrite, RenameStrategy, TransferKind, TransferPlan, UndoStep};
+use overwrite::{OverwritePlugin, OverwriteRequest};
+use peek::PeekPlugin;
+use picker::{Picker, PickerPlugin};
+use player::PlayerPlugin;
+use power::PowerPlugin;
+use preview::{PreviewPlugin, Previews};
+use properties::{ChownRequest, PropertiesPlugin, PropertiesView};
+use quality::{Quality, QualityPlugin};
+use record::RecordPlugin;
+use recovery::RecoveryPlugin;
+use remote::RemotePlugin;
+use rename::{RenameLine, RenamePlugin};
+use script::{ScriptPlugin, Scripts};
+use searches::SearchesPlugin;
+use shapes::{Shape, ShapesConfig};
+use shell::ShellPlugin;
+use snapshot::SnapshotPlugin;
+use sort::{SortPlugin, Sorting};
+use sound::SoundPlugin;
+use split::SplitPlugin;
+use stars::StarsPlugin;
+use stats::StatsPlugin;
+use statusline::StatusLinePlugin;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tags::TagsPlugin;
+use terminal::TerminalPlugin;
+use trail::TrailPlugin;
+use transition::{EntryTransition, Transition, TransitionPlugin};
+use trash::TrashPlugin;
+use tree::TreePlugin;
+use tutorial::TutorialPlugin;
+use uiscale::UiScalePlugin;
+use volumes::{Volumes, VolumesPlugin};
+use whatsnew::WhatsNewPlugin;
+use whichkey::{PendingKey, WhichKeyPlugin};
+use windowstate::WindowStatePlugin;
+use workspace::{Workspace, WorkspacePlugin};
+
+pub use cli::Theme;
+pub use command::{Command, RunCommand};
+pub use config::Config;
+pub use events::StreamEvent;
+pub use glitch::OperationFailed;
+pub use hooks::{DirectoryLoaded, EntryOpened, EntrySelected, OperationFinished};
+pub use layout::{GridLayout, LayoutProvider, Layouts};
+pub use markdown::Markup;
+pub use preview::{PreviewContent, PreviewProvider, PreviewProviders};
+#[cfg(feature = "xr")]
+pub use xr::{XrController, XrSelect};
+
+// =============================================================================
+// Constants - Felipe's Visual Identity
+// =============================================================================
+
+/// Felipe Orange - the signature color
+const FELIPE_ORANGE: Color = Color::srgb(1.0, 0.4, 0.0);
+/// Darker orange for secondary elements
+const FELIPE_ORANGE_DIM: Color = Color::srgb(0.6, 0.24, 0.0);
+/// Very dim orange for grid
+const FELIPE_GRID: Color = Color::srgb(0.3, 0.12, 0.0);
+/// Grid of a directory that can't be read
+const FELIPE_GRID_LOCKED: Color = Color::srgb(0.4, 0.04, 0.02);
+/// Background - pure black for contrast
+const FELIPE_BLACK: Color = Color::srgb(0.02, 0.02, 0.02);
+/// Diff colors - added, removed and modified entries
+const DIFF_ADDED: Color = Color::srgb(0.2, 0.9, 0.3);
+const DIFF_REMOVED: Color = Color::srgb(0.95, 0.2, 0.2);
+const DIFF_MODIFIED: Color = Color::srgb(1.0, 0.85, 0.2);
+
+/// Spacing between items
+const ITEM_SPACING: f32 = 2.0;
+/// Base height for files (scaled by size)
+const BASE_HEIGHT: f32 = 0.5;
+/// Max height for files
+const MAX_HEIGHT: f32 = 10.0;
+/// Bookshelf depth per GB of a directory (see dirweight.rs)
+const DEPTH_PER_GB: f32 = 1.0;
+/// Max seconds between two clicks to count as a double-click
+const DOUBLE_CLICK_SECONDS: f64 = 0.4;
+/// Cursor travel in pixels before a press becomes a drag
+const DRAG_THRESHOLD_PX: f32 = 6.0;
+/// Radians of orbit per pixel of middle-drag
+const ORBIT_SPEED: f32 = 0.005;
+/// World units of pan per pixel of right-drag, per unit of camera distance
+const PAN_SPEED: f32 = 0.0015;
+/// Seconds per brightness pulse of the selection
+const PULSE_PERIOD: f32 = 1.2;
+/// Hover glow fade in/out rate (fraction per second)
+const HOVER_FADE_SPEED: f32 = 8.0;
+/// Camera angle for the `zt` overview (just short of straight down)
+const CAMERA_TOP_DOWN_ANGLE: f32 = 1.5;
+/// Camera distance per world unit of directory extent when framing it
+const CAMERA_FRAME_MARGIN: f32 = 1.4;
+/// Rows spawned on each side of the selection's row
+const WINDOW_ROWS: usize = 60;
+/// The window moves once the selection is this many rows from its edge
+const WINDOW_MARGIN_ROWS: usize = 20;
+/// Rows outside the window merged into one low-detail slab
+const FAR_CHUNK_ROWS: usize = 25;
+/// Labels longer than this are cut with an ellipsis (the top panel shows the full name)
+const LABEL_MAX_CHARS: usize = 24;
+/// Labels closer to the camera than this are fully opaque
+const LABEL_FADE_START: f32 = 25.0;
+/// Labels farther than this are hidden, so huge directories don't draw thousands of texts
+const LABEL_HIDE_DISTANCE: f32 = 45.0;
+/// Labels this close to the selection stay readable however far the camera is
+const LABEL_SELECTION_RADIUS: f32 = 6.0;
+/// Width of the padlock on directories that can't be entered
+const LOCK_SIZE: f32 = 0.3;
+
+// =============================================================================
+// Core State
+// =============================================================================
+
+/// Current directory being viewed
+#[derive(Resource)]
+pub struct CurrentDirectory {
+    path: PathBuf,
+    entries: Vec<FileEntry>,
+    selected_index: usize,
+    /// Where VISUAL mode started; the range runs from here to `selected_index`
+    visual_anchor: usize,
+    /// Entry to select once the next reload finishes
+    pending_select: Option<PathBuf>,
+    needs_reload: bool,
+    /// Directory shown flattened by `:flatten`; any other directory shows normally
+    flat_root: Option<PathBuf>,
+    /// Symlinks pointing at another entry, as (link, target) indices
+    links: Vec<(usize, usize)>,
+    /// Inodes of files listed under more than one name
+    shared_inodes: HashSet<u64>,
+    /// Directory the entries were read from, to stay in when another can't be
+    /// read
+    listed: Option<PathBuf>,
+    /// Why the directory shown couldn't be read, from the OS
+    unreadable: Option<String>,
+}
+
+impl Default for CurrentDirectory {
+    fn default() -> Self {
+        Self {
+            path: std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/")),
+            entries: Vec::new(),
+            selected_index: 0,
+            visual_anchor: 0,
+            pending_select: None,
+            needs_reload: true,
+            flat_root: None,
+            links: Vec::new(),
+            shared_inodes: HashSet::new(),
+            listed: None,
+            unreadable: None,
+        }
+    }
+}
+
+impl CurrentDirectory {
+    /// Start in `path` instead of the working directory
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..default()
+        }
+    }
+
+    /// Directory being shown
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Show another directory from the next frame on
+    pub fn set_path(&mut self, path: impl Into<PathBuf>) {
+        self.path = path.into();
+        self.needs_reload = true;
+    }
+
+    /// Path of the entry under the cursor
+    pub fn selected_path(&self) -> Option<&Path> {
+        self.entries
+            .get(self.selected_index)
+            .map(|entry| entry.path.as_path())
+    }
+
+    /// Paths of the listed entries, in display order
+    pub fn entry_paths(&self) -> impl Iterator<Item = &Path> {
+        self.entries.iter().map(|entry| entry.path.as_path())
+    }
+
+    fn visual_range(&self) -> std::ops::RangeInclusive<usize> {
+        let (a, b) = (self.visual_anchor, self.selected_index);
+        a.min(b)..=a.max(b)
+    }
+
+    /// Read the directory again with these settings; the status line to
+    /// show, if the listing has something to say
+    fn reload(
+        &mut self,
+        listing: &config::ListingConfig,
+        order: &sort::SortOrder,
+        filter: &Filter,
+    ) -> Option<String> {
+        let path = self.path.clone();
+        let started = std::time::Instant::now();
+        let mut entries = Vec::new();
+        let mut message = None;
+        let mut unreadable = None;
+
+        // Add parent directory entry if not root
+        if let Some(parent) = remote::parent(&path) {
+            if parent != path {
+                entries.push(FileEntry {
+                    name: "..".to_string(),
+                    path: parent,
+                    is_dir: true,
+                    size: 0,
+                    modified: None,
+                    locked: false,
+                    link: None,
+                    inode: None,
+                    executable: false,
+                });
+            }
+        }
+
+        if let Some(location) = remote::location(&path) {
+            match remote::read_dir(&location) {
+                Ok(mut remote_entries) => {
+                    remote_entries.retain(|entry| listing.hidden || !entry.is_hidden());
+                    remote_entries.retain(|entry| filter.matches(entry));
+                    order.sort(&mut remote_entries);
+                    entries.extend(remote_entries);
+                }
+                Err(reason) => message = Some(reason),
+            }
+        } else if volumes::is_drives_view(&path) {
+            let mut drives = volumes::drive_entries();
+            drives.retain(|entry| filter.matches(entry));
+            entries.extend(drives);
+        } else if stars::is_starred_view(&path) {
+            let mut starred = stars::starred_entries();
+            starred.retain(|entry| filter.matches(entry));
+            order.sort(&mut starred);
+            entries.extend(starred);
+        } else if searches::is_searches_view(&path) {
+            let mut saved = searches::search_entries();
+            saved.retain(|entry| filter.matches(entry));
+            entries.extend(saved);
+        } else if searches::is_saved_search(&path) {
+            match searches::run(&path, listing) {
+                Ok((mut found, summary)) => {
+                    found.retain(|entry| filter.matches(entry));
+                    order.sort(&mut found);
+                    entries.extend(found);
+                    message = Some(summary);
+                }
+                Err(reason) => message = Some(reason),
+            }
+        } else if self.flat_root.as_ref() == Some(&path) {
+            let (mut files, truncated) = flatten::walk_files(&path, listing);
+            files.retain(|entry| filter.matches(entry));
+            order.sort(&mut files);
+            message = Some(format!(
+                "Flat view: {} files{}",
+                files.len(),
+                if truncated { " (truncated)" } else { "" }
+            ));
+            entries.extend(files);
+        } else {
+            match vfs::backend(&path).and_then(|fs| fs.list(&path)) {
+                Ok(listed) => {
+                    // Before filtering, so a file moved here keeps its tags for `tag:`
+                    metadata::relink(&listed);
+                    let kept = listing.gitignore.then(|| gitignore::kept_entries(&path));
+                    let mut dir_entries: Vec<FileEntry> = listed
+                        .into_iter()
+                        .filter(|entry| listing.hidden || !entry.is_hidden())
+                        .filter(|entry| kept.as_ref().is_none_or(|kept| kept.contains(&entry.path)))
+                        .filter(|entry| filter.matches(entry))
+                        .collect();
+
+                    order.sort(&mut dir_entries);
+
+                    entries.extend(dir_entries);
+                }
+                Err(err) => {
+                    let reason = format!("Cannot read {}: {}", path.display(), err);
+                    warn!("{}", reason);
+                    // Going somewhere unreadable leaves the user where they were
+                    if let Some(previous) = self.listed.clone().filter(|dir| *dir != path) {
+                        self.path = previous;
+                        self.pending_select = None;
+                        self.needs_reload = false;
+                        return Some(reason);
+                    }
+                    // The directory itself became unreadable; `..` still leads out
+                    unreadable = Some(err.to_string());
+                    message = Some(reason);
+                }
+            }
+        }
+
+        // Leaving the flattened directory ends the flat view
+        if self.flat_root.as_ref() != Some(&path) {
+            self.flat_root = None;
+        }
+        let pending_select = self.pending_select.take();
+        self.selected_index = pending_select
+            .and_then(|target| entries.iter().position(|e| e.path == target))
+            .unwrap_or(0);
+        self.links = links::sibling_links(&path, &entries);
+        self.shared_inodes = links::shared_inodes(&entries);
+        debug!(
+            "Listed {}: {} entries in {:?}",
+            path.display(),
+            entries.len(),
+            started.elapsed()
+        );
+        self.entries = entries;
+        self.visual_anchor = self.selected_index;
+        self.needs_reload = false;
+        self.listed = Some(path);
+        self.unreadable = unreadable;
+        message
+    }
+
+    /// Reload after the next change, keeping the cursor on the selected entry
+    fn keep_selection(&mut self) {
+        self.pending_select = self.selected_path().map(Path::to_path_buf);
+        self.needs_reload = true;
+    }
+
+    fn go_to_parent(&mut self) {
+        if let Some(parent) = remote::parent(&self.path) {
+            if parent != self.path {
+                self.path = parent;
+                self.needs_reload = true;
+            }
+        }
+    }
+
+    /// Enter the selected directory; a selected file is returned to be opened.
+    /// A directory we can't get into is reported instead of showing an empty room
+    fn enter_selected(&mut self) -> Result<Option<PathBuf>, String> {
+        let Some(entry) = self.entries.get(self.selected_index) else {
+            return Ok(None);
+        };
+        if entry.is_dir {
+            if let Some(err) = enter_error(&entry.path) {
+                return Err(format!("Cannot enter {}: {}", entry.name, err));
+            }
+            self.path = links::enter_path(&self.path, entry)?;
+            self.needs_reload = true;
+            Ok(None)
+        } else if let Some(Err(err)) = entry.link.as_ref().map(|link| &link.resolved) {
+            Err(format!("Cannot follow {}: {}", entry.name, err))
+        } else {
+            Ok(Some(entry.path.clone()))
+        }
+    }
+}
+
+/// A file or directory entry
+#[derive(Clone, Debug)]
+struct FileEntry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+    modified: Option<std::time::SystemTime>,
+    /// A directory we can't list or can't open anything in
+    locked: bool,
+    /// Set for symlinks; the other fields describe the target when it exists
+    link: Option<LinkTarget>,
+    /// Inode of a file with several hard links, to find the others listed
+    inode: Option<u64>,
+    /// A file with an execute bit set
+    executable: bool,
+}
+
+impl FileEntry {
+    /// Dotfiles; `..` always shows
+    fn is_hidden(&self) -> bool {
+        self.name != ".."
+            && self
+                .path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    }
+}
+
+/// Vim-like mode
+#[derive(Resource, Default, PartialEq, Eq, Clone, Copy)]
+enum VimMode {
+    #[default]
+    Normal,
+    Visual,
+    /// `:` command line (see `command.rs`)
+    Command,
+    /// Editing the selected entry's name (see `rename.rs`)
+    Rename,
+    /// Typing text that narrows the listing (see `filter.rs`)
+    Filter,
+    /// Fuzzy-finding a file below the current directory (see `deepjump.rs`)
+    Jump,
+}
+
+/// Camera state
+#[derive(Resource, Clone)]
+pub struct CameraState {
+    /// Point followed, the selected entry
+    pub target: Vec3,
+    pub distance: f32,
+    /// Angle above the floor (radians)
+    pub angle: f32,
+    /// Rotation around the target (radians), changed by middle-drag
+    pub yaw: f32,
+    /// Offset from the target, changed by right-drag and reset on selection change
+    pub pan: Vec3,
+}
+
+impl Default for CameraState {
+    fn default() -> Self {
+        Self {
+            target: Vec3::ZERO,
+            distance: 30.0,
+            angle: 0.8, // radians, looking down at ~45 degrees
+            yaw: 0.0,
+            pan: Vec3::ZERO,
+        }
+    }
+}
+
+impl CameraState {
+    /// Point the camera looks at
+    pub fn focus(&self) -> Vec3 {
+        self.target + self.pan
+    }
+}
+
+/// Entries yanked (`y`) or cut (`m`), waiting for a paste (`p`)
+#[derive(Resource, Default)]
+struct Register {
+    kind: Option<TransferKind>,
+    paths: Vec<PathBuf>,
+}
+
+impl Register {
+    /// Moved sources are gone; a second paste would only fail
+    fn forget_missing(&mut self) {
+        self.paths.retain(|path| path.exists());
+        if self.paths.is_empty() {
+            *self = Register::default();
+        }
+    }
+}
+
+/// Colors that override the normal look of entries (e.g. `:changes`), by path
+#[derive(Resource, Default)]
+struct EntryTints {
+    colors: HashMap<PathBuf, Color>,
+}
+
+/// Meshes and materials shared by all entries, so Bevy draws them as GPU instances
+///
+/// Books are one unit-height cuboid scaled per entry, and entries of the same
+/// color share one material - a directory of 10k files is then a few batched draws.
+/// Nothing here is recreated on reload, so a long session doesn't churn assets.
+#[derive(Resource, Default)]
+struct EntryPalette {
+    meshes: HashMap<&'static str, Handle<Mesh>>,
+    materials: HashMap<[u8; 3], Handle<StandardMaterial>>,
+    /// See-through variants for symlinks
+    link_materials: HashMap<[u8; 3], Handle<StandardMaterial>>,
+    ghost: Option<Handle<StandardMaterial>>,
+}
+
+impl EntryPalette {
+    fn cuboid(&mut self, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+        self.mesh(meshes, "entry", || Cuboid::new(0.8, 1.0, 0.3).into())
+    }
+
+    /// The twelve edges of the entry cuboid, for wireframe mode
+    fn edges(&mut self, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+        self.mesh(meshes, "entry edges", || {
+            let (x, y, z) = (0.4, 0.5, 0.15);
+            let corner = |i: usize| {
+                [
+                    if i & 1 == 0 { -x } else { x },
+                    if i & 2 == 0 { -y } else { y },
+                    if i & 4 == 0 { -z } else { z },
+                ]
+            };
+            // Corners that differ in exactly one axis share an edge
+            let mut positions = Vec::new();
+            for a in 0..8 {
+                for axis in [1, 2, 4] {
+                    if a & axis == 0 {
+                        positions.push(corner(a));
+                        positions.push(corner(a | axis));
+                    }
+                }
+            }
+            Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
+                .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        })
+    }
+
+    /// Shared mesh by name, built on first use
+    fn mesh(
+        &mut self,
+        meshes: &mut Assets<Mesh>,
+        name: &'static str,
+        build: impl FnOnce() -> Mesh,
+    ) -> Handle<Mesh> {
+        self.meshes
+            .entry(name)
+            .or_insert_with(|| meshes.add(build()))
+            .clone()
+    }
+
+    /// Translucent material of the drag ghost
+    fn ghost(&mut self, materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+        self.ghost
+            .get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: FELIPE_ORANGE.with_alpha(0.35),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .clone()
+    }
+
+    /// Shared material for a color; animated colors are quantized to keep the palette small
+    fn material(
+        &mut self,
+        materials: &mut Assets<StandardMaterial>,
+        color: LinearRgba,
+    ) -> Handle<StandardMaterial> {
+        let key = palette_key(color);
+        self.materials
+            .entry(key)
+            .or_insert_with(|| {
+                let color = palette_color(key);
+                materials.add(StandardMaterial {
+                    base_color: color.into(),
+                    emissive: color,
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .clone()
+    }
+
+    /// Shared translucent material for a symlink's book
+    fn link_material(
+        &mut self,
+        materials: &mut Assets<StandardMaterial>,
+        color: LinearRgba,
+    ) -> Handle<StandardMaterial> {
+        let key = palette_key(color);
+        self.link_materials
+            .entry(key)
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: palette_color(key).with_alpha(0.4).into(),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .clone()
+    }
+
+    /// Material of an entry's book in `color`
+    fn entry_material(
+        &mut self,
+        materials: &mut Assets<StandardMaterial>,
+        entry: &FileEntry,
+        color: LinearRgba,
+    ) -> Handle<StandardMaterial> {
+        if entry.link.is_some() {
+            self.link_material(materials, color)
+        } else {
+            self.material(materials, color)
+        }
+    }
+}
+
+fn palette_key(color: LinearRgba) -> [u8; 3] {
+    [color.red, color.green, color.blue].map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8)
+}
+
+fn palette_color(key: [u8; 3]) -> LinearRgba {
+    LinearRgba::rgb(
+        key[0] as f32 / 255.0,
+        key[1] as f32 / 255.0,
+        key[2] as f32 / 255.0,
+    )
+}
+
+/// Entries that currently have entities; huge directories only get a window
+/// of rows around the selection
+#[derive(Resource, Default)]
+struct EntryWindow {
+    range: std::ops::Range<usize>,
+}
+
+/// One-line feedback shown above the mode indicator
+#[derive(Resource, Default)]
+struct StatusMessage(String);
+
+/// A question waiting for a single-key answer; blocks normal input while set
+#[derive(Resource, Default)]
+struct Prompt {
+    pending: Option<PendingPrompt>,
+}
+
+enum PendingPrompt {
+    /// Paste would create names differing only by case on a case-insensitive target
+    CaseCollision(TransferPlan),
+    /// An entry was dropped onto a directory
+    ConfirmMove(TransferPlan),
+    /// A paste would replace entries already there (see overwrite.rs)
+    Overwrite(OverwriteRequest),
+    /// `d` over the selection, one question for all of it (see trash.rs)
+    ConfirmTrash(TransferPlan),
+    /// A move of several entries failed partway
+    MoveFailed(MoveFailure),
+    /// `:chown` over directories: recurse into them?
+    ConfirmChown(ChownRequest),
+    /// A user command with `confirm = true`
+    ConfirmCommand(CommandRequest),
+    /// A script's `confirm(question)`, waiting for y/n
+    ConfirmScript(String),
+    /// `t` was pressed, waiting for a color; answered in tags.rs
+    Tag,
+    /// What to do about a crashed session or a cut-short transfer, with the
+    /// question; answered in recovery.rs
+    Recover(String),
+}
+
+/// What a multi-entry move had done when one of its entries failed
+struct MoveFailure {
+    label: String,
+    /// Entries already moved, in order - what a rollback reverts
+    moved: Vec<UndoStep>,
+    failed: (PathBuf, String),
+    /// Steps after the failed one, not attempted yet
+    rest: TransferPlan,
+}
+
+impl PendingPrompt {
+    fn message(&self) -> String {
+        match self {
+            PendingPrompt::CaseCollision(plan) => {
+                let names: Vec<String> = plan
+                    .conflicts()
+                    .filter_map(|step| {
+                        let ops::Conflict::CaseCollision { existing } = step.conflict.as_ref()?;
+                        let name = step.target.file_name().unwrap_or_default();
+                        Some(format!("{} vs {}", name.to_string_lossy(), existing))
+                    })
+                    .collect();
+                trf(
+                    "Case collision ({}) - r:rename  s:skip  Esc:cancel",
+                    &[&names.join(", ")],
+                )
+            }
+            PendingPrompt::ConfirmMove(plan) => {
+                let step = &plan.steps[0];
+                trf(
+                    "Move {} to {}? y:yes  n:no",
+                    &[
+                        &step
+                            .source
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy(),
+                        &step.target.parent().unwrap_or(&step.target).display(),
+                    ],
+                )
+            }
+            PendingPrompt::Overwrite(request) => request.question(),
+            PendingPrompt::ConfirmTrash(plan) => {
+                let names: Vec<String> = plan
+                    .steps
+                    .iter()
+                    .map(|step| {
+                        step.source
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string()
+                    })
+                    .collect();
+                match names.as_slice() {
+                    [name] => trf("Trash {}? y:yes  n:no", &[name]),
+                    _ => {
+                        let shown = names.iter().take(3).cloned().collect::<Vec<_>>();
+                        let more = if names.len() > shown.len() {
+                            ", ..."
+                        } else {
+                            ""
+                        };
+                        trf(
+                            "Trash {} entries ({}{})? y:yes  n:no",
+                            &[&names.len(), &shown.join(", "), &more],
+                        )
+                    }
+                }
+            }
+            PendingPrompt::MoveFailed(failure) => {
+                let (path, err) = &failure.failed;
+                trf(
+                    "Moving {} failed ({}) after {} moved, {} left - r:roll back  c:continue  Esc:stop here",
+                    &[
+                        &path.file_name().unwrap_or_default().to_string_lossy(),
+                        err,
+                        &failure.moved.len(),
+                        &failure.rest.steps.len(),
+                    ],
+                )
+            }
+            PendingPrompt::ConfirmChown(request) => request.question(),
+            PendingPrompt::ConfirmCommand(request) => request.question(),
+            PendingPrompt::ConfirmScript(question) => trf("{} y:yes  n:no", &[question]),
+            PendingPrompt::Tag => tr("Tag: r o y g b p  x:clear  Esc:cancel").to_string(),
+            PendingPrompt::Recover(question) => question.clone(),
+        }
+    }
+}
+
+/// Left-button press on a file entity that may turn into a drag
+#[derive(Resource, Default)]
+struct DragState {
+    pressed: Option<(usize, Vec2)>,
+    active: bool,
+}
+
+/// File entity under the mouse cursor
+#[derive(Resource, Default)]
+struct HoveredEntry(Option<usize>);
+
+/// Last left click on a file entity, used for double-click detection
+#[derive(Resource, Default)]
+struct MouseClickState {
+    last_click: Option<(usize, f64)>,
+}
+
+// =============================================================================
+// Components
+// =============================================================================
+
+/// Marker for file/folder 3D entities
+#[derive(Component)]
+struct FileEntity {
+    index: usize,
+    shape: Shape,
+}
+
+/// Hover glow of a file entity, eased between 0 and 1
+#[derive(Component, Default)]
+struct EntryGlow {
+    hover: f32,
+}
+
+/// Low-detail stand-in for a chunk of rows outside the entry window
+#[derive(Component)]
+struct FarChunk;
+
+/// Marker for file/folder text labels
+#[derive(Component)]
+struct FileLabel {
+    index: usize,
+}
+
+/// Marker for the main 3D camera
+#[derive(Component)]
+struct MainCamera;
+
+/// Marker for UI elements
+#[derive(Component)]
+struct UiElement;
+
+/// Marker for the selected entry's line under the breadcrumbs
+#[derive(Component)]
+struct PathDisplay;
+
+/// Marker for the state of the directory after its breadcrumbs
+#[derive(Component)]
+struct PathInfo;
+
+/// Marker for mode indicator
+#[derive(Component)]
+struct ModeIndicator;
+
+/// Translucent copy of the entry being dragged
+#[derive(Component)]
+struct DragGhost;
+
+/// Marker for status message / prompt line
+#[derive(Component)]
+struct StatusLine;
+
+// =============================================================================
+// Setup Systems
+// =============================================================================
+
+fn setup_camera(mut commands: Commands, camera_state: Res<CameraState>, config: Res<Config>) {
+    // 3D Camera - isometric-ish view
+    let camera_pos = calculate_camera_position(&camera_state);
+
+    let bloom = quality::wants_bloom(&config);
+    let mut camera = commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                // Bloom needs HDR to pick up the emissive entries
+                hdr: bloom,
+                ..default()
+            },
+            transform: Transform::from_translation(camera_pos)
+                .looking_at(camera_state.focus(), Vec3::Y),
+            ..default()
+        },
+        MainCamera,
+    ));
+    if bloom {
+        camera.insert(BloomSettings {
+            intensity: config.render.bloom_intensity.clamp(0.0, 1.0),
+            ..BloomSettings::NATURAL
+        });
+    }
+
+    // Ambient light (very dim, cyberpunk style)
+    commands.insert_resource(AmbientLight {
+        color: FELIPE_ORANGE,
+        brightness: 50.0,
+    });
+}
+
+fn setup_ui(mut commands: Commands) {
+    // Background panel for path display
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                ..default()
+            },
+            UiElement,
+        ))
+        .with_children(|parent| {
+            // Breadcrumbs (breadcrumbs.rs), then what the directory is
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_wrap: FlexWrap::Wrap,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|line| {
+                    line.spawn((NodeBundle::default(), CrumbRow));
+                    line.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 24.0,
+                                color: FELIPE_ORANGE,
+                                ..default()
+                            },
+                        ),
+                        PathInfo,
+                    ));
+                });
+            parent.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 24.0,
+                            color: FELIPE_ORANGE,
+                            ..default()
+                        },
+                    ),
+                    ..default()
+                },
+                PathDisplay,
+            ));
+        });
+
+    // Mode indicator at bottom left
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "-- NORMAL --",
+                TextStyle {
+                    font_size: 18.0,
+                    color: FELIPE_ORANGE,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+        ModeIndicator,
+        UiElement,
+    ));
+
+    // Status message / prompt just above the mode indicator
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 18.0,
+                    color: FELIPE_ORANGE,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(34.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+        StatusLine,
+        UiElement,
+    ));
+
+    // Pointer to the help overlay (help.rs) at bottom right
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                tr("?:help  ::command"),
+                TextStyle {
+                    font_size: 16.0,
+                    color: FELIPE_ORANGE_DIM,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+        UiElement,
+    ));
+}
+
+// =============================================================================
+// Directory Loading
+// =============================================================================
+
+fn load_directory(
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut camera_state: ResMut<CameraState>,
+    mut status: ResMut<StatusMessage>,
+    sorting: Res<Sorting>,
+    config: Res<Config>,
+    filter: Res<Filter>,
+    layouts: Res<Layouts>,
+    mut loaded: EventWriter<DirectoryLoaded>,
+) {
+    if !current_dir.needs_reload {
+        return;
+    }
+    let message = debug::timed("listing", || {
+        current_dir.reload(&config.listing, sorting.active(), &filter)
+    });
+    if let Some(message) = message {
+        status.0 = message;
+    }
+    update_camera_target(&current_dir, &layouts, &mut camera_state);
+    loaded.send(DirectoryLoaded {
+        entries: current_dir.entries.len(),
+        path: current_dir.path.clone(),
+    });
+}
+
+// =============================================================================
+// 3D Visualization
+// =============================================================================
+
+fn spawn_file_entities(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    mut window: ResMut<EntryWindow>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mimes: Res<MimeTypes>,
+    layouts: Res<Layouts>,
+    mut transition: ResMut<Transition>,
+    existing_entity_query: Query<Entity, With<FileEntity>>,
+    existing_label_query: Query<Entity, With<FileLabel>>,
+) {
+    // Only spawn if directory was just loaded
+    if !existing_entity_query.is_empty()
+        || !existing_label_query.is_empty()
+        || current_dir.entries.is_empty()
+    {
+        return;
+    }
+
+    window.range = window_around(current_dir.selected_index, current_dir.entries.len());
+    debug::timed("books", || {
+        for i in window.range.clone() {
+            spawn_entry(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut palette,
+                &current_dir,
+                &config.shapes,
+                &mimes,
+                &layouts,
+                i,
+                transition.animate,
+            );
+        }
+        if layouts.is_grid() {
+            spawn_far_chunks(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut palette,
+                &current_dir,
+                &window.range,
+            );
+        }
+    });
+    transition.animate = false;
+}
+
+/// Spawn the book and label of one entry
+fn spawn_entry(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    palette: &mut EntryPalette,
+    current_dir: &CurrentDirectory,
+    shapes: &ShapesConfig,
+    mimes: &MimeTypes,
+    layouts: &Layouts,
+    i: usize,
+    animate: bool,
+) {
+    let entry = &current_dir.entries[i];
+    let place = layouts.active().transform(i, current_dir.entries.len());
+    let Vec3 { x, z, .. } = place.translation;
+
+    let height = entry_height(entry);
+    let shape = shapes.shape(entry, mimes.get(&entry.path));
+
+    let color = if i == current_dir.selected_index {
+        FELIPE_ORANGE
+    } else if entry.is_dir {
+        FELIPE_GRID
+    } else {
+        shape.color().unwrap_or(FELIPE_ORANGE_DIM)
+    };
+    let material = palette.entry_material(materials, entry, color.to_linear());
+
+    let mut transform = Transform::from_xyz(x, height / 2.0, z)
+        .with_rotation(place.rotation)
+        .with_scale(Vec3::new(1.0, height, 1.0));
+    let mut entity = commands.spawn((FileEntity { index: i, shape }, EntryGlow::default()));
+    if animate {
+        let rising = EntryTransition::rising(&transform);
+        rising.apply(&mut transform, true);
+        entity.insert(rising);
+    }
+    entity.insert(PbrBundle {
+        mesh: shape.mesh(palette, meshes, false),
+        material,
+        transform,
+        ..default()
+    });
+    if entry.locked {
+        spawn_lock_badge(&mut entity, meshes, materials, palette, height);
+    }
+
+    // Spawn text label above the file/folder
+    let label_color = if i == current_dir.selected_index {
+        FELIPE_ORANGE
+    } else {
+        FELIPE_ORANGE_DIM
+    };
+
+    let mut transform = Transform::from_xyz(x, height + 1.5, z).with_scale(Vec3::splat(0.03));
+    let mut label = commands.spawn(FileLabel { index: i });
+    if animate {
+        let rising = EntryTransition::rising(&transform);
+        rising.apply(&mut transform, false);
+        label.insert(rising);
+    }
+    label.insert(Text2dBundle {
+        text: Text::from_section(
+            truncate_label(&entry.name),
+            TextStyle {
+                font_size: 30.0,
+                color: label_color,
+                ..default()
+            },
+        ),
+        transform,
+        ..default()
+    });
+}
+
+/// Padlock on top of a directory that can't be entered
+fn spawn_lock_badge(
+    entity: &mut EntityCommands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    palette: &mut EntryPalette,
+    height: f32,
+) {
+    let body = palette.mesh(meshes, "lock body", || {
+        Cuboid::new(LOCK_SIZE, LOCK_SIZE * 0.8, LOCK_SIZE * 0.35).into()
+    });
+    let shackle = palette.mesh(meshes, "lock shackle", || {
+        Torus {
+            minor_radius: LOCK_SIZE * 0.08,
+            major_radius: LOCK_SIZE * 0.3,
+        }
+        .into()
+    });
+    let material = palette.material(materials, DIFF_REMOVED.to_linear());
+    // The book's unit cuboid is stretched to its height, which the badge undoes
+    let transform = Transform::from_xyz(0.0, 0.5 + LOCK_SIZE / height, 0.0).with_scale(Vec3::new(
+        1.0,
+        1.0 / height,
+        1.0,
+    ));
+    entity.with_children(|parent| {
+        parent
+            .spawn(PbrBundle {
+                mesh: body,
+                material: material.clone(),
+                transform,
+                ..default()
+            })
+            .with_children(|body| {
+                // Ring stood upright, its lower half hidden in the body
+                body.spawn(PbrBundle {
+                    mesh: shackle,
+                    material,
+                    transform: Transform::from_xyz(0.0, LOCK_SIZE * 0.4, 0.0)
+                        .with_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)),
+                    ..default()
+                });
+            });
+    });
+}
+
+/// Entries with entities: rows around the selection, the whole directory when it's small
+fn window_around(selected: usize, len: usize) -> std::ops::Range<usize> {
+    let row = selected / GRID_COLUMNS;
+    let start = row.saturating_sub(WINDOW_ROWS) * GRID_COLUMNS;
+    let end = ((row + WINDOW_ROWS + 1) * GRID_COLUMNS).min(len);
+    start..end
+}
+
+/// Stand-ins for the rows outside the window: one low slab per chunk of rows,
+/// as tall as the chunk's average entry, so the shape of the directory stays visible
+fn spawn_far_chunks(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    palette: &mut EntryPalette,
+    current_dir: &CurrentDirectory,
+    window: &std::ops::Range<usize>,
+) {
+    let chunk = FAR_CHUNK_ROWS * GRID_COLUMNS;
+    let material = palette.material(materials, FELIPE_GRID.to_linear());
+    let len = current_dir.entries.len();
+    let outside = [0..window.start, window.end..len];
+    for range in outside {
+        let mut start = range.start;
+        while start < range.end {
+            let end = (start + chunk).min(range.end);
+            let entries = &current_dir.entries[start..end];
+            let height = entries.iter().map(entry_height).sum::<f32>() / entries.len() as f32 * 0.5;
+            let first = GridLayout.transform(start, len).translation.z;
+            let last = GridLayout.transform(end - 1, len).translation.z;
+            // The unit cuboid is 0.8 x 1 x 0.3; stretch it over ten columns and the chunk's rows
+            let width = 9.0 * ITEM_SPACING + 0.8;
+            let depth = last - first + 0.3;
+            commands.spawn((
+                PbrBundle {
+                    mesh: palette.cuboid(meshes),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(0.0, height / 2.0, (first + last) / 2.0)
+                        .with_scale(Vec3::new(width / 0.8, height, depth / 0.3)),
+                    ..default()
+                },
+                FarChunk,
+            ));
+            start = end;
+        }
+    }
+}
+
+/// Move the window along with the selection, spawning rows that come into it
+/// and despawning rows that leave
+fn stream_entry_window(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    mut window: ResMut<EntryWindow>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mimes: Res<MimeTypes>,
+    layouts: Res<Layouts>,
+    entity_query: Query<(Entity, &FileEntity)>,
+    label_query: Query<(Entity, &FileLabel)>,
+    chunk_query: Query<Entity, With<FarChunk>>,
+) {
+    if !current_dir.is_changed() || current_dir.needs_reload || entity_query.is_empty() {
+        return;
+    }
+    let len = current_dir.entries.len();
+    let selected = current_dir.selected_index;
+    let margin = WINDOW_MARGIN_ROWS * GRID_COLUMNS;
+    // Hysteresis: only move once the selection gets near an edge that isn't the directory's
+    let near_start = window.range.start > 0 && selected < window.range.start + margin;
+    let near_end = window.range.end < len && selected + margin >= window.range.end;
+    if !near_start && !near_end {
+        return;
+    }
+
+    let old = window.range.clone();
+    window.range = window_around(selected, len);
+    for (entity, file_entity) in entity_query.iter() {
+        if !window.range.contains(&file_entity.index) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for (entity, label) in label_query.iter() {
+        if !window.range.contains(&label.index) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for i in window.range.clone().filter(|i| !old.contains(i)) {
+        spawn_entry(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &mut palette,
+            &current_dir,
+            &config.shapes,
+            &mimes,
+            &layouts,
+            i,
+            false,
+        );
+    }
+    for entity in chunk_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if layouts.is_grid() {
+        spawn_far_chunks(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &mut palette,
+            &current_dir,
+            &window.range,
+        );
+    }
+}
+
+fn despawn_file_entities(
+    mut commands: Commands,
+    current_dir: Res<CurrentDirectory>,
+    transition: Res<Transition>,
+    entity_query: Query<(Entity, &Transform), Or<(With<FileEntity>, With<FarChunk>)>>,
+    label_query: Query<(Entity, &Transform), With<FileLabel>>,
+) {
+    if current_dir.needs_reload {
+        // On a directory change, old entries sink away instead of vanishing
+        for (entity, transform) in entity_query.iter().chain(label_query.iter()) {
+            if transition.animate {
+                commands
+                    .entity(entity)
+                    .remove::<(FileEntity, FileLabel, FarChunk, EntryTransition)>()
+                    .insert(EntryTransition::sinking(transform));
+            } else {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Grid Drawing
+// =============================================================================
+
+fn draw_grid(mut gizmos: Gizmos, current_dir: Res<CurrentDirectory>, config: Res<Config>) {
+    let grid_size = config.render.quality.grid_lines();
+    let grid_spacing = 2.0;
+    let base = if current_dir.unreadable.is_some() {
+        FELIPE_GRID_LOCKED
+    } else {
+        FELIPE_GRID
+    };
+
+    // Draw grid lines
+    for i in -grid_size..=grid_size {
+        let pos = i as f32 * grid_spacing;
+        let alpha = 1.0 - (i.abs() as f32 / grid_size as f32) * 0.8;
+        let color = base.with_alpha(alpha * 0.5);
+
+        // X-axis lines
+        gizmos.line(
+            Vec3::new(-grid_size as f32 * grid_spacing, 0.0, pos),
+            Vec3::new(grid_size as f32 * grid_spacing, 0.0, pos),
+            color,
+        );
+        // Z-axis lines
+        gizmos.line(
+            Vec3::new(pos, 0.0, -grid_size as f32 * grid_spacing),
+            Vec3::new(pos, 0.0, grid_size as f32 * grid_spacing),
+            color,
+        );
+    }
+}
+
+// =============================================================================
+// Input Handling
+// =============================================================================
+
+fn handle_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut vim_mode: ResMut<VimMode>,
+    mut camera_state: ResMut<CameraState>,
+    mut register: ResMut<Register>,
+    mut prompt: ResMut<Prompt>,
+    mut status: ResMut<StatusMessage>,
+    mut jobs: ResMut<JobQueue>,
+    mut sorting: ResMut<Sorting>,
+    mut config: ResMut<Config>,
+    mut picker: ResMut<Picker>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    layouts: Res<Layouts>,
+    mut opened: EventWriter<EntryOpened>,
+    mut pending_key: ResMut<PendingKey>,
+) {
+    let entry_count = current_dir.entries.len();
+    if entry_count == 0 || prompt.pending.is_some() || focus.any_open() || fly.enabled {
+        return;
+    }
+
+    // A count (see `handle_count_keys`) goes to the next key, whatever it is
+    let count = if pending_key.count.is_some()
+        && keyboard.get_just_pressed().any(|key| !keeps_count(*key))
+    {
+        pending_key.count.take()
+    } else {
+        None
+    };
+
+    // Motions work the same in NORMAL and VISUAL mode
+    if matches!(*vim_mode, VimMode::Normal | VimMode::Visual) {
+        let last = entry_count - 1;
+        // j or Down - next item, [count] j that many
+        if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+            current_dir.selected_index =
+                (current_dir.selected_index + count.unwrap_or(1)).min(last);
+            update_camera_target(&current_dir, &layouts, &mut camera_state);
+        }
+        // k or Up - previous item, [count] k that many
+        if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+            current_dir.selected_index = current_dir
+                .selected_index
+                .saturating_sub(count.unwrap_or(1));
+            update_camera_target(&current_dir, &layouts, &mut camera_state);
+        }
+        // g - go to top, [count] g to that entry
+        if keyboard.just_pressed(KeyCode::KeyG) && !keyboard.pressed(KeyCode::ShiftLeft) {
+            current_dir.selected_index = count.map_or(0, |n| (n - 1).min(last));
+            update_camera_target(&current_dir, &layouts, &mut camera_state);
+        }
+        // G (shift+g) - go to bottom, [count] G to that entry
+        if keyboard.pressed(KeyCode::ShiftLeft) && keyboard.just_pressed(KeyCode::KeyG) {
+            current_dir.selected_index = count.map_or(last, |n| (n - 1).min(last));
+            update_camera_target(&current_dir, &layouts, &mut camera_state);
+        }
+    }
+
+    match *vim_mode {
+        VimMode::Normal => {
+            // z prefix - zz/zt/zb frame the view, zh/zi toggle hidden/ignored files, any other key cancels
+            if pending_key.key == Some('z') {
+                if keyboard.get_just_pressed().next().is_some() {
+                    pending_key.key = None;
+                    if keyboard.just_pressed(KeyCode::KeyZ) {
+                        frame_selection(&current_dir, &layouts, &mut camera_state);
+                    } else if keyboard.just_pressed(KeyCode::KeyT) {
+                        frame_directory(
+                            &current_dir,
+                            &layouts,
+                            &mut camera_state,
+                            CAMERA_TOP_DOWN_ANGLE,
+                        );
+                    } else if keyboard.just_pressed(KeyCode::KeyB) {
+                        let angle = camera_state.angle;
+                        frame_directory(&current_dir, &layouts, &mut camera_state, angle);
+                    } else if keyboard.just_pressed(KeyCode::KeyH) {
+                        toggle_listing(&mut config, "hidden", &mut status);
+                    } else if keyboard.just_pressed(KeyCode::KeyI) {
+                        toggle_listing(&mut config, "gitignore", &mut status);
+                    }
+                }
+                return;
+            }
+            if keyboard.just_pressed(KeyCode::KeyZ) {
+                pending_key.key = Some('z');
+                return;
+            }
+            // t prefix - a color key tags the selection (tags.rs); Ctrl-t is the terminal
+            if keyboard.just_pressed(KeyCode::KeyT)
+                && !keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+            {
+                prompt.pending = Some(PendingPrompt::Tag);
+                return;
+            }
+            // l or Right or Enter - enter directory / open file; in picker
+            // mode Enter on a file chooses it instead
+            let index = current_dir.selected_index;
+            let picking = keyboard.just_pressed(KeyCode::Enter)
+                && current_dir
+                    .entries
+                    .get(index)
+                    .is_some_and(|entry| picker.picks(entry));
+            if picking {
+                picker.choose_range(&current_dir, index..=index, &mut status);
+            } else if keyboard.just_pressed(KeyCode::KeyL)
+                || keyboard.just_pressed(KeyCode::ArrowRight)
+                || keyboard.just_pressed(KeyCode::Enter)
+            {
+                if let Err(message) = open_selected(&mut current_dir, &mut opened) {
+                    status.0 = message;
+                }
+            }
+            // h or Left - go to parent
+            if keyboard.just_pressed(KeyCode::KeyH) || keyboard.just_pressed(KeyCode::ArrowLeft) {
+                current_dir.go_to_parent();
+            }
+            // . - show or hide dotfiles
+            if keyboard.just_pressed(KeyCode::Period) {
+                toggle_listing(&mut config, "hidden", &mut status);
+            }
+            // s - next sort order, keeping the cursor on the same entry
+            if keyboard.just_pressed(KeyCode::KeyS) {
+                status.0 = format!("Sort: {}", sorting.cycle().name);
+                current_dir.keep_selection();
+            }
+            // v - visual mode, anchored at the cursor (Ctrl-v is clipboard.rs's paste)
+            if keyboard.just_pressed(KeyCode::KeyV)
+                && !keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+            {
+                current_dir.visual_anchor = current_dir.selected_index;
+                *vim_mode = VimMode::Visual;
+            }
+            // y and m are read in clipboard.rs, where y waits to see whether
+            // it starts `yp`, `yn` or `yd`
+            // p - paste into the current directory, P - paste symlinks to the register
+            // (`yp` copies the path instead, see clipboard.rs)
+            if keyboard.just_pressed(KeyCode::KeyP) && pending_key.key != Some('y') {
+                let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+                paste_register(
+                    &mut register,
+                    shift,
+                    &mut current_dir,
+                    &config,
+                    &mut prompt,
+                    &mut status,
+                    &mut jobs,
+                );
+            }
+        }
+        VimMode::Visual => {
+            // y / m - yank or cut the whole range
+            for (key, kind) in [
+                (KeyCode::KeyY, TransferKind::Copy),
+                (KeyCode::KeyM, TransferKind::Move),
+            ] {
+                if keyboard.just_pressed(key) {
+                    let range = current_dir.visual_range();
+                    yank_entries(&mut register, &current_dir, range, kind, &mut status);
+                    *vim_mode = VimMode::Normal;
+                }
+            }
+            // Enter - choose the range in picker mode
+            if keyboard.just_pressed(KeyCode::Enter) && picker.mode.is_some() {
+                let range = current_dir.visual_range();
+                picker.choose_range(&current_dir, range, &mut status);
+                *vim_mode = VimMode::Normal;
+            }
+            if keyboard.just_pressed(KeyCode::Escape) {
+                *vim_mode = VimMode::Normal;
+            }
+        }
+        // Text input is handled by the command line and rename mode
+        VimMode::Command | VimMode::Rename | VimMode::Filter | VimMode::Jump => {}
+    }
+}
+
+/// Digits before a motion - `7j` goes 7 down, `15G` to entry 15 (the numbers
+/// of `:set number` / `relativenumber`)
+fn handle_count_keys(
+    mut key_events: EventReader<KeyboardInput>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    mut pending_key: ResMut<PendingKey>,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if !matches!(*vim_mode, VimMode::Normal | VimMode::Visual)
+            || prompt.pending.is_some()
+            || focus.any_open()
+            || fly.enabled
+        {
+            if pending_key.count.is_some() {
+                pending_key.count = None;
+            }
+            continue;
+        }
+        // The second key of a sequence, like the 2 of `b2`
+        if pending_key.key.is_some() {
+            continue;
+        }
+        let Key::Character(c) = &event.logical_key else {
+            continue;
+        };
+        let Ok(digit) = c.parse::<usize>() else {
+            continue;
+        };
+        // 0 alone is no count
+        if digit > 0 || pending_key.count.is_some() {
+            let count = pending_key.count.unwrap_or(0);
+            pending_key.count = Some(count.saturating_mul(10).saturating_add(digit));
+        }
+    }
+}
+
+/// Keys that don't use up a count: its digits, and the modifiers of the key
+/// it goes to
+fn keeps_count(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::Digit0
+            | KeyCode::Digit1
+            | KeyCode::Digit2
+            | KeyCode::Digit3
+            | KeyCode::Digit4
+            | KeyCode::Digit5
+            | KeyCode::Digit6
+            | KeyCode::Digit7
+            | KeyCode::Digit8
+            | KeyCode::Digit9
+            | KeyCode::Numpad0
+            | KeyCode::Numpad1
+            | KeyCode::Numpad2
+            | KeyCode::Numpad3
+            | KeyCode::Numpad4
+            | KeyCode::Numpad5
+            | KeyCode::Numpad6
+            | KeyCode::Numpad7
+            | KeyCode::Numpad8
+            | KeyCode::Numpad9
+            | KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+    )
+}
+
+fn handle_prompt(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut prompt: ResMut<Prompt>,
+    mut status: ResMut<StatusMessage>,
+    mut jobs: ResMut<JobQueue>,
+    mut oplog: ResMut<OperationLog>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut summary: ResMut<JobSummary>,
+    mut properties: ResMut<PropertiesView>,
+    mut run_commands: EventWriter<RunCommand>,
+    scripts: Res<Scripts>,
+    config: Res<Config>,
+) {
+    let Some(pending) = prompt.pending.take() else {
+        return;
+    };
+
+    match pending {
+        PendingPrompt::CaseCollision(mut plan) => {
+            let strategy = if keyboard.just_pressed(KeyCode::KeyR) {
+                RenameStrategy::Suffix
+            } else if keyboard.just_pressed(KeyCode::KeyS) {
+                RenameStrategy::Skip
+            } else if keyboard.just_pressed(KeyCode::Escape) {
+                status.0 = "Paste cancelled".to_string();
+                return;
+            } else {
+                prompt.pending = Some(PendingPrompt::CaseCollision(plan));
+                return;
+            };
+            plan.resolve_case_collisions(strategy);
+            offer_transfer(plan, &config, &mut prompt, &mut jobs);
+        }
+        PendingPrompt::ConfirmMove(plan) => {
+            if keyboard.just_pressed(KeyCode::KeyY) || keyboard.just_pressed(KeyCode::Enter) {
+                offer_transfer(plan, &config, &mut prompt, &mut jobs);
+            } else if keyboard.just_pressed(KeyCode::KeyN) || keyboard.just_pressed(KeyCode::Escape)
+            {
+                status.0 = "Move cancelled".to_string();
+            } else {
+                prompt.pending = Some(PendingPrompt::ConfirmMove(plan));
+            }
+        }
+        PendingPrompt::Overwrite(request) => {
+            let choice = [
+                (KeyCode::KeyO, Overwrite::Replace),
+                (KeyCode::KeyS, Overwrite::Skip),
+                (KeyCode::KeyR, Overwrite::Rename),
+                (KeyCode::KeyN, Overwrite::IfNewer),
+            ]
+            .into_iter()
+            .find(|(key, _)| keyboard.just_pressed(*key));
+            if keyboard.just_pressed(KeyCode::Escape) {
+                status.0 = "Paste cancelled".to_string();
+                return;
+            }
+            let Some((_, choice)) = choice else {
+                prompt.pending = Some(PendingPrompt::Overwrite(request));
+                return;
+            };
+            let all = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+            match request.answer(choice, all) {
+                Ok(plan) if plan.steps.is_empty() => {
+                    status.0 = format!("Skipped all {}, nothing to paste", plan.skipped.len());
+                }
+                Ok(plan) => queue_transfer(plan, &mut jobs),
+                Err(request) => prompt.pending = Some(PendingPrompt::Overwrite(request)),
+            }
+        }
+        PendingPrompt::ConfirmTrash(plan) => {
+            if keyboard.just_pressed(KeyCode::KeyY) || keyboard.just_pressed(KeyCode::Enter) {
+                queue_transfer(plan, &mut jobs);
+            } else if keyboard.just_pressed(KeyCode::KeyN) || keyboard.just_pressed(KeyCode::Escape)
+            {
+                status.0 = "Trash cancelled".to_string();
+            } else {
+                prompt.pending = Some(PendingPrompt::ConfirmTrash(plan));
+            }
+        }
+        PendingPrompt::MoveFailed(failure) => {
+            let choice = [KeyCode::KeyR, KeyCode::KeyC, KeyCode::Escape]
+                .into_iter()
+                .find(|key| keyboard.just_pressed(*key));
+            let Some(choice) = choice else {
+                prompt.pending = Some(PendingPrompt::MoveFailed(failure));
+                return;
+            };
+            let MoveFailure {
+                label,
+                moved,
+                failed: (path, _),
+                rest,
+            } = failure;
+            let moved_count = moved.len();
+            match choice {
+                KeyCode::KeyR => {
+                    // Journal the partial move, then undo it like any other operation
+                    oplog.record(label, moved);
+                    status.0 = match oplog.undo_last() {
+                        Some(message) if moved_count > 0 => format!("Rolled back - {}", message),
+                        _ => "Nothing was moved".to_string(),
+                    };
+                }
+                KeyCode::KeyC => {
+                    oplog.record(label, moved);
+                    status.0 = format!(
+                        "Skipped {}, moving the rest",
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    );
+                    if !rest.steps.is_empty() {
+                        queue_transfer(rest, &mut jobs);
+                    }
+                }
+                _ => {
+                    oplog.record(label, moved);
+                    status.0 = format!(
+                        "Stopped after {} moved, {} not moved",
+                        moved_count,
+                        rest.steps.len() + 1
+                    );
+                }
+            }
+            current_dir.needs_reload = true;
+        }
+        PendingPrompt::ConfirmChown(request) => {
+            let recursive =
+                if keyboard.just_pressed(KeyCode::KeyY) || keyboard.just_pressed(KeyCode::Enter) {
+                    true
+                } else if keyboard.just_pressed(KeyCode::KeyN) {
+                    false
+                } else if keyboard.just_pressed(KeyCode::Escape) {
+                    status.0 = "chown cancelled".to_string();
+                    return;
+                } else {
+                    prompt.pending = Some(PendingPrompt::ConfirmChown(request));
+                    return;
+                };
+            status.0 = request.run(recursive, &mut oplog, &mut summary);
+            properties.refresh();
+        }
+        PendingPrompt::ConfirmCommand(request) => {
+            if keyboard.just_pressed(KeyCode::KeyY) || keyboard.just_pressed(KeyCode::Enter) {
+                run_commands.send(RunCommand(request.command()));
+            } else if keyboard.just_pressed(KeyCode::KeyN) || keyboard.just_pressed(KeyCode::Escape)
+            {
+                status.0 = "Command cancelled".to_string();
+            } else {
+                prompt.pending = Some(PendingPrompt::ConfirmCommand(request));
+            }
+        }
+        PendingPrompt::ConfirmScript(question) => {
+            if keyboard.just_pressed(KeyCode::KeyY) || keyboard.just_pressed(KeyCode::Enter) {
+                scripts.answer(true);
+            } else if keyboard.just_pressed(KeyCode::KeyN) || keyboard.just_pressed(KeyCode::Escape)
+            {
+                scripts.answer(false);
+            } else {
+                prompt.pending = Some(PendingPrompt::ConfirmScript(question));
+            }
+        }
+        PendingPrompt::Tag => prompt.pending = Some(PendingPrompt::Tag),
+        PendingPrompt::Recover(question) => {
+            prompt.pending = Some(PendingPrompt::Recover(question));
+        }
+    }
+}
+
+fn handle_mouse_click(
+    mouse: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    entity_query: Query<(&FileEntity, &GlobalTransform, &Aabb, &ViewVisibility)>,
+    prompt: Res<Prompt>,
+    mut click_state: ResMut<MouseClickState>,
+    mut drag_state: ResMut<DragState>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut camera_state: ResMut<CameraState>,
+    mut status: ResMut<StatusMessage>,
+    layouts: Res<Layouts>,
+    interactions: Query<&Interaction>,
+    mut opened: EventWriter<EntryOpened>,
+) {
+    // Clicks on the minimap or a breadcrumb are theirs
+    let on_ui = interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    if !mouse.just_pressed(MouseButton::Left) || prompt.pending.is_some() || on_ui {
+        return;
+    }
+    let Some((cursor, ray)) = cursor_ray(&window_query, &camera_query) else {
+        return;
+    };
+    let Some(index) = pick_file_entity(ray, &entity_query) else {
+        return;
+    };
+
+    let now = time.elapsed_seconds_f64();
+    let is_double_click = matches!(
+        click_state.last_click,
+        Some((last_index, last_time)) if last_index == index && now - last_time < DOUBLE_CLICK_SECONDS
+    );
+
+    current_dir.selected_index = index;
+    update_camera_target(&current_dir, &layouts, &mut camera_state);
+
+    if is_double_click {
+        click_state.last_click = None;
+        if let Err(message) = open_selected(&mut current_dir, &mut opened) {
+            status.0 = message;
+        }
+    } else {
+        click_state.last_click = Some((index, now));
+        drag_state.pressed = Some((index, cursor));
+    }
+}
+
+/// Drag a pressed entry past the threshold to show a ghost; drop it on a directory to move it
+fn handle_mouse_drag(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    entity_query: Query<(&FileEntity, &GlobalTransform, &Aabb, &ViewVisibility)>,
+    mesh_query: Query<(&FileEntity, &Handle<Mesh>, &Transform), Without<DragGhost>>,
+    mut ghost_query: Query<&mut Transform, With<DragGhost>>,
+    ghost_entities: Query<Entity, With<DragGhost>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    mut drag_state: ResMut<DragState>,
+    mut click_state: ResMut<MouseClickState>,
+    mut prompt: ResMut<Prompt>,
+    current_dir: Res<CurrentDirectory>,
+) {
+    let Some((index, press_cursor)) = drag_state.pressed else {
+        return;
+    };
+    let cursor_ray = cursor_ray(&window_query, &camera_query);
+
+    if mouse.just_released(MouseButton::Left) {
+        for entity in ghost_entities.iter() {
+            commands.entity(entity).despawn();
+        }
+        let was_active = drag_state.active;
+        *drag_state = DragState::default();
+        if !was_active {
+            return;
+        }
+
+        let target = cursor_ray
+            .and_then(|(_, ray)| pick_file_entity(ray, &entity_query))
+            .and_then(|i| current_dir.entries.get(i));
+        let (Some(source), Some(target)) = (current_dir.entries.get(index), target) else {
+            return;
+        };
+        if target.is_dir && target.path != source.path && target.path != current_dir.path {
+            let plan = ops::plan_transfer(
+                TransferKind::Move,
+                std::slice::from_ref(&source.path),
+                &target.path,
+            );
+            prompt.pending = Some(PendingPrompt::ConfirmMove(plan));
+        }
+        return;
+    }
+
+    if !mouse.pressed(MouseButton::Left) {
+        *drag_state = DragState::default();
+        return;
+    }
+    let Some((cursor, ray)) = cursor_ray else {
+        return;
+    };
+
+    if !drag_state.active {
+        let draggable = current_dir
+            .entries
+            .get(index)
+            .is_some_and(|entry| entry.name != "..");
+        if !draggable || cursor.distance(press_cursor) < DRAG_THRESHOLD_PX {
+            return;
+        }
+        let Some((_, mesh, source)) = mesh_query.iter().find(|(fe, _, _)| fe.index == index) else {
+            return;
+        };
+        drag_state.active = true;
+        // A drag is not the first half of a double-click
+        click_state.last_click = None;
+
+        let material = palette.ghost(&mut materials);
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material,
+                transform: Transform::from_scale(source.scale),
+                ..default()
+            },
+            DragGhost,
+        ));
+    }
+
+    // Ghost hovers slightly above the grid under the cursor
+    if let Some(distance) = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y)) {
+        let point = ray.get_point(distance);
+        for mut transform in ghost_query.iter_mut() {
+            transform.translation = point + Vec3::Y * 1.0;
+        }
+    }
+}
+
+/// Cursor position and the world-space ray under it
+fn cursor_ray(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) -> Option<(Vec2, Ray3d)> {
+    let cursor = window_query.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+    let ray = camera.viewport_to_world(camera_transform, cursor)?;
+    Some((cursor, ray))
+}
+
+/// Find the nearest file entity hit by `ray`; hidden ones (`:dashboard`) can't be hit
+fn pick_file_entity(
+    ray: Ray3d,
+    entity_query: &Query<(&FileEntity, &GlobalTransform, &Aabb, &ViewVisibility)>,
+) -> Option<usize> {
+    let ray_cast = RayCast3d::from_ray(ray, f32::MAX);
+    entity_query
+        .iter()
+        .filter(|(_, _, _, visibility)| visibility.get())
+        .filter_map(|(file_entity, transform, aabb, _)| {
+            let (scale, _, translation) = transform.to_scale_rotation_translation();
+            let bounds = Aabb3d::new(
+                translation + Vec3::from(aabb.center) * scale,
+                Vec3::from(aabb.half_extents) * scale,
+            );
+            ray_cast
+                .aabb_intersection_at(&bounds)
+                .map(|distance| (file_entity.index, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+fn update_hovered_entry(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    entity_query: Query<(&FileEntity, &GlobalTransform, &Aabb, &ViewVisibility)>,
+    fly: Res<FlyCamera>,
+    mut hovered: ResMut<HoveredEntry>,
+) {
+    // The fly camera hides and locks the cursor
+    let index = if fly.enabled {
+        None
+    } else {
+        cursor_ray(&window_query, &camera_query)
+            .and_then(|(_, ray)| pick_file_entity(ray, &entity_query))
+    };
+    if hovered.0 != index {
+        hovered.0 = index;
+    }
+}
+
+fn handle_mouse_wheel(
+    mut scroll_events: EventReader<bevy::input::mouse::MouseWheel>,
+    mut camera_state: ResMut<CameraState>,
+) {
+    for event in scroll_events.read() {
+        camera_state.distance = (camera_state.distance - event.y * 2.0).clamp(10.0, 100.0);
+    }
+}
+
+/// Middle-drag orbits around the target, right-drag pans across the grid
+fn handle_mouse_orbit(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut motion_events: EventReader<MouseMotion>,
+    mut camera_state: ResMut<CameraState>,
+) {
+    let delta: Vec2 = motion_events.read().map(|event| event.delta).sum();
+    if delta == Vec2::ZERO {
+        return;
+    }
+
+    if mouse.pressed(MouseButton::Middle) {
+        camera_state.yaw -= delta.x * ORBIT_SPEED;
+        camera_state.angle = (camera_state.angle + delta.y * ORBIT_SPEED).clamp(0.15, 1.5);
+    } else if mouse.pressed(MouseButton::Right) {
+        // Grab-style: the grid follows the cursor
+        let rotation = Quat::from_rotation_y(camera_state.yaw);
+        let right = rotation * Vec3::NEG_X;
+        let forward = rotation * Vec3::Z;
+        let scale = camera_state.distance * PAN_SPEED;
+        camera_state.pan += (-right * delta.x + forward * delta.y) * scale;
+    }
+}
+
+fn update_camera_target(
+    current_dir: &CurrentDirectory,
+    layouts: &Layouts,
+    camera_state: &mut CameraState,
+) {
+    camera_state.target = layouts
+        .active()
+        .camera_target(current_dir.selected_index, current_dir.entries.len());
+    camera_state.pan = Vec3::ZERO;
+}
+
+/// `zz` - recenter on the selection with the default view
+fn frame_selection(
+    current_dir: &CurrentDirectory,
+    layouts: &Layouts,
+    camera_state: &mut CameraState,
+) {
+    *camera_state = CameraState::default();
+    update_camera_target(current_dir, layouts, camera_state);
+}
+
+/// `zb` / `zt` - pull back until every entry of the directory is in view
+fn frame_directory(
+    current_dir: &CurrentDirectory,
+    layouts: &Layouts,
+    camera_state: &mut CameraState,
+    angle: f32,
+) {
+    let count = current_dir.entries.len().max(1);
+    let (min, max) = (0..count)
+        .map(|i| layouts.position(i, count).xz())
+        .fold((Vec2::MAX, Vec2::MIN), |(min, max), p| {
+            (min.min(p), max.max(p))
+        });
+    let extent = max - min;
+
+    let middle = (min + max) / 2.0;
+    camera_state.target = Vec3::new(middle.x, 0.0, middle.y);
+    camera_state.pan = Vec3::ZERO;
+    camera_state.angle = angle;
+    camera_state.distance = (extent.max_element() * CAMERA_FRAME_MARGIN).clamp(10.0, 100.0);
+}
+
+/// Height of an entry's book; files grow with size on the scale of heights.rs
+fn entry_height(entry: &FileEntry) -> f32 {
+    if entry.is_dir {
+        BASE_HEIGHT
+    } else {
+        BASE_HEIGHT + heights::fraction(entry.size) * (MAX_HEIGHT - BASE_HEIGHT)
+    }
+}
+
+/// Put the entries in `range` into the register (`..` is never yanked)
+fn yank_entries(
+    register: &mut Register,
+    current_dir: &CurrentDirectory,
+    range: std::ops::RangeInclusive<usize>,
+    kind: TransferKind,
+    status: &mut StatusMessage,
+) {
+    let entries: Vec<&FileEntry> = current_dir
+        .entries
+        .iter()
+        .skip(*range.start())
+        .take(range.count())
+        .filter(|entry| entry.name != "..")
+        .collect();
+    yank(register, &entries, kind, status);
+}
+
+/// `yank` the entries at `paths` that are still listed
+fn yank_paths(
+    register: &mut Register,
+    current_dir: &CurrentDirectory,
+    paths: &[PathBuf],
+    kind: TransferKind,
+    status: &mut StatusMessage,
+) {
+    let entries: Vec<&FileEntry> = current_dir
+        .entries
+        .iter()
+        .filter(|entry| paths.contains(&entry.path))
+        .collect();
+    yank(register, &entries, kind, status);
+}
+
+/// Put `entries` into the register
+fn yank(
+    register: &mut Register,
+    entries: &[&FileEntry],
+    kind: TransferKind,
+    status: &mut StatusMessage,
+) {
+    if entries.is_empty() {
+        return;
+    }
+
+    register.kind = Some(kind);
+    register.paths = entries.iter().map(|entry| entry.path.clone()).collect();
+    let what = match entries {
+        [entry] => entry.name.clone(),
+        _ => format!("{} entries", entries.len()),
+    };
+    status.0 = match kind {
+        TransferKind::Move => format!("Cut {}", what),
+        _ => format!("Yanked {}", what),
+    };
+}
+
+/// Plan a paste of the register into the current directory, asking first on
+/// conflicts
+fn paste_register(
+    register: &mut Register,
+    as_links: bool,
+    current_dir: &mut CurrentDirectory,
+    config: &Config,
+    prompt: &mut Prompt,
+    status: &mut StatusMessage,
+    jobs: &mut JobQueue,
+) {
+    let Some(plan) = plan_paste(register, as_links, current_dir, status) else {
+        return;
+    };
+    offer_transfer(plan, config, prompt, jobs);
+}
+
+/// The transfer a paste would run; `as_links` makes symlinks to the
+/// register's entries instead, named `foo (1)` where the name is taken
+fn plan_paste(
+    register: &Register,
+    as_links: bool,
+    current_dir: &CurrentDirectory,
+    status: &mut StatusMessage,
+) -> Option<TransferPlan> {
+    let Some(kind) = register.kind else {
+        status.0 = "Nothing to paste".to_string();
+        return None;
+    };
+    let kind = if as_links {
+        TransferKind::Symlink
+    } else {
+        kind
+    };
+    let mut plan = ops::plan_transfer(kind, &register.paths, &current_dir.path);
+    if as_links {
+        plan.resolve_existing(RenameStrategy::Suffix);
+    }
+    Some(plan)
+}
+
+/// Queue a plan once its case collisions, then the entries it would replace,
+/// have been asked about; under `:set autorename` they get free names instead
+fn offer_transfer(
+    mut plan: TransferPlan,
+    config: &Config,
+    prompt: &mut Prompt,
+    jobs: &mut JobQueue,
+) {
+    if config.operations.autorename {
+        plan.resolve_case_collisions(RenameStrategy::Suffix);
+        plan.resolve_existing(RenameStrategy::Suffix);
+        queue_transfer(plan, jobs);
+        return;
+    }
+    if plan.conflicts().next().is_some() {
+        prompt.pending = Some(PendingPrompt::CaseCollision(plan));
+        return;
+    }
+    match OverwriteRequest::new(plan) {
+        Ok(request) => prompt.pending = Some(PendingPrompt::Overwrite(request)),
+        Err(plan) => queue_transfer(plan, jobs),
+    }
+}
+
+/// Queue a plan; the job system records it as one undoable group when done
+fn queue_transfer(plan: TransferPlan, jobs: &mut JobQueue) {
+    // Where the trash keeps things says nothing; where they came from does
+    let (preposition, dir) = match plan.kind {
+        TransferKind::Trash => (
+            "from",
+            plan.steps.first().and_then(|step| step.source.parent()),
+        ),
+        _ => ("into", plan.destination()),
+    };
+    let label = format!(
+        "{} {} {} {}",
+        plan.steps.len(),
+        plan.kind.verb(),
+        preposition,
+        dir.map(|dir| dir.display().to_string()).unwrap_or_default()
+    );
+    jobs.push(label, plan);
+}
+
+/// Enter the selected directory, or hand the selected file to the OS
+fn open_selected(
+    current_dir: &mut CurrentDirectory,
+    opened: &mut EventWriter<EntryOpened>,
+) -> Result<(), String> {
+    if let Some(path) = current_dir.enter_selected()? {
+        if remote::location(&path).is_some() {
+            remote::open(&path);
+        } else {
+            open_with_default_app(&path);
+        }
+        opened.send(EntryOpened { path });
+    }
+    Ok(())
+}
+
+/// `.` / `zh` / `zi` - same as `:set hidden!` / `:set gitignore!`
+fn toggle_listing(config: &mut Config, option: &str, status: &mut StatusMessage) {
+    if let Some(value) = config.option_mut(option) {
+        *value = !*value;
+        status.0 = if *value {
+            option.to_string()
+        } else {
+            format!("no{}", option)
+        };
+    }
+}
+
+/// Reload when dotfiles or ignored files are shown or hidden, keeping the
+/// cursor on the selected entry or, if that just got hidden, the nearest one
+/// still listed
+fn reload_on_listing_toggle(
+    config: Res<Config>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut shown: Local<Option<(bool, bool)>>,
+) {
+    let listing = (config.listing.hidden, config.listing.gitignore);
+    if !config.is_changed() || *shown == Some(listing) {
+        return;
+    }
+    let first_run = shown.is_none();
+    *shown = Some(listing);
+    if first_run {
+        return;
+    }
+    let (hidden, gitignore) = listing;
+    let kept = gitignore.then(|| gitignore::kept_entries(&current_dir.path));
+    let selected = current_dir.selected_index;
+    let entries = &current_dir.entries;
+    let visible = |index: &usize| {
+        let entry = &entries[*index];
+        (hidden || !entry.is_hidden())
+            && kept
+                .as_ref()
+                .is_none_or(|kept| entry.name == ".." || kept.contains(&entry.path))
+    };
+    current_dir.pending_select = (selected..entries.len())
+        .find(visible)
+        .or_else(|| (0..selected).rev().find(visible))
+        .map(|index| entries[index].path.clone());
+    current_dir.needs_reload = true;
+}
+
+/// Why a directory can't be entered: it has to be listable (read permission)
+/// and its entries reachable (execute permission)
+fn enter_error(path: &Path) -> Option<std::io::Error> {
+    // A remote directory, saved search or the demo says why when it's listed
+    if remote::location(path).is_some() || searches::is_saved_search(path) || demofs::is_demo(path)
+    {
+        return None;
+    }
+    if let Err(err) = std::fs::read_dir(path) {
+        return Some(err);
+    }
+    // Listing works without execute permission, but nothing inside can be opened
+    std::fs::metadata(path.join(".")).err()
+}
+
+/// Open a file with the OS default application (Felipe doesn't reinvent viewers)
+fn open_with_default_app(path: &Path) {
+    #[cfg(target_os = "windows")]
+    let result = std::process::Command::new("cmd")
+        .args(["/C", "start", ""])
+        .arg(path)
+        .spawn();
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("open").arg(path).spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = std::process::Command::new("xdg-open").arg(path).spawn();
+
+    if let Err(err) = result {
+        warn!("Failed to open {}: {}", path.display(), err);
+    }
+}
+
+/// Per-user storage for state that outlives a session (snapshots, ...)
+fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("felipe"))
+}
+
+fn calculate_camera_position(camera_state: &CameraState) -> Vec3 {
+    let offset = Vec3::new(
+        0.0,
+        camera_state.distance * camera_state.angle.sin(),
+        -camera_state.distance * camera_state.angle.cos(),
+    );
+    camera_state.focus() + Quat::from_rotation_y(camera_state.yaw) * offset
+}
+
+// =============================================================================
+// Update Systems
+// =============================================================================
+
+fn update_camera(
+    camera_state: Res<CameraState>,
+    fly: Res<FlyCamera>,
+    config: Res<Config>,
+    mut look_at: Local<Option<Vec3>>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    // The fly-through camera drives the transform itself
+    if fly.enabled {
+        *look_at = None;
+        return;
+    }
+    // Ease the look-at point too, so reframing turns the camera instead of snapping it
+    // (accessible mode jumps straight there)
+    let ease = if config.render.accessible { 1.0 } else { 0.1 };
+    let focus = look_at.get_or_insert(camera_state.focus());
+    *focus = focus.lerp(camera_state.focus(), ease);
+    for mut transform in camera_query.iter_mut() {
+        let target_pos = calculate_camera_position(&camera_state);
+        // Smooth interpolation
+        transform.translation = transform.translation.lerp(target_pos, ease);
+        transform.look_at(*focus, Vec3::Y);
+    }
+}
+
+/// The cursor entry, or any entry inside the VISUAL range
+fn is_highlighted(current_dir: &CurrentDirectory, vim_mode: VimMode, index: usize) -> bool {
+    index == current_dir.selected_index
+        || (vim_mode == VimMode::Visual && current_dir.visual_range().contains(&index))
+}
+
+/// Tint for an entry that isn't highlighted, if something asked for one
+fn entry_tint(current_dir: &CurrentDirectory, tints: &EntryTints, index: usize) -> Option<Color> {
+    let entry = current_dir.entries.get(index)?;
+    tints.colors.get(&entry.path).copied()
+}
+
+/// Pulse the selection and fade hover glow in and out
+fn animate_file_materials(
+    time: Res<Time>,
+    current_dir: Res<CurrentDirectory>,
+    vim_mode: Res<VimMode>,
+    tints: Res<EntryTints>,
+    hovered: Res<HoveredEntry>,
+    frecency: Res<Frecency>,
+    glitch: Res<Glitch>,
+    git: Res<GitStatus>,
+    color_by: Res<ColorBy>,
+    config: Res<Config>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    mut query: Query<(&FileEntity, &mut EntryGlow, &mut Handle<StandardMaterial>)>,
+) {
+    let still = config.render.accessible;
+    let phase = time.elapsed_seconds() * std::f32::consts::TAU / PULSE_PERIOD;
+    for (file_entity, mut glow, mut material) in query.iter_mut() {
+        let is_selected = is_highlighted(&current_dir, *vim_mode, file_entity.index);
+        let entry = current_dir.entries.get(file_entity.index);
+
+        let color = if is_selected {
+            FELIPE_ORANGE
+        } else if let Some(tint) = entry_tint(&current_dir, &tints, file_entity.index) {
+            tint
+        } else if let Some(heat) = entry.and_then(|e| color_by.color(e)) {
+            heat
+        } else if entry.is_some_and(|e| e.link.as_ref().is_some_and(LinkTarget::is_broken)) {
+            DIFF_REMOVED
+        } else if entry
+            .and_then(|e| e.inode)
+            .is_some_and(|inode| current_dir.shared_inodes.contains(&inode))
+        {
+            links::HARDLINK_COLOR
+        } else if let Some(state) = entry.and_then(|e| git.entry(&e.path)) {
+            state.color()
+        } else if let Some(entry) = entry.filter(|e| e.is_dir) {
+            // Well-used shelves wear towards the file color
+            FELIPE_GRID.mix(&FELIPE_ORANGE_DIM, frecency.wear(&entry.path))
+        } else {
+            file_entity.shape.color().unwrap_or(FELIPE_ORANGE_DIM)
+        };
+        // Red flash on an entry an operation just failed on
+        let flash = entry.map(|e| glitch.flash(&e.path)).unwrap_or(0.0);
+        let color = color.mix(&DIFF_REMOVED, flash);
+
+        let hover_target = if hovered.0 == Some(file_entity.index) {
+            1.0
+        } else {
+            0.0
+        };
+        if still {
+            glow.hover = hover_target;
+        } else {
+            let step = HOVER_FADE_SPEED * time.delta_seconds();
+            glow.hover += (hover_target - glow.hover).clamp(-step, step);
+        }
+
+        let brightness = if is_selected && !still {
+            0.8 + 0.2 * phase.sin()
+        } else {
+            1.0
+        };
+        let base = color.to_linear();
+        let lift = glow.hover * 0.35;
+        let glowing = LinearRgba::new(
+            base.red * brightness * (1.0 - lift) + lift,
+            base.green * brightness * (1.0 - lift) + lift,
+            base.blue * brightness * (1.0 - lift) + lift,
+            1.0,
+        );
+
+        // Swapping handles only when the color changes keeps idle entries batched
+        let shared = match entry {
+            Some(entry) => palette.entry_material(&mut materials, entry, glowing),
+            None => palette.material(&mut materials, glowing),
+        };
+        if *material != shared {
+            *material = shared;
+        }
+    }
+}
+
+/// Give each book the mesh of its shape, as edge outlines when `wireframe` is
+/// set; a config reload or sniffed content may also change an entry's shape
+fn apply_entry_meshes(
+    config: Res<Config>,
+    mimes: Res<MimeTypes>,
+    current_dir: Res<CurrentDirectory>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut palette: ResMut<EntryPalette>,
+    mut query: Query<(&mut FileEntity, &mut Handle<Mesh>)>,
+) {
+    for (mut file_entity, mut mesh) in query.iter_mut() {
+        if !config.is_changed() && !mimes.is_changed() && !file_entity.is_added() {
+            continue;
+        }
+        if let Some(entry) = current_dir.entries.get(file_entity.index) {
+            let shape = config.shapes.shape(entry, mimes.get(&entry.path));
+            if file_entity.shape != shape {
+                file_entity.shape = shape;
+            }
+        }
+        let shared = file_entity
+            .shape
+            .mesh(&mut palette, &mut meshes, config.render.wireframe);
+        if *mesh != shared {
+            *mesh = shared;
+        }
+    }
+}
+
+fn update_file_labels(
+    current_dir: Res<CurrentDirectory>,
+    vim_mode: Res<VimMode>,
+    tints: Res<EntryTints>,
+    rename_line: Res<RenameLine>,
+    layouts: Res<Layouts>,
+    config: Res<Config>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut label_query: Query<
+        (&FileLabel, &Transform, &mut Text, &mut Visibility),
+        Without<MainCamera>,
+    >,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let count = current_dir.entries.len();
+    let selection = layouts.position(current_dir.selected_index, count);
+
+    for (file_label, transform, mut text, mut visibility) in label_query.iter_mut() {
+        let is_selected = is_highlighted(&current_dir, *vim_mode, file_label.index);
+
+        // Level of detail: near the camera or the selection stays readable
+        let near_selection = layouts
+            .position(file_label.index, count)
+            .distance(selection)
+            <= LABEL_SELECTION_RADIUS;
+        let distance = transform.translation.distance(camera.translation);
+        let opacity = if is_selected || near_selection {
+            1.0
+        } else {
+            1.0 - ((distance - LABEL_FADE_START) / (LABEL_HIDE_DISTANCE - LABEL_FADE_START))
+                .clamp(0.0, 1.0)
+        };
+        let wanted = if opacity > 0.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+        // The label being renamed is colored by rename mode
+        if opacity <= 0.0 || rename_line.target_index() == Some(file_label.index) {
+            continue;
+        }
+
+        let color = if is_selected {
+            FELIPE_ORANGE
+        } else {
+            entry_tint(&current_dir, &tints, file_label.index)
+                .unwrap_or_else(|| accessibility::dim_text(&config))
+        };
+        // Writing Text re-lays it out, so leave unchanged labels alone
+        let color = color.with_alpha(opacity);
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    }
+}
+
+/// Shorten a long name to `head…tail`, keeping the end so the extension stays visible
+fn truncate_label(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    if chars.len() <= LABEL_MAX_CHARS {
+        return name.to_string();
+    }
+    let tail = 7;
+    let head = LABEL_MAX_CHARS - tail - 1;
+    let head: String = chars[..head].iter().collect();
+    let tail: String = chars[chars.len() - tail..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+fn update_ui(
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    filter: Res<Filter>,
+    git: Res<GitStatus>,
+    mimes: Res<MimeTypes>,
+    previews: Res<Previews>,
+    volumes: Res<Volumes>,
+    prompt: Res<Prompt>,
+    status: Res<StatusMessage>,
+    (mut path_query, mut info_query): (
+        Query<&mut Text, With<PathDisplay>>,
+        Query<&mut Text, (With<PathInfo>, Without<PathDisplay>)>,
+    ),
+    mut status_query: Query<&mut Text, (With<StatusLine>, Without<PathDisplay>, Without<PathInfo>)>,
+) {
+    // Update path display
+    for mut text in path_query.iter_mut() {
+        let selected_entry = current_dir.entries.get(current_dir.selected_index);
+        let selected_name = selected_entry.map(|e| e.name.as_str()).unwrap_or("");
+        let file_info = if let Some(entry) = selected_entry {
+            let drive = volumes::is_drives_view(&current_dir.path)
+                .then(|| {
+                    volumes
+                        .list
+                        .iter()
+                        .find(|volume| volume.mount == entry.path)
+                })
+                .flatten();
+            let kind = if let Some(drive) = drive {
+                format!(
+                    " [{} free of {}, {}]",
+                    config.format.size(drive.available),
+                    config.format.size(drive.total),
+                    drive.file_system
+                )
+            } else if entry.is_dir {
+                " [DIR]".to_string()
+            } else {
+                let mut details = vec![config.format.size(entry.size)];
+                details.extend(mimes.get(&entry.path).map(str::to_string));
+                details.extend(previews.summary(&entry.path));
+                if entry.inode.is_some() {
+                    details.push("hard-linked".to_string());
+                }
+                format!(" [{}]", details.join(", "))
+            };
+            let described = match &entry.link {
+                Some(link) => match &link.resolved {
+                    Ok(_) => format!(" -> {}{}", link.path.display(), kind),
+                    Err(err) => format!(" -> {} [broken: {}]", link.path.display(), err),
+                },
+                None => kind,
+            };
+            let tagged: String = tags::tags_of(&entry.path)
+                .into_iter()
+                .map(|color| format!(" #{}", color.name()))
+                .collect();
+            let starred = if stars::is_starred(&entry.path) {
+                " (starred)"
+            } else {
+                ""
+            };
+            let note = notes::note_of(&entry.path)
+                .map(|note| format!("  \"{}\"", note))
+                .unwrap_or_default();
+            format!("{}{}{}{}", described, tagged, starred, note)
+        } else {
+            String::new()
+        };
+
+        let flat = if current_dir.flat_root.is_some() {
+            " (flat)"
+        } else {
+            ""
+        };
+        let pattern = match &filter.pattern {
+            Some(pattern) => format!("  [filter: {}]", pattern.text),
+            None => String::new(),
+        };
+        // Branch, with * like a shell prompt while there are uncommitted changes
+        let branch = match &git.repo {
+            Some(repo) => format!(
+                "  [git: {}{}]",
+                repo.branch,
+                if repo.dirty { "*" } else { "" }
+            ),
+            None => String::new(),
+        };
+        // Only `..` left, and not because of a filter; a remote listing may
+        // still be on its way
+        let empty = current_dir.entries.iter().all(|entry| entry.name == "..")
+            && filter.pattern.is_none()
+            && !filter.is_active()
+            && remote::location(&current_dir.path).is_none();
+        let state = match &current_dir.unreadable {
+            Some(err) => format!("  [unreadable: {}]", err),
+            None if empty => "  (empty)".to_string(),
+            None => String::new(),
+        };
+        for mut text in info_query.iter_mut() {
+            text.sections[0].value = format!(
+                "{}{}{}{}{}",
+                remote::connection_label(&current_dir.path),
+                state,
+                flat,
+                pattern,
+                branch,
+            );
+        }
+        text.sections[0].value = format!("▶ {}{}", selected_name, file_info);
+    }
+
+    // Update status line - a pending prompt takes precedence over messages
+    for mut text in status_query.iter_mut() {
+        text.sections[0].value = match &prompt.pending {
+            Some(pending) => pending.message(),
+            None => status.0.clone(),
+        };
+    }
+}
+
+// =============================================================================
+// App Entry Point
+// =============================================================================
+
+/// The whole file browser: everything but the window, which comes from the
+/// app's `DefaultPlugins`
+#[derive(Default)]
+pub struct FelipePlugin {
+    /// Render preset applied over the config's `[render]` section
+    pub theme: Option<Theme>,
+}
+
+impl Plugin for FelipePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<Config>() {
+            let (config, safe_mode) = config::load_or_safe_mode();
+            app.insert_resource(config).insert_resource(safe_mode);
+        }
+        if let Some(theme) = self.theme {
+            theme.apply(&mut app.world_mut().resource_mut::<Config>().render);
+        }
+        app.add_plugins((
+            CloudSyncPlugin,
+            ColorByPlugin,
+            CommandPlugin,
+            ConfigPlugin,
+            ConflictsPlugin,
+            CrtPlugin,
+            EventsPlugin,
+            FilterPlugin,
+            FlattenPlugin,
+            FlyCameraPlugin,
+            FocusPlugin,
+            FrecencyPlugin,
+            GitPlugin,
+            GitLogPlugin,
+            GlitchPlugin,
+        ))
+        // Bevy takes at most 15 plugins per tuple
+        .add_plugins((
+            HistoryPlugin,
+            JobsPlugin,
+            LinksPlugin,
+            MimePlugin,
+            OplogPlugin,
+            PlayerPlugin,
+            PropertiesPlugin,
+            RenamePlugin,
+            SnapshotPlugin,
+            SortPlugin,
+            SoundPlugin,
+            TrailPlugin,
+            TransitionPlugin,
+            TutorialPlugin,
+            WhatsNewPlugin,
+        ))
+        .add_plugins((
+            BookmarksPlugin,
+            ChecksumPlugin,
+            CustomPlugin,
+            CwdFilePlugin,
+            DeepJumpPlugin,
+            DiffPlugin,
+            HooksPlugin,
+            LayoutPlugin,
+            PickerPlugin,
+            PreviewPlugin,
+            ScriptPlugin,
+            ShellPlugin,
+            SplitPlugin,
+            TerminalPlugin,
+            WorkspacePlugin,
+        ))
+        .add_plugins((
+            BreadcrumbsPlugin,
+            DashboardPlugin,
+            DirSyncPlugin,
+            DryRunPlugin,
+            HelpPlugin,
+            MinimapPlugin,
+            NotesPlugin,
+            RecoveryPlugin,
+            RemotePlugin,
+            SearchesPlugin,
+            StarsPlugin,
+            StatsPlugin,
+            TagsPlugin,
+            TreePlugin,
+            VolumesPlugin,
+        ))
+        .add_plugins((
+            AccessibilityPlugin,
+            BasketPlugin,
+            ClipboardPlugin,
+            DroppedPlugin,
+            FontPlugin,
+            HeightsPlugin,
+            LocalePlugin,
+            NumbersPlugin,
+            PowerPlugin,
+            QualityPlugin,
+            RecordPlugin,
+            StatusLinePlugin,
+            UiScalePlugin,
+            WhichKeyPlugin,
+            WindowStatePlugin,
+        ))
+        .add_plugins((
+            DebugPlugin,
+            DirWeightPlugin,
+            GrowthPlugin,
+            OverwritePlugin,
+            PeekPlugin,
+            TrashPlugin,
+        ))
+        // Resources the app inserted first (like `run` does) are kept
+        .insert_resource(ClearColor(FELIPE_BLACK))
+        .init_resource::<CurrentDirectory>()
+        .init_resource::<VimMode>()
+        .init_resource::<CameraState>()
+        .init_resource::<MouseClickState>()
+        .init_resource::<HoveredEntry>()
+        .init_resource::<DragState>()
+        .init_resource::<Register>()
+        .init_resource::<StatusMessage>()
+        .init_resource::<Prompt>()
+        .init_resource::<EntryTints>()
+        .init_resource::<EntryPalette>()
+        .init_resource::<EntryWindow>()
+        .init_resource::<Picker>()
+        .add_systems(Startup, (setup_camera, setup_ui))
+        .add_systems(
+            Update,
+            (
+                load_directory,
+                reload_on_listing_toggle.before(load_directory),
+                despawn_file_entities.before(load_directory),
+                spawn_file_entities.after(load_directory),
+                stream_entry_window.after(spawn_file_entities),
+                apply_entry_meshes.after(stream_entry_window),
+                handle_count_keys.before(handle_keyboard),
+                handle_keyboard,
+                handle_prompt.after(handle_keyboard),
+                handle_mouse_click,
+                handle_mouse_drag.after(handle_mouse_click),
+                handle_mouse_wheel,
+                handle_mouse_orbit,
+                update_camera,
+                update_hovered_entry,
+                animate_file_materials.after(update_hovered_entry),
+                update_file_labels,
+                update_ui,
+                draw_grid,
+            ),
+        );
+        #[cfg(all(unix, feature = "ipc"))]
+        app.add_plugins(ipc::IpcPlugin);
+        #[cfg(feature = "index")]
+        app.add_plugins(index::IndexPlugin);
+        #[cfg(feature = "xr")]
+        app.add_plugins(xr::XrPlugin);
+    }
+}
+
+/// The `felipe` binary: command-line options, then the app in its own window
+pub fn run() {
+    let args = <cli::Args as clap::Parser>::parse();
+    if let Some(script) = &args.batch {
+        std::process::exit(batch::run_cli(script, args.dry_run));
+    }
+    logging::log_panics();
+    recovery::save_on_panic();
+    #[cfg(all(target_os = "linux", feature = "portal"))]
+    if args.portal {
+        std::process::exit(portal::serve());
+    }
+    if let Some(path) = args.config.clone() {
+        config::use_config_file(path);
+    }
+    let (mut config, safe_mode) = config::load_or_safe_mode();
+    if let Some(err) = &safe_mode.error {
+        eprintln!("felipe: safe mode, config not loaded: {}", err);
+    }
+    if args.show_hidden {
+        config.listing.hidden = true;
+    }
+    if args.read_only {
+        ops::lock_read_only();
+    }
+    if args.gpu_low {
+        config.render.quality = Quality::Low;
+    }
+    let pick_mode = args.pick_mode();
+
+    // A directory to start in, or a workspace whose roots open as tabs
+    let mut current_dir = CurrentDirectory::default();
+    let workspace = match &args.path {
+        _ if args.demo => {
+            current_dir.path = demofs::demo_root();
+            Workspace::default()
+        }
+        Some(path) if remote::location(path).is_some() => {
+            current_dir.path = path.clone();
+            Workspace::default()
+        }
+        Some(path) if path.is_dir() => {
+            current_dir.path = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+            Workspace::default()
+        }
+        Some(path) => match workspace::load(path) {
+            Ok(workspace) => {
+                if let Some(root) = workspace.active_path() {
+                    current_dir.path = root.to_path_buf();
+                }
+                workspace
+            }
+            Err(err) => {
+                eprintln!("felipe: {}: {}", path.display(), err);
+                std::process::exit(2);
+            }
+        },
+        None => Workspace::default(),
+    };
+    if args.layout == Some(cli::Layout::Flat) {
+        current_dir.flat_root = Some(current_dir.path.clone());
+    }
+    #[cfg(feature = "tui")]
+    if args.tui {
+        logging::log_to_file(args.verbose);
+        locale::select(&config.locale);
+        std::process::exit(tui::run(current_dir, config, args.cwd_file));
+    }
+
+    let mut window = Window {
+        title: "Felipe - File Manager".to_string(),
+        ..default()
+    };
+    windowstate::restore(&mut window, &mut config);
+
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(window),
+                ..default()
+            })
+            .set(logging::log_plugin(args.verbose)),
+    )
+    .insert_resource(current_dir)
+    .insert_resource(EventStream {
+        enabled: args.events_json,
+    })
+    .insert_resource(Picker::new(pick_mode))
+    .insert_resource(CwdFile(args.cwd_file.clone()))
+    .insert_resource(workspace)
+    .insert_resource(config)
+    .insert_resource(safe_mode);
+    // The first line of a log attached to a bug report
+    info!(
+        "Felipe {} on {}, starting in {}",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        app.world().resource::<CurrentDirectory>().path.display()
+    );
+    #[cfg(all(unix, feature = "ipc"))]
+    app.insert_resource(ipc::IpcSocket(args.socket.clone()));
+    app.add_plugins(FelipePlugin { theme: args.theme }).run();
+    if let Some(mode) = pick_mode {
+        std::process::exit(picker::finish(mode));
+    }
+}
@@ -0,0 +1,240 @@
+//! Symlinks - shown as see-through stand-ins for what they point at
+//!
+//! A symlink takes the shape of its target (a directory link is a directory),
+//! but its book is translucent, and red when the target is missing. When the
+//! target is listed in the same directory, a dashed arrow arcs from the link
+//! to it. The info bar shows where the selected link points.
+//!
+//! `l` follows links like any entry. A link back to the current directory or
+//! one of its ancestors jumps to the target instead of nesting `a/link/link/...`
+//! forever, and links that loop or dangle are reported rather than entered.
+//!
+//! Hard links have no direction, so entries listed together that are the same
+//! file (one inode under several names) are tinted violet instead.
+//!
+//! `:ln target [name]` makes a hard link in the current directory and
+//! `:ln -s target [name]` a symlink, like `ln`; `P` pastes the register as
+//! symlinks. Either way the link is queued like a paste and `u` removes it.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+use crate::command::{Command, RunCommand};
+use crate::jobs::JobQueue;
+use crate::ops::{PlannedStep, TransferKind, TransferPlan};
+use crate::{
+    entry_height, queue_transfer, CurrentDirectory, EntryWindow, FileEntry, Layouts, StatusMessage,
+    FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+/// Tint of entries that share their inode with another listed entry
+pub const HARDLINK_COLOR: Color = Color::srgb(0.7, 0.4, 1.0);
+/// Height of the arrow's arc above the taller of the two books
+const ARROW_LIFT: f32 = 1.5;
+/// Dashes per arrow; each is followed by a gap of the same length
+const ARROW_DASHES: usize = 12;
+
+/// Where a symlink points
+#[derive(Clone, Debug)]
+pub struct LinkTarget {
+    /// As written in the link, possibly relative
+    pub path: PathBuf,
+    /// The canonical target, or why there is none (dangling or looping)
+    pub resolved: Result<PathBuf, String>,
+}
+
+impl LinkTarget {
+    pub fn is_broken(&self) -> bool {
+        self.resolved.is_err()
+    }
+}
+
+/// The target of `path` if it's a symlink
+pub fn read_link(path: &Path) -> Option<LinkTarget> {
+    let target = std::fs::read_link(path).ok()?;
+    Some(LinkTarget {
+        path: target,
+        resolved: std::fs::canonicalize(path).map_err(|err| err.to_string()),
+    })
+}
+
+/// Inode of a file with more than one name, so its siblings can be found
+#[cfg(unix)]
+pub fn hardlink_inode(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    (metadata.is_file() && metadata.nlink() > 1).then(|| metadata.ino())
+}
+
+#[cfg(not(unix))]
+pub fn hardlink_inode(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Inodes that more than one of `entries` names
+pub fn shared_inodes(entries: &[FileEntry]) -> HashSet<u64> {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .filter_map(|entry| entry.inode)
+        .filter(|inode| !seen.insert(*inode))
+        .collect()
+}
+
+/// Symlinks in `entries` whose target is another of the entries, as (link,
+/// target) indices
+pub fn sibling_links(dir: &Path, entries: &[FileEntry]) -> Vec<(usize, usize)> {
+    if !entries.iter().any(|entry| entry.link.is_some()) {
+        return Vec::new();
+    }
+    let Ok(here) = std::fs::canonicalize(dir) else {
+        return Vec::new();
+    };
+    // Entries by canonical path; `..` points up, not at a sibling
+    let by_path: HashMap<PathBuf, usize> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.name != "..")
+        .filter_map(|(i, entry)| {
+            let relative = entry.path.strip_prefix(dir).ok()?;
+            Some((here.join(relative), i))
+        })
+        .collect();
+    entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let target = entry.link.as_ref()?.resolved.as_ref().ok()?;
+            let j = *by_path.get(target)?;
+            (i != j).then_some((i, j))
+        })
+        .collect()
+}
+
+/// Where `l` should go for a symlinked directory: the link itself, or its
+/// target when that's the current directory or above it
+pub fn enter_path(current: &Path, entry: &FileEntry) -> Result<PathBuf, String> {
+    let Some(link) = &entry.link else {
+        return Ok(entry.path.clone());
+    };
+    let target = link
+        .resolved
+        .as_ref()
+        .map_err(|err| format!("Cannot follow {}: {}", entry.name, err))?;
+    let here = std::fs::canonicalize(current).unwrap_or_else(|_| current.to_path_buf());
+    if here.starts_with(target) {
+        Ok(target.clone())
+    } else {
+        Ok(entry.path.clone())
+    }
+}
+
+pub struct LinksPlugin;
+
+impl Plugin for LinksPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_link_command, draw_link_arrows));
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// `:ln` - a link in the current directory, queued like a paste
+fn handle_link_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut jobs: ResMut<JobQueue>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Link {
+            target,
+            name,
+            symbolic,
+        } = command
+        else {
+            continue;
+        };
+        let name = name.clone().or_else(|| {
+            target
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        });
+        let Some(name) = name else {
+            status.0 = format!("Name the link: :ln {} name", target.display());
+            continue;
+        };
+        let path = current_dir.path.join(&name);
+        if std::fs::symlink_metadata(&path).is_ok() {
+            status.0 = format!("{} already exists", name);
+            continue;
+        }
+        let (kind, source) = if *symbolic {
+            // Kept as typed: a relative symlink resolves from where it lives
+            (TransferKind::Symlink, target.clone())
+        } else {
+            let source = current_dir.path.join(target);
+            if std::fs::symlink_metadata(&source).is_ok_and(|m| m.is_dir()) {
+                status.0 = "Directories can't be hard-linked; use :ln -s".to_string();
+                continue;
+            }
+            (TransferKind::Hardlink, source)
+        };
+        let plan = TransferPlan {
+            kind,
+            steps: vec![PlannedStep {
+                source,
+                target: path.clone(),
+                conflict: None,
+                replace: false,
+            }],
+            skipped: Vec::new(),
+        };
+        queue_transfer(plan, &mut jobs);
+        current_dir.pending_select = Some(path);
+    }
+}
+
+/// Dashed arc from each link to its target, for entries that have books
+fn draw_link_arrows(
+    current_dir: Res<CurrentDirectory>,
+    window: Res<EntryWindow>,
+    layouts: Res<Layouts>,
+    mut gizmos: Gizmos,
+) {
+    let count = current_dir.entries.len();
+    for &(link, target) in &current_dir.links {
+        if !window.range.contains(&link) && !window.range.contains(&target) {
+            continue;
+        }
+        let (Some(from), Some(to)) = (
+            current_dir.entries.get(link),
+            current_dir.entries.get(target),
+        ) else {
+            continue;
+        };
+        let color = if current_dir.selected_index == link {
+            FELIPE_ORANGE
+        } else {
+            FELIPE_ORANGE_DIM
+        };
+        let start = layouts.position(link, count) + Vec3::Y * entry_height(from);
+        let end = layouts.position(target, count) + Vec3::Y * entry_height(to);
+        let lift = Vec3::Y * (ARROW_LIFT + (start.y - end.y).abs());
+        // Quadratic curve through the midpoint raised by `lift`
+        let point = |t: f32| start.lerp(end, t) + lift * 4.0 * t * (1.0 - t);
+
+        let step = 1.0 / (ARROW_DASHES * 2) as f32;
+        for dash in 0..ARROW_DASHES - 1 {
+            let t = dash as f32 * 2.0 * step;
+            gizmos.line(point(t), point(t + step), color);
+        }
+        gizmos
+            .arrow(point(1.0 - 2.0 * step), point(1.0), color)
+            .with_tip_length(0.4);
+    }
+}
@@ -0,0 +1,292 @@
+//! Locale - the UI's words in English or Japanese
+//!
+//! Text shown on screen goes through `tr` (or `trf` when it has values in
+//! it), keyed by the English text itself, gettext-style: the catalog below
+//! maps it to the other language, and text with no entry stays English, so a
+//! message can be added before it is translated. The mode names, the help
+//! overlay, the which-key hints, the prompts and the safe-mode banner are in
+//! the catalog.
+//!
+//! The language comes from `[locale]`, or from `LC_ALL`, `LC_MESSAGES` and
+//! `LANG` when it isn't set (`ja_JP.UTF-8` picks Japanese). Japanese needs a
+//! font with its glyphs (see font.rs).
+//!
+//! ```toml
+//! [locale]
+//! language = "ja"           # or "en"
+//! ```
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+use crate::config::Config;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    En,
+    Ja,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocaleConfig {
+    /// From the environment when unset
+    pub language: Option<Language>,
+}
+
+/// The language `tr` answers in, as a `Language` discriminant
+static LANGUAGE: AtomicU8 = AtomicU8::new(Language::En as u8);
+
+/// Japanese for the English text, one pair per message
+const JA: &[(&str, &str)] = &[
+    // Status line (statusline.rs)
+    ("-- NORMAL --", "-- ノーマル --"),
+    ("-- VISUAL --", "-- ビジュアル --"),
+    ("-- FIND --", "-- 検索 --"),
+    ("-- RENAME --", "-- 名前変更 --"),
+    ("-- FILTER --", "-- 絞り込み --"),
+    ("sort: {}", "並び順: {}"),
+    ("filter: {}", "絞り込み: {}"),
+    ("1 job", "ジョブ 1 件"),
+    ("{} jobs", "ジョブ {} 件"),
+    ("basket: {}", "バスケット: {}"),
+    ("{} free", "空き {}"),
+    ("[READ-ONLY]", "[読み取り専用]"),
+    ("[DRY-RUN]", "[ドライラン]"),
+    ("?:help  ::command", "?:ヘルプ  ::コマンド"),
+    // Prompts (lib.rs, custom.rs, properties.rs)
+    (
+        "Case collision ({}) - r:rename  s:skip  Esc:cancel",
+        "大文字小文字だけ違う名前 ({}) - r:名前変更  s:スキップ  Esc:取り消し",
+    ),
+    ("Move {} to {}? y:yes  n:no", "{} を {} へ移動しますか? y:はい  n:いいえ"),
+    (
+        "Moving {} failed ({}) after {} moved, {} left - r:roll back  c:continue  Esc:stop here",
+        "{} の移動に失敗 ({})、{} 件移動済み、残り {} 件 - r:元に戻す  c:続ける  Esc:ここで止める",
+    ),
+    ("{} y:yes  n:no", "{} y:はい  n:いいえ"),
+    ("{} is already here ({})", "{} はすでにあります ({})"),
+    ("{} is already here, newer ({})", "{} はすでにあり、こちらの方が新しい ({})"),
+    (
+        "{} - o:overwrite  s:skip  r:rename  n:if newer  O/S/R/N:all  Esc:cancel",
+        "{} - o:上書き  s:スキップ  r:名前変更  n:新しければ上書き  O/S/R/N:残りすべて  Esc:取り消し",
+    ),
+    ("Trash {}? y:yes  n:no", "{} をゴミ箱へ移動しますか? y:はい  n:いいえ"),
+    (
+        "Trash {} entries ({}{})? y:yes  n:no",
+        "{} 件 ({}{}) をゴミ箱へ移動しますか? y:はい  n:いいえ",
+    ),
+    ("Tag: r o y g b p  x:clear  Esc:cancel", "タグ: r o y g b p  x:外す  Esc:取り消し"),
+    ("Run {}? y:yes  n:no", "{} を実行しますか? y:はい  n:いいえ"),
+    (
+        "Run {} on {} entries? y:yes  n:no",
+        "{} を {} 件に実行しますか? y:はい  n:いいえ",
+    ),
+    (
+        "chown {}: everything inside {} too? y:yes  n:only the selection  Esc:cancel",
+        "chown {}: {} の中身もすべて? y:はい  n:選択したものだけ  Esc:取り消し",
+    ),
+    // Safe mode and :config (config.rs)
+    ("the last working config", "最後に読めた設定"),
+    ("default settings", "既定の設定"),
+    (
+        "SAFE MODE - config not loaded, using {}\n{}\n:config edit to fix it, :config reload to retry",
+        "セーフモード - 設定を読めなかったので{}を使用中\n{}\n:config edit で直して :config reload で再読み込み",
+    ),
+    ("No config directory on this system", "このシステムには設定ディレクトリがありません"),
+    ("Could not create {}: {}", "{} を作成できません: {}"),
+    (
+        "Editing {} - :config reload when done",
+        "{} を編集中 - 終わったら :config reload",
+    ),
+    (
+        "Config reloaded (render settings apply on restart)",
+        "設定を再読み込みしました (描画の設定は再起動後に反映)",
+    ),
+    ("Config still has errors", "設定にまだエラーがあります"),
+    // Which-key (whichkey.rs)
+    ("frame the selection", "選択を画面に収める"),
+    ("the directory from above", "ディレクトリを上から見る"),
+    ("the whole directory", "ディレクトリ全体を見る"),
+    ("show or hide dotfiles", "ドットファイルの表示切り替え"),
+    ("show or hide ignored files", "無視されたファイルの表示切り替え"),
+    ("yank the selection", "選択をヤンク"),
+    ("copy the full path", "フルパスをコピー"),
+    ("copy the name", "名前をコピー"),
+    ("copy the directory", "ディレクトリをコピー"),
+    ("{} down", "{} 下へ"),
+    ("{} up", "{} 上へ"),
+    ("entry {}", "{} 番目へ"),
+    ("nothing to go to", "移動先がありません"),
+    ("... {} more", "... ほか {} 件"),
+    // Help (help.rs)
+    ("HELP  ?/Esc:close", "ヘルプ  ?/Esc:閉じる"),
+    ("MOVE", "移動"),
+    ("VIEW", "表示"),
+    ("FILES", "ファイル"),
+    ("MOUSE", "マウス"),
+    ("PANELS", "パネル"),
+    ("COMMANDS", "コマンド"),
+    ("YOUR COMMANDS", "ユーザーコマンド"),
+    ("next / previous entry", "次 / 前のエントリ"),
+    ("first / last entry", "最初 / 最後のエントリ"),
+    ("7 down / to entry 15", "7 つ下へ / 15 番目へ"),
+    ("open the directory or file", "ディレクトリかファイルを開く"),
+    ("up to the parent", "親ディレクトリへ"),
+    ("up the path to that segment", "パスのその段まで上がる"),
+    ("go to that portal", "そのポータルへ"),
+    ("fuzzy-find below here", "ここから下をあいまい検索"),
+    ("browse the :tree sidebar", ":tree サイドバーを操作"),
+    ("next root of the workspace", "ワークスペースの次のルート"),
+    (
+        "the whole directory, from above / as is",
+        "ディレクトリ全体を上から / そのまま",
+    ),
+    ("next sort order", "次の並び順"),
+    ("filter by name, Esc clears it", "名前で絞り込み、Esc で解除"),
+    ("swap the sides of :vsplit", ":vsplit の左右を入れ替え"),
+    (
+        "bigger / smaller text and panels, Ctrl-0 resets",
+        "文字とパネルを大きく / 小さく、Ctrl-0 で元に戻す",
+    ),
+    ("fullscreen", "全画面"),
+    ("visual mode, a range of entries", "ビジュアルモード、範囲選択"),
+    ("yank / cut the selection", "選択をヤンク / カット"),
+    ("copy the path / name / directory", "パス / 名前 / ディレクトリをコピー"),
+    ("paste files copied in another app", "他のアプリでコピーしたファイルを貼り付け"),
+    ("put it on the clipboard for another app", "他のアプリ向けにクリップボードへ"),
+    ("copy them here, Shift moves", "ここへコピー、Shiftで移動"),
+    ("paste here / paste as links", "ここに貼り付け / リンクとして貼り付け"),
+    ("rename in place", "その場で名前変更"),
+    ("properties", "プロパティ"),
+    ("trash the selection, u brings it back", "選択をゴミ箱へ、u で戻す"),
+    ("tag with r o y g b p, x clears", "r o y g b p でタグ、x で外す"),
+    ("star", "スター"),
+    ("undo the last operation", "直前の操作を取り消す"),
+    ("play or pause a sound file", "音声ファイルの再生 / 一時停止"),
+    ("mark or unmark, y / m / : act on the marks", "マークの切り替え、y / m / : はマークに作用"),
+    ("open the marked files, together", "マークしたファイルをまとめて開く"),
+    ("unmark everything", "すべてのマークを解除"),
+    ("mark entries here", "ここのエントリをマーク"),
+    ("copy (move) the basket here", "バスケットの中身をここへコピー (移動)"),
+    ("terminal here", "ここでターミナル"),
+    ("select", "選択"),
+    ("open", "開く"),
+    ("move the selection into a directory", "選択をディレクトリへ移動"),
+    ("orbit", "回転"),
+    ("pan", "平行移動"),
+    ("zoom", "ズーム"),
+    ("next / previous open panel", "次 / 前の開いているパネル"),
+    ("close the focused panel", "フォーカス中のパネルを閉じる"),
+    ("this help", "このヘルプ"),
+    ("go to a directory, local or remote", "ディレクトリへ移動、ローカルでもリモートでも"),
+    ("the most used directory matching", "一致する中で最もよく使うディレクトリ"),
+    ("portals to directories", "ディレクトリへのポータル"),
+    ("files matching, :filter! clears", "一致するファイルだけ、:filter! で解除"),
+    ("sort order", "並び順"),
+    ("what colors the books", "本の色分け"),
+    ("arrange the entries another way", "エントリの並べ方を変える"),
+    ("every file below here", "ここから下の全ファイル"),
+    ("a second directory beside this one", "隣にもう一つのディレクトリ"),
+    ("differences with another tree", "別のツリーとの差分"),
+    ("fly around and save a video", "周回して動画に保存"),
+    ("mirror here to dest", "ここを dest へミラー"),
+    ("permissions and owners", "パーミッションと所有者"),
+    ("attributes and links", "拡張属性とリンク"),
+    ("checksums", "チェックサム"),
+    (
+        "a note on the selection, :note! drops it",
+        "選択にメモ、:note! で削除",
+    ),
+    ("starred entries, saved searches", "スター付き、保存した検索"),
+    ("what the tree holds", "ツリーの中身の統計"),
+    ("tag the selection, :tag! color untags", "選択にタグ、:tag! 色 で外す"),
+    ("follow the selected file, :tail! pauses", "選択中のファイルを追跡、:tail! で一時停止"),
+    ("directory tree sidebar", "ディレクトリツリーのサイドバー"),
+    ("what changed since", "その時からの変更"),
+    ("older versions, sync conflicts", "以前の版、同期の競合"),
+    ("history of the selected file", "選択したファイルの履歴"),
+    ("operations, undo and redo", "操作の記録、取り消しとやり直し"),
+    ("drives and devices", "ドライブとデバイス"),
+    ("shell command on the selection", "選択にシェルコマンド"),
+    ("run a Rhai script", "Rhai スクリプトを実行"),
+    ("first-person fly-through", "一人称視点で飛び回る"),
+    ("frame rate, counts and timings", "フレームレート・個数・処理時間"),
+    ("lighter rendering for weak GPUs", "非力な GPU 向けの軽い描画"),
+    ("how sizes become heights", "サイズから高さへの換算"),
+    ("the config file", "設定ファイル"),
+    ("walkthrough, what's new", "チュートリアル、新機能"),
+    ("quit", "終了"),
+];
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        // Before startup spawns the first text, and again on `:config reload`
+        app.add_systems(PreStartup, apply_language)
+            .add_systems(Update, apply_language.run_if(resource_changed::<Config>));
+    }
+}
+
+// =============================================================================
+// Catalog
+// =============================================================================
+
+/// Answer in this language from now on
+pub fn select(config: &LocaleConfig) {
+    let language = config.language.unwrap_or_else(from_env);
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+/// The language the first set locale variable names
+fn from_env() -> Language {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .filter(|value| value.starts_with("ja"))
+        .map_or(Language::En, |_| Language::Ja)
+}
+
+/// `text` in the selected language
+pub fn tr(text: &'static str) -> &'static str {
+    if LANGUAGE.load(Ordering::Relaxed) != Language::Ja as u8 {
+        return text;
+    }
+    static CATALOG: OnceLock<HashMap<&str, &str>> = OnceLock::new();
+    CATALOG
+        .get_or_init(|| JA.iter().copied().collect())
+        .get(text)
+        .copied()
+        .unwrap_or(text)
+}
+
+/// `text` in the selected language with its `{}` filled with `args` in order
+/// (the translation may move them around the sentence, not reorder them)
+pub fn trf(text: &'static str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    for (i, piece) in tr(text).split("{}").enumerate() {
+        if i > 0 {
+            if let Some(arg) = args.next() {
+                out.push_str(&arg.to_string());
+            }
+        }
+        out.push_str(piece);
+    }
+    out
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn apply_language(config: Res<Config>) {
+    select(&config.locale);
+}
@@ -0,0 +1,122 @@
+//! Log file - what Felipe did, for bug reports
+//!
+//! Everything logged (directory loads, file operations, calls to remote
+//! backends, panics with a backtrace, and Bevy's own warnings) goes to stderr
+//! and to `logs/felipe.YYYY-MM-DD.log` in the data directory. A new file is
+//! started every day and the last week's are kept. `felipe --verbose` adds
+//! Felipe's debug lines: each listing with its timing, each step of a
+//! transfer, each backend call. `RUST_LOG` overrides both, as in any Bevy app.
+//! Built with `--features trace`, the file also gets the time spent in each
+//! span around the heavy work (see debug.rs) as it closes.
+//!
+//! The terminal view (tui.rs) logs to the file only, since stderr is the
+//! screen there. The browser build keeps no files; its console has the log.
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::log::tracing_subscriber::fmt::format::FmtSpan;
+use bevy::log::tracing_subscriber::{fmt, prelude::*, EnvFilter, Layer, Registry};
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use std::backtrace::Backtrace;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::data_dir;
+
+/// Daily log files kept before the oldest is deleted
+#[cfg(not(target_arch = "wasm32"))]
+const KEPT_LOG_FILES: usize = 7;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn log_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("logs"))
+}
+
+/// Bevy's log plugin, writing to the log file too
+pub fn log_plugin(verbose: bool) -> LogPlugin {
+    let default = LogPlugin::default();
+    LogPlugin {
+        filter: filter(&default.filter, verbose),
+        custom_layer: |_| file_layer().map(Layer::boxed),
+        ..default
+    }
+}
+
+/// Log to the file alone, for runs without Bevy's log plugin
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn log_to_file(verbose: bool) {
+    let Some(layer) = file_layer() else {
+        return;
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(filter("info", verbose)))
+        .expect("the default filter parses");
+    let subscriber = Registry::default().with(layer).with(filter);
+    let _ = bevy::utils::tracing::subscriber::set_global_default(subscriber);
+}
+
+/// Log panics, with where they happened, before the default hook prints them
+pub fn log_panics() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        error!("{}\n{}", info, Backtrace::force_capture());
+        default_hook(info);
+    }));
+}
+
+fn filter(base: &str, verbose: bool) -> String {
+    if verbose {
+        format!("{},felipe=debug", base)
+    } else {
+        base.to_string()
+    }
+}
+
+/// Closing spans are logged with their timings in `trace` builds
+#[cfg(not(target_arch = "wasm32"))]
+fn span_events() -> FmtSpan {
+    if cfg!(feature = "trace") {
+        FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn file_layer<S>() -> Option<impl Layer<S> + Send + Sync>
+where
+    S: bevy::utils::tracing::Subscriber
+        + for<'a> bevy::log::tracing_subscriber::registry::LookupSpan<'a>,
+{
+    let dir = log_dir()?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("felipe")
+        .filename_suffix("log")
+        .max_log_files(KEPT_LOG_FILES)
+        .build(&dir);
+    match appender {
+        Ok(appender) => Some(
+            fmt::layer()
+                .with_writer(appender)
+                .with_ansi(false)
+                .with_span_events(span_events()),
+        ),
+        Err(err) => {
+            eprintln!("felipe: no log file in {}: {}", dir.display(), err);
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn file_layer<S>() -> Option<impl Layer<S> + Send + Sync>
+where
+    S: bevy::utils::tracing::Subscriber
+        + for<'a> bevy::log::tracing_subscriber::registry::LookupSpan<'a>,
+{
+    None::<fmt::Layer<S>>
+}
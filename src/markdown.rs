@@ -0,0 +1,213 @@
+//! Markdown as styled runs of text, for the preview pane
+//!
+//! Bevy text has one font and no layout beyond line breaks, so a document
+//! becomes a list of runs, each tagged with what it is (heading, code, list
+//! marker...), and the pane picks a size and color per tag. Block structure
+//! is kept with line breaks and indentation; HTML is left out.
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// What a run of text is, which decides how it looks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Markup {
+    /// Heading level 1-6
+    Heading(u8),
+    Text,
+    Strong,
+    Emphasis,
+    /// Inline code
+    Code,
+    CodeBlock,
+    Link,
+    Quote,
+    /// List bullets and numbers, quote bars, rules, table separators
+    Marker,
+}
+
+/// Runs of `text`, stopping after about `max_lines` lines
+pub fn render(text: &str, max_lines: usize) -> Vec<(Markup, String)> {
+    let mut out = Renderer {
+        runs: Vec::new(),
+        styles: Vec::new(),
+        lists: Vec::new(),
+        quote_depth: 0,
+        lines: 0,
+    };
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(text, options) {
+        if out.lines >= max_lines {
+            out.break_line();
+            out.push(Markup::Marker, "...");
+            break;
+        }
+        out.event(event);
+    }
+    // Paragraphs leave a blank line behind them
+    while out
+        .runs
+        .last()
+        .is_some_and(|(_, text)| text.trim().is_empty())
+    {
+        out.runs.pop();
+    }
+    out.runs
+}
+
+struct Renderer {
+    runs: Vec<(Markup, String)>,
+    /// Innermost last
+    styles: Vec<Markup>,
+    /// Next number of each open list, None for bullets
+    lists: Vec<Option<u64>>,
+    quote_depth: usize,
+    lines: usize,
+}
+
+impl Renderer {
+    fn style(&self) -> Markup {
+        self.styles
+            .last()
+            .copied()
+            .unwrap_or(if self.quote_depth > 0 {
+                Markup::Quote
+            } else {
+                Markup::Text
+            })
+    }
+
+    /// Add to the last run when it has the same markup
+    fn push(&mut self, markup: Markup, text: &str) {
+        self.lines += text.matches('\n').count();
+        match self.runs.last_mut() {
+            Some((last, run)) if *last == markup => run.push_str(text),
+            _ => self.runs.push((markup, text.to_string())),
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.runs
+            .last()
+            .is_none_or(|(_, text)| text.ends_with('\n'))
+    }
+
+    fn break_line(&mut self) {
+        if !self.at_line_start() {
+            self.push(Markup::Text, "\n");
+        }
+    }
+
+    /// End a block with a blank line
+    fn end_block(&mut self) {
+        self.break_line();
+        if self.lists.is_empty() {
+            self.push(Markup::Text, "\n");
+        }
+    }
+
+    /// Quote bars at the start of a line
+    fn start_line(&mut self) {
+        if self.at_line_start() && self.quote_depth > 0 {
+            self.push(Markup::Marker, &"| ".repeat(self.quote_depth));
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) if self.style() == Markup::CodeBlock => {
+                for line in text.split_inclusive('\n') {
+                    self.start_line();
+                    self.push(Markup::CodeBlock, &format!("  {}", line));
+                }
+            }
+            Event::Text(text) => {
+                self.start_line();
+                self.push(self.style(), &text);
+            }
+            Event::Code(code) => {
+                self.start_line();
+                self.push(Markup::Code, &code);
+            }
+            Event::SoftBreak => self.push(self.style(), " "),
+            Event::HardBreak => self.push(Markup::Text, "\n"),
+            Event::Rule => {
+                self.break_line();
+                self.push(Markup::Marker, &"-".repeat(24));
+                self.end_block();
+            }
+            Event::TaskListMarker(done) => {
+                self.push(Markup::Marker, if done { "[x] " } else { "[ ] " });
+            }
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.break_line();
+                self.styles.push(Markup::Heading(level as u8));
+            }
+            Tag::BlockQuote(_) => {
+                self.break_line();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.break_line();
+                self.styles.push(Markup::CodeBlock);
+            }
+            Tag::List(first) => {
+                self.break_line();
+                self.lists.push(first);
+            }
+            Tag::Item => {
+                self.break_line();
+                self.start_line();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}{}. ", indent, *number - 1)
+                    }
+                    _ => format!("{}* ", indent),
+                };
+                self.push(Markup::Marker, &marker);
+            }
+            Tag::Emphasis | Tag::Strikethrough => self.styles.push(Markup::Emphasis),
+            Tag::Strong => self.styles.push(Markup::Strong),
+            Tag::Link { .. } | Tag::Image { .. } => self.styles.push(Markup::Link),
+            Tag::TableCell => self.start_line(),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) | TagEnd::CodeBlock => {
+                self.styles.pop();
+                self.end_block();
+            }
+            TagEnd::Paragraph | TagEnd::Table => self.end_block(),
+            TagEnd::BlockQuote(_) => {
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.end_block();
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+                self.end_block();
+            }
+            TagEnd::Item | TagEnd::TableHead | TagEnd::TableRow => self.break_line(),
+            TagEnd::TableCell => self.push(Markup::Marker, " | "),
+            TagEnd::Emphasis
+            | TagEnd::Strikethrough
+            | TagEnd::Strong
+            | TagEnd::Link
+            | TagEnd::Image => {
+                self.styles.pop();
+            }
+            _ => {}
+        }
+    }
+}
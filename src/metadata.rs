@@ -0,0 +1,967 @@
+//! Metadata store - what Felipe knows about files that the filesystem doesn't
+//!
+//! Tags (tags.rs), stars (stars.rs), notes (notes.rs), directory visits
+//! (frecency.rs), measured directory sizes, saved searches (searches.rs), the
+//! log of file operations (oplog.rs) and the journal of transfers that are
+//! running (recovery.rs) live in one SQLite database,
+//! `metadata.sqlite` in the data directory. Everything about single files
+//! hangs off a row of `entries`, keyed by path with the device and inode kept
+//! alongside: a path keeps its metadata when an editor saves it as a new file.
+//! Moves and renames Felipe makes take the metadata along (`moved`); a file
+//! moved or renamed outside Felipe gets it back once the directory it went to
+//! is listed, found by its inode, if its size and modification time are still
+//! the ones noted - an inode alone may have been handed to another file.
+//!
+//! The schema version is SQLite's `user_version`; opening the database runs
+//! the `MIGRATIONS` it hasn't had yet, each in one transaction. The first one
+//! also takes in the `.tsv` files earlier versions kept, and renames them to
+//! `.tsv.imported`.
+
+use bevy::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::oplog::OperationGroup;
+use crate::ops::{TransferPlan, UndoStep};
+use crate::tags::TagColor;
+use crate::{data_dir, FileEntry};
+
+/// Schema changes, oldest first; version N has run the first N. Append only:
+/// a database that has run one must never see it change
+const MIGRATIONS: &[fn(&Transaction) -> rusqlite::Result<()>] = &[
+    create_schema,
+    create_searches,
+    create_operations,
+    create_journal,
+    create_journal_steps,
+    add_entry_stamps,
+];
+
+/// Operations the log keeps; older ones are dropped as new ones come
+const LOGGED_OPERATIONS: i64 = 10_000;
+
+/// Tags, star and note of one path; paths without any aren't kept
+#[derive(Clone, Default)]
+struct Marks {
+    tags: Vec<TagColor>,
+    starred: bool,
+    note: Option<String>,
+}
+
+impl Marks {
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && !self.starred && self.note.is_none()
+    }
+}
+
+struct Store {
+    connection: Connection,
+    /// Marks by path, so listings and the scene don't query for every entry
+    marks: HashMap<PathBuf, Marks>,
+    /// Marked paths that were gone at startup, by device and inode, waiting
+    /// to turn up somewhere else; with their size and modification time
+    missing: HashMap<(u64, u64), (PathBuf, Option<Stamp>)>,
+}
+
+/// Opened on first use; None if the database can't be opened
+static STORE: LazyLock<Mutex<Option<Store>>> = LazyLock::new(|| {
+    Mutex::new(Store::open().map_or_else(
+        |err| {
+            warn!("Cannot open the metadata store: {}", err);
+            None
+        },
+        Some,
+    ))
+});
+
+fn store() -> MutexGuard<'static, Option<Store>> {
+    STORE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Run `change` on the store, as an io error if there's none or it fails
+fn with_store<T>(change: impl FnOnce(&mut Store) -> rusqlite::Result<T>) -> io::Result<T> {
+    let mut store = store();
+    let store = store
+        .as_mut()
+        .ok_or_else(|| io::Error::other("the metadata store couldn't be opened"))?;
+    change(store).map_err(io::Error::other)
+}
+
+fn database_file() -> io::Result<PathBuf> {
+    Ok(data_dir()
+        .ok_or_else(|| io::Error::other("no data directory on this system"))?
+        .join("metadata.sqlite"))
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Device and inode of `path` itself, where the platform has them
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Size and modification time (nanoseconds) of a file, which tell whether an
+/// inode still holds the file it was noted for
+type Stamp = (i64, i64);
+
+fn file_stamp(path: &Path) -> Option<Stamp> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len() as i64, modified.as_nanos() as i64))
+}
+
+// =============================================================================
+// Reading
+// =============================================================================
+
+/// Tags of `path`, in the order they were added
+pub fn tags(path: &Path) -> Vec<TagColor> {
+    marks(path).map(|marks| marks.tags).unwrap_or_default()
+}
+
+pub fn is_starred(path: &Path) -> bool {
+    marks(path).is_some_and(|marks| marks.starred)
+}
+
+pub fn note(path: &Path) -> Option<String> {
+    marks(path).and_then(|marks| marks.note)
+}
+
+fn marks(path: &Path) -> Option<Marks> {
+    store().as_ref()?.marks.get(path).cloned()
+}
+
+/// Saved searches as (name, directory searched, query), by name
+pub fn searches() -> Vec<(String, PathBuf, String)> {
+    let store = store();
+    let Some(store) = store.as_ref() else {
+        return Vec::new();
+    };
+    let searches = store
+        .connection
+        .prepare_cached("SELECT name, root, query FROM searches ORDER BY name")
+        .and_then(|mut statement| {
+            statement
+                .query_map([], |row| {
+                    Ok((
+                        row.get(0)?,
+                        PathBuf::from(row.get::<_, String>(1)?),
+                        row.get(2)?,
+                    ))
+                })?
+                .collect()
+        });
+    searches.unwrap_or_else(|err| {
+        warn!("Cannot read the saved searches: {}", err);
+        Vec::new()
+    })
+}
+
+/// The latest `limit` logged operations, oldest first
+pub fn operations(limit: usize) -> io::Result<Vec<OperationGroup>> {
+    with_store(|store| {
+        let mut statement = store.connection.prepare(
+            "SELECT id, at, user, label, steps, failures, undone FROM operations
+             ORDER BY id DESC LIMIT ?1",
+        )?;
+        let mut groups = statement
+            .query_map([limit as i64], |row| {
+                Ok(OperationGroup {
+                    id: Some(row.get(0)?),
+                    at: UNIX_EPOCH + Duration::from_secs(row.get::<_, i64>(1)?.max(0) as u64),
+                    user: row.get(2)?,
+                    label: row.get(3)?,
+                    // A step this version doesn't know leaves the list empty
+                    steps: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+                    failures: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                    undone: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        groups.reverse();
+        Ok(groups)
+    })
+}
+
+/// Transfers started and not finished, oldest first: what a crash or a kill
+/// interrupted, with the steps noted whole (see `note_whole_step`)
+pub fn unfinished_transfers() -> io::Result<Vec<(i64, String, TransferPlan, HashSet<usize>)>> {
+    with_store(|store| {
+        let mut statement = store
+            .connection
+            .prepare("SELECT id, label, plan FROM journal ORDER BY id")?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut statement = store
+            .connection
+            .prepare("SELECT step FROM journal_steps WHERE transfer = ?1")?;
+        let mut transfers = Vec::new();
+        for (id, label, plan) in rows {
+            // A plan this version can't read is left for the version that wrote it
+            let Ok(plan) = serde_json::from_str(&plan) else {
+                continue;
+            };
+            let whole = statement
+                .query_map([id], |row| Ok(row.get::<_, i64>(0)? as usize))?
+                .collect::<rusqlite::Result<HashSet<_>>>()?;
+            transfers.push((id, label, plan, whole));
+        }
+        Ok(transfers)
+    })
+}
+
+/// Every starred path, gone or not
+pub fn starred() -> Vec<PathBuf> {
+    let store = store();
+    let Some(store) = store.as_ref() else {
+        return Vec::new();
+    };
+    store
+        .marks
+        .iter()
+        .filter(|(_, marks)| marks.starred)
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Directory visits as (path, count, last visit in seconds since the epoch)
+pub fn visits() -> io::Result<Vec<(PathBuf, u64, u64)>> {
+    with_store(|store| {
+        let mut statement = store.connection.prepare(
+            "SELECT entries.path, visits.count, visits.last
+             FROM visits JOIN entries ON entries.id = visits.entry",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                PathBuf::from(row.get::<_, String>(0)?),
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, i64>(2)? as u64,
+            ))
+        })?;
+        rows.collect()
+    })
+}
+
+/// Size of the directory at `path` and when it was measured, if it was
+pub fn dir_size(path: &Path) -> Option<(u64, SystemTime)> {
+    let store = store();
+    let store = store.as_ref()?;
+    let (size, measured): (i64, i64) = store
+        .connection
+        .query_row(
+            "SELECT dir_sizes.size, dir_sizes.measured
+             FROM dir_sizes JOIN entries ON entries.id = dir_sizes.entry
+             WHERE entries.path = ?1",
+            [path.to_string_lossy()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .ok()?;
+    Some((
+        size as u64,
+        UNIX_EPOCH + std::time::Duration::from_secs(measured as u64),
+    ))
+}
+
+// =============================================================================
+// Writing
+// =============================================================================
+
+pub fn set_tags(path: &Path, tags: &[TagColor]) -> io::Result<()> {
+    with_store(|store| {
+        let transaction = store.connection.transaction()?;
+        let entry = entry_id(&transaction, path)?;
+        transaction.execute("DELETE FROM tags WHERE entry = ?1", [entry])?;
+        for (position, color) in tags.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO tags (entry, color, position) VALUES (?1, ?2, ?3)",
+                params![entry, color.name(), position as i64],
+            )?;
+        }
+        forget_if_unused(&transaction, entry)?;
+        transaction.commit()?;
+        store.update_marks(path, |marks| marks.tags = tags.to_vec());
+        Ok(())
+    })
+}
+
+pub fn set_starred(path: &Path, starred: bool) -> io::Result<()> {
+    with_store(|store| {
+        let transaction = store.connection.transaction()?;
+        let entry = entry_id(&transaction, path)?;
+        if starred {
+            transaction.execute(
+                "INSERT OR IGNORE INTO stars (entry, added) VALUES (?1, ?2)",
+                params![entry, now_secs()],
+            )?;
+        } else {
+            transaction.execute("DELETE FROM stars WHERE entry = ?1", [entry])?;
+        }
+        forget_if_unused(&transaction, entry)?;
+        transaction.commit()?;
+        store.update_marks(path, |marks| marks.starred = starred);
+        Ok(())
+    })
+}
+
+pub fn set_note(path: &Path, note: Option<&str>) -> io::Result<()> {
+    with_store(|store| {
+        let transaction = store.connection.transaction()?;
+        let entry = entry_id(&transaction, path)?;
+        match note {
+            Some(text) => transaction.execute(
+                "INSERT OR REPLACE INTO notes (entry, text) VALUES (?1, ?2)",
+                params![entry, text],
+            )?,
+            None => transaction.execute("DELETE FROM notes WHERE entry = ?1", [entry])?,
+        };
+        forget_if_unused(&transaction, entry)?;
+        transaction.commit()?;
+        store.update_marks(path, |marks| marks.note = note.map(str::to_string));
+        Ok(())
+    })
+}
+
+/// Save `query` over `root` as `name`, replacing a search of that name
+pub fn save_search(name: &str, root: &Path, query: &str) -> io::Result<()> {
+    with_store(|store| {
+        store.connection.execute(
+            "INSERT OR REPLACE INTO searches (name, root, query) VALUES (?1, ?2, ?3)",
+            params![name, root.to_string_lossy(), query],
+        )?;
+        Ok(())
+    })
+}
+
+/// Forget the search saved as `name`; whether there was one
+pub fn delete_search(name: &str) -> io::Result<bool> {
+    with_store(|store| {
+        let deleted = store
+            .connection
+            .execute("DELETE FROM searches WHERE name = ?1", [name])?;
+        Ok(deleted > 0)
+    })
+}
+
+/// Add `group` to the log; its row, to mark it undone later
+pub fn log_operation(group: &OperationGroup) -> io::Result<i64> {
+    let steps = serde_json::to_string(&group.steps)?;
+    let failures = serde_json::to_string(&group.failures)?;
+    let at = group
+        .at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    with_store(|store| {
+        store.connection.execute(
+            "INSERT INTO operations (at, user, label, steps, failures, undone)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![at, group.user, group.label, steps, failures, group.undone],
+        )?;
+        let id = store.connection.last_insert_rowid();
+        store.connection.execute(
+            "DELETE FROM operations WHERE id <= ?1",
+            [id - LOGGED_OPERATIONS],
+        )?;
+        Ok(id)
+    })
+}
+
+pub fn set_undone(id: i64) -> io::Result<()> {
+    with_store(|store| {
+        store
+            .connection
+            .execute("UPDATE operations SET undone = 1 WHERE id = ?1", [id])?;
+        Ok(())
+    })
+}
+
+/// Replace the steps of the group at row `id`, e.g. with those still to undo
+pub fn set_steps(id: i64, steps: &[UndoStep]) -> io::Result<()> {
+    let steps = serde_json::to_string(steps)?;
+    with_store(|store| {
+        store.connection.execute(
+            "UPDATE operations SET steps = ?2 WHERE id = ?1",
+            params![id, steps],
+        )?;
+        Ok(())
+    })
+}
+
+/// Note a transfer as started, before its first step; the id ends it
+pub fn begin_transfer(label: &str, plan: &TransferPlan) -> io::Result<i64> {
+    let plan = serde_json::to_string(plan)?;
+    with_store(|store| {
+        store.connection.execute(
+            "INSERT INTO journal (started, label, plan) VALUES (?1, ?2, ?3)",
+            params![now_secs(), label, plan],
+        )?;
+        Ok(store.connection.last_insert_rowid())
+    })
+}
+
+/// Step `step` of transfer `id` has a complete target; a move's source may
+/// still be there
+pub fn note_whole_step(id: i64, step: usize) -> io::Result<()> {
+    with_store(|store| {
+        store.connection.execute(
+            "INSERT OR IGNORE INTO journal_steps (transfer, step) VALUES (?1, ?2)",
+            params![id, step as i64],
+        )?;
+        Ok(())
+    })
+}
+
+/// The transfer is over, whatever came of it
+pub fn end_transfer(id: i64) -> io::Result<()> {
+    with_store(|store| {
+        store
+            .connection
+            .execute("DELETE FROM journal WHERE id = ?1", [id])?;
+        Ok(())
+    })
+}
+
+/// Replace all directory visits with `visits`, as (path, count, last visit)
+pub fn save_visits<'a>(visits: impl Iterator<Item = (&'a Path, u64, u64)>) -> io::Result<()> {
+    with_store(|store| {
+        let transaction = store.connection.transaction()?;
+        transaction.execute("DELETE FROM visits", [])?;
+        for (path, count, last) in visits {
+            let entry = entry_id(&transaction, path)?;
+            transaction.execute(
+                "INSERT INTO visits (entry, count, last) VALUES (?1, ?2, ?3)",
+                params![entry, count as i64, last as i64],
+            )?;
+        }
+        transaction.execute(
+            "DELETE FROM entries WHERE id NOT IN (SELECT entry FROM tags)
+                 AND id NOT IN (SELECT entry FROM stars)
+                 AND id NOT IN (SELECT entry FROM notes)
+                 AND id NOT IN (SELECT entry FROM visits)
+                 AND id NOT IN (SELECT entry FROM dir_sizes)",
+            [],
+        )?;
+        transaction.commit()
+    })
+}
+
+/// Remember that the directory at `path` holds `size` bytes
+pub fn cache_dir_size(path: &Path, size: u64) -> io::Result<()> {
+    with_store(|store| {
+        let transaction = store.connection.transaction()?;
+        let entry = entry_id(&transaction, path)?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO dir_sizes (entry, size, measured) VALUES (?1, ?2, ?3)",
+            params![entry, size as i64, now_secs()],
+        )?;
+        transaction.commit()
+    })
+}
+
+/// Felipe moved or renamed `from` to `to`: its metadata, and that of
+/// everything under it, goes along
+pub fn moved(from: &Path, to: &Path) -> io::Result<()> {
+    with_store(|store| {
+        let transaction = store.connection.transaction()?;
+        move_rows(&transaction, from, to)?;
+        transaction.commit()?;
+        let marks = std::mem::take(&mut store.marks);
+        store.marks = marks
+            .into_iter()
+            .filter(|(path, _)| !path.starts_with(to))
+            .map(|(path, marks)| match path.strip_prefix(from) {
+                Ok(rest) if rest.as_os_str().is_empty() => (to.to_path_buf(), marks),
+                Ok(rest) => (to.join(rest), marks),
+                Err(_) => (path, marks),
+            })
+            .collect();
+        Ok(())
+    })
+}
+
+/// Give marked files that went missing their marks back if they're among
+/// `entries` under a new name
+pub fn relink(entries: &[FileEntry]) {
+    let mut store = store();
+    let Some(store) = store.as_mut() else {
+        return;
+    };
+    if store.missing.is_empty() {
+        return;
+    }
+    for entry in entries {
+        let Some(id) = file_id(&entry.path) else {
+            continue;
+        };
+        let Some((old, stamp)) = store.missing.remove(&id) else {
+            continue;
+        };
+        if stamp.is_none() || stamp != file_stamp(&entry.path) {
+            continue;
+        }
+        // The new path may have marks of its own already; those stay
+        if store.marks.contains_key(&entry.path) {
+            continue;
+        }
+        let moved = store.connection.execute(
+            "UPDATE entries SET path = ?1 WHERE path = ?2",
+            params![entry.path.to_string_lossy(), old.to_string_lossy()],
+        );
+        match moved {
+            Ok(_) => {
+                if let Some(marks) = store.marks.remove(&old) {
+                    store.marks.insert(entry.path.clone(), marks);
+                }
+            }
+            Err(err) => warn!("Cannot move the metadata of {}: {}", old.display(), err),
+        }
+    }
+}
+
+/// Row of `path` in `entries`, added if it has none
+fn entry_id(connection: &Connection, path: &Path) -> rusqlite::Result<i64> {
+    let name = path.to_string_lossy();
+    let id = file_id(path);
+    let stamp = file_stamp(path);
+    let existing: Option<i64> = connection
+        .query_row("SELECT id FROM entries WHERE path = ?1", [&name], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    if let Some(entry) = existing {
+        // Saving a file often replaces it; the path keeps its metadata
+        if id.is_some() {
+            note_file(connection, path, id, stamp)?;
+        }
+        return Ok(entry);
+    }
+    connection.execute(
+        "INSERT INTO entries (path, device, inode, size, modified)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            name,
+            id.map(|(device, _)| device as i64),
+            id.map(|(_, inode)| inode as i64),
+            stamp.map(|(size, _)| size),
+            stamp.map(|(_, modified)| modified)
+        ],
+    )?;
+    Ok(connection.last_insert_rowid())
+}
+
+/// Note which file is at `path` now
+fn note_file(
+    connection: &Connection,
+    path: &Path,
+    id: Option<(u64, u64)>,
+    stamp: Option<Stamp>,
+) -> rusqlite::Result<()> {
+    connection.execute(
+        "UPDATE entries SET device = ?2, inode = ?3, size = ?4, modified = ?5
+         WHERE path = ?1",
+        params![
+            path.to_string_lossy(),
+            id.map(|(device, _)| device as i64),
+            id.map(|(_, inode)| inode as i64),
+            stamp.map(|(size, _)| size),
+            stamp.map(|(_, modified)| modified)
+        ],
+    )?;
+    Ok(())
+}
+
+/// Point the rows of `from` and of everything under it at `to`; rows already
+/// at `to` or under it were for entries that aren't there anymore
+fn move_rows(connection: &Connection, from: &Path, to: &Path) -> rusqlite::Result<()> {
+    let below = |path: &Path| format!("{}{}", path.to_string_lossy(), MAIN_SEPARATOR);
+    connection.execute(
+        "DELETE FROM entries WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2",
+        params![to.to_string_lossy(), below(to)],
+    )?;
+    connection.execute(
+        "UPDATE entries SET path = ?3 || substr(path, length(?1) + 1)
+         WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2",
+        params![from.to_string_lossy(), below(from), to.to_string_lossy()],
+    )?;
+    Ok(())
+}
+
+/// Drop the row of an entry nothing refers to anymore
+fn forget_if_unused(connection: &Connection, entry: i64) -> rusqlite::Result<()> {
+    connection.execute(
+        "DELETE FROM entries WHERE id = ?1
+             AND NOT EXISTS (SELECT 1 FROM tags WHERE entry = ?1)
+             AND NOT EXISTS (SELECT 1 FROM stars WHERE entry = ?1)
+             AND NOT EXISTS (SELECT 1 FROM notes WHERE entry = ?1)
+             AND NOT EXISTS (SELECT 1 FROM visits WHERE entry = ?1)
+             AND NOT EXISTS (SELECT 1 FROM dir_sizes WHERE entry = ?1)",
+        [entry],
+    )?;
+    Ok(())
+}
+
+// =============================================================================
+// Opening
+// =============================================================================
+
+impl Store {
+    fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = database_file()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut connection = Connection::open(&path)?;
+        connection.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")?;
+        let imported = migrate(&mut connection)?;
+        // Only once the import is committed, or a failed one would lose them
+        for legacy in imported {
+            let mut done = legacy.clone().into_os_string();
+            done.push(".imported");
+            if let Err(err) = std::fs::rename(&legacy, &done) {
+                warn!("Cannot rename {}: {}", legacy.display(), err);
+            }
+        }
+
+        let mut store = Store {
+            connection,
+            marks: HashMap::new(),
+            missing: HashMap::new(),
+        };
+        store.load_marks()?;
+        Ok(store)
+    }
+
+    fn load_marks(&mut self) -> rusqlite::Result<()> {
+        let mut marks: HashMap<PathBuf, Marks> = HashMap::new();
+        // Device, inode, size and modification time as noted
+        let mut noted: HashMap<PathBuf, [Option<i64>; 4]> = HashMap::new();
+        let note = |row: &rusqlite::Row| -> rusqlite::Result<[Option<i64>; 4]> {
+            Ok([row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?])
+        };
+        let mut statement = self.connection.prepare(
+            "SELECT entries.path, entries.device, entries.inode, entries.size,
+                    entries.modified, tags.color
+             FROM tags JOIN entries ON entries.id = tags.entry
+             ORDER BY tags.entry, tags.position",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let path = PathBuf::from(row.get::<_, String>(0)?);
+            noted.insert(path.clone(), note(row)?);
+            if let Ok(color) = row.get::<_, String>(5)?.parse() {
+                marks.entry(path).or_default().tags.push(color);
+            }
+        }
+        let mut statement = self.connection.prepare(
+            "SELECT entries.path, entries.device, entries.inode, entries.size,
+                    entries.modified
+             FROM stars JOIN entries ON entries.id = stars.entry",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let path = PathBuf::from(row.get::<_, String>(0)?);
+            noted.insert(path.clone(), note(row)?);
+            marks.entry(path).or_default().starred = true;
+        }
+        let mut statement = self.connection.prepare(
+            "SELECT entries.path, entries.device, entries.inode, entries.size,
+                    entries.modified, notes.text
+             FROM notes JOIN entries ON entries.id = notes.entry",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let path = PathBuf::from(row.get::<_, String>(0)?);
+            noted.insert(path.clone(), note(row)?);
+            marks.entry(path).or_default().note = Some(row.get(5)?);
+        }
+
+        for (path, [device, inode, size, modified]) in noted {
+            let stamp = size.zip(modified);
+            if std::fs::symlink_metadata(&path).is_err() {
+                if let (Some(device), Some(inode)) = (device, inode) {
+                    self.missing
+                        .insert((device as u64, inode as u64), (path, stamp));
+                }
+                continue;
+            }
+            // Edited since, or noted before stamps were: note it as it is
+            let (id, now) = (file_id(&path), file_stamp(&path));
+            let then = device.zip(inode).map(|(d, i)| (d as u64, i as u64));
+            if id.is_some() && (id != then || now != stamp) {
+                note_file(&self.connection, &path, id, now)?;
+            }
+        }
+        self.marks = marks;
+        Ok(())
+    }
+
+    fn update_marks(&mut self, path: &Path, change: impl FnOnce(&mut Marks)) {
+        let marks = self.marks.entry(path.to_path_buf()).or_default();
+        change(marks);
+        if marks.is_empty() {
+            self.marks.remove(path);
+        }
+    }
+}
+
+/// Bring the schema up to date; the legacy files the first migration took in
+fn migrate(connection: &mut Connection) -> rusqlite::Result<Vec<PathBuf>> {
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        warn!(
+            "The metadata store is from a newer Felipe (schema {}, this one knows {})",
+            version,
+            MIGRATIONS.len()
+        );
+    }
+    let mut imported = Vec::new();
+    for (done, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        migration(&transaction)?;
+        if done == 0 {
+            imported = import_legacy(&transaction)?;
+        }
+        transaction.pragma_update(None, "user_version", done + 1)?;
+        transaction.commit()?;
+    }
+    Ok(imported)
+}
+
+/// Version 1
+fn create_schema(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE entries (
+             id INTEGER PRIMARY KEY,
+             path TEXT NOT NULL UNIQUE,
+             device INTEGER,
+             inode INTEGER
+         );
+         CREATE INDEX entries_by_file ON entries (device, inode);
+         CREATE TABLE tags (
+             entry INTEGER NOT NULL REFERENCES entries (id) ON DELETE CASCADE,
+             color TEXT NOT NULL,
+             position INTEGER NOT NULL,
+             PRIMARY KEY (entry, color)
+         );
+         CREATE TABLE stars (
+             entry INTEGER PRIMARY KEY REFERENCES entries (id) ON DELETE CASCADE,
+             added INTEGER NOT NULL
+         );
+         CREATE TABLE notes (
+             entry INTEGER PRIMARY KEY REFERENCES entries (id) ON DELETE CASCADE,
+             text TEXT NOT NULL
+         );
+         CREATE TABLE visits (
+             entry INTEGER PRIMARY KEY REFERENCES entries (id) ON DELETE CASCADE,
+             count INTEGER NOT NULL,
+             last INTEGER NOT NULL
+         );
+         CREATE TABLE dir_sizes (
+             entry INTEGER PRIMARY KEY REFERENCES entries (id) ON DELETE CASCADE,
+             size INTEGER NOT NULL,
+             measured INTEGER NOT NULL
+         );",
+    )
+}
+
+/// Version 2; searches are about a directory tree, not one entry, so they
+/// don't hang off `entries`
+fn create_searches(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE searches (
+             name TEXT PRIMARY KEY,
+             root TEXT NOT NULL,
+             query TEXT NOT NULL
+         );",
+    )
+}
+
+/// Version 3; steps and failures are JSON, as oplog.rs has them
+fn create_operations(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE operations (
+             id INTEGER PRIMARY KEY,
+             at INTEGER NOT NULL,
+             user TEXT NOT NULL,
+             label TEXT NOT NULL,
+             steps TEXT NOT NULL,
+             failures TEXT NOT NULL,
+             undone INTEGER NOT NULL
+         );",
+    )
+}
+
+fn create_journal(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE journal (
+             id INTEGER PRIMARY KEY,
+             started INTEGER NOT NULL,
+             label TEXT NOT NULL,
+             plan TEXT NOT NULL
+         );",
+    )
+}
+
+/// Version 5; the steps of a journaled transfer whose target is complete
+fn create_journal_steps(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "CREATE TABLE journal_steps (
+             transfer INTEGER NOT NULL REFERENCES journal (id) ON DELETE CASCADE,
+             step INTEGER NOT NULL,
+             PRIMARY KEY (transfer, step)
+         );",
+    )
+}
+
+/// Version 6; the size and modification time of the file an entry's inode
+/// was noted for, so relinking by inode can't pick up another file
+fn add_entry_stamps(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(
+        "ALTER TABLE entries ADD COLUMN size INTEGER;
+         ALTER TABLE entries ADD COLUMN modified INTEGER;",
+    )
+}
+
+// =============================================================================
+// Legacy files
+// =============================================================================
+
+/// Take in tags.tsv, stars.tsv, notes.tsv and frecency.tsv; the files that
+/// were there
+fn import_legacy(transaction: &Transaction) -> rusqlite::Result<Vec<PathBuf>> {
+    let Some(dir) = data_dir() else {
+        return Ok(Vec::new());
+    };
+    let mut imported = Vec::new();
+
+    if let Some(lines) = legacy_lines(&dir, "tags.tsv", "felipe-tags-1", &mut imported) {
+        for line in lines {
+            let Some((colors, name)) = line.split_once('\t') else {
+                continue;
+            };
+            let entry = entry_id(transaction, Path::new(name))?;
+            let colors = colors
+                .split(',')
+                .filter_map(|color| color.parse::<TagColor>().ok());
+            for (position, color) in colors.enumerate() {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO tags (entry, color, position) VALUES (?1, ?2, ?3)",
+                    params![entry, color.name(), position as i64],
+                )?;
+            }
+        }
+    }
+    if let Some(lines) = legacy_lines(&dir, "stars.tsv", "felipe-stars-1", &mut imported) {
+        for name in lines.iter().filter(|line| !line.is_empty()) {
+            let entry = entry_id(transaction, Path::new(name))?;
+            transaction.execute(
+                "INSERT OR IGNORE INTO stars (entry, added) VALUES (?1, ?2)",
+                params![entry, now_secs()],
+            )?;
+        }
+    }
+    if let Some(lines) = legacy_lines(&dir, "notes.tsv", "felipe-notes-1", &mut imported) {
+        for line in lines {
+            let Some((note, name)) = line.split_once('\t') else {
+                continue;
+            };
+            let entry = entry_id(transaction, Path::new(name))?;
+            transaction.execute(
+                "INSERT OR REPLACE INTO notes (entry, text) VALUES (?1, ?2)",
+                params![entry, note],
+            )?;
+        }
+    }
+    if let Some(lines) = legacy_lines(&dir, "frecency.tsv", "felipe-frecency-1", &mut imported) {
+        for line in lines {
+            let fields: Vec<&str> = line.splitn(3, '\t').collect();
+            let [count, last, name] = fields.as_slice() else {
+                continue;
+            };
+            let (Ok(count), Ok(last)) = (count.parse::<i64>(), last.parse::<i64>()) else {
+                continue;
+            };
+            let entry = entry_id(transaction, Path::new(name))?;
+            transaction.execute(
+                "INSERT OR REPLACE INTO visits (entry, count, last) VALUES (?1, ?2, ?3)",
+                params![entry, count, last],
+            )?;
+        }
+    }
+    Ok(imported)
+}
+
+/// Lines after the header of a legacy file, None if it's missing or not one
+/// of ours
+fn legacy_lines(
+    dir: &Path,
+    name: &str,
+    header: &str,
+    imported: &mut Vec<PathBuf>,
+) -> Option<Vec<String>> {
+    let path = dir.join(name);
+    let file = std::fs::File::open(&path).ok()?;
+    let mut lines = BufReader::new(file).lines().map_while(Result::ok);
+    if lines.next().as_deref() != Some(header) {
+        warn!("Not importing {}: unrecognized format", path.display());
+        return None;
+    }
+    let lines = lines.collect();
+    imported.push(path);
+    Some(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(connection: &Connection) -> Vec<String> {
+        let mut statement = connection
+            .prepare("SELECT path FROM entries ORDER BY path")
+            .unwrap();
+        let rows = statement.query_map([], |row| row.get(0)).unwrap();
+        rows.map(|row| row.unwrap()).collect()
+    }
+
+    #[test]
+    fn move_rows_takes_everything_under_a_directory_along() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        let transaction = connection.transaction().unwrap();
+        create_schema(&transaction).unwrap();
+        add_entry_stamps(&transaction).unwrap();
+        transaction.commit().unwrap();
+        let (a, ab, b) = (Path::new("a"), Path::new("ab"), Path::new("b"));
+        for entry in [a, &a.join("x.txt"), ab, b, &b.join("old.txt")] {
+            entry_id(&connection, entry).unwrap();
+        }
+
+        move_rows(&connection, a, b).unwrap();
+        let moved = b.join("x.txt").to_string_lossy().to_string();
+        assert_eq!(paths(&connection), ["ab", "b", moved.as_str()]);
+    }
+}
@@ -0,0 +1,126 @@
+//! Metadata store in the browser build - there is none
+//!
+//! The store (metadata.rs) is an SQLite database in the data directory, and
+//! neither builds for wasm32: SQLite is C and the browser has no data
+//! directory. This stands in with the same functions, so the modules using
+//! the store build unchanged. Nothing is remembered: reads find nothing, and
+//! tagging, starring and the like say why they didn't happen.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::oplog::OperationGroup;
+use crate::ops::{TransferPlan, UndoStep};
+use crate::tags::TagColor;
+use crate::FileEntry;
+
+fn missing() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "nothing is stored in the browser",
+    )
+}
+
+// =============================================================================
+// Reading
+// =============================================================================
+
+pub fn tags(_path: &Path) -> Vec<TagColor> {
+    Vec::new()
+}
+
+pub fn is_starred(_path: &Path) -> bool {
+    false
+}
+
+pub fn note(_path: &Path) -> Option<String> {
+    None
+}
+
+pub fn searches() -> Vec<(String, PathBuf, String)> {
+    Vec::new()
+}
+
+pub fn operations(_limit: usize) -> io::Result<Vec<OperationGroup>> {
+    Ok(Vec::new())
+}
+
+pub fn unfinished_transfers() -> io::Result<Vec<(i64, String, TransferPlan, HashSet<usize>)>> {
+    Ok(Vec::new())
+}
+
+pub fn starred() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+pub fn visits() -> io::Result<Vec<(PathBuf, u64, u64)>> {
+    Ok(Vec::new())
+}
+
+pub fn dir_size(_path: &Path) -> Option<(u64, SystemTime)> {
+    None
+}
+
+// =============================================================================
+// Writing
+// =============================================================================
+
+pub fn set_tags(_path: &Path, _tags: &[TagColor]) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn set_starred(_path: &Path, _starred: bool) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn set_note(_path: &Path, _note: Option<&str>) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn save_search(_name: &str, _root: &Path, _query: &str) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn delete_search(_name: &str) -> io::Result<bool> {
+    Ok(false)
+}
+
+pub fn log_operation(_group: &OperationGroup) -> io::Result<i64> {
+    Err(missing())
+}
+
+pub fn set_undone(_id: i64) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn set_steps(_id: i64, _steps: &[UndoStep]) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn begin_transfer(_label: &str, _plan: &TransferPlan) -> io::Result<i64> {
+    Err(missing())
+}
+
+pub fn note_whole_step(_id: i64, _step: usize) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn end_transfer(_id: i64) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn save_visits<'a>(_visits: impl Iterator<Item = (&'a Path, u64, u64)>) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn cache_dir_size(_path: &Path, _size: u64) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn moved(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(missing())
+}
+
+pub fn relink(_entries: &[FileEntry]) {}
@@ -0,0 +1,101 @@
+//! Content types - what a file is, judged by its first bytes
+//!
+//! Extensions can be missing or wrong, so files are sniffed for the magic
+//! numbers of known formats. The result picks the entry's shape when the
+//! config doesn't name one for the extension (a PNG saved as `scan` is still a
+//! slab, a zip renamed to `.png` a stack), and the info bar shows it for the
+//! selected file.
+//!
+//! Sniffing reads a few KB per file, on a worker thread whenever the listing
+//! reloads; directories beyond `MAX_SNIFFED` files are only partly sniffed.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use crate::CurrentDirectory;
+
+/// Files sniffed per listing, in listing order
+const MAX_SNIFFED: usize = 5_000;
+
+/// Detected content type of the listed files, by path
+#[derive(Resource, Default)]
+pub struct MimeTypes {
+    types: HashMap<PathBuf, &'static str>,
+    worker: Option<JoinHandle<HashMap<PathBuf, &'static str>>>,
+    /// A reload happened while the worker was busy
+    stale: bool,
+}
+
+impl MimeTypes {
+    /// MIME type of `path`, if it was sniffed and recognized
+    pub fn get(&self, path: &Path) -> Option<&'static str> {
+        self.types.get(path).copied()
+    }
+}
+
+pub struct MimePlugin;
+
+impl Plugin for MimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MimeTypes>().add_systems(
+            Update,
+            (
+                mark_mime_types_stale.before(crate::load_directory),
+                (start_sniffing, finish_sniffing).after(crate::load_directory),
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn mark_mime_types_stale(current_dir: Res<CurrentDirectory>, mut mimes: ResMut<MimeTypes>) {
+    if current_dir.needs_reload {
+        mimes.stale = true;
+    }
+}
+
+fn start_sniffing(current_dir: Res<CurrentDirectory>, mut mimes: ResMut<MimeTypes>) {
+    if !mimes.stale || mimes.worker.is_some() || current_dir.needs_reload {
+        return;
+    }
+    mimes.stale = false;
+    let files: Vec<PathBuf> = current_dir
+        .entries
+        .iter()
+        .filter(|entry| !entry.is_dir && entry.link.as_ref().is_none_or(|l| !l.is_broken()))
+        .take(MAX_SNIFFED)
+        .map(|entry| entry.path.clone())
+        .collect();
+    mimes.worker = Some(std::thread::spawn(move || sniff(&files)));
+}
+
+fn finish_sniffing(mut mimes: ResMut<MimeTypes>) {
+    if !mimes
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        return;
+    }
+    let worker = mimes.worker.take().expect("checked above");
+    mimes.types = worker.join().unwrap_or_default();
+}
+
+// =============================================================================
+// Sniffing
+// =============================================================================
+
+fn sniff(files: &[PathBuf]) -> HashMap<PathBuf, &'static str> {
+    files
+        .iter()
+        .filter_map(|path| {
+            let kind = infer::get_from_path(path).ok()??;
+            Some((path.clone(), kind.mime_type()))
+        })
+        .collect()
+}
@@ -0,0 +1,329 @@
+//! Minimap - the whole directory from above, in a corner
+//!
+//! Every entry is a dot where the active layout puts it, directories darker
+//! than files, the selection bright; the outline is what the camera sees of
+//! the floor. It is drawn the way the default view looks, far rows at the
+//! top. Clicking a dot selects that entry and flies the camera there.
+//!
+//! On by default; `minimap = false` under `[render]` or `:set minimap` turns
+//! it off.
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::ui::RelativeCursorPosition;
+
+use crate::config::Config;
+use crate::layout::Layouts;
+use crate::{
+    update_camera_target, CameraState, CurrentDirectory, MainCamera, UiElement, FELIPE_GRID,
+    FELIPE_ORANGE, FELIPE_ORANGE_DIM, ITEM_SPACING,
+};
+
+/// Width and height of the map in pixels
+const MAP_SIZE: u32 = 160;
+
+/// How far down the floor the camera outline goes when the view looks above
+/// the horizon
+const FAR_FLOOR: f32 = 300.0;
+
+/// The map image
+#[derive(Resource, Default)]
+struct Minimap {
+    image: Handle<Image>,
+}
+
+/// Marker for the map's UI node
+#[derive(Component)]
+struct MinimapNode;
+
+/// Floor area the map covers: its middle and the length of a side
+#[derive(Clone, Copy, Default)]
+struct MapArea {
+    middle: Vec2,
+    side: f32,
+}
+
+impl MapArea {
+    /// Around `points`, square, with a little room at the edges
+    fn around(points: &[Vec2]) -> Self {
+        let (min, max) = points.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), p| {
+            (min.min(*p), max.max(*p))
+        });
+        if points.is_empty() {
+            return Self {
+                middle: Vec2::ZERO,
+                side: ITEM_SPACING * 4.0,
+            };
+        }
+        Self {
+            middle: (min + max) / 2.0,
+            side: (max - min).max_element() + ITEM_SPACING * 2.0,
+        }
+    }
+
+    /// Floor point (x, z) to map position, 0 to 1 from the top left; +X is to
+    /// the left and +Z up, as the default camera sees them
+    fn to_map(self, point: Vec2) -> Vec2 {
+        let corner = self.middle + Vec2::splat(self.side / 2.0);
+        (corner - point) / self.side
+    }
+
+    fn to_floor(self, map: Vec2) -> Vec2 {
+        let corner = self.middle + Vec2::splat(self.side / 2.0);
+        corner - map * self.side
+    }
+}
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Minimap>()
+            .add_systems(Startup, spawn_minimap)
+            .add_systems(Update, (show_minimap, handle_map_click, draw_minimap));
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn spawn_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut minimap: ResMut<Minimap>,
+) {
+    minimap.image = images.add(Image::new_fill(
+        Extent3d {
+            width: MAP_SIZE,
+            height: MAP_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        // Redrawn here, shown there
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    ));
+    commands.spawn((
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(40.0),
+                right: Val::Px(10.0),
+                width: Val::Px(MAP_SIZE as f32),
+                height: Val::Px(MAP_SIZE as f32),
+                border: UiRect::all(Val::Px(1.0)),
+                ..default()
+            },
+            image: UiImage::new(minimap.image.clone()),
+            ..default()
+        },
+        BorderColor(FELIPE_ORANGE_DIM),
+        // Takes clicks from the scene behind it (see `handle_mouse_click`)
+        Interaction::default(),
+        RelativeCursorPosition::default(),
+        MinimapNode,
+        UiElement,
+    ));
+}
+
+fn show_minimap(config: Res<Config>, mut node_query: Query<&mut Style, With<MinimapNode>>) {
+    if !config.is_changed() {
+        return;
+    }
+    let display = if config.render.minimap {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    for mut style in node_query.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+/// Click - select the entry nearest to the spot
+fn handle_map_click(
+    node_query: Query<
+        (&Interaction, &RelativeCursorPosition),
+        (Changed<Interaction>, With<MinimapNode>),
+    >,
+    mut current_dir: ResMut<CurrentDirectory>,
+    layouts: Res<Layouts>,
+    mut camera_state: ResMut<CameraState>,
+) {
+    let Some(spot) = node_query
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .find_map(|(_, cursor)| cursor.normalized)
+    else {
+        return;
+    };
+    let points = floor_points(&current_dir, &layouts);
+    let floor = MapArea::around(&points).to_floor(spot);
+    let nearest = points
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            a.distance_squared(floor)
+                .total_cmp(&b.distance_squared(floor))
+        })
+        .map(|(index, _)| index);
+    if let Some(index) = nearest {
+        current_dir.selected_index = index;
+        update_camera_target(&current_dir, &layouts, &mut camera_state);
+    }
+}
+
+/// Redraw when the entries, the selection or the camera move
+fn draw_minimap(
+    minimap: Res<Minimap>,
+    config: Res<Config>,
+    current_dir: Res<CurrentDirectory>,
+    layouts: Res<Layouts>,
+    camera_query: Query<(&Camera, Ref<GlobalTransform>), With<MainCamera>>,
+    mut points: Local<Vec<Vec2>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+    if !config.render.minimap {
+        return;
+    }
+    let moved = current_dir.is_changed() || layouts.is_changed();
+    if !moved && !camera_transform.is_changed() && !config.is_changed() {
+        return;
+    }
+    if moved || points.len() != current_dir.entries.len() {
+        *points = floor_points(&current_dir, &layouts);
+    }
+    let Some(image) = images.get_mut(&minimap.image) else {
+        return;
+    };
+
+    let area = MapArea::around(&points);
+    let mut canvas = Canvas(&mut image.data);
+    canvas.clear(Color::srgba(0.02, 0.02, 0.02, 0.9));
+
+    // Dots grow as there are fewer entries to tell apart
+    let dot = (MAP_SIZE as f32 * ITEM_SPACING / area.side / 2.0).clamp(1.0, 4.0) as i32;
+    for (i, point) in points.iter().enumerate() {
+        if i == current_dir.selected_index {
+            continue;
+        }
+        let color = if current_dir.entries[i].is_dir {
+            FELIPE_GRID
+        } else {
+            FELIPE_ORANGE_DIM
+        };
+        canvas.square(area.to_map(*point), dot, color);
+    }
+    if let Some(selected) = points.get(current_dir.selected_index) {
+        canvas.square(area.to_map(*selected), dot + 2, FELIPE_ORANGE);
+    }
+
+    if let Some(corners) = view_corners(camera, &camera_transform) {
+        let corners = corners.map(|corner| area.to_map(corner));
+        for i in 0..corners.len() {
+            canvas.line(corners[i], corners[(i + 1) % corners.len()], FELIPE_ORANGE);
+        }
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Where each entry stands on the floor, as (x, z)
+fn floor_points(current_dir: &CurrentDirectory, layouts: &Layouts) -> Vec<Vec2> {
+    let count = current_dir.entries.len();
+    (0..count)
+        .map(|i| layouts.position(i, count).xz())
+        .collect()
+}
+
+/// The floor under the four corners of the view, clockwise
+fn view_corners(camera: &Camera, transform: &GlobalTransform) -> Option<[Vec2; 4]> {
+    let size = camera.logical_viewport_size()?;
+    let floor = |corner: Vec2| {
+        let ray = camera.viewport_to_world(transform, corner)?;
+        let distance = if ray.direction.y < -f32::EPSILON {
+            (-ray.origin.y / ray.direction.y).min(FAR_FLOOR)
+        } else {
+            FAR_FLOOR
+        };
+        Some(ray.get_point(distance).xz())
+    };
+    Some([
+        floor(Vec2::ZERO)?,
+        floor(Vec2::new(size.x, 0.0))?,
+        floor(size)?,
+        floor(Vec2::new(0.0, size.y))?,
+    ])
+}
+
+/// The pixels of the map image, RGBA rows from the top
+struct Canvas<'a>(&'a mut [u8]);
+
+impl Canvas<'_> {
+    fn clear(&mut self, color: Color) {
+        let rgba = color.to_srgba().to_u8_array();
+        for pixel in self.0.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+
+    fn set(&mut self, x: i32, y: i32, rgba: [u8; 4]) {
+        let size = MAP_SIZE as i32;
+        if (0..size).contains(&x) && (0..size).contains(&y) {
+            let at = (y * size + x) as usize * 4;
+            self.0[at..at + 4].copy_from_slice(&rgba);
+        }
+    }
+
+    /// A `size` pixel square around `at` (0 to 1 from the top left)
+    fn square(&mut self, at: Vec2, size: i32, color: Color) {
+        let rgba = color.to_srgba().to_u8_array();
+        let center = (at * MAP_SIZE as f32).as_ivec2();
+        let half = size / 2;
+        for y in center.y - half..center.y - half + size {
+            for x in center.x - half..center.x - half + size {
+                self.set(x, y, rgba);
+            }
+        }
+    }
+
+    fn line(&mut self, from: Vec2, to: Vec2, color: Color) {
+        let rgba = color.to_srgba().to_u8_array();
+        let (from, to) = (from * MAP_SIZE as f32, to * MAP_SIZE as f32);
+        // Outlines of distant views reach far off the map; only its part
+        // on the map is walked
+        let delta = to - from;
+        let (mut start, mut end) = (0.0_f32, 1.0_f32);
+        for axis in 0..2 {
+            let (p, d) = (from[axis], delta[axis]);
+            if d.abs() < f32::EPSILON {
+                if !(0.0..=MAP_SIZE as f32).contains(&p) {
+                    return;
+                }
+                continue;
+            }
+            let (a, b) = ((0.0 - p) / d, (MAP_SIZE as f32 - p) / d);
+            start = start.max(a.min(b));
+            end = end.min(a.max(b));
+        }
+        if start > end {
+            return;
+        }
+        let steps = ((end - start) * delta.abs().max_element()).ceil().max(1.0) as i32;
+        for step in 0..=steps {
+            let t = start + (end - start) * step as f32 / steps as f32;
+            let point = (from + delta * t).floor();
+            self.set(point.x as i32, point.y as i32, rgba);
+        }
+    }
+}
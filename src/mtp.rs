@@ -0,0 +1,218 @@
+//! MTP backend of `mtp://device/storage/path` locations (feature `mtp`, Linux)
+//!
+//! Phones and cameras that speak MTP don't show up as mounts, but GVfs (the
+//! desktop's virtual filesystems) already talks to them, so this backend
+//! drives its `gio` tool: the device is the id GVfs gives it, and its top
+//! level lists the storages (internal storage, SD card) as directories.
+//! `:mtp` finds connected devices and goes to one; a phone has to be unlocked
+//! and set to file transfer first.
+//!
+//! ```text
+//! :mtp
+//! :mtp pixel
+//! :cd mtp://Google_Pixel_7_2B301FDH/Internal shared storage/DCIM
+//! ```
+
+use std::fs::{File, FileTimes};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, SystemTime};
+
+use crate::copier::CopyProgress;
+use crate::remote::{self, Backend, RemoteEntry};
+
+/// Attributes asked of every entry
+const ATTRIBUTES: &str = "standard::type,standard::size,time::modified";
+
+/// A connected device: the name the desktop shows and the id in its URL
+pub struct Device {
+    pub name: String,
+    pub id: String,
+}
+
+/// MTP devices GVfs can see, mounted or not
+pub fn devices() -> io::Result<Vec<Device>> {
+    let output = gio(&["mount", "-li"])?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut devices: Vec<Device> = Vec::new();
+    let mut volume = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some((_, name)) = line
+            .split_once("): ")
+            .filter(|_| line.starts_with("Volume("))
+        {
+            volume = Some(name.to_string());
+        }
+        // `activation_root=mtp://id/` on a volume, `name -> mtp://id/` on a mount
+        let Some((_, url)) = line.split_once("mtp://") else {
+            continue;
+        };
+        let id = url.trim_end_matches('/').to_string();
+        if !id.is_empty() && devices.iter().all(|device| device.id != id) {
+            devices.push(Device {
+                name: volume.clone().unwrap_or_else(|| id.clone()),
+                id,
+            });
+        }
+    }
+    Ok(devices)
+}
+
+pub struct MtpBackend {
+    /// `mtp://id`, which paths are appended to
+    root: String,
+}
+
+impl MtpBackend {
+    /// Mount the device through GVfs, unless it is already
+    pub fn connect(id: &str) -> io::Result<Self> {
+        let backend = Self {
+            root: format!("mtp://{}", id),
+        };
+        // Mounting a mounted device fails, which is fine; `info` tells
+        let _ = gio(&["mount", &backend.uri("/")]);
+        checked(gio(&["info", "-a", "standard::type", &backend.uri("/")])?)?;
+        Ok(backend)
+    }
+
+    fn uri(&self, path: &str) -> String {
+        format!("{}{}", self.root, remote::encode_path(path))
+    }
+}
+
+impl Backend for MtpBackend {
+    fn list(&self, path: &str) -> io::Result<Vec<RemoteEntry>> {
+        let output = checked(gio(&[
+            "list",
+            "-l",
+            "-h",
+            "-a",
+            ATTRIBUTES,
+            &self.uri(path),
+        ])?)?;
+        // name, size, (type), then the attributes asked for
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let name = fields.next()?.to_string();
+                let size = fields.next()?.parse().unwrap_or(0);
+                let is_dir = fields.next()? == "(directory)";
+                let modified = fields
+                    .find_map(|field| field.strip_prefix("time::modified="))
+                    .and_then(unix_time);
+                Some(RemoteEntry {
+                    name,
+                    is_dir,
+                    size,
+                    modified,
+                })
+            })
+            .collect())
+    }
+
+    fn stat(&self, path: &str) -> io::Result<RemoteEntry> {
+        let output = gio(&["info", "-a", ATTRIBUTES, &self.uri(path)])?;
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no {}", path),
+            ));
+        }
+        let mut entry = RemoteEntry {
+            name: path.rsplit('/').next().unwrap_or_default().to_string(),
+            is_dir: false,
+            size: 0,
+            modified: None,
+        };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            match line.trim().split_once(": ") {
+                Some(("type", kind)) => entry.is_dir = kind.trim() == "directory",
+                Some(("standard::size", size)) => entry.size = size.trim().parse().unwrap_or(0),
+                Some(("time::modified", time)) => entry.modified = unix_time(time.trim()),
+                _ => {}
+            }
+        }
+        Ok(entry)
+    }
+
+    fn download(&self, path: &str, local: &Path, progress: &CopyProgress) -> io::Result<()> {
+        let modified = self.stat(path)?.modified;
+        let mut child = Command::new("gio")
+            .args(["cat", &self.uri(path)])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(missing_gio)?;
+        let mut file = File::create(local)?;
+        if let Some(mut stdout) = child.stdout.take() {
+            remote::transfer(&mut stdout, &mut file, progress)?;
+        }
+        checked(child.wait_with_output()?)?;
+        if let Some(modified) = modified {
+            let _ = file.set_times(FileTimes::new().set_modified(modified));
+        }
+        Ok(())
+    }
+
+    fn upload(&self, local: &Path, path: &str, progress: &CopyProgress) -> io::Result<()> {
+        let mut child = Command::new("gio")
+            .args(["save", &self.uri(path)])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(missing_gio)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            remote::transfer(&mut File::open(local)?, &mut stdin, progress)?;
+        }
+        checked(child.wait_with_output()?)?;
+        Ok(())
+    }
+
+    fn make_dir(&self, path: &str) -> io::Result<()> {
+        checked(gio(&["mkdir", &self.uri(path)])?)?;
+        Ok(())
+    }
+}
+
+fn gio(args: &[&str]) -> io::Result<Output> {
+    Command::new("gio")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(missing_gio)
+}
+
+/// The output of a run that worked, else its error message as the error
+fn checked(output: Output) -> io::Result<Output> {
+    if output.status.success() {
+        return Ok(output);
+    }
+    let message = String::from_utf8_lossy(&output.stderr);
+    // `gio: mtp://...: Unable to open MTP device` - the part after the URI says why
+    let reason = message.trim().rsplit(": ").next().unwrap_or_default();
+    Err(io::Error::other(if reason.is_empty() {
+        "gio failed".to_string()
+    } else {
+        reason.to_string()
+    }))
+}
+
+fn missing_gio(err: io::Error) -> io::Error {
+    if err.kind() == io::ErrorKind::NotFound {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "MTP needs the gio tool of GVfs (install gvfs-backends or gvfs-mtp)",
+        )
+    } else {
+        err
+    }
+}
+
+fn unix_time(seconds: &str) -> Option<SystemTime> {
+    let seconds = seconds.parse().ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
@@ -0,0 +1,141 @@
+//! Notes - `:note text` pins a line of text to the selected entry
+//!
+//! Meant for context a name can't hold: where a download came from, what a
+//! dataset's columns mean. The note shows in the properties panel (`i`) and
+//! on the entry's info line, and an entry with a note has a small pale sticky
+//! note on its front. `:note!` takes it off. Notes are kept in the metadata
+//! store (metadata.rs).
+
+use bevy::prelude::*;
+use std::path::Path;
+
+use crate::command::{Command, RunCommand};
+use crate::metadata;
+use crate::properties::PropertiesView;
+use crate::{entry_height, CurrentDirectory, EntryPalette, FileEntity, StatusMessage};
+
+/// Side of the sticky note on an entry's front
+const STICKY_SIZE: f32 = 0.24;
+const STICKY_COLOR: Color = Color::srgb(0.98, 0.95, 0.6);
+
+// =============================================================================
+// Store
+// =============================================================================
+
+pub fn note_of(path: &Path) -> Option<String> {
+    metadata::note(path)
+}
+
+/// Pin `text` to `path`, replacing any note it had; None takes the note off
+fn set_note(path: &Path, text: Option<&str>) -> std::io::Result<()> {
+    // One line, so it fits the info line
+    let line = text.map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "));
+    metadata::set_note(path, line.as_deref())
+}
+
+// =============================================================================
+// Plugin
+// =============================================================================
+
+/// Bumped on every edit, so the sticky notes get rebuilt
+#[derive(Resource, Default)]
+struct NoteEdits(u64);
+
+/// Marker for the sticky note on an entry
+#[derive(Component)]
+struct StickyNote;
+
+pub struct NotesPlugin;
+
+impl Plugin for NotesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NoteEdits>()
+            .add_systems(Update, (handle_note_command, update_sticky_notes));
+    }
+}
+
+fn handle_note_command(
+    mut run_commands: EventReader<RunCommand>,
+    current_dir: Res<CurrentDirectory>,
+    mut properties: ResMut<PropertiesView>,
+    mut status: ResMut<StatusMessage>,
+    mut edits: ResMut<NoteEdits>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Note(text) = command else {
+            continue;
+        };
+        let Some(path) = current_dir
+            .entries
+            .get(current_dir.selected_index)
+            .filter(|entry| entry.name != "..")
+            .map(|entry| entry.path.clone())
+        else {
+            status.0 = "Nothing selected to note".to_string();
+            continue;
+        };
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        status.0 = match set_note(&path, text.as_deref()) {
+            Ok(()) if text.is_some() => format!("Noted {}", name),
+            Ok(()) => format!("Note on {} removed", name),
+            Err(err) => format!("Cannot save the notes: {}", err),
+        };
+        edits.0 += 1;
+        properties.refresh();
+    }
+}
+
+/// Rebuild sticky notes when notes change or the entries were respawned
+fn update_sticky_notes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    edits: Res<NoteEdits>,
+    current_dir: Res<CurrentDirectory>,
+    entity_query: Query<(Entity, &FileEntity)>,
+    new_entities: Query<(), Added<FileEntity>>,
+    sticky_query: Query<Entity, With<StickyNote>>,
+) {
+    if !edits.is_changed() && new_entities.is_empty() {
+        return;
+    }
+    for sticky in sticky_query.iter() {
+        commands.entity(sticky).despawn();
+    }
+
+    let mesh = palette.mesh(&mut meshes, "sticky note", || {
+        Cuboid::new(STICKY_SIZE, STICKY_SIZE, 0.02).into()
+    });
+    let material = palette.material(&mut materials, STICKY_COLOR.to_linear());
+    for (entity, file_entity) in entity_query.iter() {
+        let Some(entry) = current_dir.entries.get(file_entity.index) else {
+            continue;
+        };
+        if metadata::note(&entry.path).is_none() {
+            continue;
+        }
+        // Stuck on the front, left of center; the entry's unit cuboid is
+        // stretched to its height, which the note undoes
+        let height = entry_height(entry);
+        let sticky = commands
+            .spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(-0.2, 0.0, 0.16).with_scale(Vec3::new(
+                        1.0,
+                        1.0 / height,
+                        1.0,
+                    )),
+                    ..default()
+                },
+                StickyNote,
+            ))
+            .id();
+        commands.entity(entity).add_child(sticky);
+    }
+}
@@ -0,0 +1,108 @@
+//! Entry numbers - a small number under each name, for counts like `15G`
+//!
+//! `:set number` numbers the entries from 1 in listing order, so `15G` goes
+//! to the one marked 15. `:set relativenumber` shows instead how far each is
+//! from the selection, so `7j` or `3k` reaches the one marked 7 or 3; the
+//! selection itself shows its own number with both on, 0 with only
+//! `relativenumber`, as in vim.
+//!
+//! ```toml
+//! [render]
+//! number = false
+//! relativenumber = false
+//! ```
+
+use bevy::prelude::*;
+
+use crate::config::Config;
+use crate::{CurrentDirectory, FileLabel, FELIPE_ORANGE_DIM};
+
+/// Below the name, in the label's own (scaled) units
+const NUMBER_OFFSET: f32 = -32.0;
+
+/// Number under a label, a child of it
+#[derive(Component)]
+struct EntryNumber;
+
+pub struct NumbersPlugin;
+
+impl Plugin for NumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (attach_numbers, update_numbers).chain());
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Give new labels their number, or take them all off
+fn attach_numbers(
+    mut commands: Commands,
+    config: Res<Config>,
+    label_query: Query<(Entity, Option<&Children>), With<FileLabel>>,
+    number_query: Query<Entity, With<EntryNumber>>,
+) {
+    let shown = config.render.number || config.render.relativenumber;
+    if !shown {
+        if config.is_changed() {
+            for entity in number_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        return;
+    }
+    for (label, children) in label_query.iter() {
+        let numbered = children
+            .is_some_and(|children| children.iter().any(|child| number_query.contains(*child)));
+        if numbered {
+            continue;
+        }
+        commands.entity(label).with_children(|label| {
+            label.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 22.0,
+                            color: FELIPE_ORANGE_DIM,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_xyz(0.0, NUMBER_OFFSET, 0.0),
+                    ..default()
+                },
+                EntryNumber,
+            ));
+        });
+    }
+}
+
+fn update_numbers(
+    config: Res<Config>,
+    current_dir: Res<CurrentDirectory>,
+    label_query: Query<&FileLabel>,
+    mut number_query: Query<(&Parent, Ref<EntryNumber>, &mut Text)>,
+) {
+    let render = &config.render;
+    let selected = current_dir.selected_index;
+    let changed = config.is_changed() || current_dir.is_changed();
+    for (parent, number, mut text) in number_query.iter_mut() {
+        if !changed && !number.is_added() {
+            continue;
+        }
+        // Labels sinking away after a directory change have lost theirs
+        let Ok(label) = label_query.get(parent.get()) else {
+            continue;
+        };
+        let shown = match (render.number, render.relativenumber) {
+            (true, true) if label.index == selected => label.index + 1,
+            (_, true) => label.index.abs_diff(selected),
+            _ => label.index + 1,
+        };
+        let value = shown.to_string();
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
@@ -0,0 +1,544 @@
+//! Operation log - every file operation is recorded as one undoable group
+//!
+//! A multi-file paste is a single group, so one `u` reverts the whole batch.
+//! Each group is also logged, with when it ran, as whom and what failed, to
+//! the metadata store (metadata.rs), so the history outlives the session.
+//!
+//! `:oplog` lists the groups, this session's and then earlier ones, each
+//! expandable into its individual steps and failures. `U` reverts the
+//! selected group, however old; `r` runs its steps again as a new group, for
+//! the copies, moves, new directories and deletions that say enough to be
+//! redone.
+
+use bevy::prelude::*;
+use std::collections::HashSet;
+use std::time::SystemTime;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::metadata;
+use crate::ops::UndoStep;
+use crate::{
+    CurrentDirectory, Prompt, StatusMessage, UiElement, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+// =============================================================================
+// Log
+// =============================================================================
+
+/// Steps performed by one user action
+pub struct OperationGroup {
+    pub label: String,
+    pub steps: Vec<UndoStep>,
+    pub undone: bool,
+    /// When it ran, and as which user
+    pub at: SystemTime,
+    pub user: String,
+    /// What went wrong, one line per step that failed
+    pub failures: Vec<String>,
+    /// Row in the metadata store; None if it couldn't be logged there
+    pub id: Option<i64>,
+}
+
+impl OperationGroup {
+    /// Revert the steps, last first; what to tell the user. The group only
+    /// counts as undone once every step is; the steps that failed stay in it,
+    /// so undoing it again retries just those
+    fn revert(&mut self) -> String {
+        if self.undone {
+            return format!("Already undone: {}", self.label);
+        }
+        let mut failures = Vec::new();
+        let mut left = Vec::new();
+        for step in self.steps.iter().rev() {
+            if let Err(err) = step.revert() {
+                failures.push(format!("{} ({})", step.describe(), err));
+                left.push(step.clone());
+            }
+        }
+        info!("Undone: {} ({} failed)", self.label, failures.len());
+        for failure in &failures {
+            warn!("Not undone: {}", failure);
+        }
+
+        let Some(first) = failures.first() else {
+            self.undone = true;
+            if let Some(id) = self.id {
+                if let Err(err) = metadata::set_undone(id) {
+                    warn!("Cannot mark {} undone in the log: {}", self.label, err);
+                }
+            }
+            return format!("Undone: {}", self.label);
+        };
+        if left.len() < self.steps.len() {
+            left.reverse();
+            self.steps = left;
+            if let Some(id) = self.id {
+                if let Err(err) = metadata::set_steps(id, &self.steps) {
+                    warn!("Cannot update {} in the log: {}", self.label, err);
+                }
+            }
+        }
+        format!(
+            "Partly undone, {} step(s) left: {} - {}",
+            failures.len(),
+            self.label,
+            first
+        )
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct OperationLog {
+    /// This session's groups, oldest first
+    pub groups: Vec<OperationGroup>,
+    /// Groups of earlier sessions, oldest first, read at startup
+    pub earlier: Vec<OperationGroup>,
+}
+
+impl OperationLog {
+    /// Record an action; actions that changed nothing are not worth undoing
+    pub fn record(&mut self, label: String, steps: Vec<UndoStep>) {
+        self.record_outcome(label, steps, Vec::new());
+    }
+
+    /// Record an action with the steps that failed, one line each; it's kept
+    /// when nothing was done but something failed, so the log says why
+    pub fn record_outcome(&mut self, label: String, steps: Vec<UndoStep>, failures: Vec<String>) {
+        if steps.is_empty() && failures.is_empty() {
+            return;
+        }
+        info!("{}: {} done, {} failed", label, steps.len(), failures.len());
+        for step in &steps {
+            debug!("  {}", step.describe());
+        }
+        let mut group = OperationGroup {
+            label,
+            steps,
+            undone: false,
+            at: SystemTime::now(),
+            user: current_user(),
+            failures,
+            id: None,
+        };
+        match metadata::log_operation(&group) {
+            Ok(id) => group.id = Some(id),
+            Err(err) => warn!("Cannot log {}: {}", group.label, err),
+        }
+        self.groups.push(group);
+    }
+
+    /// Revert the newest group that hasn't been undone, last step first
+    pub fn undo_last(&mut self) -> Option<String> {
+        let group = self.groups.iter_mut().rev().find(|g| !g.undone)?;
+        Some(group.revert())
+    }
+
+    /// Earlier sessions' groups then this session's, oldest first
+    fn all(&self) -> impl Iterator<Item = &OperationGroup> {
+        self.earlier.iter().chain(&self.groups)
+    }
+
+    fn len(&self) -> usize {
+        self.earlier.len() + self.groups.len()
+    }
+
+    /// Group at `index` in `all`
+    fn get_mut(&mut self, index: usize) -> Option<&mut OperationGroup> {
+        match index.checked_sub(self.earlier.len()) {
+            None => self.earlier.get_mut(index),
+            Some(index) => self.groups.get_mut(index),
+        }
+    }
+
+    /// Do the steps of the group at `index` in `all` again, as a new group;
+    /// what to tell the user
+    fn rerun(&mut self, index: usize) -> Option<String> {
+        let group = self.get_mut(index)?;
+        let label = format!("re-run {}", group.label);
+        let mut steps = Vec::new();
+        let mut failures = Vec::new();
+        for step in &group.steps {
+            match step.rerun() {
+                Some(Ok(step)) => steps.push(step),
+                Some(Err(err)) => failures.push(format!("{} ({})", step.describe(), err)),
+                None => failures.push(format!("{} (can't be redone)", step.describe())),
+            }
+        }
+        let message = match failures.first() {
+            None => format!("Done again: {}", label),
+            Some(first) => format!(
+                "{}: {} done, {} failed - {}",
+                label,
+                steps.len(),
+                failures.len(),
+                first
+            ),
+        };
+        self.record_outcome(label, steps, failures);
+        Some(message)
+    }
+}
+
+/// Who operations ran as, for the log
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+// =============================================================================
+// View
+// =============================================================================
+
+/// Groups of earlier sessions read into the log at startup
+const EARLIER_GROUPS: usize = 200;
+
+/// `:oplog` panel state
+#[derive(Resource, Default)]
+pub struct OplogView {
+    /// Index into the newest-first list
+    cursor: usize,
+    /// Expanded groups, by index into `OperationLog::all`
+    expanded: HashSet<usize>,
+}
+
+/// Marker for the oplog panel
+#[derive(Component)]
+struct OplogPanel;
+
+/// Marker for the oplog panel text
+#[derive(Component)]
+struct OplogText;
+
+pub struct OplogPlugin;
+
+impl Plugin for OplogPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(OperationLog::default())
+            .insert_resource(OplogView::default())
+            .add_systems(Startup, load_earlier_sessions)
+            .add_systems(
+                Update,
+                (
+                    handle_oplog_command,
+                    handle_undo_key,
+                    handle_oplog_keys,
+                    update_oplog_panel,
+                ),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn load_earlier_sessions(mut oplog: ResMut<OperationLog>) {
+    match metadata::operations(EARLIER_GROUPS) {
+        Ok(groups) => oplog.earlier = groups,
+        Err(err) => warn!("Cannot read the operation log: {}", err),
+    }
+}
+
+fn handle_oplog_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut view: ResMut<OplogView>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<OplogPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Oplog {
+            continue;
+        }
+        if focus.is_open(Panel::Oplog) {
+            close_panel(&mut commands, &mut focus, &panel_query);
+        } else {
+            focus.open(Panel::Oplog);
+            view.cursor = 0;
+            spawn_panel(&mut commands);
+        }
+    }
+}
+
+/// `u` in NORMAL mode undoes the latest group, or retries what of it failed
+fn handle_undo_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    mut oplog: ResMut<OperationLog>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyU)
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || focus.any_open()
+    {
+        return;
+    }
+    undo(&mut oplog, &mut current_dir, &mut status);
+}
+
+fn handle_oplog_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut view: ResMut<OplogView>,
+    mut oplog: ResMut<OperationLog>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<OplogPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::Oplog)
+    {
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    if !focus.has_focus(Panel::Oplog) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    let count = oplog.len();
+
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        view.cursor = (view.cursor + 1).min(count.saturating_sub(1));
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        view.cursor = view.cursor.saturating_sub(1);
+    }
+    if (keyboard.just_pressed(KeyCode::Enter) || keyboard.just_pressed(KeyCode::KeyL)) && count > 0
+    {
+        let group = count - 1 - view.cursor;
+        if !view.expanded.remove(&group) {
+            view.expanded.insert(group);
+        }
+    }
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keyboard.just_pressed(KeyCode::KeyU) && !shift {
+        undo(&mut oplog, &mut current_dir, &mut status);
+    }
+    // The selected group, wherever it is in the history
+    let selected = count.checked_sub(1 + view.cursor);
+    if keyboard.just_pressed(KeyCode::KeyU) && shift {
+        if let Some(group) = selected.and_then(|index| oplog.get_mut(index)) {
+            status.0 = group.revert();
+            current_dir.needs_reload = true;
+        }
+    }
+    if keyboard.just_pressed(KeyCode::KeyR) {
+        if let Some(message) = selected.and_then(|index| oplog.rerun(index)) {
+            status.0 = message;
+            current_dir.needs_reload = true;
+            // The new group is the newest; follow the one it repeats
+            view.cursor += 1;
+        }
+    }
+}
+
+fn update_oplog_panel(
+    view: Res<OplogView>,
+    focus: Res<Focus>,
+    oplog: Res<OperationLog>,
+    config: Res<Config>,
+    mut text_query: Query<&mut Text, With<OplogText>>,
+) {
+    if !focus.is_open(Panel::Oplog) {
+        return;
+    }
+    for mut text in text_query.iter_mut() {
+        let mut sections = vec![TextSection::new(
+            "OPLOG  j/k:select  Enter:expand  u:undo latest  U:revert selected  r:re-run  Esc:close\n",
+            panel_style(FELIPE_ORANGE),
+        )];
+        if oplog.len() == 0 {
+            sections.push(TextSection::new(
+                "(no operations yet)",
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+        }
+
+        let groups: Vec<&OperationGroup> = oplog.all().collect();
+        for (row, (index, group)) in groups.into_iter().enumerate().rev().enumerate() {
+            if index + 1 == oplog.earlier.len() {
+                sections.push(TextSection::new(
+                    "  -- earlier sessions --\n",
+                    panel_style(FELIPE_ORANGE_DIM),
+                ));
+            }
+            let selected = row == view.cursor;
+            let expanded = view.expanded.contains(&index);
+            let line = format!(
+                "{} {} #{} {} {} {} ({} step{}){}{}\n",
+                if selected { ">" } else { " " },
+                if expanded { "-" } else { "+" },
+                index + 1,
+                config.format.date(group.at),
+                group.user,
+                group.label,
+                group.steps.len(),
+                if group.steps.len() == 1 { "" } else { "s" },
+                if group.failures.is_empty() {
+                    String::new()
+                } else {
+                    format!("  [{} failed]", group.failures.len())
+                },
+                if group.undone { "  [undone]" } else { "" }
+            );
+            let color = if selected {
+                FELIPE_ORANGE
+            } else {
+                FELIPE_ORANGE_DIM
+            };
+            sections.push(TextSection::new(line, panel_style(color)));
+
+            if expanded {
+                for step in &group.steps {
+                    sections.push(TextSection::new(
+                        format!("      {}\n", step.describe()),
+                        panel_style(FELIPE_ORANGE_DIM),
+                    ));
+                }
+                for failure in &group.failures {
+                    sections.push(TextSection::new(
+                        format!("      failed: {}\n", failure),
+                        panel_style(FELIPE_ORANGE_DIM),
+                    ));
+                }
+            }
+        }
+        text.sections = sections;
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn undo(oplog: &mut OperationLog, current_dir: &mut CurrentDirectory, status: &mut StatusMessage) {
+    match oplog.undo_last() {
+        Some(message) => {
+            status.0 = message;
+            current_dir.needs_reload = true;
+        }
+        None => status.0 = "Already at oldest change".to_string(),
+    }
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(640.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            OplogPanel,
+            Focusable(Panel::Oplog),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), OplogText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<OplogPanel>>,
+) {
+    focus.close(Panel::Oplog);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(steps: Vec<UndoStep>) -> OperationGroup {
+        OperationGroup {
+            label: "test".to_string(),
+            steps,
+            undone: false,
+            at: SystemTime::now(),
+            user: String::new(),
+            failures: Vec::new(),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn revert_keeps_the_steps_that_failed_for_a_retry() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty");
+        let full = dir.path().join("full");
+        std::fs::create_dir(&empty).unwrap();
+        std::fs::create_dir(&full).unwrap();
+        std::fs::write(full.join("kept"), "").unwrap();
+        let mut group = group(vec![
+            UndoStep::Created { dir: empty.clone() },
+            UndoStep::Created { dir: full.clone() },
+        ]);
+
+        group.revert();
+        assert!(!group.undone);
+        assert!(!empty.exists());
+        assert!(matches!(&group.steps[..], [UndoStep::Created { dir }] if *dir == full));
+
+        std::fs::remove_file(full.join("kept")).unwrap();
+        assert_eq!(group.revert(), "Undone: test");
+        assert!(group.undone);
+        assert!(!full.exists());
+    }
+
+    #[test]
+    fn undo_last_retries_a_partly_undone_group_before_older_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let older = dir.path().join("older");
+        let full = dir.path().join("full");
+        std::fs::create_dir(&older).unwrap();
+        std::fs::create_dir(&full).unwrap();
+        std::fs::write(full.join("kept"), "").unwrap();
+        let mut log = OperationLog::default();
+        log.groups
+            .push(group(vec![UndoStep::Created { dir: older.clone() }]));
+        log.groups
+            .push(group(vec![UndoStep::Created { dir: full.clone() }]));
+
+        log.undo_last();
+        log.undo_last();
+        assert!(older.exists());
+        assert!(!log.groups[1].undone);
+
+        std::fs::remove_file(full.join("kept")).unwrap();
+        log.undo_last();
+        log.undo_last();
+        assert!(!full.exists() && !older.exists());
+        assert!(log.groups.iter().all(|group| group.undone));
+    }
+}
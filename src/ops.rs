@@ -0,0 +1,1456 @@
+//! File operations - transfers are planned (dry-run) before they touch the disk
+//!
+//! Planning inspects the destination and reports conflicts, so the UI can ask
+//! the user how to resolve them instead of silently overwriting anything.
+//!
+//! In read-only mode (`felipe --read-only`, or `:set readonly` for a while)
+//! every operation here fails with "read-only mode" instead, whichever key,
+//! command or script asked for it. Started with `--read-only`, Felipe stays
+//! read-only for good.
+//!
+//! Copies, including moves across filesystems, go through the copy engine in
+//! copier.rs; the rest reaches the disk through the filesystem's `VfsBackend`
+//! (vfs.rs).
+
+use bevy::log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::copier::{self, CopyProgress};
+use crate::metadata;
+use crate::remote;
+use crate::vfs;
+use crate::FileEntry;
+
+/// Whether operations may change anything on disk
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+/// Set by `--read-only`; nothing turns read-only mode off then
+static READ_ONLY_LOCKED: AtomicBool = AtomicBool::new(false);
+/// Trash folders handed out by this process, so two in the same instant differ
+static TRASH_BINS: AtomicU64 = AtomicU64::new(0);
+
+/// Read-only for the rest of the session
+pub fn lock_read_only() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+    READ_ONLY_LOCKED.store(true, Ordering::Relaxed);
+}
+
+/// `:set readonly`; refused when it would lift `--read-only`
+pub fn set_read_only(read_only: bool) -> Result<(), String> {
+    if !read_only && READ_ONLY_LOCKED.load(Ordering::Relaxed) {
+        return Err("Started with --read-only, which stays on".to_string());
+    }
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// How a taken name is numbered, `rename_pattern` under `[operations]`;
+/// empty for `DEFAULT_RENAME_PATTERN`
+static RENAME_PATTERN: RwLock<String> = RwLock::new(String::new());
+
+/// `foo.txt` becomes `foo (1).txt`
+pub const DEFAULT_RENAME_PATTERN: &str = "{name} ({n}){ext}";
+
+/// Number taken names after `pattern` from now on (see `unique_name`)
+pub fn set_rename_pattern(pattern: &str) {
+    *RENAME_PATTERN
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = pattern.to_string();
+}
+
+/// Why `pattern` can't number names, if it can't
+pub fn check_rename_pattern(pattern: &str) -> Result<(), String> {
+    if !pattern.contains("{n}") {
+        Err(format!("{:?} has no {{n}} for the number", pattern))
+    } else if pattern.contains(['/', '\\']) {
+        Err(format!("{:?} would make a path, not a name", pattern))
+    } else {
+        Ok(())
+    }
+}
+
+/// The error every operation gives in read-only mode
+fn writable() -> io::Result<()> {
+    if is_read_only() {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "read-only mode",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Plan
+// =============================================================================
+
+/// What a paste does with its sources
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferKind {
+    Copy,
+    Move,
+    /// Symlink at the target pointing at the source, which is kept as written
+    Symlink,
+    /// Hard link at the target to the source file
+    Hardlink,
+    /// Into Felipe's trash, the target a fresh folder there (see `plan_trash`)
+    Trash,
+}
+
+impl TransferKind {
+    pub fn verb(self) -> &'static str {
+        match self {
+            TransferKind::Copy => "copied",
+            TransferKind::Move => "moved",
+            TransferKind::Symlink => "linked",
+            TransferKind::Hardlink => "hard-linked",
+            TransferKind::Trash => "trashed",
+        }
+    }
+
+    /// Links leave the source alone, so a target named like it is a clash
+    fn is_link(self) -> bool {
+        matches!(self, TransferKind::Symlink | TransferKind::Hardlink)
+    }
+}
+
+/// Why a planned step can't run as-is
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Conflict {
+    /// Target name differs from an existing (or earlier planned) name only by case,
+    /// and the destination filesystem is case-insensitive
+    CaseCollision { existing: String },
+}
+
+/// One source → target step of a transfer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedStep {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub conflict: Option<Conflict>,
+    /// The target exists and goes to the trash first (see `resolve_overwrites`)
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// A transfer computed without touching the disk
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransferPlan {
+    pub kind: TransferKind,
+    pub steps: Vec<PlannedStep>,
+    /// Sources left out while resolving conflicts, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// What to do with a step whose target already exists
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Trash what's there, then transfer
+    Replace,
+    Skip,
+    /// Give the incoming entry a free name like `foo (1).txt`
+    Rename,
+    /// Replace only what was modified before the incoming entry
+    IfNewer,
+}
+
+/// How to resolve case collisions found during planning
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenameStrategy {
+    /// Give the incoming entry a free name like `foo (1).txt`
+    Suffix,
+    /// Leave the colliding entries out of the transfer
+    Skip,
+}
+
+impl TransferPlan {
+    /// Directory the plan writes into
+    pub fn destination(&self) -> Option<&Path> {
+        self.steps.first().and_then(|step| step.target.parent())
+    }
+
+    pub fn conflicts(&self) -> impl Iterator<Item = &PlannedStep> {
+        self.steps.iter().filter(|step| step.conflict.is_some())
+    }
+
+    /// Rewrite conflicting steps according to `strategy`
+    pub fn resolve_case_collisions(&mut self, strategy: RenameStrategy) {
+        match strategy {
+            RenameStrategy::Skip => {
+                let (conflicting, keep): (Vec<_>, Vec<_>) = std::mem::take(&mut self.steps)
+                    .into_iter()
+                    .partition(|step| step.conflict.is_some());
+                self.steps = keep;
+                self.skipped.extend(
+                    conflicting
+                        .into_iter()
+                        .map(|step| (step.source, "case collision".to_string())),
+                );
+            }
+            RenameStrategy::Suffix => {
+                let mut taken: HashSet<String> = HashSet::new();
+                if let Some(dest_dir) = self.destination() {
+                    taken.extend(list_names(dest_dir).iter().map(|n| n.to_lowercase()));
+                }
+                for step in &self.steps {
+                    if step.conflict.is_none() {
+                        taken.insert(file_name_of(&step.target).to_lowercase());
+                    }
+                }
+                for step in self.steps.iter_mut().filter(|s| s.conflict.is_some()) {
+                    let name = unique_name(&file_name_of(&step.target), |candidate| {
+                        taken.contains(&candidate.to_lowercase())
+                    });
+                    taken.insert(name.to_lowercase());
+                    step.target.set_file_name(name);
+                    step.conflict = None;
+                }
+            }
+        }
+    }
+
+    /// Steps whose target name is already taken (by something other than the source)
+    pub fn existing_targets(&self) -> impl Iterator<Item = &PlannedStep> {
+        self.steps
+            .iter()
+            .filter(|step| target_taken(self.kind, step))
+    }
+
+    /// Give incoming entries a free name, or leave them out, where the target exists
+    pub fn resolve_existing(&mut self, strategy: RenameStrategy) {
+        match strategy {
+            RenameStrategy::Skip => {
+                let (existing, keep): (Vec<_>, Vec<_>) = std::mem::take(&mut self.steps)
+                    .into_iter()
+                    .partition(|step| target_taken(self.kind, step));
+                self.steps = keep;
+                self.skipped.extend(
+                    existing
+                        .into_iter()
+                        .map(|step| (step.source, "already exists".to_string())),
+                );
+            }
+            RenameStrategy::Suffix => {
+                let mut planned: HashSet<PathBuf> =
+                    self.steps.iter().map(|step| step.target.clone()).collect();
+                for step in self.steps.iter_mut() {
+                    if !target_taken(self.kind, step) {
+                        continue;
+                    }
+                    let dir = step.target.parent().unwrap_or(Path::new("")).to_path_buf();
+                    let name = unique_name(&file_name_of(&step.target), |candidate| {
+                        let path = dir.join(candidate);
+                        planned.contains(&path) || vfs::exists(&path)
+                    });
+                    step.target = dir.join(name);
+                    planned.insert(step.target.clone());
+                }
+            }
+        }
+    }
+
+    /// Steps `report` says were done that landed under another name than
+    /// their source's: (source, new name)
+    pub fn renamed(&self, report: &TransferReport) -> Vec<(PathBuf, String)> {
+        if self.kind == TransferKind::Trash {
+            return Vec::new();
+        }
+        self.steps
+            .iter()
+            .filter(|step| step.source.file_name() != step.target.file_name())
+            .filter(|step| !report.failed.iter().any(|(path, _)| *path == step.source))
+            .map(|step| (step.source.clone(), file_name_of(&step.target)))
+            .collect()
+    }
+
+    /// Settle the steps of `existing_targets`, in that order, one choice each
+    pub fn resolve_overwrites(&mut self, choices: &[Overwrite]) {
+        let kind = self.kind;
+        let mut choices = choices.iter().copied();
+        let mut planned: HashSet<PathBuf> =
+            self.steps.iter().map(|step| step.target.clone()).collect();
+        let mut keep = Vec::with_capacity(self.steps.len());
+        for mut step in std::mem::take(&mut self.steps) {
+            if !target_taken(kind, &step) {
+                keep.push(step);
+                continue;
+            }
+            let choice = match choices.next().unwrap_or(Overwrite::Skip) {
+                Overwrite::IfNewer if is_newer(&step.source, &step.target) => Overwrite::Replace,
+                Overwrite::IfNewer => {
+                    self.skipped
+                        .push((step.source, "the one there isn't older".to_string()));
+                    continue;
+                }
+                choice => choice,
+            };
+            match choice {
+                Overwrite::Replace => step.replace = true,
+                Overwrite::Rename => {
+                    let dir = step.target.parent().unwrap_or(Path::new("")).to_path_buf();
+                    let name = unique_name(&file_name_of(&step.target), |candidate| {
+                        let path = dir.join(candidate);
+                        planned.contains(&path) || vfs::exists(&path)
+                    });
+                    step.target = dir.join(name);
+                    planned.insert(step.target.clone());
+                }
+                Overwrite::Skip | Overwrite::IfNewer => {
+                    self.skipped
+                        .push((step.source, "already exists".to_string()));
+                    continue;
+                }
+            }
+            keep.push(step);
+        }
+        self.steps = keep;
+    }
+}
+
+/// Whether `source` was modified after `target`; unknown times count as not
+pub fn is_newer(source: &Path, target: &Path) -> bool {
+    let modified = |path: &Path| {
+        vfs::backend(path)
+            .and_then(|fs| fs.stat(path))
+            .ok()
+            .and_then(|entry| entry.modified)
+    };
+    matches!((modified(source), modified(target)), (Some(s), Some(t)) if s > t)
+}
+
+fn target_taken(kind: TransferKind, step: &PlannedStep) -> bool {
+    (kind.is_link() || step.source != step.target) && vfs::exists(&step.target)
+}
+
+/// Dry-run phase: work out where every source would land in `dest_dir`
+pub fn plan_transfer(kind: TransferKind, sources: &[PathBuf], dest_dir: &Path) -> TransferPlan {
+    let existing = list_names(dest_dir);
+    let case_insensitive = is_case_insensitive(dest_dir, &existing);
+
+    // Lowercased name → name as it will exist after the transfer
+    let mut seen: Vec<(String, String)> = existing
+        .iter()
+        .map(|name| (name.to_lowercase(), name.clone()))
+        .collect();
+
+    let steps = sources
+        .iter()
+        .map(|source| {
+            let name = file_name_of(source);
+            let target = dest_dir.join(&name);
+            let lower = name.to_lowercase();
+
+            let conflict = if case_insensitive {
+                seen.iter()
+                    .find(|(l, n)| *l == lower && *n != name)
+                    .map(|(_, n)| Conflict::CaseCollision {
+                        existing: n.clone(),
+                    })
+            } else {
+                None
+            };
+            seen.push((lower, name));
+
+            PlannedStep {
+                source: source.clone(),
+                target,
+                conflict,
+                replace: false,
+            }
+        })
+        .collect();
+
+    TransferPlan {
+        kind,
+        steps,
+        skipped: Vec::new(),
+    }
+}
+
+/// Plan sending `sources` to the trash, all into one fresh folder of it; they
+/// can't clash, coming from one directory
+pub fn plan_trash(sources: &[PathBuf]) -> io::Result<TransferPlan> {
+    let bin = trash_bin()?;
+    let steps = sources
+        .iter()
+        .map(|source| PlannedStep {
+            source: source.clone(),
+            target: bin.join(file_name_of(source)),
+            conflict: None,
+            replace: false,
+        })
+        .collect();
+    Ok(TransferPlan {
+        kind: TransferKind::Trash,
+        steps,
+        skipped: Vec::new(),
+    })
+}
+
+// =============================================================================
+// Execution
+// =============================================================================
+
+/// A completed filesystem change and enough information to revert it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum UndoStep {
+    Copied {
+        source: PathBuf,
+        target: PathBuf,
+    },
+    /// Symlink or hard link created at `target`
+    Linked {
+        target: PathBuf,
+    },
+    /// Empty directory created at `dir`
+    Created {
+        dir: PathBuf,
+    },
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Deleted by moving into Felipe's trash
+    Trashed {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Permission bits changed; `mode` is what they were before
+    Chmod {
+        path: PathBuf,
+        mode: u32,
+    },
+    /// Owner and group changed from `uid` and `gid`; `follow` if the change
+    /// went through a symlink to its target
+    Chown {
+        path: PathBuf,
+        uid: u32,
+        gid: u32,
+        follow: bool,
+    },
+    /// Extended attribute `name` set or removed; `value` is what it was before,
+    /// None if it didn't exist
+    Xattr {
+        path: PathBuf,
+        name: String,
+        value: Option<Vec<u8>>,
+    },
+}
+
+impl UndoStep {
+    pub fn describe(&self) -> String {
+        match self {
+            UndoStep::Copied { source, target } => {
+                format!("copy {} -> {}", source.display(), target.display())
+            }
+            UndoStep::Linked { target } => format!("link -> {}", target.display()),
+            UndoStep::Created { dir } => format!("mkdir {}", dir.display()),
+            UndoStep::Moved { from, to } => format!("move {} -> {}", from.display(), to.display()),
+            UndoStep::Trashed { from, .. } => format!("trash {}", from.display()),
+            UndoStep::Chmod { path, mode } => {
+                format!("chmod {} (was {:04o})", path.display(), mode)
+            }
+            UndoStep::Chown { path, uid, gid, .. } => {
+                format!("chown {} (was {}:{})", path.display(), uid, gid)
+            }
+            UndoStep::Xattr { path, name, value } => match value {
+                Some(value) => format!(
+                    "xattr {} {} (was {} bytes)",
+                    path.display(),
+                    name,
+                    value.len()
+                ),
+                None => format!("xattr {} {} (was unset)", path.display(), name),
+            },
+        }
+    }
+
+    /// Revert this step: delete the copy, link or new directory, move the
+    /// entry back or restore its mode, owner or attribute
+    pub fn revert(&self) -> io::Result<()> {
+        writable()?;
+        match self {
+            UndoStep::Copied { target, .. } => remove_entry(target),
+            // Only the link goes; what it points at stays
+            UndoStep::Linked { target } => remove_entry(target),
+            // Only if nothing has been put in it since
+            UndoStep::Created { dir } => std::fs::remove_dir(dir),
+            UndoStep::Chmod { path, mode } => set_mode(path, *mode).map(|_| ()),
+            UndoStep::Chown {
+                path,
+                uid,
+                gid,
+                follow,
+            } => set_owner(path, Some(*uid), Some(*gid), *follow).map(|_| ()),
+            UndoStep::Xattr { path, name, value } => {
+                set_xattr(path, name, value.as_deref()).map(|_| ())
+            }
+            UndoStep::Moved { from, to } | UndoStep::Trashed { from, to } => {
+                if from.exists() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} exists again", from.display()),
+                    ));
+                }
+                move_entry(to, from)
+            }
+        }
+    }
+
+    /// Do the step again, like copying the same file once more after the
+    /// copy was undone; None for steps that don't say enough to be redone
+    pub fn rerun(&self) -> Option<io::Result<UndoStep>> {
+        Some(match self {
+            UndoStep::Copied { source, target } => copy_path(source, target),
+            UndoStep::Created { dir } => make_dir(dir).and_then(|step| {
+                step.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", dir.display()),
+                    )
+                })
+            }),
+            UndoStep::Moved { from, to } => move_path(from, to),
+            UndoStep::Trashed { from, .. } => trash_entry(from),
+            _ => return None,
+        })
+    }
+}
+
+/// Result of running a plan
+#[derive(Default, Debug)]
+pub struct TransferReport {
+    pub done: usize,
+    pub failed: Vec<(PathBuf, io::Error)>,
+    /// Successful steps, in execution order
+    pub steps: Vec<UndoStep>,
+}
+
+pub fn execute_plan(plan: &TransferPlan) -> TransferReport {
+    execute_plan_tracked(plan, false, &CopyProgress::default(), &|_| {})
+}
+
+/// `execute_plan` counting the bytes copied into `progress`. With
+/// `stop_on_failure` it stops at the first failure so the caller can roll
+/// back what was done or go on; the failed step is `plan.steps[report.done]`.
+/// `whole` hears the index of each step as soon as its target is complete -
+/// for a move across filesystems, before the source is deleted - so a
+/// journal can tell a finished step from one cut short (see recovery.rs)
+pub fn execute_plan_tracked(
+    plan: &TransferPlan,
+    stop_on_failure: bool,
+    progress: &CopyProgress,
+    whole: &dyn Fn(usize),
+) -> TransferReport {
+    let mut report = TransferReport::default();
+    // Copies that needn't wait for each other all run at once
+    if plan.kind == TransferKind::Copy && !stop_on_failure && writable().is_ok() {
+        let mut ready = Vec::new();
+        for (index, step) in plan.steps.iter().enumerate() {
+            match make_room(step, &mut report) {
+                Ok(()) => ready.push((index, step)),
+                Err(err) => record_step(&mut report, plan.kind, step, Err(err)),
+            }
+        }
+        let pairs: Vec<(PathBuf, PathBuf)> = ready
+            .iter()
+            .map(|(_, step)| (step.source.clone(), step.target.clone()))
+            .collect();
+        let free: Vec<bool> = pairs.iter().map(|(_, target)| is_free(target)).collect();
+        let results = copier::copy_entries(&pairs, progress);
+        for (((index, step), result), free) in ready.into_iter().zip(results).zip(free) {
+            let failed = result.is_err();
+            if !failed {
+                whole(index);
+            }
+            record_step(&mut report, plan.kind, step, result);
+            if failed && free {
+                keep_partial(&mut report, &step.source, &step.target);
+            }
+        }
+        return report;
+    }
+    for (index, step) in plan.steps.iter().enumerate() {
+        let result = writable().and_then(|()| make_room(step, &mut report));
+        let free = result.is_ok() && is_free(&step.target);
+        let whole = || whole(index);
+        let result = result.and_then(|()| match plan.kind {
+            TransferKind::Copy => copier::copy_entry(&step.source, &step.target, progress),
+            TransferKind::Move => move_entry_tracked(&step.source, &step.target, progress, &whole),
+            TransferKind::Symlink => vfs::require_local(&step.target)
+                .and_then(|()| make_symlink(&step.source, &step.target)),
+            TransferKind::Hardlink => vfs::require_local(&step.source)
+                .and_then(|()| vfs::require_local(&step.target))
+                .and_then(|()| std::fs::hard_link(&step.source, &step.target)),
+            TransferKind::Trash => step
+                .target
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| move_entry_tracked(&step.source, &step.target, progress, &whole)),
+        });
+        let failed = result.is_err();
+        // Moves said so themselves, before deleting their source
+        if !failed && !matches!(plan.kind, TransferKind::Move | TransferKind::Trash) {
+            whole();
+        }
+        record_step(&mut report, plan.kind, step, result);
+        if failed && free && plan.kind == TransferKind::Copy {
+            keep_partial(&mut report, &step.source, &step.target);
+        }
+        if failed && stop_on_failure {
+            break;
+        }
+    }
+    report
+}
+
+/// Trash the target a step replaces, noting it in `report` for undo
+fn make_room(step: &PlannedStep, report: &mut TransferReport) -> io::Result<()> {
+    if step.replace && vfs::exists(&step.target) {
+        report.steps.push(trash_entry(&step.target)?);
+    }
+    Ok(())
+}
+
+fn is_free(target: &Path) -> bool {
+    !vfs::exists(target)
+}
+
+/// What a failed copy left at a target that was free is its own: undo takes
+/// the partial tree away like a finished copy
+fn keep_partial(report: &mut TransferReport, source: &Path, target: &Path) {
+    if !is_free(target) {
+        report.steps.push(UndoStep::Copied {
+            source: source.to_path_buf(),
+            target: target.to_path_buf(),
+        });
+    }
+}
+
+fn record_step(
+    report: &mut TransferReport,
+    kind: TransferKind,
+    step: &PlannedStep,
+    result: io::Result<()>,
+) {
+    match result {
+        Ok(()) => {
+            debug!(
+                "{} {} -> {}",
+                kind.verb(),
+                step.source.display(),
+                step.target.display()
+            );
+            report.done += 1;
+            report.steps.push(match kind {
+                TransferKind::Copy => UndoStep::Copied {
+                    source: step.source.clone(),
+                    target: step.target.clone(),
+                },
+                TransferKind::Move => UndoStep::Moved {
+                    from: step.source.clone(),
+                    to: step.target.clone(),
+                },
+                TransferKind::Symlink | TransferKind::Hardlink => UndoStep::Linked {
+                    target: step.target.clone(),
+                },
+                TransferKind::Trash => UndoStep::Trashed {
+                    from: step.source.clone(),
+                    to: step.target.clone(),
+                },
+            });
+        }
+        Err(err) => {
+            warn!(
+                "Not {} {} -> {}: {}",
+                kind.verb(),
+                step.source.display(),
+                step.target.display(),
+                err
+            );
+            report.failed.push((step.source.clone(), err));
+        }
+    }
+}
+
+// =============================================================================
+// Sync
+// =============================================================================
+
+/// What it takes for one directory to mirror another
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    /// (source, target) pairs; a directory missing at the target is copied whole
+    pub copies: Vec<(PathBuf, PathBuf)>,
+    /// Entries at the target with no counterpart in the source
+    pub deletions: Vec<PathBuf>,
+}
+
+/// Dry-run phase of a one-way sync: new or changed entries of `source` go to `dest`
+pub fn plan_sync(source: &Path, dest: &Path, delete_extraneous: bool) -> io::Result<SyncPlan> {
+    let mut plan = SyncPlan::default();
+    if !vfs::backend(source)?.stat(source)?.is_dir {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a directory", source.display()),
+        ));
+    }
+    // Through links and `..`: a mirror inside its source would copy itself,
+    // and a source inside its mirror would be deleted as extraneous
+    let (resolved_source, resolved_dest) = (resolve_path(source)?, resolve_path(dest)?);
+    if resolved_dest.starts_with(&resolved_source) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot sync a directory into itself",
+        ));
+    }
+    if resolved_source.starts_with(&resolved_dest) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot sync a directory into one that holds it",
+        ));
+    }
+    plan_sync_dir(source, dest, delete_extraneous, &mut plan)?;
+    Ok(plan)
+}
+
+/// `path` with links and `..` resolved, as far as it exists; the missing rest
+/// is appended as it is
+fn resolve_path(path: &Path) -> io::Result<PathBuf> {
+    match path.canonicalize() {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                return Err(err);
+            };
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            Ok(resolve_path(parent)?.join(name))
+        }
+        resolved => resolved,
+    }
+}
+
+fn plan_sync_dir(
+    source: &Path,
+    dest: &Path,
+    delete_extraneous: bool,
+    plan: &mut SyncPlan,
+) -> io::Result<()> {
+    // Links are copied as links, not followed
+    let is_dir = |entry: &FileEntry| entry.is_dir && entry.link.is_none();
+    let dest_fs = vfs::backend(dest)?;
+    if !dest_fs.stat(dest).is_ok_and(|entry| is_dir(&entry)) {
+        plan.copies.push((source.to_path_buf(), dest.to_path_buf()));
+        return Ok(());
+    }
+
+    let mut names = HashSet::new();
+    for entry in vfs::backend(source)?.list(source)? {
+        // The name as it is on disk, even where it isn't valid UTF-8
+        let name = entry.path.file_name().unwrap_or(entry.name.as_ref());
+        let target = dest.join(name);
+        names.insert(name.to_os_string());
+
+        if is_dir(&entry) {
+            plan_sync_dir(&entry.path, &target, delete_extraneous, plan)?;
+            continue;
+        }
+        let changed = match dest_fs.stat(&target) {
+            Err(_) => true,
+            Ok(existing) => {
+                is_dir(&existing)
+                    || existing.size != entry.size
+                    || matches!(
+                        (entry.modified, existing.modified),
+                        (Some(s), Some(t)) if s > t
+                    )
+            }
+        };
+        if changed {
+            plan.copies.push((entry.path, target));
+        }
+    }
+
+    if delete_extraneous {
+        for entry in dest_fs.list(dest)? {
+            if !entry
+                .path
+                .file_name()
+                .is_some_and(|name| names.contains(name))
+            {
+                plan.deletions.push(entry.path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run a sync plan; replaced and deleted entries go to the trash
+pub fn execute_sync(plan: &SyncPlan) -> TransferReport {
+    let mut report = TransferReport::default();
+    // Make room for every copy first, then copy them all at once
+    let mut ready = Vec::new();
+    for (source, target) in &plan.copies {
+        let result = (|| {
+            writable()?;
+            if vfs::exists(target) {
+                report.steps.push(trash_entry(target)?);
+            }
+            if let Some(parent) = target.parent() {
+                vfs::backend(parent)?.make_dir(parent)?;
+            }
+            Ok(())
+        })();
+        match result {
+            Ok(()) => ready.push((source.clone(), target.clone())),
+            Err(err) => report.failed.push((source.clone(), err)),
+        }
+    }
+    // Every target is free now; the trashed ones are in the undo already
+    let results = copier::copy_entries(&ready, &CopyProgress::default());
+    for ((source, target), result) in ready.into_iter().zip(results) {
+        match result {
+            Ok(()) => {
+                report.done += 1;
+                report.steps.push(UndoStep::Copied { source, target });
+            }
+            Err(err) => {
+                keep_partial(&mut report, &source, &target);
+                report.failed.push((source, err));
+            }
+        }
+    }
+    for path in &plan.deletions {
+        match trash_entry(path) {
+            Ok(step) => {
+                report.done += 1;
+                report.steps.push(step);
+            }
+            Err(err) => report.failed.push((path.clone(), err)),
+        }
+    }
+    report
+}
+
+/// Delete an entry for good (no trash, no undo)
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    writable()?;
+    remove_entry(path)
+}
+
+/// Create a directory and any missing parents; None if it was already there.
+/// Undo removes only the directory itself
+pub fn make_dir(path: &Path) -> io::Result<Option<UndoStep>> {
+    writable()?;
+    let fs = vfs::backend(path)?;
+    if fs.stat(path).is_ok_and(|entry| entry.is_dir) {
+        return Ok(None);
+    }
+    fs.make_dir(path)?;
+    Ok(Some(UndoStep::Created {
+        dir: path.to_path_buf(),
+    }))
+}
+
+/// Copy a single entry to a path that's free
+fn copy_path(source: &Path, target: &Path) -> io::Result<UndoStep> {
+    writable()?;
+    if target.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    let free = is_free(target);
+    if let Err(err) = copier::copy_entry(source, target, &CopyProgress::default()) {
+        // Nothing to return a step for, so nothing is left half copied
+        if free {
+            let _ = remove_entry(target);
+        }
+        return Err(err);
+    }
+    Ok(UndoStep::Copied {
+        source: source.to_path_buf(),
+        target: target.to_path_buf(),
+    })
+}
+
+/// Move a single entry, e.g. to resolve a conflict by renaming
+pub fn move_path(source: &Path, target: &Path) -> io::Result<UndoStep> {
+    writable()?;
+    if target.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    move_entry(source, target)?;
+    Ok(UndoStep::Moved {
+        from: source.to_path_buf(),
+        to: target.to_path_buf(),
+    })
+}
+
+/// Give an entry a new name in the same directory; a case-only change is allowed
+/// even where the filesystem sees both names as the same entry
+pub fn rename_path(path: &Path, new_name: &str) -> io::Result<UndoStep> {
+    writable()?;
+    let target = path.with_file_name(new_name);
+    // Only where the filesystem sees both names as this entry; on one that
+    // tells case apart, `FOO` next to `foo` is another file
+    let same_entry =
+        file_name_of(path).to_lowercase() == new_name.to_lowercase() && is_same_file(path, &target);
+    if vfs::exists(&target) && !same_entry {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    vfs::backend(path)?.rename(path, &target)?;
+    carry_metadata(path, &target);
+    Ok(UndoStep::Moved {
+        from: path.to_path_buf(),
+        to: target,
+    })
+}
+
+/// Whether both paths name the same entry on disk
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (std::fs::symlink_metadata(a), std::fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Set the permission bits of an entry (following symlinks, like chmod)
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> io::Result<UndoStep> {
+    use std::os::unix::fs::PermissionsExt;
+
+    writable()?;
+    vfs::require_local(path)?;
+    let previous = std::fs::metadata(path)?.permissions().mode() & 0o7777;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(UndoStep::Chmod {
+        path: path.to_path_buf(),
+        mode: previous,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn set_mode(_path: &Path, _mode: u32) -> io::Result<UndoStep> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "permission bits are a Unix feature",
+    ))
+}
+
+/// Change the owner and/or group of an entry; `follow` changes a symlink's
+/// target instead of the link
+#[cfg(unix)]
+pub fn set_owner(
+    path: &Path,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    follow: bool,
+) -> io::Result<UndoStep> {
+    use std::os::unix::fs::MetadataExt;
+
+    writable()?;
+    vfs::require_local(path)?;
+    let metadata = if follow {
+        std::fs::metadata(path)?
+    } else {
+        std::fs::symlink_metadata(path)?
+    };
+    if follow {
+        std::os::unix::fs::chown(path, uid, gid)?;
+    } else {
+        std::os::unix::fs::lchown(path, uid, gid)?;
+    }
+    Ok(UndoStep::Chown {
+        path: path.to_path_buf(),
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        follow,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn set_owner(
+    _path: &Path,
+    _uid: Option<u32>,
+    _gid: Option<u32>,
+    _follow: bool,
+) -> io::Result<UndoStep> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "owners are a Unix feature",
+    ))
+}
+
+/// Set extended attribute `name`, or remove it with `value` None; symlinks
+/// get the attribute themselves, like `setfattr -h`
+#[cfg(unix)]
+pub fn set_xattr(path: &Path, name: &str, value: Option<&[u8]>) -> io::Result<UndoStep> {
+    writable()?;
+    vfs::require_local(path)?;
+    let previous = xattr::get(path, name)?;
+    match value {
+        Some(value) => xattr::set(path, name, value)?,
+        None if previous.is_none() => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no attribute {}", name),
+            ))
+        }
+        None => xattr::remove(path, name)?,
+    }
+    Ok(UndoStep::Xattr {
+        path: path.to_path_buf(),
+        name: name.to_string(),
+        value: previous,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn set_xattr(_path: &Path, _name: &str, _value: Option<&[u8]>) -> io::Result<UndoStep> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "extended attributes are a Unix feature",
+    ))
+}
+
+/// Delete an entry by moving it into a fresh folder of Felipe's trash, so undo can restore it
+pub fn trash_entry(path: &Path) -> io::Result<UndoStep> {
+    writable()?;
+    let bin = trash_bin()?;
+    std::fs::create_dir_all(&bin)?;
+    let to = bin.join(file_name_of(path));
+    move_entry(path, &to)?;
+    Ok(UndoStep::Trashed {
+        from: path.to_path_buf(),
+        to,
+    })
+}
+
+/// A folder of Felipe's trash nothing went into yet (it isn't created here).
+/// The clock alone can repeat - a coarse timer, two plans in one tick, two
+/// Felipes at once - so the process id and a count follow it
+fn trash_bin() -> io::Result<PathBuf> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let count = TRASH_BINS.fetch_add(1, Ordering::Relaxed);
+    Ok(crate::data_dir()
+        .ok_or_else(|| io::Error::other("no data directory for the trash"))?
+        .join("trash")
+        .join(format!("{}-{}-{}", stamp, std::process::id(), count)))
+}
+
+/// Symlink at `target` whose content is `source`, as given
+#[cfg(unix)]
+fn make_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+/// Windows has separate file and directory symlinks; the kind follows what
+/// `source` names, seen from the link's directory
+#[cfg(windows)]
+fn make_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    let resolved = target.parent().unwrap_or(Path::new("")).join(source);
+    if std::fs::metadata(resolved).is_ok_and(|m| m.is_dir()) {
+        std::os::windows::fs::symlink_dir(source, target)
+    } else {
+        std::os::windows::fs::symlink_file(source, target)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn make_symlink(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+fn remove_entry(path: &Path) -> io::Result<()> {
+    vfs::backend(path)?.delete(path)
+}
+
+fn move_entry(source: &Path, target: &Path) -> io::Result<()> {
+    move_entry_tracked(source, target, &CopyProgress::default(), &|| {})
+}
+
+/// `move_entry` counting the bytes it copies across filesystems; `whole` is
+/// called once the target is complete and before the source goes
+fn move_entry_tracked(
+    source: &Path,
+    target: &Path,
+    progress: &CopyProgress,
+    whole: &dyn Fn(),
+) -> io::Result<()> {
+    if source == target {
+        return Ok(());
+    }
+    if remote::location(source).is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "remote entries can be copied, not moved",
+        ));
+    }
+    // rename() fails across filesystems; only then fall back to copy +
+    // delete, any other failure (permissions, a busy target) stands
+    match vfs::backend(source)?.rename(source, target) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {}
+        result => {
+            return result.map(|()| {
+                whole();
+                carry_metadata(source, target);
+            })
+        }
+    }
+    let free = is_free(target);
+    if let Err(err) = copier::copy_entry(source, target, progress) {
+        // The source is still whole; what got across goes
+        if free {
+            let _ = remove_entry(target);
+        }
+        return Err(err);
+    }
+    whole();
+    remove_entry(source)?;
+    carry_metadata(source, target);
+    Ok(())
+}
+
+/// Tags, stars and notes follow an entry Felipe moved (metadata.rs)
+fn carry_metadata(from: &Path, to: &Path) {
+    if let Err(err) = metadata::moved(from, to) {
+        warn!("Cannot move the metadata of {}: {}", from.display(), err);
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn list_names(dir: &Path) -> Vec<String> {
+    vfs::backend(dir)
+        .and_then(|fs| fs.list(dir))
+        .map(|entries| entries.into_iter().map(|entry| entry.name).collect())
+        .unwrap_or_default()
+}
+
+/// Detect case-insensitivity without writing: an existing name with its case
+/// flipped either resolves (insensitive) or doesn't (sensitive)
+pub fn is_case_insensitive(dir: &Path, existing: &[String]) -> bool {
+    for name in existing {
+        let flipped: String = name
+            .chars()
+            .map(|c| {
+                if c.is_lowercase() {
+                    c.to_uppercase().next().unwrap_or(c)
+                } else {
+                    c.to_lowercase().next().unwrap_or(c)
+                }
+            })
+            .collect();
+        if flipped != *name && !existing.contains(&flipped) {
+            return vfs::exists(&dir.join(&flipped));
+        }
+    }
+    // Nothing to probe with - fall back to the platform default
+    cfg!(any(target_os = "windows", target_os = "macos"))
+}
+
+/// First name numbered after the rename pattern (`stem (n).ext` unless set)
+/// for which `is_taken` is false
+pub fn unique_name(name: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    let pattern = RENAME_PATTERN
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let pattern = if pattern.is_empty() {
+        DEFAULT_RENAME_PATTERN
+    } else {
+        pattern.as_str()
+    };
+    numbered_name(pattern, name, is_taken)
+}
+
+fn numbered_name(pattern: &str, name: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(name) {
+        return name.to_string();
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], &name[dot..]),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| fill_pattern(pattern, stem, ext, n))
+        .find(|candidate| !is_taken(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// `pattern` with its placeholders filled in one pass, so a name that
+/// itself holds `{n}` or `{ext}` stays as it is
+fn fill_pattern(pattern: &str, stem: &str, ext: &str, n: usize) -> String {
+    let number = n.to_string();
+    let mut filled = String::new();
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        let (part, len) = [("{name}", stem), ("{ext}", ext), ("{n}", number.as_str())]
+            .into_iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+            .map_or(
+                (&rest[..c.len_utf8()], c.len_utf8()),
+                |(placeholder, value)| (value, placeholder.len()),
+            );
+        filled.push_str(part);
+        rest = &rest[len..];
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn numbered_name_keeps_a_free_name() {
+        assert_eq!(
+            numbered_name(DEFAULT_RENAME_PATTERN, "a.txt", |_| false),
+            "a.txt"
+        );
+    }
+
+    #[test]
+    fn numbered_name_counts_up_to_the_first_free_number() {
+        let taken = ["a.txt", "a (1).txt", "a (2).txt"];
+        let name = numbered_name(DEFAULT_RENAME_PATTERN, "a.txt", |name| {
+            taken.contains(&name)
+        });
+        assert_eq!(name, "a (3).txt");
+    }
+
+    #[test]
+    fn numbered_name_splits_at_the_last_dot_only() {
+        let numbered = |name| numbered_name(DEFAULT_RENAME_PATTERN, name, |n| n == name);
+        assert_eq!(numbered("photos.tar.gz"), "photos.tar (1).gz");
+        assert_eq!(numbered(".bashrc"), ".bashrc (1)");
+        assert_eq!(numbered("Makefile"), "Makefile (1)");
+    }
+
+    #[test]
+    fn numbered_name_follows_the_pattern() {
+        let numbered = |pattern, name| numbered_name(pattern, name, |n| n == name);
+        assert_eq!(numbered("{name}_{n}{ext}", "a.txt"), "a_1.txt");
+        assert_eq!(numbered("{n} - {name}{ext}", "a.txt"), "1 - a.txt");
+        assert_eq!(numbered("{name}{ext}.{n}", "a.txt"), "a.txt.1");
+    }
+
+    #[test]
+    fn numbered_name_leaves_placeholders_inside_the_name_alone() {
+        let numbered = |name| numbered_name(DEFAULT_RENAME_PATTERN, name, |n| n == name);
+        assert_eq!(numbered("{n}.txt"), "{n} (1).txt");
+        assert_eq!(numbered("{ext}{name}"), "{ext}{name} (1)");
+    }
+
+    #[test]
+    fn rename_patterns_need_a_number_and_no_separator() {
+        assert!(check_rename_pattern(DEFAULT_RENAME_PATTERN).is_ok());
+        assert!(check_rename_pattern("{name}-{n}{ext}").is_ok());
+        assert!(check_rename_pattern("{name} copy{ext}").is_err());
+        assert!(check_rename_pattern("{n}/{name}{ext}").is_err());
+        assert!(check_rename_pattern("{name}\\{n}").is_err());
+    }
+
+    #[test]
+    fn plan_trash_puts_every_source_in_one_fresh_folder() {
+        let sources = [PathBuf::from("/x/a.txt"), PathBuf::from("/x/b")];
+        let plan = plan_trash(&sources).unwrap();
+        assert_eq!(plan.kind, TransferKind::Trash);
+        assert_eq!(plan.steps.len(), 2);
+        let bin = plan.steps[0].target.parent().unwrap();
+        assert_eq!(plan.steps[0].target, bin.join("a.txt"));
+        assert_eq!(plan.steps[1].target, bin.join("b"));
+        assert!(plan.steps.iter().all(|step| step.conflict.is_none()));
+        // Planning creates nothing
+        assert!(!bin.exists());
+    }
+
+    #[test]
+    fn plan_trash_never_hands_out_a_folder_twice() {
+        let sources = [PathBuf::from("/x/a.txt")];
+        let bins: HashSet<PathBuf> = (0..100)
+            .map(|_| plan_trash(&sources).unwrap().steps[0].target.clone())
+            .collect();
+        assert_eq!(bins.len(), 100);
+    }
+
+    #[test]
+    fn plan_sync_copies_new_and_changed_files_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, dest) = (dir.path().join("src"), dir.path().join("dst"));
+        touch(&source.join("same.txt"), "same");
+        touch(&source.join("changed.txt"), "new content");
+        touch(&source.join("new.txt"), "new");
+        touch(&source.join("sub/deep.txt"), "deep");
+        touch(&dest.join("same.txt"), "same");
+        touch(&dest.join("changed.txt"), "old");
+        touch(&dest.join("extra.txt"), "extra");
+
+        let plan = plan_sync(&source, &dest, false).unwrap();
+        let mut copies = plan.copies.clone();
+        copies.sort();
+        assert_eq!(
+            copies,
+            [
+                (source.join("changed.txt"), dest.join("changed.txt")),
+                (source.join("new.txt"), dest.join("new.txt")),
+                // Missing at the target, so copied whole
+                (source.join("sub"), dest.join("sub")),
+            ]
+        );
+        assert!(plan.deletions.is_empty());
+
+        let plan = plan_sync(&source, &dest, true).unwrap();
+        assert_eq!(plan.deletions, [dest.join("extra.txt")]);
+    }
+
+    #[test]
+    fn plan_sync_copies_everything_into_a_missing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, dest) = (dir.path().join("src"), dir.path().join("dst"));
+        touch(&source.join("a.txt"), "a");
+        let plan = plan_sync(&source, &dest, true).unwrap();
+        assert_eq!(plan.copies, [(source.clone(), dest.clone())]);
+        assert!(plan.deletions.is_empty());
+    }
+
+    #[test]
+    fn plan_sync_refuses_a_file_as_the_source() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(&dir.path().join("a.txt"), "a");
+        let err = plan_sync(&dir.path().join("a.txt"), &dir.path().join("b"), false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn plan_sync_refuses_directories_holding_each_other() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("src");
+        touch(&source.join("sub/a.txt"), "a");
+        let nested = [
+            (source.clone(), source.join("mirror")),
+            (source.clone(), source.join("sub/../mirror")),
+            (source.clone(), source.clone()),
+            (source.clone(), dir.path().to_path_buf()),
+        ];
+        for (from, to) in nested {
+            let err = plan_sync(&from, &to, true).unwrap_err();
+            assert_eq!(
+                err.kind(),
+                io::ErrorKind::InvalidInput,
+                "{:?} -> {:?}",
+                from,
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn execute_plan_notes_each_step_whose_target_is_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(&dir.path().join("a"), "a");
+        touch(&dir.path().join("c"), "c");
+        let out = dir.path().join("out");
+        std::fs::create_dir(&out).unwrap();
+        for kind in [TransferKind::Copy, TransferKind::Move] {
+            let steps = ["a", "missing", "c"]
+                .iter()
+                .map(|name| PlannedStep {
+                    source: dir.path().join(name),
+                    target: out.join(format!("{:?}-{}", kind, name)),
+                    conflict: None,
+                    replace: false,
+                })
+                .collect();
+            let plan = TransferPlan {
+                kind,
+                steps,
+                skipped: Vec::new(),
+            };
+            let noted = std::cell::RefCell::new(Vec::new());
+            let report = execute_plan_tracked(&plan, false, &CopyProgress::default(), &|step| {
+                noted.borrow_mut().push(step)
+            });
+            assert_eq!(report.failed.len(), 1);
+            assert_eq!(noted.into_inner(), [0, 2], "{:?}", kind);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn plan_sync_sees_through_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("src");
+        touch(&source.join("a.txt"), "a");
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&source, &link).unwrap();
+        assert!(plan_sync(&link, &source.join("mirror"), false).is_err());
+        assert!(plan_sync(&source, &link.join("mirror"), false).is_err());
+    }
+
+    #[test]
+    fn copies_and_syncs_reach_the_demo_tree_through_its_backend() {
+        let source = crate::demofs::demo_root().join("project");
+        let copy = crate::demofs::demo_root().join("project copy");
+        copier::copy_entry(&source, &copy, &CopyProgress::default()).unwrap();
+        assert!(vfs::exists(&copy.join("src/main.rs")));
+        assert!(!copy.exists());
+
+        let plan = plan_sync(&source, &copy, true).unwrap();
+        assert!(plan.copies.is_empty() && plan.deletions.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn what_only_a_disk_has_is_refused_in_the_demo_tree() {
+        let notes = crate::demofs::demo_root().join("notes.txt");
+        let err = set_mode(&notes, 0o600).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,110 @@
+//! Overwrite - what a paste does about names already taken where it lands
+//!
+//! Before a copy or move replaces anything, each clash is asked about in turn:
+//!
+//! ```text
+//! o  overwrite       the entry there goes to the trash, u brings it back
+//! s  skip            leave this one out
+//! r  rename          paste it as `name (1).ext`
+//! n  if newer        overwrite only if the incoming entry was modified later
+//! ```
+//!
+//! The capital letter settles this clash and all the rest the same way; Esc
+//! drops the whole paste. What's skipped is listed in the job summary.
+//!
+//! `:set autorename` (or `autorename = true` under `[operations]`) doesn't ask:
+//! clashing entries are numbered like `r` does, after `rename_pattern` -
+//! `{name} ({n}){ext}` unless the config says otherwise. Either way the status
+//! line says what they were pasted as.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::locale::trf;
+use crate::ops::{self, Overwrite, TransferPlan, TransferReport};
+
+pub struct OverwritePlugin;
+
+impl Plugin for OverwritePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, follow_rename_pattern);
+    }
+}
+
+/// Number names after the config's pattern, also once it's reloaded
+fn follow_rename_pattern(config: Res<Config>) {
+    if config.is_changed() {
+        ops::set_rename_pattern(&config.operations.rename_pattern);
+    }
+}
+
+/// `, as a (1).txt, b (1).txt` for the entries of a transfer that got
+/// another name, or nothing
+pub fn renamed_note(plan: &TransferPlan, report: &TransferReport) -> String {
+    let renamed = plan.renamed(report);
+    if renamed.is_empty() {
+        return String::new();
+    }
+    let names: Vec<&str> = renamed.iter().map(|(_, name)| name.as_str()).collect();
+    format!(", as {}", names.join(", "))
+}
+
+/// A transfer waiting for the clashes of its targets to be settled
+pub struct OverwriteRequest {
+    plan: TransferPlan,
+    /// (source, target) of every step whose target exists, in plan order
+    clashes: Vec<(PathBuf, PathBuf)>,
+    /// Choices made so far, one per clash
+    choices: Vec<Overwrite>,
+}
+
+impl OverwriteRequest {
+    /// `plan` back when nothing in it would be overwritten
+    pub fn new(plan: TransferPlan) -> Result<Self, TransferPlan> {
+        let clashes: Vec<(PathBuf, PathBuf)> = plan
+            .existing_targets()
+            .map(|step| (step.source.clone(), step.target.clone()))
+            .collect();
+        if clashes.is_empty() {
+            return Err(plan);
+        }
+        Ok(OverwriteRequest {
+            plan,
+            clashes,
+            choices: Vec::new(),
+        })
+    }
+
+    pub fn question(&self) -> String {
+        let at = self.choices.len();
+        let (source, target) = &self.clashes[at];
+        let name = target.file_name().unwrap_or_default().to_string_lossy();
+        let place = format!("{}/{}", at + 1, self.clashes.len());
+        let clash = if ops::is_newer(target, source) {
+            trf("{} is already here, newer ({})", &[&name, &place])
+        } else {
+            trf("{} is already here ({})", &[&name, &place])
+        };
+        trf(
+            "{} - o:overwrite  s:skip  r:rename  n:if newer  O/S/R/N:all  Esc:cancel",
+            &[&clash],
+        )
+    }
+
+    /// Settle the clash asked about, or it and all the rest; the plan to
+    /// queue once none is left
+    pub fn answer(mut self, choice: Overwrite, all: bool) -> Result<TransferPlan, Self> {
+        let left = if all {
+            self.clashes.len() - self.choices.len()
+        } else {
+            1
+        };
+        self.choices.extend(std::iter::repeat_n(choice, left));
+        if self.choices.len() < self.clashes.len() {
+            return Err(self);
+        }
+        self.plan.resolve_overwrites(&self.choices);
+        Ok(self.plan)
+    }
+}
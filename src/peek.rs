@@ -0,0 +1,177 @@
+//! Peek - a glance inside the selected directory without going in
+//!
+//! Once the cursor rests on a directory, its first entries (in the listing's
+//! order, dotfiles as `:set hidden` says) stand on top of it as miniature
+//! books, a shelf's worth at most: directories low and dark, files orange
+//! and as tall as their size makes them. They're listed on a worker thread
+//! when the cursor stops, so scrolling past directories reads nothing.
+//! `:set nopreview` turns them off along with the other previews; remote
+//! directories aren't peeked into.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+use crate::config::Config;
+use crate::debug;
+use crate::layout::Layouts;
+use crate::sort::Sorting;
+use crate::{
+    entry_height, vfs, CurrentDirectory, EntryPalette, FileEntry, FELIPE_GRID, FELIPE_ORANGE_DIM,
+};
+
+/// Entries shown, in rows of `PEEK_COLUMNS`
+const PEEK_ENTRIES: usize = 9;
+const PEEK_COLUMNS: usize = 3;
+/// Size of a miniature against a book
+const MINI_SCALE: f32 = 0.3;
+/// Gap between the directory's top and its miniatures
+const PEEK_GAP: f32 = 0.1;
+/// Seconds the cursor rests on a directory before it's listed
+const PEEK_DELAY: f32 = 0.25;
+
+/// The directory being peeked into and what's in it
+#[derive(Resource, Default)]
+struct Peek {
+    dir: Option<PathBuf>,
+    /// When the cursor got to `dir`
+    since: f32,
+    worker: Option<JoinHandle<Vec<FileEntry>>>,
+    entries: Option<Vec<FileEntry>>,
+    spawned: bool,
+}
+
+/// Marker for a miniature on top of the selected directory
+#[derive(Component)]
+struct PeekMini;
+
+pub struct PeekPlugin;
+
+impl Plugin for PeekPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Peek>()
+            .add_systems(Update, (follow_selection, spawn_minis).chain());
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Start over when the cursor moves, and list the directory once it rests
+fn follow_selection(
+    mut commands: Commands,
+    time: Res<Time>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    sorting: Res<Sorting>,
+    layouts: Res<Layouts>,
+    mut peek: ResMut<Peek>,
+    mini_query: Query<Entity, With<PeekMini>>,
+) {
+    let selected = current_dir
+        .entries
+        .get(current_dir.selected_index)
+        .filter(|entry| entry.is_dir && entry.name != ".." && config.preview.enabled)
+        .map(|entry| entry.path.clone());
+    if selected != peek.dir {
+        for mini in mini_query.iter() {
+            commands.entity(mini).despawn();
+        }
+        *peek = Peek {
+            dir: selected,
+            since: time.elapsed_seconds(),
+            ..default()
+        };
+        return;
+    }
+    // The directory moved with the layout; put its miniatures back on it
+    if layouts.is_changed() && peek.spawned {
+        for mini in mini_query.iter() {
+            commands.entity(mini).despawn();
+        }
+        peek.spawned = false;
+    }
+    let Some(dir) = peek.dir.clone() else {
+        return;
+    };
+    if peek.entries.is_some()
+        || peek.worker.is_some()
+        || time.elapsed_seconds() - peek.since < PEEK_DELAY
+    {
+        return;
+    }
+    let hidden = config.listing.hidden;
+    let order = sorting.active().clone();
+    peek.worker = Some(std::thread::spawn(move || {
+        debug::timed("peek", || {
+            let Ok(mut entries) = vfs::backend(&dir).and_then(|fs| fs.list(&dir)) else {
+                return Vec::new();
+            };
+            entries.retain(|entry| hidden || !entry.is_hidden());
+            order.sort(&mut entries);
+            entries.truncate(PEEK_ENTRIES);
+            entries
+        })
+    }));
+}
+
+/// Stand the listed entries on top of the selected directory
+fn spawn_minis(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    current_dir: Res<CurrentDirectory>,
+    layouts: Res<Layouts>,
+    mut peek: ResMut<Peek>,
+) {
+    if peek
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        let worker = peek.worker.take().expect("checked above");
+        peek.entries = Some(worker.join().unwrap_or_default());
+    }
+    if peek.spawned {
+        return;
+    }
+    let Some(entries) = &peek.entries else {
+        return;
+    };
+    let Some(dir) = current_dir.entries.get(current_dir.selected_index) else {
+        return;
+    };
+    let place = layouts
+        .active()
+        .transform(current_dir.selected_index, current_dir.entries.len());
+    let top = entry_height(dir) + PEEK_GAP;
+    let mesh = palette.cuboid(&mut meshes);
+    // Columns across the book's width, rows front to back
+    let (step_x, step_z) = (0.8 * MINI_SCALE * 1.2, 0.3 * MINI_SCALE * 1.6);
+    let rows = entries.len().div_ceil(PEEK_COLUMNS);
+    for (i, entry) in entries.iter().enumerate() {
+        let (column, row) = (i % PEEK_COLUMNS, i / PEEK_COLUMNS);
+        let x = (column as f32 - (PEEK_COLUMNS - 1) as f32 / 2.0) * step_x;
+        let z = (row as f32 - (rows.max(1) - 1) as f32 / 2.0) * step_z;
+        let height = entry_height(entry) * MINI_SCALE;
+        let color = if entry.is_dir {
+            FELIPE_GRID
+        } else {
+            FELIPE_ORANGE_DIM
+        };
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: palette.material(&mut materials, color.to_linear()),
+                transform: place
+                    * Transform::from_xyz(x, top + height / 2.0, z)
+                        .with_scale(Vec3::new(MINI_SCALE, height, MINI_SCALE)),
+                ..default()
+            },
+            PeekMini,
+        ));
+    }
+    peek.spawned = true;
+}
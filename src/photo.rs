@@ -0,0 +1,144 @@
+//! Photo metadata - what the camera wrote into the file
+//!
+//! JPEG, TIFF, HEIF, PNG and WebP files (and TIFF-based raws) can carry EXIF
+//! data: the camera and lens, the picture's size, the exposure, when it was
+//! taken and where. The properties panel lists whatever the selected photo
+//! has, and the `taken` sort key orders a photo dump by the moment each
+//! picture was shot rather than when it was copied off the card.
+
+use exif::{Exif, In, Reader, Tag, Value};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Extensions worth looking into; other files are never opened
+const PHOTO_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "jpe", "tif", "tiff", "heic", "heif", "avif", "png", "webp", "dng", "nef",
+    "nrw", "arw", "cr2", "orf", "rw2", "pef", "srw",
+];
+
+/// EXIF of `path` if it's a photo that has any
+fn read(path: &Path) -> Option<Exif> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    if !PHOTO_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let file = File::open(path).ok()?;
+    Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()
+}
+
+/// When the photo was taken, as `YYYY-MM-DD HH:MM:SS` so it sorts as text
+pub fn taken(path: &Path) -> Option<String> {
+    date_taken(&read(path)?)
+}
+
+/// Labelled EXIF fields of `path`, empty for anything that isn't a photo
+pub fn properties(path: &Path) -> Vec<(&'static str, String)> {
+    let Some(exif) = read(path) else {
+        return Vec::new();
+    };
+    [
+        ("camera", camera(&exif)),
+        ("lens", text(&exif, Tag::LensModel)),
+        ("pixels", pixels(&exif)),
+        ("exposure", exposure(&exif)),
+        ("taken", date_taken(&exif)),
+        ("gps", gps(&exif)),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| Some((label, value?)))
+    .collect()
+}
+
+/// A text field, trimmed of the padding some cameras leave in
+fn text(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Ascii(ref values) = field.value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(values.first()?);
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Make and model, without the make twice when the model repeats it
+fn camera(exif: &Exif) -> Option<String> {
+    let model = text(exif, Tag::Model);
+    match (text(exif, Tag::Make), model) {
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    }
+}
+
+fn pixels(exif: &Exif) -> Option<String> {
+    let dimension = |tags: [Tag; 2]| {
+        tags.into_iter().find_map(|tag| {
+            exif.get_field(tag, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+    };
+    let width = dimension([Tag::PixelXDimension, Tag::ImageWidth])?;
+    let height = dimension([Tag::PixelYDimension, Tag::ImageLength])?;
+    let megapixels = width as f64 * height as f64 / 1e6;
+    Some(format!("{} x {} ({:.1} MP)", width, height, megapixels))
+}
+
+/// Shutter, aperture and ISO, as far as they were recorded
+fn exposure(exif: &Exif) -> Option<String> {
+    let shown = |tag: Tag| {
+        exif.get_field(tag, In::PRIMARY)
+            .map(|field| field.display_value().with_unit(exif).to_string())
+    };
+    let parts: Vec<String> = [
+        shown(Tag::ExposureTime),
+        shown(Tag::FNumber),
+        shown(Tag::PhotographicSensitivity).map(|iso| format!("ISO {}", iso)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!parts.is_empty()).then(|| parts.join("  "))
+}
+
+fn date_taken(exif: &Exif) -> Option<String> {
+    [Tag::DateTimeOriginal, Tag::DateTime]
+        .into_iter()
+        .find_map(|tag| {
+            let field = exif.get_field(tag, In::PRIMARY)?;
+            let Value::Ascii(ref values) = field.value else {
+                return None;
+            };
+            let date = exif::DateTime::from_ascii(values.first()?).ok()?;
+            Some(format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                date.year, date.month, date.day, date.hour, date.minute, date.second
+            ))
+        })
+}
+
+/// Latitude and longitude in decimal degrees
+fn gps(exif: &Exif) -> Option<String> {
+    let degrees = |tag: Tag, reference: Tag, negative: &str| {
+        let field = exif.get_field(tag, In::PRIMARY)?;
+        let Value::Rational(ref parts) = field.value else {
+            return None;
+        };
+        let value = parts
+            .iter()
+            .zip([1.0, 60.0, 3600.0])
+            .map(|(part, unit)| part.to_f64() / unit)
+            .sum::<f64>();
+        let sign = if text(exif, reference).as_deref() == Some(negative) {
+            -1.0
+        } else {
+            1.0
+        };
+        Some(sign * value)
+    };
+    let latitude = degrees(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?;
+    let longitude = degrees(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?;
+    Some(format!("{:.5}, {:.5}", latitude, longitude))
+}
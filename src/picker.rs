@@ -0,0 +1,159 @@
+//! Picker mode - `felipe --choose-file` / `felipe --choose-dir`
+//!
+//! Felipe as a file dialog for shell scripts: browse as usual, confirm, and
+//! the chosen paths are printed to stdout, one per line, once the window has
+//! closed. The exit status is 0 when something was chosen and 1 when Felipe
+//! was quit without choosing:
+//!
+//! ```text
+//! file=$(felipe --choose-file ~/Downloads) && mpv "$file"
+//! cd "$(felipe --choose-dir)"
+//! ```
+//!
+//! With `--choose-file`, Enter on a file chooses it instead of opening it.
+//! With `--choose-dir`, Enter still walks into directories and `:choose`
+//! picks the one you're in. In both, Enter in visual mode chooses every entry
+//! of the range that fits, and `:choose` takes the selected file or the
+//! current directory.
+
+use bevy::prelude::*;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::command::{Command, RunCommand};
+use crate::{CurrentDirectory, FileEntry, StatusMessage};
+
+/// What the picker hands back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickMode {
+    File,
+    Dir,
+}
+
+impl PickMode {
+    fn fits(self, entry: &FileEntry) -> bool {
+        entry.is_dir == (self == PickMode::Dir)
+    }
+
+    fn noun(self) -> &'static str {
+        match self {
+            PickMode::File => "file",
+            PickMode::Dir => "directory",
+        }
+    }
+}
+
+/// Picker state; `mode` is None when Felipe runs as a file manager
+#[derive(Resource, Default)]
+pub struct Picker {
+    pub mode: Option<PickMode>,
+    /// Set once the user confirmed; Felipe closes on the next frame
+    chosen: Option<Vec<PathBuf>>,
+}
+
+/// Paths chosen before the app closed, printed by `finish`
+static CHOSEN: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+impl Picker {
+    pub fn new(mode: Option<PickMode>) -> Self {
+        Self { mode, chosen: None }
+    }
+
+    /// Whether Enter on `entry` chooses rather than opens it
+    pub fn picks(&self, entry: &FileEntry) -> bool {
+        self.mode.is_some() && !entry.is_dir
+    }
+
+    /// Choose the entries of `range` that fit the mode
+    pub fn choose_range(
+        &mut self,
+        current_dir: &CurrentDirectory,
+        range: RangeInclusive<usize>,
+        status: &mut StatusMessage,
+    ) {
+        let Some(mode) = self.mode else {
+            return;
+        };
+        let paths: Vec<PathBuf> = current_dir
+            .entries
+            .get(range)
+            .unwrap_or_default()
+            .iter()
+            .filter(|entry| mode.fits(entry))
+            .map(|entry| entry.path.clone())
+            .collect();
+        if paths.is_empty() {
+            status.0 = match mode {
+                PickMode::File => "Choose a file: Enter on a file, :q to cancel".to_string(),
+                PickMode::Dir => "Choose a directory: :choose takes this one".to_string(),
+            };
+            return;
+        }
+        self.chosen = Some(paths);
+    }
+}
+
+pub struct PickerPlugin;
+
+impl Plugin for PickerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, announce_picker)
+            .add_systems(Update, (handle_choose_command, close_when_chosen).chain());
+    }
+}
+
+fn announce_picker(picker: Res<Picker>, mut status: ResMut<StatusMessage>) {
+    status.0 = match picker.mode {
+        Some(PickMode::File) => "Choose a file: Enter chooses, v+Enter several, :q cancels",
+        Some(PickMode::Dir) => "Choose a directory: :choose takes the one you're in, :q cancels",
+        None => return,
+    }
+    .to_string();
+}
+
+/// `:choose` - the selected file, or the directory you're in
+fn handle_choose_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut picker: ResMut<Picker>,
+    current_dir: Res<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Choose {
+            continue;
+        }
+        match picker.mode {
+            Some(PickMode::File) => {
+                let index = current_dir.selected_index;
+                picker.choose_range(&current_dir, index..=index, &mut status);
+            }
+            Some(PickMode::Dir) => picker.chosen = Some(vec![current_dir.path.clone()]),
+            None => status.0 = "Not picking: start with --choose-file or --choose-dir".to_string(),
+        }
+    }
+}
+
+fn close_when_chosen(mut picker: ResMut<Picker>, mut exit: EventWriter<AppExit>) {
+    let Some(paths) = picker.chosen.take() else {
+        return;
+    };
+    let _ = CHOSEN.set(paths);
+    exit.send(AppExit::Success);
+}
+
+/// Print what was chosen; the exit status for picker mode
+pub fn finish(mode: PickMode) -> i32 {
+    match CHOSEN.get() {
+        Some(paths) => {
+            for path in paths {
+                println!("{}", path.display());
+            }
+            0
+        }
+        None => {
+            eprintln!("felipe: no {} chosen", mode.noun());
+            1
+        }
+    }
+}
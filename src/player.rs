@@ -0,0 +1,301 @@
+//! Audio preview - Shift-Space plays the selected sound file
+//!
+//! Shift-Space on an mp3, flac, ogg or wav file plays it; again on it pauses
+//! and resumes, and on another sound file switches to it, so a samples
+//! directory can be triaged with j/k, Shift-Space, and Space to mark the
+//! keepers (Space alone marks, sound files like any other, see basket.rs). A line above the status line
+//! shows what's playing and how far along it is. Playback stops at the end of
+//! the file.
+//!
+//! Like the sound effects, playback needs the `audio` cargo feature (and ALSA
+//! on Linux); without it Shift-Space says so.
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::{CurrentDirectory, Prompt, StatusMessage, UiElement, VimMode, FELIPE_ORANGE};
+
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "oga", "wav"];
+/// Cells in the progress bar
+const BAR_WIDTH: usize = 24;
+
+/// Whether `path` is a file Shift-Space can play
+pub fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// What Shift-Space asked for
+#[derive(Event)]
+enum PlayerAction {
+    Play(PathBuf),
+    TogglePause,
+}
+
+/// The file being played, if any
+#[derive(Resource, Default)]
+pub struct NowPlaying {
+    track: Option<Track>,
+}
+
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+struct Track {
+    path: PathBuf,
+    /// Entity of the audio source, despawned when the file ends
+    entity: Entity,
+    /// Time played so far, not counting pauses
+    elapsed: Duration,
+    /// Length of the file, when its format tells
+    length: Option<Duration>,
+    paused: bool,
+}
+
+/// Marker for the now-playing line
+#[derive(Component)]
+struct PlayerLine;
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NowPlaying>()
+            .add_event::<PlayerAction>()
+            .add_systems(Startup, spawn_player_line)
+            .add_systems(
+                Update,
+                (
+                    handle_play_key,
+                    #[cfg(feature = "audio")]
+                    playback::handle_player_actions,
+                    #[cfg(feature = "audio")]
+                    playback::track_progress,
+                    #[cfg(not(feature = "audio"))]
+                    report_no_audio,
+                    update_player_line,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn spawn_player_line(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 18.0,
+                    color: FELIPE_ORANGE,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(58.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+        PlayerLine,
+        UiElement,
+    ));
+}
+
+/// Shift-Space plays the selected sound file, or pauses the one playing
+fn handle_play_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    current_dir: Res<CurrentDirectory>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    now_playing: Res<NowPlaying>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    if !keyboard.just_pressed(KeyCode::Space)
+        || !keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || focus.any_open()
+        || fly.enabled
+    {
+        return;
+    }
+    let selected = current_dir
+        .entries
+        .get(current_dir.selected_index)
+        .filter(|entry| !entry.is_dir && is_audio(&entry.path));
+    let playing = now_playing.track.as_ref().map(|track| &track.path);
+    match selected {
+        Some(entry) if playing != Some(&entry.path) => {
+            actions.send(PlayerAction::Play(entry.path.clone()));
+        }
+        Some(_) => {
+            actions.send(PlayerAction::TogglePause);
+        }
+        None => {}
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+fn report_no_audio(mut actions: EventReader<PlayerAction>, mut status: ResMut<StatusMessage>) {
+    for action in actions.read() {
+        if let PlayerAction::Play(path) = action {
+            status.0 = format!(
+                "Cannot play {}: Felipe was built without --features audio",
+                path.display()
+            );
+        }
+    }
+}
+
+fn update_player_line(
+    now_playing: Res<NowPlaying>,
+    mut text_query: Query<&mut Text, With<PlayerLine>>,
+) {
+    if !now_playing.is_changed() {
+        return;
+    }
+    let line = match &now_playing.track {
+        Some(track) => {
+            let name = track
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let position = match track.length {
+                Some(length) => {
+                    let done = track.elapsed.as_secs_f32() / length.as_secs_f32().max(0.001);
+                    let filled = ((done * BAR_WIDTH as f32) as usize).min(BAR_WIDTH);
+                    format!(
+                        "[{}{}] {} / {}",
+                        "#".repeat(filled),
+                        "-".repeat(BAR_WIDTH - filled),
+                        clock(track.elapsed),
+                        clock(length)
+                    )
+                }
+                None => clock(track.elapsed),
+            };
+            let state = if track.paused {
+                "paused  Shift-Space:resume"
+            } else {
+                "Shift-Space:pause"
+            };
+            format!("♪ {}  {}  {}", name, position, state)
+        }
+        None => String::new(),
+    };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value.clone_from(&line);
+    }
+}
+
+/// `m:ss`
+fn clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(feature = "audio")]
+mod playback {
+    use bevy::audio::{Source, Volume};
+    use bevy::prelude::*;
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{NowPlaying, PlayerAction, Track};
+    use crate::config::Config;
+    use crate::StatusMessage;
+
+    pub(super) fn handle_player_actions(
+        mut commands: Commands,
+        mut actions: EventReader<PlayerAction>,
+        mut now_playing: ResMut<NowPlaying>,
+        mut sources: ResMut<Assets<AudioSource>>,
+        sinks: Query<&AudioSink>,
+        config: Res<Config>,
+        mut status: ResMut<StatusMessage>,
+    ) {
+        for action in actions.read() {
+            match action {
+                PlayerAction::Play(path) => {
+                    // The old source may have ended and gone already
+                    let previous = now_playing.track.take();
+                    if let Some(mut entity) = previous.and_then(|t| commands.get_entity(t.entity)) {
+                        entity.despawn();
+                    }
+                    let bytes: Arc<[u8]> = match std::fs::read(path) {
+                        Ok(bytes) => bytes.into(),
+                        Err(err) => {
+                            status.0 = format!("Cannot read {}: {}", path.display(), err);
+                            continue;
+                        }
+                    };
+                    // Bevy panics on files it can't decode, so try first
+                    let length = match rodio::Decoder::new(Cursor::new(bytes.clone())) {
+                        Ok(decoder) => decoder.total_duration(),
+                        Err(err) => {
+                            status.0 = format!("Cannot play {}: {}", path.display(), err);
+                            continue;
+                        }
+                    };
+                    let source = sources.add(AudioSource { bytes });
+                    let entity = commands
+                        .spawn(AudioBundle {
+                            source,
+                            settings: PlaybackSettings::DESPAWN
+                                .with_volume(Volume::new(config.sound.volume.clamp(0.0, 1.0))),
+                        })
+                        .id();
+                    now_playing.track = Some(Track {
+                        path: path.clone(),
+                        entity,
+                        elapsed: Duration::ZERO,
+                        length,
+                        paused: false,
+                    });
+                }
+                PlayerAction::TogglePause => {
+                    let Some(track) = &mut now_playing.track else {
+                        continue;
+                    };
+                    // The sink appears a frame after the source; until then
+                    // there's nothing to pause
+                    if let Ok(sink) = sinks.get(track.entity) {
+                        sink.toggle();
+                        track.paused = sink.is_paused();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Count the time played, and forget the track once its source is gone
+    pub(super) fn track_progress(
+        time: Res<Time>,
+        mut now_playing: ResMut<NowPlaying>,
+        sources: Query<(), With<Handle<AudioSource>>>,
+    ) {
+        let Some(track) = &now_playing.track else {
+            return;
+        };
+        if !sources.contains(track.entity) {
+            now_playing.track = None;
+        } else if !track.paused {
+            let track = now_playing.track.as_mut().expect("checked above");
+            let elapsed = track.elapsed + time.delta();
+            track.elapsed = track.length.map_or(elapsed, |length| elapsed.min(length));
+        }
+    }
+}
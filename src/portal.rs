@@ -0,0 +1,247 @@
+//! File chooser portal - Felipe as the desktop's open/save dialog (Linux,
+//! `--features portal`)
+//!
+//! `felipe --portal` serves `org.freedesktop.impl.portal.FileChooser` on the
+//! session bus. Apps that ask xdg-desktop-portal for a file (Flatpaks, and
+//! GTK or Qt apps set up to use portals) then get a Felipe window in picker
+//! mode (see picker.rs): Enter or `:choose` picks, `q` cancels. Saving picks
+//! the folder and keeps the name the app suggested.
+//!
+//! To install, tell the portal frontend about the backend, in
+//! `/usr/share/xdg-desktop-portal/portals/felipe.portal`:
+//!
+//! ```ini
+//! [portal]
+//! DBusName=org.freedesktop.impl.portal.desktop.felipe
+//! Interfaces=org.freedesktop.impl.portal.FileChooser
+//! ```
+//!
+//! let D-Bus start it, in
+//! `~/.local/share/dbus-1/services/org.freedesktop.impl.portal.desktop.felipe.service`:
+//!
+//! ```ini
+//! [D-BUS Service]
+//! Name=org.freedesktop.impl.portal.desktop.felipe
+//! Exec=/usr/local/bin/felipe --portal
+//! ```
+//!
+//! and pick it for file dialogs in `~/.config/xdg-desktop-portal/portals.conf`:
+//!
+//! ```ini
+//! [preferred]
+//! org.freedesktop.impl.portal.FileChooser=felipe
+//! ```
+//!
+//! One dialog is served at a time; a second request waits for the first.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command as Process, Stdio};
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+const BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.felipe";
+const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+
+/// Portal response codes
+const RESPONSE_SUCCESS: u32 = 0;
+const RESPONSE_CANCELLED: u32 = 1;
+const RESPONSE_OTHER: u32 = 2;
+
+type Options<'a> = HashMap<&'a str, Value<'a>>;
+type Results = HashMap<String, OwnedValue>;
+
+struct FileChooser;
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.FileChooser")]
+impl FileChooser {
+    #[zbus(out_args("response", "results"))]
+    fn open_file(
+        &self,
+        _handle: ObjectPath<'_>,
+        _app_id: &str,
+        _parent_window: &str,
+        _title: &str,
+        options: Options<'_>,
+    ) -> (u32, Results) {
+        let directory = flag(&options, "directory");
+        let start = path_option(&options, "current_folder");
+        match choose(directory, start.as_deref()) {
+            Ok(Some(mut paths)) => {
+                if !flag(&options, "multiple") {
+                    paths.truncate(1);
+                }
+                reply(&paths)
+            }
+            Ok(None) => (RESPONSE_CANCELLED, Results::new()),
+            Err(err) => failed(&err),
+        }
+    }
+
+    #[zbus(out_args("response", "results"))]
+    fn save_file(
+        &self,
+        _handle: ObjectPath<'_>,
+        _app_id: &str,
+        _parent_window: &str,
+        _title: &str,
+        options: Options<'_>,
+    ) -> (u32, Results) {
+        let current_file = path_option(&options, "current_file");
+        let name = match &options.get("current_name") {
+            Some(Value::Str(name)) => name.as_str().to_string(),
+            _ => current_file
+                .as_deref()
+                .and_then(Path::file_name)
+                .map_or("untitled".to_string(), |name| {
+                    name.to_string_lossy().to_string()
+                }),
+        };
+        let start = path_option(&options, "current_folder").or_else(|| {
+            current_file
+                .as_deref()
+                .and_then(Path::parent)
+                .map(Path::to_path_buf)
+        });
+        match choose(true, start.as_deref()) {
+            Ok(Some(dirs)) => reply(&[dirs[0].join(name)]),
+            Ok(None) => (RESPONSE_CANCELLED, Results::new()),
+            Err(err) => failed(&err),
+        }
+    }
+
+    #[zbus(out_args("response", "results"))]
+    fn save_files(
+        &self,
+        _handle: ObjectPath<'_>,
+        _app_id: &str,
+        _parent_window: &str,
+        _title: &str,
+        options: Options<'_>,
+    ) -> (u32, Results) {
+        let files: Vec<PathBuf> = match options.get("files") {
+            Some(Value::Array(files)) => files.iter().filter_map(bytes_path).collect(),
+            _ => Vec::new(),
+        };
+        let start = path_option(&options, "current_folder");
+        match choose(true, start.as_deref()) {
+            Ok(Some(dirs)) => {
+                let paths: Vec<PathBuf> = files
+                    .iter()
+                    .filter_map(|file| Some(dirs[0].join(file.file_name()?)))
+                    .collect();
+                reply(&paths)
+            }
+            Ok(None) => (RESPONSE_CANCELLED, Results::new()),
+            Err(err) => failed(&err),
+        }
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        4
+    }
+}
+
+/// Serve the portal until killed; the exit code if it can't start
+pub fn serve() -> i32 {
+    let connection = zbus::blocking::connection::Builder::session()
+        .and_then(|builder| builder.name(BUS_NAME))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, FileChooser))
+        .and_then(|builder| builder.build());
+    match connection {
+        // Requests are served on the connection's own thread
+        Ok(_connection) => loop {
+            std::thread::park();
+        },
+        Err(err) => {
+            eprintln!("felipe: cannot serve the file chooser portal: {}", err);
+            1
+        }
+    }
+}
+
+/// Run Felipe in picker mode; None when nothing was picked
+fn choose(directory: bool, start: Option<&Path>) -> Result<Option<Vec<PathBuf>>, String> {
+    let exe = std::env::current_exe().map_err(|err| err.to_string())?;
+    let mut picker = Process::new(exe);
+    picker.arg(if directory {
+        "--choose-dir"
+    } else {
+        "--choose-file"
+    });
+    if let Some(start) = start.filter(|start| start.is_dir()) {
+        picker.arg(start);
+    }
+    let output = picker
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| err.to_string())?;
+    // picker::finish exits 1 when nothing was chosen
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let paths: Vec<PathBuf> = output
+        .stdout
+        .split(|&byte| byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| PathBuf::from(OsStr::from_bytes(line)))
+        .collect();
+    Ok((!paths.is_empty()).then_some(paths))
+}
+
+fn reply(paths: &[PathBuf]) -> (u32, Results) {
+    let uris: Vec<String> = paths.iter().map(|path| file_uri(path)).collect();
+    let mut results = Results::new();
+    match OwnedValue::try_from(Value::from(uris)) {
+        Ok(uris) => {
+            results.insert("uris".to_string(), uris);
+            (RESPONSE_SUCCESS, results)
+        }
+        Err(_) => (RESPONSE_OTHER, results),
+    }
+}
+
+fn failed(err: &str) -> (u32, Results) {
+    eprintln!("felipe: file chooser: {}", err);
+    (RESPONSE_OTHER, Results::new())
+}
+
+fn flag(options: &Options<'_>, key: &str) -> bool {
+    matches!(options.get(key), Some(Value::Bool(true)))
+}
+
+/// Paths come as NUL-terminated byte arrays
+fn path_option(options: &Options<'_>, key: &str) -> Option<PathBuf> {
+    bytes_path(options.get(key)?)
+}
+
+fn bytes_path(value: &Value<'_>) -> Option<PathBuf> {
+    let Value::Array(array) = value else {
+        return None;
+    };
+    let bytes: Vec<u8> = array
+        .iter()
+        .map_while(|value| match value {
+            Value::U8(byte) if *byte != 0 => Some(*byte),
+            _ => None,
+        })
+        .collect();
+    (!bytes.is_empty()).then(|| PathBuf::from(OsStr::from_bytes(&bytes)))
+}
+
+/// `file://` URI of an absolute path, percent-encoding all but unreserved bytes
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -0,0 +1,127 @@
+//! Power - no full frame rate while nothing on screen changes
+//!
+//! Felipe draws at full rate for a moment after every key, click, directory
+//! change or camera move, and while something animates or a job runs; once
+//! all is still, it waits for input and only wakes `idle_fps` times a second
+//! (for the selection's pulse and background work), and once a second while
+//! the window is in the background. `max_fps` caps the full rate below the
+//! display's.
+//!
+//! ```toml
+//! [render]
+//! idle_fps = 10     # 0 draws at full rate all the time
+//! max_fps = 0       # 0 leaves it to the display
+//! ```
+
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::{MouseButtonInput, MouseWheel};
+use bevy::prelude::*;
+use bevy::window::{CursorMoved, RequestRedraw};
+use bevy::winit::{UpdateMode, WinitSettings};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::flycam::FlyCamera;
+use crate::glitch::Glitch;
+use crate::jobs::JobQueue;
+use crate::transition::EntryTransition;
+use crate::{CameraState, CurrentDirectory, VimMode};
+
+/// Full rate lasts this long after the last thing that moved, long enough
+/// for the camera to ease in
+const AWAKE_SECONDS: f32 = 1.5;
+
+/// How often a window in the background wakes
+const UNFOCUSED_WAIT: Duration = Duration::from_secs(1);
+
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                apply_power_settings.run_if(resource_changed::<Config>),
+                stay_awake,
+            ),
+        )
+        .add_systems(Last, limit_frame_rate);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Wait for input between frames once still, or never
+fn apply_power_settings(config: Res<Config>, winit: Option<ResMut<WinitSettings>>) {
+    // Apps without winit run their own loop
+    let Some(mut winit) = winit else {
+        return;
+    };
+    let idle_fps = config.render.idle_fps;
+    let wanted = if idle_fps == 0 {
+        WinitSettings::game()
+    } else {
+        WinitSettings {
+            focused_mode: UpdateMode::reactive_low_power(Duration::from_secs_f32(
+                1.0 / idle_fps as f32,
+            )),
+            unfocused_mode: UpdateMode::reactive_low_power(UNFOCUSED_WAIT),
+        }
+    };
+    if winit.focused_mode != wanted.focused_mode || winit.unfocused_mode != wanted.unfocused_mode {
+        *winit = wanted;
+    }
+}
+
+/// Ask for the next frame right away while anything moves
+fn stay_awake(
+    time: Res<Time<Real>>,
+    mut keys: EventReader<KeyboardInput>,
+    mut buttons: EventReader<MouseButtonInput>,
+    mut wheel: EventReader<MouseWheel>,
+    mut cursor: EventReader<CursorMoved>,
+    current_dir: Res<CurrentDirectory>,
+    camera_state: Res<CameraState>,
+    vim_mode: Res<VimMode>,
+    glitch: Res<Glitch>,
+    jobs: Res<JobQueue>,
+    fly: Res<FlyCamera>,
+    transition_query: Query<(), With<EntryTransition>>,
+    mut redraw: EventWriter<RequestRedraw>,
+    mut awake_until: Local<f32>,
+) {
+    let input = keys.read().count() + buttons.read().count() + wheel.read().count();
+    let moved = cursor.read().count() > 0;
+    let busy = input > 0
+        || moved
+        || current_dir.is_changed()
+        || camera_state.is_changed()
+        || vim_mode.is_changed()
+        || glitch.is_changed()
+        || jobs.count() > 0
+        || fly.enabled
+        || !transition_query.is_empty();
+    let now = time.elapsed_seconds();
+    if busy {
+        *awake_until = now + AWAKE_SECONDS;
+    }
+    if now < *awake_until {
+        redraw.send(RequestRedraw);
+    }
+}
+
+/// Hold each frame until its share of a second under `max_fps` has passed
+fn limit_frame_rate(config: Res<Config>, mut last_frame: Local<Option<Instant>>) {
+    let max_fps = config.render.max_fps;
+    if max_fps > 0 {
+        let frame = Duration::from_secs_f32(1.0 / max_fps as f32);
+        if let Some(elapsed) = last_frame.map(|last| last.elapsed()) {
+            if elapsed < frame {
+                std::thread::sleep(frame - elapsed);
+            }
+        }
+    }
+    *last_frame = Some(Instant::now());
+}
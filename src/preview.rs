@@ -0,0 +1,876 @@
+//! Preview pane - a look inside the selected file
+//!
+//! When the selected file is a video, a poster frame (a tenth of the way in,
+//! past any black lead-in) appears in a pane on the right, and the info bar
+//! shows its resolution and duration. Frames and facts come from `ffmpeg` and
+//! `ffprobe`. PDFs show their first page, rendered with pdfium when Felipe is
+//! built with the `pdf` cargo feature, and their page count. Markdown files
+//! are shown rendered, with headings, lists, quotes and code set apart (see
+//! markdown.rs). Previews are made on a worker thread so browsing never waits
+//! for them, once per file and session; the properties panel lists the same
+//! facts.
+//!
+//! `:tail` follows the selected file in the pane instead, like `tail -f`: its
+//! last lines, and the ones appended as it's written, whatever is selected
+//! meanwhile. `:tail!` pauses and resumes the following, `:tail` again stops
+//! it. A file that shrinks (a rotated log) is followed from its new start.
+//!
+//! ```toml
+//! [preview]
+//! enabled = true        # also `:set preview` / `:set nopreview`
+//! ffmpeg = "ffmpeg"     # programs to run, if not on the PATH
+//! ffprobe = "ffprobe"
+//! pdfium = "/opt/pdfium/lib/libpdfium.so"   # if not a system library
+//! ```
+//!
+//! Other crates add previewers by registering a [`PreviewProvider`] in the
+//! [`PreviewProviders`] resource; they are asked first, in the order they were
+//! registered. A provider can give text, an image, facts for the info bar and
+//! a mesh, which floats above the selected entry:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use felipe::{Markup, PreviewContent, PreviewProvider, PreviewProviders};
+//! use std::path::Path;
+//!
+//! struct CsvPreview;
+//!
+//! impl PreviewProvider for CsvPreview {
+//!     fn matches(&self, path: &Path, _mime: Option<&str>) -> bool {
+//!         path.extension().is_some_and(|ext| ext == "csv")
+//!     }
+//!
+//!     fn preview(&self, path: &Path) -> Result<PreviewContent, String> {
+//!         let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+//!         let head: Vec<&str> = text.lines().take(20).collect();
+//!         Ok(PreviewContent {
+//!             facts: vec![("rows", text.lines().count().to_string())],
+//!             document: vec![(Markup::CodeBlock, head.join("\n"))],
+//!             ..default()
+//!         })
+//!     }
+//! }
+//!
+//! fn register(mut providers: ResMut<PreviewProviders>) {
+//!     providers.register(CsvPreview);
+//! }
+//! ```
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::texture::{CompressedImageFormats, ImageSampler, ImageType};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Command as Process;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::markdown::{self, Markup};
+use crate::mime::MimeTypes;
+use crate::{
+    entry_height, remote, CurrentDirectory, Layouts, StatusMessage, UiElement, DIFF_ADDED,
+    DIFF_MODIFIED, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "m4v", "mkv", "webm", "mov", "avi", "wmv", "flv", "mpg", "mpeg", "ts", "3gp",
+];
+/// Width of poster frames, in pixels; the pane shows them at this size
+const POSTER_WIDTH: u32 = 360;
+/// How much of a Markdown file is read, and how many lines of it shown
+const MARKDOWN_BYTES: u64 = 64 * 1024;
+const MARKDOWN_LINES: usize = 40;
+const LINK_COLOR: Color = Color::srgb(0.4, 0.7, 1.0);
+/// Largest side of a previewed mesh, and its gap above the entry
+const MODEL_SIZE: f32 = 1.5;
+const MODEL_GAP: f32 = 1.0;
+/// Turns of the previewed mesh per second
+const MODEL_SPIN: f32 = 0.1;
+/// Lines `:tail` keeps, how far back from the end it starts, and how often
+/// it looks for more, in seconds
+const TAIL_LINES: usize = 30;
+const TAIL_BYTES: u64 = 16 * 1024;
+const TAIL_POLL: f32 = 0.25;
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct PreviewConfig {
+    pub enabled: bool,
+    pub ffmpeg: String,
+    pub ffprobe: String,
+    /// pdfium library to load instead of the system's
+    #[cfg_attr(not(feature = "pdf"), allow(dead_code))]
+    pub pdfium: Option<PathBuf>,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ffmpeg: "ffmpeg".to_string(),
+            ffprobe: "ffprobe".to_string(),
+            pdfium: None,
+        }
+    }
+}
+
+/// What kind of preview a file gets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Video,
+    Pdf,
+    Markdown,
+}
+
+impl Kind {
+    fn of(path: &Path) -> Option<Kind> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(Kind::Pdf),
+            "md" | "markdown" | "mdown" => Some(Kind::Markdown),
+            ext if VIDEO_EXTENSIONS.contains(&ext) => Some(Kind::Video),
+            _ => None,
+        }
+    }
+}
+
+/// Labelled facts about a file's contents, like `pages` and `12`
+pub type Facts = Vec<(&'static str, String)>;
+
+/// Makes previews of the files it matches, for files Felipe has no preview for
+/// or to replace Felipe's own
+pub trait PreviewProvider: Send + Sync + 'static {
+    /// Whether it previews `path`; `mime` is its sniffed type once known
+    fn matches(&self, path: &Path, mime: Option<&str>) -> bool;
+
+    /// The preview, made on a worker thread so it can take its time
+    fn preview(&self, path: &Path) -> Result<PreviewContent, String>;
+}
+
+/// Previewers registered by other crates, tried before the built-in ones
+#[derive(Resource, Default)]
+pub struct PreviewProviders {
+    providers: Vec<Arc<dyn PreviewProvider>>,
+}
+
+impl PreviewProviders {
+    pub fn register(&mut self, provider: impl PreviewProvider) {
+        self.providers.push(Arc::new(provider));
+    }
+}
+
+/// What makes a file's preview
+enum Source {
+    BuiltIn(Kind),
+    Provider(Arc<dyn PreviewProvider>),
+}
+
+impl Source {
+    fn of(path: &Path, mime: Option<&str>, providers: &PreviewProviders) -> Option<Source> {
+        match providers.providers.iter().find(|p| p.matches(path, mime)) {
+            Some(provider) => Some(Source::Provider(provider.clone())),
+            None => Kind::of(path).map(Source::BuiltIn),
+        }
+    }
+}
+
+/// A file's preview, ready to show
+pub struct Preview {
+    image: Option<Handle<Image>>,
+    facts: Facts,
+    /// Rendered text, for documents
+    document: Vec<(Markup, String)>,
+    model: Option<Model>,
+}
+
+/// A previewed mesh, fitted to `MODEL_SIZE`
+struct Model {
+    mesh: Handle<Mesh>,
+    scale: f32,
+    /// Middle of the mesh's bounds, in its own units
+    center: Vec3,
+}
+
+/// What a preview is made of, before the image and mesh are assets
+#[derive(Default)]
+pub struct PreviewContent {
+    /// Shown at the top of the pane
+    pub image: Option<Image>,
+    /// Shown in the info bar and the properties panel
+    pub facts: Facts,
+    /// Text under the image, styled by kind of run
+    pub document: Vec<(Markup, String)>,
+    /// Shown turning slowly above the entry, at any scale
+    pub mesh: Option<Mesh>,
+}
+
+/// Previews made so far, by path, and the one being made
+#[derive(Resource, Default)]
+pub struct Previews {
+    done: HashMap<PathBuf, Result<Preview, String>>,
+    worker: Option<(PathBuf, JoinHandle<Result<PreviewContent, String>>)>,
+}
+
+impl Previews {
+    /// Facts about `path`, once its preview is made
+    pub fn facts(&self, path: &Path) -> &[(&'static str, String)] {
+        match self.done.get(path) {
+            Some(Ok(preview)) => &preview.facts,
+            _ => &[],
+        }
+    }
+
+    /// The facts in one line for the info bar, like `1920x1080, 3:25`
+    pub fn summary(&self, path: &Path) -> Option<String> {
+        let facts = self.facts(path);
+        (!facts.is_empty()).then(|| {
+            facts
+                .iter()
+                .map(|(_, value)| value.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+    }
+}
+
+/// The file `:tail` follows, and its last lines
+#[derive(Resource, Default)]
+struct Tail {
+    path: Option<PathBuf>,
+    paused: bool,
+    /// Bytes read so far
+    offset: u64,
+    lines: VecDeque<String>,
+    /// The end of the file after its last newline, not a line yet
+    partial: String,
+    /// The lines as the pane shows them
+    document: Vec<(Markup, String)>,
+    last_poll: f32,
+}
+
+impl Tail {
+    fn follow(&mut self, path: PathBuf) {
+        *self = Tail {
+            path: Some(path),
+            ..default()
+        };
+    }
+
+    /// Take in what was appended since the last read
+    fn read(&mut self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut file = std::fs::File::open(path)?;
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            // Truncated or replaced; start over at its new start
+            self.offset = 0;
+            self.lines.clear();
+            self.partial.clear();
+        }
+        if len == self.offset {
+            return Ok(());
+        }
+        // The first read starts near the end, not at the start of a huge log
+        if self.offset == 0 {
+            self.offset = len.saturating_sub(TAIL_BYTES);
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        file.take(len - self.offset).read_to_end(&mut bytes)?;
+        self.offset += bytes.len() as u64;
+        self.partial.push_str(&String::from_utf8_lossy(&bytes));
+        if let Some(end) = self.partial.rfind('\n') {
+            let complete: String = self.partial.drain(..=end).collect();
+            self.lines.extend(complete.lines().map(str::to_string));
+            let excess = self.lines.len().saturating_sub(TAIL_LINES);
+            self.lines.drain(..excess);
+        }
+        let text = self
+            .lines
+            .iter()
+            .map(String::as_str)
+            .chain((!self.partial.is_empty()).then_some(self.partial.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.document = vec![(Markup::CodeBlock, text)];
+        Ok(())
+    }
+}
+
+/// Marker for the preview pane
+#[derive(Component)]
+struct PreviewPane;
+
+/// Marker for the pane's picture
+#[derive(Component)]
+struct PreviewImage;
+
+/// Marker for the pane's caption
+#[derive(Component)]
+struct PreviewText;
+
+/// Marker for the mesh floating above the selected entry
+#[derive(Component)]
+struct PreviewModel;
+
+pub struct PreviewPlugin;
+
+impl Plugin for PreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Previews>()
+            .init_resource::<PreviewProviders>()
+            .init_resource::<Tail>()
+            .add_systems(Startup, spawn_preview_pane)
+            .add_systems(
+                Update,
+                (
+                    start_preview,
+                    finish_preview,
+                    (handle_tail_command, follow_tail).chain(),
+                    (update_preview_pane, update_preview_model),
+                )
+                    .chain(),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn spawn_preview_pane(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    right: Val::Px(10.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    row_gap: Val::Px(6.0),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE_DIM),
+                ..default()
+            },
+            PreviewPane,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ImageBundle {
+                    style: Style {
+                        width: Val::Px(POSTER_WIDTH as f32),
+                        ..default()
+                    },
+                    ..default()
+                },
+                PreviewImage,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: FELIPE_ORANGE,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    max_width: Val::Px(POSTER_WIDTH as f32),
+                    ..default()
+                }),
+                PreviewText,
+            ));
+        });
+}
+
+/// Start on the selected file's preview when it has none yet
+fn start_preview(
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mimes: Res<MimeTypes>,
+    providers: Res<PreviewProviders>,
+    mut previews: ResMut<Previews>,
+) {
+    if !config.preview.enabled || previews.worker.is_some() {
+        return;
+    }
+    let Some(entry) = current_dir.entries.get(current_dir.selected_index) else {
+        return;
+    };
+    if entry.is_dir || previews.done.contains_key(&entry.path) {
+        return;
+    }
+    let Some(source) = Source::of(&entry.path, mimes.get(&entry.path), &providers) else {
+        return;
+    };
+    let path = entry.path.clone();
+    let settings = config.preview.clone();
+    let worker = {
+        let path = path.clone();
+        std::thread::spawn(move || match source {
+            Source::BuiltIn(kind) => extract(&path, kind, &settings),
+            Source::Provider(provider) => provider.preview(&path),
+        })
+    };
+    previews.worker = Some((path, worker));
+}
+
+fn finish_preview(
+    mut previews: ResMut<Previews>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if !previews
+        .worker
+        .as_ref()
+        .is_some_and(|(_, worker)| worker.is_finished())
+    {
+        return;
+    }
+    let (path, worker) = previews.worker.take().expect("checked above");
+    let extracted = worker
+        .join()
+        .unwrap_or_else(|_| Err("preview failed".to_string()));
+    let preview = extracted.map(|extracted| Preview {
+        image: extracted.image.map(|image| images.add(image)),
+        facts: extracted.facts,
+        document: extracted.document,
+        model: extracted.mesh.map(|mesh| {
+            let (scale, center) = match mesh.compute_aabb() {
+                Some(aabb) => (
+                    MODEL_SIZE / (aabb.half_extents.max_element() * 2.0).max(f32::EPSILON),
+                    Vec3::from(aabb.center),
+                ),
+                None => (1.0, Vec3::ZERO),
+            };
+            Model {
+                mesh: meshes.add(mesh),
+                scale,
+                center,
+            }
+        }),
+    });
+    previews.done.insert(path, preview);
+}
+
+/// Show the selected file's preview, or hide the pane when it has none
+fn update_preview_pane(
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mimes: Res<MimeTypes>,
+    providers: Res<PreviewProviders>,
+    previews: Res<Previews>,
+    tail: Res<Tail>,
+    mut pane_query: Query<&mut Style, With<PreviewPane>>,
+    mut image_query: Query<(&mut UiImage, &mut Style), (With<PreviewImage>, Without<PreviewPane>)>,
+    mut text_query: Query<&mut Text, With<PreviewText>>,
+) {
+    if !current_dir.is_changed()
+        && !previews.is_changed()
+        && !config.is_changed()
+        && !mimes.is_changed()
+        && !tail.is_changed()
+    {
+        return;
+    }
+    let selected = current_dir
+        .entries
+        .get(current_dir.selected_index)
+        .filter(|_| config.preview.enabled)
+        .filter(|entry| {
+            !entry.is_dir && Source::of(&entry.path, mimes.get(&entry.path), &providers).is_some()
+        });
+    const NO_TEXT: &[(Markup, String)] = &[];
+    let tailed = tail.path.as_ref().map(|path| {
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        let state = if tail.paused { " (paused)" } else { "" };
+        (
+            None,
+            format!("tail -f {}{}", name, state),
+            tail.document.as_slice(),
+        )
+    });
+    let shown = tailed.or_else(|| {
+        selected.map(|entry| {
+            let making = previews
+                .worker
+                .as_ref()
+                .is_some_and(|(path, _)| path == &entry.path);
+            match previews.done.get(&entry.path) {
+                Some(Ok(preview)) => (
+                    preview.image.clone(),
+                    entry.name.clone(),
+                    preview.document.as_slice(),
+                ),
+                Some(Err(err)) => (
+                    None,
+                    format!("{}\nNo preview: {}", entry.name, err),
+                    NO_TEXT,
+                ),
+                None if making => (None, format!("{}\nMaking preview...", entry.name), NO_TEXT),
+                None => (None, entry.name.clone(), NO_TEXT),
+            }
+        })
+    });
+
+    for mut style in pane_query.iter_mut() {
+        style.display = if shown.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    let Some((image, caption, document)) = shown else {
+        return;
+    };
+    for (mut ui_image, mut style) in image_query.iter_mut() {
+        style.display = if image.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if let Some(image) = &image {
+            ui_image.texture = image.clone();
+        }
+    }
+    let mut sections = vec![TextSection::new(
+        caption,
+        TextStyle {
+            font_size: 16.0,
+            color: FELIPE_ORANGE,
+            ..default()
+        },
+    )];
+    if !document.is_empty() {
+        sections[0].value.push_str("\n\n");
+    }
+    sections.extend(
+        document
+            .iter()
+            .map(|(markup, text)| TextSection::new(text.clone(), markup_style(*markup))),
+    );
+    for mut text in text_query.iter_mut() {
+        text.sections.clone_from(&sections);
+    }
+}
+
+/// `:tail` follows the selected file or stops, `:tail!` pauses or resumes
+fn handle_tail_command(
+    mut run_commands: EventReader<RunCommand>,
+    current_dir: Res<CurrentDirectory>,
+    mut tail: ResMut<Tail>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Tail { pause } = command else {
+            continue;
+        };
+        if *pause {
+            if tail.path.is_none() {
+                status.0 = "Not following a file - :tail follows the selected one".to_string();
+                continue;
+            }
+            tail.paused = !tail.paused;
+            status.0 = if tail.paused { "Paused" } else { "Following" }.to_string();
+            continue;
+        }
+        if tail.path.is_some() {
+            *tail = Tail::default();
+            status.0 = "Stopped following".to_string();
+            continue;
+        }
+        let Some(entry) = current_dir.entries.get(current_dir.selected_index) else {
+            continue;
+        };
+        if entry.is_dir || remote::location(&entry.path).is_some() {
+            status.0 = ":tail follows local files only".to_string();
+            continue;
+        }
+        tail.follow(entry.path.clone());
+        if let Err(err) = tail.read() {
+            status.0 = format!("Cannot read {}: {}", entry.name, err);
+            *tail = Tail::default();
+        }
+    }
+}
+
+/// Read what the followed file got since the last look
+fn follow_tail(time: Res<Time>, mut tail: ResMut<Tail>, mut status: ResMut<StatusMessage>) {
+    let now = time.elapsed_seconds();
+    if tail.path.is_none() || tail.paused || now - tail.last_poll < TAIL_POLL {
+        return;
+    }
+    // Changed only when lines came in, so the pane isn't rebuilt for nothing
+    let unchanged = tail.bypass_change_detection();
+    unchanged.last_poll = now;
+    let before = (unchanged.offset, unchanged.partial.len());
+    if let Err(err) = unchanged.read() {
+        status.0 = format!("Stopped following: {}", err);
+        *tail = Tail::default();
+        return;
+    }
+    if (tail.offset, tail.partial.len()) != before {
+        tail.set_changed();
+    }
+}
+
+/// Float the selected file's mesh, if its preview has one, above its entry
+fn update_preview_model(
+    mut commands: Commands,
+    time: Res<Time>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    previews: Res<Previews>,
+    layouts: Res<Layouts>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut model_query: Query<(Entity, &mut Transform, &Handle<Mesh>), With<PreviewModel>>,
+) {
+    let shown = current_dir
+        .entries
+        .get(current_dir.selected_index)
+        .filter(|_| config.preview.enabled)
+        .and_then(|entry| match previews.done.get(&entry.path) {
+            Some(Ok(Preview {
+                model: Some(model), ..
+            })) => Some((entry, model)),
+            _ => None,
+        });
+    let Some((entry, model)) = shown else {
+        for (entity, ..) in model_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    let position = layouts.position(current_dir.selected_index, current_dir.entries.len())
+        + Vec3::Y * (entry_height(entry) + MODEL_GAP + MODEL_SIZE / 2.0);
+    let rotation =
+        Quat::from_rotation_y(time.elapsed_seconds() * MODEL_SPIN * std::f32::consts::TAU);
+    let transform = Transform::from_translation(position)
+        .with_rotation(rotation)
+        .with_scale(Vec3::splat(model.scale))
+        * Transform::from_translation(-model.center);
+
+    match model_query.get_single_mut() {
+        Ok((_, mut current, mesh)) if *mesh == model.mesh => *current = transform,
+        _ => {
+            for (entity, ..) in model_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            commands.spawn((
+                PbrBundle {
+                    mesh: model.mesh.clone(),
+                    material: materials.add(StandardMaterial {
+                        base_color: FELIPE_ORANGE_DIM,
+                        emissive: LinearRgba::from(FELIPE_ORANGE) * 0.5,
+                        ..default()
+                    }),
+                    transform,
+                    ..default()
+                },
+                PreviewModel,
+            ));
+        }
+    }
+}
+
+/// Size and color of each kind of Markdown run; there's only one font
+fn markup_style(markup: Markup) -> TextStyle {
+    let (font_size, color) = match markup {
+        Markup::Heading(1) => (22.0, DIFF_MODIFIED),
+        Markup::Heading(2) => (19.0, DIFF_MODIFIED),
+        Markup::Heading(_) => (16.0, DIFF_MODIFIED),
+        Markup::Text => (14.0, FELIPE_ORANGE),
+        Markup::Strong => (14.0, DIFF_MODIFIED),
+        Markup::Emphasis | Markup::Quote | Markup::Marker => (14.0, FELIPE_ORANGE_DIM),
+        Markup::Code | Markup::CodeBlock => (14.0, DIFF_ADDED),
+        Markup::Link => (14.0, LINK_COLOR),
+    };
+    TextStyle {
+        font_size,
+        color,
+        ..default()
+    }
+}
+
+// =============================================================================
+// Extraction (worker thread)
+// =============================================================================
+
+fn extract(path: &Path, kind: Kind, config: &PreviewConfig) -> Result<PreviewContent, String> {
+    match kind {
+        Kind::Video => extract_video(path, config),
+        Kind::Pdf => extract_pdf(path, config),
+        Kind::Markdown => extract_markdown(path),
+    }
+}
+
+/// The start of the file, rendered
+fn extract_markdown(path: &Path) -> Result<PreviewContent, String> {
+    use std::io::Read;
+
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(MARKDOWN_BYTES).read_to_end(&mut bytes))
+        .map_err(|err| err.to_string())?;
+    Ok(PreviewContent {
+        document: markdown::render(&String::from_utf8_lossy(&bytes), MARKDOWN_LINES),
+        ..default()
+    })
+}
+
+/// Resolution and duration from ffprobe, then a poster frame from ffmpeg
+fn extract_video(path: &Path, config: &PreviewConfig) -> Result<PreviewContent, String> {
+    let probe = run(
+        &config.ffprobe,
+        Process::new(&config.ffprobe)
+            .args(["-v", "error", "-select_streams", "v:0"])
+            .args(["-show_entries", "stream=width,height:format=duration"])
+            .args(["-of", "json"])
+            .arg(path),
+    )?;
+    let probe: serde_json::Value =
+        serde_json::from_slice(&probe).map_err(|err| format!("ffprobe: {}", err))?;
+    let stream = &probe["streams"][0];
+    let size = stream["width"]
+        .as_u64()
+        .zip(stream["height"].as_u64())
+        .map(|(width, height)| format!("{}x{}", width, height));
+    let duration = probe["format"]["duration"]
+        .as_str()
+        .and_then(|duration| duration.parse::<f64>().ok());
+    let facts = [("video", size.clone()), ("duration", duration.map(clock))]
+        .into_iter()
+        .filter_map(|(label, value)| Some((label, value?)))
+        .collect();
+
+    // Audio-only files have no frame to show
+    let image = match size {
+        Some(_) => Some(decode_png(&run(
+            &config.ffmpeg,
+            Process::new(&config.ffmpeg)
+                .args(["-v", "error", "-nostdin"])
+                .args(["-ss", &format!("{:.2}", duration.unwrap_or(0.0) / 10.0)])
+                .arg("-i")
+                .arg(path)
+                .args([
+                    "-frames:v",
+                    "1",
+                    "-vf",
+                    &format!("scale={}:-2", POSTER_WIDTH),
+                ])
+                .args(["-f", "image2pipe", "-c:v", "png", "-"]),
+        )?)?),
+        None => None,
+    };
+    Ok(PreviewContent {
+        image,
+        facts,
+        ..default()
+    })
+}
+
+/// Page count, first page size and the first page itself
+#[cfg(feature = "pdf")]
+fn extract_pdf(path: &Path, config: &PreviewConfig) -> Result<PreviewContent, String> {
+    use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+    use pdfium_render::prelude::*;
+
+    let bindings = match &config.pdfium {
+        Some(library) => Pdfium::bind_to_library(library),
+        None => Pdfium::bind_to_system_library(),
+    }
+    .map_err(|err| format!("pdfium not loaded: {}", err))?;
+    let pdfium = Pdfium::new(bindings);
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|err| err.to_string())?;
+    let pages = document.pages();
+    let mut facts = vec![("pages", pages.len().to_string())];
+    let Ok(page) = pages.get(0) else {
+        return Ok(PreviewContent { facts, ..default() });
+    };
+    facts.push((
+        "page size",
+        format!(
+            "{:.0} x {:.0} mm",
+            page.width().to_mm(),
+            page.height().to_mm()
+        ),
+    ));
+    let bitmap = page
+        .render_with_config(&PdfRenderConfig::new().set_target_width(POSTER_WIDTH as i32))
+        .map_err(|err| err.to_string())?;
+    let image = Image::new(
+        Extent3d {
+            width: bitmap.width() as u32,
+            height: bitmap.height() as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        bitmap.as_rgba_bytes(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    Ok(PreviewContent {
+        image: Some(image),
+        facts,
+        ..default()
+    })
+}
+
+#[cfg(not(feature = "pdf"))]
+fn extract_pdf(_path: &Path, _config: &PreviewConfig) -> Result<PreviewContent, String> {
+    Err("Felipe was built without --features pdf".to_string())
+}
+
+/// Stdout of a finished program, or why there is none
+fn run(program: &str, command: &mut Process) -> Result<Vec<u8>, String> {
+    let output = command.output().map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => format!("{} not found", program),
+        _ => format!("{}: {}", program, err),
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().next().unwrap_or("failed");
+        return Err(format!("{}: {}", program, reason));
+    }
+    Ok(output.stdout)
+}
+
+fn decode_png(png: &[u8]) -> Result<Image, String> {
+    Image::from_buffer(
+        png,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// `h:mm:ss`, or `m:ss` under an hour
+fn clock(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
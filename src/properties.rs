@@ -0,0 +1,1189 @@
+//! Properties - `i` shows everything the filesystem knows about the selection
+//!
+//! The panel follows the selected entry (click another one to switch) and
+//! lists its type, exact size and space taken on disk (less for sparse files),
+//! permission bits, owner and group, link count, inode and timestamps, and
+//! for photos the camera, size, exposure, date taken and GPS position from
+//! their EXIF data. Files with a preview (see preview.rs) add what it found,
+//! like a video's duration or a PDF's page count. `i`, Esc or q closes it.
+//!
+//! Permission bits can be edited in the panel's rwx grid (hjkl to move, Space
+//! to flip a bit) or with `:chmod 755` / `:chmod u+x,go-w`, owners with
+//! `:chown user:group` (Unix). Both commands act on a visual range when typed
+//! over one, and `:chown` asks before going into directories. Changes go into
+//! the operation log, so `u` puts the old mode or owner back.
+//!
+//! Extended attributes (tags, capabilities, SELinux labels) are listed under
+//! the rest, text as text and anything else in hex. `:xattr set user.tag
+//! value` and `:xattr rm user.tag` edit them, undoable the same way.
+//!
+//! `:hash` (see checksum.rs) lists its digests at the bottom until the panel
+//! closes.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use std::fs::Metadata;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::checksum::FileDigest;
+use crate::command::{Command, CommandLine, RunCommand};
+use crate::config::Config;
+use crate::flycam::FlyCamera;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::format::FormatConfig;
+use crate::jobs::JobSummary;
+use crate::locale::trf;
+use crate::oplog::OperationLog;
+use crate::ops::{self, UndoStep};
+use crate::preview::Previews;
+use crate::{notes, photo};
+use crate::{
+    CurrentDirectory, FileEntry, PendingPrompt, Prompt, StatusMessage, UiElement, VimMode,
+    FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+// =============================================================================
+// Reading
+// =============================================================================
+
+/// One labelled line of the panel
+struct Property {
+    label: &'static str,
+    value: String,
+}
+
+/// Properties of `path`, without following it if it's a symlink
+fn read_properties(path: &Path, format: &FormatConfig) -> Result<Vec<Property>, String> {
+    let metadata = std::fs::symlink_metadata(path).map_err(|e| e.to_string())?;
+    let mut properties = vec![
+        Property {
+            label: "path",
+            value: path.display().to_string(),
+        },
+        Property {
+            label: "type",
+            value: file_type(&metadata, path),
+        },
+        Property {
+            label: "size",
+            value: format!(
+                "{} ({} bytes)",
+                format.size(metadata.len()),
+                format.number(metadata.len())
+            ),
+        },
+    ];
+    properties.extend(platform_properties(&metadata, format));
+    let times = [
+        ("modified", metadata.modified()),
+        ("accessed", metadata.accessed()),
+        ("created", metadata.created()),
+    ];
+    for (label, time) in times {
+        properties.push(Property {
+            label,
+            value: time
+                .map(|time| date(time, format))
+                .unwrap_or_else(|_| "unknown".to_string()),
+        });
+    }
+    if let Some(note) = notes::note_of(path) {
+        properties.push(Property {
+            label: "note",
+            value: note,
+        });
+    }
+    if metadata.is_file() {
+        properties.extend(
+            photo::properties(path)
+                .into_iter()
+                .map(|(label, value)| Property { label, value }),
+        );
+    }
+    Ok(properties)
+}
+
+fn file_type(metadata: &Metadata, path: &Path) -> String {
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        match std::fs::read_link(path) {
+            Ok(target) => format!("symlink -> {}", target.display()),
+            Err(_) => "symlink".to_string(),
+        }
+    } else if file_type.is_dir() {
+        "directory".to_string()
+    } else if file_type.is_file() {
+        "file".to_string()
+    } else {
+        special_type(metadata).to_string()
+    }
+}
+
+/// Extended attributes of `path`, not following symlinks, sorted by name
+#[cfg(unix)]
+fn read_xattrs(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let names = xattr::list(path).map_err(|err| xattr_error(&err))?;
+    let mut xattrs: Vec<(String, String)> = names
+        .map(|name| {
+            let value = match xattr::get(path, &name) {
+                Ok(Some(value)) => xattr_value(&value),
+                Ok(None) => "(gone)".to_string(),
+                Err(err) => format!("({})", xattr_error(&err)),
+            };
+            (name.to_string_lossy().to_string(), value)
+        })
+        .collect();
+    xattrs.sort();
+    Ok(xattrs)
+}
+
+#[cfg(not(unix))]
+fn read_xattrs(_path: &Path) -> Result<Vec<(String, String)>, String> {
+    Err("a Unix feature".to_string())
+}
+
+/// Printable text quoted, anything else (capabilities, say) as hex
+#[cfg(unix)]
+fn xattr_value(value: &[u8]) -> String {
+    const MAX_SHOWN: usize = 48;
+    match std::str::from_utf8(value) {
+        Ok(text) if !text.chars().any(char::is_control) => {
+            let shown: String = text.chars().take(MAX_SHOWN).collect();
+            let more = if shown.len() < text.len() { "..." } else { "" };
+            format!("\"{}{}\"", shown, more)
+        }
+        _ => {
+            let hex: String = value
+                .iter()
+                .take(MAX_SHOWN / 2)
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            let more = if value.len() > MAX_SHOWN / 2 {
+                "..."
+            } else {
+                ""
+            };
+            format!("0x{}{} ({} bytes)", hex, more, value.len())
+        }
+    }
+}
+
+fn xattr_error(err: &io::Error) -> String {
+    match err.kind() {
+        io::ErrorKind::Unsupported => "not supported by this filesystem".to_string(),
+        io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+        io::ErrorKind::ReadOnlyFilesystem => "read-only filesystem".to_string(),
+        _ => err.to_string(),
+    }
+}
+
+/// The date plus how long ago it was, which a bare date makes hard to judge
+fn date(time: SystemTime, format: &FormatConfig) -> String {
+    let ago = match SystemTime::now().duration_since(time) {
+        Ok(elapsed) => match elapsed.as_secs() {
+            seconds @ 0..60 => format!("{}s ago", seconds),
+            seconds @ 60..3600 => format!("{}m ago", seconds / 60),
+            seconds @ 3600..86400 => format!("{}h ago", seconds / 3600),
+            seconds => format!("{}d ago", seconds / 86400),
+        },
+        Err(_) => "in the future".to_string(),
+    };
+    format!("{}  ({})", format.date(time), ago)
+}
+
+#[cfg(unix)]
+fn platform_properties(metadata: &Metadata, format: &FormatConfig) -> Vec<Property> {
+    use std::os::unix::fs::MetadataExt;
+
+    let user = owner_name(metadata);
+    let group = uzers::get_group_by_gid(metadata.gid())
+        .map(|group| group.name().to_string_lossy().to_string())
+        .unwrap_or_else(|| "?".to_string());
+    // Status change: permissions, owner or links, not the contents
+    let changed = SystemTime::UNIX_EPOCH
+        + std::time::Duration::new(metadata.ctime().max(0) as u64, metadata.ctime_nsec() as u32);
+    // `blocks` counts 512-byte units whatever the filesystem's block size
+    let allocated = metadata.blocks() * 512;
+    let sparse = if metadata.is_file() && allocated < metadata.len() {
+        format!(
+            "  sparse, {}% allocated",
+            allocated * 100 / metadata.len().max(1)
+        )
+    } else {
+        String::new()
+    };
+    let shared = if metadata.is_file() && metadata.nlink() > 1 {
+        "  (hard-linked: same data under other names)"
+    } else {
+        ""
+    };
+    vec![
+        Property {
+            label: "on disk",
+            value: format!(
+                "{} ({} bytes){}",
+                format.size(allocated),
+                format.number(allocated),
+                sparse
+            ),
+        },
+        Property {
+            label: "mode",
+            value: format!(
+                "{} ({:04o})",
+                mode_string(metadata.mode()),
+                metadata.mode() & 0o7777
+            ),
+        },
+        Property {
+            label: "owner",
+            value: format!("{} ({})", user, metadata.uid()),
+        },
+        Property {
+            label: "group",
+            value: format!("{} ({})", group, metadata.gid()),
+        },
+        Property {
+            label: "links",
+            value: format!("{}{}", metadata.nlink(), shared),
+        },
+        Property {
+            label: "inode",
+            value: format!("{} on device {:#x}", metadata.ino(), metadata.dev()),
+        },
+        Property {
+            label: "changed",
+            value: date(changed, format),
+        },
+    ]
+}
+
+#[cfg(not(unix))]
+fn platform_properties(metadata: &Metadata, _format: &FormatConfig) -> Vec<Property> {
+    vec![Property {
+        label: "mode",
+        value: if metadata.permissions().readonly() {
+            "read-only".to_string()
+        } else {
+            "read-write".to_string()
+        },
+    }]
+}
+
+/// `rwxr-x---` as `ls -l` shows it, including setuid, setgid and sticky bits
+fn mode_string(mode: u32) -> String {
+    let mut text = String::with_capacity(9);
+    for (shift, special, mark) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => mark,
+            (false, true) => mark.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+#[cfg(unix)]
+fn special_type(metadata: &Metadata) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if file_type.is_block_device() {
+        "block device"
+    } else if file_type.is_char_device() {
+        "character device"
+    } else {
+        "special file"
+    }
+}
+
+#[cfg(not(unix))]
+fn special_type(_metadata: &Metadata) -> &'static str {
+    "special file"
+}
+
+// =============================================================================
+// Mode changes
+// =============================================================================
+
+/// Bits each class of `ugo` can change, special bits included
+const CLASS_BITS: [u32; 3] = [0o4700, 0o2070, 0o1007];
+
+/// `:chmod` argument: octal bits, or symbolic clauses like `u+x,go-w`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModeChange {
+    text: String,
+    octal: Option<u32>,
+    clauses: Vec<ModeClause>,
+}
+
+/// One operator of a symbolic mode, e.g. the `-w` of `go-w`
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ModeClause {
+    /// Bits of the classes named before the operator
+    who: u32,
+    op: char,
+    perms: u32,
+    /// `X`: execute only for directories and files someone can already execute
+    exec_if_any: bool,
+}
+
+impl std::str::FromStr for ModeChange {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let bad = || format!("Bad mode: {} (e.g. 755 or u+x,go-w)", text);
+        if !text.is_empty() && text.chars().all(|c| c.is_digit(8)) {
+            let octal = u32::from_str_radix(text, 8)
+                .ok()
+                .filter(|mode| *mode <= 0o7777)
+                .ok_or_else(bad)?;
+            return Ok(Self {
+                text: text.to_string(),
+                octal: Some(octal),
+                clauses: Vec::new(),
+            });
+        }
+
+        let mut clauses = Vec::new();
+        for part in text.split(',') {
+            let mut chars = part.chars().peekable();
+            let mut who = 0;
+            while let Some(class) = chars.peek().and_then(|c| "ugoa".find(*c)) {
+                who |= CLASS_BITS.get(class).copied().unwrap_or(0o7777);
+                chars.next();
+            }
+            if who == 0 {
+                who = 0o7777;
+            }
+            let mut has_op = false;
+            while let Some(op) = chars.next() {
+                if !matches!(op, '+' | '-' | '=') {
+                    return Err(bad());
+                }
+                let mut clause = ModeClause {
+                    who,
+                    op,
+                    perms: 0,
+                    exec_if_any: false,
+                };
+                while let Some(&perm) = chars.peek() {
+                    match perm {
+                        'r' => clause.perms |= 0o444,
+                        'w' => clause.perms |= 0o222,
+                        'x' => clause.perms |= 0o111,
+                        'X' => clause.exec_if_any = true,
+                        's' => clause.perms |= 0o6000,
+                        't' => clause.perms |= 0o1000,
+                        '+' | '-' | '=' => break,
+                        _ => return Err(bad()),
+                    }
+                    chars.next();
+                }
+                clauses.push(clause);
+                has_op = true;
+            }
+            if !has_op {
+                return Err(bad());
+            }
+        }
+        Ok(Self {
+            text: text.to_string(),
+            octal: None,
+            clauses,
+        })
+    }
+}
+
+impl std::fmt::Display for ModeChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl ModeChange {
+    /// The mode an entry with `mode` gets
+    fn apply(&self, mode: u32, is_dir: bool) -> u32 {
+        if let Some(octal) = self.octal {
+            return octal;
+        }
+        self.clauses.iter().fold(mode & 0o7777, |mode, clause| {
+            let mut perms = clause.perms;
+            if clause.exec_if_any && (is_dir || mode & 0o111 != 0) {
+                perms |= 0o111;
+            }
+            let bits = perms & clause.who;
+            match clause.op {
+                '+' => mode | bits,
+                '-' => mode & !bits,
+                _ => (mode & !clause.who) | bits,
+            }
+        })
+    }
+}
+
+/// Set `path` to `mode`; the error says why it couldn't be
+fn chmod(path: &Path, mode: u32) -> Result<UndoStep, String> {
+    ops::set_mode(path, mode).map_err(|err| match err.kind() {
+        io::ErrorKind::PermissionDenied => {
+            let owner = std::fs::metadata(path)
+                .map(|metadata| owner_name(&metadata))
+                .unwrap_or_else(|_| "its owner".to_string());
+            format!(
+                "permission denied, only {} or root can change its mode",
+                owner
+            )
+        }
+        io::ErrorKind::ReadOnlyFilesystem => "read-only filesystem".to_string(),
+        _ => err.to_string(),
+    })
+}
+
+/// Change `path`'s owner and group, and with `recursive` everything inside it
+/// without following symlinks; changes and failures are appended
+fn chown(
+    path: &Path,
+    request: &ChownRequest,
+    recursive: bool,
+    steps: &mut Vec<UndoStep>,
+    failed: &mut Vec<(PathBuf, String)>,
+) {
+    let change = |path: &Path, follow: bool| {
+        ops::set_owner(path, request.uid, request.gid, follow)
+            .map_err(|err| (path.to_path_buf(), chown_error(&err)))
+    };
+    match change(path, true) {
+        Ok(step) => steps.push(step),
+        Err(failure) => failed.push(failure),
+    }
+    if !recursive {
+        return;
+    }
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        if !std::fs::symlink_metadata(&dir).is_ok_and(|m| m.is_dir()) {
+            continue;
+        }
+        let read_dir = match std::fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) => {
+                failed.push((dir, format!("can't list: {}", err)));
+                continue;
+            }
+        };
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            match change(&path, false) {
+                Ok(step) => steps.push(step),
+                Err(failure) => failed.push(failure),
+            }
+            dirs.push(path);
+        }
+    }
+}
+
+fn chown_error(err: &io::Error) -> String {
+    match err.kind() {
+        // Only root may give entries away; owners may only pick one of their groups
+        io::ErrorKind::PermissionDenied => {
+            "permission denied, needs root or a group you belong to".to_string()
+        }
+        io::ErrorKind::ReadOnlyFilesystem => "read-only filesystem".to_string(),
+        _ => err.to_string(),
+    }
+}
+
+/// Journal a change over several entries as one operation, list failures in
+/// the job summary, and describe it for the status line
+fn finish_change(
+    label: String,
+    steps: Vec<UndoStep>,
+    failed: Vec<(PathBuf, String)>,
+    oplog: &mut OperationLog,
+    summary: &mut JobSummary,
+) -> String {
+    let message = match failed.first() {
+        None => format!("{}: {} changed", label, steps.len()),
+        Some((path, reason)) => format!(
+            "{}: {} changed, {} failed ({}: {})",
+            label,
+            steps.len(),
+            failed.len(),
+            path.display(),
+            reason
+        ),
+    };
+    summary.add("changed", steps.len(), failed);
+    oplog.record(label, steps);
+    message
+}
+
+/// `:chown` with names resolved, waiting for the recursive question if needed
+pub struct ChownRequest {
+    /// As typed, e.g. `www-data:` or `:staff`
+    spec: String,
+    targets: Vec<PathBuf>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+}
+
+impl ChownRequest {
+    /// Question asked when some targets are directories
+    pub fn question(&self) -> String {
+        let dirs: Vec<String> = self
+            .targets
+            .iter()
+            .filter(|path| std::fs::symlink_metadata(path).is_ok_and(|m| m.is_dir()))
+            .map(|path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        trf(
+            "chown {}: everything inside {} too? y:yes  n:only the selection  Esc:cancel",
+            &[&self.spec, &dirs.join(", ")],
+        )
+    }
+
+    /// Run it; the message says how it went
+    pub fn run(
+        self,
+        recursive: bool,
+        oplog: &mut OperationLog,
+        summary: &mut JobSummary,
+    ) -> String {
+        let mut steps = Vec::new();
+        let mut failed = Vec::new();
+        for target in &self.targets {
+            chown(target, &self, recursive, &mut steps, &mut failed);
+        }
+        let label = format!("chown {}", self.spec);
+        finish_change(label, steps, failed, oplog, summary)
+    }
+}
+
+/// uid and gid for `user` and `group`, given as names or numbers
+#[cfg(unix)]
+fn resolve_owner(
+    user: Option<&str>,
+    group: Option<&str>,
+) -> Result<(Option<u32>, Option<u32>), String> {
+    let uid = match user {
+        None => None,
+        Some(user) => Some(match user.parse() {
+            Ok(uid) => uid,
+            Err(_) => uzers::get_user_by_name(user)
+                .ok_or_else(|| format!("No such user: {}", user))?
+                .uid(),
+        }),
+    };
+    let gid = match group {
+        None => None,
+        Some(group) => Some(match group.parse() {
+            Ok(gid) => gid,
+            Err(_) => uzers::get_group_by_name(group)
+                .ok_or_else(|| format!("No such group: {}", group))?
+                .gid(),
+        }),
+    };
+    Ok((uid, gid))
+}
+
+#[cfg(not(unix))]
+fn resolve_owner(
+    _user: Option<&str>,
+    _group: Option<&str>,
+) -> Result<(Option<u32>, Option<u32>), String> {
+    Err("Owners are a Unix feature".to_string())
+}
+
+/// Mode bits `chmod` can change, or None for symlinks (whose own mode can't be
+/// set on most systems) and where there are no mode bits
+#[cfg(unix)]
+fn editable_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    (!metadata.file_type().is_symlink()).then(|| metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn editable_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Mode bits of `path`, or of its target for a symlink
+#[cfg(unix)]
+fn current_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(std::fs::metadata(path).ok()?.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn current_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn owner_name(metadata: &Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    uzers::get_user_by_uid(metadata.uid())
+        .map(|user| user.name().to_string_lossy().to_string())
+        .unwrap_or_else(|| metadata.uid().to_string())
+}
+
+#[cfg(not(unix))]
+fn owner_name(_metadata: &Metadata) -> String {
+    "someone else".to_string()
+}
+
+// =============================================================================
+// View
+// =============================================================================
+
+/// Properties panel state
+#[derive(Resource, Default)]
+pub struct PropertiesView {
+    /// Entry the properties were read for
+    path: Option<PathBuf>,
+    properties: Vec<Property>,
+    /// Why the properties couldn't be read
+    error: Option<String>,
+    /// Mode bits shown in the rwx grid, if they can be edited
+    mode: Option<u32>,
+    /// Grid cell: class (user, group, other) and permission (r, w, x)
+    cursor: (usize, usize),
+    /// Extended attributes as name and printable value
+    xattrs: Vec<(String, String)>,
+    /// Why the extended attributes couldn't be listed
+    xattr_error: Option<String>,
+    /// Algorithm and results of the last `:hash`
+    digests: Option<(&'static str, Vec<FileDigest>)>,
+}
+
+impl PropertiesView {
+    /// Read the selected entry again, e.g. after changing it
+    pub fn refresh(&mut self) {
+        self.path = None;
+    }
+
+    /// List the digests `:hash` computed under the properties
+    pub fn show_digests(&mut self, algorithm: &'static str, digests: Vec<FileDigest>) {
+        self.digests = Some((algorithm, digests));
+    }
+}
+
+/// Marker for the properties panel
+#[derive(Component)]
+struct PropertiesPanel;
+
+/// Marker for the properties panel text
+#[derive(Component)]
+struct PropertiesText;
+
+pub struct PropertiesPlugin;
+
+impl Plugin for PropertiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PropertiesView>().add_systems(
+            Update,
+            (
+                handle_properties_keys.after(crate::handle_keyboard),
+                handle_mode_grid_keys,
+                handle_chmod_command,
+                handle_chown_command,
+                handle_xattr_command,
+                refresh_properties,
+                update_properties_panel,
+            )
+                .chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_properties_keys(
+    mut commands: Commands,
+    mut key_events: EventReader<KeyboardInput>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    fly: Res<FlyCamera>,
+    mut view: ResMut<PropertiesView>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<PropertiesPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::Properties)
+    {
+        view.digests = None;
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    let pressed_i = key_events.read().any(|event| {
+        event.state == ButtonState::Pressed
+            && matches!(&event.logical_key, Key::Character(c) if c == "i")
+    });
+    if !pressed_i || *vim_mode != VimMode::Normal || prompt.pending.is_some() {
+        return;
+    }
+
+    if focus.has_focus(Panel::Properties) {
+        view.digests = None;
+        close_panel(&mut commands, &mut focus, &panel_query);
+    } else if !focus.any_open() && !fly.enabled {
+        // Read afresh: the entry may have changed since the panel last showed it
+        view.path = None;
+        open_panel(&mut commands, &mut focus);
+    }
+}
+
+/// Read the properties of the selected entry whenever the selection moves
+fn refresh_properties(
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    focus: Res<Focus>,
+    mut view: ResMut<PropertiesView>,
+) {
+    if !focus.is_open(Panel::Properties) {
+        return;
+    }
+    let selected = current_dir
+        .entries
+        .get(current_dir.selected_index)
+        .filter(|entry| entry.name != "..")
+        .map(|entry| entry.path.clone());
+    if view.path == selected {
+        return;
+    }
+    match selected
+        .as_deref()
+        .map(|path| read_properties(path, &config.format))
+    {
+        Some(Ok(properties)) => {
+            view.properties = properties;
+            view.error = None;
+        }
+        Some(Err(err)) => {
+            view.properties.clear();
+            view.error = Some(err);
+        }
+        None => {
+            view.properties.clear();
+            view.error = Some("Nothing selected".to_string());
+        }
+    }
+    view.mode = selected.as_deref().and_then(editable_mode);
+    match selected.as_deref().map(read_xattrs) {
+        Some(Ok(xattrs)) => {
+            view.xattrs = xattrs;
+            view.xattr_error = None;
+        }
+        Some(Err(err)) => {
+            view.xattrs.clear();
+            view.xattr_error = Some(err);
+        }
+        None => {
+            view.xattrs.clear();
+            view.xattr_error = None;
+        }
+    }
+    view.path = selected;
+}
+
+/// hjkl move around the rwx grid, Space flips the bit under the cursor
+fn handle_mode_grid_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    focus: Res<Focus>,
+    mut view: ResMut<PropertiesView>,
+    mut oplog: ResMut<OperationLog>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !focus.has_focus(Panel::Properties) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    let Some(mode) = view.mode else {
+        return;
+    };
+    let (mut row, mut column) = view.cursor;
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        row = (row + 1).min(2);
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        row = row.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::KeyL) || keyboard.just_pressed(KeyCode::ArrowRight) {
+        column = (column + 1).min(2);
+    }
+    if keyboard.just_pressed(KeyCode::KeyH) || keyboard.just_pressed(KeyCode::ArrowLeft) {
+        column = column.saturating_sub(1);
+    }
+    if view.cursor != (row, column) {
+        view.cursor = (row, column);
+    }
+
+    if keyboard.just_pressed(KeyCode::Space) {
+        let Some(path) = view.path.clone() else {
+            return;
+        };
+        let bit = 0o400 >> (row * 3 + column);
+        let new_mode = mode ^ bit;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        status.0 = match chmod(&path, new_mode) {
+            Ok(step) => {
+                oplog.record(format!("chmod {:04o} {}", new_mode, name), vec![step]);
+                format!("{}: {} ({:04o})", name, mode_string(new_mode), new_mode)
+            }
+            Err(reason) => format!("Can't chmod {}: {}", name, reason),
+        };
+        // Read back what the filesystem made of it
+        view.path = None;
+    }
+}
+
+fn handle_chmod_command(
+    mut run_commands: EventReader<RunCommand>,
+    command_line: Res<CommandLine>,
+    current_dir: Res<CurrentDirectory>,
+    mut view: ResMut<PropertiesView>,
+    mut oplog: ResMut<OperationLog>,
+    mut summary: ResMut<JobSummary>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Chmod(change) = command else {
+            continue;
+        };
+        let targets = command_line.targets(&current_dir);
+        let chmod_entry = |entry: &FileEntry| {
+            let mode = current_mode(&entry.path).ok_or("can't read its mode")?;
+            let new_mode = change.apply(mode, entry.is_dir);
+            chmod(&entry.path, new_mode).map(|step| (step, new_mode))
+        };
+        status.0 = match targets.as_slice() {
+            [] => "Nothing selected".to_string(),
+            [entry] => match chmod_entry(entry) {
+                Ok((step, mode)) => {
+                    oplog.record(format!("chmod {} {}", change, entry.name), vec![step]);
+                    format!("{}: {} ({:04o})", entry.name, mode_string(mode), mode)
+                }
+                Err(reason) => format!("Can't chmod {}: {}", entry.name, reason),
+            },
+            entries => {
+                let mut steps = Vec::new();
+                let mut failed = Vec::new();
+                for entry in entries {
+                    match chmod_entry(entry) {
+                        Ok((step, _)) => steps.push(step),
+                        Err(reason) => failed.push((entry.path.clone(), reason)),
+                    }
+                }
+                let label = format!("chmod {}", change);
+                finish_change(label, steps, failed, &mut oplog, &mut summary)
+            }
+        };
+        view.path = None;
+    }
+}
+
+fn handle_chown_command(
+    mut run_commands: EventReader<RunCommand>,
+    command_line: Res<CommandLine>,
+    current_dir: Res<CurrentDirectory>,
+    mut prompt: ResMut<Prompt>,
+    mut view: ResMut<PropertiesView>,
+    mut oplog: ResMut<OperationLog>,
+    mut summary: ResMut<JobSummary>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Chown { user, group } = command else {
+            continue;
+        };
+        let targets: Vec<PathBuf> = command_line
+            .targets(&current_dir)
+            .into_iter()
+            .map(|entry| entry.path.clone())
+            .collect();
+        if targets.is_empty() {
+            status.0 = "Nothing selected".to_string();
+            continue;
+        }
+        let (uid, gid) = match resolve_owner(user.as_deref(), group.as_deref()) {
+            Ok(ids) => ids,
+            Err(message) => {
+                status.0 = message;
+                continue;
+            }
+        };
+        let request = ChownRequest {
+            spec: format!(
+                "{}{}",
+                user.as_deref().unwrap_or(""),
+                group
+                    .as_ref()
+                    .map(|g| format!(":{}", g))
+                    .unwrap_or_default()
+            ),
+            targets,
+            uid,
+            gid,
+        };
+        // Symlinks to directories don't count: recursion doesn't follow them
+        let has_dirs = request
+            .targets
+            .iter()
+            .any(|path| std::fs::symlink_metadata(path).is_ok_and(|m| m.is_dir()));
+        if has_dirs {
+            prompt.pending = Some(PendingPrompt::ConfirmChown(request));
+        } else {
+            status.0 = request.run(false, &mut oplog, &mut summary);
+            view.path = None;
+        }
+    }
+}
+
+fn handle_xattr_command(
+    mut run_commands: EventReader<RunCommand>,
+    command_line: Res<CommandLine>,
+    current_dir: Res<CurrentDirectory>,
+    mut view: ResMut<PropertiesView>,
+    mut oplog: ResMut<OperationLog>,
+    mut summary: ResMut<JobSummary>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Xattr { name, value } = command else {
+            continue;
+        };
+        let action = if value.is_some() { "set" } else { "rm" };
+        let set_xattr = |entry: &FileEntry| {
+            ops::set_xattr(&entry.path, name, value.as_ref().map(|v| v.as_bytes()))
+                .map_err(|err| xattr_error(&err))
+        };
+        let targets = command_line.targets(&current_dir);
+        status.0 = match targets.as_slice() {
+            [] => "Nothing selected".to_string(),
+            [entry] => match set_xattr(entry) {
+                Ok(step) => {
+                    let label = format!("xattr {} {} {}", action, name, entry.name);
+                    oplog.record(label.clone(), vec![step]);
+                    label
+                }
+                Err(reason) => format!("Can't {} {} on {}: {}", action, name, entry.name, reason),
+            },
+            entries => {
+                let mut steps = Vec::new();
+                let mut failed = Vec::new();
+                for entry in entries {
+                    match set_xattr(entry) {
+                        Ok(step) => steps.push(step),
+                        Err(reason) => failed.push((entry.path.clone(), reason)),
+                    }
+                }
+                let label = format!("xattr {} {}", action, name);
+                finish_change(label, steps, failed, &mut oplog, &mut summary)
+            }
+        };
+        view.refresh();
+    }
+}
+
+fn update_properties_panel(
+    view: Res<PropertiesView>,
+    previews: Res<Previews>,
+    focus: Res<Focus>,
+    mut text_query: Query<&mut Text, With<PropertiesText>>,
+) {
+    if !focus.is_open(Panel::Properties) {
+        return;
+    }
+    let mut sections = vec![TextSection::new(
+        "PROPERTIES  i/Esc:close\n",
+        panel_style(FELIPE_ORANGE),
+    )];
+    if let Some(err) = &view.error {
+        sections.push(TextSection::new(
+            format!("{}\n", err),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    let facts = view
+        .path
+        .as_deref()
+        .map(|path| previews.facts(path))
+        .unwrap_or_default();
+    let labelled = view
+        .properties
+        .iter()
+        .map(|property| (property.label, &property.value))
+        .chain(facts.iter().map(|(label, value)| (*label, value)));
+    for (label, value) in labelled {
+        sections.push(TextSection::new(
+            format!("{:>9}  ", label),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+        sections.push(TextSection::new(
+            format!("{}\n", value),
+            panel_style(FELIPE_ORANGE),
+        ));
+    }
+    if let Some(mode) = view.mode {
+        sections.push(TextSection::new(
+            format!("\n{:>9}   r   w   x   hjkl:move  Space:flip\n", ""),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+        for (row, class) in ["user", "group", "other"].into_iter().enumerate() {
+            sections.push(TextSection::new(
+                format!("{:>9} ", class),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+            for column in 0..3 {
+                let set = mode & (0o400 >> (row * 3 + column)) != 0;
+                let under_cursor = view.cursor == (row, column);
+                sections.push(TextSection::new(
+                    match (under_cursor, set) {
+                        (true, true) => " <x>",
+                        (true, false) => " < >",
+                        (false, true) => " [x]",
+                        (false, false) => " [ ]",
+                    },
+                    panel_style(if under_cursor {
+                        FELIPE_ORANGE
+                    } else {
+                        FELIPE_ORANGE_DIM
+                    }),
+                ));
+            }
+            sections.push(TextSection::new("\n", panel_style(FELIPE_ORANGE_DIM)));
+        }
+    }
+    if view.path.is_some() {
+        sections.push(TextSection::new(
+            "\n   xattrs  :xattr set name value  :xattr rm name\n",
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+        if let Some(err) = &view.xattr_error {
+            sections.push(TextSection::new(
+                format!("{:>9}  {}\n", "", err),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+        } else if view.xattrs.is_empty() {
+            sections.push(TextSection::new(
+                format!("{:>9}  none\n", ""),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+        }
+        for (name, value) in &view.xattrs {
+            sections.push(TextSection::new(
+                format!("{:>9}  {} ", "", name),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+            sections.push(TextSection::new(
+                format!("{}\n", value),
+                panel_style(FELIPE_ORANGE),
+            ));
+        }
+    }
+
+    if let Some((algorithm, digests)) = &view.digests {
+        sections.push(TextSection::new("\n", panel_style(FELIPE_ORANGE_DIM)));
+        for (i, file) in digests.iter().enumerate() {
+            let label = if i == 0 { *algorithm } else { "" };
+            sections.push(TextSection::new(
+                format!("{:>9}  {} ", label, file.name),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+            sections.push(match &file.digest {
+                Ok(digest) => TextSection::new(format!("{}\n", digest), panel_style(FELIPE_ORANGE)),
+                Err(err) => {
+                    TextSection::new(format!("({})\n", err), panel_style(FELIPE_ORANGE_DIM))
+                }
+            });
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+/// Show the panel (or bring it forward) with the keyboard on it
+pub fn open_panel(commands: &mut Commands, focus: &mut Focus) {
+    if !focus.is_open(Panel::Properties) {
+        spawn_panel(commands);
+    }
+    focus.open(Panel::Properties);
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    right: Val::Px(10.0),
+                    max_width: Val::Px(620.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            PropertiesPanel,
+            Focusable(Panel::Properties),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), PropertiesText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<PropertiesPanel>>,
+) {
+    focus.close(Panel::Properties);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
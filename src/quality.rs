@@ -0,0 +1,140 @@
+//! Quality - lighter rendering for integrated graphics
+//!
+//! `quality` under `[render]`, `:set quality low|medium|high` or `--gpu-low`
+//! (low for this run) trade looks for frame rate:
+//!
+//! - `high`: everything, the grid out to 50 lines each way (the default)
+//! - `medium`: no bloom (it renders in HDR with a chain of blurs), 25 lines
+//! - `low`: no bloom and no CRT overlay either, 12 lines
+//!
+//! The grid is drawn again every frame, so its lines count on weak GPUs.
+//! `:set quality` alone shows the one in use.
+//!
+//! ```toml
+//! [render]
+//! quality = "medium"
+//! ```
+
+use bevy::core_pipeline::bloom::BloomSettings;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::{MainCamera, StatusMessage};
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl Quality {
+    pub fn name(self) -> &'static str {
+        match self {
+            Quality::Low => "low",
+            Quality::Medium => "medium",
+            Quality::High => "high",
+        }
+    }
+
+    /// Grid lines on each side of the origin
+    pub fn grid_lines(self) -> i32 {
+        match self {
+            Quality::Low => 12,
+            Quality::Medium => 25,
+            Quality::High => 50,
+        }
+    }
+
+    /// Screen effects (the CRT overlay)
+    pub fn effects(self) -> bool {
+        self != Quality::Low
+    }
+}
+
+impl std::str::FromStr for Quality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "low" => Ok(Quality::Low),
+            "med" | "medium" => Ok(Quality::Medium),
+            "high" => Ok(Quality::High),
+            _ => Err(format!("Unknown quality: {} (low, medium, high)", s)),
+        }
+    }
+}
+
+/// Whether the camera renders with bloom, all settings considered
+pub fn wants_bloom(config: &Config) -> bool {
+    let render = &config.render;
+    // A see-through background is drawn without HDR
+    render.bloom
+        && !render.accessible
+        && render.quality == Quality::High
+        && !config.window.transparent
+}
+
+pub struct QualityPlugin;
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                handle_quality_command,
+                apply_bloom.run_if(resource_changed::<Config>),
+            )
+                .chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_quality_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut config: ResMut<Config>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Quality(quality) = command else {
+            continue;
+        };
+        if let Some(quality) = quality {
+            config.render.quality = *quality;
+        }
+        status.0 = format!("quality={}", config.render.quality.name());
+    }
+}
+
+/// Put bloom on the camera or take it off; `setup_camera` made it with the
+/// settings at startup
+fn apply_bloom(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut camera_query: Query<(Entity, &mut Camera, Has<BloomSettings>), With<MainCamera>>,
+) {
+    let bloom = wants_bloom(&config);
+    for (entity, mut camera, has_bloom) in camera_query.iter_mut() {
+        if bloom == has_bloom {
+            continue;
+        }
+        // Bloom needs HDR to pick up the emissive entries
+        camera.hdr = bloom;
+        if bloom {
+            commands.entity(entity).insert(BloomSettings {
+                intensity: config.render.bloom_intensity.clamp(0.0, 1.0),
+                ..BloomSettings::NATURAL
+            });
+        } else {
+            commands.entity(entity).remove::<BloomSettings>();
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! Recording - a fly-around of the directory, saved as frames or a video
+//!
+//! `:record` circles the camera once around the whole directory, rising over
+//! it halfway, and saves every frame; the status line and panels are hidden
+//! meanwhile. `:record` again or Esc stops early.
+//!
+//! ```text
+//! :record                  ~/Videos/felipe-20250101-120000.mp4
+//! :record demo.webm        a video through ffmpeg, which must be installed
+//! :record frames/          PNG frames, frame-00001.png and on
+//! ```
+//!
+//! Frames come at a steady 30 a second of video however fast they render, so
+//! the video plays smoothly on a slow machine too.
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::window::PrimaryWindow;
+use std::f32::consts::{PI, TAU};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command as Process, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+
+use crate::bookmarks::expand_home;
+use crate::command::{Command, RunCommand};
+use crate::{
+    calculate_camera_position, frame_directory, CameraState, CurrentDirectory, Layouts, MainCamera,
+    StatusMessage, UiElement,
+};
+
+/// Frames a second of video
+const RECORD_FPS: u32 = 30;
+
+/// Length of the fly-around
+const RECORD_SECONDS: f32 = 12.0;
+
+/// Camera angle above the floor at the start and end, and added at the top
+const START_ANGLE: f32 = 0.45;
+const RISE_ANGLE: f32 = 0.6;
+
+/// Where the frames go
+enum Target {
+    /// PNG files in a directory
+    Frames(PathBuf),
+    /// Piped raw into ffmpeg
+    Video(PathBuf),
+}
+
+impl Target {
+    fn path(&self) -> &Path {
+        match self {
+            Target::Frames(path) | Target::Video(path) => path,
+        }
+    }
+}
+
+/// A recording running, or still being written out
+struct Take {
+    /// Frames asked for so far
+    frame: u32,
+    /// Where the fly-around starts (the whole directory in view)
+    start: CameraState,
+    /// Dropped when the last frame is asked for, which ends the worker
+    frames: Option<Sender<Image>>,
+    worker: JoinHandle<Result<u32, String>>,
+    path: PathBuf,
+    /// UI that was showing and how, put back afterwards
+    hidden: Vec<(Entity, Visibility)>,
+}
+
+#[derive(Resource, Default)]
+pub struct Recording {
+    take: Option<Take>,
+}
+
+impl Recording {
+    /// Frames are being captured (not just written out)
+    pub fn is_active(&self) -> bool {
+        self.take.as_ref().is_some_and(|take| take.frames.is_some())
+    }
+}
+
+pub struct RecordPlugin;
+
+impl Plugin for RecordPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recording>().add_systems(
+            Update,
+            (
+                handle_record_command,
+                capture_frame.after(crate::update_camera),
+                finish_recording,
+            )
+                .chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_record_command(
+    mut run_commands: EventReader<RunCommand>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    current_dir: Res<CurrentDirectory>,
+    layouts: Res<Layouts>,
+    camera_state: Res<CameraState>,
+    mut recording: ResMut<Recording>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if recording.is_active() && keyboard.just_pressed(KeyCode::Escape) {
+        stop(&mut recording);
+    }
+    for RunCommand(command) in run_commands.read() {
+        let Command::Record(path) = command else {
+            continue;
+        };
+        if recording.is_active() {
+            stop(&mut recording);
+            continue;
+        }
+        if recording.take.is_some() {
+            status.0 = "The last recording is still being written".to_string();
+            continue;
+        }
+        let target = match path {
+            Some(path) if path.extension().is_some() => {
+                Target::Video(current_dir.path.join(expand_home(path)))
+            }
+            Some(path) => Target::Frames(current_dir.path.join(expand_home(path))),
+            None => Target::Video(default_video_path()),
+        };
+        let mut start = camera_state.clone();
+        frame_directory(&current_dir, &layouts, &mut start, START_ANGLE);
+
+        let path = target.path().to_path_buf();
+        let (sender, receiver) = mpsc::channel();
+        recording.take = Some(Take {
+            frame: 0,
+            start,
+            frames: Some(sender),
+            worker: std::thread::spawn(move || write_frames(target, receiver)),
+            path: path.clone(),
+            hidden: Vec::new(),
+        });
+        info!("Recording to {}", path.display());
+    }
+}
+
+/// Put the camera on the path and ask for this frame
+fn capture_frame(
+    mut recording: ResMut<Recording>,
+    screenshots: Option<ResMut<ScreenshotManager>>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    mut ui_query: Query<(Entity, &mut Visibility), With<UiElement>>,
+) {
+    let Some(take) = recording.take.as_mut() else {
+        return;
+    };
+    let Some(frames) = take.frames.clone() else {
+        return;
+    };
+    // Felipe's own status line and panels aren't part of the picture
+    for (entity, mut visibility) in ui_query.iter_mut() {
+        if *visibility != Visibility::Hidden {
+            take.hidden.push((entity, *visibility));
+            *visibility = Visibility::Hidden;
+        }
+    }
+
+    let total = (RECORD_SECONDS * RECORD_FPS as f32) as u32;
+    let t = take.frame as f32 / total as f32;
+    let mut state = take.start.clone();
+    state.yaw += TAU * t;
+    state.angle += RISE_ANGLE * (PI * t).sin();
+    for mut transform in camera_query.iter_mut() {
+        *transform = Transform::from_translation(calculate_camera_position(&state))
+            .looking_at(state.focus(), Vec3::Y);
+    }
+
+    // Apps without a renderer have nothing to capture
+    let (Some(mut screenshots), Ok(window)) = (screenshots, window_query.get_single()) else {
+        return;
+    };
+    // The last frame may still be on its way back from the GPU
+    let asked = screenshots.take_screenshot(window, move |image| {
+        let _ = frames.send(image);
+    });
+    if asked.is_ok() {
+        take.frame += 1;
+        if take.frame > total {
+            take.frames = None;
+        }
+    }
+}
+
+/// Show the UI again and say how it went once the frames are written
+fn finish_recording(
+    mut recording: ResMut<Recording>,
+    mut status: ResMut<StatusMessage>,
+    mut ui_query: Query<&mut Visibility, With<UiElement>>,
+) {
+    let done = recording
+        .take
+        .as_ref()
+        .is_some_and(|take| take.frames.is_none());
+    if !done {
+        return;
+    }
+    let Some(take) = recording.take.as_mut() else {
+        return;
+    };
+    for (entity, shown) in take.hidden.drain(..) {
+        if let Ok(mut visibility) = ui_query.get_mut(entity) {
+            *visibility = shown;
+        }
+    }
+    if !take.worker.is_finished() {
+        return;
+    }
+    let Some(take) = recording.take.take() else {
+        return;
+    };
+    status.0 = match take.worker.join() {
+        Ok(Ok(frames)) => format!("Recorded {} frames to {}", frames, take.path.display()),
+        Ok(Err(err)) => format!("Recording failed: {}", err),
+        Err(_) => "Recording failed".to_string(),
+    };
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn stop(recording: &mut Recording) {
+    if let Some(take) = recording.take.as_mut() {
+        take.frames = None;
+    }
+}
+
+/// `felipe-<time>.mp4` in the videos directory, or home
+fn default_video_path() -> PathBuf {
+    let name = format!(
+        "felipe-{}.mp4",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    dirs::video_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join(name)
+}
+
+/// Write the frames as they arrive until the channel closes; runs on a worker
+/// thread
+fn write_frames(target: Target, frames: Receiver<Image>) -> Result<u32, String> {
+    if let Target::Frames(dir) = &target {
+        std::fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    }
+    let mut ffmpeg: Option<Child> = None;
+    let mut written = 0;
+    for image in frames {
+        // The alpha channel holds brightness with HDR; drop it
+        let rgb = image
+            .try_into_dynamic()
+            .map_err(|err| err.to_string())?
+            .to_rgb8();
+        written += 1;
+        match &target {
+            Target::Frames(dir) => {
+                let path = dir.join(format!("frame-{:05}.png", written));
+                rgb.save(&path)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+            }
+            Target::Video(path) => {
+                // Started on the first frame, which tells the size
+                let child = match &mut ffmpeg {
+                    Some(child) => child,
+                    None => ffmpeg.insert(start_ffmpeg(path, rgb.width(), rgb.height())?),
+                };
+                let stdin = child.stdin.as_mut().ok_or("ffmpeg has no input")?;
+                stdin
+                    .write_all(rgb.as_raw())
+                    .map_err(|err| format!("ffmpeg stopped: {}", err))?;
+            }
+        }
+    }
+    if let Some(mut child) = ffmpeg {
+        // Closing its input ends the video
+        drop(child.stdin.take());
+        let exit = child.wait().map_err(|err| err.to_string())?;
+        if !exit.success() {
+            return Err(format!("ffmpeg failed ({})", exit));
+        }
+    }
+    Ok(written)
+}
+
+fn start_ffmpeg(path: &Path, width: u32, height: u32) -> Result<Child, String> {
+    Process::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgb24",
+        ])
+        .args(["-s", &format!("{}x{}", width, height)])
+        .args(["-r", &RECORD_FPS.to_string(), "-i", "-"])
+        // Most encoders want even sides
+        .args([
+            "-vf",
+            "scale=trunc(iw/2)*2:trunc(ih/2)*2",
+            "-pix_fmt",
+            "yuv420p",
+        ])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("cannot run ffmpeg: {}", err))
+}
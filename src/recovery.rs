@@ -0,0 +1,608 @@
+//! Crash recovery - where Felipe was, and transfers a crash cut short
+//!
+//! When Felipe panics, the session (the directory and selection, a
+//! workspace's tabs and the jobs still queued) is written to
+//! `crashed-session.json` in the data directory, and the next start asks
+//! whether to go back to it.
+//!
+//! Every transfer is noted in the journal (metadata.rs) before its first step
+//! and crossed out after its last, so one still there at startup was cut short
+//! by a crash, a kill or a power cut. Each step is noted too once its target
+//! is complete, before a move across filesystems deletes its source. The
+//! sizes on disk can't tell: a large file is set to its full length before
+//! its chunks are written (see copier.rs). With the notes and the disk:
+//!
+//! - a noted step is done, or left its source behind
+//! - a target that isn't noted is a halfway copy, or a step that never got
+//!   to note itself; either way it can't be trusted
+//! - a step without a target never started
+//!
+//! The next start asks what to do about it:
+//!
+//! - `c` continues: halfway copies go to the trash and are queued again with
+//!   the steps that never started
+//! - `r` rolls back: halfway copies go to the trash, done steps are undone
+//! - Esc leaves everything as it is
+//!
+//! Only a noted step loses its source to the trash. Either way the done steps
+//! and whatever went to the trash end up in the operation log, so `u` still
+//! works.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::jobs::JobQueue;
+use crate::oplog::OperationLog;
+use crate::ops::{self, PlannedStep, TransferKind, TransferPlan, UndoStep};
+use crate::workspace::Workspace;
+use crate::{data_dir, metadata, CurrentDirectory, PendingPrompt, Prompt, StatusMessage};
+
+/// The session as JSON, kept current for the panic hook to write out
+static SESSION: Mutex<Option<String>> = Mutex::new(None);
+
+fn session_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("crashed-session.json"))
+}
+
+/// What `crashed-session.json` holds
+#[derive(Deserialize)]
+struct Session {
+    path: PathBuf,
+    selected: Option<PathBuf>,
+    workspace: Workspace,
+    /// (label, plan) of the jobs that hadn't started
+    queued: Vec<(String, TransferPlan)>,
+}
+
+/// `Session`, written from the live resources without copying them
+#[derive(Serialize)]
+struct SessionRef<'a> {
+    path: &'a Path,
+    selected: Option<&'a Path>,
+    workspace: &'a Workspace,
+    queued: Vec<(&'a str, &'a TransferPlan)>,
+}
+
+/// Write the session out when Felipe panics, for the next start to offer
+pub fn save_on_panic() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // The panic may have struck while the session was being updated
+        let session = SESSION.try_lock().ok().and_then(|session| session.clone());
+        if let (Some(session), Some(path)) = (session, session_file()) {
+            let _ = std::fs::write(path, session);
+        }
+        default_hook(info);
+    }));
+}
+
+// =============================================================================
+// Interrupted Transfers
+// =============================================================================
+
+/// A journaled transfer that never finished
+struct Interrupted {
+    id: i64,
+    label: String,
+    plan: TransferPlan,
+    /// Indexes of the steps noted with a complete target
+    whole: HashSet<usize>,
+}
+
+/// How far a step of an interrupted transfer got
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StepState {
+    NotStarted,
+    /// The target is there but wasn't noted complete: a copy stopped halfway,
+    /// or the step stopped before it could say it was done
+    Partial,
+    /// A move across filesystems copied everything but stopped deleting the
+    /// source
+    Leftover,
+    Done,
+}
+
+/// `whole` is whether the journal noted the step's target complete
+fn step_state(kind: TransferKind, step: &PlannedStep, whole: bool) -> StepState {
+    let source = std::fs::symlink_metadata(&step.source).is_ok();
+    let target = std::fs::symlink_metadata(&step.target).is_ok();
+    let moves = matches!(kind, TransferKind::Move | TransferKind::Trash);
+    match (whole, target) {
+        (true, _) if moves && source => StepState::Leftover,
+        (true, _) => StepState::Done,
+        // A rename doesn't stop halfway
+        (false, true) if moves && !source => StepState::Done,
+        (false, true) => StepState::Partial,
+        (false, false) if moves && !source => StepState::Done,
+        (false, false) => StepState::NotStarted,
+    }
+}
+
+/// What undoes a done step
+fn undo_step(kind: TransferKind, step: &PlannedStep) -> UndoStep {
+    match kind {
+        TransferKind::Copy => UndoStep::Copied {
+            source: step.source.clone(),
+            target: step.target.clone(),
+        },
+        TransferKind::Move => UndoStep::Moved {
+            from: step.source.clone(),
+            to: step.target.clone(),
+        },
+        TransferKind::Symlink | TransferKind::Hardlink => UndoStep::Linked {
+            target: step.target.clone(),
+        },
+        TransferKind::Trash => UndoStep::Trashed {
+            from: step.source.clone(),
+            to: step.target.clone(),
+        },
+    }
+}
+
+impl Interrupted {
+    fn states(&self) -> Vec<StepState> {
+        self.plan
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| step_state(self.plan.kind, step, self.whole.contains(&index)))
+            .collect()
+    }
+
+    fn question(&self) -> String {
+        let states = self.states();
+        let count = |state| states.iter().filter(|s| **s == state).count();
+        format!(
+            "Interrupted: {} - {} done, {} halfway, {} not started - c:continue  r:roll back  Esc:leave",
+            self.label,
+            count(StepState::Done) + count(StepState::Leftover),
+            count(StepState::Partial),
+            count(StepState::NotStarted)
+        )
+    }
+
+    /// Sort the steps out as the user chose; what to tell them
+    fn resolve(self, choice: Choice, oplog: &mut OperationLog, jobs: &mut JobQueue) -> String {
+        let states = self.states();
+        let Resolution { done, trash, rest } = sort_out(self.plan, &states, choice);
+        let mut cleanup = Vec::new();
+        let mut failures = Vec::new();
+        for path in trash {
+            match ops::trash_entry(&path) {
+                Ok(step) => cleanup.push(step),
+                Err(err) => failures.push(format!("trash {} ({})", path.display(), err)),
+            }
+        }
+
+        oplog.record_outcome(
+            format!("clean up after {}", self.label),
+            cleanup,
+            failures.clone(),
+        );
+        let undo = choice == Choice::RollBack && !done.is_empty();
+        oplog.record(self.label.clone(), done);
+        let message = match choice {
+            Choice::Continue => {
+                let left = rest.steps.len();
+                if left > 0 {
+                    jobs.push(self.label.clone(), rest);
+                }
+                format!("Continuing {}: {} steps queued again", self.label, left)
+            }
+            Choice::RollBack if undo => {
+                format!("Rolled back - {}", oplog.undo_last().unwrap_or_default())
+            }
+            Choice::RollBack => format!("Rolled back {}: nothing was done yet", self.label),
+            Choice::Leave => format!("Left {} as it was (u undoes what was done)", self.label),
+        };
+        if let Err(err) = metadata::end_transfer(self.id) {
+            warn!("Cannot note {} as finished: {}", self.label, err);
+        }
+        match failures.first() {
+            Some(first) => format!("{} - {} failed: {}", message, failures.len(), first),
+            None => message,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Choice {
+    Continue,
+    RollBack,
+    Leave,
+}
+
+/// What becomes of an interrupted transfer's steps
+struct Resolution {
+    /// Done steps, for the operation log
+    done: Vec<UndoStep>,
+    /// Halfway targets and leftover sources
+    trash: Vec<PathBuf>,
+    /// Steps to queue again
+    rest: TransferPlan,
+}
+
+fn sort_out(plan: TransferPlan, states: &[StepState], choice: Choice) -> Resolution {
+    let kind = plan.kind;
+    let mut resolution = Resolution {
+        done: Vec::new(),
+        trash: Vec::new(),
+        rest: TransferPlan {
+            kind,
+            steps: Vec::new(),
+            skipped: Vec::new(),
+        },
+    };
+    for (step, state) in plan.steps.into_iter().zip(states) {
+        match (state, choice) {
+            (StepState::Done, _) => resolution.done.push(undo_step(kind, &step)),
+            (StepState::NotStarted, Choice::Continue) => resolution.rest.steps.push(step),
+            (StepState::Partial, Choice::Continue) => {
+                resolution.trash.push(step.target.clone());
+                resolution.rest.steps.push(step);
+            }
+            (StepState::Partial, Choice::RollBack) => resolution.trash.push(step.target),
+            // The target was noted whole; what's left of the source goes,
+            // then it's a done move
+            (StepState::Leftover, Choice::Continue | Choice::RollBack) => {
+                resolution.trash.push(step.source.clone());
+                resolution.done.push(undo_step(kind, &step));
+            }
+            (StepState::NotStarted | StepState::Partial | StepState::Leftover, _) => {}
+        }
+    }
+    resolution
+}
+
+// =============================================================================
+// Plugin
+// =============================================================================
+
+/// What the last run left behind, asked about one at a time
+#[derive(Resource, Default)]
+struct Recovery {
+    session: Option<Session>,
+    interrupted: VecDeque<Interrupted>,
+    /// The prompt up now is about the session, not a transfer
+    asking_session: bool,
+}
+
+pub struct RecoveryPlugin;
+
+impl Plugin for RecoveryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recovery>()
+            .add_systems(Startup, load_recovery)
+            .add_systems(Update, (ask_recovery, answer_recovery, mirror_session))
+            .add_systems(Last, forget_session_on_exit);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn load_recovery(mut recovery: ResMut<Recovery>) {
+    if let Some(path) = session_file().filter(|path| path.exists()) {
+        match std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| serde_json::from_str(&text).map_err(|err| err.to_string()))
+        {
+            Ok(session) => recovery.session = Some(session),
+            Err(err) => {
+                warn!("Cannot read {}: {}", path.display(), err);
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+    match metadata::unfinished_transfers() {
+        Ok(transfers) => {
+            recovery.interrupted = transfers
+                .into_iter()
+                .map(|(id, label, plan, whole)| Interrupted {
+                    id,
+                    label,
+                    plan,
+                    whole,
+                })
+                .collect();
+        }
+        Err(err) => warn!("Cannot read the transfer journal: {}", err),
+    }
+}
+
+/// Put the next question up once nothing else is being asked
+fn ask_recovery(mut recovery: ResMut<Recovery>, mut prompt: ResMut<Prompt>) {
+    if prompt.pending.is_some() {
+        return;
+    }
+    let recovery = &mut *recovery;
+    let question = if let Some(session) = &recovery.session {
+        recovery.asking_session = true;
+        let mut details = Vec::new();
+        if session.workspace.tab_count() > 1 {
+            details.push(format!("{} tabs", session.workspace.tab_count()));
+        }
+        if !session.queued.is_empty() {
+            details.push(format!("{} queued jobs", session.queued.len()));
+        }
+        format!(
+            "Felipe crashed last time in {}{} - y:go back there  n:start here",
+            session.path.display(),
+            if details.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", details.join(", "))
+            }
+        )
+    } else if let Some(interrupted) = recovery.interrupted.front() {
+        recovery.asking_session = false;
+        interrupted.question()
+    } else {
+        return;
+    };
+    prompt.pending = Some(PendingPrompt::Recover(question));
+}
+
+fn answer_recovery(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut recovery: ResMut<Recovery>,
+    mut prompt: ResMut<Prompt>,
+    mut workspace: ResMut<Workspace>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut jobs: ResMut<JobQueue>,
+    mut oplog: ResMut<OperationLog>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !matches!(prompt.pending, Some(PendingPrompt::Recover(_))) {
+        return;
+    }
+
+    if recovery.asking_session {
+        let restore = keyboard.just_pressed(KeyCode::KeyY);
+        if !restore && !keyboard.any_just_pressed([KeyCode::KeyN, KeyCode::Escape]) {
+            return;
+        }
+        prompt.pending = None;
+        let Some(session) = recovery.session.take() else {
+            return;
+        };
+        if let Some(path) = session_file() {
+            let _ = std::fs::remove_file(path);
+        }
+        if !restore {
+            status.0 = "Starting here".to_string();
+            return;
+        }
+        *workspace = session.workspace;
+        current_dir.path = session.path;
+        current_dir.pending_select = session.selected;
+        current_dir.needs_reload = true;
+        let queued = session.queued.len();
+        for (label, plan) in session.queued {
+            jobs.push(label, plan);
+        }
+        status.0 = format!(
+            "Back in {}{}",
+            current_dir.path.display(),
+            if queued > 0 {
+                format!(", {} jobs queued again", queued)
+            } else {
+                String::new()
+            }
+        );
+        return;
+    }
+
+    let choice = if keyboard.just_pressed(KeyCode::KeyC) {
+        Choice::Continue
+    } else if keyboard.just_pressed(KeyCode::KeyR) {
+        Choice::RollBack
+    } else if keyboard.just_pressed(KeyCode::Escape) {
+        Choice::Leave
+    } else {
+        return;
+    };
+    prompt.pending = None;
+    if let Some(interrupted) = recovery.interrupted.pop_front() {
+        status.0 = interrupted.resolve(choice, &mut oplog, &mut jobs);
+        current_dir.keep_selection();
+    }
+}
+
+/// Keep the JSON the panic hook writes in step with the live session
+fn mirror_session(
+    current_dir: Res<CurrentDirectory>,
+    workspace: Res<Workspace>,
+    jobs: Res<JobQueue>,
+) {
+    if !current_dir.is_changed() && !workspace.is_changed() && !jobs.is_changed() {
+        return;
+    }
+    let session = SessionRef {
+        path: &current_dir.path,
+        selected: current_dir.selected_path(),
+        workspace: &workspace,
+        queued: jobs.queued().collect(),
+    };
+    match serde_json::to_string(&session) {
+        Ok(json) => *SESSION.lock().unwrap_or_else(|e| e.into_inner()) = Some(json),
+        Err(err) => warn!("Cannot keep the session for crash recovery: {}", err),
+    }
+}
+
+/// A panic on a worker thread that Felipe survived wrote the session too;
+/// a clean exit means there's nothing to go back to, unless the last crash's
+/// session hasn't been answered yet
+fn forget_session_on_exit(mut exits: EventReader<AppExit>, recovery: Res<Recovery>) {
+    if exits.read().next().is_none() || recovery.session.is_some() {
+        return;
+    }
+    if let Some(path) = session_file() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(source: &Path, target: &Path) -> PlannedStep {
+        PlannedStep {
+            source: source.to_path_buf(),
+            target: target.to_path_buf(),
+            conflict: None,
+            replace: false,
+        }
+    }
+
+    fn plan(kind: TransferKind, steps: Vec<PlannedStep>) -> TransferPlan {
+        TransferPlan {
+            kind,
+            steps,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// A source file, and where a transfer would put it
+    fn paths() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, target) = (dir.path().join("source"), dir.path().join("target"));
+        std::fs::write(&source, "content").unwrap();
+        (dir, source, target)
+    }
+
+    #[test]
+    fn a_target_that_is_not_noted_is_never_trusted() {
+        let (_dir, source, target) = paths();
+        // As a large file is: full length before its chunks are written
+        let file = std::fs::File::create(&target).unwrap();
+        file.set_len(1 << 20).unwrap();
+        for kind in [
+            TransferKind::Copy,
+            TransferKind::Move,
+            TransferKind::Hardlink,
+        ] {
+            assert_eq!(
+                step_state(kind, &step(&source, &target), false),
+                StepState::Partial,
+                "{:?}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn a_noted_move_with_its_source_left_is_a_leftover() {
+        let (_dir, source, target) = paths();
+        std::fs::write(&target, "content").unwrap();
+        let step = step(&source, &target);
+        assert_eq!(
+            step_state(TransferKind::Move, &step, true),
+            StepState::Leftover
+        );
+        assert_eq!(step_state(TransferKind::Copy, &step, true), StepState::Done);
+        std::fs::remove_file(&source).unwrap();
+        assert_eq!(step_state(TransferKind::Move, &step, true), StepState::Done);
+    }
+
+    #[test]
+    fn a_renamed_source_is_a_done_move() {
+        let (_dir, source, target) = paths();
+        std::fs::rename(&source, &target).unwrap();
+        let step = step(&source, &target);
+        assert_eq!(
+            step_state(TransferKind::Move, &step, false),
+            StepState::Done
+        );
+        assert_eq!(
+            step_state(TransferKind::Trash, &step, false),
+            StepState::Done
+        );
+    }
+
+    #[test]
+    fn no_target_is_a_step_that_never_started() {
+        let (_dir, source, target) = paths();
+        let step = step(&source, &target);
+        assert_eq!(
+            step_state(TransferKind::Copy, &step, false),
+            StepState::NotStarted
+        );
+        assert_eq!(
+            step_state(TransferKind::Move, &step, false),
+            StepState::NotStarted
+        );
+    }
+
+    fn sorted_out(kind: TransferKind, states: &[StepState], choice: Choice) -> Resolution {
+        let steps = (0..states.len())
+            .map(|i| {
+                step(
+                    Path::new(&format!("/s/{}", i)),
+                    Path::new(&format!("/t/{}", i)),
+                )
+            })
+            .collect();
+        sort_out(plan(kind, steps), states, choice)
+    }
+
+    #[test]
+    fn only_leftovers_lose_their_source() {
+        let states = [
+            StepState::Done,
+            StepState::Partial,
+            StepState::Leftover,
+            StepState::NotStarted,
+        ];
+        for choice in [Choice::Continue, Choice::RollBack, Choice::Leave] {
+            let resolution = sorted_out(TransferKind::Move, &states, choice);
+            let sources: Vec<&PathBuf> = resolution
+                .trash
+                .iter()
+                .filter(|path| path.starts_with("/s"))
+                .collect();
+            match choice {
+                Choice::Leave => assert!(resolution.trash.is_empty()),
+                _ => assert_eq!(sources, [Path::new("/s/2")], "{:?}", choice),
+            }
+        }
+    }
+
+    #[test]
+    fn continuing_trashes_halfway_targets_and_queues_them_again() {
+        let states = [StepState::Done, StepState::Partial, StepState::NotStarted];
+        let resolution = sorted_out(TransferKind::Copy, &states, Choice::Continue);
+        assert_eq!(resolution.trash, [PathBuf::from("/t/1")]);
+        let queued: Vec<&Path> = resolution
+            .rest
+            .steps
+            .iter()
+            .map(|step| step.source.as_path())
+            .collect();
+        assert_eq!(queued, [Path::new("/s/1"), Path::new("/s/2")]);
+        assert_eq!(resolution.done.len(), 1);
+    }
+
+    #[test]
+    fn rolling_back_queues_nothing() {
+        let states = [StepState::Done, StepState::Partial, StepState::NotStarted];
+        let resolution = sorted_out(TransferKind::Copy, &states, Choice::RollBack);
+        assert_eq!(resolution.trash, [PathBuf::from("/t/1")]);
+        assert!(resolution.rest.steps.is_empty());
+        assert_eq!(resolution.done.len(), 1);
+    }
+
+    #[test]
+    fn leaving_keeps_everything_but_logs_what_was_done() {
+        let states = [StepState::Done, StepState::Leftover, StepState::Partial];
+        let resolution = sorted_out(TransferKind::Move, &states, Choice::Leave);
+        assert!(resolution.trash.is_empty());
+        assert!(resolution.rest.steps.is_empty());
+        assert_eq!(resolution.done.len(), 1);
+    }
+}
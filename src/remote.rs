@@ -0,0 +1,612 @@
+//! Remote locations - `sftp://user@host/path`, `s3://bucket/prefix`,
+//! `ftp://host/path` and `dav://host/path` browsed like a local directory
+//!
+//! A remote location is kept as a path spelling its URL, so everything that
+//! only carries paths around (history, pins, tabs) works unchanged. Listings
+//! come from the backend of the scheme. The first visit connects on a worker
+//! thread and the top bar shows the connection state; the grid fills in once
+//! it's up. Copy and paste between a remote and a local directory download
+//! and upload through the copy engine (an upload never replaces a remote
+//! entry), and opening a remote file downloads it to the cache first. Moving,
+//! renaming and deleting remote entries isn't supported.
+//!
+//! Each scheme has its backend behind a Cargo feature: `sftp` (sftp.rs), `s3`
+//! (s3.rs), `ftp` for `ftp://` and `ftps://` (ftp.rs) and `webdav` for
+//! `dav://` and `davs://` (webdav.rs), and `mtp` for phones and cameras
+//! (mtp.rs). FTP and WebDAV read passwords from
+//! `~/.netrc`, so they never show up in a path:
+//!
+//! ```text
+//! machine nas.local login me password secret
+//! ```
+//!
+//! ```text
+//! felipe sftp://me@example.org/var/www
+//! :cd sftp://me@example.org:2222/srv
+//! :cd s3://my-bucket/logs/2024
+//! :cd ftps://me@nas.local/share
+//! :cd davs://me@cloud.example.org/remote.php/dav/files/me
+//! ```
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::bookmarks::expand_home;
+use crate::command::{Command, RunCommand};
+use crate::copier::CopyProgress;
+use crate::{demofs, searches, stars, volumes};
+use crate::{CurrentDirectory, FileEntry, StatusMessage};
+
+/// URL schemes felipe knows, whether or not this build has their backend
+const SCHEMES: [&str; 7] = ["sftp", "s3", "ftp", "ftps", "dav", "davs", "mtp"];
+
+/// Bytes moved per read while transferring
+#[cfg_attr(
+    not(any(feature = "sftp", feature = "ftp", feature = "webdav")),
+    allow(dead_code)
+)]
+const TRANSFER_BUFFER_SIZE: usize = 256 * 1024;
+
+/// A failed connection is tried again when visited after this long
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// An entry of a remote directory
+pub struct RemoteEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// What felipe needs from a remote filesystem; paths are absolute and
+/// `/`-separated
+pub trait Backend: Send + Sync {
+    fn list(&self, path: &str) -> io::Result<Vec<RemoteEntry>>;
+    /// The entry at `path`; NotFound if there's nothing there
+    fn stat(&self, path: &str) -> io::Result<RemoteEntry>;
+    fn download(&self, path: &str, local: &Path, progress: &CopyProgress) -> io::Result<()>;
+    fn upload(&self, local: &Path, path: &str, progress: &CopyProgress) -> io::Result<()>;
+    fn make_dir(&self, path: &str) -> io::Result<()>;
+}
+
+/// A remote path taken apart: `sftp://me@host:2222/srv/www` is scheme
+/// `sftp`, authority `me@host:2222` and path `/srv/www`; for `s3` the
+/// authority is the bucket
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub scheme: &'static str,
+    pub authority: String,
+    pub path: String,
+}
+
+impl Location {
+    /// The path spelling this location
+    pub fn to_path(&self) -> PathBuf {
+        PathBuf::from(format!("{}://{}{}", self.scheme, self.authority, self.path))
+    }
+
+    fn parent(&self) -> Option<Location> {
+        let (parent, _) = self.path.rsplit_once('/').filter(|_| self.path != "/")?;
+        Some(Location {
+            path: if parent.is_empty() { "/" } else { parent }.to_string(),
+            ..self.clone()
+        })
+    }
+
+    /// Key of the connection serving this location
+    fn server(&self) -> String {
+        format!("{}://{}", self.scheme, self.authority)
+    }
+}
+
+/// The remote location `path` spells, if it's a URL of a known scheme
+pub fn location(path: &Path) -> Option<Location> {
+    let text = path.to_str()?;
+    // Joined on Windows, the separators come out backwards
+    let text = if cfg!(windows) {
+        text.replace('\\', "/")
+    } else {
+        text.to_string()
+    };
+    let (scheme, rest) = text.split_once("://")?;
+    let scheme = SCHEMES.into_iter().find(|known| *known == scheme)?;
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    if authority.is_empty() {
+        return None;
+    }
+    let path = path.trim_matches('/');
+    Some(Location {
+        scheme,
+        authority: authority.to_string(),
+        path: format!("/{}", path),
+    })
+}
+
+/// Parent directory of `path`, remote or local; None at the top
+pub fn parent(path: &Path) -> Option<PathBuf> {
+    match location(path) {
+        Some(location) => location.parent().map(|parent| parent.to_path()),
+        // Drive and share roots lead up to the view of all drives
+        None if volumes::is_drives_view(path) => None,
+        None if stars::is_starred_view(path) => None,
+        None if demofs::is_demo_root(path) => None,
+        None if searches::is_searches_view(path) => None,
+        None if volumes::is_drive_root(path) => Some(volumes::drives_view()),
+        None => path.parent().map(Path::to_path_buf),
+    }
+}
+
+// =============================================================================
+// Connections
+// =============================================================================
+
+enum Connection {
+    Connecting,
+    Up(Arc<dyn Backend>),
+    Failed { reason: String, at: Instant },
+}
+
+/// Connections by server, shared by the UI and transfer workers
+static CONNECTIONS: LazyLock<Mutex<HashMap<String, Connection>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Set when a connection attempt ends, so the listing is read again
+static SETTLED: AtomicBool = AtomicBool::new(false);
+
+fn connections() -> std::sync::MutexGuard<'static, HashMap<String, Connection>> {
+    CONNECTIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The backend for `location`, without waiting: connecting starts on a
+/// worker and the error says why there's nothing to list yet
+fn backend_now(location: &Location) -> Result<Arc<dyn Backend>, String> {
+    let server = location.server();
+    let mut known = connections();
+    match known.get(&server) {
+        Some(Connection::Up(backend)) => return Ok(Arc::clone(backend)),
+        Some(Connection::Connecting) => return Err(format!("Connecting to {}...", server)),
+        Some(Connection::Failed { reason, at }) if at.elapsed() < RETRY_AFTER => {
+            return Err(format!("Cannot connect to {}: {}", server, reason));
+        }
+        _ => {}
+    }
+    known.insert(server.clone(), Connection::Connecting);
+    drop(known);
+    let (scheme, authority) = (location.scheme, location.authority.clone());
+    std::thread::spawn(move || {
+        let connection = match connect(scheme, &authority) {
+            Ok(backend) => Connection::Up(backend),
+            Err(err) => Connection::Failed {
+                reason: err.to_string(),
+                at: Instant::now(),
+            },
+        };
+        connections().insert(server, connection);
+        SETTLED.store(true, Ordering::Relaxed);
+    });
+    Err(format!("Connecting to {}...", location.server()))
+}
+
+/// The backend for `location`, connecting on this thread if need be
+fn backend_blocking(location: &Location) -> io::Result<Arc<dyn Backend>> {
+    let server = location.server();
+    if let Some(Connection::Up(backend)) = connections().get(&server) {
+        return Ok(Arc::clone(backend));
+    }
+    let backend = connect(location.scheme, &location.authority)?;
+    connections().insert(server, Connection::Up(Arc::clone(&backend)));
+    SETTLED.store(true, Ordering::Relaxed);
+    Ok(backend)
+}
+
+fn connect(scheme: &str, authority: &str) -> io::Result<Arc<dyn Backend>> {
+    // Left of `@` may be a password
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let started = Instant::now();
+    let backend = open_backend(scheme, authority);
+    match &backend {
+        Ok(_) => info!(
+            "Connected to {}://{} in {:?}",
+            scheme,
+            host,
+            started.elapsed()
+        ),
+        Err(err) => warn!("Cannot connect to {}://{}: {}", scheme, host, err),
+    }
+    backend
+}
+
+fn open_backend(scheme: &str, authority: &str) -> io::Result<Arc<dyn Backend>> {
+    match scheme {
+        #[cfg(feature = "sftp")]
+        "sftp" => Ok(Arc::new(crate::sftp::SftpBackend::connect(authority)?)),
+        #[cfg(feature = "s3")]
+        "s3" => Ok(Arc::new(crate::s3::S3Backend::connect(authority)?)),
+        #[cfg(feature = "ftp")]
+        "ftp" | "ftps" => Ok(Arc::new(crate::ftp::FtpBackend::connect(
+            authority,
+            scheme == "ftps",
+        )?)),
+        #[cfg(feature = "webdav")]
+        "dav" | "davs" => Ok(Arc::new(crate::webdav::WebDavBackend::connect(
+            authority,
+            scheme == "davs",
+        )?)),
+        #[cfg(all(target_os = "linux", feature = "mtp"))]
+        "mtp" => Ok(Arc::new(crate::mtp::MtpBackend::connect(authority)?)),
+        _ => {
+            let _ = authority;
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("this felipe was built without the {} feature", scheme),
+            ))
+        }
+    }
+}
+
+/// `host[:port]` taken apart, with `default` when the port is left out
+#[cfg_attr(
+    not(any(feature = "sftp", feature = "ftp", feature = "webdav")),
+    allow(dead_code)
+)]
+pub fn host_port(address: &str, default: u16) -> io::Result<(&str, u16)> {
+    match address.rsplit_once(':') {
+        Some((host, port)) => Ok((
+            host,
+            port.parse()
+                .map_err(|_| io::Error::other(format!("bad port: {}", port)))?,
+        )),
+        None => Ok((address, default)),
+    }
+}
+
+/// Password for `user` on `host` from `~/.netrc`, the way ftp and curl find it
+#[cfg_attr(not(any(feature = "ftp", feature = "webdav")), allow(dead_code))]
+pub fn netrc_password(host: &str, user: &str) -> Option<String> {
+    let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+    let text = fs::read_to_string(dirs::home_dir()?.join(name)).ok()?;
+    // (machine, login, password); `default` has no machine and comes last
+    let mut entries: Vec<(Option<&str>, Option<&str>, Option<&str>)> = Vec::new();
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match (token, entries.last_mut()) {
+            ("machine", _) => entries.push((tokens.next(), None, None)),
+            ("default", _) => entries.push((None, None, None)),
+            ("login", Some(entry)) => entry.1 = tokens.next(),
+            ("password", Some(entry)) => entry.2 = tokens.next(),
+            _ => {}
+        }
+    }
+    entries
+        .into_iter()
+        .find(|(machine, login, _)| {
+            machine.is_none_or(|machine| machine == host) && login.is_none_or(|login| login == user)
+        })
+        .and_then(|(_, _, password)| password.map(str::to_string))
+}
+
+/// Percent-encode what isn't safe in a URL path
+#[cfg_attr(not(any(feature = "mtp", feature = "webdav")), allow(dead_code))]
+pub fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Undo `encode_path`
+#[cfg_attr(not(feature = "webdav"), allow(dead_code))]
+pub fn decode_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Connection state for the top bar, empty for local paths
+pub fn connection_label(path: &Path) -> String {
+    let Some(location) = location(path) else {
+        return String::new();
+    };
+    let state = match connections().get(&location.server()) {
+        Some(Connection::Up(_)) => "connected",
+        Some(Connection::Connecting) => "connecting",
+        Some(Connection::Failed { .. }) => "offline",
+        None => "not connected",
+    };
+    format!("  [{}: {}]", location.scheme, state)
+}
+
+// =============================================================================
+// Listing and Transfers
+// =============================================================================
+
+/// Entries of a remote directory, or why they can't be listed (yet)
+pub fn read_dir(location: &Location) -> Result<Vec<FileEntry>, String> {
+    let backend = backend_now(location)?;
+    let dir = location.to_path();
+    let started = Instant::now();
+    let entries = backend.list(&location.path).map_err(|err| {
+        let reason = format!("Cannot list {}: {}", dir.display(), err);
+        warn!("{}", reason);
+        reason
+    })?;
+    debug!(
+        "{} listed {} entries in {:?}",
+        location.server(),
+        entries.len(),
+        started.elapsed()
+    );
+    Ok(entries
+        .into_iter()
+        .map(|entry| FileEntry {
+            path: dir.join(&entry.name),
+            name: entry.name,
+            is_dir: entry.is_dir,
+            size: entry.size,
+            modified: entry.modified,
+            locked: false,
+            link: None,
+            inode: None,
+            executable: false,
+        })
+        .collect())
+}
+
+/// Copy between a remote and a local location, either way
+pub fn copy(source: &Path, target: &Path, progress: &CopyProgress) -> io::Result<()> {
+    match (location(source), location(target)) {
+        (Some(from), None) => {
+            let backend = backend_blocking(&from)?;
+            let entry = backend.stat(&from.path)?;
+            download_tree(&*backend, &from.path, &entry, target, progress)
+        }
+        (None, Some(to)) => {
+            let backend = backend_blocking(&to)?;
+            match backend.stat(&to.path) {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", target.display()),
+                    ))
+                }
+            }
+            upload_tree(&*backend, source, &to.path, progress)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "copy between remote locations through a local directory",
+        )),
+    }
+}
+
+fn download_tree(
+    backend: &dyn Backend,
+    path: &str,
+    entry: &RemoteEntry,
+    local: &Path,
+    progress: &CopyProgress,
+) -> io::Result<()> {
+    if !entry.is_dir {
+        progress.add_total(entry.size);
+        return backend.download(path, local, progress);
+    }
+    fs::create_dir_all(local)?;
+    for child in backend.list(path)? {
+        download_tree(
+            backend,
+            &format!("{}/{}", path.trim_end_matches('/'), child.name),
+            &child,
+            &local.join(&child.name),
+            progress,
+        )?;
+    }
+    Ok(())
+}
+
+fn upload_tree(
+    backend: &dyn Backend,
+    local: &Path,
+    path: &str,
+    progress: &CopyProgress,
+) -> io::Result<()> {
+    let metadata = fs::metadata(local)?;
+    if !metadata.is_dir() {
+        progress.add_total(metadata.len());
+        return backend.upload(local, path, progress);
+    }
+    backend.make_dir(path)?;
+    for child in fs::read_dir(local)? {
+        let child = child?;
+        upload_tree(
+            backend,
+            &child.path(),
+            &format!("{}/{}", path, child.file_name().to_string_lossy()),
+            progress,
+        )?;
+    }
+    Ok(())
+}
+
+/// Copy `reader` to `writer`, counting bytes into `progress`
+#[cfg_attr(
+    not(any(feature = "sftp", feature = "ftp", feature = "webdav")),
+    allow(dead_code)
+)]
+pub fn transfer(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    progress: &CopyProgress,
+) -> io::Result<()> {
+    let mut buffer = vec![0; TRANSFER_BUFFER_SIZE];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return writer.flush(),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buffer[..read])?;
+        progress.add_copied(read as u64);
+    }
+}
+
+/// Download a remote file to the cache and open that with its default app
+pub fn open(path: &Path) {
+    let Some(location) = location(path) else {
+        return;
+    };
+    let Some(local) = dirs::cache_dir().map(|dir| {
+        dir.join("felipe")
+            .join("remote")
+            .join(location.scheme)
+            .join(location.authority.replace(':', "_"))
+            .join(location.path.trim_start_matches('/'))
+    }) else {
+        return;
+    };
+    std::thread::spawn(move || {
+        let downloaded = backend_blocking(&location).and_then(|backend| {
+            if let Some(dir) = local.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            backend.download(&location.path, &local, &CopyProgress::default())
+        });
+        match downloaded {
+            Ok(()) => crate::open_with_default_app(&local),
+            Err(err) => warn!("Cannot download {}: {}", location.to_path().display(), err),
+        }
+    });
+}
+
+// =============================================================================
+// Plugin
+// =============================================================================
+
+pub struct RemotePlugin;
+
+impl Plugin for RemotePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (handle_cd_command, handle_mtp_command, reload_when_settled),
+        );
+    }
+}
+
+/// `:cd path` - go to a directory, local or remote
+fn handle_cd_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Cd(path) = command else {
+            continue;
+        };
+        if let Some(location) = location(path) {
+            current_dir.set_path(location.to_path());
+            continue;
+        }
+        let path = current_dir.path.join(expand_home(path));
+        match path.canonicalize() {
+            Ok(path) if path.is_dir() => current_dir.set_path(volumes::simplified(path)),
+            _ => status.0 = format!("No such directory: {}", path.display()),
+        }
+    }
+}
+
+/// `:mtp [name]` - go to a connected phone or camera
+fn handle_mtp_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Mtp(name) = command else {
+            continue;
+        };
+        match mtp_device(name.as_deref()) {
+            Ok(location) => current_dir.set_path(location.to_path()),
+            Err(message) => status.0 = message,
+        }
+    }
+}
+
+/// The one connected device matching `name`, any when None
+#[cfg(all(target_os = "linux", feature = "mtp"))]
+fn mtp_device(name: Option<&str>) -> Result<Location, String> {
+    let devices =
+        crate::mtp::devices().map_err(|err| format!("Cannot look for devices: {}", err))?;
+    let wanted = name.map(str::to_lowercase);
+    let matching: Vec<&crate::mtp::Device> = devices
+        .iter()
+        .filter(|device| {
+            wanted.as_ref().is_none_or(|wanted| {
+                device.name.to_lowercase().contains(wanted)
+                    || device.id.to_lowercase().contains(wanted)
+            })
+        })
+        .collect();
+    match matching.as_slice() {
+        [device] => Ok(Location {
+            scheme: "mtp",
+            authority: device.id.clone(),
+            path: "/".to_string(),
+        }),
+        [] if devices.is_empty() => {
+            Err("No MTP device found - is it unlocked and set to file transfer?".to_string())
+        }
+        [] => Err(format!("No device matches {}", name.unwrap_or_default())),
+        several => {
+            let names: Vec<&str> = several.iter().map(|device| device.name.as_str()).collect();
+            Err(format!(
+                "Devices: {} - :mtp name picks one",
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "mtp")))]
+fn mtp_device(_name: Option<&str>) -> Result<Location, String> {
+    Err("this felipe was built without the mtp feature".to_string())
+}
+
+/// List the directory again once a connection to it is up (or has failed)
+fn reload_when_settled(mut current_dir: ResMut<CurrentDirectory>) {
+    if SETTLED.swap(false, Ordering::Relaxed) && location(&current_dir.path).is_some() {
+        current_dir.keep_selection();
+    }
+}
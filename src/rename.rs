@@ -0,0 +1,450 @@
+//! Rename mode - `r` edits the selected entry's name in place
+//!
+//! The 3D label follows the typed name, and collisions or names the target
+//! filesystem can't store are flagged while typing, before anything is renamed.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::dryrun::DryRunReview;
+use crate::focus::Focus;
+use crate::glitch::OperationFailed;
+use crate::oplog::OperationLog;
+use crate::ops;
+use crate::{
+    truncate_label, CurrentDirectory, FileLabel, Prompt, StatusMessage, VimMode, DIFF_MODIFIED,
+    DIFF_REMOVED, FELIPE_ORANGE,
+};
+
+/// Names Windows reserves for devices, with or without an extension
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+/// Longest name (in bytes) common filesystems accept
+const MAX_NAME_BYTES: usize = 255;
+
+// =============================================================================
+// Validation
+// =============================================================================
+
+/// What's wrong with a new name, if anything
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum NameCheck {
+    Valid,
+    /// Allowed here, but won't survive a trip to another filesystem
+    Warning(String),
+    /// Renaming would fail or clobber another entry
+    Invalid(String),
+}
+
+fn check_name(
+    name: &str,
+    original: &str,
+    siblings: &[String],
+    case_insensitive: bool,
+) -> NameCheck {
+    if name.is_empty() {
+        return NameCheck::Invalid("name is empty".to_string());
+    }
+    if name == "." || name == ".." {
+        return NameCheck::Invalid(format!("{} is not a name", name));
+    }
+    if name.contains('/') || name.contains('\0') {
+        return NameCheck::Invalid("names cannot contain '/'".to_string());
+    }
+    if name.len() > MAX_NAME_BYTES {
+        return NameCheck::Invalid(format!("longer than {} bytes", MAX_NAME_BYTES));
+    }
+    let clash = siblings.iter().find(|sibling| {
+        *sibling != original
+            && (*sibling == name
+                || case_insensitive && sibling.to_lowercase() == name.to_lowercase())
+    });
+    if let Some(sibling) = clash {
+        return NameCheck::Invalid(format!("{} already exists", sibling));
+    }
+
+    match windows_problem(name) {
+        Some(problem) if cfg!(windows) => NameCheck::Invalid(problem),
+        Some(problem) => NameCheck::Warning(format!("{} - not valid on Windows", problem)),
+        None => NameCheck::Valid,
+    }
+}
+
+fn windows_problem(name: &str) -> Option<String> {
+    if let Some(c) = name
+        .chars()
+        .find(|c| r#"<>:"\|?*"#.contains(*c) || c.is_control())
+    {
+        return Some(format!("contains {:?}", c));
+    }
+    if name.ends_with(' ') || name.ends_with('.') {
+        return Some("ends with a space or dot".to_string());
+    }
+    let stem = name.split('.').next().unwrap_or(name).to_uppercase();
+    if WINDOWS_RESERVED.contains(&stem.as_str()) {
+        return Some(format!("{} is a reserved device name", stem));
+    }
+    None
+}
+
+// =============================================================================
+// State
+// =============================================================================
+
+/// Name being typed in RENAME mode
+#[derive(Resource, Default)]
+pub struct RenameLine {
+    pub input: String,
+    /// Entry being renamed (index into the current entries) and its path
+    target: Option<(usize, PathBuf)>,
+    original: String,
+    /// Other names in the directory, captured when renaming starts
+    siblings: Vec<String>,
+    case_insensitive: bool,
+    check: Option<NameCheck>,
+}
+
+impl RenameLine {
+    /// Index of the entry whose label shows the typed name
+    pub fn target_index(&self) -> Option<usize> {
+        self.target.as_ref().map(|(index, _)| *index)
+    }
+}
+
+pub struct RenamePlugin;
+
+impl Plugin for RenamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RenameLine::default()).add_systems(
+            Update,
+            (
+                handle_rename_input.after(crate::handle_keyboard),
+                update_rename_label.after(crate::update_file_labels),
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_rename_input(
+    mut key_events: EventReader<KeyboardInput>,
+    mut vim_mode: ResMut<VimMode>,
+    mut rename: ResMut<RenameLine>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut oplog: ResMut<OperationLog>,
+    mut status: ResMut<StatusMessage>,
+    mut failures: EventWriter<OperationFailed>,
+    mut review: ResMut<DryRunReview>,
+    config: Res<Config>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed || prompt.pending.is_some() {
+            continue;
+        }
+
+        if *vim_mode == VimMode::Normal {
+            if !focus.any_open() && matches!(&event.logical_key, Key::Character(c) if c == "r") {
+                start_rename(&mut rename, &current_dir, &mut status);
+                if rename.target.is_some() {
+                    *vim_mode = VimMode::Rename;
+                }
+            }
+            continue;
+        }
+        if *vim_mode != VimMode::Rename {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(c) => rename.input.push_str(c),
+            Key::Space => rename.input.push(' '),
+            Key::Backspace => {
+                rename.input.pop();
+            }
+            Key::Escape => {
+                status.0 = "Rename cancelled".to_string();
+                finish_rename(&mut rename, &mut vim_mode);
+                continue;
+            }
+            Key::Enter => {
+                if let Some(NameCheck::Invalid(reason)) = &rename.check {
+                    status.0 = format!("Cannot rename: {}", reason);
+                    continue;
+                }
+                if config.operations.dry_run {
+                    plan_rename(&rename, &mut review);
+                } else {
+                    commit_rename(
+                        &rename,
+                        &mut current_dir,
+                        &mut oplog,
+                        &mut status,
+                        &mut failures,
+                    );
+                }
+                finish_rename(&mut rename, &mut vim_mode);
+                continue;
+            }
+            _ => continue,
+        }
+
+        let check = check_name(
+            &rename.input,
+            &rename.original,
+            &rename.siblings,
+            rename.case_insensitive,
+        );
+        status.0 = match &check {
+            NameCheck::Valid => String::new(),
+            NameCheck::Warning(reason) => format!("Warning: {}", reason),
+            NameCheck::Invalid(reason) => format!("Invalid: {}", reason),
+        };
+        rename.check = Some(check);
+    }
+}
+
+/// Show the typed name on the entry's label, colored by how valid it is
+fn update_rename_label(
+    rename: Res<RenameLine>,
+    current_dir: Res<CurrentDirectory>,
+    mut label_query: Query<(&FileLabel, &mut Text)>,
+    mut shown: Local<Option<usize>>,
+) {
+    if !rename.is_changed() {
+        return;
+    }
+    // Put the real name back on the label we were editing
+    if let Some(previous) = shown.take() {
+        if let Some(entry) = current_dir.entries.get(previous) {
+            for (label, mut text) in label_query.iter_mut() {
+                if label.index == previous {
+                    text.sections[0].value = truncate_label(&entry.name);
+                }
+            }
+        }
+    }
+    let Some(index) = rename.target_index() else {
+        return;
+    };
+    let color = match &rename.check {
+        Some(NameCheck::Invalid(_)) => DIFF_REMOVED,
+        Some(NameCheck::Warning(_)) => DIFF_MODIFIED,
+        _ => FELIPE_ORANGE,
+    };
+    for (label, mut text) in label_query.iter_mut() {
+        if label.index == index {
+            text.sections[0].value = format!("{}_", rename.input);
+            text.sections[0].style.color = color;
+        }
+    }
+    *shown = Some(index);
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn start_rename(
+    rename: &mut RenameLine,
+    current_dir: &CurrentDirectory,
+    status: &mut StatusMessage,
+) {
+    let index = current_dir.selected_index;
+    let Some(entry) = current_dir.entries.get(index) else {
+        return;
+    };
+    if entry.name == ".." {
+        status.0 = "Cannot rename ..".to_string();
+        return;
+    }
+    let siblings: Vec<String> = current_dir
+        .entries
+        .iter()
+        .filter(|e| e.name != "..")
+        .map(|e| e.name.clone())
+        .collect();
+    *rename = RenameLine {
+        input: entry.name.clone(),
+        target: Some((index, entry.path.clone())),
+        original: entry.name.clone(),
+        case_insensitive: ops::is_case_insensitive(&current_dir.path, &siblings),
+        siblings,
+        check: Some(NameCheck::Valid),
+    };
+    status.0 = "Rename: Enter to apply, Esc to cancel".to_string();
+}
+
+fn commit_rename(
+    rename: &RenameLine,
+    current_dir: &mut CurrentDirectory,
+    oplog: &mut OperationLog,
+    status: &mut StatusMessage,
+    failures: &mut EventWriter<OperationFailed>,
+) {
+    let Some((_, path)) = &rename.target else {
+        return;
+    };
+    if rename.input == rename.original {
+        status.0 = String::new();
+        return;
+    }
+    match apply_rename(path, &rename.original, &rename.input, current_dir, oplog) {
+        Ok(message) => status.0 = message,
+        Err(message) => {
+            status.0 = message;
+            failures.send(OperationFailed {
+                path: Some(path.clone()),
+            });
+        }
+    }
+}
+
+/// Hold the rename back for the dry-run review
+fn plan_rename(rename: &RenameLine, review: &mut DryRunReview) {
+    let Some((_, path)) = &rename.target else {
+        return;
+    };
+    if rename.input == rename.original {
+        return;
+    }
+    let (path, original, name) = (path.clone(), rename.original.clone(), rename.input.clone());
+    review.plan(
+        format!("rename {} -> {}", original, name),
+        vec![format!(
+            "rename {} -> {}",
+            path.display(),
+            path.with_file_name(&name).display()
+        )],
+        move |oplog, current_dir| {
+            apply_rename(&path, &original, &name, current_dir, oplog).unwrap_or_else(|err| err)
+        },
+    );
+}
+
+/// Rename `path` to `name` and log it; what to tell the user either way
+fn apply_rename(
+    path: &Path,
+    original: &str,
+    name: &str,
+    current_dir: &mut CurrentDirectory,
+    oplog: &mut OperationLog,
+) -> Result<String, String> {
+    let step = ops::rename_path(path, name)
+        .map_err(|err| format!("Could not rename {}: {}", original, err))?;
+    oplog.record(format!("rename {} -> {}", original, name), vec![step]);
+    current_dir.pending_select = path.parent().map(|dir| dir.join(name));
+    current_dir.needs_reload = true;
+    Ok(format!("Renamed {} to {}", original, name))
+}
+
+fn finish_rename(rename: &mut RenameLine, vim_mode: &mut VimMode) {
+    *rename = RenameLine::default();
+    *vim_mode = VimMode::Normal;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn siblings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn is_invalid(check: NameCheck) -> bool {
+        matches!(check, NameCheck::Invalid(_))
+    }
+
+    #[test]
+    fn ordinary_names_are_valid() {
+        let names = siblings(&["a.txt", "b.txt"]);
+        assert_eq!(
+            check_name("c.txt", "a.txt", &names, false),
+            NameCheck::Valid
+        );
+        assert_eq!(
+            check_name("日本語.md", "a.txt", &names, false),
+            NameCheck::Valid
+        );
+        assert_eq!(
+            check_name(".hidden", "a.txt", &names, false),
+            NameCheck::Valid
+        );
+    }
+
+    #[test]
+    fn names_that_are_not_names_are_invalid() {
+        for name in ["", ".", "..", "a/b", "a\0b"] {
+            assert!(is_invalid(check_name(name, "a", &[], false)), "{:?}", name);
+        }
+        let long = "x".repeat(MAX_NAME_BYTES + 1);
+        assert!(is_invalid(check_name(&long, "a", &[], false)));
+        // Bytes count, not characters: 86 three-byte characters are too many
+        assert!(is_invalid(check_name(&"あ".repeat(86), "a", &[], false)));
+        assert_eq!(
+            check_name(&"x".repeat(MAX_NAME_BYTES), "a", &[], false),
+            NameCheck::Valid
+        );
+    }
+
+    #[test]
+    fn a_sibling_with_the_name_clashes() {
+        let names = siblings(&["a.txt", "b.txt"]);
+        assert_eq!(
+            check_name("b.txt", "a.txt", &names, false),
+            NameCheck::Invalid("b.txt already exists".to_string())
+        );
+        // Keeping the name is no clash with itself
+        assert_eq!(
+            check_name("a.txt", "a.txt", &names, false),
+            NameCheck::Valid
+        );
+    }
+
+    #[test]
+    fn case_only_clashes_depend_on_the_filesystem() {
+        let names = siblings(&["a.txt", "README"]);
+        assert_eq!(
+            check_name("readme", "a.txt", &names, false),
+            NameCheck::Valid
+        );
+        assert_eq!(
+            check_name("readme", "a.txt", &names, true),
+            NameCheck::Invalid("README already exists".to_string())
+        );
+        // Changing only the case of the entry itself is fine either way
+        assert_eq!(check_name("A.TXT", "a.txt", &names, true), NameCheck::Valid);
+    }
+
+    #[test]
+    fn names_windows_rejects_are_flagged() {
+        for name in [
+            "a:b",
+            "what?",
+            "tab\tname",
+            "trailing.",
+            "trailing ",
+            "CON",
+            "nul.txt",
+            "Com1.log",
+        ] {
+            let check = check_name(name, "a", &[], false);
+            if cfg!(windows) {
+                assert!(is_invalid(check), "{:?}", name);
+            } else {
+                assert!(matches!(check, NameCheck::Warning(_)), "{:?}", name);
+            }
+        }
+        assert_eq!(check_name("console", "a", &[], false), NameCheck::Valid);
+        assert_eq!(check_name("COM10", "a", &[], false), NameCheck::Valid);
+    }
+}
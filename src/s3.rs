@@ -0,0 +1,238 @@
+//! S3 backend of `s3://bucket/prefix` locations (feature `s3`)
+//!
+//! A bucket has no directories, only keys; like the AWS console, felipe shows
+//! the prefixes up to the next `/` as directories and the objects under them
+//! as files. Credentials and region come from where the AWS CLI finds them
+//! (environment, `~/.aws/config`, SSO, instance roles). `AWS_ENDPOINT_URL`
+//! points it at an S3-compatible store such as MinIO or R2 instead.
+//!
+//! The SDK is async; its calls run on a small runtime of their own, so the
+//! backend blocks like the others.
+
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::primitives::{ByteStream, DateTime};
+use aws_sdk_s3::Client;
+use std::fs::{File, FileTimes};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::runtime::Runtime;
+
+use crate::copier::CopyProgress;
+use crate::remote::{Backend, RemoteEntry};
+
+/// Threads driving the SDK; transfers mostly wait on the network
+const RUNTIME_THREADS: usize = 2;
+
+pub struct S3Backend {
+    bucket: String,
+    client: Client,
+    runtime: Runtime,
+}
+
+impl S3Backend {
+    /// Set up a client for `bucket` and check it can be reached
+    pub fn connect(bucket: &str) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(RUNTIME_THREADS)
+            .enable_all()
+            .build()?;
+        let client = runtime.block_on(async {
+            let shared = aws_config::load_defaults(BehaviorVersion::latest()).await;
+            // Self-hosted stores rarely have a DNS name per bucket
+            let path_style = std::env::var_os("AWS_ENDPOINT_URL").is_some();
+            let config = aws_sdk_s3::config::Builder::from(&shared)
+                .force_path_style(path_style)
+                .build();
+            Client::from_conf(config)
+        });
+        runtime
+            .block_on(client.head_bucket().bucket(bucket).send())
+            .map_err(sdk_error)?;
+        Ok(Self {
+            bucket: bucket.to_string(),
+            client,
+            runtime,
+        })
+    }
+
+    /// Whether any key starts with `prefix`
+    fn has_prefix(&self, prefix: &str) -> io::Result<bool> {
+        let listed = self.runtime.block_on(
+            self.client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .max_keys(1)
+                .send(),
+        );
+        Ok(listed.map_err(sdk_error)?.key_count().unwrap_or(0) > 0)
+    }
+}
+
+impl Backend for S3Backend {
+    fn list(&self, path: &str) -> io::Result<Vec<RemoteEntry>> {
+        let prefix = dir_prefix(path);
+        let mut entries = Vec::new();
+        let mut token = None;
+        loop {
+            let page = self
+                .runtime
+                .block_on(
+                    self.client
+                        .list_objects_v2()
+                        .bucket(&self.bucket)
+                        .prefix(&prefix)
+                        .delimiter("/")
+                        .set_continuation_token(token)
+                        .send(),
+                )
+                .map_err(sdk_error)?;
+            for common in page.common_prefixes() {
+                let Some(dir) = common.prefix() else {
+                    continue;
+                };
+                entries.push(RemoteEntry {
+                    name: dir[prefix.len()..].trim_end_matches('/').to_string(),
+                    is_dir: true,
+                    size: 0,
+                    modified: None,
+                });
+            }
+            for object in page.contents() {
+                let Some(key) = object.key() else {
+                    continue;
+                };
+                // The placeholder a console leaves for an empty "folder"
+                if key == prefix {
+                    continue;
+                }
+                entries.push(RemoteEntry {
+                    name: key[prefix.len()..].to_string(),
+                    is_dir: false,
+                    size: object.size().unwrap_or(0).max(0) as u64,
+                    modified: object.last_modified().and_then(system_time),
+                });
+            }
+            match page.next_continuation_token() {
+                Some(next) if page.is_truncated() == Some(true) => token = Some(next.to_string()),
+                _ => return Ok(entries),
+            }
+        }
+    }
+
+    fn stat(&self, path: &str) -> io::Result<RemoteEntry> {
+        let key = path.trim_start_matches('/');
+        let name = key.rsplit('/').next().unwrap_or_default().to_string();
+        if !key.is_empty() {
+            let head = self.runtime.block_on(
+                self.client
+                    .head_object()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .send(),
+            );
+            match head {
+                Ok(head) => {
+                    return Ok(RemoteEntry {
+                        name,
+                        is_dir: false,
+                        size: head.content_length().unwrap_or(0).max(0) as u64,
+                        modified: head.last_modified().and_then(system_time),
+                    })
+                }
+                Err(err) if err.as_service_error().is_some_and(|e| e.is_not_found()) => {}
+                Err(err) => return Err(sdk_error(err)),
+            }
+        }
+        if key.is_empty() || self.has_prefix(&dir_prefix(path))? {
+            return Ok(RemoteEntry {
+                name,
+                is_dir: true,
+                size: 0,
+                modified: None,
+            });
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no key s3://{}/{}", self.bucket, key),
+        ))
+    }
+
+    fn download(&self, path: &str, local: &Path, progress: &CopyProgress) -> io::Result<()> {
+        self.runtime.block_on(async {
+            let object = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(path.trim_start_matches('/'))
+                .send()
+                .await
+                .map_err(sdk_error)?;
+            let modified = object.last_modified().and_then(system_time);
+            let mut body = object.body;
+            let mut file = File::create(local)?;
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk.map_err(io::Error::other)?;
+                file.write_all(&chunk)?;
+                progress.add_copied(chunk.len() as u64);
+            }
+            if let Some(modified) = modified {
+                let _ = file.set_times(FileTimes::new().set_modified(modified));
+            }
+            Ok(())
+        })
+    }
+
+    fn upload(&self, local: &Path, path: &str, progress: &CopyProgress) -> io::Result<()> {
+        let size = local.metadata()?.len();
+        self.runtime.block_on(async {
+            let body = ByteStream::from_path(local)
+                .await
+                .map_err(io::Error::other)?;
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(path.trim_start_matches('/'))
+                .body(body)
+                .send()
+                .await
+                .map_err(sdk_error)
+        })?;
+        progress.add_copied(size);
+        Ok(())
+    }
+
+    /// Prefixes exist only through their keys, so leave the empty placeholder
+    /// object the console makes for a new folder
+    fn make_dir(&self, path: &str) -> io::Result<()> {
+        self.runtime
+            .block_on(
+                self.client
+                    .put_object()
+                    .bucket(&self.bucket)
+                    .key(dir_prefix(path))
+                    .body(ByteStream::from_static(b""))
+                    .send(),
+            )
+            .map_err(sdk_error)?;
+        Ok(())
+    }
+}
+
+/// Key prefix of the keys inside directory `path`: "" at the top, else "a/b/"
+fn dir_prefix(path: &str) -> String {
+    match path.trim_matches('/') {
+        "" => String::new(),
+        key => format!("{}/", key),
+    }
+}
+
+fn system_time(time: &DateTime) -> Option<SystemTime> {
+    SystemTime::try_from(*time).ok()
+}
+
+fn sdk_error(err: impl std::error::Error) -> io::Error {
+    io::Error::other(DisplayErrorContext(err).to_string())
+}
@@ -0,0 +1,484 @@
+//! Scripts - automate Felipe with Rhai
+//!
+//! `:script name` runs `scripts/name.rhai` from the config directory on a
+//! worker thread, `:script` lists what's there and `:script!` stops the one
+//! running. Scripts see the listing as it was when they started:
+//!
+//! ```text
+//! cwd()                  directory being shown
+//! selected()             selected path, or () when the listing is empty
+//! selection()            paths of the visual range (else the selected one)
+//! entries()              paths of the listing
+//! list(dir)              paths in any directory
+//! exists(p)  is_dir(p)  size(p)  modified(p)     modified: "YYYY-MM-DD HH:MM:SS"
+//! file_name(p)  extension(p)  parent(p)  join(dir, name)
+//! mkdir(p)  move_to(p, dir)  rename(p, name)  trash(p)
+//! queue_copy(paths, dir)  queue_move(paths, dir)  on the job queue, once done
+//! shell(cmd)             run through the shell in cwd(), returns its output
+//! confirm(question)      ask y/n and wait for the answer
+//! status(text), print(x) show a message
+//! ```
+//!
+//! mkdir, move_to, rename and trash go into the operation log as one group,
+//! so `u` undoes a whole script run. For example, to file downloads away
+//! into a folder per month:
+//!
+//! ```rhai
+//! let files = entries().filter(|p| !is_dir(p));
+//! if confirm(`Sort ${files.len()} files by month?`) {
+//!     for path in files {
+//!         let month = join(cwd(), modified(path).sub_string(0, 7));
+//!         mkdir(month);
+//!         move_to(path, month);
+//!     }
+//! }
+//! ```
+//!
+//! Scripts don't start under `:set dryrun` (see dryrun.rs): their changes
+//! happen as they run, with no plan to review first.
+
+use bevy::prelude::*;
+use rhai::{Array, Dynamic, Engine, EvalAltResult};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::command::{Command, CommandLine, RunCommand};
+use crate::config::{self, Config};
+use crate::jobs::JobQueue;
+use crate::oplog::OperationLog;
+use crate::ops::{self, TransferKind, TransferPlan, UndoStep};
+use crate::shell::{shell_process, ChildEnv};
+use crate::{CurrentDirectory, PendingPrompt, Prompt, StatusMessage};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// What a running script asks of the app
+enum Request {
+    Status(String),
+    Confirm(String),
+}
+
+/// What a script leaves behind for the app to apply
+#[derive(Default)]
+struct Effects {
+    steps: Vec<UndoStep>,
+    jobs: Vec<TransferPlan>,
+    /// The script set the status line itself
+    spoke: bool,
+}
+
+struct Running {
+    name: String,
+    worker: JoinHandle<Result<(), String>>,
+    effects: Arc<Mutex<Effects>>,
+    requests: Mutex<Receiver<Request>>,
+    answers: Sender<bool>,
+    stop: Arc<AtomicBool>,
+}
+
+/// The script running, if any
+#[derive(Resource, Default)]
+pub struct Scripts {
+    running: Option<Running>,
+}
+
+impl Scripts {
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Hand the y/n answer to the script waiting on `confirm`
+    pub fn answer(&self, yes: bool) {
+        if let Some(running) = &self.running {
+            let _ = running.answers.send(yes);
+        }
+    }
+}
+
+/// The listing as a script sees it
+struct Snapshot {
+    cwd: PathBuf,
+    /// For the programs `shell` starts
+    child_env: ChildEnv,
+    selected: Option<PathBuf>,
+    selection: Vec<PathBuf>,
+    entries: Vec<PathBuf>,
+}
+
+pub struct ScriptPlugin;
+
+impl Plugin for ScriptPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scripts>().add_systems(
+            Update,
+            (handle_script_command, serve_script_requests, finish_script).chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_script_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut scripts: ResMut<Scripts>,
+    command_line: Res<CommandLine>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    child_env: Res<ChildEnv>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        match command {
+            Command::Script(None) => {
+                let names = script_names();
+                status.0 = if names.is_empty() {
+                    format!(
+                        "No scripts yet - put name.rhai files in {}",
+                        scripts_dir().map_or("scripts/".into(), |dir| dir.display().to_string())
+                    )
+                } else {
+                    format!("Scripts: {}", names.join("  "))
+                };
+            }
+            Command::Script(Some(name)) => {
+                if let Some(running) = &scripts.running {
+                    status.0 = format!("{} is still running (:script! stops it)", running.name);
+                    continue;
+                }
+                // Scripts change files as they go, with nothing to review first
+                if config.operations.dry_run {
+                    status.0 = "Scripts don't run in dry-run mode (:set nodryrun)".to_string();
+                    continue;
+                }
+                let Some(path) = scripts_dir().map(|dir| dir.join(format!("{}.rhai", name))) else {
+                    status.0 = "No config directory on this system".to_string();
+                    continue;
+                };
+                let source = match std::fs::read_to_string(&path) {
+                    Ok(source) => source,
+                    Err(err) => {
+                        status.0 = format!("Cannot read {}: {}", path.display(), err);
+                        continue;
+                    }
+                };
+                let snapshot = Snapshot {
+                    cwd: current_dir.path.clone(),
+                    child_env: child_env.clone(),
+                    selected: current_dir
+                        .entries
+                        .get(current_dir.selected_index)
+                        .filter(|entry| entry.name != "..")
+                        .map(|entry| entry.path.clone()),
+                    selection: command_line
+                        .targets(&current_dir)
+                        .into_iter()
+                        .map(|entry| entry.path.clone())
+                        .collect(),
+                    entries: current_dir
+                        .entries
+                        .iter()
+                        .filter(|entry| entry.name != "..")
+                        .map(|entry| entry.path.clone())
+                        .collect(),
+                };
+                scripts.running = Some(start(name.clone(), source, snapshot));
+                status.0 = format!("Running {}...", name);
+            }
+            Command::ScriptStop => match &scripts.running {
+                Some(running) => running.stop.store(true, Ordering::Relaxed),
+                None => status.0 = "No script is running".to_string(),
+            },
+            _ => {}
+        }
+    }
+}
+
+fn serve_script_requests(
+    scripts: Res<Scripts>,
+    mut prompt: ResMut<Prompt>,
+    mut status: ResMut<StatusMessage>,
+) {
+    let Some(running) = &scripts.running else {
+        return;
+    };
+    let Ok(requests) = running.requests.lock() else {
+        return;
+    };
+    // One prompt at a time; the script's question waits its turn
+    if prompt.pending.is_some() {
+        return;
+    }
+    while let Ok(request) = requests.try_recv() {
+        match request {
+            Request::Status(text) => status.0 = text,
+            Request::Confirm(question) => {
+                prompt.pending = Some(PendingPrompt::ConfirmScript(question));
+                return;
+            }
+        }
+    }
+}
+
+/// Apply what the script did once it ends
+fn finish_script(
+    mut scripts: ResMut<Scripts>,
+    mut oplog: ResMut<OperationLog>,
+    mut jobs: ResMut<JobQueue>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !scripts
+        .running
+        .as_ref()
+        .is_some_and(|running| running.worker.is_finished())
+    {
+        return;
+    }
+    let Some(running) = scripts.running.take() else {
+        return;
+    };
+    let result = running
+        .worker
+        .join()
+        .unwrap_or_else(|_| Err("the script crashed".to_string()));
+    let effects = std::mem::take(&mut *running.effects.lock().unwrap_or_else(|e| e.into_inner()));
+    let changes = effects.steps.len();
+    oplog.record(format!("script {}", running.name), effects.steps);
+    for plan in effects.jobs {
+        crate::queue_transfer(plan, &mut jobs);
+    }
+    match result {
+        Err(err) => status.0 = format!("{}: {}", running.name, err),
+        Ok(()) if !effects.spoke => {
+            status.0 = match changes {
+                0 => format!("{} done", running.name),
+                n => format!("{} done, {} changes (u undoes them)", running.name, n),
+            }
+        }
+        Ok(()) => {}
+    }
+    current_dir.keep_selection();
+}
+
+// =============================================================================
+// Running
+// =============================================================================
+
+fn scripts_dir() -> Option<PathBuf> {
+    Some(config::config_path()?.parent()?.join("scripts"))
+}
+
+fn script_names() -> Vec<String> {
+    let Some(read) = scripts_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = read
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+fn start(name: String, source: String, snapshot: Snapshot) -> Running {
+    let (request_tx, request_rx) = mpsc::channel();
+    let (answer_tx, answer_rx) = mpsc::channel();
+    let effects = Arc::new(Mutex::new(Effects::default()));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let engine = engine(
+        Arc::new(snapshot),
+        effects.clone(),
+        request_tx,
+        Arc::new(Mutex::new(answer_rx)),
+        stop.clone(),
+    );
+    let worker = std::thread::spawn(move || {
+        engine.run(&source).map_err(|err| match *err {
+            EvalAltResult::ErrorTerminated(..) => "stopped".to_string(),
+            err => err.to_string(),
+        })
+    });
+    Running {
+        name,
+        worker,
+        effects,
+        requests: Mutex::new(request_rx),
+        answers: answer_tx,
+        stop,
+    }
+}
+
+fn engine(
+    snapshot: Arc<Snapshot>,
+    effects: Arc<Mutex<Effects>>,
+    requests: Sender<Request>,
+    answers: Arc<Mutex<Receiver<bool>>>,
+    stop: Arc<AtomicBool>,
+) -> Engine {
+    let mut engine = Engine::new();
+    engine.on_progress(move |_| stop.load(Ordering::Relaxed).then_some(Dynamic::UNIT));
+
+    // The listing
+    let s = snapshot.clone();
+    engine.register_fn("cwd", move || text(&s.cwd));
+    let s = snapshot.clone();
+    engine.register_fn("selected", move || {
+        s.selected
+            .as_deref()
+            .map_or(Dynamic::UNIT, |p| text(p).into())
+    });
+    let s = snapshot.clone();
+    engine.register_fn("selection", move || paths(&s.selection));
+    let s = snapshot.clone();
+    engine.register_fn("entries", move || paths(&s.entries));
+
+    // Looking around
+    engine.register_fn("list", |dir: &str| -> ScriptResult<Array> {
+        let mut found: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|err| failed("list", dir, err))?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        found.sort();
+        Ok(paths(&found))
+    });
+    engine.register_fn("exists", |path: &str| Path::new(path).exists());
+    engine.register_fn("is_dir", |path: &str| Path::new(path).is_dir());
+    engine.register_fn("size", |path: &str| -> ScriptResult<i64> {
+        let metadata = std::fs::metadata(path).map_err(|err| failed("size", path, err))?;
+        Ok(metadata.len() as i64)
+    });
+    engine.register_fn("modified", |path: &str| -> ScriptResult<String> {
+        let time = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|err| failed("modified", path, err))?;
+        let local = chrono::DateTime::<chrono::Local>::from(time);
+        Ok(local.format("%Y-%m-%d %H:%M:%S").to_string())
+    });
+    engine.register_fn("file_name", |path: &str| {
+        Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+    engine.register_fn("extension", |path: &str| {
+        Path::new(path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+    engine.register_fn("parent", |path: &str| {
+        Path::new(path).parent().map(text).unwrap_or_default()
+    });
+    engine.register_fn("join", |dir: &str, name: &str| {
+        text(&Path::new(dir).join(name))
+    });
+
+    // Changing things, undone together
+    let e = effects.clone();
+    engine.register_fn("mkdir", move |path: &str| -> ScriptResult<()> {
+        let step = ops::make_dir(Path::new(path)).map_err(|err| failed("mkdir", path, err))?;
+        e.lock().unwrap().steps.extend(step);
+        Ok(())
+    });
+    let e = effects.clone();
+    engine.register_fn(
+        "move_to",
+        move |path: &str, dir: &str| -> ScriptResult<String> {
+            let source = Path::new(path);
+            let target = Path::new(dir).join(source.file_name().unwrap_or_default());
+            let step = ops::move_path(source, &target).map_err(|err| failed("move", path, err))?;
+            e.lock().unwrap().steps.push(step);
+            Ok(text(&target))
+        },
+    );
+    let e = effects.clone();
+    engine.register_fn(
+        "rename",
+        move |path: &str, name: &str| -> ScriptResult<String> {
+            let step = ops::rename_path(Path::new(path), name)
+                .map_err(|err| failed("rename", path, err))?;
+            e.lock().unwrap().steps.push(step);
+            Ok(text(&Path::new(path).with_file_name(name)))
+        },
+    );
+    let e = effects.clone();
+    engine.register_fn("trash", move |path: &str| -> ScriptResult<()> {
+        let step = ops::trash_entry(Path::new(path)).map_err(|err| failed("trash", path, err))?;
+        e.lock().unwrap().steps.push(step);
+        Ok(())
+    });
+    for (name, kind) in [
+        ("queue_copy", TransferKind::Copy),
+        ("queue_move", TransferKind::Move),
+    ] {
+        let e = effects.clone();
+        engine.register_fn(name, move |sources: Array, dir: &str| {
+            let sources: Vec<PathBuf> = sources
+                .into_iter()
+                .map(|source| PathBuf::from(source.to_string()))
+                .collect();
+            let plan = ops::plan_transfer(kind, &sources, Path::new(dir));
+            e.lock().unwrap().jobs.push(plan);
+        });
+    }
+
+    // Other programs, refused in read-only mode like `:!`
+    let s = snapshot.clone();
+    engine.register_fn("shell", move |line: &str| -> ScriptResult<String> {
+        if ops::is_read_only() {
+            return Err("shell commands are off in read-only mode".into());
+        }
+        let output = s
+            .child_env
+            .apply(&mut shell_process(line))
+            .current_dir(&s.cwd)
+            .output()
+            .map_err(|err| failed("shell", line, err))?;
+        if !output.status.success() {
+            let err = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("shell {:?}: {} {}", line, output.status, err.trim()).into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    });
+
+    // Talking to the user
+    let r = requests.clone();
+    engine.register_fn("confirm", move |question: &str| -> ScriptResult<bool> {
+        r.send(Request::Confirm(question.to_string()))
+            .map_err(|_| "Felipe is gone")?;
+        let answer = answers.lock().unwrap().recv();
+        answer.map_err(|_| "Felipe is gone".into())
+    });
+    let (r, e) = (requests.clone(), effects.clone());
+    engine.register_fn("status", move |message: &str| {
+        e.lock().unwrap().spoke = true;
+        let _ = r.send(Request::Status(message.to_string()));
+    });
+    let (r, e) = (requests, effects);
+    engine.on_print(move |message| {
+        e.lock().unwrap().spoke = true;
+        let _ = r.send(Request::Status(message.to_string()));
+    });
+    engine
+}
+
+fn text(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn paths(paths: &[PathBuf]) -> Array {
+    paths.iter().map(|path| text(path).into()).collect()
+}
+
+fn failed(what: &str, path: &str, err: std::io::Error) -> Box<EvalAltResult> {
+    format!("{} {}: {}", what, path, err).into()
+}
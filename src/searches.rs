@@ -0,0 +1,251 @@
+//! Saved searches - `:savesearch name query` keeps a query as a directory of
+//! its own
+//!
+//! `:savesearch bigvideos size>1GB ext:mp4` saves the query together with the
+//! current directory. `:searches` lists the saved searches as directories, and
+//! entering one walks the tree it was saved in and shows every file that
+//! passes, named by its path below that tree. The results are read again on
+//! every visit, so they follow the disk. `:savesearch! name` forgets a search.
+//!
+//! A file must pass every term of the query:
+//!
+//! - `size>1GB`, `size<10MB` - bigger or smaller than (units as in format.rs)
+//! - `ext:mp4,mkv` - one of these extensions, in any case
+//! - `newer:7d`, `older:12w` - modified within, or before, so many hours (`h`),
+//!   days (`d`) or weeks (`w`)
+//! - `tag:red` - tagged with the color (see tags.rs)
+//! - anything else is a glob, or a `/regex/`, for the file name
+//!
+//! Searches are kept in the metadata store (metadata.rs).
+
+use bevy::prelude::*;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::command::{Command, RunCommand};
+use crate::config::ListingConfig;
+use crate::filter::NamePattern;
+use crate::{flatten, format, metadata};
+use crate::{CurrentDirectory, FileEntry, StatusMessage};
+
+/// Path of the view `:searches` opens; a saved search is a directory in it
+const SEARCHES_VIEW: &str = "Saved searches";
+
+// =============================================================================
+// Queries
+// =============================================================================
+
+/// One condition of a query
+enum Term {
+    Larger(u64),
+    Smaller(u64),
+    Extension(Vec<String>),
+    Newer(Duration),
+    Older(Duration),
+    Name(NamePattern),
+}
+
+/// A parsed query; files must pass all of its terms
+struct Query(Vec<Term>);
+
+impl Query {
+    fn parse(text: &str) -> Result<Self, String> {
+        let terms = text
+            .split_whitespace()
+            .map(parse_term)
+            .collect::<Result<Vec<_>, _>>()?;
+        if terms.is_empty() {
+            return Err("an empty query would list every file".to_string());
+        }
+        Ok(Self(terms))
+    }
+
+    fn matches(&self, entry: &FileEntry) -> bool {
+        let name = entry
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let age = entry
+            .modified
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        self.0.iter().all(|term| match term {
+            Term::Larger(size) => entry.size > *size,
+            Term::Smaller(size) => entry.size < *size,
+            Term::Extension(extensions) => entry
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .is_some_and(|ext| extensions.contains(&ext)),
+            Term::Newer(within) => age.is_some_and(|age| age <= *within),
+            Term::Older(before) => age.is_some_and(|age| age > *before),
+            Term::Name(pattern) => pattern.matches(&name, &entry.path),
+        })
+    }
+}
+
+fn parse_term(word: &str) -> Result<Term, String> {
+    let size = |text: &str| format::parse_size(text).ok_or_else(|| format!("bad size in {}", word));
+    let age = |text: &str| parse_age(text).ok_or_else(|| format!("bad age in {}, like 7d", word));
+    if let Some(text) = word.strip_prefix("size>") {
+        Ok(Term::Larger(size(text)?))
+    } else if let Some(text) = word.strip_prefix("size<") {
+        Ok(Term::Smaller(size(text)?))
+    } else if let Some(list) = word.strip_prefix("ext:") {
+        Ok(Term::Extension(
+            list.split(',')
+                .filter(|ext| !ext.is_empty())
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect(),
+        ))
+    } else if let Some(text) = word.strip_prefix("newer:") {
+        Ok(Term::Newer(age(text)?))
+    } else if let Some(text) = word.strip_prefix("older:") {
+        Ok(Term::Older(age(text)?))
+    } else {
+        NamePattern::parse(word).map(Term::Name)
+    }
+}
+
+/// `36h`, `7d`, `2w`
+fn parse_age(text: &str) -> Option<Duration> {
+    let (count, unit) = text.split_at(text.len().checked_sub(1)?);
+    let hours = match unit {
+        "h" => 1,
+        "d" => 24,
+        "w" => 24 * 7,
+        _ => return None,
+    };
+    Some(Duration::from_secs(
+        count.parse::<u64>().ok()? * hours * 3600,
+    ))
+}
+
+// =============================================================================
+// Views
+// =============================================================================
+
+pub fn is_searches_view(path: &Path) -> bool {
+    path == Path::new(SEARCHES_VIEW)
+}
+
+/// Whether `path` is a saved search in the view, rather than a directory
+pub fn is_saved_search(path: &Path) -> bool {
+    path.parent().is_some_and(is_searches_view)
+}
+
+/// The saved searches as directory entries
+pub fn search_entries() -> Vec<FileEntry> {
+    metadata::searches()
+        .into_iter()
+        .map(|(name, _, _)| FileEntry {
+            path: Path::new(SEARCHES_VIEW).join(&name),
+            name,
+            is_dir: true,
+            size: 0,
+            modified: None,
+            locked: false,
+            link: None,
+            inode: None,
+            executable: false,
+        })
+        .collect()
+}
+
+/// Files the saved search at `path` finds, and a line saying how many; an
+/// error for a search that's gone or a query that no longer parses
+pub fn run(path: &Path, listing: &ListingConfig) -> Result<(Vec<FileEntry>, String), String> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (root, text) = metadata::searches()
+        .into_iter()
+        .find(|(saved, _, _)| *saved == name)
+        .map(|(_, root, text)| (root, text))
+        .ok_or_else(|| format!("No search saved as {}", name))?;
+    let query = Query::parse(&text).map_err(|err| format!("Bad search {}: {}", name, err))?;
+    if !root.is_dir() {
+        return Err(format!(
+            "{} searches {}, which is gone",
+            name,
+            root.display()
+        ));
+    }
+    let (mut files, truncated) = flatten::walk_files(&root, listing);
+    files.retain(|entry| query.matches(entry));
+    let message = format!(
+        "{}: {} files under {}{}",
+        name,
+        files.len(),
+        root.display(),
+        if truncated { " (truncated)" } else { "" }
+    );
+    Ok((files, message))
+}
+
+// =============================================================================
+// Plugin
+// =============================================================================
+
+pub struct SearchesPlugin;
+
+impl Plugin for SearchesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, handle_search_commands);
+    }
+}
+
+fn handle_search_commands(
+    mut run_commands: EventReader<RunCommand>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        match command {
+            Command::SaveSearch {
+                name,
+                query: Some(text),
+            } => {
+                status.0 = save(name, current_dir.path(), text);
+            }
+            Command::SaveSearch { name, query: None } => {
+                status.0 = match metadata::delete_search(name) {
+                    Ok(true) => format!("Forgot the search {}", name),
+                    Ok(false) => format!("No search saved as {}", name),
+                    Err(err) => format!("Cannot save the searches: {}", err),
+                };
+                let path = Path::new(SEARCHES_VIEW).join(name);
+                if current_dir.path() == path {
+                    current_dir.set_path(SEARCHES_VIEW);
+                } else if is_searches_view(current_dir.path()) {
+                    current_dir.keep_selection();
+                }
+            }
+            Command::Searches => {
+                if metadata::searches().is_empty() {
+                    status.0 =
+                        "No saved searches yet - :savesearch name query saves one".to_string();
+                    continue;
+                }
+                current_dir.set_path(SEARCHES_VIEW);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Save `text` over `root` as `name`; what to tell the user
+fn save(name: &str, root: &Path, text: &str) -> String {
+    if let Err(err) = Query::parse(text) {
+        return format!("Bad search {}: {}", name, err);
+    }
+    // Searches walk the disk; views and remote locations aren't absolute paths
+    if !root.is_absolute() {
+        return "Saved searches work on local directories only".to_string();
+    }
+    match metadata::save_search(name, root, text) {
+        Ok(()) => format!("Saved search {} - :searches lists it", name),
+        Err(err) => format!("Cannot save the searches: {}", err),
+    }
+}
@@ -0,0 +1,196 @@
+//! SFTP backend of `sftp://user@host:port/path` locations (feature `sftp`)
+//!
+//! Connects like `ssh` would without a config file: the user defaults to the
+//! local one and the port to 22, the host key has to be in
+//! `~/.ssh/known_hosts` already (felipe never adds or replaces one), and
+//! authentication tries the ssh-agent first, then unencrypted keys in
+//! `~/.ssh`. Listings share one SFTP channel; every transfer opens its own.
+
+use ssh2::{CheckResult, FileStat, KnownHostFileKind, Session, Sftp};
+use std::fs::{self, File, FileTimes};
+use std::io;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::copier::CopyProgress;
+use crate::remote::{self, Backend, RemoteEntry};
+
+const DEFAULT_PORT: u16 = 22;
+/// Blocking calls give up after this long, in milliseconds
+const TIMEOUT_MS: u32 = 15_000;
+/// Keys tried when the agent has none the server takes
+const KEY_FILES: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+pub struct SftpBackend {
+    session: Session,
+    sftp: Mutex<Sftp>,
+}
+
+impl SftpBackend {
+    /// Connect and log in to `[user@]host[:port]`
+    pub fn connect(authority: &str) -> io::Result<Self> {
+        let (user, address) = match authority.split_once('@') {
+            Some((user, address)) => (user.to_string(), address),
+            None => (local_user()?, authority),
+        };
+        let (host, port) = remote::host_port(address, DEFAULT_PORT)?;
+
+        let tcp = TcpStream::connect((host, port))?;
+        let mut session = Session::new()?;
+        session.set_timeout(TIMEOUT_MS);
+        session.set_tcp_stream(tcp);
+        session.handshake()?;
+        check_host_key(&session, host, port)?;
+        authenticate(&session, &user)?;
+
+        let sftp = session.sftp()?;
+        Ok(Self {
+            session,
+            sftp: Mutex::new(sftp),
+        })
+    }
+}
+
+impl Backend for SftpBackend {
+    fn list(&self, path: &str) -> io::Result<Vec<RemoteEntry>> {
+        let sftp = self.sftp.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(sftp
+            .readdir(Path::new(path))?
+            .into_iter()
+            .map(|(path, stat)| entry(&path, &stat))
+            .collect())
+    }
+
+    fn stat(&self, path: &str) -> io::Result<RemoteEntry> {
+        let sftp = self.sftp.lock().unwrap_or_else(|e| e.into_inner());
+        let stat = sftp.stat(Path::new(path))?;
+        Ok(entry(Path::new(path), &stat))
+    }
+
+    fn download(&self, path: &str, local: &Path, progress: &CopyProgress) -> io::Result<()> {
+        let sftp = self.session.sftp()?;
+        let mut remote = sftp.open(Path::new(path))?;
+        let stat = remote.stat()?;
+        let mut file = File::create(local)?;
+        remote::transfer(&mut remote, &mut file, progress)?;
+        if let Some(mtime) = stat.mtime {
+            let _ = file.set_times(FileTimes::new().set_modified(from_unix(mtime)));
+        }
+        Ok(())
+    }
+
+    fn upload(&self, local: &Path, path: &str, progress: &CopyProgress) -> io::Result<()> {
+        let metadata = fs::metadata(local)?;
+        let sftp = self.session.sftp()?;
+        let mut remote = sftp.create(Path::new(path))?;
+        remote::transfer(&mut File::open(local)?, &mut remote, progress)?;
+        drop(remote);
+        // Like the local copy engine, keep mode and mtime where the server lets us
+        let _ = sftp.setstat(
+            Path::new(path),
+            FileStat {
+                size: None,
+                uid: None,
+                gid: None,
+                perm: permissions(&metadata),
+                atime: metadata.accessed().ok().and_then(to_unix),
+                mtime: metadata.modified().ok().and_then(to_unix),
+            },
+        );
+        Ok(())
+    }
+
+    fn make_dir(&self, path: &str) -> io::Result<()> {
+        let sftp = self.sftp.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(sftp.mkdir(Path::new(path), 0o755)?)
+    }
+}
+
+fn entry(path: &Path, stat: &FileStat) -> RemoteEntry {
+    RemoteEntry {
+        name: path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        is_dir: stat.is_dir(),
+        size: stat.size.unwrap_or(0),
+        modified: stat.mtime.map(from_unix),
+    }
+}
+
+/// Refuse hosts whose key isn't known, or has changed
+fn check_host_key(session: &Session, host: &str, port: u16) -> io::Result<()> {
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| io::Error::other("server sent no host key"))?;
+    let mut known_hosts = session.known_hosts()?;
+    if let Some(file) = ssh_dir().map(|dir| dir.join("known_hosts")) {
+        // A missing file just means no host is known
+        let _ = known_hosts.read_file(&file, KnownHostFileKind::OpenSSH);
+    }
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(io::Error::other(format!(
+            "{} isn't in ~/.ssh/known_hosts - connect with ssh once first",
+            host
+        ))),
+        CheckResult::Mismatch => Err(io::Error::other(format!(
+            "the host key of {} has CHANGED - refusing to connect",
+            host
+        ))),
+        CheckResult::Failure => Err(io::Error::other("cannot check the host key")),
+    }
+}
+
+fn authenticate(session: &Session, user: &str) -> io::Result<()> {
+    if session.userauth_agent(user).is_ok() && session.authenticated() {
+        return Ok(());
+    }
+    let keys = ssh_dir()
+        .into_iter()
+        .flat_map(|dir| KEY_FILES.map(|name| dir.join(name)))
+        .filter(|key| key.is_file());
+    for key in keys {
+        if session.userauth_pubkey_file(user, None, &key, None).is_ok() && session.authenticated() {
+            return Ok(());
+        }
+    }
+    Err(io::Error::other(format!(
+        "no key the server accepts for {} (ssh-agent or ~/.ssh)",
+        user
+    )))
+}
+
+fn local_user() -> io::Result<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map_err(|_| io::Error::other("no user name - use sftp://user@host"))
+}
+
+fn ssh_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh"))
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn permissions(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+fn from_unix(seconds: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+fn to_unix(time: SystemTime) -> Option<u64> {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .ok()
+        .map(|since| since.as_secs())
+}
@@ -0,0 +1,299 @@
+//! Entry shapes - what a book looks like says what kind of entry it is
+//!
+//! Directories are open frames, images thin slabs, executables pyramids and
+//! archives stacked boxes; everything else stays a book. Files are matched by
+//! their sniffed content type (see mime.rs), then by extension, executables
+//! also by their execute bit. The config maps extensions to shapes, which
+//! overrides both:
+//!
+//! ```toml
+//! [shapes]
+//! # extension (lowercase, no dot) = book, frame, slab, pyramid or stack
+//! psd = "slab"
+//! py = "pyramid"
+//! txt = "book"
+//! ```
+//!
+//! Every shape fits the book's 0.8 x 1 x 0.3 box, so entries still line up,
+//! grow with their size and can be clicked the same way. `:set wireframe`
+//! outlines each shape.
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::PrimitiveTopology;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::Metadata;
+
+use crate::{EntryPalette, FileEntry};
+
+/// Half extents of the book every shape fits into
+const HALF: Vec3 = Vec3::new(0.4, 0.5, 0.15);
+
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "svg", "tif", "tiff", "ico", "heic", "avif",
+];
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    "zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "jar", "deb", "rpm", "dmg", "iso",
+];
+const EXECUTABLE_EXTENSIONS: &[&str] = &["exe", "bat", "cmd", "com", "msi", "appimage"];
+
+/// Shape of an entry's book
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shape {
+    Book,
+    /// Four bars around an open middle
+    Frame,
+    Slab,
+    Pyramid,
+    /// Three boxes, each a little smaller than the one below
+    Stack,
+}
+
+impl Shape {
+    /// Shared mesh of the shape, solid or as edge outlines
+    pub(crate) fn mesh(
+        self,
+        palette: &mut EntryPalette,
+        meshes: &mut Assets<Mesh>,
+        wireframe: bool,
+    ) -> Handle<Mesh> {
+        match (self, wireframe) {
+            (Shape::Book, false) => palette.cuboid(meshes),
+            (Shape::Book, true) => palette.edges(meshes),
+            (Shape::Pyramid, false) => palette.mesh(meshes, "pyramid", pyramid_mesh),
+            (Shape::Pyramid, true) => {
+                palette.mesh(meshes, "pyramid edges", || line_mesh(pyramid_edges()))
+            }
+            (Shape::Frame, false) => palette.mesh(meshes, "frame", || boxes_mesh(&frame_boxes())),
+            (Shape::Frame, true) => palette.mesh(meshes, "frame edges", || {
+                line_mesh(boxes_edges(&frame_boxes()))
+            }),
+            (Shape::Slab, false) => palette.mesh(meshes, "slab", || boxes_mesh(&slab_boxes())),
+            (Shape::Slab, true) => palette.mesh(meshes, "slab edges", || {
+                line_mesh(boxes_edges(&slab_boxes()))
+            }),
+            (Shape::Stack, false) => palette.mesh(meshes, "stack", || boxes_mesh(&stack_boxes())),
+            (Shape::Stack, true) => palette.mesh(meshes, "stack edges", || {
+                line_mesh(boxes_edges(&stack_boxes()))
+            }),
+        }
+    }
+
+    /// Resting color of files of this shape, None for the usual file color
+    pub fn color(self) -> Option<Color> {
+        match self {
+            Shape::Book | Shape::Frame => None,
+            Shape::Slab => Some(Color::srgb(0.75, 0.25, 0.2)),
+            Shape::Pyramid => Some(Color::srgb(0.8, 0.5, 0.0)),
+            Shape::Stack => Some(Color::srgb(0.45, 0.22, 0.08)),
+        }
+    }
+}
+
+/// `[shapes]` - extension to shape, on top of the built-in map
+#[derive(Deserialize, Default)]
+#[serde(transparent)]
+pub struct ShapesConfig {
+    extensions: HashMap<String, Shape>,
+}
+
+impl ShapesConfig {
+    /// Shape of `entry`, whose content was sniffed as `mime` if known
+    pub(crate) fn shape(&self, entry: &FileEntry, mime: Option<&str>) -> Shape {
+        if entry.is_dir {
+            return Shape::Frame;
+        }
+        let extension = entry
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if let Some(shape) = self.extensions.get(&extension) {
+            return *shape;
+        }
+        if let Some(shape) = mime.and_then(mime_shape) {
+            return shape;
+        }
+        let extension = extension.as_str();
+        if IMAGE_EXTENSIONS.contains(&extension) {
+            Shape::Slab
+        } else if ARCHIVE_EXTENSIONS.contains(&extension) {
+            Shape::Stack
+        } else if entry.executable || EXECUTABLE_EXTENSIONS.contains(&extension) {
+            Shape::Pyramid
+        } else {
+            Shape::Book
+        }
+    }
+}
+
+fn mime_shape(mime: &str) -> Option<Shape> {
+    match mime {
+        _ if mime.starts_with("image/") => Some(Shape::Slab),
+        "application/zip"
+        | "application/gzip"
+        | "application/x-tar"
+        | "application/x-bzip2"
+        | "application/x-xz"
+        | "application/zstd"
+        | "application/x-7z-compressed"
+        | "application/vnd.rar"
+        | "application/java-archive"
+        | "application/vnd.debian.binary-package"
+        | "application/x-rpm" => Some(Shape::Stack),
+        "application/x-executable"
+        | "application/x-mach-binary"
+        | "application/vnd.microsoft.portable-executable" => Some(Shape::Pyramid),
+        _ => None,
+    }
+}
+
+/// A file anyone may execute
+#[cfg(unix)]
+pub fn is_executable(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(_metadata: &Metadata) -> bool {
+    false
+}
+
+// =============================================================================
+// Geometry
+// =============================================================================
+
+/// Boxes as (center, half extents)
+type Boxes = Vec<(Vec3, Vec3)>;
+
+fn frame_boxes() -> Boxes {
+    let bar = 0.06;
+    vec![
+        (
+            Vec3::new(-HALF.x + bar, 0.0, 0.0),
+            Vec3::new(bar, HALF.y, HALF.z),
+        ),
+        (
+            Vec3::new(HALF.x - bar, 0.0, 0.0),
+            Vec3::new(bar, HALF.y, HALF.z),
+        ),
+        (
+            Vec3::new(0.0, HALF.y - bar, 0.0),
+            Vec3::new(HALF.x, bar, HALF.z),
+        ),
+        (
+            Vec3::new(0.0, -HALF.y + bar, 0.0),
+            Vec3::new(HALF.x, bar, HALF.z),
+        ),
+    ]
+}
+
+fn slab_boxes() -> Boxes {
+    vec![(Vec3::ZERO, Vec3::new(HALF.x, HALF.y, 0.04))]
+}
+
+fn stack_boxes() -> Boxes {
+    let layer = 2.0 * HALF.y / 3.0;
+    (0..3)
+        .map(|i| {
+            let shrink = 1.0 - 0.12 * i as f32;
+            let center = Vec3::new(0.0, -HALF.y + layer * (i as f32 + 0.5), 0.0);
+            let half = Vec3::new(HALF.x * shrink, layer * 0.45, HALF.z * shrink);
+            (center, half)
+        })
+        .collect()
+}
+
+/// Corner `i` of a box: bit 0 picks +x, bit 1 +y, bit 2 +z
+fn corner(center: Vec3, half: Vec3, i: usize) -> Vec3 {
+    center
+        + Vec3::new(
+            if i & 1 == 0 { -half.x } else { half.x },
+            if i & 2 == 0 { -half.y } else { half.y },
+            if i & 4 == 0 { -half.z } else { half.z },
+        )
+}
+
+/// Solid boxes as one mesh, faces wound outwards
+fn boxes_mesh(boxes: &Boxes) -> Mesh {
+    let mut positions: Vec<Vec3> = Vec::new();
+    for &(center, half) in boxes {
+        for (axis, others) in [(1, [2, 4]), (2, [4, 1]), (4, [1, 2])] {
+            for side in [0, axis] {
+                let [b, c] = others;
+                let quad =
+                    [side, side | b, side | b | c, side | c].map(|i| corner(center, half, i));
+                push_outwards(&mut positions, [quad[0], quad[1], quad[2]], center);
+                push_outwards(&mut positions, [quad[0], quad[2], quad[3]], center);
+            }
+        }
+    }
+    triangle_mesh(positions)
+}
+
+/// Square pyramid on the book's footprint
+fn pyramid_mesh() -> Mesh {
+    let apex = Vec3::new(0.0, HALF.y, 0.0);
+    let base = [0, 1, 5, 4].map(|i| corner(Vec3::ZERO, HALF, i));
+    let inside = Vec3::new(0.0, -HALF.y / 2.0, 0.0);
+    let mut positions = Vec::new();
+    for i in 0..4 {
+        push_outwards(&mut positions, [base[i], apex, base[(i + 1) % 4]], inside);
+    }
+    push_outwards(&mut positions, [base[0], base[1], base[2]], inside);
+    push_outwards(&mut positions, [base[0], base[2], base[3]], inside);
+    triangle_mesh(positions)
+}
+
+/// Add a triangle wound so its front faces away from `inside`
+fn push_outwards(positions: &mut Vec<Vec3>, [a, b, c]: [Vec3; 3], inside: Vec3) {
+    let normal = (b - a).cross(c - a);
+    if (a - inside).dot(normal) > 0.0 {
+        positions.extend([a, b, c]);
+    } else {
+        positions.extend([a, c, b]);
+    }
+}
+
+fn triangle_mesh(positions: Vec<Vec3>) -> Mesh {
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_computed_flat_normals()
+}
+
+/// The twelve edges of each box, as line segment end points
+fn boxes_edges(boxes: &Boxes) -> Vec<Vec3> {
+    let mut points = Vec::new();
+    for &(center, half) in boxes {
+        // Corners that differ in exactly one axis share an edge
+        for a in 0..8 {
+            for axis in [1, 2, 4] {
+                if a & axis == 0 {
+                    points.push(corner(center, half, a));
+                    points.push(corner(center, half, a | axis));
+                }
+            }
+        }
+    }
+    points
+}
+
+fn pyramid_edges() -> Vec<Vec3> {
+    let apex = Vec3::new(0.0, HALF.y, 0.0);
+    let base = [0, 1, 5, 4].map(|i| corner(Vec3::ZERO, HALF, i));
+    (0..4)
+        .flat_map(|i| [base[i], base[(i + 1) % 4], base[i], apex])
+        .collect()
+}
+
+fn line_mesh(points: Vec<Vec3>) -> Mesh {
+    Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, points)
+}
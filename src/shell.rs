@@ -0,0 +1,506 @@
+//! Shell commands - `:!cmd`
+//!
+//! Runs a command line through the shell (`sh -c`, `cmd /C` on Windows) in
+//! the current directory without waiting for it. Output and errors stream
+//! into a panel as they come, and the panel's title shows how the command
+//! ended. In the command, `%` is the selected path and `%*` the entries of the
+//! visual range it was typed over (else the selected one), each quoted for
+//! the shell; `%%` is a plain `%`.
+//!
+//! ```text
+//! :!du -sh %*
+//! :!git add % && git status --short
+//! ```
+//!
+//! The panel scrolls with j/k, Ctrl-d/Ctrl-u and g/G, and follows the end of
+//! the output until scrolled up (G follows again). Ctrl-c stops the command;
+//! Esc or q closes the panel and lets it run on. The listing is reloaded when
+//! the command ends, since it may have changed files. Read-only mode refuses
+//! `:!` altogether, as there's no telling what a command writes.
+
+use bevy::prelude::*;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, Command as Process, Stdio};
+use std::sync::{Arc, Mutex};
+
+use crate::command::{Command, CommandLine, RunCommand};
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::{
+    ops, CurrentDirectory, StatusMessage, UiElement, VimMode, DIFF_REMOVED, FELIPE_ORANGE,
+    FELIPE_ORANGE_DIM,
+};
+
+/// Lines of the panel body
+const PANEL_ROWS: usize = 30;
+/// Output kept of one command; the rest is read and dropped
+const MAX_LINES: usize = 100_000;
+
+/// A line of output; `error` for lines from stderr
+#[derive(Clone)]
+struct OutputLine {
+    error: bool,
+    text: String,
+}
+
+/// The last `:!` command and what it printed
+#[derive(Resource, Default)]
+pub struct ShellView {
+    command: String,
+    /// Filled by reader threads while the command runs
+    output: Arc<Mutex<Vec<OutputLine>>>,
+    child: Option<Child>,
+    /// How the command ended, once it has
+    outcome: Option<String>,
+    scroll: usize,
+    /// Keep the end of the output in view
+    follow: bool,
+}
+
+/// Variables for every program started on the user's behalf (`:!`, hooks,
+/// scripts' `shell`, `:terminal`), like `FELIPE_SOCKET` from ipc.rs
+#[derive(Resource, Default, Clone)]
+pub struct ChildEnv(pub Vec<(&'static str, OsString)>);
+
+impl ChildEnv {
+    pub fn apply<'a>(&self, process: &'a mut Process) -> &'a mut Process {
+        process.envs(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+/// Marker for the shell output panel
+#[derive(Component)]
+struct ShellPanel;
+
+/// Marker for the shell output panel text
+#[derive(Component)]
+struct ShellText;
+
+pub struct ShellPlugin;
+
+impl Plugin for ShellPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShellView>()
+            .init_resource::<ChildEnv>()
+            .add_systems(
+                Update,
+                (
+                    handle_shell_command,
+                    watch_shell_command,
+                    handle_shell_keys,
+                    update_shell_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_shell_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut view: ResMut<ShellView>,
+    command_line: Res<CommandLine>,
+    current_dir: Res<CurrentDirectory>,
+    child_env: Res<ChildEnv>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Shell(line) = command else {
+            continue;
+        };
+        if ops::is_read_only() {
+            status.0 = "Shell commands are off in read-only mode".to_string();
+            continue;
+        }
+        if view.child.is_some() {
+            status.0 = format!(
+                "{} is still running (Ctrl-c in its panel stops it)",
+                view.command
+            );
+            continue;
+        }
+        let selected = current_dir
+            .entries
+            .get(current_dir.selected_index)
+            .map(|entry| entry.path.as_path());
+        let targets: Vec<&Path> = command_line
+            .targets(&current_dir)
+            .into_iter()
+            .map(|entry| entry.path.as_path())
+            .collect();
+        let expanded = match expand(line, selected, &targets) {
+            Ok(expanded) => expanded,
+            Err(message) => {
+                status.0 = message;
+                continue;
+            }
+        };
+        let output = Arc::new(Mutex::new(Vec::new()));
+        match spawn(&expanded, &current_dir.path, &child_env, &output) {
+            Ok(child) => {
+                *view = ShellView {
+                    command: line.clone(),
+                    output,
+                    child: Some(child),
+                    outcome: None,
+                    scroll: 0,
+                    follow: true,
+                };
+                if !focus.is_open(Panel::Shell) {
+                    spawn_panel(&mut commands);
+                }
+                focus.open(Panel::Shell);
+            }
+            Err(err) => status.0 = format!("Cannot run {}: {}", line, err),
+        }
+    }
+}
+
+/// Notice when the command ends
+fn watch_shell_command(
+    mut view: ResMut<ShellView>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    focus: Res<Focus>,
+    mut status: ResMut<StatusMessage>,
+) {
+    let Some(child) = &mut view.child else {
+        return;
+    };
+    let outcome = match child.try_wait() {
+        Ok(None) => return,
+        Ok(Some(exit)) => match exit.code() {
+            Some(0) => "done".to_string(),
+            Some(code) => format!("exit {}", code),
+            None => "stopped".to_string(),
+        },
+        Err(err) => err.to_string(),
+    };
+    if !focus.is_open(Panel::Shell) {
+        status.0 = format!("{}: {}", view.command, outcome);
+    }
+    view.child = None;
+    view.outcome = Some(outcome);
+    current_dir.keep_selection();
+}
+
+fn handle_shell_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut view: ResMut<ShellView>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<ShellPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::Shell)
+    {
+        focus.close(Panel::Shell);
+        for entity in panel_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    if !focus.has_focus(Panel::Shell) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    let count = view.output.lock().map(|lines| lines.len()).unwrap_or(0);
+    let last = count.saturating_sub(PANEL_ROWS);
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if ctrl && keyboard.just_pressed(KeyCode::KeyC) {
+        if let Some(child) = &mut view.child {
+            let _ = child.kill();
+        }
+    }
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        view.scroll = (view.scroll + 1).min(last);
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        view.scroll = view.scroll.saturating_sub(1);
+        view.follow = false;
+    }
+    if ctrl && keyboard.just_pressed(KeyCode::KeyD) {
+        view.scroll = (view.scroll + PANEL_ROWS / 2).min(last);
+    }
+    if ctrl && keyboard.just_pressed(KeyCode::KeyU) {
+        view.scroll = view.scroll.saturating_sub(PANEL_ROWS / 2);
+        view.follow = false;
+    }
+    if keyboard.just_pressed(KeyCode::KeyG) {
+        view.follow = shift;
+        view.scroll = if shift { last } else { 0 };
+    }
+}
+
+fn update_shell_panel(
+    mut view: ResMut<ShellView>,
+    focus: Res<Focus>,
+    mut text_query: Query<&mut Text, With<ShellText>>,
+) {
+    if !focus.is_open(Panel::Shell) {
+        return;
+    }
+    let output = view.output.clone();
+    let Ok(lines) = output.lock() else {
+        return;
+    };
+    let last = lines.len().saturating_sub(PANEL_ROWS);
+    if view.follow {
+        view.scroll = last;
+    }
+    let scroll = view.scroll.min(last);
+    let end = (scroll + PANEL_ROWS).min(lines.len());
+    let state = match &view.outcome {
+        Some(outcome) => outcome.as_str(),
+        None => "running  Ctrl-c:stop",
+    };
+    let mut sections = vec![TextSection::new(
+        format!(
+            ":!{}  [{}]  {}-{}/{}  j/k:scroll  G:follow  Esc:close\n",
+            view.command,
+            state,
+            (scroll + 1).min(end),
+            end,
+            lines.len()
+        ),
+        panel_style(FELIPE_ORANGE),
+    )];
+    for line in &lines[scroll..end] {
+        let color = if line.error {
+            DIFF_REMOVED
+        } else {
+            FELIPE_ORANGE_DIM
+        };
+        sections.push(TextSection::new(
+            format!("{}\n", line.text),
+            panel_style(color),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Running
+// =============================================================================
+
+/// `line` with `%*`, `%` and `%%` replaced
+pub fn expand(line: &str, selected: Option<&Path>, targets: &[&Path]) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('%') => {
+                chars.next();
+                expanded.push('%');
+            }
+            Some('*') => {
+                chars.next();
+                if targets.is_empty() {
+                    return Err("Nothing selected for %*".to_string());
+                }
+                let quoted: Vec<String> = targets.iter().map(|path| quote(path)).collect();
+                expanded.push_str(&quoted.join(" "));
+            }
+            _ => match selected {
+                Some(path) => expanded.push_str(&quote(path)),
+                None => return Err("Nothing selected for %".to_string()),
+            },
+        }
+    }
+    Ok(expanded)
+}
+
+/// `path` as one shell word
+#[cfg(not(target_os = "windows"))]
+fn quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+#[cfg(target_os = "windows")]
+fn quote(path: &Path) -> String {
+    // Windows paths can't contain double quotes
+    format!("\"{}\"", path.to_string_lossy())
+}
+
+/// `line` run by the platform's shell
+#[cfg(not(target_os = "windows"))]
+pub fn shell_process(line: &str) -> Process {
+    let mut process = Process::new("sh");
+    process.arg("-c").arg(line);
+    process
+}
+
+#[cfg(target_os = "windows")]
+pub fn shell_process(line: &str) -> Process {
+    use std::os::windows::process::CommandExt;
+    // cmd has its own quoting rules; hand it the line as typed
+    let mut process = Process::new("cmd");
+    process.arg("/C").raw_arg(line);
+    process
+}
+
+fn spawn(
+    line: &str,
+    dir: &Path,
+    child_env: &ChildEnv,
+    output: &Arc<Mutex<Vec<OutputLine>>>,
+) -> std::io::Result<Child> {
+    let mut child = child_env
+        .apply(&mut shell_process(line))
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(stdout) = child.stdout.take() {
+        read_lines(stdout, false, output.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        read_lines(stderr, true, output.clone());
+    }
+    Ok(child)
+}
+
+/// Collect lines of `pipe` on a thread until the command closes it
+fn read_lines(pipe: impl Read + Send + 'static, error: bool, output: Arc<Mutex<Vec<OutputLine>>>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(pipe).split(b'\n') {
+            let Ok(line) = line else {
+                break;
+            };
+            let text = String::from_utf8_lossy(&line)
+                .trim_end_matches('\r')
+                .to_string();
+            if let Ok(mut lines) = output.lock() {
+                if lines.len() < MAX_LINES {
+                    lines.push(OutputLine { error, text });
+                }
+            }
+        }
+    });
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 14.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    right: Val::Px(10.0),
+                    max_width: Val::Px(720.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            ShellPanel,
+            Focusable(Panel::Shell),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), ShellText));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_is_the_selected_path_quoted() {
+        let path = Path::new("/home/me/a b.txt");
+        assert_eq!(
+            expand("wc -l %", Some(path), &[path]).unwrap(),
+            format!("wc -l {}", quote(path))
+        );
+    }
+
+    #[test]
+    fn percent_star_is_every_target_quoted() {
+        let (a, b) = (Path::new("/x/a"), Path::new("/x/b c"));
+        assert_eq!(
+            expand("du -sh %*", Some(a), &[a, b]).unwrap(),
+            format!("du -sh {} {}", quote(a), quote(b))
+        );
+    }
+
+    #[test]
+    fn double_percent_is_a_plain_percent() {
+        assert_eq!(
+            expand("date +%%Y-%%m %%*", None, &[]).unwrap(),
+            "date +%Y-%m %*"
+        );
+        assert_eq!(
+            expand("echo 100%% done", None, &[]).unwrap(),
+            "echo 100% done"
+        );
+    }
+
+    #[test]
+    fn lines_without_percent_are_left_alone() {
+        assert_eq!(expand("ls -la", None, &[]).unwrap(), "ls -la");
+        assert_eq!(expand("", None, &[]).unwrap(), "");
+    }
+
+    #[test]
+    fn nothing_selected_is_an_error() {
+        assert!(expand("rm %", None, &[]).is_err());
+        assert!(expand("rm %*", Some(Path::new("/x")), &[]).is_err());
+        // A trailing % stands for the selection too
+        assert!(expand("echo %", None, &[]).is_err());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn quote_makes_one_sh_word() {
+        assert_eq!(quote(Path::new("/x/a b")), "'/x/a b'");
+        assert_eq!(quote(Path::new("it's")), r"'it'\''s'");
+        for name in [
+            "a b",
+            "it's",
+            "$(rm -rf ~)",
+            "`x`",
+            "a\"b",
+            "*",
+            "new\nline",
+            "'",
+        ] {
+            let output = shell_process(&format!("printf %s {}", quote(Path::new(name))))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), name);
+        }
+    }
+}
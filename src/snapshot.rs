@@ -0,0 +1,630 @@
+//! Snapshots - lightweight change tracking without git
+//!
+//! `:snapshot` records the tree under the current directory (sizes and mtimes,
+//! plus content hashes with `:snapshot hash`). `:changes` compares the tree
+//! against it and colors the scene like a diff; removed entries have nothing
+//! left to draw, so they are listed in a panel instead.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::{
+    data_dir, CurrentDirectory, EntryTints, StatusMessage, UiElement, DIFF_ADDED, DIFF_MODIFIED,
+    DIFF_REMOVED, FELIPE_ORANGE, FELIPE_ORANGE_DIM,
+};
+
+/// Walks stop here so a snapshot of `/` can't run forever
+const MAX_ENTRIES: usize = 200_000;
+/// Changed paths listed in the panel per category
+const PANEL_ROWS: usize = 12;
+const FILE_HEADER: &str = "# felipe snapshot v1";
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+// =============================================================================
+// Tree State
+// =============================================================================
+
+#[derive(Clone, PartialEq, Eq)]
+struct EntryState {
+    is_dir: bool,
+    size: u64,
+    /// Seconds since the epoch
+    mtime: u64,
+    hash: Option<u64>,
+}
+
+impl EntryState {
+    /// Directories change mtime whenever a child does, so only files compare
+    fn differs_from(&self, old: &EntryState) -> bool {
+        if self.is_dir != old.is_dir {
+            return true;
+        }
+        if self.is_dir {
+            return false;
+        }
+        match (self.hash, old.hash) {
+            (Some(a), Some(b)) => a != b || self.size != old.size,
+            _ => self.size != old.size || self.mtime != old.mtime,
+        }
+    }
+}
+
+/// Every entry below `root`, keyed by path relative to it
+pub struct TreeState {
+    root: PathBuf,
+    taken_at: SystemTime,
+    entries: HashMap<PathBuf, EntryState>,
+    pub truncated: bool,
+}
+
+/// Added, removed and modified paths, relative to `root`
+pub struct ChangeSet {
+    pub root: PathBuf,
+    taken_at: SystemTime,
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub truncated: bool,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Colors for changed entries; directories holding any change count as modified
+    pub fn tints(&self) -> HashMap<PathBuf, Color> {
+        let mut colors = HashMap::new();
+        for (paths, color) in [
+            (&self.removed, DIFF_REMOVED),
+            (&self.modified, DIFF_MODIFIED),
+            (&self.added, DIFF_ADDED),
+        ] {
+            for path in paths {
+                for ancestor in path.ancestors().skip(1) {
+                    if ancestor.as_os_str().is_empty() {
+                        break;
+                    }
+                    colors
+                        .entry(self.root.join(ancestor))
+                        .or_insert(DIFF_MODIFIED);
+                }
+                colors.insert(self.root.join(path), color);
+            }
+        }
+        colors
+    }
+}
+
+// =============================================================================
+// State
+// =============================================================================
+
+enum TaskResult {
+    Recorded {
+        root: PathBuf,
+        count: usize,
+        truncated: bool,
+    },
+    Compared(ChangeSet),
+    Failed(String),
+}
+
+/// Snapshot work runs on a worker thread; large trees take a while
+#[derive(Resource, Default)]
+struct SnapshotTask {
+    worker: Option<JoinHandle<TaskResult>>,
+}
+
+/// `:changes` result currently shown in the scene
+#[derive(Resource, Default)]
+struct ChangesView {
+    changes: Option<ChangeSet>,
+}
+
+/// Marker for the changes panel
+#[derive(Component)]
+struct ChangesPanel;
+
+/// Marker for the changes panel text
+#[derive(Component)]
+struct ChangesText;
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SnapshotTask::default())
+            .insert_resource(ChangesView::default())
+            .add_systems(
+                Update,
+                (
+                    handle_snapshot_commands,
+                    finish_snapshot_task,
+                    update_changes_panel,
+                ),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_snapshot_commands(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut task: ResMut<SnapshotTask>,
+    mut view: ResMut<ChangesView>,
+    mut tints: ResMut<EntryTints>,
+    current_dir: Res<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    panel_query: Query<Entity, With<ChangesPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let job: Box<dyn FnOnce() -> TaskResult + Send> = match *command {
+            Command::Snapshot { hash } => {
+                let root = current_dir.path.clone();
+                status.0 = format!("Recording snapshot of {}...", root.display());
+                Box::new(move || record_snapshot(root, hash))
+            }
+            Command::Changes if view.changes.is_some() => {
+                view.changes = None;
+                tints.colors.clear();
+                for entity in panel_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                continue;
+            }
+            Command::Changes => {
+                let root = current_dir.path.clone();
+                status.0 = format!("Comparing {} with its snapshot...", root.display());
+                Box::new(move || compare_with_snapshot(root))
+            }
+            _ => continue,
+        };
+
+        if task.worker.is_some() {
+            status.0 = "A snapshot task is already running".to_string();
+            continue;
+        }
+        task.worker = Some(std::thread::spawn(job));
+    }
+}
+
+fn finish_snapshot_task(
+    mut commands: Commands,
+    mut task: ResMut<SnapshotTask>,
+    mut view: ResMut<ChangesView>,
+    mut tints: ResMut<EntryTints>,
+    mut status: ResMut<StatusMessage>,
+    panel_query: Query<Entity, With<ChangesPanel>>,
+) {
+    if !task
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        return;
+    }
+    let worker = task.worker.take().expect("checked above");
+    let result = worker
+        .join()
+        .unwrap_or_else(|_| TaskResult::Failed("Snapshot worker crashed".to_string()));
+
+    match result {
+        TaskResult::Recorded {
+            root,
+            count,
+            truncated,
+        } => {
+            status.0 = format!(
+                "Snapshot of {}: {} entries{}",
+                root.display(),
+                count,
+                if truncated { " (truncated)" } else { "" }
+            );
+        }
+        TaskResult::Compared(changes) => {
+            status.0 = if changes.is_empty() {
+                "No changes since the snapshot".to_string()
+            } else {
+                format!(
+                    "{} added, {} removed, {} modified since the snapshot",
+                    changes.added.len(),
+                    changes.removed.len(),
+                    changes.modified.len()
+                )
+            };
+            tints.colors = changes.tints();
+            view.changes = Some(changes);
+            if panel_query.is_empty() {
+                spawn_panel(&mut commands);
+            }
+        }
+        TaskResult::Failed(message) => status.0 = message,
+    }
+}
+
+fn update_changes_panel(
+    view: Res<ChangesView>,
+    config: Res<Config>,
+    mut text_query: Query<&mut Text, With<ChangesText>>,
+) {
+    let Some(changes) = &view.changes else {
+        return;
+    };
+    let age = SystemTime::now()
+        .duration_since(changes.taken_at)
+        .unwrap_or_default();
+
+    let mut sections = vec![
+        TextSection::new(
+            "CHANGES  (:changes again to close)\n",
+            panel_style(FELIPE_ORANGE),
+        ),
+        TextSection::new(
+            format!(
+                "{}\nsnapshot taken {} ({} ago){}\n",
+                changes.root.display(),
+                config.format.date(changes.taken_at),
+                format_age(age),
+                if changes.truncated {
+                    ", tree truncated"
+                } else {
+                    ""
+                }
+            ),
+            panel_style(FELIPE_ORANGE_DIM),
+        ),
+    ];
+    if changes.is_empty() {
+        sections.push(TextSection::new(
+            "(no changes)",
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    for (marker, paths, color) in [
+        ("+", &changes.added, DIFF_ADDED),
+        ("-", &changes.removed, DIFF_REMOVED),
+        ("~", &changes.modified, DIFF_MODIFIED),
+    ] {
+        for path in paths.iter().take(PANEL_ROWS) {
+            sections.push(TextSection::new(
+                format!("{} {}\n", marker, path.display()),
+                panel_style(color),
+            ));
+        }
+        if paths.len() > PANEL_ROWS {
+            sections.push(TextSection::new(
+                format!("  ... {} more\n", paths.len() - PANEL_ROWS),
+                panel_style(color),
+            ));
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Worker Tasks
+// =============================================================================
+
+fn record_snapshot(root: PathBuf, hash: bool) -> TaskResult {
+    let state = scan_tree(&root, hash);
+    match save_snapshot(&state) {
+        Ok(()) => TaskResult::Recorded {
+            root,
+            count: state.entries.len(),
+            truncated: state.truncated,
+        },
+        Err(err) => TaskResult::Failed(format!("Could not save snapshot: {}", err)),
+    }
+}
+
+fn compare_with_snapshot(root: PathBuf) -> TaskResult {
+    let old = match load_snapshot(&root) {
+        Ok(Some(old)) => old,
+        Ok(None) => {
+            return TaskResult::Failed(format!(
+                "No snapshot for {} - take one with :snapshot",
+                root.display()
+            ))
+        }
+        Err(err) => return TaskResult::Failed(format!("Could not read snapshot: {}", err)),
+    };
+    // Only hash again if the snapshot has hashes to compare against
+    let hashed = old.entries.values().any(|state| state.hash.is_some());
+    let new = scan_tree(&root, hashed);
+    TaskResult::Compared(compare_trees(&old, &new))
+}
+
+/// What changed from `old` to `new`, as seen from `new`'s root
+pub fn compare_trees(old: &TreeState, new: &TreeState) -> ChangeSet {
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+    for (path, state) in &new.entries {
+        match old.entries.get(path) {
+            None => added.push(path.clone()),
+            Some(old_state) if state.differs_from(old_state) => modified.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    let mut removed: Vec<PathBuf> = old
+        .entries
+        .keys()
+        .filter(|path| !new.entries.contains_key(*path))
+        .cloned()
+        .collect();
+
+    // Inside an added or removed directory, the directory itself says it all
+    let added_dirs: HashSet<PathBuf> = added
+        .iter()
+        .filter(|path| new.entries[*path].is_dir)
+        .cloned()
+        .collect();
+    let removed_dirs: HashSet<PathBuf> = removed
+        .iter()
+        .filter(|path| old.entries[*path].is_dir)
+        .cloned()
+        .collect();
+    added.retain(|path| !has_ancestor_in(path, &added_dirs));
+    removed.retain(|path| !has_ancestor_in(path, &removed_dirs));
+
+    added.sort();
+    removed.sort();
+    modified.sort();
+    ChangeSet {
+        root: new.root.clone(),
+        taken_at: old.taken_at,
+        added,
+        removed,
+        modified,
+        truncated: old.truncated || new.truncated,
+    }
+}
+
+fn has_ancestor_in(path: &Path, dirs: &HashSet<PathBuf>) -> bool {
+    path.ancestors()
+        .skip(1)
+        .any(|ancestor| dirs.contains(ancestor))
+}
+
+/// Walk `root` without following symlinks
+pub fn scan_tree(root: &Path, hash: bool) -> TreeState {
+    let mut state = TreeState {
+        root: root.to_path_buf(),
+        taken_at: SystemTime::now(),
+        entries: HashMap::new(),
+        truncated: false,
+    };
+    let mut stack = vec![root.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            if state.entries.len() >= MAX_ENTRIES {
+                state.truncated = true;
+                return state;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let is_dir = metadata.is_dir();
+            let mtime = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|age| age.as_secs())
+                .unwrap_or(0);
+            let content_hash = if hash && metadata.is_file() {
+                hash_file(&path).ok()
+            } else {
+                None
+            };
+
+            state.entries.insert(
+                relative.to_path_buf(),
+                EntryState {
+                    is_dir,
+                    size: if is_dir { 0 } else { metadata.len() },
+                    mtime,
+                    hash: content_hash,
+                },
+            );
+            if is_dir {
+                stack.push(path);
+            }
+        }
+    }
+    state
+}
+
+/// FNV-1a over the file contents; stable across builds, unlike `DefaultHasher`
+fn hash_file(path: &Path) -> std::io::Result<u64> {
+    let mut file = std::fs::File::open(path)?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut hash = FNV_OFFSET;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hash);
+        }
+        hash = fnv1a(hash, &buffer[..read]);
+    }
+}
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+// =============================================================================
+// Storage
+// =============================================================================
+
+/// One file per snapshotted directory, named after a hash of its path
+fn snapshot_file(root: &Path) -> std::io::Result<PathBuf> {
+    let dir = data_dir()
+        .ok_or_else(|| std::io::Error::other("no data directory on this system"))?
+        .join("snapshots");
+    let name = fnv1a(FNV_OFFSET, root.as_os_str().as_encoded_bytes());
+    Ok(dir.join(format!("{:016x}.tsv", name)))
+}
+
+/// Tab-separated, path last: `kind size mtime hash path`
+fn save_snapshot(state: &TreeState) -> std::io::Result<()> {
+    let path = snapshot_file(&state.root)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let taken_at = state
+        .taken_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut out = BufWriter::new(std::fs::File::create(&path)?);
+    writeln!(
+        out,
+        "{}\t{}\t{}\t{}",
+        FILE_HEADER,
+        taken_at,
+        state.truncated,
+        state.root.display()
+    )?;
+    for (relative, entry) in &state.entries {
+        let Some(name) = relative.to_str() else {
+            continue;
+        };
+        // A newline in a name would break the line format
+        if name.contains('\n') {
+            continue;
+        }
+        let hash = entry
+            .hash
+            .map(|h| format!("{:016x}", h))
+            .unwrap_or_else(|| "-".to_string());
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}",
+            if entry.is_dir { "d" } else { "f" },
+            entry.size,
+            entry.mtime,
+            hash,
+            name
+        )?;
+    }
+    out.flush()
+}
+
+fn load_snapshot(root: &Path) -> std::io::Result<Option<TreeState>> {
+    let path = snapshot_file(root)?;
+    let file = match std::fs::File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "unrecognized format");
+
+    let mut lines = BufReader::new(file).lines();
+    let header = lines.next().ok_or_else(invalid)??;
+    let mut fields = header.splitn(4, '\t');
+    if fields.next() != Some(FILE_HEADER) {
+        return Err(invalid());
+    }
+    let taken_at = fields
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(invalid)?;
+    let truncated = fields.next() == Some("true");
+
+    let mut entries = HashMap::new();
+    for line in lines {
+        let line = line?;
+        let fields: Vec<&str> = line.splitn(5, '\t').collect();
+        let [kind, size, mtime, hash, name] = fields.as_slice() else {
+            continue;
+        };
+        entries.insert(
+            PathBuf::from(name),
+            EntryState {
+                is_dir: *kind == "d",
+                size: size.parse().unwrap_or(0),
+                mtime: mtime.parse().unwrap_or(0),
+                hash: u64::from_str_radix(hash, 16).ok(),
+            },
+        );
+    }
+
+    Ok(Some(TreeState {
+        root: root.to_path_buf(),
+        taken_at: UNIX_EPOCH + Duration::from_secs(taken_at),
+        entries,
+        truncated,
+    }))
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(520.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            ChangesPanel,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), ChangesText));
+        });
+}
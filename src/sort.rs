@@ -0,0 +1,529 @@
+//! Sort orders - `s` cycles through them
+//!
+//! An order is a list of keys, compared in turn until two entries differ.
+//! A leading `-` reverses a key. Besides the built-in orders, more can be
+//! defined in the config:
+//!
+//! ```toml
+//! [sort]
+//! default = "dirs last"
+//!
+//! [[sort.order]]
+//! name = "dirs last"
+//! keys = ["-dirs", "name"]
+//!
+//! [[sort.order]]
+//! name = "dotfiles first"
+//! keys = ["dotfiles", "dirs", "extension", "name"]
+//! ```
+//!
+//! Keys: `dirs` (directories first), `dotfiles` (hidden entries first),
+//! `name`, `extension`, `size`, `mtime` (last modified), `taken` (when a
+//! photo was shot, from its EXIF data; entries without a date come last).
+//! `taken` opens every photo in the directory, so it is slow on big ones.
+//!
+//! Names compare naturally by default, so `file2` comes before `file10`. An
+//! order can set `collation = "plain"` (character by character) or
+//! `collation = "unicode"` (dictionary order for accented names); `collation`
+//! under `[sort]` sets it for the built-in orders.
+//!
+//! `:sort size desc` sorts by a single key (directories still first) until `s`
+//! moves on to the next order.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::photo;
+use crate::{CurrentDirectory, FileEntry, StatusMessage};
+
+// =============================================================================
+// Orders
+// =============================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortField {
+    Dirs,
+    Dotfiles,
+    Name,
+    Extension,
+    Size,
+    Modified,
+    Taken,
+}
+
+/// EXIF dates of the entries being sorted, read up front for `taken`
+type TakenDates = HashMap<PathBuf, String>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortKey {
+    field: SortField,
+    descending: bool,
+}
+
+impl<'de> Deserialize<'de> for SortKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("unknown sort key {:?}", value)))
+    }
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (name, descending) = match s.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (s, false),
+        };
+        let field = match name {
+            "dirs" => SortField::Dirs,
+            "dotfiles" => SortField::Dotfiles,
+            "name" => SortField::Name,
+            "extension" => SortField::Extension,
+            "size" => SortField::Size,
+            "mtime" => SortField::Modified,
+            "taken" => SortField::Taken,
+            _ => return Err(()),
+        };
+        Ok(Self { field, descending })
+    }
+}
+
+impl SortKey {
+    fn compare(
+        &self,
+        a: &FileEntry,
+        b: &FileEntry,
+        names: &mut NameComparer,
+        dates: &TakenDates,
+    ) -> Ordering {
+        let ordering = match self.field {
+            // `true` sorts after `false`, so flip the flags that should come first
+            SortField::Dirs => b.is_dir.cmp(&a.is_dir),
+            SortField::Dotfiles => b.name.starts_with('.').cmp(&a.name.starts_with('.')),
+            SortField::Name => names.compare(&a.name, &b.name),
+            SortField::Extension => names.compare(extension(&a.name), extension(&b.name)),
+            SortField::Size => a.size.cmp(&b.size),
+            SortField::Modified => a.modified.cmp(&b.modified),
+            // Undated entries go last either way, so only dates are reversed
+            SortField::Taken => match (dates.get(&a.path), dates.get(&b.path)) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => return Ordering::Less,
+                (None, Some(_)) => return Ordering::Greater,
+                (None, None) => return Ordering::Equal,
+            },
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self.field {
+            SortField::Dirs => "dirs",
+            SortField::Dotfiles => "dotfiles",
+            SortField::Name => "name",
+            SortField::Extension => "extension",
+            SortField::Size => "size",
+            SortField::Modified => "mtime",
+            SortField::Taken => "taken",
+        };
+        if self.descending {
+            write!(f, "{} desc", name)
+        } else {
+            f.write_str(name)
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SortOrder {
+    pub name: String,
+    keys: Vec<SortKey>,
+    /// How names (and extensions) compare
+    collation: Option<Collation>,
+}
+
+impl SortOrder {
+    fn builtin(name: &str, keys: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            keys: keys.iter().filter_map(|key| key.parse().ok()).collect(),
+            collation: None,
+        }
+    }
+
+    /// Sort by each key in turn; names break any remaining tie
+    pub(crate) fn sort(&self, entries: &mut [FileEntry]) {
+        let mut names = NameComparer::new(self.collation.unwrap_or_default());
+        let dates: TakenDates = if self.keys.iter().any(|key| key.field == SortField::Taken) {
+            entries
+                .iter()
+                .filter(|entry| !entry.is_dir)
+                .filter_map(|entry| Some((entry.path.clone(), photo::taken(&entry.path)?)))
+                .collect()
+        } else {
+            TakenDates::new()
+        };
+        entries.sort_by(|a, b| {
+            self.keys
+                .iter()
+                .map(|key| key.compare(a, b, &mut names, &dates))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| names.compare(&a.name, &b.name))
+        });
+    }
+}
+
+// =============================================================================
+// Names
+// =============================================================================
+
+/// How `name` and `extension` compare
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Collation {
+    /// Ignoring case, with digit runs compared as numbers: file2 < file10
+    #[default]
+    Natural,
+    /// Ignoring case, character by character: file10 < file2
+    Plain,
+    /// Unicode collation (accented letters next to their base letter,
+    /// as in a dictionary), with numbers compared like `natural`
+    Unicode,
+}
+
+struct NameComparer {
+    collation: Collation,
+    /// Built on first use; it's only needed for `unicode`
+    collator: Option<feruca::Collator>,
+}
+
+impl NameComparer {
+    fn new(collation: Collation) -> Self {
+        Self {
+            collation,
+            collator: None,
+        }
+    }
+
+    fn compare(&mut self, a: &str, b: &str) -> Ordering {
+        let ordering = match self.collation {
+            Collation::Plain => compare_text(a, b),
+            Collation::Natural | Collation::Unicode => {
+                let mut a_chunks = chunks(a);
+                let mut b_chunks = chunks(b);
+                loop {
+                    let ordering = match (a_chunks.next(), b_chunks.next()) {
+                        (None, None) => break Ordering::Equal,
+                        (None, Some(_)) => break Ordering::Less,
+                        (Some(_), None) => break Ordering::Greater,
+                        (Some(a), Some(b)) => self.compare_chunk(a, b),
+                    };
+                    if ordering.is_ne() {
+                        break ordering;
+                    }
+                }
+            }
+        };
+        // Names differing only in case or leading zeros still get a fixed order
+        ordering.then_with(|| a.cmp(b))
+    }
+
+    fn compare_chunk(&mut self, a: &str, b: &str) -> Ordering {
+        match (is_number(a), is_number(b)) {
+            (true, true) => compare_numbers(a, b),
+            // Numbers sort before text, as they do by character code
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) if self.collation == Collation::Unicode => self
+                .collator
+                .get_or_insert_with(feruca::Collator::default)
+                .collate(a, b),
+            (false, false) => compare_text(a, b),
+        }
+    }
+}
+
+fn compare_text(a: &str, b: &str) -> Ordering {
+    let lower = |s: &str| s.chars().flat_map(char::to_lowercase).collect::<Vec<_>>();
+    lower(a).cmp(&lower(b))
+}
+
+/// Runs of ASCII digits and runs of everything else
+fn chunks(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+fn is_number(chunk: &str) -> bool {
+    chunk.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Compare digit runs by value, however long they are
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Text after the last dot; dotfiles without another dot have none
+fn extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(0) | None => "",
+        Some(dot) => &name[dot + 1..],
+    }
+}
+
+// =============================================================================
+// Config
+// =============================================================================
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SortConfig {
+    /// Name of the order to start with
+    pub default: Option<String>,
+    /// Collation for the built-in orders and `:sort`
+    pub collation: Option<Collation>,
+    /// Orders added after the built-in ones
+    #[serde(rename = "order")]
+    pub orders: Vec<SortOrder>,
+}
+
+// =============================================================================
+// Cycle
+// =============================================================================
+
+/// Orders `s` cycles through, and the one in use
+#[derive(Resource)]
+pub struct Sorting {
+    orders: Vec<SortOrder>,
+    active: usize,
+    /// Order set with `:sort`, used instead of the cycled one while set
+    command: Option<SortOrder>,
+    /// `[sort] collation`, for orders that don't set their own
+    collation: Option<Collation>,
+}
+
+impl Default for Sorting {
+    fn default() -> Self {
+        Self {
+            orders: vec![
+                SortOrder::builtin("dirs first", &["dirs", "name"]),
+                SortOrder::builtin("name", &["name"]),
+                SortOrder::builtin("size", &["dirs", "-size"]),
+                SortOrder::builtin("modified", &["dirs", "-mtime"]),
+                SortOrder::builtin("extension", &["dirs", "extension", "name"]),
+            ],
+            active: 0,
+            command: None,
+            collation: None,
+        }
+    }
+}
+
+impl Sorting {
+    /// The built-in orders with the config's added, starting on its default
+    pub fn from_config(config: &SortConfig) -> Self {
+        let mut sorting = Sorting {
+            collation: config.collation,
+            ..default()
+        };
+        for order in &mut sorting.orders {
+            order.collation = config.collation;
+        }
+        for order in &config.orders {
+            // A config order with a built-in's name replaces it
+            match sorting.orders.iter_mut().find(|o| o.name == order.name) {
+                Some(existing) => *existing = order.clone(),
+                None => sorting.orders.push(order.clone()),
+            }
+        }
+        if let Some(default) = &config.default {
+            match sorting.orders.iter().position(|o| &o.name == default) {
+                Some(index) => sorting.active = index,
+                None => warn!("No sort order named {:?}", default),
+            }
+        }
+        sorting
+    }
+
+    pub fn active(&self) -> &SortOrder {
+        self.command.as_ref().unwrap_or(&self.orders[self.active])
+    }
+
+    /// Switch to the next order and return it
+    pub fn cycle(&mut self) -> &SortOrder {
+        // Leaving a `:sort` order resumes the cycle where it was
+        if self.command.take().is_none() {
+            self.active = (self.active + 1) % self.orders.len();
+        }
+        self.active()
+    }
+
+    /// Sort by `key`, with directories first unless `key` is about them
+    pub fn sort_by(&mut self, key: SortKey) -> &SortOrder {
+        let keys = match key.field {
+            SortField::Dirs | SortField::Dotfiles => vec![key],
+            _ => vec!["dirs".parse().expect("built-in key"), key],
+        };
+        self.command.insert(SortOrder {
+            name: key.to_string(),
+            keys,
+            collation: self.collation,
+        })
+    }
+}
+
+pub struct SortPlugin;
+
+impl Plugin for SortPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sorting>()
+            .add_systems(Startup, load_sort_orders)
+            .add_systems(Update, handle_sort_command);
+    }
+}
+
+fn load_sort_orders(config: Res<Config>, mut sorting: ResMut<Sorting>) {
+    *sorting = Sorting::from_config(&config.sort);
+}
+
+fn handle_sort_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut sorting: ResMut<Sorting>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Sort(key) = command else {
+            continue;
+        };
+        let Some(key) = key else {
+            status.0 = format!("Sort: {}", sorting.active().name);
+            continue;
+        };
+        status.0 = format!("Sort: {}", sorting.sort_by(*key).name);
+        // Keep the cursor on the same entry, like `s`
+        current_dir.keep_selection();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(collation: Collation, names: &[&str]) -> Vec<String> {
+        let mut comparer = NameComparer::new(collation);
+        let mut names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        names.sort_by(|a, b| comparer.compare(a, b));
+        names
+    }
+
+    #[test]
+    fn natural_compares_digit_runs_as_numbers() {
+        assert_eq!(
+            sorted(Collation::Natural, &["file10", "file2", "file1"]),
+            ["file1", "file2", "file10"]
+        );
+        assert_eq!(
+            sorted(Collation::Natural, &["v1.10", "v1.9", "v1.9.1"]),
+            ["v1.9", "v1.9.1", "v1.10"]
+        );
+    }
+
+    #[test]
+    fn natural_handles_numbers_longer_than_any_integer() {
+        let long = "99999999999999999999999999999";
+        let longer = format!("1{}", long);
+        assert_eq!(
+            sorted(Collation::Natural, &[&longer, long]),
+            [long, longer.as_str()]
+        );
+    }
+
+    #[test]
+    fn natural_ignores_case_and_leading_zeros_until_a_tie() {
+        assert_eq!(
+            sorted(Collation::Natural, &["b", "A", "a"]),
+            ["A", "a", "b"]
+        );
+        assert_eq!(
+            sorted(Collation::Natural, &["img3", "img002", "img02"]),
+            ["img002", "img02", "img3"]
+        );
+    }
+
+    #[test]
+    fn natural_puts_numbers_before_text() {
+        assert_eq!(
+            sorted(Collation::Natural, &["a", "10", "9"]),
+            ["9", "10", "a"]
+        );
+    }
+
+    #[test]
+    fn plain_compares_character_by_character() {
+        assert_eq!(
+            sorted(Collation::Plain, &["file2", "File10", "file1"]),
+            ["file1", "File10", "file2"]
+        );
+    }
+
+    #[test]
+    fn unicode_puts_accents_next_to_their_letter() {
+        assert_eq!(
+            sorted(Collation::Unicode, &["zebra", "éclair", "eagle"]),
+            ["eagle", "éclair", "zebra"]
+        );
+        // Code point order puts é after z
+        assert_eq!(
+            sorted(Collation::Natural, &["zebra", "éclair", "eagle"]),
+            ["eagle", "zebra", "éclair"]
+        );
+        assert_eq!(sorted(Collation::Unicode, &["é10", "é9"]), ["é9", "é10"]);
+    }
+
+    #[test]
+    fn extension_skips_dotfiles() {
+        assert_eq!(extension("photo.tar.gz"), "gz");
+        assert_eq!(extension(".bashrc"), "");
+        assert_eq!(extension(".config.toml"), "toml");
+        assert_eq!(extension("Makefile"), "");
+    }
+
+    #[test]
+    fn sort_keys_parse_with_a_leading_minus_for_descending() {
+        let key: SortKey = "-size".parse().unwrap();
+        assert_eq!(key.field, SortField::Size);
+        assert!(key.descending);
+        assert_eq!(key.to_string(), "size desc");
+        assert_eq!(
+            "mtime".parse::<SortKey>().unwrap().field,
+            SortField::Modified
+        );
+        assert!("bogus".parse::<SortKey>().is_err());
+    }
+}
@@ -0,0 +1,316 @@
+//! Sound effects - synthesized blips and sweeps for navigation, deletion and errors
+//!
+//! ```toml
+//! [sound]
+//! enabled = true    # also `:set sound` / `:set nosound`
+//! volume = 0.5      # 0.0-1.0
+//! ```
+//!
+//! Playback needs the `audio` cargo feature (and ALSA on Linux); without it
+//! the plugin does nothing and the settings are ignored.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SoundConfig {
+    pub enabled: bool,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    pub volume: f32,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 0.5,
+        }
+    }
+}
+
+pub struct SoundPlugin;
+
+#[cfg(feature = "audio")]
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        use bevy::audio::AddAudioSource;
+
+        app.add_audio_source::<playback::Synth>()
+            .add_systems(Startup, playback::load_sound_bank)
+            .add_systems(
+                Update,
+                (
+                    playback::navigation_sounds.after(crate::load_directory),
+                    playback::operation_sounds,
+                    playback::error_sounds,
+                ),
+            );
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+impl Plugin for SoundPlugin {
+    fn build(&self, _app: &mut App) {}
+}
+
+#[cfg(feature = "audio")]
+mod playback {
+    use bevy::audio::{Source, Volume};
+    use bevy::prelude::*;
+    use bevy::reflect::TypePath;
+    use std::collections::HashMap;
+    use std::f32::consts::PI;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use crate::config::Config;
+    use crate::glitch::OperationFailed;
+    use crate::oplog::OperationLog;
+    use crate::ops::UndoStep;
+    use crate::CurrentDirectory;
+
+    const SAMPLE_RATE: u32 = 44_100;
+
+    // =========================================================================
+    // Synthesis
+    // =========================================================================
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    enum Cue {
+        /// Selection moved
+        Click,
+        /// Entered another directory
+        Whoosh,
+        /// Something went to the trash
+        Delete,
+        /// An operation failed
+        Error,
+    }
+
+    impl Cue {
+        const ALL: [Cue; 4] = [Cue::Click, Cue::Whoosh, Cue::Delete, Cue::Error];
+
+        fn seconds(self) -> f32 {
+            match self {
+                Cue::Click => 0.025,
+                Cue::Whoosh => 0.25,
+                Cue::Delete => 0.2,
+                Cue::Error => 0.3,
+            }
+        }
+    }
+
+    /// A cue, rendered sample by sample when played
+    #[derive(Asset, TypePath)]
+    pub struct Synth {
+        cue: Cue,
+    }
+
+    impl bevy::audio::Decodable for Synth {
+        type DecoderItem = f32;
+        type Decoder = SynthDecoder;
+
+        fn decoder(&self) -> SynthDecoder {
+            SynthDecoder {
+                cue: self.cue,
+                sample: 0,
+                len: (self.cue.seconds() * SAMPLE_RATE as f32) as u32,
+                phase: 0.0,
+                noise: 0x9e37_79b9,
+                filtered: 0.0,
+            }
+        }
+    }
+
+    pub struct SynthDecoder {
+        cue: Cue,
+        sample: u32,
+        len: u32,
+        /// Oscillator position within one period, 0.0-1.0
+        phase: f32,
+        /// xorshift state for the whoosh
+        noise: u32,
+        /// Low-pass filter memory for the whoosh
+        filtered: f32,
+    }
+
+    impl SynthDecoder {
+        /// Step the oscillator at `frequency` Hz
+        fn advance(&mut self, frequency: f32) -> f32 {
+            self.phase = (self.phase + frequency / SAMPLE_RATE as f32).fract();
+            self.phase
+        }
+
+        /// White noise in -1.0..1.0
+        fn white(&mut self) -> f32 {
+            self.noise ^= self.noise << 13;
+            self.noise ^= self.noise >> 17;
+            self.noise ^= self.noise << 5;
+            self.noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+        }
+    }
+
+    fn square(phase: f32) -> f32 {
+        if phase < 0.5 {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    fn saw(phase: f32) -> f32 {
+        phase * 2.0 - 1.0
+    }
+
+    impl Iterator for SynthDecoder {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<f32> {
+            if self.sample >= self.len {
+                return None;
+            }
+            let seconds = self.sample as f32 / SAMPLE_RATE as f32;
+            let progress = self.sample as f32 / self.len as f32;
+            self.sample += 1;
+
+            let value = match self.cue {
+                // Short square blip with a fast decay
+                Cue::Click => square(self.advance(1800.0)) * (-progress * 6.0).exp(),
+                // Noise through a low-pass that opens and closes again
+                Cue::Whoosh => {
+                    let swell = (progress * PI).sin();
+                    let white = self.white();
+                    self.filtered += (white - self.filtered) * (0.02 + 0.25 * swell);
+                    self.filtered * swell * 2.0
+                }
+                // Saw sweeping down two octaves and a bit
+                Cue::Delete => {
+                    let frequency = 600.0 * 0.2f32.powf(progress);
+                    saw(self.advance(frequency)) * (1.0 - progress)
+                }
+                // Low buzz chopped by a 16 Hz tremolo
+                Cue::Error => {
+                    let tremolo = if (seconds * 16.0).fract() < 0.5 {
+                        1.0
+                    } else {
+                        0.4
+                    };
+                    square(self.advance(110.0)) * tremolo * (1.0 - progress)
+                }
+            };
+            Some(value * 0.3)
+        }
+    }
+
+    impl Source for SynthDecoder {
+        fn current_frame_len(&self) -> Option<usize> {
+            Some((self.len - self.sample) as usize)
+        }
+
+        fn channels(&self) -> u16 {
+            1
+        }
+
+        fn sample_rate(&self) -> u32 {
+            SAMPLE_RATE
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            Some(Duration::from_secs_f32(
+                self.len as f32 / SAMPLE_RATE as f32,
+            ))
+        }
+    }
+
+    // =========================================================================
+    // Systems
+    // =========================================================================
+
+    #[derive(Resource)]
+    pub struct SoundBank {
+        cues: HashMap<Cue, Handle<Synth>>,
+    }
+
+    pub fn load_sound_bank(mut commands: Commands, mut synths: ResMut<Assets<Synth>>) {
+        let cues = Cue::ALL
+            .into_iter()
+            .map(|cue| (cue, synths.add(Synth { cue })))
+            .collect();
+        commands.insert_resource(SoundBank { cues });
+    }
+
+    fn play(commands: &mut Commands, bank: &SoundBank, config: &Config, cue: Cue) {
+        if !config.sound.enabled {
+            return;
+        }
+        let Some(source) = bank.cues.get(&cue) else {
+            return;
+        };
+        commands.spawn(AudioSourceBundle {
+            source: source.clone(),
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new(config.sound.volume.clamp(0.0, 1.0))),
+        });
+    }
+
+    /// Click on selection moves, whoosh on directory changes
+    pub fn navigation_sounds(
+        mut commands: Commands,
+        bank: Res<SoundBank>,
+        config: Res<Config>,
+        current_dir: Res<CurrentDirectory>,
+        mut shown: Local<(Option<PathBuf>, usize)>,
+    ) {
+        if !current_dir.is_changed() || current_dir.needs_reload {
+            return;
+        }
+        let (shown_dir, shown_index) = &mut *shown;
+        if shown_dir.as_ref() != Some(&current_dir.path) {
+            // No whoosh for the directory Felipe starts in
+            if shown_dir.is_some() {
+                play(&mut commands, &bank, &config, Cue::Whoosh);
+            }
+            *shown_dir = Some(current_dir.path.clone());
+        } else if *shown_index != current_dir.selected_index {
+            play(&mut commands, &bank, &config, Cue::Click);
+        }
+        *shown_index = current_dir.selected_index;
+    }
+
+    /// A descending sweep when a recorded operation trashed something
+    pub fn operation_sounds(
+        mut commands: Commands,
+        bank: Res<SoundBank>,
+        config: Res<Config>,
+        oplog: Res<OperationLog>,
+        mut seen_groups: Local<usize>,
+    ) {
+        if !oplog.is_changed() {
+            return;
+        }
+        let new_groups = oplog.groups.get(*seen_groups..).unwrap_or_default();
+        let trashed = new_groups.iter().any(|group| {
+            group
+                .steps
+                .iter()
+                .any(|step| matches!(step, UndoStep::Trashed { .. }))
+        });
+        if trashed {
+            play(&mut commands, &bank, &config, Cue::Delete);
+        }
+        *seen_groups = oplog.groups.len();
+    }
+
+    pub fn error_sounds(
+        mut commands: Commands,
+        bank: Res<SoundBank>,
+        config: Res<Config>,
+        mut failures: EventReader<OperationFailed>,
+    ) {
+        // Several failures at once still make one buzz
+        if failures.read().count() > 0 {
+            play(&mut commands, &bank, &config, Cue::Error);
+        }
+    }
+}
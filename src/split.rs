@@ -0,0 +1,269 @@
+//! `:vsplit path` - a second directory beside the first, mirrored
+//!
+//! The other directory's grid stands to the right of the current one,
+//! reflected across a line parallel to the Z axis so the two face each other
+//! column for column. Keys always act on the current grid, framed bright on
+//! the floor; Ctrl-w swaps the sides, bringing the other directory (with its
+//! selection) into the current grid. `:only` closes the other side.
+//!
+//! ```text
+//! :vsplit ../backup    compare with the backup next door
+//! :vsplit              the same directory on both sides
+//! Ctrl-w               swap sides
+//! :only                back to one grid
+//! ```
+
+use bevy::prelude::*;
+
+use crate::bookmarks::expand_home;
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::filter::Filter;
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::hooks::DirectoryLoaded;
+use crate::layout::{GridLayout, LayoutProvider, GRID_COLUMNS};
+use crate::sort::Sorting;
+use crate::{
+    entry_height, truncate_label, window_around, CurrentDirectory, EntryPalette, Prompt,
+    StatusMessage, VimMode, FELIPE_GRID, FELIPE_ORANGE, FELIPE_ORANGE_DIM, ITEM_SPACING,
+};
+
+/// The mirror line, half a column gap right of the current grid
+const MIRROR_X: f32 = 9.0 + ITEM_SPACING * 1.5;
+
+/// The directory on the other side
+#[derive(Resource, Default)]
+pub struct SplitView {
+    other: Option<CurrentDirectory>,
+}
+
+/// A book or label of the other side
+#[derive(Component)]
+struct TwinEntity;
+
+pub struct SplitPlugin;
+
+impl Plugin for SplitPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SplitView::default()).add_systems(
+            Update,
+            (
+                handle_split_commands,
+                handle_swap_key,
+                refresh_other_side,
+                spawn_twin_grid,
+                draw_split_frames,
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// `:vsplit [path]` and `:only`
+fn handle_split_commands(
+    mut run_commands: EventReader<RunCommand>,
+    mut split: ResMut<SplitView>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    sorting: Res<Sorting>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        match command {
+            Command::VSplit(path) => {
+                let path = match path {
+                    Some(path) => current_dir.path.join(expand_home(path)),
+                    None => current_dir.path.clone(),
+                };
+                let Ok(path) = path.canonicalize() else {
+                    status.0 = format!("No such directory: {}", path.display());
+                    continue;
+                };
+                if !path.is_dir() {
+                    status.0 = format!("Not a directory: {}", path.display());
+                    continue;
+                }
+                let mut other = CurrentDirectory::new(&path);
+                let listing = other.reload(&config.listing, sorting.active(), &Filter::default());
+                status.0 = listing.unwrap_or_else(|| {
+                    format!("Split with {} - Ctrl-w swaps, :only closes", path.display())
+                });
+                split.other = Some(other);
+            }
+            Command::Only => {
+                let was_split = split.other.take().is_some();
+                if !was_split {
+                    status.0 = "Only one grid already".to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Ctrl-w - bring the other side into the current grid
+fn handle_swap_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    mut split: ResMut<SplitView>,
+    mut current_dir: ResMut<CurrentDirectory>,
+) {
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !ctrl
+        || !keyboard.just_pressed(KeyCode::KeyW)
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || focus.any_open()
+        || fly.enabled
+    {
+        return;
+    }
+    let Some(other) = split.other.as_mut() else {
+        return;
+    };
+    std::mem::swap(&mut *current_dir, other);
+    current_dir.keep_selection();
+}
+
+/// Re-list the other side whenever the current one is listed, so changes
+/// made across the split (a paste, a sort) show on both
+fn refresh_other_side(
+    mut loaded: EventReader<DirectoryLoaded>,
+    mut split: ResMut<SplitView>,
+    config: Res<Config>,
+    sorting: Res<Sorting>,
+) {
+    if loaded.read().count() == 0 {
+        return;
+    }
+    let Some(other) = split.other.as_mut() else {
+        return;
+    };
+    other.keep_selection();
+    other.reload(&config.listing, sorting.active(), &Filter::default());
+}
+
+fn spawn_twin_grid(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    split: Res<SplitView>,
+    twin_query: Query<Entity, With<TwinEntity>>,
+) {
+    if !split.is_changed() {
+        return;
+    }
+    for entity in twin_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(other) = &split.other else {
+        return;
+    };
+
+    let len = other.entries.len();
+    for i in window_around(other.selected_index, len) {
+        let entry = &other.entries[i];
+        let place = mirrored(GridLayout.transform(i, len).translation);
+        let height = entry_height(entry);
+        let selected = i == other.selected_index;
+        let color = if selected {
+            FELIPE_ORANGE
+        } else if entry.is_dir {
+            FELIPE_GRID
+        } else {
+            FELIPE_ORANGE_DIM
+        };
+        let material = palette.entry_material(&mut materials, entry, color.to_linear());
+        commands.spawn((
+            PbrBundle {
+                mesh: palette.cuboid(&mut meshes),
+                material,
+                transform: Transform::from_xyz(place.x, height / 2.0, place.z)
+                    .with_scale(Vec3::new(1.0, height, 1.0)),
+                ..default()
+            },
+            TwinEntity,
+        ));
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    truncate_label(&entry.name),
+                    TextStyle {
+                        font_size: 30.0,
+                        color: if selected {
+                            FELIPE_ORANGE
+                        } else {
+                            FELIPE_ORANGE_DIM
+                        },
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_xyz(place.x, height + 1.5, place.z)
+                    .with_scale(Vec3::splat(0.03)),
+                ..default()
+            },
+            TwinEntity,
+        ));
+    }
+
+    // The other side's path over its first row
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                other.path.to_string_lossy().to_string(),
+                TextStyle {
+                    font_size: 40.0,
+                    color: FELIPE_ORANGE_DIM,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_xyz(mirrored(Vec3::ZERO).x, 4.0, -ITEM_SPACING)
+                .with_scale(Vec3::splat(0.03)),
+            ..default()
+        },
+        TwinEntity,
+    ));
+}
+
+/// Floor frames: bright around the grid keys act on, dim around the other
+fn draw_split_frames(
+    mut gizmos: Gizmos,
+    split: Res<SplitView>,
+    current_dir: Res<CurrentDirectory>,
+) {
+    let Some(other) = &split.other else {
+        return;
+    };
+    let depth = |len: usize| len.div_ceil(GRID_COLUMNS).max(1) as f32 * ITEM_SPACING;
+    let half_width = 9.0 + ITEM_SPACING / 2.0;
+    for (center_x, len, color) in [
+        (0.0, current_dir.entries.len(), FELIPE_ORANGE),
+        (mirrored(Vec3::ZERO).x, other.entries.len(), FELIPE_GRID),
+    ] {
+        let depth = depth(len);
+        let center = Vec3::new(center_x, 0.01, depth / 2.0 - ITEM_SPACING / 2.0);
+        gizmos.rect(
+            center,
+            Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2),
+            Vec2::new(half_width * 2.0, depth),
+            color,
+        );
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Reflect a point of the current grid across the mirror line
+fn mirrored(point: Vec3) -> Vec3 {
+    Vec3::new(2.0 * MIRROR_X - point.x, point.y, point.z)
+}
@@ -0,0 +1,195 @@
+//! Stars - `*` marks an entry as a favorite, `:starred` lists them all
+//!
+//! A starred file or directory wears a gold ring above it wherever it's shown.
+//! `:starred` opens a view of every starred entry, wherever it lives: Enter
+//! goes into a directory or opens a file as usual, and `*` there takes the star
+//! off. Stars are kept in the metadata store (metadata.rs); entries that no
+//! longer exist are left out of the view but keep their star, in case their
+//! drive comes back.
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::command::{Command, RunCommand};
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::metadata;
+use crate::{
+    entry_height, vfs, CurrentDirectory, EntryPalette, FileEntity, FileEntry, Prompt,
+    StatusMessage, VimMode,
+};
+
+/// Path of the view `:starred` opens; nothing on disk is called that
+const STARRED_VIEW: &str = "Starred";
+/// Gold of the ring over a starred entry
+const STAR_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+
+// =============================================================================
+// Store
+// =============================================================================
+
+pub fn is_starred(path: &Path) -> bool {
+    metadata::is_starred(path)
+}
+
+/// Star `path`, or take its star off; whether it's starred now
+fn toggle(path: &Path) -> std::io::Result<bool> {
+    let on = !metadata::is_starred(path);
+    metadata::set_starred(path, on)?;
+    Ok(on)
+}
+
+pub fn starred_view() -> PathBuf {
+    PathBuf::from(STARRED_VIEW)
+}
+
+pub fn is_starred_view(path: &Path) -> bool {
+    path == Path::new(STARRED_VIEW)
+}
+
+/// The starred entries that are still there, for the `:starred` view
+pub fn starred_entries() -> Vec<FileEntry> {
+    metadata::starred()
+        .iter()
+        .filter_map(|path| vfs::backend(path).and_then(|fs| fs.stat(path)).ok())
+        .collect()
+}
+
+// =============================================================================
+// Plugin
+// =============================================================================
+
+/// Bumped on every star or unstar, so the rings get rebuilt
+#[derive(Resource, Default)]
+struct StarEdits(u64);
+
+/// Marker for the ring over a starred entry
+#[derive(Component)]
+struct StarMarker;
+
+pub struct StarsPlugin;
+
+impl Plugin for StarsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StarEdits>().add_systems(
+            Update,
+            (handle_star_key, handle_starred_command, update_star_markers),
+        );
+    }
+}
+
+/// `*` stars the selected entry, or takes its star off
+fn handle_star_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut edits: ResMut<StarEdits>,
+) {
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let star = (shift && keyboard.just_pressed(KeyCode::Digit8))
+        || keyboard.just_pressed(KeyCode::NumpadMultiply);
+    if !star
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || focus.any_open()
+        || fly.enabled
+    {
+        return;
+    }
+    let Some(path) = current_dir.selected_path().map(Path::to_path_buf) else {
+        return;
+    };
+    // `..` isn't an entry of its own
+    if current_dir
+        .entries
+        .get(current_dir.selected_index)
+        .is_some_and(|e| e.name == "..")
+    {
+        return;
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    status.0 = match toggle(&path) {
+        Ok(true) => format!("Starred {}", name),
+        Ok(false) => format!("Unstarred {}", name),
+        Err(err) => format!("Cannot save the stars: {}", err),
+    };
+    edits.0 += 1;
+    // Unstarring in the starred view takes the entry out of it
+    if is_starred_view(current_dir.path()) {
+        current_dir.keep_selection();
+    }
+}
+
+fn handle_starred_command(
+    mut run_commands: EventReader<RunCommand>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Starred {
+            continue;
+        }
+        if metadata::starred().is_empty() {
+            status.0 = "Nothing starred yet - * stars the selected entry".to_string();
+            continue;
+        }
+        current_dir.set_path(starred_view());
+    }
+}
+
+/// Rebuild rings when stars change or the entries were respawned
+fn update_star_markers(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    edits: Res<StarEdits>,
+    current_dir: Res<CurrentDirectory>,
+    entity_query: Query<(Entity, &FileEntity)>,
+    new_entities: Query<(), Added<FileEntity>>,
+    marker_query: Query<Entity, With<StarMarker>>,
+) {
+    if !edits.is_changed() && new_entities.is_empty() {
+        return;
+    }
+    for marker in marker_query.iter() {
+        commands.entity(marker).despawn();
+    }
+
+    if metadata::starred().is_empty() {
+        return;
+    }
+    let mesh = palette.mesh(&mut meshes, "star ring", || Torus::new(0.12, 0.18).into());
+    let material = palette.material(&mut materials, STAR_COLOR.to_linear());
+    for (entity, file_entity) in entity_query.iter() {
+        let Some(entry) = current_dir.entries.get(file_entity.index) else {
+            continue;
+        };
+        if !metadata::is_starred(&entry.path) {
+            continue;
+        }
+        // A halo floating over the entry; the entry's unit cuboid is
+        // stretched to its height, which the ring undoes
+        let height = entry_height(entry);
+        let marker = commands
+            .spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(0.0, 0.5 + 0.25 / height, 0.0)
+                        .with_scale(Vec3::new(1.0, 1.0 / height, 1.0)),
+                    ..default()
+                },
+                StarMarker,
+            ))
+            .id();
+        commands.entity(entity).add_child(marker);
+    }
+}
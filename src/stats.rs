@@ -0,0 +1,396 @@
+//! `:stats` - what the current directory holds, in numbers and bars
+//!
+//! A worker walks everything below the current directory that the listing
+//! would show (dotfiles and ignored files as `:set hidden` and `gitignore`
+//! say) and the panel fills in: files and directories counted, their total
+//! size, the largest files, and the share of the total each extension takes.
+//! The numbers are those of the moment `:stats` ran; `:stats` again or Esc
+//! closes the panel.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use crate::command::{Command, RunCommand};
+use crate::config::{Config, ListingConfig};
+use crate::debug;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::gitignore;
+use crate::{CurrentDirectory, StatusMessage, UiElement, FELIPE_ORANGE, FELIPE_ORANGE_DIM};
+
+/// Largest files listed
+const LARGEST_SHOWN: usize = 8;
+
+/// Extensions listed; the rest are summed up in one row
+const EXTENSIONS_SHOWN: usize = 8;
+
+/// Width of an extension's bar at 100% of the total
+const BAR_WIDTH: usize = 24;
+
+/// Upper bounds of the file-age buckets in days, newest first; older files
+/// go in one more bucket after them
+pub const AGE_BUCKETS: &[(&str, u64)] = &[
+    ("a day", 1),
+    ("a week", 7),
+    ("a month", 30),
+    ("6 months", 182),
+    ("a year", 365),
+    ("3 years", 3 * 365),
+];
+
+/// Totals for a tree (`:dashboard` charts them too)
+#[derive(Default)]
+pub struct DirStats {
+    pub files: usize,
+    pub dirs: usize,
+    pub bytes: u64,
+    /// (size, path below the root), largest first
+    pub largest: Vec<(u64, PathBuf)>,
+    /// (extension, files, bytes), most bytes first; "" for no extension
+    pub extensions: Vec<(String, usize, u64)>,
+    /// (name, bytes) of each subdirectory, most bytes first; "" for the files
+    /// right in the root
+    pub subdirs: Vec<(String, u64)>,
+    /// Files per age bucket (see `AGE_BUCKETS`), the last one for the oldest
+    pub ages: Vec<usize>,
+}
+
+/// The tree being counted, or counted
+#[derive(Resource, Default)]
+struct StatsView {
+    root: PathBuf,
+    worker: Option<JoinHandle<DirStats>>,
+    stats: Option<DirStats>,
+}
+
+/// Marker for the stats panel
+#[derive(Component)]
+struct StatsPanel;
+
+/// Marker for the stats panel text
+#[derive(Component)]
+struct StatsText;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StatsView>().add_systems(
+            Update,
+            (
+                handle_stats_command,
+                close_on_dismiss,
+                finish_count,
+                update_stats_panel,
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Counting
+// =============================================================================
+
+/// Walk `root` as the listing would show it; slow on big trees, so call it
+/// from a worker thread
+pub fn count(root: &Path, listing: &ListingConfig) -> DirStats {
+    let mut stats = DirStats {
+        ages: vec![0; AGE_BUCKETS.len() + 1],
+        ..default()
+    };
+    let mut extensions: HashMap<String, (usize, u64)> = HashMap::new();
+    let mut subdirs: HashMap<String, u64> = HashMap::new();
+    let now = SystemTime::now();
+    for entry in gitignore::walker(root, listing).build().flatten() {
+        if entry.depth() == 0 {
+            continue;
+        }
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            stats.dirs += 1;
+            continue;
+        }
+        let metadata = entry.metadata().ok();
+        let size = metadata.as_ref().map_or(0, |metadata| metadata.len());
+        stats.files += 1;
+        stats.bytes += size;
+        let extension = entry
+            .path()
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let totals = extensions.entry(extension).or_default();
+        totals.0 += 1;
+        totals.1 += size;
+
+        let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let subdir = match path.components().count() {
+            1 => String::new(),
+            _ => path
+                .components()
+                .next()
+                .map(|first| first.as_os_str().to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        *subdirs.entry(subdir).or_default() += size;
+
+        let age_days = metadata
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| now.duration_since(modified).ok())
+            .map_or(0, |age| age.as_secs() / (24 * 3600));
+        let bucket = AGE_BUCKETS
+            .iter()
+            .position(|(_, days)| age_days < *days)
+            .unwrap_or(AGE_BUCKETS.len());
+        stats.ages[bucket] += 1;
+
+        // Kept sorted and short as the walk goes
+        if stats.largest.len() < LARGEST_SHOWN || size > stats.largest[LARGEST_SHOWN - 1].0 {
+            let at = stats.largest.partition_point(|(other, _)| *other >= size);
+            stats.largest.insert(at, (size, path.to_path_buf()));
+            stats.largest.truncate(LARGEST_SHOWN);
+        }
+    }
+    stats.extensions = extensions
+        .into_iter()
+        .map(|(extension, (files, bytes))| (extension, files, bytes))
+        .collect();
+    stats
+        .extensions
+        .sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    stats.subdirs = subdirs.into_iter().collect();
+    stats
+        .subdirs
+        .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    stats
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_stats_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut view: ResMut<StatsView>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<StatsPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Stats {
+            continue;
+        }
+        if focus.is_open(Panel::Stats) {
+            close_panel(&mut commands, &mut view, &mut focus, &panel_query);
+            continue;
+        }
+        // Views and remote locations have no tree on this disk to walk
+        if !current_dir.path().is_dir() {
+            status.0 = "Stats work on local directories only".to_string();
+            continue;
+        }
+        let root = current_dir.path().to_path_buf();
+        let listing = config.listing.clone();
+        view.root = root.clone();
+        view.stats = None;
+        view.worker = Some(std::thread::spawn(move || {
+            debug::timed("stats scan", || count(&root, &listing))
+        }));
+        focus.open(Panel::Stats);
+        spawn_panel(&mut commands);
+    }
+}
+
+fn close_on_dismiss(
+    mut commands: Commands,
+    mut dismissed: EventReader<Dismiss>,
+    mut view: ResMut<StatsView>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<StatsPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::Stats)
+    {
+        close_panel(&mut commands, &mut view, &mut focus, &panel_query);
+    }
+}
+
+fn finish_count(mut view: ResMut<StatsView>, mut status: ResMut<StatusMessage>) {
+    if !view
+        .worker
+        .as_ref()
+        .is_some_and(|worker| worker.is_finished())
+    {
+        return;
+    }
+    let worker = view.worker.take().expect("checked above");
+    match worker.join() {
+        Ok(stats) => view.stats = Some(stats),
+        Err(_) => status.0 = "Counting crashed".to_string(),
+    }
+}
+
+fn update_stats_panel(
+    view: Res<StatsView>,
+    config: Res<Config>,
+    mut text_query: Query<&mut Text, With<StatsText>>,
+    spawned: Query<(), Added<StatsText>>,
+) {
+    // The panel is spawned a frame after `:stats` changes the view
+    if !view.is_changed() && spawned.is_empty() {
+        return;
+    }
+
+    let mut sections = vec![TextSection::new(
+        format!("STATS  {}\n", view.root.display()),
+        panel_style(FELIPE_ORANGE),
+    )];
+    let Some(stats) = &view.stats else {
+        sections.push(TextSection::new(
+            "counting...\n",
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+        for mut text in text_query.iter_mut() {
+            text.sections = sections.clone();
+        }
+        return;
+    };
+
+    sections.push(TextSection::new(
+        format!(
+            "{} files in {} directories, {}\n",
+            stats.files,
+            stats.dirs,
+            config.format.size(stats.bytes)
+        ),
+        panel_style(FELIPE_ORANGE_DIM),
+    ));
+
+    if !stats.largest.is_empty() {
+        sections.push(TextSection::new("\nLARGEST\n", panel_style(FELIPE_ORANGE)));
+        for (size, path) in &stats.largest {
+            sections.push(TextSection::new(
+                format!("{:>10}  {}\n", config.format.size(*size), path.display()),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+        }
+    }
+
+    if !stats.extensions.is_empty() {
+        sections.push(TextSection::new(
+            "\nBY EXTENSION\n",
+            panel_style(FELIPE_ORANGE),
+        ));
+        let mut rows: Vec<(String, usize, u64)> = stats
+            .extensions
+            .iter()
+            .take(EXTENSIONS_SHOWN)
+            .map(|(extension, files, bytes)| {
+                let name = if extension.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    format!(".{}", extension)
+                };
+                (name, *files, *bytes)
+            })
+            .collect();
+        let others = &stats.extensions[rows.len()..];
+        if !others.is_empty() {
+            rows.push((
+                format!("{} more", others.len()),
+                others.iter().map(|(_, files, _)| files).sum(),
+                others.iter().map(|(_, _, bytes)| bytes).sum(),
+            ));
+        }
+        for (name, files, bytes) in rows {
+            let fraction = if stats.bytes > 0 {
+                bytes as f64 / stats.bytes as f64
+            } else {
+                0.0
+            };
+            let filled = ((fraction * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+            sections.push(TextSection::new(
+                format!("{:<10} ", name),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+            sections.push(TextSection::new(
+                format!(
+                    "[{}{}] {:>3.0}%",
+                    "#".repeat(filled),
+                    ".".repeat(BAR_WIDTH - filled),
+                    fraction * 100.0
+                ),
+                panel_style(FELIPE_ORANGE),
+            ));
+            sections.push(TextSection::new(
+                format!("  {} in {} files\n", config.format.size(bytes), files),
+                panel_style(FELIPE_ORANGE_DIM),
+            ));
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(620.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            StatsPanel,
+            Focusable(Panel::Stats),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), StatsText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    view: &mut StatsView,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<StatsPanel>>,
+) {
+    // A count still running finishes on its own and is dropped
+    view.worker = None;
+    view.stats = None;
+    focus.close(Panel::Stats);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
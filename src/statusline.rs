@@ -0,0 +1,167 @@
+//! Status line - the bar at the bottom left, built from segments
+//!
+//! In NORMAL, VISUAL and FIND mode the bar shows the segments listed in the
+//! config, in that order, two spaces apart; a segment with nothing to say
+//! (no filter, no jobs) is left out. While a line is being typed (`:`, `r`,
+//! `f`) the bar shows that line instead.
+//!
+//! ```toml
+//! [statusline]
+//! segments = ["mode", "position", "sort", "filter", "jobs", "basket", "free", "flags"]
+//! ```
+//!
+//! Segments: `mode` (-- NORMAL --), `path` (the current directory),
+//! `position` (selection / entries), `sort` (the active order), `filter` (the
+//! `f` text or the `:filter` pattern), `jobs` (transfers running or queued),
+//! `basket` (entries gathered with Space, see basket.rs), `free` (free space
+//! on the current drive) and `flags` (READ-ONLY, DRY-RUN).
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::basket::Basket;
+use crate::command::CommandLine;
+use crate::config::Config;
+use crate::filter::Filter;
+use crate::jobs::JobQueue;
+use crate::locale::{tr, trf};
+use crate::ops;
+use crate::rename::RenameLine;
+use crate::sort::Sorting;
+use crate::volumes::Volumes;
+use crate::{CurrentDirectory, ModeIndicator, VimMode};
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Segment {
+    Mode,
+    Path,
+    Position,
+    Sort,
+    Filter,
+    Jobs,
+    Basket,
+    Free,
+    Flags,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusLineConfig {
+    pub segments: Vec<Segment>,
+}
+
+impl Default for StatusLineConfig {
+    fn default() -> Self {
+        Self {
+            segments: vec![
+                Segment::Mode,
+                Segment::Position,
+                Segment::Sort,
+                Segment::Filter,
+                Segment::Jobs,
+                Segment::Basket,
+                Segment::Free,
+                Segment::Flags,
+            ],
+        }
+    }
+}
+
+pub struct StatusLinePlugin;
+
+impl Plugin for StatusLinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_status_line);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn update_status_line(
+    current_dir: Res<CurrentDirectory>,
+    vim_mode: Res<VimMode>,
+    command_line: Res<CommandLine>,
+    rename_line: Res<RenameLine>,
+    config: Res<Config>,
+    sorting: Res<Sorting>,
+    filter: Res<Filter>,
+    jobs: Res<JobQueue>,
+    basket: Res<Basket>,
+    volumes: Res<Volumes>,
+    mut mode_query: Query<&mut Text, With<ModeIndicator>>,
+) {
+    let mode = match *vim_mode {
+        VimMode::Normal => tr("-- NORMAL --"),
+        VimMode::Visual => tr("-- VISUAL --"),
+        VimMode::Jump => tr("-- FIND --"),
+        // A line being typed has the bar to itself
+        VimMode::Command | VimMode::Rename | VimMode::Filter => "",
+    };
+    let line = match *vim_mode {
+        VimMode::Command if command_line.range.is_some() => {
+            format!(":'<,'>{}", command_line.input)
+        }
+        VimMode::Command => format!(":{}", command_line.input),
+        VimMode::Rename => format!("{} {}", tr("-- RENAME --"), rename_line.input),
+        VimMode::Filter => format!("{} {}", tr("-- FILTER --"), filter.narrow),
+        VimMode::Normal | VimMode::Visual | VimMode::Jump => {
+            let segment = |segment: &Segment| -> Option<String> {
+                match segment {
+                    Segment::Mode => Some(mode.to_string()),
+                    Segment::Path => Some(current_dir.path().display().to_string()),
+                    Segment::Position => (!current_dir.entries.is_empty()).then(|| {
+                        format!(
+                            "{}/{}",
+                            current_dir.selected_index + 1,
+                            current_dir.entries.len()
+                        )
+                    }),
+                    Segment::Sort => Some(trf("sort: {}", &[&sorting.active().name])),
+                    Segment::Filter if filter.is_active() => {
+                        Some(trf("filter: {}", &[&filter.narrow]))
+                    }
+                    Segment::Filter => filter
+                        .pattern
+                        .as_ref()
+                        .map(|pattern| trf("filter: {}", &[&pattern.text])),
+                    Segment::Jobs => match jobs.count() {
+                        0 => None,
+                        1 => Some(tr("1 job").to_string()),
+                        count => Some(trf("{} jobs", &[&count])),
+                    },
+                    Segment::Basket => (!basket.paths.is_empty())
+                        .then(|| trf("basket: {}", &[&basket.paths.len()])),
+                    Segment::Free => volumes
+                        .containing(current_dir.path())
+                        .map(|volume| trf("{} free", &[&config.format.size(volume.available)])),
+                    Segment::Flags => {
+                        let mut flags = Vec::new();
+                        if ops::is_read_only() {
+                            flags.push(tr("[READ-ONLY]"));
+                        }
+                        if config.operations.dry_run {
+                            flags.push(tr("[DRY-RUN]"));
+                        }
+                        (!flags.is_empty()).then(|| flags.join("  "))
+                    }
+                }
+            };
+            config
+                .statusline
+                .segments
+                .iter()
+                .filter_map(segment)
+                .collect::<Vec<_>>()
+                .join("  ")
+        }
+    };
+
+    for mut text in mode_query.iter_mut() {
+        if text.sections[0].value != line {
+            text.sections[0].value.clone_from(&line);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Color tags - `t` then a color marks the selected entry
+//!
+//! `t r` toggles red on the selected file or directory (`o`range, `y`ellow,
+//! `g`reen, `b`lue, `p`urple), `t x` takes all its tags off. With entries
+//! marked here (see basket.rs) it goes for all of them: red comes off if they
+//! all have it, else they all get it. `:tag red` tags the visual range or the
+//! marks, `:tag! red` takes the tag off them and `:tag!` every tag. Each tag is a
+//! colored band around the top of the book, and `:filter tag:red` lists only
+//! the red files. Tags are kept in the metadata store (metadata.rs), so they
+//! last across sessions and follow files moved outside Felipe.
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::basket::Basket;
+use crate::command::{Command, CommandLine, RunCommand};
+use crate::filter::Filter;
+use crate::jobs::JobSummary;
+use crate::metadata;
+use crate::{
+    entry_height, CurrentDirectory, EntryPalette, FileEntity, PendingPrompt, Prompt, StatusMessage,
+};
+
+/// Height of a band, and the space it takes below the one above
+const BAND_HEIGHT: f32 = 0.08;
+const BAND_SPACING: f32 = 0.12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TagColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl TagColor {
+    const ALL: [TagColor; 6] = [
+        TagColor::Red,
+        TagColor::Orange,
+        TagColor::Yellow,
+        TagColor::Green,
+        TagColor::Blue,
+        TagColor::Purple,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TagColor::Red => "red",
+            TagColor::Orange => "orange",
+            TagColor::Yellow => "yellow",
+            TagColor::Green => "green",
+            TagColor::Blue => "blue",
+            TagColor::Purple => "purple",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            TagColor::Red => Color::srgb(0.9, 0.2, 0.2),
+            TagColor::Orange => Color::srgb(1.0, 0.55, 0.1),
+            TagColor::Yellow => Color::srgb(0.95, 0.85, 0.2),
+            TagColor::Green => Color::srgb(0.3, 0.8, 0.3),
+            TagColor::Blue => Color::srgb(0.25, 0.5, 1.0),
+            TagColor::Purple => Color::srgb(0.65, 0.35, 0.9),
+        }
+    }
+
+    /// The color a key after `t` stands for: its first letter
+    fn from_key(key: KeyCode) -> Option<Self> {
+        Some(match key {
+            KeyCode::KeyR => TagColor::Red,
+            KeyCode::KeyO => TagColor::Orange,
+            KeyCode::KeyY => TagColor::Yellow,
+            KeyCode::KeyG => TagColor::Green,
+            KeyCode::KeyB => TagColor::Blue,
+            KeyCode::KeyP => TagColor::Purple,
+            _ => return None,
+        })
+    }
+}
+
+impl std::str::FromStr for TagColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        TagColor::ALL
+            .into_iter()
+            .find(|color| color.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown tag {}, one of red, orange, yellow, green, blue, purple",
+                    s
+                )
+            })
+    }
+}
+
+// =============================================================================
+// Store
+// =============================================================================
+
+/// Tags of `path`, in the order they were added
+pub fn tags_of(path: &Path) -> Vec<TagColor> {
+    metadata::tags(path)
+}
+
+pub fn has_tag(path: &Path, color: TagColor) -> bool {
+    metadata::tags(path).contains(&color)
+}
+
+/// Put `color` on `path`, or take it off
+fn set_tag(path: &Path, color: TagColor, on: bool) -> std::io::Result<()> {
+    let mut colors = metadata::tags(path);
+    colors.retain(|c| *c != color);
+    if on {
+        colors.push(color);
+    }
+    metadata::set_tags(path, &colors)
+}
+
+fn clear(path: &Path) -> std::io::Result<()> {
+    metadata::set_tags(path, &[])
+}
+
+// =============================================================================
+// Plugin
+// =============================================================================
+
+/// Bumped on every edit, so the bands get rebuilt
+#[derive(Resource, Default)]
+struct TagEdits(u64);
+
+/// Marker for a tag band on an entry
+#[derive(Component)]
+struct TagBand;
+
+pub struct TagsPlugin;
+
+impl Plugin for TagsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TagEdits>().add_systems(
+            Update,
+            (
+                handle_tag_keys.after(crate::handle_prompt),
+                handle_tag_command,
+                update_tag_bands,
+            ),
+        );
+    }
+}
+
+/// The color key after `t` (handle_keyboard sets the prompt) toggles that tag
+/// on the entries marked here, or the selected entry
+fn handle_tag_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    filter: Res<Filter>,
+    basket: Res<Basket>,
+    mut prompt: ResMut<Prompt>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut summary: ResMut<JobSummary>,
+    mut status: ResMut<StatusMessage>,
+    mut edits: ResMut<TagEdits>,
+) {
+    if !matches!(prompt.pending, Some(PendingPrompt::Tag)) {
+        return;
+    }
+    // The `t` that just set the prompt isn't its answer
+    if keyboard.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    let Some(&key) = keyboard.get_just_pressed().next() else {
+        return;
+    };
+    prompt.pending = None;
+    let mut paths = basket.marked_here(&current_dir);
+    if paths.is_empty() {
+        paths.extend(current_dir.selected_path().map(Path::to_path_buf));
+    }
+    let (color, on) = if key == KeyCode::KeyX {
+        (None, false)
+    } else if let Some(color) = TagColor::from_key(key) {
+        // Toggled as one: off only if every one of them has it
+        let on = !paths.iter().all(|path| has_tag(path, color));
+        (Some(color), on)
+    } else {
+        status.0 = "Tagging cancelled".to_string();
+        return;
+    };
+    status.0 = apply(&paths, color, on, &mut summary);
+    edits.0 += 1;
+    refilter(&filter, &mut current_dir);
+}
+
+/// `:tag color` / `:tag! color` / `:tag!` over the command's targets
+fn handle_tag_command(
+    mut run_commands: EventReader<RunCommand>,
+    command_line: Res<CommandLine>,
+    filter: Res<Filter>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut summary: ResMut<JobSummary>,
+    mut status: ResMut<StatusMessage>,
+    mut edits: ResMut<TagEdits>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        let Command::Tag { color, on } = command else {
+            continue;
+        };
+        let paths: Vec<PathBuf> = command_line
+            .targets(&current_dir)
+            .into_iter()
+            .map(|entry| entry.path.clone())
+            .collect();
+        status.0 = apply(&paths, *color, *on, &mut summary);
+        edits.0 += 1;
+        refilter(&filter, &mut current_dir);
+    }
+}
+
+/// Put `color` on `paths` or take it off, or every tag without a color;
+/// failures of a batch are listed in the job summary. What to tell the user
+fn apply(paths: &[PathBuf], color: Option<TagColor>, on: bool, summary: &mut JobSummary) -> String {
+    let verb = if on { "Tagged" } else { "Untagged" };
+    let color_name = color
+        .map(|color| format!(" {}", color.name()))
+        .unwrap_or_default();
+    let mut failed = Vec::new();
+    for path in paths {
+        let result = match color {
+            Some(color) => set_tag(path, color, on),
+            None => clear(path),
+        };
+        if let Err(err) = result {
+            failed.push((path.clone(), err.to_string()));
+        }
+    }
+    let done = paths.len() - failed.len();
+    match (paths, failed.first()) {
+        ([], _) => "Nothing selected".to_string(),
+        ([path], None) => format!(
+            "{} {}{}",
+            verb,
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            color_name
+        ),
+        ([_], Some((_, err))) => format!("Cannot save the tags: {}", err),
+        (_, None) => {
+            summary.add("tagged", done, Vec::new());
+            format!("{} {} entries{}", verb, done, color_name)
+        }
+        (_, Some((path, err))) => {
+            let message = format!(
+                "{} {} entries{}, {} failed ({}: {})",
+                verb,
+                done,
+                color_name,
+                failed.len(),
+                path.display(),
+                err
+            );
+            summary.add("tagged", done, failed);
+            message
+        }
+    }
+}
+
+/// A tag filter may now hide or show the entries
+fn refilter(filter: &Filter, current_dir: &mut CurrentDirectory) {
+    if filter
+        .pattern
+        .as_ref()
+        .is_some_and(|pattern| pattern.is_tag())
+    {
+        current_dir.keep_selection();
+    }
+}
+
+/// Rebuild bands when tags change or the entries were respawned
+fn update_tag_bands(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<EntryPalette>,
+    edits: Res<TagEdits>,
+    current_dir: Res<CurrentDirectory>,
+    entity_query: Query<(Entity, &FileEntity)>,
+    new_entities: Query<(), Added<FileEntity>>,
+    band_query: Query<Entity, With<TagBand>>,
+) {
+    if !edits.is_changed() && new_entities.is_empty() {
+        return;
+    }
+    for band in band_query.iter() {
+        commands.entity(band).despawn();
+    }
+
+    let mesh = palette.mesh(&mut meshes, "tag band", || {
+        Cuboid::new(0.84, BAND_HEIGHT, 0.34).into()
+    });
+    for (entity, file_entity) in entity_query.iter() {
+        let Some(entry) = current_dir.entries.get(file_entity.index) else {
+            continue;
+        };
+        let colors = metadata::tags(&entry.path);
+        let height = entry_height(entry);
+        for (i, color) in colors.iter().enumerate() {
+            let material = palette.material(&mut materials, color.color().to_linear());
+            // Child of the entry, so it rises and sinks along with it; the
+            // entry's unit cuboid is stretched to its height, which the band undoes
+            let y = 0.5 - BAND_SPACING * (i + 1) as f32 / height;
+            let band = commands
+                .spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material,
+                        transform: Transform::from_xyz(0.0, y, 0.0).with_scale(Vec3::new(
+                            1.0,
+                            1.0 / height,
+                            1.0,
+                        )),
+                        ..default()
+                    },
+                    TagBand,
+                ))
+                .id();
+            commands.entity(entity).add_child(band);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Terminal here - `:terminal` or Ctrl-t
+//!
+//! Opens a terminal emulator in the directory Felipe is showing. Without a
+//! `[terminal]` section Felipe uses `$TERMINAL`, or the first of the usual
+//! terminals it finds on the PATH on Linux, Terminal.app on macOS and Windows
+//! Terminal (or cmd) on Windows.
+//!
+//! ```toml
+//! [terminal]
+//! command = "wezterm"
+//! args = ["start", "--cwd", "{dir}"]   # {dir} is the directory
+//! ```
+//!
+//! The terminal is started in the directory too, so most need no arguments.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Command as Process;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::shell::ChildEnv;
+use crate::{CurrentDirectory, Prompt, StatusMessage, VimMode};
+
+/// Terminals tried in order on Linux and the BSDs, with the arguments that
+/// make them start in `{dir}` when the working directory isn't enough
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const TERMINALS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &[]),
+    ("gnome-terminal", &["--working-directory={dir}"]),
+    ("konsole", &["--workdir", "{dir}"]),
+    ("xfce4-terminal", &["--working-directory={dir}"]),
+    ("kitty", &["--directory", "{dir}"]),
+    ("alacritty", &["--working-directory", "{dir}"]),
+    ("wezterm", &["start", "--cwd", "{dir}"]),
+    ("foot", &["--working-directory={dir}"]),
+    ("xterm", &[]),
+];
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerminalConfig {
+    /// Terminal to run instead of the platform's default
+    pub command: Option<String>,
+    /// Its arguments; `{dir}` becomes the directory
+    pub args: Vec<String>,
+}
+
+pub struct TerminalPlugin;
+
+impl Plugin for TerminalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_terminal_key, handle_terminal_command));
+    }
+}
+
+/// Ctrl-t - same as `:terminal`
+fn handle_terminal_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    mut run_commands: EventWriter<RunCommand>,
+) {
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if ctrl
+        && keyboard.just_pressed(KeyCode::KeyT)
+        && *vim_mode == VimMode::Normal
+        && prompt.pending.is_none()
+        && !focus.any_open()
+        && !fly.enabled
+    {
+        run_commands.send(RunCommand(Command::Terminal));
+    }
+}
+
+fn handle_terminal_command(
+    mut run_commands: EventReader<RunCommand>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+    child_env: Res<ChildEnv>,
+    mut status: ResMut<StatusMessage>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Terminal {
+            continue;
+        }
+        let terminal = match &config.terminal.command {
+            Some(program) => Some((program.clone(), config.terminal.args.clone())),
+            None => default_terminal(),
+        };
+        let Some((program, args)) = terminal else {
+            status.0 = "No terminal found: set [terminal] command in the config".to_string();
+            continue;
+        };
+        status.0 = match spawn(&program, &args, &current_dir.path, &child_env) {
+            Ok(()) => format!("Opened {} in {}", program, current_dir.path.display()),
+            Err(err) => format!("Cannot start {}: {}", program, err),
+        };
+    }
+}
+
+fn spawn(program: &str, args: &[String], dir: &Path, child_env: &ChildEnv) -> std::io::Result<()> {
+    let dir_text = dir.to_string_lossy();
+    child_env
+        .apply(&mut Process::new(program))
+        .args(args.iter().map(|arg| arg.replace("{dir}", &dir_text)))
+        .current_dir(dir)
+        .spawn()
+        .map(|_| ())
+}
+
+fn owned(program: &str, args: &[&str]) -> (String, Vec<String>) {
+    let args = args.iter().map(|arg| arg.to_string()).collect();
+    (program.to_string(), args)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn default_terminal() -> Option<(String, Vec<String>)> {
+    if let Some(program) = std::env::var("TERMINAL").ok().filter(|p| !p.is_empty()) {
+        return Some((program, Vec::new()));
+    }
+    TERMINALS
+        .iter()
+        .find(|(program, _)| on_path(program))
+        .map(|(program, args)| owned(program, args))
+}
+
+#[cfg(target_os = "macos")]
+fn default_terminal() -> Option<(String, Vec<String>)> {
+    Some(owned("open", &["-a", "Terminal", "{dir}"]))
+}
+
+#[cfg(target_os = "windows")]
+fn default_terminal() -> Option<(String, Vec<String>)> {
+    if on_path("wt.exe") {
+        Some(owned("wt", &["-d", "{dir}"]))
+    } else {
+        // `start` opens a new console window in the working directory
+        Some(owned("cmd", &["/C", "start", "cmd"]))
+    }
+}
+
+/// Whether `program` is in a directory on the PATH
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
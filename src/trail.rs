@@ -0,0 +1,117 @@
+//! Light trail - a fading line along the entries selected this session
+//!
+//! Like a light cycle, the trail runs along the grid in straight segments and
+//! turns at right angles. Each directory keeps its own trail, so going back up
+//! shows the path that led into the directory just left.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+
+use crate::{CurrentDirectory, Layouts, FELIPE_ORANGE};
+
+/// How long a point of the trail stays visible
+const TRAIL_SECONDS: f32 = 20.0;
+/// Points kept per directory
+const TRAIL_MAX_POINTS: usize = 64;
+/// Height above the grid, so the trail isn't hidden by the grid lines
+const TRAIL_HEIGHT: f32 = 0.05;
+
+struct TrailPoint {
+    /// Index when selected; the path checks it still names the same entry
+    index: usize,
+    path: PathBuf,
+    /// `Time::elapsed_seconds` when selected
+    at: f32,
+}
+
+/// Recently selected entries, oldest first, per directory
+#[derive(Resource, Default)]
+pub struct Trail {
+    points: HashMap<PathBuf, VecDeque<TrailPoint>>,
+}
+
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Trail>().add_systems(
+            Update,
+            (record_trail.after(crate::load_directory), draw_trail),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn record_trail(time: Res<Time>, current_dir: Res<CurrentDirectory>, mut trail: ResMut<Trail>) {
+    if !current_dir.is_changed() || current_dir.needs_reload {
+        return;
+    }
+    let Some(entry) = current_dir.entries.get(current_dir.selected_index) else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let points = trail.points.entry(current_dir.path.clone()).or_default();
+    if let Some(last) = points.back_mut() {
+        if last.path == entry.path {
+            // Still on the same entry; it may have moved with a re-sort
+            last.index = current_dir.selected_index;
+            return;
+        }
+    }
+    points.push_back(TrailPoint {
+        index: current_dir.selected_index,
+        path: entry.path.clone(),
+        at: now,
+    });
+    if points.len() > TRAIL_MAX_POINTS {
+        points.pop_front();
+    }
+    // Forget directories whose trails have faded out entirely
+    trail.points.retain(|_, points| {
+        points
+            .back()
+            .is_some_and(|point| now - point.at < TRAIL_SECONDS)
+    });
+}
+
+fn draw_trail(
+    time: Res<Time>,
+    current_dir: Res<CurrentDirectory>,
+    trail: Res<Trail>,
+    layouts: Res<Layouts>,
+    mut gizmos: Gizmos,
+) {
+    let Some(points) = trail.points.get(&current_dir.path) else {
+        return;
+    };
+    let count = current_dir.entries.len();
+    let now = time.elapsed_seconds();
+    let mut strip: Vec<(Vec3, Color)> = Vec::new();
+    for point in points {
+        let fade = 1.0 - (now - point.at) / TRAIL_SECONDS;
+        // Entries that faded, vanished or moved since are left out
+        let still_there = current_dir
+            .entries
+            .get(point.index)
+            .is_some_and(|entry| entry.path == point.path);
+        if fade <= 0.0 || !still_there {
+            continue;
+        }
+        let position = layouts.position(point.index, count) + Vec3::Y * TRAIL_HEIGHT;
+        let color = FELIPE_ORANGE.with_alpha(fade);
+        if let Some(&(previous, _)) = strip.last() {
+            // On the grid, turn at a right angle: along the row first, then across rows
+            if layouts.is_grid() && previous.x != position.x && previous.z != position.z {
+                strip.push((Vec3::new(position.x, position.y, previous.z), color));
+            }
+        }
+        strip.push((position, color));
+    }
+    if strip.len() >= 2 {
+        gizmos.linestrip_gradient(strip);
+    }
+}
@@ -0,0 +1,147 @@
+//! Directory transitions - old entries sink into the grid, new ones rise out of it
+//!
+//! The camera swoops forward when entering a directory and back when leaving,
+//! so a change of directory reads as movement through the tree.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::flycam::FlyCamera;
+use crate::{CurrentDirectory, MainCamera};
+
+/// Length of the sink / rise animation
+const TRANSITION_SECONDS: f32 = 0.35;
+/// How far back (entering) or forward (leaving) the camera starts its swoop
+const SWOOP_DISTANCE: f32 = 12.0;
+
+/// Which directory the scene shows, to tell a directory change from a refresh
+#[derive(Resource, Default)]
+pub struct Transition {
+    shown_path: Option<PathBuf>,
+    /// The pending reload changes directory; entries sink and rise instead of popping
+    pub animate: bool,
+}
+
+/// Entry (mesh or label) on its way into or out of the grid
+#[derive(Component)]
+pub struct EntryTransition {
+    elapsed: f32,
+    /// Height of the entity's origin when at rest
+    rest_y: f32,
+    /// Vertical scale at rest (books are a unit cuboid stretched to their height)
+    rest_scale_y: f32,
+    sinking: bool,
+}
+
+impl EntryTransition {
+    pub fn sinking(rest: &Transform) -> Self {
+        Self {
+            elapsed: 0.0,
+            rest_y: rest.translation.y,
+            rest_scale_y: rest.scale.y,
+            sinking: true,
+        }
+    }
+
+    pub fn rising(rest: &Transform) -> Self {
+        Self {
+            elapsed: 0.0,
+            rest_y: rest.translation.y,
+            rest_scale_y: rest.scale.y,
+            sinking: false,
+        }
+    }
+
+    fn progress(&self) -> f32 {
+        (self.elapsed / TRANSITION_SECONDS).min(1.0)
+    }
+
+    /// Move (and for meshes, squash) toward the grid; labels keep their scale
+    pub fn apply(&self, transform: &mut Transform, is_mesh: bool) {
+        let t = self.progress();
+        let eased = t * t * (3.0 - 2.0 * t);
+        let shown = if self.sinking { 1.0 - eased } else { eased };
+        transform.translation.y = self.rest_y * shown;
+        if is_mesh {
+            transform.scale.y = (self.rest_scale_y * shown).max(0.001);
+        }
+    }
+}
+
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Transition::default()).add_systems(
+            Update,
+            (
+                begin_transition.before(crate::despawn_file_entities),
+                animate_entry_transitions,
+            ),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Decide whether a reload is a directory change, and start the camera swoop
+fn begin_transition(
+    current_dir: Res<CurrentDirectory>,
+    fly: Res<FlyCamera>,
+    config: Res<Config>,
+    mut transition: ResMut<Transition>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    if !current_dir.needs_reload {
+        return;
+    }
+    let new_path = &current_dir.path;
+    let Some(old_path) = transition.shown_path.replace(new_path.clone()) else {
+        // First listing rises out of an empty grid
+        transition.animate = !config.render.accessible;
+        return;
+    };
+    transition.animate = old_path != *new_path && !config.render.accessible;
+    // The fly camera places itself after a directory change
+    if !transition.animate || fly.enabled {
+        return;
+    }
+
+    let entering = new_path.starts_with(&old_path);
+    for mut transform in camera_query.iter_mut() {
+        let forward = transform.forward();
+        // update_camera eases back to the normal position from here
+        transform.translation += if entering {
+            -forward * SWOOP_DISTANCE
+        } else {
+            forward * SWOOP_DISTANCE * 0.5
+        };
+    }
+}
+
+fn animate_entry_transitions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut EntryTransition,
+        &mut Transform,
+        Has<Handle<Mesh>>,
+    )>,
+) {
+    for (entity, mut transition, mut transform, is_mesh) in query.iter_mut() {
+        transition.elapsed += time.delta_seconds();
+        transition.apply(&mut transform, is_mesh);
+        if transition.progress() < 1.0 {
+            continue;
+        }
+        if transition.sinking {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            commands.entity(entity).remove::<EntryTransition>();
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Trash - `d` sends the selection to Felipe's trash
+//!
+//! `d` takes the visual range, else the entries marked here (see basket.rs),
+//! else the entry under the cursor, and asks once for all of them. What's
+//! confirmed runs through the job queue like a paste: entries that can't be
+//! trashed are listed in the summary, the rest go, and `u` brings them back.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::basket::Basket;
+use crate::flycam::FlyCamera;
+use crate::focus::Focus;
+use crate::ops;
+use crate::whichkey::PendingKey;
+use crate::{CurrentDirectory, PendingPrompt, Prompt, StatusMessage, VimMode};
+
+pub struct TrashPlugin;
+
+impl Plugin for TrashPlugin {
+    fn build(&self, app: &mut App) {
+        // Before `yd` is taken as copying the directory
+        app.add_systems(Update, handle_trash_key.before(crate::handle_keyboard));
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// `d` - ask to trash the range, the marks or the selected entry
+fn handle_trash_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut vim_mode: ResMut<VimMode>,
+    mut prompt: ResMut<Prompt>,
+    focus: Res<Focus>,
+    fly: Res<FlyCamera>,
+    pending_key: Res<PendingKey>,
+    current_dir: Res<CurrentDirectory>,
+    basket: Res<Basket>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyD)
+        || keyboard.any_pressed([
+            KeyCode::ControlLeft,
+            KeyCode::ControlRight,
+            KeyCode::AltLeft,
+            KeyCode::AltRight,
+        ])
+        || !matches!(*vim_mode, VimMode::Normal | VimMode::Visual)
+        || prompt.pending.is_some()
+        || focus.any_open()
+        || fly.enabled
+        || pending_key.key.is_some()
+    {
+        return;
+    }
+    let marked = basket.marked_here(&current_dir);
+    let paths: Vec<PathBuf> = if *vim_mode == VimMode::Visual {
+        current_dir
+            .entries
+            .get(current_dir.visual_range())
+            .unwrap_or_default()
+            .iter()
+            .filter(|entry| entry.name != "..")
+            .map(|entry| entry.path.clone())
+            .collect()
+    } else if !marked.is_empty() {
+        marked
+    } else {
+        current_dir
+            .entries
+            .get(current_dir.selected_index)
+            .filter(|entry| entry.name != "..")
+            .map(|entry| entry.path.clone())
+            .into_iter()
+            .collect()
+    };
+    *vim_mode = VimMode::Normal;
+    if paths.is_empty() {
+        return;
+    }
+    if ops::is_read_only() {
+        status.0 = "Read-only mode - nothing is trashed".to_string();
+        return;
+    }
+    match ops::plan_trash(&paths) {
+        Ok(plan) => prompt.pending = Some(PendingPrompt::ConfirmTrash(plan)),
+        Err(err) => status.0 = format!("Cannot trash: {}", err),
+    }
+}
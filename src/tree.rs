@@ -0,0 +1,393 @@
+//! Tree sidebar - where the current directory sits in the hierarchy
+//!
+//! `:tree` shows or hides a sidebar on the left: the directories above the
+//! current one, one level deeper each, then the current one among its
+//! siblings with its own subdirectories under it. The entry under the grid's
+//! cursor is marked there too, and the tree follows every move in the grid.
+//!
+//! Ctrl-e moves the keyboard into the tree and back (opening it if needed).
+//! There j/k and g/G move, l or Enter goes to the directory under the tree's
+//! cursor, h goes up one, and Esc gives the keyboard back to the grid with
+//! the tree still showing.
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::breadcrumbs::{ancestors, crumb_label};
+use crate::command::{Command, RunCommand};
+use crate::config::{Config, ListingConfig};
+use crate::flycam::FlyCamera;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::{gitignore, remote};
+use crate::{CurrentDirectory, Prompt, UiElement, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM};
+
+/// Rows shown at once, around the tree's cursor
+const TREE_ROWS: usize = 30;
+
+/// Width of the sidebar in pixels
+const TREE_WIDTH: f32 = 260.0;
+
+/// A directory of the tree
+struct TreeRow {
+    path: PathBuf,
+    label: String,
+    depth: usize,
+}
+
+/// The tree of the current directory, and whether it shows
+#[derive(Resource, Default)]
+struct TreeSidebar {
+    visible: bool,
+    rows: Vec<TreeRow>,
+    /// Row of the current directory
+    current: usize,
+    cursor: usize,
+    /// Directory the rows were built for, with its siblings
+    listed: Option<(PathBuf, Vec<PathBuf>)>,
+}
+
+/// Marker for the sidebar
+#[derive(Component)]
+struct TreePanel;
+
+/// Marker for the sidebar text
+#[derive(Component)]
+struct TreeText;
+
+pub struct TreePlugin;
+
+impl Plugin for TreePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TreeSidebar>().add_systems(
+            Update,
+            (
+                handle_tree_command,
+                handle_focus_key,
+                handle_tree_keys,
+                follow_directory,
+                update_tree_panel,
+            )
+                .chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// `:tree` - show or hide the sidebar
+fn handle_tree_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut tree: ResMut<TreeSidebar>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<TreePanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Tree {
+            continue;
+        }
+        if tree.visible {
+            hide(&mut commands, &mut tree, &mut focus, &panel_query);
+        } else {
+            show(&mut commands, &mut tree);
+        }
+    }
+}
+
+/// Ctrl-e - keyboard to the tree, or back to the grid
+fn handle_focus_key(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    fly: Res<FlyCamera>,
+    mut tree: ResMut<TreeSidebar>,
+    mut focus: ResMut<Focus>,
+) {
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !ctrl
+        || !keyboard.just_pressed(KeyCode::KeyE)
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || fly.enabled
+    {
+        return;
+    }
+    if focus.has_focus(Panel::Tree) {
+        focus.close(Panel::Tree);
+        return;
+    }
+    // Other panels keep the keyboard until they close
+    if focus.any_open() {
+        return;
+    }
+    if !tree.visible {
+        show(&mut commands, &mut tree);
+    }
+    tree.cursor = tree.current;
+    focus.open(Panel::Tree);
+}
+
+fn handle_tree_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut tree: ResMut<TreeSidebar>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+) {
+    // Esc only hands the keyboard back; `:tree` hides the sidebar
+    if dismissed.read().any(|Dismiss(panel)| *panel == Panel::Tree) {
+        focus.close(Panel::Tree);
+        return;
+    }
+    if !focus.has_focus(Panel::Tree) || *vim_mode != VimMode::Normal {
+        return;
+    }
+
+    let last = tree.rows.len().saturating_sub(1);
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        tree.cursor = (tree.cursor + 1).min(last);
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        tree.cursor = tree.cursor.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::KeyG) {
+        tree.cursor = if shift { last } else { 0 };
+    }
+    if keyboard.just_pressed(KeyCode::KeyL)
+        || keyboard.just_pressed(KeyCode::ArrowRight)
+        || keyboard.just_pressed(KeyCode::Enter)
+    {
+        if let Some(row) = tree.rows.get(tree.cursor) {
+            if row.path != current_dir.path() {
+                // Going up, land on the directory we came through
+                current_dir.pending_select = ancestors(current_dir.path())
+                    .into_iter()
+                    .find(|dir| remote::parent(dir).as_ref() == Some(&row.path));
+                current_dir.set_path(row.path.clone());
+            }
+        }
+    }
+    if keyboard.just_pressed(KeyCode::KeyH) || keyboard.just_pressed(KeyCode::ArrowLeft) {
+        current_dir.pending_select = Some(current_dir.path().to_path_buf());
+        current_dir.go_to_parent();
+    }
+}
+
+/// Rebuild the rows as the grid moves
+fn follow_directory(
+    mut tree: ResMut<TreeSidebar>,
+    current_dir: Res<CurrentDirectory>,
+    config: Res<Config>,
+) {
+    if !tree.visible || !(current_dir.is_changed() || tree.is_changed()) {
+        return;
+    }
+    let path = current_dir.path();
+    let moved = tree
+        .listed
+        .as_ref()
+        .is_none_or(|(listed, _)| listed != path);
+    let listing_changed = config.is_changed();
+    if moved || listing_changed {
+        tree.listed = Some((path.to_path_buf(), siblings(path, &config.listing)));
+    }
+    let Some((_, siblings)) = &tree.listed else {
+        return;
+    };
+
+    let above = ancestors(path);
+    let depth = above.len() - 1;
+    let mut rows: Vec<TreeRow> = above[..depth]
+        .iter()
+        .enumerate()
+        .map(|(i, path)| TreeRow {
+            path: path.clone(),
+            label: crumb_label(path, i.checked_sub(1).map(|p| &*above[p])),
+            depth: i,
+        })
+        .collect();
+    let parent = depth.checked_sub(1).map(|p| above[p].as_path());
+    let mut current = rows.len();
+    for sibling in siblings {
+        if sibling == path {
+            current = rows.len();
+        }
+        rows.push(TreeRow {
+            path: sibling.clone(),
+            label: crumb_label(sibling, parent),
+            depth,
+        });
+        if sibling != path {
+            continue;
+        }
+        rows.extend(
+            current_dir
+                .entries
+                .iter()
+                .filter(|entry| entry.is_dir && entry.name != "..")
+                .map(|entry| TreeRow {
+                    path: entry.path.clone(),
+                    label: entry.name.clone(),
+                    depth: depth + 1,
+                }),
+        );
+    }
+
+    let tree = tree.bypass_change_detection();
+    tree.rows = rows;
+    // The tree's cursor starts on the current directory again after a move
+    if moved {
+        tree.cursor = current;
+    }
+    tree.current = current;
+    tree.cursor = tree.cursor.min(tree.rows.len().saturating_sub(1));
+}
+
+fn update_tree_panel(
+    tree: Res<TreeSidebar>,
+    current_dir: Res<CurrentDirectory>,
+    focus: Res<Focus>,
+    mut text_query: Query<&mut Text, With<TreeText>>,
+    spawned: Query<(), Added<TreeText>>,
+) {
+    if !tree.visible
+        || !(tree.is_changed()
+            || current_dir.is_changed()
+            || focus.is_changed()
+            || !spawned.is_empty())
+    {
+        return;
+    }
+    let focused = focus.has_focus(Panel::Tree);
+    let header = if focused {
+        "TREE  j/k:move  l:go  h:up  Esc:grid\n"
+    } else {
+        "TREE  Ctrl-e:browse  :tree hides\n"
+    };
+    let mut sections = vec![TextSection::new(header, panel_style(FELIPE_ORANGE))];
+
+    let start = tree
+        .cursor
+        .saturating_sub(TREE_ROWS / 2)
+        .min(tree.rows.len().saturating_sub(TREE_ROWS));
+    let selected = current_dir.selected_path();
+    for (i, row) in tree.rows.iter().enumerate().skip(start).take(TREE_ROWS) {
+        let mark = if focused && i == tree.cursor {
+            "> "
+        } else if Some(row.path.as_path()) == selected {
+            "* "
+        } else {
+            "  "
+        };
+        let color = if i == tree.current || (focused && i == tree.cursor) {
+            FELIPE_ORANGE
+        } else {
+            FELIPE_ORANGE_DIM
+        };
+        sections.push(TextSection::new(
+            format!("{}{}{}\n", mark, "  ".repeat(row.depth), row.label),
+            panel_style(color),
+        ));
+    }
+    let hidden = tree.rows.len().saturating_sub(start + TREE_ROWS);
+    if hidden > 0 {
+        sections.push(TextSection::new(
+            format!("  ... {} more\n", hidden),
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Directories next to `path` (itself included), by name; only `path` for
+/// remote locations and views, which can't be listed from here
+fn siblings(path: &Path, listing: &ListingConfig) -> Vec<PathBuf> {
+    let parent = match remote::parent(path) {
+        Some(parent) if remote::location(path).is_none() && parent.is_dir() => parent,
+        _ => return vec![path.to_path_buf()],
+    };
+    let mut dirs: Vec<PathBuf> = gitignore::walker(&parent, listing)
+        .max_depth(Some(1))
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() == 1)
+        .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_dir()))
+        .map(|entry| entry.into_path())
+        .collect();
+    // The current directory shows even when the listing would hide it
+    if !dirs.iter().any(|dir| dir == path) {
+        dirs.push(path.to_path_buf());
+    }
+    dirs.sort_by_key(|dir| {
+        dir.file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+    });
+    dirs
+}
+
+fn show(commands: &mut Commands, tree: &mut TreeSidebar) {
+    tree.visible = true;
+    tree.listed = None;
+    spawn_panel(commands);
+}
+
+fn hide(
+    commands: &mut Commands,
+    tree: &mut TreeSidebar,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<TreePanel>>,
+) {
+    tree.visible = false;
+    tree.rows.clear();
+    focus.close(Panel::Tree);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(90.0),
+                    left: Val::Px(10.0),
+                    width: Val::Px(TREE_WIDTH),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE_DIM),
+                ..default()
+            },
+            TreePanel,
+            Focusable(Panel::Tree),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), TreeText));
+        });
+}
@@ -0,0 +1,549 @@
+//! Terminal mode - `felipe --tui` (`--features tui`)
+//!
+//! The same listing as the 3D view, drawn as text with ratatui: for SSH
+//! sessions and machines without a GPU. Loading, sorting, filtering and file
+//! operations are the scene's own (`CurrentDirectory::reload`, sort.rs,
+//! filter.rs, ops.rs); only the drawing and the key reading are different.
+//!
+//! The keys are the scene's: `j`/`k`/`g`/`G` move, `h`/`l`/Enter leave and
+//! enter, `.` shows dotfiles, `s` cycles the sort orders, `f` narrows, `v`
+//! selects a range, `y`/`m`/`p`/`P` yank, cut and paste, `u` undoes and `q`
+//! quits. The command line takes `:q`, `:set hidden|gitignore`, `:sort`,
+//! `:filter` and `:flatten`; the other commands need the 3D view.
+//!
+//! Pastes run before the next key is read rather than in the job queue.
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::path::PathBuf;
+
+use crate::command::{self, Command, CommandLine};
+use crate::config::Config;
+use crate::filter::Filter;
+use crate::oplog::OperationLog;
+use crate::ops::{self, Overwrite, RenameStrategy, TransferKind, TransferPlan};
+use crate::overwrite::{renamed_note, OverwriteRequest};
+use crate::sort::Sorting;
+use crate::{
+    cwdfile, flatten, open_with_default_app, plan_paste, yank_entries, CurrentDirectory,
+    PendingPrompt, Prompt, Register, StatusMessage, VimMode,
+};
+
+/// Felipe Orange, as the terminal's true color
+const ORANGE: Color = Color::Rgb(255, 102, 0);
+const ORANGE_DIM: Color = Color::Rgb(153, 61, 0);
+const RED: Color = Color::Rgb(242, 51, 51);
+
+/// Everything the terminal view keeps between keys
+struct Tui {
+    current_dir: CurrentDirectory,
+    config: Config,
+    sorting: Sorting,
+    filter: Filter,
+    register: Register,
+    oplog: OperationLog,
+    status: StatusMessage,
+    vim_mode: VimMode,
+    command_line: CommandLine,
+    /// A paste waiting for r/s/Esc on case collisions
+    prompt: Prompt,
+    list: ListState,
+    quit: bool,
+}
+
+/// Run the terminal view until quit; the exit code
+pub fn run(current_dir: CurrentDirectory, config: Config, cwd_file: Option<PathBuf>) -> i32 {
+    ops::set_rename_pattern(&config.operations.rename_pattern);
+    let mut tui = Tui {
+        sorting: Sorting::from_config(&config.sort),
+        current_dir,
+        config,
+        filter: Filter::default(),
+        register: Register::default(),
+        oplog: OperationLog::default(),
+        status: StatusMessage::default(),
+        vim_mode: VimMode::Normal,
+        command_line: CommandLine::default(),
+        prompt: Prompt::default(),
+        list: ListState::default(),
+        quit: false,
+    };
+    let mut terminal = ratatui::init();
+    let result = tui.run(&mut terminal);
+    ratatui::restore();
+    if let Some(file) = &cwd_file {
+        cwdfile::write(file, tui.current_dir.path());
+    }
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("felipe: terminal: {}", err);
+            1
+        }
+    }
+}
+
+impl Tui {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        while !self.quit {
+            if self.current_dir.needs_reload {
+                let message = self.current_dir.reload(
+                    &self.config.listing,
+                    self.sorting.active(),
+                    &self.filter,
+                );
+                if let Some(message) = message {
+                    self.status.0 = message;
+                }
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key.code, key.modifiers);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // =========================================================================
+    // Drawing
+    // =========================================================================
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, listing, status, bottom] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let mut title = vec![Span::styled(
+            self.current_dir.path().display().to_string(),
+            Style::new().fg(ORANGE).add_modifier(Modifier::BOLD),
+        )];
+        if let Some(err) = &self.current_dir.unreadable {
+            title.push(Span::styled(
+                format!("  [unreadable: {}]", err),
+                Style::new().fg(RED),
+            ));
+        }
+        title.push(Span::styled(
+            format!("  [{}]", self.sorting.active().name),
+            Style::new().fg(ORANGE_DIM),
+        ));
+        if let Some(pattern) = &self.filter.pattern {
+            title.push(Span::styled(
+                format!("  filter: {}", pattern.text),
+                Style::new().fg(ORANGE_DIM),
+            ));
+        }
+        frame.render_widget(Paragraph::new(Line::from(title)), top);
+
+        let format = &self.config.format;
+        let visual = (self.vim_mode == VimMode::Visual).then(|| self.current_dir.visual_range());
+        let items: Vec<ListItem> = self
+            .current_dir
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let name = if entry.is_dir {
+                    format!("{}/", entry.name)
+                } else {
+                    entry.name.clone()
+                };
+                let size = if entry.is_dir {
+                    String::new()
+                } else {
+                    format.size(entry.size)
+                };
+                let date = entry
+                    .modified
+                    .map(|time| format.date(time))
+                    .unwrap_or_default();
+                let color = if entry.locked {
+                    RED
+                } else if entry.is_dir {
+                    ORANGE
+                } else {
+                    ORANGE_DIM
+                };
+                let mut style = Style::new().fg(color);
+                if visual.as_ref().is_some_and(|range| range.contains(&index)) {
+                    style = style.add_modifier(Modifier::UNDERLINED);
+                }
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<40} ", name), style),
+                    Span::styled(
+                        format!("{:>12}  {}", size, date),
+                        Style::new().fg(ORANGE_DIM),
+                    ),
+                ]))
+            })
+            .collect();
+        self.list.select(Some(self.current_dir.selected_index));
+        frame.render_stateful_widget(
+            List::new(items).highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            listing,
+            &mut self.list,
+        );
+
+        let message = match &self.prompt.pending {
+            Some(pending) => pending.message(),
+            None => self.status.0.clone(),
+        };
+        frame.render_widget(
+            Paragraph::new(message).style(Style::new().fg(ORANGE)),
+            status,
+        );
+
+        let mut line = match self.vim_mode {
+            VimMode::Command => format!(":{}", self.command_line.input),
+            VimMode::Filter => format!("f/{}", self.filter.narrow),
+            VimMode::Visual => "-- VISUAL --".to_string(),
+            _ => "-- NORMAL --".to_string(),
+        };
+        if ops::is_read_only() {
+            line.push_str("  [READ-ONLY]");
+        }
+        frame.render_widget(
+            Paragraph::new(line).style(Style::new().fg(ORANGE_DIM)),
+            bottom,
+        );
+    }
+
+    // =========================================================================
+    // Keys
+    // =========================================================================
+
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        if self.prompt.pending.is_some() {
+            self.handle_prompt(code);
+            return;
+        }
+        match self.vim_mode {
+            VimMode::Command => self.handle_command_line(code),
+            VimMode::Filter => self.handle_filter_input(code),
+            VimMode::Normal | VimMode::Visual => self.handle_motion(code, modifiers),
+            VimMode::Rename | VimMode::Jump => self.vim_mode = VimMode::Normal,
+        }
+    }
+
+    fn handle_motion(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        let current_dir = &mut self.current_dir;
+        let last = current_dir.entries.len().saturating_sub(1);
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                current_dir.selected_index = (current_dir.selected_index + 1).min(last);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                current_dir.selected_index = current_dir.selected_index.saturating_sub(1);
+            }
+            KeyCode::Char('g') | KeyCode::Home => current_dir.selected_index = 0,
+            KeyCode::Char('G') | KeyCode::End => current_dir.selected_index = last,
+            KeyCode::Char(':') => {
+                self.command_line.range =
+                    (self.vim_mode == VimMode::Visual).then(|| current_dir.visual_range());
+                self.command_line.input.clear();
+                self.vim_mode = VimMode::Command;
+            }
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            _ if self.vim_mode == VimMode::Visual => self.handle_visual_key(code),
+            _ => self.handle_normal_key(code),
+        }
+    }
+
+    fn handle_normal_key(&mut self, code: KeyCode) {
+        let current_dir = &mut self.current_dir;
+        match code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => {
+                match current_dir.enter_selected() {
+                    Ok(Some(path)) => open_with_default_app(&path),
+                    Ok(None) => {}
+                    Err(message) => self.status.0 = message,
+                }
+            }
+            KeyCode::Char('h') | KeyCode::Left | KeyCode::Backspace => {
+                current_dir.go_to_parent();
+            }
+            KeyCode::Char('.') => self.toggle_listing("hidden"),
+            KeyCode::Char('s') => {
+                self.status.0 = format!("Sort: {}", self.sorting.cycle().name);
+                current_dir.keep_selection();
+            }
+            KeyCode::Char('f') => {
+                if self.filter.is_active() {
+                    self.filter.narrow.clear();
+                    current_dir.keep_selection();
+                }
+                self.vim_mode = VimMode::Filter;
+            }
+            KeyCode::Esc if self.filter.is_active() => {
+                self.filter.narrow.clear();
+                current_dir.keep_selection();
+            }
+            KeyCode::Char('v') => {
+                current_dir.visual_anchor = current_dir.selected_index;
+                self.vim_mode = VimMode::Visual;
+            }
+            KeyCode::Char('y') => self.yank(TransferKind::Copy),
+            KeyCode::Char('m') => self.yank(TransferKind::Move),
+            KeyCode::Char('p') => self.paste(false),
+            KeyCode::Char('P') => self.paste(true),
+            KeyCode::Char('u') => {
+                if let Some(message) = self.oplog.undo_last() {
+                    self.status.0 = message;
+                    current_dir.keep_selection();
+                } else {
+                    self.status.0 = "Nothing to undo".to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_visual_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('y') => self.yank(TransferKind::Copy),
+            KeyCode::Char('m') => self.yank(TransferKind::Move),
+            KeyCode::Esc | KeyCode::Char('v') => {}
+            _ => return,
+        }
+        self.vim_mode = VimMode::Normal;
+    }
+
+    fn handle_filter_input(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => self.filter.narrow.push(c),
+            // Backspace on empty text leaves filter mode, like the command line
+            KeyCode::Backspace => {
+                if self.filter.narrow.pop().is_none() {
+                    self.vim_mode = VimMode::Normal;
+                    return;
+                }
+            }
+            KeyCode::Esc => {
+                self.filter.narrow.clear();
+                self.vim_mode = VimMode::Normal;
+            }
+            KeyCode::Enter => {
+                self.vim_mode = VimMode::Normal;
+                return;
+            }
+            _ => return,
+        }
+        self.current_dir.keep_selection();
+    }
+
+    fn handle_command_line(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => self.command_line.input.push(c),
+            KeyCode::Backspace => {
+                // Backspace on an empty line leaves command mode, like vim
+                let was_empty = self.command_line.input.pop().is_none();
+                if was_empty {
+                    self.vim_mode = VimMode::Normal;
+                }
+            }
+            KeyCode::Esc => {
+                self.command_line.input.clear();
+                self.vim_mode = VimMode::Normal;
+            }
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.command_line.input);
+                self.vim_mode = VimMode::Normal;
+                match command::parse_command(&input) {
+                    Ok(command) => self.run_command(command, &input),
+                    Err(message) => self.status.0 = message,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_prompt(&mut self, code: KeyCode) {
+        let mut plan = match self.prompt.pending.take() {
+            Some(PendingPrompt::CaseCollision(plan)) => plan,
+            Some(PendingPrompt::Overwrite(request)) => {
+                self.answer_overwrite(request, code);
+                return;
+            }
+            _ => return,
+        };
+        let strategy = match code {
+            KeyCode::Char('r') => RenameStrategy::Suffix,
+            KeyCode::Char('s') => RenameStrategy::Skip,
+            KeyCode::Esc => {
+                self.status.0 = "Paste cancelled".to_string();
+                return;
+            }
+            _ => {
+                self.prompt.pending = Some(PendingPrompt::CaseCollision(plan));
+                return;
+            }
+        };
+        plan.resolve_case_collisions(strategy);
+        self.offer(plan);
+    }
+
+    /// o / s / r / n settle a clash, capitals all the rest (see overwrite.rs)
+    fn answer_overwrite(&mut self, request: OverwriteRequest, code: KeyCode) {
+        let KeyCode::Char(c) = code else {
+            if code == KeyCode::Esc {
+                self.status.0 = "Paste cancelled".to_string();
+            } else {
+                self.prompt.pending = Some(PendingPrompt::Overwrite(request));
+            }
+            return;
+        };
+        let choice = match c.to_ascii_lowercase() {
+            'o' => Overwrite::Replace,
+            's' => Overwrite::Skip,
+            'r' => Overwrite::Rename,
+            'n' => Overwrite::IfNewer,
+            _ => {
+                self.prompt.pending = Some(PendingPrompt::Overwrite(request));
+                return;
+            }
+        };
+        match request.answer(choice, c.is_ascii_uppercase()) {
+            Ok(plan) if plan.steps.is_empty() => {
+                self.status.0 = format!("Skipped all {}, nothing to paste", plan.skipped.len());
+            }
+            Ok(plan) => self.transfer(plan),
+            Err(request) => self.prompt.pending = Some(PendingPrompt::Overwrite(request)),
+        }
+    }
+
+    // =========================================================================
+    // Actions
+    // =========================================================================
+
+    fn run_command(&mut self, command: Command, input: &str) {
+        match command {
+            Command::Quit => self.quit = true,
+            Command::Set { option, value } if matches!(option.as_str(), "hidden" | "gitignore") => {
+                if let Some(current) = self.config.option_mut(&option) {
+                    if value != Some(*current) {
+                        self.toggle_listing(&option);
+                    }
+                }
+            }
+            Command::Set { option, value } if option == "readonly" => {
+                let read_only = value.unwrap_or(!ops::is_read_only());
+                self.status.0 = match ops::set_read_only(read_only) {
+                    Ok(()) if read_only => "readonly".to_string(),
+                    Ok(()) => "noreadonly".to_string(),
+                    Err(message) => message,
+                };
+            }
+            Command::Sort(None) => self.status.0 = format!("Sort: {}", self.sorting.active().name),
+            Command::Sort(Some(key)) => {
+                self.status.0 = format!("Sort: {}", self.sorting.sort_by(key).name);
+                self.current_dir.keep_selection();
+            }
+            Command::Filter(text) => match self.filter.set_pattern(text.as_deref()) {
+                Ok(()) => {
+                    self.status.0 = if text.is_none() {
+                        "Filter cleared".to_string()
+                    } else {
+                        String::new()
+                    };
+                    self.current_dir.keep_selection();
+                }
+                Err(message) => self.status.0 = message,
+            },
+            Command::Flatten => {
+                if !flatten::toggle_flat_view(&mut self.current_dir) {
+                    self.status.0 = "Flat view off".to_string();
+                }
+            }
+            _ => self.status.0 = format!("Not in the terminal view: :{}", input.trim()),
+        }
+    }
+
+    /// `.` and `:set hidden!` / `:set gitignore!`
+    fn toggle_listing(&mut self, option: &str) {
+        crate::toggle_listing(&mut self.config, option, &mut self.status);
+        self.current_dir.keep_selection();
+    }
+
+    fn yank(&mut self, kind: TransferKind) {
+        let range = match self.vim_mode {
+            VimMode::Visual => self.current_dir.visual_range(),
+            _ => self.current_dir.selected_index..=self.current_dir.selected_index,
+        };
+        yank_entries(
+            &mut self.register,
+            &self.current_dir,
+            range,
+            kind,
+            &mut self.status,
+        );
+    }
+
+    fn paste(&mut self, as_links: bool) {
+        let Some(plan) = plan_paste(
+            &self.register,
+            as_links,
+            &self.current_dir,
+            &mut self.status,
+        ) else {
+            return;
+        };
+        self.offer(plan);
+    }
+
+    /// Run a paste once its case collisions and overwrites have been asked about
+    fn offer(&mut self, mut plan: TransferPlan) {
+        if self.config.operations.autorename {
+            plan.resolve_case_collisions(RenameStrategy::Suffix);
+            plan.resolve_existing(RenameStrategy::Suffix);
+            self.transfer(plan);
+            return;
+        }
+        if plan.conflicts().next().is_some() {
+            self.prompt.pending = Some(PendingPrompt::CaseCollision(plan));
+            return;
+        }
+        match OverwriteRequest::new(plan) {
+            Ok(request) => self.prompt.pending = Some(PendingPrompt::Overwrite(request)),
+            Err(plan) => self.transfer(plan),
+        }
+    }
+
+    /// Run a paste now and record it for `u`
+    fn transfer(&mut self, plan: TransferPlan) {
+        let report = ops::execute_plan(&plan);
+        let verb = plan.kind.verb();
+        let renamed = renamed_note(&plan, &report);
+        self.status.0 = match report.failed.first() {
+            None => format!("{} {}{}", report.done, verb, renamed),
+            Some((path, err)) => format!(
+                "{} {}{}, {} failed ({}: {})",
+                report.done,
+                verb,
+                renamed,
+                report.failed.len(),
+                path.display(),
+                err
+            ),
+        };
+        let label = format!(
+            "{} {} into {}",
+            plan.steps.len(),
+            verb,
+            self.current_dir.path().display()
+        );
+        self.oplog.record(label, report.steps);
+        self.register.forget_missing();
+        self.current_dir.keep_selection();
+    }
+}
@@ -0,0 +1,281 @@
+//! `:tutor` - a vimtutor-style walkthrough in a throwaway sandbox directory
+//!
+//! Each step shows an instruction and advances once the app state proves the
+//! user did it, so nothing here needs to hook into the input systems.
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::command::{Command, RunCommand};
+use crate::ops::TransferKind;
+use crate::{CurrentDirectory, Register, StatusMessage, UiElement, VimMode, FELIPE_ORANGE};
+
+// =============================================================================
+// Steps
+// =============================================================================
+
+/// What a step can look at to decide it's done
+struct TutorContext<'a> {
+    sandbox: &'a Path,
+    current_dir: &'a CurrentDirectory,
+    vim_mode: VimMode,
+    register: &'a Register,
+}
+
+impl TutorContext<'_> {
+    fn selected_name(&self) -> Option<&str> {
+        self.current_dir
+            .entries
+            .get(self.current_dir.selected_index)
+            .map(|e| e.name.as_str())
+    }
+
+    fn register_holds(&self, kind: TransferKind, name: &str) -> bool {
+        self.register.kind == Some(kind) && self.register.paths.contains(&self.sandbox.join(name))
+    }
+}
+
+struct TutorStep {
+    instruction: &'static str,
+    done: fn(&TutorContext) -> bool,
+}
+
+const STEPS: &[TutorStep] = &[
+    TutorStep {
+        instruction: "Move the selection down with j (or Down).",
+        done: |ctx| ctx.current_dir.selected_index > 0,
+    },
+    TutorStep {
+        instruction: "Move back up with k (or Up).",
+        done: |ctx| ctx.current_dir.selected_index == 0,
+    },
+    TutorStep {
+        instruction: "Jump to the last entry with G (Shift+g).",
+        done: |ctx| ctx.current_dir.selected_index + 1 == ctx.current_dir.entries.len(),
+    },
+    TutorStep {
+        instruction: "Jump back to the first entry with g.",
+        done: |ctx| ctx.current_dir.selected_index == 0,
+    },
+    TutorStep {
+        instruction: "Select the 'archive' folder and enter it with l (or Enter).",
+        done: |ctx| ctx.current_dir.path == ctx.sandbox.join("archive"),
+    },
+    TutorStep {
+        instruction: "Go back to the parent folder with h (or Left).",
+        done: |ctx| ctx.current_dir.path == ctx.sandbox,
+    },
+    TutorStep {
+        instruction: "Modes: enter VISUAL mode with v.",
+        done: |ctx| ctx.vim_mode == VimMode::Visual,
+    },
+    TutorStep {
+        instruction: "Return to NORMAL mode with Esc.",
+        done: |ctx| ctx.vim_mode == VimMode::Normal,
+    },
+    TutorStep {
+        instruction: "Registers: select notes.txt and yank it with y.",
+        done: |ctx| ctx.register_holds(TransferKind::Copy, "notes.txt"),
+    },
+    TutorStep {
+        instruction: "Enter 'archive' and paste a copy with p.",
+        done: |ctx| ctx.sandbox.join("archive/notes.txt").exists(),
+    },
+    TutorStep {
+        instruction: "Go back with h, select draft.txt and cut it with m.",
+        done: |ctx| ctx.register_holds(TransferKind::Move, "draft.txt"),
+    },
+    TutorStep {
+        instruction: "Enter 'archive' and paste with p - a cut entry moves.",
+        done: |ctx| {
+            ctx.sandbox.join("archive/draft.txt").exists()
+                && !ctx.sandbox.join("draft.txt").exists()
+        },
+    },
+    TutorStep {
+        instruction: "Go back with h, then click an entry to select it (double-click opens).",
+        done: |ctx| ctx.selected_name() != Some("..") && ctx.current_dir.path == ctx.sandbox,
+    },
+    TutorStep {
+        instruction: "Command mode: type :tutor and press Enter to finish.",
+        done: |_| false,
+    },
+];
+
+// =============================================================================
+// State
+// =============================================================================
+
+/// Active tutorial session
+#[derive(Resource, Default)]
+struct Tutorial {
+    session: Option<TutorSession>,
+}
+
+struct TutorSession {
+    sandbox: PathBuf,
+    return_path: PathBuf,
+    step: usize,
+}
+
+/// Marker for the tutorial panel (despawned with the session)
+#[derive(Component)]
+struct TutorialPanel;
+
+/// Marker for the tutorial instruction text
+#[derive(Component)]
+struct TutorialText;
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Tutorial::default()).add_systems(
+            Update,
+            (handle_tutor_command, advance_tutorial, cleanup_on_exit),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn handle_tutor_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut tutorial: ResMut<Tutorial>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    panel_query: Query<Entity, With<TutorialPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Tutor {
+            continue;
+        }
+
+        if let Some(session) = tutorial.session.take() {
+            let _ = std::fs::remove_dir_all(&session.sandbox);
+            current_dir.path = session.return_path;
+            current_dir.needs_reload = true;
+            for entity in panel_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            status.0 = "Tutorial finished".to_string();
+            continue;
+        }
+
+        let sandbox = std::env::temp_dir().join(format!("felipe-tutor-{}", std::process::id()));
+        if let Err(err) = create_sandbox(&sandbox) {
+            status.0 = format!("Could not create tutorial sandbox: {}", err);
+            continue;
+        }
+
+        tutorial.session = Some(TutorSession {
+            sandbox: sandbox.clone(),
+            return_path: std::mem::replace(&mut current_dir.path, sandbox.clone()),
+            step: 0,
+        });
+        current_dir.needs_reload = true;
+        status.0 = format!("Tutorial sandbox: {}", sandbox.display());
+        spawn_panel(&mut commands);
+    }
+}
+
+fn advance_tutorial(
+    mut tutorial: ResMut<Tutorial>,
+    current_dir: Res<CurrentDirectory>,
+    vim_mode: Res<VimMode>,
+    register: Res<Register>,
+    mut text_query: Query<&mut Text, With<TutorialText>>,
+) {
+    let Some(session) = tutorial.session.as_mut() else {
+        return;
+    };
+    // Wait for the listing to settle after a directory change
+    if current_dir.needs_reload {
+        return;
+    }
+
+    let ctx = TutorContext {
+        sandbox: &session.sandbox,
+        current_dir: &current_dir,
+        vim_mode: *vim_mode,
+        register: &register,
+    };
+    if session.step + 1 < STEPS.len() && (STEPS[session.step].done)(&ctx) {
+        session.step += 1;
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "TUTOR {}/{}\n{}\n\n(:tutor again to leave)",
+            session.step + 1,
+            STEPS.len(),
+            STEPS[session.step].instruction
+        );
+    }
+}
+
+fn cleanup_on_exit(mut exit_events: EventReader<AppExit>, tutorial: Res<Tutorial>) {
+    if exit_events.read().next().is_some() {
+        if let Some(session) = &tutorial.session {
+            let _ = std::fs::remove_dir_all(&session.sandbox);
+        }
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Lay out a small tree with something to move, copy, and compare heights on
+fn create_sandbox(sandbox: &Path) -> std::io::Result<()> {
+    let _ = std::fs::remove_dir_all(sandbox);
+    std::fs::create_dir_all(sandbox.join("archive"))?;
+    std::fs::create_dir_all(sandbox.join("docs"))?;
+    std::fs::write(sandbox.join("docs/readme.md"), "# Felipe tutor\n")?;
+    std::fs::write(
+        sandbox.join("notes.txt"),
+        "Yank me with y, paste me with p.\n",
+    )?;
+    std::fs::write(sandbox.join("draft.txt"), "Cut me with m, then paste.\n")?;
+    // Big enough to stand out as a tall book
+    std::fs::write(sandbox.join("video.bin"), vec![0u8; 8 * 1024 * 1024])?;
+    Ok(())
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    right: Val::Px(10.0),
+                    max_width: Val::Px(420.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            TutorialPanel,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: FELIPE_ORANGE,
+                        ..default()
+                    },
+                ),
+                TutorialText,
+            ));
+        });
+}
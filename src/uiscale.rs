@@ -0,0 +1,132 @@
+//! UI scale - text and panels sized for the screen
+//!
+//! The UI is laid out in pixels for a screen about 1080 pixels tall. Bevy
+//! already multiplies them by the scale factor the system reports, but a big
+//! screen left at 100% (a 4K monitor on many Linux desktops) reports none,
+//! and everything comes out tiny; there the scale is raised to match the
+//! screen's height. `ui_scale` under `[render]` sets it instead, and Ctrl-=
+//! / Ctrl-- change it while Felipe runs (Ctrl-0 goes back).
+//!
+//! ```toml
+//! [render]
+//! ui_scale = 1.5
+//! ```
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy::winit::WinitWindows;
+
+use crate::config::Config;
+use crate::{Prompt, StatusMessage, VimMode};
+
+/// Logical screen height the pixel sizes were picked for
+const DESIGN_HEIGHT: f64 = 1080.0;
+
+/// Ctrl-= / Ctrl-- change the scale by this much
+const SCALE_STEP: f32 = 0.25;
+
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 4.0;
+
+#[derive(Resource, Default)]
+struct UiScaling {
+    /// What the screen asks for, once the window is up
+    screen: Option<f32>,
+    /// Set with Ctrl-= / Ctrl--, over the config and the screen
+    runtime: Option<f32>,
+}
+
+impl UiScaling {
+    fn scale(&self, config: &Config) -> f32 {
+        self.runtime
+            .or(config.render.ui_scale)
+            .or(self.screen)
+            .unwrap_or(1.0)
+            .clamp(MIN_SCALE, MAX_SCALE)
+    }
+}
+
+pub struct UiScalePlugin;
+
+impl Plugin for UiScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiScaling>().add_systems(
+            Update,
+            (
+                detect_screen.run_if(|scaling: Res<UiScaling>| scaling.screen.is_none()),
+                handle_scale_keys,
+                apply_scale,
+            )
+                .chain(),
+        );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Size up the monitor the window opened on; winit makes the window after
+/// startup, so this waits for it
+fn detect_screen(
+    winit_windows: Option<NonSend<WinitWindows>>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    mut scaling: ResMut<UiScaling>,
+) {
+    // An app without winit has no screen to size up
+    let Some(winit_windows) = winit_windows else {
+        scaling.screen = Some(1.0);
+        return;
+    };
+    let Ok(entity) = window_query.get_single() else {
+        return;
+    };
+    let Some(window) = winit_windows.get_window(entity) else {
+        return;
+    };
+    let screen = window.current_monitor().map_or(1.0, |monitor| {
+        let logical_height = monitor.size().height as f64 / monitor.scale_factor();
+        let scale = (logical_height / DESIGN_HEIGHT).clamp(1.0, MAX_SCALE as f64) as f32;
+        // On the steps Ctrl-= / Ctrl-- take
+        (scale / SCALE_STEP).floor() * SCALE_STEP
+    });
+    scaling.screen = Some(screen);
+}
+
+/// Ctrl-= bigger, Ctrl-- smaller, Ctrl-0 back to the config's or the screen's
+fn handle_scale_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    config: Res<Config>,
+    mut scaling: ResMut<UiScaling>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !matches!(*vim_mode, VimMode::Normal | VimMode::Visual)
+        || prompt.pending.is_some()
+    {
+        return;
+    }
+    let scale = scaling.scale(&config);
+    if keyboard.any_just_pressed([KeyCode::Equal, KeyCode::NumpadAdd]) {
+        scaling.runtime = Some((scale + SCALE_STEP).min(MAX_SCALE));
+    } else if keyboard.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        scaling.runtime = Some((scale - SCALE_STEP).max(MIN_SCALE));
+    } else if keyboard.any_just_pressed([KeyCode::Digit0, KeyCode::Numpad0]) {
+        scaling.runtime = None;
+    } else {
+        return;
+    }
+    status.0 = format!("UI scale {}", scaling.scale(&config));
+}
+
+fn apply_scale(config: Res<Config>, scaling: Res<UiScaling>, mut ui_scale: ResMut<UiScale>) {
+    if !config.is_changed() && !scaling.is_changed() {
+        return;
+    }
+    let scale = scaling.scale(&config);
+    if ui_scale.0 != scale {
+        ui_scale.0 = scale;
+    }
+}
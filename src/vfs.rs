@@ -0,0 +1,148 @@
+//! Virtual filesystem - what a place holding files has to offer
+//!
+//! Listings and the file operations in ops.rs and copier.rs reach files
+//! through a `VfsBackend` instead of calling `std::fs` themselves, so a new
+//! kind of place (an archive, the trash, a server) only has to implement the
+//! trait. The local disk, `LocalFs`, and the in-memory `--demo` tree
+//! (demofs.rs) implement it so far; remote locations still list and transfer
+//! through remote.rs's `Backend`, and `backend` turns them away with
+//! Unsupported.
+//!
+//! What only a disk has stays out of the trait: hard links and symlinks,
+//! permission bits, owners and extended attributes, the times and modes a copy
+//! keeps, and the chunked writes of large files. Those go to `std::fs` and
+//! `require_local` refuses them anywhere else.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{demofs, links, remote, shapes, FileEntry};
+
+/// Files and directories somewhere; paths are full paths as felipe shows them
+pub trait VfsBackend: Send + Sync {
+    /// Entries of the directory at `path`, unsorted and unfiltered
+    fn list(&self, path: &Path) -> io::Result<Vec<FileEntry>>;
+    /// The entry at `path` (a symlink itself, not what it points at); NotFound
+    /// if there's nothing there
+    fn stat(&self, path: &Path) -> io::Result<FileEntry>;
+    fn read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+    /// Create the file at `path`, or empty the one there
+    fn write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>>;
+    /// Move an entry in one step; fails where that's impossible, e.g. across
+    /// filesystems, and the caller copies instead
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Delete an entry for good, a directory with everything in it
+    fn delete(&self, path: &Path) -> io::Result<()>;
+    /// Create a directory and any missing parents
+    fn make_dir(&self, path: &Path) -> io::Result<()>;
+}
+
+/// The backend holding `path`
+pub fn backend(path: &Path) -> io::Result<&'static dyn VfsBackend> {
+    if demofs::is_demo(path) {
+        return Ok(demofs::demo());
+    }
+    if let Some(location) = remote::location(path) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("not supported on {}:// locations", location.scheme),
+        ));
+    }
+    Ok(&LocalFs)
+}
+
+/// Whether there's an entry at `path`, a dangling symlink included
+pub fn exists(path: &Path) -> bool {
+    backend(path).and_then(|fs| fs.stat(path)).is_ok()
+}
+
+/// Whether `path` is on the local disk, where `std::fs` reaches it
+pub fn is_local(path: &Path) -> bool {
+    !demofs::is_demo(path) && remote::location(path).is_none()
+}
+
+/// Unsupported unless `path` is on the local disk
+pub fn require_local(path: &Path) -> io::Result<()> {
+    if is_local(path) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("only on the local disk: {}", path.display()),
+        ))
+    }
+}
+
+/// The local disk
+pub struct LocalFs;
+
+impl VfsBackend for LocalFs {
+    fn list(&self, path: &Path) -> io::Result<Vec<FileEntry>> {
+        Ok(fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| local_entry(entry.path()).ok())
+            .collect())
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileEntry> {
+        local_entry(path.to_path_buf())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Ok(Box::new(File::create(path)?))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        if fs::symlink_metadata(path)?.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+
+    fn make_dir(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+}
+
+fn local_entry(path: PathBuf) -> io::Result<FileEntry> {
+    let own = fs::symlink_metadata(&path)?;
+    let link = own
+        .file_type()
+        .is_symlink()
+        .then(|| links::read_link(&path))
+        .flatten();
+    // A link looks like its target; a dangling one like itself
+    let metadata = match &link {
+        Some(_) => fs::metadata(&path).unwrap_or(own),
+        None => own,
+    };
+    let is_dir = metadata.is_dir();
+    Ok(FileEntry {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string()),
+        locked: is_dir && crate::enter_error(&path).is_some(),
+        is_dir,
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        // A symlink's metadata is its target's, whose names don't matter here
+        inode: link
+            .is_none()
+            .then(|| links::hardlink_inode(&metadata))
+            .flatten(),
+        executable: shapes::is_executable(&metadata),
+        link,
+        path,
+    })
+}
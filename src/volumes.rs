@@ -0,0 +1,614 @@
+//! Volumes - the disks and shares paths live on
+//!
+//! On Windows a drive root like `C:\` has no parent, so `h` there goes up to
+//! a "This PC" view listing every drive with its free space instead of
+//! stopping. Network shares work as `\\server\share` paths, from `:cd` or the
+//! command line, and their root leads up to the same view.
+//!
+//! `:volumes` lists every mounted volume with its filesystem and free space;
+//! Enter goes to one and `e` unmounts and ejects a removable one, like a USB
+//! stick, once nothing is shown from it. A gauge in the top panel shows how
+//! full the volume under the current directory is.
+
+use bevy::prelude::*;
+use std::io;
+use std::path::{Component, Path, PathBuf, Prefix};
+use std::process::Stdio;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use sysinfo::Disks;
+
+use crate::command::{Command, RunCommand};
+use crate::config::Config;
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::{
+    CurrentDirectory, FileEntry, StatusMessage, UiElement, VimMode, DIFF_REMOVED, FELIPE_ORANGE,
+    FELIPE_ORANGE_DIM,
+};
+
+/// Path standing for the view of all drives; never a real directory, as it
+/// isn't absolute
+const DRIVES_VIEW: &str = "This PC";
+
+/// Width of the free-space gauge's bar
+const GAUGE_WIDTH: f32 = 120.0;
+/// Fuller than this, the bar turns red
+const GAUGE_FULL: f32 = 0.9;
+
+/// Free space is read again after this long
+const REFRESH_EVERY: Duration = Duration::from_secs(5);
+
+/// A mounted filesystem
+#[derive(Clone)]
+pub struct Volume {
+    pub mount: PathBuf,
+    /// Volume label, or the device name where there's none
+    pub name: String,
+    pub file_system: String,
+    pub total: u64,
+    pub available: u64,
+    /// USB sticks, SD cards and the like, which can be ejected
+    pub removable: bool,
+}
+
+/// Volumes mounted right now
+pub fn list() -> Vec<Volume> {
+    Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .map(|disk| Volume {
+            mount: disk.mount_point().to_path_buf(),
+            name: disk.name().to_string_lossy().to_string(),
+            file_system: disk.file_system().to_string_lossy().to_string(),
+            total: disk.total_space(),
+            available: disk.available_space(),
+            removable: disk.is_removable(),
+        })
+        .collect()
+}
+
+/// Volumes with their free space, refreshed in the background
+#[derive(Resource)]
+pub struct Volumes {
+    pub list: Vec<Volume>,
+    refresh: Timer,
+}
+
+impl Volumes {
+    /// The volume `path` is on: the one mounted deepest above it
+    pub fn containing(&self, path: &Path) -> Option<&Volume> {
+        self.list
+            .iter()
+            .filter(|volume| path.starts_with(&volume.mount))
+            .max_by_key(|volume| volume.mount.components().count())
+    }
+}
+
+/// The `:volumes` panel
+#[derive(Resource, Default)]
+struct VolumesView {
+    cursor: usize,
+    /// Mount point being ejected, and the eject running in the background
+    eject: Option<(PathBuf, JoinHandle<io::Result<()>>)>,
+}
+
+/// The view of all drives
+pub fn drives_view() -> PathBuf {
+    PathBuf::from(DRIVES_VIEW)
+}
+
+pub fn is_drives_view(path: &Path) -> bool {
+    path == Path::new(DRIVES_VIEW)
+}
+
+/// Whether `path` is the top of a Windows drive or share (`C:\`,
+/// `\\server\share`), whose parent is the drives view
+pub fn is_drive_root(path: &Path) -> bool {
+    let mut components = path.components();
+    matches!(components.next(), Some(Component::Prefix(_)))
+        && matches!(components.next(), Some(Component::RootDir))
+        && components.next().is_none()
+}
+
+/// The drives as directory entries
+pub fn drive_entries() -> Vec<FileEntry> {
+    list()
+        .into_iter()
+        .map(|volume| {
+            let letter = volume
+                .mount
+                .to_string_lossy()
+                .trim_end_matches('\\')
+                .to_string();
+            FileEntry {
+                name: if volume.name.is_empty() {
+                    letter
+                } else {
+                    format!("{} {}", letter, volume.name)
+                },
+                path: volume.mount,
+                is_dir: true,
+                size: 0,
+                modified: None,
+                locked: false,
+                link: None,
+                inode: None,
+                executable: false,
+            }
+        })
+        .collect()
+}
+
+/// `path` without the `\\?\` prefix Windows puts on canonical paths, so
+/// `\\?\UNC\server\share` reads `\\server\share` again; other paths as they are
+pub fn simplified(path: PathBuf) -> PathBuf {
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return path;
+    };
+    let rest: PathBuf = path.components().skip(1).collect();
+    match prefix.kind() {
+        Prefix::VerbatimDisk(letter) => PathBuf::from(format!("{}:", letter as char)).join(rest),
+        Prefix::VerbatimUNC(server, share) => PathBuf::from(format!(
+            r"\\{}\{}",
+            server.to_string_lossy(),
+            share.to_string_lossy()
+        ))
+        .join(rest),
+        _ => path,
+    }
+}
+
+pub struct VolumesPlugin;
+
+impl Plugin for VolumesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Volumes {
+            list: Vec::new(),
+            refresh: Timer::new(REFRESH_EVERY, TimerMode::Repeating),
+        })
+        .insert_resource(VolumesView::default())
+        .add_systems(Startup, spawn_gauge)
+        .add_systems(
+            Update,
+            (
+                refresh_volumes,
+                handle_volumes_command,
+                handle_volumes_keys,
+                finish_eject,
+                update_volumes_panel,
+                update_gauge,
+            ),
+        );
+    }
+}
+
+/// Marker for the free-space gauge
+#[derive(Component)]
+struct Gauge;
+
+/// Marker for the gauge's label
+#[derive(Component)]
+struct GaugeText;
+
+/// Marker for the used part of the gauge's bar
+#[derive(Component)]
+struct GaugeFill;
+
+/// Marker for the volumes panel
+#[derive(Component)]
+struct VolumesPanel;
+
+/// Marker for the volumes panel text
+#[derive(Component)]
+struct VolumesText;
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// Read the volumes on the first frame and every few seconds after
+fn refresh_volumes(time: Res<Time>, mut volumes: ResMut<Volumes>, mut started: Local<bool>) {
+    if volumes.refresh.tick(time.delta()).just_finished() || !*started {
+        *started = true;
+        volumes.list = list();
+    }
+}
+
+fn spawn_gauge(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(14.0),
+                    right: Val::Px(10.0),
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8.0),
+                    display: Display::None,
+                    ..default()
+                },
+                ..default()
+            },
+            Gauge,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: FELIPE_ORANGE_DIM,
+                        ..default()
+                    },
+                ),
+                GaugeText,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(GAUGE_WIDTH),
+                        height: Val::Px(10.0),
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    border_color: BorderColor(FELIPE_ORANGE_DIM),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(FELIPE_ORANGE),
+                            ..default()
+                        },
+                        GaugeFill,
+                    ));
+                });
+        });
+}
+
+/// Show how full the current directory's volume is; hidden where there's
+/// no local volume, like remote locations
+fn update_gauge(
+    current_dir: Res<CurrentDirectory>,
+    volumes: Res<Volumes>,
+    config: Res<Config>,
+    mut shown: Local<Option<(PathBuf, u64, u64)>>,
+    mut gauge_query: Query<&mut Style, (With<Gauge>, Without<GaugeFill>)>,
+    mut text_query: Query<&mut Text, With<GaugeText>>,
+    mut fill_query: Query<(&mut Style, &mut BackgroundColor), With<GaugeFill>>,
+) {
+    let volume = volumes
+        .containing(&current_dir.path)
+        .filter(|volume| volume.total > 0);
+    let now = volume.map(|volume| (volume.mount.clone(), volume.available, volume.total));
+    if *shown == now {
+        return;
+    }
+    *shown = now;
+
+    for mut style in gauge_query.iter_mut() {
+        style.display = if volume.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    let Some(volume) = volume else {
+        return;
+    };
+    let used = 1.0 - volume.available as f32 / volume.total as f32;
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "{} free of {} on {}",
+            config.format.size(volume.available),
+            config.format.size(volume.total),
+            volume.mount.display()
+        );
+    }
+    for (mut style, mut color) in fill_query.iter_mut() {
+        style.width = Val::Percent(used.clamp(0.0, 1.0) * 100.0);
+        color.0 = if used > GAUGE_FULL {
+            DIFF_REMOVED
+        } else {
+            FELIPE_ORANGE
+        };
+    }
+}
+
+fn handle_volumes_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut view: ResMut<VolumesView>,
+    mut volumes: ResMut<Volumes>,
+    current_dir: Res<CurrentDirectory>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<VolumesPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::Volumes {
+            continue;
+        }
+        if focus.is_open(Panel::Volumes) {
+            close_panel(&mut commands, &mut focus, &panel_query);
+            continue;
+        }
+        volumes.list = list();
+        // Start on the volume the current directory is on
+        view.cursor = volumes
+            .containing(&current_dir.path)
+            .and_then(|here| {
+                volumes
+                    .list
+                    .iter()
+                    .position(|volume| volume.mount == here.mount)
+            })
+            .unwrap_or(0);
+        focus.open(Panel::Volumes);
+        spawn_panel(&mut commands);
+    }
+}
+
+fn handle_volumes_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut view: ResMut<VolumesView>,
+    volumes: Res<Volumes>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<VolumesPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::Volumes)
+    {
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    if !focus.has_focus(Panel::Volumes) || *vim_mode != VimMode::Normal {
+        return;
+    }
+
+    let count = volumes.list.len();
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        view.cursor = (view.cursor + 1).min(count.saturating_sub(1));
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        view.cursor = view.cursor.saturating_sub(1);
+    }
+    let Some(volume) = volumes.list.get(view.cursor) else {
+        return;
+    };
+    if keyboard.just_pressed(KeyCode::Enter) {
+        current_dir.set_path(volume.mount.clone());
+        close_panel(&mut commands, &mut focus, &panel_query);
+    } else if keyboard.just_pressed(KeyCode::KeyE) {
+        if !volume.removable {
+            status.0 = format!("{} is not removable", volume.mount.display());
+            return;
+        }
+        if view.eject.is_some() {
+            status.0 = "Still ejecting the last volume".to_string();
+            return;
+        }
+        // Nothing may be open on it, so stop showing it first
+        if current_dir.path.starts_with(&volume.mount) {
+            let away = volume
+                .mount
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(drives_view);
+            current_dir.set_path(away);
+        }
+        status.0 = format!("Ejecting {}...", volume.mount.display());
+        let target = volume.clone();
+        view.eject = Some((
+            volume.mount.clone(),
+            std::thread::spawn(move || eject(&target)),
+        ));
+    }
+}
+
+fn finish_eject(
+    mut view: ResMut<VolumesView>,
+    mut volumes: ResMut<Volumes>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if !view
+        .eject
+        .as_ref()
+        .is_some_and(|(_, eject)| eject.is_finished())
+    {
+        return;
+    }
+    let (mount, eject) = view.eject.take().expect("checked above");
+    let result = eject
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("eject panicked")));
+    status.0 = match result {
+        Ok(()) => format!("Ejected {}; it is safe to remove", mount.display()),
+        Err(err) => format!("Could not eject {}: {}", mount.display(), err),
+    };
+    volumes.list = list();
+    view.cursor = view.cursor.min(volumes.list.len().saturating_sub(1));
+}
+
+fn update_volumes_panel(
+    view: Res<VolumesView>,
+    volumes: Res<Volumes>,
+    focus: Res<Focus>,
+    config: Res<Config>,
+    mut text_query: Query<&mut Text, With<VolumesText>>,
+) {
+    if !focus.is_open(Panel::Volumes) {
+        return;
+    }
+    let mut sections = vec![TextSection::new(
+        "VOLUMES  j/k:select  Enter:go  e:eject  Esc:close\n",
+        panel_style(FELIPE_ORANGE),
+    )];
+    if volumes.list.is_empty() {
+        sections.push(TextSection::new(
+            "no volumes found\n",
+            panel_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    for (i, volume) in volumes.list.iter().enumerate() {
+        let selected = i == view.cursor;
+        let ejecting = view
+            .eject
+            .as_ref()
+            .is_some_and(|(mount, _)| *mount == volume.mount);
+        sections.push(TextSection::new(
+            format!(
+                "{} {}  {}  {}  {} free of {}{}\n",
+                if selected { ">" } else { " " },
+                volume.mount.display(),
+                volume.name,
+                volume.file_system,
+                config.format.size(volume.available),
+                config.format.size(volume.total),
+                if ejecting {
+                    "  ejecting..."
+                } else if volume.removable {
+                    "  removable"
+                } else {
+                    ""
+                }
+            ),
+            panel_style(if selected {
+                FELIPE_ORANGE
+            } else {
+                FELIPE_ORANGE_DIM
+            }),
+        ));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Unmount `volume` and power it off where the system can
+fn eject(volume: &Volume) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    let mut command = {
+        // udisks lets a desktop user unmount what they mounted; plain umount
+        // is left for devices it doesn't know
+        if volume.name.starts_with("/dev/") {
+            let mut command = std::process::Command::new("udisksctl");
+            command.args(["unmount", "-b", &volume.name]);
+            command
+        } else {
+            let mut command = std::process::Command::new("umount");
+            command.arg(&volume.mount);
+            command
+        }
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("diskutil");
+        command.arg("eject").arg(&volume.mount);
+        command
+    };
+    #[cfg(windows)]
+    let mut command = {
+        // The same Eject as the Explorer context menu
+        let drive = volume
+            .mount
+            .to_string_lossy()
+            .trim_end_matches('\\')
+            .to_string();
+        let mut command = std::process::Command::new("powershell");
+        command.args([
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "(New-Object -ComObject Shell.Application).Namespace(17).ParseName('{}').InvokeVerb('Eject')",
+                drive
+            ),
+        ]);
+        command
+    };
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    let mut command = {
+        let mut command = std::process::Command::new("umount");
+        command.arg(&volume.mount);
+        command
+    };
+
+    let output = command.stdin(Stdio::null()).output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(if message.is_empty() {
+            "unmount failed".to_string()
+        } else {
+            message
+        }));
+    }
+    // Spin the stick down too, so the light goes off; it's unmounted either way
+    #[cfg(target_os = "linux")]
+    if volume.name.starts_with("/dev/") {
+        let _ = std::process::Command::new("udisksctl")
+            .args(["power-off", "-b", &volume.name])
+            .stdin(Stdio::null())
+            .output();
+    }
+    Ok(())
+}
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Percent(45.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.95)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            VolumesPanel,
+            Focusable(Panel::Volumes),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), VolumesText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<VolumesPanel>>,
+) {
+    focus.close(Panel::Volumes);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
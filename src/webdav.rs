@@ -0,0 +1,222 @@
+//! WebDAV backend of `dav://user@host/path` and `davs://` (HTTPS) locations
+//! (feature `webdav`)
+//!
+//! The path is the server's, so a Nextcloud share is
+//! `davs://me@cloud.example.org/remote.php/dav/files/me`. The password comes
+//! from `~/.netrc` (see remote.rs); without a user requests go out
+//! unauthenticated. Listings are `PROPFIND` with depth 1, transfers plain
+//! `GET` and `PUT`.
+
+use base64::Engine;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::fs::{File, FileTimes};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use ureq::{Agent, AgentBuilder, Request, Response};
+
+use crate::copier::CopyProgress;
+use crate::remote::{self, Backend, RemoteEntry};
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+/// Properties asked of each entry
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop>
+<d:resourcetype/><d:getcontentlength/><d:getlastmodified/>
+</d:prop></d:propfind>"#;
+
+pub struct WebDavBackend {
+    agent: Agent,
+    /// `https://host:port`, which paths are appended to
+    base: String,
+    /// `Authorization` header value, when logging in
+    authorization: Option<String>,
+}
+
+impl WebDavBackend {
+    /// Reach `[user@]host[:port]` over HTTPS if `secure`, else plain HTTP
+    pub fn connect(authority: &str, secure: bool) -> io::Result<Self> {
+        let (user, address) = match authority.split_once('@') {
+            Some((user, address)) => (Some(user), address),
+            None => (None, authority),
+        };
+        let (host, _) = remote::host_port(address, 0)?;
+        let authorization = user.map(|user| {
+            let password = remote::netrc_password(host, user).unwrap_or_default();
+            let token =
+                base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+            format!("Basic {}", token)
+        });
+        let backend = Self {
+            agent: AgentBuilder::new().timeout_connect(TIMEOUT).build(),
+            base: format!("{}://{}", if secure { "https" } else { "http" }, address),
+            authorization,
+        };
+        // Any answer but a refused login means the server is there
+        match backend.request("OPTIONS", "/").call() {
+            Ok(_) => Ok(backend),
+            Err(ureq::Error::Status(401, _)) => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "login refused (password in ~/.netrc?)",
+            )),
+            Err(ureq::Error::Status(..)) => Ok(backend),
+            Err(err) => Err(http_error(err)),
+        }
+    }
+
+    fn request(&self, method: &str, path: &str) -> Request {
+        let request = self.agent.request(
+            method,
+            &format!("{}{}", self.base, remote::encode_path(path)),
+        );
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    /// Entries of a `PROPFIND`: the one at `path`, and its children with depth 1
+    fn propfind(&self, path: &str, depth: &str) -> io::Result<Vec<(String, RemoteEntry)>> {
+        let response = self
+            .request("PROPFIND", path)
+            .set("Depth", depth)
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(PROPFIND_BODY)
+            .map_err(http_error)?;
+        parse_multistatus(&response.into_string()?)
+    }
+}
+
+impl Backend for WebDavBackend {
+    fn list(&self, path: &str) -> io::Result<Vec<RemoteEntry>> {
+        let own = path.trim_end_matches('/');
+        Ok(self
+            .propfind(path, "1")?
+            .into_iter()
+            .filter(|(href, _)| href.trim_end_matches('/') != own)
+            .map(|(_, entry)| entry)
+            .collect())
+    }
+
+    fn stat(&self, path: &str) -> io::Result<RemoteEntry> {
+        self.propfind(path, "0")?
+            .into_iter()
+            .next()
+            .map(|(_, entry)| entry)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no {}", path)))
+    }
+
+    fn download(&self, path: &str, local: &Path, progress: &CopyProgress) -> io::Result<()> {
+        let response = self.request("GET", path).call().map_err(http_error)?;
+        let modified = last_modified(&response);
+        let mut file = File::create(local)?;
+        remote::transfer(&mut response.into_reader(), &mut file, progress)?;
+        if let Some(modified) = modified {
+            let _ = file.set_times(FileTimes::new().set_modified(modified));
+        }
+        Ok(())
+    }
+
+    fn upload(&self, local: &Path, path: &str, progress: &CopyProgress) -> io::Result<()> {
+        let size = local.metadata()?.len();
+        self.request("PUT", path)
+            .set("Content-Length", &size.to_string())
+            .send(File::open(local)?)
+            .map_err(http_error)?;
+        progress.add_copied(size);
+        Ok(())
+    }
+
+    fn make_dir(&self, path: &str) -> io::Result<()> {
+        self.request("MKCOL", path).call().map_err(http_error)?;
+        Ok(())
+    }
+}
+
+/// `(path, entry)` of each `<response>` in a `207 Multi-Status` body
+fn parse_multistatus(xml: &str) -> io::Result<Vec<(String, RemoteEntry)>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut entries = Vec::new();
+    let mut element = Vec::new();
+    let mut href = String::new();
+    let mut entry = None;
+    loop {
+        match reader.read_event().map_err(io::Error::other)? {
+            Event::Start(start) | Event::Empty(start) => {
+                element = start.local_name().as_ref().to_vec();
+                match element.as_slice() {
+                    b"response" => {
+                        href.clear();
+                        entry = Some(RemoteEntry {
+                            name: String::new(),
+                            is_dir: false,
+                            size: 0,
+                            modified: None,
+                        });
+                    }
+                    b"collection" => {
+                        if let Some(entry) = entry.as_mut() {
+                            entry.is_dir = true;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(io::Error::other)?;
+                let Some(entry) = entry.as_mut() else {
+                    continue;
+                };
+                match element.as_slice() {
+                    b"href" => href = server_path(&text),
+                    b"getcontentlength" => entry.size = text.trim().parse().unwrap_or(0),
+                    b"getlastmodified" => entry.modified = http_date(&text),
+                    _ => {}
+                }
+            }
+            Event::End(end) if end.local_name().as_ref() == b"response" => {
+                if let Some(mut entry) = entry.take() {
+                    let name = href.trim_end_matches('/').rsplit('/').next();
+                    entry.name = name.unwrap_or_default().to_string();
+                    entries.push((std::mem::take(&mut href), entry));
+                }
+            }
+            Event::End(_) => element.clear(),
+            Event::Eof => return Ok(entries),
+            _ => {}
+        }
+    }
+}
+
+/// Decoded path of an `href`, which some servers send as a full URL
+fn server_path(href: &str) -> String {
+    let path = match href.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |slash| &rest[slash..]),
+        None => href,
+    };
+    remote::decode_path(path)
+}
+
+fn last_modified(response: &Response) -> Option<SystemTime> {
+    http_date(response.header("Last-Modified")?)
+}
+
+/// `Sun, 06 Nov 1994 08:49:37 GMT`
+fn http_date(text: &str) -> Option<SystemTime> {
+    chrono::DateTime::parse_from_rfc2822(text.trim())
+        .ok()
+        .map(SystemTime::from)
+}
+
+fn http_error(err: ureq::Error) -> io::Error {
+    match err {
+        ureq::Error::Status(404, _) => io::Error::new(io::ErrorKind::NotFound, "not found"),
+        ureq::Error::Status(403, _) => io::Error::new(io::ErrorKind::PermissionDenied, "forbidden"),
+        ureq::Error::Status(code, response) => {
+            io::Error::other(format!("HTTP {} {}", code, response.status_text()))
+        }
+        ureq::Error::Transport(transport) => io::Error::other(transport.to_string()),
+    }
+}
@@ -0,0 +1,812 @@
+//! What's new - after an upgrade, a one-time overlay lists the new commands and keys
+//!
+//! Felipe has no menus, so this is how new features get found. The list comes
+//! from `CHANGELOG` below; entries with a command can be tried right from the
+//! overlay with Enter. `:whatsnew` shows it again.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::command::{parse_command, Command, RunCommand};
+use crate::focus::{Dismiss, Focus, Focusable, Panel};
+use crate::{data_dir, StatusMessage, UiElement, VimMode, FELIPE_ORANGE, FELIPE_ORANGE_DIM};
+
+// =============================================================================
+// Changelog
+// =============================================================================
+
+struct Feature {
+    keys: &'static str,
+    what: &'static str,
+    /// Command line (without `:`) that Enter runs to show the feature
+    command: Option<&'static str>,
+}
+
+struct Release {
+    version: &'static str,
+    features: &'static [Feature],
+}
+
+/// Newest first; add a release here whenever the version is bumped
+const CHANGELOG: &[Release] = &[Release {
+    version: "0.1.0",
+    features: &[
+        Feature {
+            keys: ":tutor",
+            what: "interactive walkthrough of the basics",
+            command: Some("tutor"),
+        },
+        Feature {
+            keys: "r",
+            what: "rename in place, with name checks while typing",
+            command: None,
+        },
+        Feature {
+            keys: "i",
+            what: "properties of the selection: mode, owner, links, times",
+            command: None,
+        },
+        Feature {
+            keys: ":chmod 755",
+            what: "change permissions, or flip rwx bits in the i panel",
+            command: None,
+        },
+        Feature {
+            keys: ":chown user:group",
+            what: "change owners, over a visual range too (v, then :)",
+            command: None,
+        },
+        Feature {
+            keys: ":xattr set name value",
+            what: "add or (with rm) remove extended attributes, listed in the i panel",
+            command: None,
+        },
+        Feature {
+            keys: "l on a symlink",
+            what: "links are see-through, with an arrow to their target; l follows them",
+            command: None,
+        },
+        Feature {
+            keys: ":ln -s target name",
+            what: "make symlinks (or hard links without -s); P pastes the register as links",
+            command: None,
+        },
+        Feature {
+            keys: "i",
+            what: "hard links listed together turn violet; i shows space on disk for sparse files",
+            command: None,
+        },
+        Feature {
+            keys: ":colorby mtime",
+            what: "recently changed entries glow, stale ones fade (:colorby none to go back)",
+            command: None,
+        },
+        Feature {
+            keys: "[shapes] in config",
+            what: "folders are frames, images slabs, programs pyramids, archives stacks",
+            command: None,
+        },
+        Feature {
+            keys: "info bar",
+            what: "shows the file type sniffed from content, which also picks the shape",
+            command: None,
+        },
+        Feature {
+            keys: ":sort taken",
+            what: "photos: i shows camera, exposure, date and GPS; sort a dump by date taken",
+            command: Some("sort taken"),
+        },
+        Feature {
+            keys: "Shift-Space",
+            what: "play the selected mp3/flac/ogg/wav, again to pause (audio builds)",
+            command: None,
+        },
+        Feature {
+            keys: ":set preview",
+            what: "videos show a poster frame, resolution and duration (needs ffmpeg)",
+            command: Some("set preview!"),
+        },
+        Feature {
+            keys: ":set preview",
+            what: "PDFs show their first page and page count (pdf builds)",
+            command: None,
+        },
+        Feature {
+            keys: ":set preview",
+            what: "READMEs and other Markdown show rendered: headings, lists, code",
+            command: None,
+        },
+        Feature {
+            keys: "felipe --help",
+            what: "start in a path with a theme, flat layout, --read-only or another config",
+            command: None,
+        },
+        Feature {
+            keys: "--choose-file",
+            what: "pick files (or a directory with --choose-dir) for a script; paths go to stdout",
+            command: None,
+        },
+        Feature {
+            keys: "--cwd-file",
+            what: "cd on quit: with a small shell function, the shell lands where Felipe was",
+            command: None,
+        },
+        Feature {
+            keys: "Ctrl-t",
+            what: "open a terminal in the current directory ([terminal] picks which)",
+            command: Some("terminal"),
+        },
+        Feature {
+            keys: ":!cmd %",
+            what: "shell command on the selection (%* is the visual range), output in a panel",
+            command: None,
+        },
+        Feature {
+            keys: "[[commands]]",
+            what: "your own shell commands in the config, run as :name or bound to a key",
+            command: None,
+        },
+        Feature {
+            keys: ":script name",
+            what: "run a Rhai script from the config's scripts/ folder; u undoes what it did",
+            command: Some("script"),
+        },
+        Feature {
+            keys: "[[hooks]]",
+            what: "run a command or script when a folder loads, a file opens, an operation ends...",
+            command: None,
+        },
+        Feature {
+            keys: ":layout",
+            what: "list the entry layouts plugins added; :layout name switches to one",
+            command: Some("layout"),
+        },
+        Feature {
+            keys: "felipe --tui",
+            what: "the listing as text in the terminal, for SSH or no GPU (--features tui)",
+            command: None,
+        },
+        Feature {
+            keys: ":z fragment",
+            what: "jump to the most used directory whose path matches, like zoxide",
+            command: None,
+        },
+        Feature {
+            keys: "F",
+            what: "fuzzy-find a file anywhere below the current directory and jump to it",
+            command: Some("find"),
+        },
+        Feature {
+            keys: "'1 / :pin",
+            what: "portals to home, downloads, drives and pinned directories in front of the grid",
+            command: None,
+        },
+        Feature {
+            keys: ":vsplit path",
+            what: "another directory mirrored beside this one; Ctrl-w swaps sides, :only closes",
+            command: Some("vsplit"),
+        },
+        Feature {
+            keys: ":diff other-dir",
+            what: "color what differs from another tree; :diff > and :diff < sync the differences",
+            command: None,
+        },
+        Feature {
+            keys: ":hash sha256",
+            what: "checksums of the selection, shown under i and copied to the clipboard",
+            command: Some("hash"),
+        },
+        Feature {
+            keys: ":verify",
+            what: "check files against SHA256SUMS or .sha256/.md5 sidecars: green matches, red doesn't",
+            command: Some("verify"),
+        },
+        Feature {
+            keys: ":sync dest",
+            what: "mirror this directory to another after previewing the plan; :sync! also deletes",
+            command: None,
+        },
+        Feature {
+            keys: ":cd sftp://user@host/path",
+            what: "browse a server over SFTP and copy files to and from it (feature sftp)",
+            command: None,
+        },
+        Feature {
+            keys: ":cd s3://bucket/prefix",
+            what: "browse an S3 (or MinIO, R2...) bucket with prefixes as folders, and copy to and from it (feature s3)",
+            command: None,
+        },
+        Feature {
+            keys: ":cd ftps://me@nas/share",
+            what: "browse FTP(S) servers and WebDAV shares like Nextcloud (dav://, davs://), passwords from ~/.netrc (features ftp, webdav)",
+            command: None,
+        },
+        Feature {
+            keys: ":mtp",
+            what: "browse a connected phone or camera and copy photos off it (feature mtp, Linux)",
+            command: Some("mtp"),
+        },
+        Feature {
+            keys: "h on C:\\",
+            what: "Windows: go up from a drive to all drives with their free space; :cd \\\\server\\share works too",
+            command: None,
+        },
+        Feature {
+            keys: ":volumes",
+            what: "mounted volumes with their free space; Enter goes there, e ejects a USB stick",
+            command: Some("volumes"),
+        },
+        Feature {
+            keys: ":find name",
+            what: "with [index] roots in the config, find files anywhere under them instantly (feature index)",
+            command: None,
+        },
+        Feature {
+            keys: "t r",
+            what: "tag the selected entry red (o y g b p for other colors, x clears); :filter tag:red lists them",
+            command: None,
+        },
+        Feature {
+            keys: "top bar",
+            what: "a gauge of the free space left on the volume you're in, red when it's nearly full",
+            command: None,
+        },
+        Feature {
+            keys: "* / :starred",
+            what: "star favorite files and directories, then find them all in one view",
+            command: Some("starred"),
+        },
+        Feature {
+            keys: ":note text",
+            what: "pin a note to the selected entry, shown in i and as a sticky note on it",
+            command: None,
+        },
+        Feature {
+            keys: ":savesearch name query",
+            what: "keep a search like size>1GB ext:mp4 as a directory; :searches lists them",
+            command: None,
+        },
+        Feature {
+            keys: ":oplog U / r",
+            what: "operations are logged across sessions: revert any of them, or run one again",
+            command: Some("oplog"),
+        },
+        Feature {
+            keys: ":set readonly",
+            what: "refuse every change to the disk until :set noreadonly, shown as [READ-ONLY]",
+            command: None,
+        },
+        Feature {
+            keys: ":set dryrun",
+            what: "pastes, renames and conflict choices are planned for review; y runs them for real",
+            command: None,
+        },
+        Feature {
+            keys: "after a crash",
+            what: "go back to where Felipe was, and continue or roll back transfers it cut short",
+            command: None,
+        },
+        Feature {
+            keys: ":stats",
+            what: "files, sizes, the largest ones and a bar per extension for this directory",
+            command: Some("stats"),
+        },
+        Feature {
+            keys: ":dashboard",
+            what: "3D charts of the tree: size by subdirectory, file age, size by type",
+            command: Some("dashboard"),
+        },
+        Feature {
+            keys: "minimap",
+            what: "every entry from above in the corner; click to go there (:set minimap hides it)",
+            command: None,
+        },
+        Feature {
+            keys: "b 2 / click the path",
+            what: "jump to any directory above this one from the breadcrumbs in the top bar",
+            command: None,
+        },
+        Feature {
+            keys: ":tree / Ctrl-e",
+            what: "a sidebar with the directories above and beside this one, browsable with j/k/l/h",
+            command: Some("tree"),
+        },
+        Feature {
+            keys: "?",
+            what: "every key and command on one screen, your own key bindings too",
+            command: None,
+        },
+        Feature {
+            keys: "z / b / ' and wait",
+            what: "a popup lists the keys that can follow",
+            command: None,
+        },
+        Feature {
+            keys: "[statusline] segments",
+            what: "pick what the bottom bar shows: position, jobs, free space, the path...",
+            command: Some("config edit"),
+        },
+        Feature {
+            keys: "7j / 15G",
+            what: "counts before motions; :set number or relativenumber shows the numbers",
+            command: Some("set relativenumber"),
+        },
+        Feature {
+            keys: "Ctrl-= / Ctrl--",
+            what: "scale text and panels; big screens start scaled up, or set render.ui_scale",
+            command: None,
+        },
+        Feature {
+            keys: "[font] paths",
+            what: "Japanese, Chinese and Korean names readable, with a system CJK font by default",
+            command: Some("config edit"),
+        },
+        Feature {
+            keys: "[locale] language",
+            what: "the UI in Japanese, from LANG or the config",
+            command: Some("config edit"),
+        },
+        Feature {
+            keys: ":set accessible",
+            what: "no camera easing, pulsing or screen effects; brighter text, bigger names",
+            command: Some("set accessible"),
+        },
+        Feature {
+            keys: "render.idle_fps",
+            what: "a still screen redraws 10 times a second instead of full rate; max_fps caps it",
+            command: Some("config edit"),
+        },
+        Feature {
+            keys: ":set quality low / --gpu-low",
+            what: "no bloom, CRT or far grid lines for integrated graphics",
+            command: Some("set quality low"),
+        },
+        Feature {
+            keys: "F11",
+            what: "fullscreen; the window reopens where and as big as it was",
+            command: Some("set fullscreen"),
+        },
+        Feature {
+            keys: "[window] transparent",
+            what: "float over a terminal: see-through background, no title bar, :set ontop",
+            command: Some("config edit"),
+        },
+        Feature {
+            keys: ":record",
+            what: "fly once around the directory and save it as a video (ffmpeg) or frames",
+            command: Some("record"),
+        },
+        Feature {
+            keys: "--demo",
+            what: "start in a sample tree kept in memory, nothing on disk is touched",
+            command: None,
+        },
+        Feature {
+            keys: "yp / yn / yd",
+            what: "copy the selection's path, name or directory to the clipboard",
+            command: None,
+        },
+        Feature {
+            keys: "Ctrl-v",
+            what: "paste files copied in Explorer, Finder or another file manager",
+            command: None,
+        },
+        Feature {
+            keys: "drag to the edge",
+            what: "an entry dragged off the window goes on the clipboard to paste elsewhere",
+            command: None,
+        },
+        Feature {
+            keys: "drop files in",
+            what: "files dropped from other apps are copied here, or moved with Shift",
+            command: None,
+        },
+        Feature {
+            keys: "Space, :paste-basket",
+            what: "gather entries from several directories, then copy or move them all here",
+            command: Some("basket"),
+        },
+        Feature {
+            keys: "Space, :clearsel",
+            what: "marked entries wear a ring; y, m and : commands act on the marks",
+            command: None,
+        },
+        Feature {
+            keys: ":select all|invert|*.log",
+            what: "mark every entry, flip the marks, or mark the matching ones",
+            command: Some("select all"),
+        },
+        Feature {
+            keys: "Enter on marks",
+            what: "open every marked file, or pass them all to [operations] open_marked",
+            command: None,
+        },
+        Feature {
+            keys: ":set heightscale linear|log|sqrt|rank",
+            what: "heights by size on another scale, so small files differ too ([heights])",
+            command: Some("set heightscale sqrt"),
+        },
+        Feature {
+            keys: "thick directories",
+            what: "a directory is as thick as what's in it weighs, the grid on top as fine as it's full",
+            command: None,
+        },
+        Feature {
+            keys: "rest on a directory",
+            what: "its first entries stand on top of it as miniature books",
+            command: None,
+        },
+        Feature {
+            keys: "select a growing file",
+            what: "a log or download being written rises as it grows, its rate over it",
+            command: None,
+        },
+        Feature {
+            keys: ":tail / :tail!",
+            what: "follow a log in the preview pane as lines come in, pause and resume",
+            command: Some("tail"),
+        },
+        Feature {
+            keys: "d / :tag",
+            what: "trash or tag the visual range or the marked entries, asked once",
+            command: None,
+        },
+        Feature {
+            keys: "o / s / r / n",
+            what: "a paste onto names already there asks: overwrite, skip, rename or if newer",
+            command: None,
+        },
+        Feature {
+            keys: ":set autorename",
+            what: "pastes number clashing names instead of asking (rename_pattern in the config)",
+            command: Some("set autorename"),
+        },
+        Feature {
+            keys: ":debug",
+            what: "frame rate, entities, assets, load and scan timings and the job queue",
+            command: Some("debug"),
+        },
+        Feature {
+            keys: "s",
+            what: "cycle sort orders ([sort] in the config adds more)",
+            command: None,
+        },
+        Feature {
+            keys: ":sort size desc",
+            what: "sort by one key: name, size, mtime, extension",
+            command: Some("sort mtime desc"),
+        },
+        Feature {
+            keys: ". / zh",
+            what: "show or hide dotfiles (hidden by default)",
+            command: Some("set hidden!"),
+        },
+        Feature {
+            keys: "zi",
+            what: "hide what .gitignore ignores (target/, node_modules/...)",
+            command: Some("set gitignore!"),
+        },
+        Feature {
+            keys: "Tab",
+            what: "switch roots of a workspace (felipe my.workspace)",
+            command: None,
+        },
+        Feature {
+            keys: ":oplog",
+            what: "history of file operations, u to undo",
+            command: Some("oplog"),
+        },
+        Feature {
+            keys: ":snapshot / :changes",
+            what: "record a tree and review what changed since",
+            command: Some("changes"),
+        },
+        Feature {
+            keys: ":history",
+            what: "browse btrfs, ZFS and Time Machine snapshots",
+            command: Some("history"),
+        },
+        Feature {
+            keys: ":gitlog / :blame",
+            what: "git history of the selected file, read-only",
+            command: Some("gitlog"),
+        },
+        Feature {
+            keys: ":conflicts",
+            what: "find and resolve sync-conflict copies",
+            command: Some("conflicts"),
+        },
+        Feature {
+            keys: ":flatten",
+            what: "every file below here in one scene",
+            command: Some("flatten"),
+        },
+        Feature {
+            keys: ":fly",
+            what: "first-person fly-through of the shelves",
+            command: Some("fly"),
+        },
+        Feature {
+            keys: ":set wireframe",
+            what: "draw entries as outlines",
+            command: Some("set wireframe!"),
+        },
+        Feature {
+            keys: ":set crt",
+            what: "scanlines and a vignette over the view",
+            command: Some("set crt!"),
+        },
+        Feature {
+            keys: "felipe batch",
+            what: "run scripted copy/move/delete/sync jobs",
+            command: None,
+        },
+    ],
+}];
+
+/// Releases after `seen`, newest first; everything if `seen` isn't in the changelog
+fn releases_since(seen: Option<&str>) -> Vec<&'static Release> {
+    CHANGELOG
+        .iter()
+        .take_while(|release| Some(release.version) != seen)
+        .collect()
+}
+
+// =============================================================================
+// Seen Version
+// =============================================================================
+
+fn seen_version_file() -> Option<PathBuf> {
+    Some(data_dir()?.join("seen_version"))
+}
+
+fn read_seen_version() -> Option<String> {
+    let text = std::fs::read_to_string(seen_version_file()?).ok()?;
+    Some(text.trim().to_string())
+}
+
+fn write_seen_version() {
+    let Some(path) = seen_version_file() else {
+        return;
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, env!("CARGO_PKG_VERSION")));
+    if let Err(err) = result {
+        warn!("Could not write {}: {}", path.display(), err);
+    }
+}
+
+// =============================================================================
+// View
+// =============================================================================
+
+/// Overlay state
+#[derive(Resource, Default)]
+pub struct WhatsNewView {
+    releases: Vec<&'static Release>,
+    /// Index into the features of all shown releases, in order
+    cursor: usize,
+}
+
+impl WhatsNewView {
+    fn features(&self) -> impl Iterator<Item = &'static Feature> + '_ {
+        self.releases.iter().flat_map(|release| release.features)
+    }
+}
+
+/// Marker for the overlay panel
+#[derive(Component)]
+struct WhatsNewPanel;
+
+/// Marker for the overlay text
+#[derive(Component)]
+struct WhatsNewText;
+
+pub struct WhatsNewPlugin;
+
+impl Plugin for WhatsNewPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WhatsNewView::default())
+            .add_systems(Startup, show_after_upgrade)
+            .add_systems(
+                Update,
+                (
+                    handle_whatsnew_command,
+                    handle_whatsnew_keys,
+                    update_whatsnew_panel,
+                ),
+            );
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn show_after_upgrade(
+    mut commands: Commands,
+    mut view: ResMut<WhatsNewView>,
+    mut focus: ResMut<Focus>,
+) {
+    let seen = read_seen_version();
+    if seen.as_deref() == Some(env!("CARGO_PKG_VERSION")) {
+        return;
+    }
+    write_seen_version();
+    // A fresh install has nothing to compare against; :tutor is the better start
+    let Some(seen) = seen else {
+        return;
+    };
+    let releases = releases_since(Some(&seen));
+    if releases.is_empty() {
+        return;
+    }
+    open_panel(&mut commands, &mut view, &mut focus, releases);
+}
+
+fn handle_whatsnew_command(
+    mut commands: Commands,
+    mut run_commands: EventReader<RunCommand>,
+    mut view: ResMut<WhatsNewView>,
+    mut focus: ResMut<Focus>,
+    panel_query: Query<Entity, With<WhatsNewPanel>>,
+) {
+    for RunCommand(command) in run_commands.read() {
+        if *command != Command::WhatsNew {
+            continue;
+        }
+        if focus.is_open(Panel::WhatsNew) {
+            close_panel(&mut commands, &mut focus, &panel_query);
+        } else {
+            open_panel(&mut commands, &mut view, &mut focus, releases_since(None));
+        }
+    }
+}
+
+fn handle_whatsnew_keys(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    mut view: ResMut<WhatsNewView>,
+    mut run_command: EventWriter<RunCommand>,
+    mut status: ResMut<StatusMessage>,
+    mut focus: ResMut<Focus>,
+    mut dismissed: EventReader<Dismiss>,
+    panel_query: Query<Entity, With<WhatsNewPanel>>,
+) {
+    if dismissed
+        .read()
+        .any(|Dismiss(panel)| *panel == Panel::WhatsNew)
+    {
+        close_panel(&mut commands, &mut focus, &panel_query);
+        return;
+    }
+    if !focus.has_focus(Panel::WhatsNew) || *vim_mode != VimMode::Normal {
+        return;
+    }
+    let count = view.features().count();
+
+    if keyboard.just_pressed(KeyCode::KeyJ) || keyboard.just_pressed(KeyCode::ArrowDown) {
+        view.cursor = (view.cursor + 1).min(count.saturating_sub(1));
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) || keyboard.just_pressed(KeyCode::ArrowUp) {
+        view.cursor = view.cursor.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::Enter) {
+        let Some(feature) = view.features().nth(view.cursor) else {
+            return;
+        };
+        let Some(line) = feature.command else {
+            status.0 = format!("{} - {}", feature.keys, feature.what);
+            return;
+        };
+        close_panel(&mut commands, &mut focus, &panel_query);
+        match parse_command(line) {
+            Ok(command) => {
+                run_command.send(RunCommand(command));
+            }
+            Err(message) => status.0 = message,
+        }
+    }
+}
+
+fn update_whatsnew_panel(
+    view: Res<WhatsNewView>,
+    focus: Res<Focus>,
+    mut text_query: Query<&mut Text, With<WhatsNewText>>,
+) {
+    if !focus.is_open(Panel::WhatsNew) {
+        return;
+    }
+    let mut sections = vec![TextSection::new(
+        "WHAT'S NEW  j/k:select  Enter:try it  Esc:close\n",
+        panel_style(FELIPE_ORANGE),
+    )];
+    let mut index = 0;
+    for release in &view.releases {
+        sections.push(TextSection::new(
+            format!("\n{}\n", release.version),
+            panel_style(FELIPE_ORANGE),
+        ));
+        for feature in release.features {
+            let selected = index == view.cursor;
+            sections.push(TextSection::new(
+                format!(
+                    "{} {:<22} {}\n",
+                    if selected { ">" } else { " " },
+                    feature.keys,
+                    feature.what
+                ),
+                panel_style(if selected {
+                    FELIPE_ORANGE
+                } else {
+                    FELIPE_ORANGE_DIM
+                }),
+            ));
+            index += 1;
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn panel_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn open_panel(
+    commands: &mut Commands,
+    view: &mut WhatsNewView,
+    focus: &mut Focus,
+    releases: Vec<&'static Release>,
+) {
+    view.releases = releases;
+    view.cursor = 0;
+    focus.open(Panel::WhatsNew);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    right: Val::Px(10.0),
+                    max_width: Val::Px(560.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE),
+                ..default()
+            },
+            WhatsNewPanel,
+            Focusable(Panel::WhatsNew),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), WhatsNewText));
+        });
+}
+
+fn close_panel(
+    commands: &mut Commands,
+    focus: &mut Focus,
+    panel_query: &Query<Entity, With<WhatsNewPanel>>,
+) {
+    focus.close(Panel::WhatsNew);
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,196 @@
+//! Which-key - what can follow a key that starts a sequence
+//!
+//! `z` (frame, hidden files), `b` (up the path), `'` (portals) and `y` (copy
+//! the path) wait for a second key, and a count (`7`) for the motion it
+//! repeats. The handlers of those keys note what waits in `PendingKey`; when
+//! nothing follows for a moment, a small popup above the status line lists
+//! the keys that would complete it and what each does. `g` (top), `d` (trash)
+//! and `m` (cut) act at once here rather than wait, so they have no hints.
+//!
+//! ```text
+//! z   z:frame the selection  t:directory from above  b:whole directory ...
+//! '   1:home  2:downloads  3:projects
+//! 7   j:7 down  k:7 up  g:entry 7  G:entry 7
+//! ```
+
+use bevy::prelude::*;
+
+use crate::bookmarks::Bookmarks;
+use crate::breadcrumbs::{ancestors, crumb_label};
+use crate::locale::{tr, trf};
+use crate::{CurrentDirectory, UiElement, FELIPE_ORANGE, FELIPE_ORANGE_DIM};
+
+/// Seconds a sequence waits before its hints show
+const HINT_DELAY: f32 = 0.5;
+
+/// Completions listed at most; the rest are counted
+const HINTS_SHOWN: usize = 12;
+
+/// What follows `z` (see `handle_keyboard`)
+const Z_KEYS: &[(&str, &str)] = &[
+    ("z", "frame the selection"),
+    ("t", "the directory from above"),
+    ("b", "the whole directory"),
+    ("h", "show or hide dotfiles"),
+    ("i", "show or hide ignored files"),
+];
+
+/// What follows `y`; anything else yanks (see clipboard.rs)
+const Y_KEYS: &[(&str, &str)] = &[
+    ("y", "yank the selection"),
+    ("p", "copy the full path"),
+    ("n", "copy the name"),
+    ("d", "copy the directory"),
+];
+
+/// Keys typed so far that wait for more
+#[derive(Resource, Default)]
+pub struct PendingKey {
+    /// The first key of a sequence while it waits for the next one
+    pub key: Option<char>,
+    /// Digits before a motion, the 7 of `7j` (see `handle_count_keys`)
+    pub count: Option<usize>,
+}
+
+/// Marker for the popup
+#[derive(Component)]
+struct HintPopup;
+
+pub struct WhichKeyPlugin;
+
+impl Plugin for WhichKeyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingKey>()
+            .add_systems(Update, show_hints);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn show_hints(
+    mut commands: Commands,
+    pending_key: Res<PendingKey>,
+    time: Res<Time>,
+    current_dir: Res<CurrentDirectory>,
+    bookmarks: Res<Bookmarks>,
+    popup_query: Query<Entity, With<HintPopup>>,
+    mut waiting_since: Local<f32>,
+) {
+    if pending_key.is_changed() {
+        *waiting_since = time.elapsed_seconds();
+        for entity in popup_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    if !popup_query.is_empty() || time.elapsed_seconds() - *waiting_since < HINT_DELAY {
+        return;
+    }
+    let key = match (pending_key.key, pending_key.count) {
+        (Some(key), _) => key.to_string(),
+        (None, Some(count)) => count.to_string(),
+        (None, None) => return,
+    };
+
+    let hints: Vec<(String, String)> = match (pending_key.key, pending_key.count) {
+        (None, Some(count)) => vec![
+            ("j".to_string(), trf("{} down", &[&count])),
+            ("k".to_string(), trf("{} up", &[&count])),
+            ("g".to_string(), trf("entry {}", &[&count])),
+            ("G".to_string(), trf("entry {}", &[&count])),
+        ],
+        (Some('z'), _) => Z_KEYS
+            .iter()
+            .map(|(key, what)| (key.to_string(), tr(what).to_string()))
+            .collect(),
+        (Some('y'), _) => Y_KEYS
+            .iter()
+            .map(|(key, what)| (key.to_string(), tr(what).to_string()))
+            .collect(),
+        (Some('b'), _) => {
+            let crumbs = ancestors(current_dir.path());
+            crumbs
+                .iter()
+                .enumerate()
+                .map(|(i, path)| {
+                    let parent = i.checked_sub(1).map(|p| crumbs[p].as_path());
+                    ((i + 1).to_string(), crumb_label(path, parent))
+                })
+                .collect()
+        }
+        (Some('\''), _) => bookmarks
+            .names()
+            .enumerate()
+            .map(|(i, name)| ((i + 1).to_string(), name.to_string()))
+            .collect(),
+        _ => return,
+    };
+    spawn_popup(&mut commands, &key, &hints);
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn hint_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color,
+        ..default()
+    }
+}
+
+fn spawn_popup(commands: &mut Commands, key: &str, hints: &[(String, String)]) {
+    let mut sections = vec![TextSection::new(
+        format!("{}  ", key),
+        hint_style(FELIPE_ORANGE),
+    )];
+    if hints.is_empty() {
+        sections.push(TextSection::new(
+            tr("nothing to go to"),
+            hint_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    for (key, what) in hints.iter().take(HINTS_SHOWN) {
+        sections.push(TextSection::new(
+            format!("{}:", key),
+            hint_style(FELIPE_ORANGE),
+        ));
+        sections.push(TextSection::new(
+            format!("{}  ", what),
+            hint_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+    if hints.len() > HINTS_SHOWN {
+        sections.push(TextSection::new(
+            trf("... {} more", &[&(hints.len() - HINTS_SHOWN)]),
+            hint_style(FELIPE_ORANGE_DIM),
+        ));
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(60.0),
+                    left: Val::Px(10.0),
+                    max_width: Val::Px(620.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.02, 0.02, 0.02, 0.9)),
+                border_color: BorderColor(FELIPE_ORANGE_DIM),
+                z_index: ZIndex::Global(3),
+                ..default()
+            },
+            HintPopup,
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_sections(sections));
+        });
+}
@@ -0,0 +1,328 @@
+//! Window state - fullscreen, overlay, and the window where it was last time
+//!
+//! F11 or `:set fullscreen` switches to borderless fullscreen and back. When
+//! Felipe quits, the window's size, place and monitor and whether it was
+//! fullscreen go to `window.json` in the data directory, and the next start
+//! opens it the same way; a monitor that is gone since puts it back in the
+//! middle of the main one. `remember = false` opens at the default size
+//! every time.
+//!
+//! For a HUD over a terminal, `transparent` lets the desktop show through the
+//! black at `opacity` (entries and text stay solid), `decorations = false`
+//! drops the title bar and `ontop` (also `:set ontop`) keeps the window above
+//! the others. Transparency needs a compositor and is set when the window is
+//! made, so it takes a restart; Windows drivers often show the window opaque
+//! anyway, and bloom (HDR) is left out while it's on.
+//!
+//! ```toml
+//! [window]
+//! fullscreen = false
+//! remember = true
+//! transparent = false
+//! opacity = 0.75
+//! decorations = true
+//! ontop = false
+//! ```
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::window::{
+    CompositeAlphaMode, MonitorSelection, PrimaryWindow, WindowLevel, WindowMode, WindowMoved,
+    WindowPosition,
+};
+use bevy::winit::WinitWindows;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::{data_dir, Prompt, VimMode, FELIPE_BLACK};
+
+/// Size of the first window, before there's one to remember
+pub const DEFAULT_SIZE: Vec2 = Vec2::new(1200.0, 800.0);
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    /// Start in borderless fullscreen (also F11, `:set fullscreen`)
+    pub fullscreen: bool,
+    /// Open where the last session left the window
+    pub remember: bool,
+    /// Let the desktop show through the background (on the next start)
+    pub transparent: bool,
+    /// How much of the background is drawn while transparent, 0.0-1.0
+    pub opacity: f32,
+    /// Title bar and borders
+    pub decorations: bool,
+    /// Above every other window (also `:set ontop`)
+    pub ontop: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            fullscreen: false,
+            remember: true,
+            transparent: false,
+            opacity: 0.75,
+            decorations: true,
+            ontop: false,
+        }
+    }
+}
+
+/// How the compositor should blend a transparent window; each platform's
+/// surface takes a different one
+fn composite_alpha_mode() -> CompositeAlphaMode {
+    if cfg!(target_os = "macos") {
+        CompositeAlphaMode::PostMultiplied
+    } else if cfg!(target_os = "linux") {
+        CompositeAlphaMode::PreMultiplied
+    } else {
+        CompositeAlphaMode::Auto
+    }
+}
+
+/// What `window.json` holds
+#[derive(Serialize, Deserialize, Clone)]
+struct SavedWindow {
+    /// Logical size while windowed
+    width: f32,
+    height: f32,
+    /// Top left in physical pixels; Wayland doesn't tell
+    position: Option<IVec2>,
+    /// Name of the monitor it was on
+    monitor: Option<String>,
+    fullscreen: bool,
+}
+
+impl Default for SavedWindow {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_SIZE.x,
+            height: DEFAULT_SIZE.y,
+            position: None,
+            monitor: None,
+            fullscreen: false,
+        }
+    }
+}
+
+/// The window as it is now, to write out at exit
+#[derive(Resource, Default)]
+struct WindowGeometry {
+    current: SavedWindow,
+    /// The monitor it should open on is still to be checked
+    check_monitor: bool,
+}
+
+fn state_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("window.json"))
+}
+
+fn load() -> Option<SavedWindow> {
+    let text = std::fs::read_to_string(state_file()?).ok()?;
+    serde_json::from_str(&text)
+        .map_err(|err| warn!("Ignoring window.json: {}", err))
+        .ok()
+}
+
+/// Size, place and mode for the first window: the last session's, if it's
+/// remembered
+pub fn restore(window: &mut Window, config: &mut Config) {
+    window.resolution = DEFAULT_SIZE.into();
+    if config.window.transparent {
+        window.transparent = true;
+        window.composite_alpha_mode = composite_alpha_mode();
+    }
+    if !config.window.remember {
+        return;
+    }
+    let Some(saved) = load() else {
+        return;
+    };
+    window.resolution = (saved.width, saved.height).into();
+    if let Some(position) = saved.position {
+        window.position = WindowPosition::At(position);
+    }
+    config.window.fullscreen |= saved.fullscreen;
+}
+
+pub struct WindowStatePlugin;
+
+impl Plugin for WindowStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WindowGeometry {
+            current: load().unwrap_or_default(),
+            check_monitor: true,
+        })
+        .add_systems(
+            Update,
+            (
+                handle_fullscreen_key,
+                apply_window_settings.run_if(resource_changed::<Config>),
+                check_monitor.run_if(|geometry: Res<WindowGeometry>| geometry.check_monitor),
+                track_window,
+            )
+                .chain(),
+        )
+        .add_systems(Last, save_on_exit);
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+/// F11 - fullscreen on or off
+fn handle_fullscreen_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    mut config: ResMut<Config>,
+) {
+    if keyboard.just_pressed(KeyCode::F11)
+        && matches!(*vim_mode, VimMode::Normal | VimMode::Visual)
+        && prompt.pending.is_none()
+    {
+        config.window.fullscreen = !config.window.fullscreen;
+    }
+}
+
+/// Fullscreen, title bar, stacking and the background's see-through
+fn apply_window_settings(
+    config: Res<Config>,
+    mut clear_color: ResMut<ClearColor>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let settings = &config.window;
+    let mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    let level = if settings.ontop {
+        WindowLevel::AlwaysOnTop
+    } else {
+        WindowLevel::Normal
+    };
+    for mut window in window_query.iter_mut() {
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        if window.decorations != settings.decorations {
+            window.decorations = settings.decorations;
+        }
+        if window.window_level != level {
+            window.window_level = level;
+        }
+        // Only a window made transparent can show through
+        let background = if window.transparent {
+            FELIPE_BLACK.with_alpha(settings.opacity.clamp(0.0, 1.0))
+        } else {
+            FELIPE_BLACK
+        };
+        if clear_color.0 != background {
+            clear_color.0 = background;
+        }
+    }
+}
+
+/// Once the window is up: a remembered monitor that's gone leaves the window
+/// off every screen, so center it on the main one
+fn check_monitor(
+    winit_windows: Option<NonSend<WinitWindows>>,
+    mut geometry: ResMut<WindowGeometry>,
+    mut window_query: Query<(Entity, &mut Window), With<PrimaryWindow>>,
+) {
+    let Some(winit_windows) = winit_windows else {
+        geometry.check_monitor = false;
+        return;
+    };
+    let Ok((entity, mut window)) = window_query.get_single_mut() else {
+        return;
+    };
+    let Some(winit_window) = winit_windows.get_window(entity) else {
+        return;
+    };
+    geometry.check_monitor = false;
+    let current = winit_window
+        .current_monitor()
+        .and_then(|monitor| monitor.name());
+    let Some(saved) = geometry.current.monitor.clone() else {
+        geometry.current.monitor = current;
+        return;
+    };
+    let still_there = winit_window
+        .available_monitors()
+        .any(|monitor| monitor.name().as_ref() == Some(&saved));
+    if !still_there {
+        info!("Monitor {} is gone, centering the window", saved);
+        window.position = WindowPosition::Centered(MonitorSelection::Primary);
+    }
+    geometry.current.monitor = current;
+}
+
+/// Follow the window's size, place and monitor as they change
+fn track_window(
+    config: Res<Config>,
+    winit_windows: Option<NonSend<WinitWindows>>,
+    mut moved: EventReader<WindowMoved>,
+    mut geometry: ResMut<WindowGeometry>,
+    window_query: Query<(Entity, Ref<Window>), With<PrimaryWindow>>,
+) {
+    let Ok((entity, window)) = window_query.get_single() else {
+        return;
+    };
+    let fullscreen = config.window.fullscreen;
+    if geometry.current.fullscreen != fullscreen {
+        geometry.current.fullscreen = fullscreen;
+    }
+    // Fullscreen keeps the windowed size and place for coming back
+    if fullscreen || window.mode != WindowMode::Windowed {
+        moved.clear();
+        return;
+    }
+    if window.is_changed() {
+        let size = Vec2::new(window.width(), window.height());
+        if size != Vec2::new(geometry.current.width, geometry.current.height) {
+            geometry.current.width = size.x;
+            geometry.current.height = size.y;
+        }
+    }
+    let Some(position) = moved
+        .read()
+        .filter(|event| event.window == entity)
+        .last()
+        .map(|event| event.position)
+    else {
+        return;
+    };
+    geometry.current.position = Some(position);
+    geometry.current.monitor = winit_windows
+        .as_ref()
+        .and_then(|windows| windows.get_window(entity))
+        .and_then(|window| window.current_monitor())
+        .and_then(|monitor| monitor.name());
+}
+
+fn save_on_exit(
+    mut exits: EventReader<AppExit>,
+    config: Res<Config>,
+    geometry: Res<WindowGeometry>,
+) {
+    if exits.read().next().is_none() || !config.window.remember {
+        return;
+    }
+    let Some(path) = state_file() else {
+        return;
+    };
+    let saved = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .map_err(|err| err.to_string())
+        .and_then(|_| serde_json::to_string(&geometry.current).map_err(|err| err.to_string()))
+        .and_then(|json| std::fs::write(&path, json).map_err(|err| err.to_string()));
+    if let Err(err) = saved {
+        warn!("Could not save {}: {}", path.display(), err);
+    }
+}
@@ -0,0 +1,261 @@
+//! Workspace files - `felipe my.workspace` opens several roots as tabs
+//!
+//! ```toml
+//! [[root]]
+//! name = "code"            # tab title (default: the directory name)
+//! path = "~/src/game"      # relative paths are relative to the workspace file
+//! select = "Cargo.toml"    # entry selected on open
+//! camera_distance = 20.0
+//! camera_angle = 1.2       # radians above the grid, 1.5 is a top-down view
+//! ```
+//!
+//! Tab / Shift+Tab switch roots; each tab remembers its directory, selection
+//! and camera.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::focus::Focus;
+use crate::{
+    CameraState, CurrentDirectory, Prompt, StatusMessage, UiElement, VimMode, FELIPE_ORANGE,
+    FELIPE_ORANGE_DIM,
+};
+
+// =============================================================================
+// File Format
+// =============================================================================
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkspaceFile {
+    #[serde(rename = "root")]
+    roots: Vec<RootSettings>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RootSettings {
+    name: Option<String>,
+    path: PathBuf,
+    select: Option<PathBuf>,
+    camera_distance: Option<f32>,
+    camera_angle: Option<f32>,
+}
+
+/// Read a workspace file; errors are ready to print
+pub fn load(path: &Path) -> Result<Workspace, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let file: WorkspaceFile = toml::from_str(&text).map_err(|err| err.to_string())?;
+    if file.roots.is_empty() {
+        return Err("no [[root]] entries".to_string());
+    }
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let mut tabs = Vec::new();
+    for root in file.roots {
+        let path = resolve(base, &root.path);
+        if !path.is_dir() {
+            return Err(format!("{} is not a directory", path.display()));
+        }
+        let defaults = CameraState::default();
+        tabs.push(Tab {
+            name: root.name.unwrap_or_else(|| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string())
+            }),
+            selected: root.select.map(|select| path.join(select)),
+            path,
+            distance: root.camera_distance.unwrap_or(defaults.distance),
+            angle: root.camera_angle.unwrap_or(defaults.angle),
+            yaw: defaults.yaw,
+        });
+    }
+    Ok(Workspace { tabs, active: 0 })
+}
+
+/// `~/x` is home-relative, other relative paths are relative to the workspace file
+fn resolve(base: &Path, path: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    base.join(path)
+}
+
+// =============================================================================
+// Tabs
+// =============================================================================
+
+/// One root of the workspace, with what to restore when switching back to it
+#[derive(Serialize, Deserialize)]
+struct Tab {
+    name: String,
+    path: PathBuf,
+    selected: Option<PathBuf>,
+    distance: f32,
+    angle: f32,
+    yaw: f32,
+}
+
+/// Open roots; empty when Felipe wasn't started with a workspace. Saved
+/// with the session when Felipe crashes (see recovery.rs)
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Workspace {
+    tabs: Vec<Tab>,
+    active: usize,
+}
+
+impl Workspace {
+    /// Directory of the active tab, if Felipe was started with a workspace
+    pub fn active_path(&self) -> Option<&Path> {
+        self.tabs.get(self.active).map(|tab| tab.path.as_path())
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    /// Remember where the active tab is, before leaving it
+    fn save_active(&mut self, current_dir: &CurrentDirectory, camera_state: &CameraState) {
+        let Some(tab) = self.tabs.get_mut(self.active) else {
+            return;
+        };
+        tab.path = current_dir.path.clone();
+        tab.selected = current_dir
+            .entries
+            .get(current_dir.selected_index)
+            .map(|entry| entry.path.clone());
+        tab.distance = camera_state.distance;
+        tab.angle = camera_state.angle;
+        tab.yaw = camera_state.yaw;
+    }
+
+    /// Show the active tab's directory and camera
+    fn restore_active(&self, current_dir: &mut CurrentDirectory, camera_state: &mut CameraState) {
+        let Some(tab) = self.tabs.get(self.active) else {
+            return;
+        };
+        current_dir.path = tab.path.clone();
+        current_dir.pending_select = tab.selected.clone();
+        current_dir.needs_reload = true;
+        camera_state.distance = tab.distance;
+        camera_state.angle = tab.angle;
+        camera_state.yaw = tab.yaw;
+        camera_state.pan = Vec3::ZERO;
+    }
+}
+
+/// Marker for the tab bar
+#[derive(Component)]
+struct TabBar;
+
+pub struct WorkspacePlugin;
+
+impl Plugin for WorkspacePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Workspace>()
+            .add_systems(Startup, open_workspace)
+            .add_systems(Update, (handle_tab_keys, update_tab_bar));
+    }
+}
+
+// =============================================================================
+// Systems
+// =============================================================================
+
+fn open_workspace(
+    mut commands: Commands,
+    workspace: Res<Workspace>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut camera_state: ResMut<CameraState>,
+) {
+    // Without tabs the bar stays empty, until a recovered session brings some
+    if !workspace.tabs.is_empty() {
+        workspace.restore_active(&mut current_dir, &mut camera_state);
+    }
+    commands.spawn((
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+        TabBar,
+        UiElement,
+    ));
+}
+
+fn handle_tab_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    vim_mode: Res<VimMode>,
+    prompt: Res<Prompt>,
+    focus: Res<Focus>,
+    mut workspace: ResMut<Workspace>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut camera_state: ResMut<CameraState>,
+    mut status: ResMut<StatusMessage>,
+) {
+    if workspace.tabs.len() < 2
+        || !keyboard.just_pressed(KeyCode::Tab)
+        || *vim_mode != VimMode::Normal
+        || prompt.pending.is_some()
+        || focus.any_open()
+    {
+        return;
+    }
+
+    workspace.save_active(&current_dir, &camera_state);
+    let count = workspace.tabs.len();
+    let backward = keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight);
+    workspace.active = if backward {
+        (workspace.active + count - 1) % count
+    } else {
+        (workspace.active + 1) % count
+    };
+    workspace.restore_active(&mut current_dir, &mut camera_state);
+    status.0 = format!(
+        "Root {}/{}: {}",
+        workspace.active + 1,
+        count,
+        workspace.tabs[workspace.active].name
+    );
+}
+
+fn update_tab_bar(workspace: Res<Workspace>, mut query: Query<&mut Text, With<TabBar>>) {
+    if !workspace.is_changed() {
+        return;
+    }
+    let sections: Vec<TextSection> = workspace
+        .tabs
+        .iter()
+        .enumerate()
+        .map(|(i, tab)| {
+            let active = i == workspace.active;
+            TextSection::new(
+                if active {
+                    format!(" [{}] ", tab.name)
+                } else {
+                    format!("  {}  ", tab.name)
+                },
+                TextStyle {
+                    font_size: 18.0,
+                    color: if active {
+                        FELIPE_ORANGE
+                    } else {
+                        FELIPE_ORANGE_DIM
+                    },
+                    ..default()
+                },
+            )
+        })
+        .collect();
+    for mut text in query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
@@ -0,0 +1,57 @@
+//! Controller-ray selection for VR (`--features xr`, experimental)
+//!
+//! Felipe doesn't open an OpenXR session itself: no OpenXR crate for this
+//! Bevy version is among its dependencies, so the headset, the stereo camera
+//! rig and reading the controllers are left to the app that embeds
+//! `FelipePlugin`. That app puts `XrController` on the entity that follows a
+//! tracked controller and sends `XrSelect` when its trigger is pressed; the
+//! entry the controller points at (along its forward axis) gets selected,
+//! the same as a click.
+
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+
+use crate::layout::Layouts;
+use crate::{CameraState, CurrentDirectory, FileEntity};
+
+/// An entity whose transform follows a tracked controller
+#[derive(Component, Default)]
+pub struct XrController;
+
+/// The trigger of `controller` was pressed
+#[derive(Event, Clone, Debug)]
+pub struct XrSelect {
+    pub controller: Entity,
+}
+
+pub struct XrPlugin;
+
+impl Plugin for XrPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrSelect>()
+            .add_systems(Update, select_pointed_entry);
+    }
+}
+
+fn select_pointed_entry(
+    mut selects: EventReader<XrSelect>,
+    controllers: Query<&GlobalTransform, With<XrController>>,
+    entity_query: Query<(&FileEntity, &GlobalTransform, &Aabb, &ViewVisibility)>,
+    mut current_dir: ResMut<CurrentDirectory>,
+    mut camera_state: ResMut<CameraState>,
+    layouts: Res<Layouts>,
+) {
+    for select in selects.read() {
+        let Ok(transform) = controllers.get(select.controller) else {
+            continue;
+        };
+        let ray = Ray3d {
+            origin: transform.translation(),
+            direction: transform.forward(),
+        };
+        if let Some(index) = crate::pick_file_entity(ray, &entity_query) {
+            current_dir.selected_index = index;
+            crate::update_camera_target(&current_dir, &layouts, &mut camera_state);
+        }
+    }
+}